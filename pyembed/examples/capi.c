@@ -0,0 +1,37 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/* Embed Python via the library built from capi.rs. */
+
+#include <stdio.h>
+
+#include "pyembed.h"
+
+int main(void) {
+    PyembedConfig *config = pyembed_config_new_default();
+    char *error = NULL;
+
+    PyembedInterpreter *interp = pyembed_interpreter_new(config, &error);
+    pyembed_config_free(config);
+
+    if (!interp) {
+        fprintf(stderr, "error creating interpreter: %s\n", error);
+        pyembed_string_free(error);
+        return 1;
+    }
+
+    int code = pyembed_interpreter_run_code(
+        interp, "print('evaluated from C')", &error);
+    if (code == -1) {
+        fprintf(stderr, "error running code: %s\n", error);
+        pyembed_string_free(error);
+        pyembed_interpreter_free(interp);
+        return 1;
+    }
+
+    code = pyembed_interpreter_run_as_main(interp);
+    pyembed_interpreter_free(interp);
+
+    return code;
+}
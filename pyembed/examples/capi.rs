@@ -0,0 +1,30 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Shared library exporting the C API declared in `include/pyembed.h`.
+
+`capi.c` in this directory is a C program using it. Build both with:
+
+```text
+cargo build -p pyembed --example capi --no-default-features --features build-mode-test,capi
+cc -I pyembed/include pyembed/examples/capi.c -L target/debug/examples -lcapi -o capi
+LD_LIBRARY_PATH=target/debug/examples ./capi
+```
+
+Libraries built from a PyOxidizer configuration file export the
+configuration PyOxidizer derived instead. See the `rust_c_api` docs.
+*/
+
+/// The configuration returned by `pyembed_config_new_default()`.
+fn default_python_config() -> pyembed::OxidizedPythonInterpreterConfig<'static> {
+    let mut config = pyembed::OxidizedPythonInterpreterConfig::default();
+    config.run = pyembed::PythonRunMode::Eval {
+        code: "import sys; print('hello from Python', sys.version)".to_string(),
+    };
+
+    config
+}
+
+pyembed::export_default_config!(default_python_config);
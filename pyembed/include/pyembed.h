@@ -0,0 +1,104 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/*
+ * C API for embedding a Python interpreter via the pyembed crate.
+ *
+ * These functions are exported by Rust crates depending on pyembed with
+ * the `capi` feature enabled and built as a `cdylib` or `staticlib`.
+ *
+ * Functions returning int return 0 on success and -1 on failure unless
+ * documented otherwise. Strings are NUL terminated UTF-8. Panics are
+ * caught and reported as failures.
+ */
+
+#ifndef PYEMBED_H
+#define PYEMBED_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct PyembedConfig PyembedConfig;
+typedef struct PyembedInterpreter PyembedInterpreter;
+typedef struct PyembedInterruptHandle PyembedInterruptHandle;
+
+/* Create a configuration with default settings. */
+PyembedConfig *pyembed_config_new(void);
+
+/* Create the configuration PyOxidizer derived at build time.
+ *
+ * Only available if the library defines it via
+ * `pyembed::export_default_config!`. */
+PyembedConfig *pyembed_config_new_default(void);
+
+/* Release a configuration. */
+void pyembed_config_free(PyembedConfig *config);
+
+/* Use packed resources data. The data isn't copied and must outlive all
+ * interpreters created from the configuration. NULL data removes packed
+ * resources from the configuration. */
+int pyembed_config_set_packed_resources(PyembedConfig *config,
+                                        const uint8_t *data, size_t size);
+
+/* Set whether to install the importer loading packed resources. */
+int pyembed_config_set_oxidized_importer(PyembedConfig *config, int enabled);
+
+/* Set whether to install the importer loading modules from the filesystem. */
+int pyembed_config_set_filesystem_importer(PyembedConfig *config, int enabled);
+
+/* Define what pyembed_interpreter_run_as_main() runs. */
+int pyembed_config_set_run_none(PyembedConfig *config);
+int pyembed_config_set_run_repl(PyembedConfig *config);
+int pyembed_config_set_run_module(PyembedConfig *config, const char *module);
+int pyembed_config_set_run_eval(PyembedConfig *config, const char *code);
+int pyembed_config_set_run_file(PyembedConfig *config, const char *path);
+
+/* Create and initialize an interpreter. The configuration is copied.
+ *
+ * Returns NULL on failure. If error isn't NULL, it receives a message which
+ * must be released with pyembed_string_free(). */
+PyembedInterpreter *pyembed_interpreter_new(const PyembedConfig *config,
+                                            char **error);
+
+/* Finalize and release an interpreter. */
+void pyembed_interpreter_free(PyembedInterpreter *interpreter);
+
+/* Run what the configuration defines, like `python` would. Returns an exit
+ * code, or -1 if the interpreter is NULL or running it failed unexpectedly.
+ * The interpreter is finalized afterwards. */
+int pyembed_interpreter_run_as_main(PyembedInterpreter *interpreter);
+
+/* Evaluate code in the __main__ module. Returns 0 on success. If an
+ * exception is raised, it is printed and 1 or the SystemExit code is
+ * returned.
+ *
+ * Returns -1 if the code couldn't be run. If error isn't NULL, it receives
+ * a message which must be released with pyembed_string_free(). */
+int pyembed_interpreter_run_code(PyembedInterpreter *interpreter,
+                                 const char *code, char **error);
+
+/* Obtain a handle to raise KeyboardInterrupt in an interpreter from other
+ * threads. Returns NULL if the interpreter isn't running. */
+PyembedInterruptHandle *
+pyembed_interpreter_interrupt_handle(const PyembedInterpreter *interpreter);
+
+/* Raise KeyboardInterrupt in the interpreter. Must not be called from a
+ * signal handler. */
+int pyembed_interrupt_handle_interrupt(const PyembedInterruptHandle *handle);
+
+/* Release an interrupt handle. */
+void pyembed_interrupt_handle_free(PyembedInterruptHandle *handle);
+
+/* Release a string returned by this API. */
+void pyembed_string_free(char *value);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* PYEMBED_H */
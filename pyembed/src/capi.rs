@@ -0,0 +1,418 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+C API for embedding a Python interpreter.
+
+This module exposes `extern "C"` functions wrapping interpreter
+configuration, construction, and execution so applications written in
+other languages can embed Python via this crate. The declarations are
+in `include/pyembed.h`.
+
+Configurations, interpreters, and interrupt handles are opaque pointers
+owned by the caller. They must be released with the corresponding
+`pyembed_*_free()` function. Strings returned to the caller must be
+released with `pyembed_string_free()`.
+
+Functions returning `int` return 0 on success and -1 on failure unless
+documented otherwise.
+
+Pointers passed to these functions must be NULL or have been returned by
+this API and not yet released. Strings must be NUL terminated UTF-8.
+
+Unwinding into C is undefined behavior. So panics in functions doing more
+than checking their arguments are caught and reported as failures.
+*/
+
+use {
+    crate::config::{OxidizedPythonInterpreterConfig, PythonRunMode},
+    crate::interpreter::MainPythonInterpreter,
+    crate::python_eval::{run_and_handle_error, PythonRunResult},
+    crate::signals::InterruptHandle,
+    libc::{c_char, c_int},
+    std::ffi::{CStr, CString},
+    std::panic::{catch_unwind, AssertUnwindSafe},
+    std::path::PathBuf,
+};
+
+/// Configuration of an interpreter created through the C API.
+pub struct PyembedConfig {
+    config: OxidizedPythonInterpreterConfig<'static>,
+}
+
+/// Interpreter created through the C API.
+pub struct PyembedInterpreter {
+    interpreter: MainPythonInterpreter<'static, 'static, 'static>,
+}
+
+/// Handle to interrupt an interpreter created through the C API.
+pub struct PyembedInterruptHandle {
+    handle: InterruptHandle,
+}
+
+/// Move a configuration to the heap and hand ownership to C.
+///
+/// Used by `export_default_config!`.
+#[doc(hidden)]
+pub fn config_into_raw(config: OxidizedPythonInterpreterConfig<'static>) -> *mut PyembedConfig {
+    Box::into_raw(Box::new(PyembedConfig { config }))
+}
+
+/// Define `pyembed_config_new_default()` returning the given configuration.
+///
+/// The argument is a function returning a value convertible into an
+/// `OxidizedPythonInterpreterConfig<'static>`, typically the
+/// `default_python_config()` function generated by PyOxidizer. This exposes
+/// the configuration and packed resources PyOxidizer derived at build time to
+/// C applications linking against a `cdylib` or `staticlib` crate.
+#[macro_export]
+macro_rules! export_default_config {
+    ($config:path) => {
+        #[no_mangle]
+        pub extern "C" fn pyembed_config_new_default() -> *mut $crate::capi::PyembedConfig {
+            $crate::capi::config_into_raw($config().into())
+        }
+    };
+}
+
+/// Store a message in an error out parameter, if one was given.
+fn set_error(error: *mut *mut c_char, message: &str) {
+    if error.is_null() {
+        return;
+    }
+
+    let message =
+        CString::new(message.replace('\0', "")).expect("NUL bytes should have been removed");
+
+    unsafe {
+        *error = message.into_raw();
+    }
+}
+
+/// Call a function, returning `failure` if it panics.
+///
+/// The panic message is stored in `error`, if it isn't NULL.
+fn catch_panic<T>(error: *mut *mut c_char, failure: T, f: impl FnOnce() -> T) -> T {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(value) => value,
+        Err(payload) => {
+            let message = if let Some(message) = payload.downcast_ref::<&str>() {
+                message.to_string()
+            } else if let Some(message) = payload.downcast_ref::<String>() {
+                message.clone()
+            } else {
+                "unknown error".to_string()
+            };
+
+            set_error(error, &format!("panic: {}", message));
+            failure
+        }
+    }
+}
+
+/// Obtain a Rust string from a NUL terminated UTF-8 C string.
+fn str_from_ptr(value: *const c_char) -> Option<String> {
+    if value.is_null() {
+        return None;
+    }
+
+    unsafe { CStr::from_ptr(value) }
+        .to_str()
+        .ok()
+        .map(|s| s.to_string())
+}
+
+/// Create a configuration with default settings.
+#[no_mangle]
+pub extern "C" fn pyembed_config_new() -> *mut PyembedConfig {
+    config_into_raw(OxidizedPythonInterpreterConfig::default())
+}
+
+/// Release a configuration.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_config_free(config: *mut PyembedConfig) {
+    if !config.is_null() {
+        drop(Box::from_raw(config));
+    }
+}
+
+/// Use packed resources data, as produced by PyOxidizer.
+///
+/// The data isn't copied. It must outlive all interpreters created from the
+/// configuration. NULL data removes packed resources from the configuration.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_config_set_packed_resources(
+    config: *mut PyembedConfig,
+    data: *const u8,
+    size: usize,
+) -> c_int {
+    if config.is_null() || (data.is_null() && size > 0) {
+        return -1;
+    }
+
+    (*config).config.packed_resources = if data.is_null() {
+        None
+    } else {
+        Some(std::slice::from_raw_parts(data, size))
+    };
+
+    0
+}
+
+/// Set whether to install the importer loading packed resources.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_config_set_oxidized_importer(
+    config: *mut PyembedConfig,
+    enabled: c_int,
+) -> c_int {
+    if config.is_null() {
+        return -1;
+    }
+
+    (*config).config.oxidized_importer = enabled != 0;
+
+    0
+}
+
+/// Set whether to install the importer loading modules from the filesystem.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_config_set_filesystem_importer(
+    config: *mut PyembedConfig,
+    enabled: c_int,
+) -> c_int {
+    if config.is_null() {
+        return -1;
+    }
+
+    (*config).config.filesystem_importer = enabled != 0;
+
+    0
+}
+
+/// Don't run anything from `pyembed_interpreter_run_as_main()`.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_config_set_run_none(config: *mut PyembedConfig) -> c_int {
+    if config.is_null() {
+        return -1;
+    }
+
+    (*config).config.run = PythonRunMode::None;
+
+    0
+}
+
+/// Run a REPL from `pyembed_interpreter_run_as_main()`.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_config_set_run_repl(config: *mut PyembedConfig) -> c_int {
+    if config.is_null() {
+        return -1;
+    }
+
+    (*config).config.run = PythonRunMode::Repl;
+
+    0
+}
+
+/// Run a module as `__main__` from `pyembed_interpreter_run_as_main()`.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_config_set_run_module(
+    config: *mut PyembedConfig,
+    module: *const c_char,
+) -> c_int {
+    match (config.is_null(), str_from_ptr(module)) {
+        (false, Some(module)) => {
+            (*config).config.run = PythonRunMode::Module { module };
+            0
+        }
+        _ => -1,
+    }
+}
+
+/// Evaluate code from `pyembed_interpreter_run_as_main()`.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_config_set_run_eval(
+    config: *mut PyembedConfig,
+    code: *const c_char,
+) -> c_int {
+    match (config.is_null(), str_from_ptr(code)) {
+        (false, Some(code)) => {
+            (*config).config.run = PythonRunMode::Eval { code };
+            0
+        }
+        _ => -1,
+    }
+}
+
+/// Run a file from `pyembed_interpreter_run_as_main()`.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_config_set_run_file(
+    config: *mut PyembedConfig,
+    path: *const c_char,
+) -> c_int {
+    match (config.is_null(), str_from_ptr(path)) {
+        (false, Some(path)) => {
+            (*config).config.run = PythonRunMode::File {
+                path: PathBuf::from(path),
+            };
+            0
+        }
+        _ => -1,
+    }
+}
+
+/// Create and initialize an interpreter.
+///
+/// The configuration is copied and can be released afterwards. Returns NULL
+/// on failure, storing a message in `error` if it isn't NULL.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_interpreter_new(
+    config: *const PyembedConfig,
+    error: *mut *mut c_char,
+) -> *mut PyembedInterpreter {
+    if config.is_null() {
+        set_error(error, "config is NULL");
+        return std::ptr::null_mut();
+    }
+
+    catch_panic(
+        error,
+        std::ptr::null_mut(),
+        || match MainPythonInterpreter::new((*config).config.clone()) {
+            Ok(interpreter) => Box::into_raw(Box::new(PyembedInterpreter { interpreter })),
+            Err(err) => {
+                set_error(error, &err.to_string());
+                std::ptr::null_mut()
+            }
+        },
+    )
+}
+
+/// Finalize and release an interpreter.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_interpreter_free(interpreter: *mut PyembedInterpreter) {
+    if !interpreter.is_null() {
+        catch_panic(std::ptr::null_mut(), (), || {
+            drop(Box::from_raw(interpreter))
+        });
+    }
+}
+
+/// Run what the configuration's run mode defines, like `python` would.
+///
+/// Returns an exit code, or -1 if the interpreter is NULL or running it
+/// failed unexpectedly. The interpreter is finalized afterwards, though it
+/// must still be released with `pyembed_interpreter_free()`.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_interpreter_run_as_main(
+    interpreter: *mut PyembedInterpreter,
+) -> c_int {
+    if interpreter.is_null() {
+        return -1;
+    }
+
+    catch_panic(std::ptr::null_mut(), -1, || {
+        (*interpreter).interpreter.run_as_main()
+    })
+}
+
+/// Evaluate code in the `__main__` module.
+///
+/// Returns 0 on success. If an exception is raised, it is printed and 1 is
+/// returned, or the exit code for `SystemExit`. Returns -1 if the code
+/// couldn't be run, storing a message in `error` if it isn't NULL.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_interpreter_run_code(
+    interpreter: *mut PyembedInterpreter,
+    code: *const c_char,
+    error: *mut *mut c_char,
+) -> c_int {
+    if interpreter.is_null() {
+        set_error(error, "interpreter is NULL");
+        return -1;
+    }
+
+    let code = match str_from_ptr(code) {
+        Some(code) => code,
+        None => {
+            set_error(error, "code is NULL or not UTF-8");
+            return -1;
+        }
+    };
+
+    catch_panic(error, -1, || {
+        let py = match (*interpreter).interpreter.acquire_gil() {
+            Ok(py) => py,
+            Err(msg) => {
+                set_error(error, msg);
+                return -1;
+            }
+        };
+
+        match run_and_handle_error(py, &PythonRunMode::Eval { code }) {
+            PythonRunResult::Ok {} => 0,
+            PythonRunResult::Err {} => 1,
+            PythonRunResult::Exit { code } => code,
+        }
+    })
+}
+
+/// Obtain a handle to raise `KeyboardInterrupt` in an interpreter.
+///
+/// Returns NULL if the interpreter isn't running. The handle must be
+/// released with `pyembed_interrupt_handle_free()`.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_interpreter_interrupt_handle(
+    interpreter: *const PyembedInterpreter,
+) -> *mut PyembedInterruptHandle {
+    if interpreter.is_null() {
+        return std::ptr::null_mut();
+    }
+
+    catch_panic(
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        || match (*interpreter).interpreter.interrupt_handle() {
+            Ok(handle) => Box::into_raw(Box::new(PyembedInterruptHandle { handle })),
+            Err(_) => std::ptr::null_mut(),
+        },
+    )
+}
+
+/// Raise `KeyboardInterrupt` in the interpreter a handle belongs to.
+///
+/// May be called from any thread, e.g. while another thread is in
+/// `pyembed_interpreter_run_as_main()`. It must not be called from a signal
+/// handler. Fails if the interpreter is no longer running.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_interrupt_handle_interrupt(
+    handle: *const PyembedInterruptHandle,
+) -> c_int {
+    if handle.is_null() {
+        return -1;
+    }
+
+    catch_panic(std::ptr::null_mut(), -1, || {
+        if (*handle).handle.interrupt() {
+            0
+        } else {
+            -1
+        }
+    })
+}
+
+/// Release an interrupt handle.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_interrupt_handle_free(handle: *mut PyembedInterruptHandle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Release a string returned by this API.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_string_free(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
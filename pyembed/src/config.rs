@@ -5,6 +5,7 @@
 //! Data structures for configuring a Python interpreter.
 
 use {
+    super::python_resources::InMemoryModuleFile,
     libc::c_ulong,
     python3_sys as pyffi,
     std::ffi::{CString, OsString},
@@ -20,14 +21,121 @@ pub enum PythonRunMode {
     Repl,
     /// Run a Python module as the main module.
     Module { module: String },
+    /// Run a Python module as the main module with extra arguments.
+    ///
+    /// `args` are inserted into `sys.argv` after `sys.argv[0]`, before the
+    /// arguments the process was invoked with.
+    ModuleWithArgs { module: String, args: Vec<String> },
+    /// Run a Python module as the main program, like zipapp archives.
+    ///
+    /// Packages are run via their `__main__` submodule and `__package__`
+    /// is set, so relative imports work. Unlike `Module`, `sys.argv[0]`
+    /// isn't replaced by the module's origin, which modules imported from
+    /// memory don't have. Running the `__main__` module behaves like
+    /// running an archive created by `python -m zipapp`.
+    MainModule { module: String },
     /// Evaluate Python code from a string.
     Eval { code: String },
+    /// Evaluate multiple strings of Python code in order.
+    ///
+    /// Each string is compiled separately and evaluated in the `__main__`
+    /// module, so later code sees names defined by earlier code. An uncaught
+    /// exception stops evaluation.
+    EvalMany { code: Vec<String> },
     /// Execute Python code in a file.
     ///
     /// We define this as a CString because the underlying API wants
     /// a char* and we want the constructor of this type to worry about
     /// the type coercion.
     File { path: PathBuf },
+    /// Execute the Python file named by the first argument of the process.
+    ///
+    /// Like `python <path>`, the path is removed from the arguments and
+    /// becomes `sys.argv[0]`. If the process received no arguments, a REPL
+    /// is run instead.
+    FileFromArgs,
+    /// Run a Jupyter kernel implemented by the `ipykernel` package.
+    ///
+    /// The process is started by Jupyter with the arguments of a kernel spec.
+    /// Running the process with an `install-kernel` argument installs a
+    /// kernel spec with the given default name and display name which
+    /// starts the current executable.
+    JupyterKernel { name: String, display_name: String },
+    /// Serve a WSGI or ASGI application over HTTP.
+    ///
+    /// `application` names the application object as `module:attribute`.
+    /// If the attribute is omitted, `application` is used. `address` is the
+    /// socket address to listen on, e.g. `127.0.0.1:8000`. The server runs
+    /// until the process receives Ctrl+C.
+    ///
+    /// Request bodies are read into memory before the application is called.
+    /// Requests with a body larger than `max_request_body_size` bytes are
+    /// rejected with `413 Payload Too Large`.
+    ///
+    /// Requires the `server` feature.
+    Server {
+        application: String,
+        interface: ServerInterface,
+        address: String,
+        max_request_body_size: usize,
+    },
+}
+
+/// Defines the interface of an application run by `PythonRunMode::Server`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ServerInterface {
+    /// PEP 3333 WSGI application.
+    Wsgi,
+    /// ASGI 3 application.
+    Asgi,
+}
+
+/// Defines where output written to stdout and stderr goes.
+///
+/// Executables using the Windows GUI subsystem don't have a console. So
+/// output written to the standard streams is lost unless it is redirected.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StdioRedirect {
+    /// Leave the standard streams alone.
+    None,
+    /// Append output to a file.
+    ///
+    /// `$ORIGIN` resolves to the directory of the application at run-time.
+    File(String),
+    /// Send output to the debugger via `OutputDebugStringW()`.
+    ///
+    /// Output is discarded on platforms other than Windows.
+    Debugger,
+}
+
+/// Defines where a site-packages overlay is searched relative to packed resources.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SitePackagesOverlayPriority {
+    /// Modules in the overlay take precedence over packed resources.
+    Before,
+    /// Packed resources take precedence over modules in the overlay.
+    After,
+}
+
+/// A writable directory layered on top of packed resources on the import path.
+///
+/// This allows installing packages at run-time, e.g. plugins installed by
+/// users or via `pip install --target`.
+///
+/// The directory is created if it doesn't exist. It is tagged with the
+/// Python version and platform it was created for and ignored with a
+/// `RuntimeWarning` when used by an incompatible interpreter.
+#[derive(Clone, Debug)]
+pub struct SitePackagesOverlay {
+    /// Path of the directory.
+    ///
+    /// `$ORIGIN` resolves to the directory of the application at run-time.
+    /// `~` and environment variables (e.g. `%APPDATA%` on Windows or `$HOME`)
+    /// are expanded.
+    pub path: String,
+
+    /// Where the directory is searched relative to packed resources.
+    pub priority: SitePackagesOverlayPriority,
 }
 
 /// Defines `terminfo`` database resolution semantics.
@@ -38,9 +146,53 @@ pub enum TerminfoResolution {
     /// Do not attempt to resolve the `terminfo` database. Basically a no-op.
     None,
     /// Use a specified string as the `TERMINFO_DIRS` value.
+    ///
+    /// `$ORIGIN` resolves to the directory of the application at run-time,
+    /// allowing use of a `terminfo` database distributed with it.
+    Static(String),
+}
+
+/// Defines how CA certificates trusted by the `ssl` module are located.
+///
+/// OpenSSL is pointed at certificates via the `SSL_CERT_FILE` and
+/// `SSL_CERT_DIR` environment variables. Environment variables already set
+/// when the interpreter starts are never overwritten.
+#[derive(Clone, Debug)]
+pub enum CertificateResolution {
+    /// Look for CA certificates at well-known locations for the current OS.
+    Dynamic,
+    /// Do not configure CA certificate locations. OpenSSL's defaults apply.
+    None,
+    /// Use a specified CA bundle file.
+    ///
+    /// `$ORIGIN` resolves to the directory of the application at run-time.
     Static(String),
 }
 
+/// Start method for processes created by the `multiprocessing` module.
+///
+/// See https://docs.python.org/3/library/multiprocessing.html#contexts-and-start-methods.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MultiprocessingStartMethod {
+    /// Fork the current process.
+    Fork,
+    /// Fork new processes from a dedicated server process.
+    ForkServer,
+    /// Start a fresh process by invoking `sys.executable`.
+    Spawn,
+}
+
+impl MultiprocessingStartMethod {
+    /// The name `multiprocessing.set_start_method()` accepts for this method.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MultiprocessingStartMethod::Fork => "fork",
+            MultiprocessingStartMethod::ForkServer => "forkserver",
+            MultiprocessingStartMethod::Spawn => "spawn",
+        }
+    }
+}
+
 /// Defines an extra extension module to load.
 #[derive(Clone, Debug)]
 pub struct ExtensionModule {
@@ -133,6 +285,12 @@ pub struct PythonConfig<'a> {
     /// Controls the level of the verbose mode for the interpreter.
     pub verbose: i32,
 
+    /// Whether the GIL is enabled on free-threaded interpreters.
+    ///
+    /// `None` uses Python's default. Has no effect on interpreters with a GIL.
+    /// The value is passed to Python as the `gil` X option.
+    pub gil_enabled: Option<bool>,
+
     /// Reference to packed resources data.
     ///
     /// The referenced data contains Python module data. It likely comes from an
@@ -140,9 +298,58 @@ pub struct PythonConfig<'a> {
     ///
     /// The format of the data is defined by the ``python-packed-resources``
     /// crate. The data will be parsed as part of initializing the custom
-    /// meta path importer during interpreter initialization.
+    /// meta path importer during interpreter initialization. Empty data
+    /// is ignored.
     pub packed_resources: &'a [u8],
 
+    /// Files holding packed resources data, in priority order.
+    ///
+    /// The files are memory-mapped and parsed when the interpreter is
+    /// initialized. A resource in a file takes precedence over resources of
+    /// the same name in later files and in `packed_resources`. This allows
+    /// shipping resources next to the executable and updating them without
+    /// relinking it. `$ORIGIN` is expanded to the directory of the current
+    /// executable. A missing file is an error.
+    pub packed_resources_files: Vec<String>,
+
+    /// ed25519 public key verifying `packed_resources_files`.
+    ///
+    /// If set, each file must be accompanied by a file of the same name
+    /// with a `.sig` suffix holding its signature. Files whose signature is
+    /// missing or doesn't verify are an error and prevent the interpreter
+    /// from starting. Signed files are read into memory rather than memory
+    /// mapped, so they can't change after they were verified.
+    ///
+    /// `zip_archives` can't be verified and are an error if this is set.
+    pub packed_resources_public_key: Option<&'a [u8]>,
+
+    /// Whether to reload `packed_resources_files` when they change.
+    ///
+    /// If set, the importer checks the modification time and size of the
+    /// files before finding a module and reloads all of them if any changed.
+    /// Resources added at run-time are discarded on reload. Files that can't
+    /// be loaded, or don't verify against `packed_resources_public_key`, emit
+    /// a `RuntimeWarning` and the previously loaded resources are kept.
+    /// Replace the files by renaming a new file over them rather than by
+    /// rewriting them in place.
+    pub watch_packed_resources_files: bool,
+
+    /// How `__file__` is set for modules imported from memory.
+    pub in_memory_module_file: InMemoryModuleFile,
+
+    /// Whether `__path__` is set for packages imported from memory.
+    ///
+    /// If set, `__path__` of these packages contains a path under the
+    /// current executable, which doesn't exist. Otherwise it is empty.
+    pub in_memory_package_path: bool,
+
+    /// Names of modules to import lazily.
+    ///
+    /// Execution of these modules and of submodules of these packages is
+    /// deferred until an attribute of the module is accessed, like
+    /// `importlib.util.LazyLoader` does.
+    pub lazy_modules: Vec<String>,
+
     /// Extra extension modules to make available to the interpreter.
     ///
     /// The values will effectively be passed to ``PyImport_ExtendInitTab()``.
@@ -154,6 +361,27 @@ pub struct PythonConfig<'a> {
     /// values passed to `int main()`.
     pub argvb: bool,
 
+    /// Number of process arguments following the program name to remove
+    /// from `sys.argv`.
+    ///
+    /// This allows wrappers invoking the executable with leading arguments
+    /// of their own to hide them from Python code.
+    pub sys_argv_skip: usize,
+
+    /// Arguments inserted into `sys.argv` after the program name, before
+    /// the arguments the process was invoked with.
+    pub sys_argv_insert: Vec<String>,
+
+    /// Names of environment variables Python code is allowed to read.
+    ///
+    /// If set, other variables are removed from `os.environ` after the
+    /// interpreter is initialized. The environment of the process and of
+    /// subprocesses is left alone. Names are case-insensitive on Windows.
+    ///
+    /// Environment variables read by Python during initialization, like
+    /// `PYTHONPATH`, are controlled by `ignore_python_env` instead.
+    pub environment_allowlist: Option<Vec<String>>,
+
     /// Whether to set sys.frozen=True.
     ///
     /// Setting this will enable Python to emulate "frozen" binaries, such as
@@ -172,6 +400,30 @@ pub struct PythonConfig<'a> {
     /// How to resolve the `terminfo` database.
     pub terminfo_resolution: TerminfoResolution,
 
+    /// How to locate CA certificates for the `ssl` module.
+    pub ssl_certificate_resolution: CertificateResolution,
+
+    /// Path to the Tcl library directory used by `tkinter`.
+    ///
+    /// If set, `TCL_LIBRARY` is set to this path and `TK_LIBRARY` to the
+    /// sibling Tk library directory when they aren't already defined.
+    /// `$ORIGIN` is expanded to the directory of the current executable.
+    pub tcl_library: Option<String>,
+
+    /// Whether to enable line editing, history, and tab completion in REPLs.
+    ///
+    /// If set and `site` didn't already install one, `sys.__interactivehook__`
+    /// is defined to configure `readline` and `rlcompleter` when an
+    /// interactive REPL starts, as `site` does for `python`. Nothing happens
+    /// if no `readline` module can be imported.
+    pub repl_line_editing: bool,
+
+    /// Path to the file storing REPL command history.
+    ///
+    /// `~` and `$ORIGIN` are expanded. If not set, `~/.python_history` is
+    /// used, like `python` does. Only used if `repl_line_editing` is set.
+    pub repl_history_file: Option<String>,
+
     /// Environment variable holding the directory to write a loaded modules file.
     ///
     /// If this value is set and the environment it refers to is set,
@@ -180,9 +432,212 @@ pub struct PythonConfig<'a> {
     /// loaded in ``sys.modules``.
     pub write_modules_directory_env: Option<String>,
 
+    /// Environment variable holding the path to write a startup profile to.
+    ///
+    /// If this value is set and the environment variable it refers to is set,
+    /// timings of interpreter initialization phases and of modules imported
+    /// via the oxidized importer are recorded. They are written to the path
+    /// as JSON when the interpreter is finalized.
+    pub startup_profile_env: Option<String>,
+
+    /// Path to write a startup profile to.
+    ///
+    /// Like `startup_profile_env` but with the path given directly. If set,
+    /// this takes precedence over `startup_profile_env`.
+    pub startup_profile_path: Option<PathBuf>,
+
+    /// Environment variable holding the path to a file containing the key to decrypt packed resources data with.
+    ///
+    /// If this value is set, `packed_resources_key` isn't, and the environment
+    /// variable it refers to is set, the 32 byte key is read from the file at
+    /// that path when the interpreter starts. This allows executables using
+    /// the default configuration to import encrypted resources.
+    pub packed_resources_key_env: Option<String>,
+
+    /// Where to redirect output written to stdout and stderr.
+    pub stdio_redirect: StdioRedirect,
+
+    /// Writable directory to layer on top of packed resources.
+    pub site_packages_overlay: Option<SitePackagesOverlay>,
+
+    /// Zip archives to import modules and resources from.
+    ///
+    /// `$ORIGIN` is expanded to the directory of the current executable and
+    /// `$EXECUTABLE` to its path. The latter allows importing from an archive
+    /// appended to the executable. Packed resources take precedence over
+    /// archives.
+    pub zip_archives: Vec<String>,
+
+    /// Process arguments causing a console to be allocated.
+    ///
+    /// If the process receives any of these arguments, it is attached to a
+    /// console and `stdio_redirect` is ignored. This allows executables
+    /// using the Windows GUI subsystem to show output when asked to.
+    pub allocate_console_args: Vec<String>,
+
     /// Defines what code to run by default.
     ///
     pub run: PythonRunMode,
+
+    /// Whether to run `multiprocessing` worker processes instead of `run`.
+    ///
+    /// See `OxidizedPythonInterpreterConfig.multiprocessing_auto_dispatch`.
+    pub multiprocessing_auto_dispatch: bool,
+
+    /// Start method to configure the `multiprocessing` module with.
+    ///
+    /// `None` keeps Python's default for the platform.
+    pub multiprocessing_start_method: Option<MultiprocessingStartMethod>,
+
+    /// Memory allocator to configure Python with.
+    ///
+    /// `None` keeps Python's default. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyPreConfig.allocator.
+    pub allocator: Option<Allocator>,
+
+    /// Whether to set the `LC_CTYPE` locale to the user preferred locale.
+    ///
+    /// `None` keeps the profile's default. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyPreConfig.configure_locale.
+    pub configure_locale: Option<bool>,
+
+    /// How to coerce the C locale.
+    ///
+    /// `None` keeps the profile's default. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyPreConfig.coerce_c_locale.
+    pub coerce_c_locale: Option<CoerceCLocale>,
+
+    /// Whether to warn when the C locale is coerced.
+    ///
+    /// `None` keeps the default of not warning. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyPreConfig.coerce_c_locale_warn.
+    pub coerce_c_locale_warn: Option<bool>,
+
+    /// Whether to enable Python development mode.
+    ///
+    /// `None` keeps the default of disabled. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyConfig.dev_mode.
+    pub development_mode: Option<bool>,
+
+    /// Whether to parse command line arguments like the `python` executable.
+    ///
+    /// `None` keeps the profile's default. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyConfig.parse_argv.
+    pub parse_argv: Option<bool>,
+
+    /// Whether to enable UTF-8 mode.
+    ///
+    /// `None` keeps the profile's default. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyPreConfig.utf8_mode.
+    pub utf8_mode: Option<bool>,
+
+    /// How to validate hash-based `.pyc` files.
+    ///
+    /// `None` keeps the default of `default`. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyConfig.check_hash_pycs_mode.
+    pub check_hash_pycs_mode: Option<CheckHashPYCsMode>,
+
+    /// Whether to configure the C standard streams.
+    ///
+    /// `None` keeps the profile's default. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyConfig.configure_c_stdio.
+    pub configure_c_stdio: Option<bool>,
+
+    /// Whether to dump references still alive at exit in debug builds.
+    ///
+    /// `None` keeps the default of disabled. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyConfig.dump_refs.
+    pub dump_refs: Option<bool>,
+
+    /// Whether to enable the `faulthandler` module.
+    ///
+    /// `None` keeps the default of disabled. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyConfig.faulthandler.
+    pub fault_handler: Option<bool>,
+
+    /// Filesystem encoding.
+    ///
+    /// `None` derives it from the locale. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyConfig.filesystem_encoding.
+    pub filesystem_encoding: Option<String>,
+
+    /// Filesystem encoding error handler.
+    ///
+    /// `None` derives it from the locale. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyConfig.filesystem_errors.
+    pub filesystem_errors: Option<String>,
+
+    /// Seed for randomized hashing.
+    ///
+    /// `None` randomizes hashes. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyConfig.hash_seed.
+    pub hash_seed: Option<c_ulong>,
+
+    /// Whether to report how long imports take.
+    ///
+    /// `None` keeps the default of disabled. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyConfig.import_time.
+    pub import_time: Option<bool>,
+
+    /// Whether to install signal handlers.
+    ///
+    /// `None` keeps the default of installing them. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyConfig.install_signal_handlers.
+    pub install_signal_handlers: Option<bool>,
+
+    /// Whether to dump pymalloc statistics at exit.
+    ///
+    /// `None` keeps the default of disabled. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyConfig.malloc_stats.
+    pub malloc_stats: Option<bool>,
+
+    /// Whether to emit warnings when computing the path configuration.
+    ///
+    /// `None` keeps the default of enabled. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyConfig.pathconfig_warnings.
+    pub pathconfig_warnings: Option<bool>,
+
+    /// Directory to write `.pyc` files to instead of `__pycache__` directories.
+    ///
+    /// `None` keeps the default of `__pycache__` directories. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyConfig.pycache_prefix.
+    pub pycache_prefix: Option<PathBuf>,
+
+    /// Whether to dump allocation counts at exit in debug builds.
+    ///
+    /// `None` keeps the default of disabled. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyConfig.show_alloc_count.
+    pub show_alloc_count: Option<bool>,
+
+    /// Whether to show the total reference count at exit in debug builds.
+    ///
+    /// `None` keeps the default of disabled. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyConfig.show_ref_count.
+    pub show_ref_count: Option<bool>,
+
+    /// Whether to skip the first line of a file being run.
+    ///
+    /// `None` keeps the default of not skipping it. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyConfig.skip_source_first_line.
+    pub skip_first_source_line: Option<bool>,
+
+    /// Whether to start tracing memory allocations with `tracemalloc`.
+    ///
+    /// `None` keeps the default of disabled. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyConfig.tracemalloc.
+    pub tracemalloc: Option<bool>,
+
+    /// Warning filters, like `-W` arguments to `python`.
+    ///
+    /// Empty by default. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyConfig.warnoptions.
+    pub warn_options: Vec<String>,
+
+    /// X options, like `-X` arguments to `python`.
+    ///
+    /// Empty by default. `gil_enabled` adds to these. See
+    /// https://docs.python.org/3/c-api/init_config.html#c.PyConfig.xoptions.
+    pub x_options: Vec<String>,
 }
 
 impl<'a> Default for PythonConfig<'a> {
@@ -211,15 +666,63 @@ impl<'a> Default for PythonConfig<'a> {
             quiet: false,
             use_hash_seed: false,
             verbose: 0,
+            gil_enabled: None,
             packed_resources: &[],
+            packed_resources_files: vec![],
+            packed_resources_public_key: None,
+            watch_packed_resources_files: false,
+            in_memory_module_file: InMemoryModuleFile::None,
+            in_memory_package_path: true,
+            lazy_modules: vec![],
             extra_extension_modules: vec![],
             argvb: false,
+            sys_argv_skip: 0,
+            sys_argv_insert: vec![],
+            environment_allowlist: None,
             sys_frozen: false,
             sys_meipass: false,
             raw_allocator: PythonRawAllocator::default(),
             terminfo_resolution: TerminfoResolution::Dynamic,
+            ssl_certificate_resolution: CertificateResolution::Dynamic,
+            tcl_library: None,
+            repl_line_editing: true,
+            repl_history_file: None,
             write_modules_directory_env: None,
+            startup_profile_env: None,
+            startup_profile_path: None,
+            packed_resources_key_env: None,
+            stdio_redirect: StdioRedirect::None,
+            site_packages_overlay: None,
+            zip_archives: vec![],
+            allocate_console_args: vec![],
             run: PythonRunMode::None,
+            multiprocessing_auto_dispatch: true,
+            multiprocessing_start_method: None,
+            allocator: None,
+            configure_locale: None,
+            coerce_c_locale: None,
+            coerce_c_locale_warn: None,
+            development_mode: None,
+            parse_argv: None,
+            utf8_mode: None,
+            check_hash_pycs_mode: None,
+            configure_c_stdio: None,
+            dump_refs: None,
+            fault_handler: None,
+            filesystem_encoding: None,
+            filesystem_errors: None,
+            hash_seed: None,
+            import_time: None,
+            install_signal_handlers: None,
+            malloc_stats: None,
+            pathconfig_warnings: None,
+            pycache_prefix: None,
+            show_alloc_count: None,
+            show_ref_count: None,
+            skip_first_source_line: None,
+            tracemalloc: None,
+            warn_options: vec![],
+            x_options: vec![],
         }
     }
 }
@@ -477,12 +980,16 @@ pub struct PythonInterpreterConfig {
 }
 
 /// Defines a backend for a memory allocator.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum MemoryAllocatorBackend {
     /// The default system allocator.
     System,
     /// Use jemalloc.
     Jemalloc,
+    /// Use mimalloc.
+    Mimalloc,
+    /// Use snmalloc.
+    Snmalloc,
     /// Use Rust's global allocator.
     Rust,
 }
@@ -498,6 +1005,11 @@ pub struct PythonRawAllocator {
     pub backend: MemoryAllocatorBackend,
     /// Whether memory debugging should be enabled.
     pub debug: bool,
+    /// Whether to also use the backend for Python's `PYMEM_DOMAIN_MEM` domain.
+    ///
+    /// By default, `PyMem_Malloc()` and friends use Python's own small object
+    /// allocator (pymalloc) on top of the raw allocator.
+    pub mem_domain: bool,
 }
 
 impl PythonRawAllocator {
@@ -515,6 +1027,20 @@ impl PythonRawAllocator {
         }
     }
 
+    pub fn mimalloc() -> Self {
+        Self {
+            backend: MemoryAllocatorBackend::Mimalloc,
+            ..PythonRawAllocator::default()
+        }
+    }
+
+    pub fn snmalloc() -> Self {
+        Self {
+            backend: MemoryAllocatorBackend::Snmalloc,
+            ..PythonRawAllocator::default()
+        }
+    }
+
     pub fn rust() -> Self {
         Self {
             backend: MemoryAllocatorBackend::Rust,
@@ -532,6 +1058,7 @@ impl Default for PythonRawAllocator {
                 MemoryAllocatorBackend::Jemalloc
             },
             debug: false,
+            mem_domain: false,
         }
     }
 }
@@ -589,9 +1116,74 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// meta path importer during interpreter initialization.
     pub packed_resources: Option<&'a [u8]>,
 
+    /// Files holding packed resources data, in priority order.
+    ///
+    /// The files are memory-mapped and parsed when the interpreter is
+    /// initialized. A resource in a file takes precedence over resources of
+    /// the same name in later files and in `packed_resources`. This allows
+    /// shipping resources next to the executable and updating them without
+    /// relinking it. `$ORIGIN` is expanded to the directory of the current
+    /// executable. A missing file is an error.
+    pub packed_resources_files: Vec<String>,
+
+    /// ed25519 public key verifying `packed_resources_files`.
+    ///
+    /// If set, each file must be accompanied by a file of the same name
+    /// with a `.sig` suffix holding its signature. Files whose signature is
+    /// missing or doesn't verify are an error and prevent the interpreter
+    /// from starting. Signed files are read into memory rather than memory
+    /// mapped, so they can't change after they were verified.
+    ///
+    /// `zip_archives` can't be verified and are an error if this is set.
+    pub packed_resources_public_key: Option<&'a [u8]>,
+
+    /// Whether to reload `packed_resources_files` when they change.
+    ///
+    /// If set, the importer checks the modification time and size of the
+    /// files before finding a module and reloads all of them if any changed.
+    /// Resources added at run-time are discarded on reload. Files that can't
+    /// be loaded, or don't verify against `packed_resources_public_key`, emit
+    /// a `RuntimeWarning` and the previously loaded resources are kept.
+    /// Replace the files by renaming a new file over them rather than by
+    /// rewriting them in place.
+    pub watch_packed_resources_files: bool,
+
+    /// Key to decrypt encrypted packed resources data with.
+    ///
+    /// Packed resources data can be written with resource data encrypted, so
+    /// module source and bytecode aren't stored in the clear. The 32 byte key
+    /// it was encrypted with must be supplied here for the interpreter to
+    /// import from it. The key applies to `packed_resources` and all
+    /// `packed_resources_files`. Data is indexed in its encrypted form and
+    /// each blob is decrypted when it is accessed. Encrypted data without a
+    /// key, or with the wrong key, is an error.
+    ///
+    /// The key isn't embedded in the executable. Applications are
+    /// responsible for obtaining it, e.g. from a license server or hardware
+    /// security module, or for pointing `packed_resources_key_env` at it.
+    pub packed_resources_key: Option<&'a [u8]>,
+
+    /// How `__file__` is set for modules imported from memory.
+    pub in_memory_module_file: InMemoryModuleFile,
+
+    /// Whether `__path__` is set for packages imported from memory.
+    ///
+    /// If set, `__path__` of these packages contains a path under the
+    /// current executable, which doesn't exist. Otherwise it is empty.
+    pub in_memory_package_path: bool,
+
+    /// Names of modules to import lazily.
+    ///
+    /// Execution of these modules and of submodules of these packages is
+    /// deferred until an attribute of the module is accessed, like
+    /// `importlib.util.LazyLoader` does.
+    pub lazy_modules: Vec<String>,
+
     /// Extra extension modules to make available to the interpreter.
     ///
     /// The values will effectively be passed to ``PyImport_ExtendInitTab()``.
+    /// Built-in modules having the same name are replaced. See
+    /// `add_extension_module()`.
     pub extra_extension_modules: Option<Vec<ExtensionModule>>,
 
     /// Whether to set sys.argvb with bytes versions of process arguments.
@@ -600,6 +1192,28 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// values passed to `int main()`.
     pub argvb: bool,
 
+    /// Number of process arguments following the program name to remove
+    /// from `sys.argv`.
+    ///
+    /// This allows wrappers invoking the executable with leading arguments
+    /// of their own to hide them from Python code.
+    pub sys_argv_skip: usize,
+
+    /// Arguments inserted into `sys.argv` after the program name, before
+    /// the arguments the process was invoked with.
+    pub sys_argv_insert: Vec<String>,
+
+    /// Names of environment variables Python code is allowed to read.
+    ///
+    /// If set, other variables are removed from `os.environ` after the
+    /// interpreter is initialized. The environment of the process and of
+    /// subprocesses is left alone. Names are case-insensitive on Windows.
+    ///
+    /// Environment variables read by Python during initialization, like
+    /// `PYTHONPATH`, are controlled by `interpreter_config.use_environment`
+    /// instead.
+    pub environment_allowlist: Option<Vec<String>>,
+
     /// Whether to set sys.frozen=True.
     ///
     /// Setting this will enable Python to emulate "frozen" binaries, such as
@@ -615,6 +1229,30 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// How to resolve the `terminfo` database.
     pub terminfo_resolution: TerminfoResolution,
 
+    /// How to locate CA certificates for the `ssl` module.
+    pub ssl_certificate_resolution: CertificateResolution,
+
+    /// Path to the Tcl library directory used by `tkinter`.
+    ///
+    /// If set, `TCL_LIBRARY` is set to this path and `TK_LIBRARY` to the
+    /// sibling Tk library directory when they aren't already defined.
+    /// `$ORIGIN` is expanded to the directory of the current executable.
+    pub tcl_library: Option<String>,
+
+    /// Whether to enable line editing, history, and tab completion in REPLs.
+    ///
+    /// If set and `site` didn't already install one, `sys.__interactivehook__`
+    /// is defined to configure `readline` and `rlcompleter` when an
+    /// interactive REPL starts, as `site` does for `python`. Nothing happens
+    /// if no `readline` module can be imported.
+    pub repl_line_editing: bool,
+
+    /// Path to the file storing REPL command history.
+    ///
+    /// `~` and `$ORIGIN` are expanded. If not set, `~/.python_history` is
+    /// used, like `python` does. Only used if `repl_line_editing` is set.
+    pub repl_history_file: Option<String>,
+
     /// Environment variable holding the directory to write a loaded modules file.
     ///
     /// If this value is set and the environment it refers to is set,
@@ -623,9 +1261,62 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// loaded in ``sys.modules``.
     pub write_modules_directory_env: Option<String>,
 
+    /// Environment variable holding the path to write a startup profile to.
+    ///
+    /// If this value is set and the environment variable it refers to is set,
+    /// timings of interpreter initialization phases and of modules imported
+    /// via the oxidized importer are recorded. They are written to the path
+    /// as JSON when the interpreter is finalized.
+    pub startup_profile_env: Option<String>,
+
+    /// Environment variable holding the path to a file containing the key to decrypt packed resources data with.
+    ///
+    /// If this value is set, `packed_resources_key` isn't, and the environment
+    /// variable it refers to is set, the 32 byte key is read from the file at
+    /// that path when the interpreter starts. This allows executables using
+    /// the default configuration to import encrypted resources.
+    pub packed_resources_key_env: Option<String>,
+
+    /// Where to redirect output written to stdout and stderr.
+    pub stdio_redirect: StdioRedirect,
+
+    /// Writable directory to layer on top of packed resources.
+    pub site_packages_overlay: Option<SitePackagesOverlay>,
+
+    /// Zip archives to import modules and resources from.
+    ///
+    /// `$ORIGIN` is expanded to the directory of the current executable and
+    /// `$EXECUTABLE` to its path. The latter allows importing from an archive
+    /// appended to the executable. Packed resources take precedence over
+    /// archives.
+    pub zip_archives: Vec<String>,
+
+    /// Process arguments causing a console to be allocated.
+    ///
+    /// If the process receives any of these arguments, it is attached to a
+    /// console and `stdio_redirect` is ignored. This allows executables
+    /// using the Windows GUI subsystem to show output when asked to.
+    pub allocate_console_args: Vec<String>,
+
     /// Defines what code to run by default.
     ///
     pub run: PythonRunMode,
+
+    /// Whether to run `multiprocessing` worker processes instead of `run`.
+    ///
+    /// The `spawn` and `forkserver` start methods as well as the
+    /// `multiprocessing` resource tracker start new processes by invoking
+    /// `sys.executable` with arguments a `python` executable understands.
+    /// When this is set and the process arguments are such an invocation,
+    /// the code `multiprocessing` requested is run instead of `run`.
+    pub multiprocessing_auto_dispatch: bool,
+
+    /// Start method to configure the `multiprocessing` module with.
+    ///
+    /// If set, `multiprocessing.set_start_method()` is called during
+    /// interpreter initialization. `None` keeps Python's default for the
+    /// platform.
+    pub multiprocessing_start_method: Option<MultiprocessingStartMethod>,
 }
 
 impl<'a> Default for OxidizedPythonInterpreterConfig<'a> {
@@ -639,19 +1330,46 @@ impl<'a> Default for OxidizedPythonInterpreterConfig<'a> {
             oxidized_importer: false,
             filesystem_importer: true,
             packed_resources: None,
+            packed_resources_files: vec![],
+            packed_resources_public_key: None,
+            watch_packed_resources_files: false,
+            packed_resources_key: None,
+            in_memory_module_file: InMemoryModuleFile::None,
+            in_memory_package_path: true,
+            lazy_modules: vec![],
             extra_extension_modules: None,
             argvb: false,
+            sys_argv_skip: 0,
+            sys_argv_insert: vec![],
+            environment_allowlist: None,
             sys_frozen: false,
             sys_meipass: false,
             terminfo_resolution: TerminfoResolution::Dynamic,
+            ssl_certificate_resolution: CertificateResolution::Dynamic,
+            tcl_library: None,
+            repl_line_editing: true,
+            repl_history_file: None,
             write_modules_directory_env: None,
+            startup_profile_env: None,
+            packed_resources_key_env: None,
+            stdio_redirect: StdioRedirect::None,
+            site_packages_overlay: None,
+            zip_archives: vec![],
+            allocate_console_args: vec![],
             run: PythonRunMode::Repl,
+            multiprocessing_auto_dispatch: true,
+            multiprocessing_start_method: None,
         }
     }
 }
 
 impl<'a> From<PythonConfig<'a>> for OxidizedPythonInterpreterConfig<'a> {
     fn from(config: PythonConfig<'a>) -> Self {
+        let mut x_options = config.x_options;
+        if let Some(enabled) = config.gil_enabled {
+            x_options.push(format!("gil={}", if enabled { 1 } else { 0 }));
+        }
+
         Self {
             interpreter_config: PythonInterpreterConfig {
                 profile: if config.isolated {
@@ -690,19 +1408,79 @@ impl<'a> From<PythonConfig<'a>> for OxidizedPythonInterpreterConfig<'a> {
                 parser_debug: Some(config.parser_debug),
                 quiet: Some(config.quiet),
                 verbose: Some(config.verbose != 0),
+                x_options: if x_options.is_empty() {
+                    None
+                } else {
+                    Some(x_options)
+                },
+                warn_options: if config.warn_options.is_empty() {
+                    None
+                } else {
+                    Some(config.warn_options)
+                },
+                allocator: config.allocator,
+                configure_locale: config.configure_locale,
+                coerce_c_locale: config.coerce_c_locale,
+                coerce_c_locale_warn: config.coerce_c_locale_warn,
+                development_mode: config.development_mode,
+                parse_argv: config.parse_argv,
+                utf8_mode: config.utf8_mode,
+                check_hash_pycs_mode: config.check_hash_pycs_mode,
+                configure_c_stdio: config.configure_c_stdio,
+                dump_refs: config.dump_refs,
+                fault_handler: config.fault_handler,
+                filesystem_encoding: config.filesystem_encoding,
+                filesystem_errors: config.filesystem_errors,
+                hash_seed: config.hash_seed,
+                import_time: config.import_time,
+                install_signal_handlers: config.install_signal_handlers,
+                malloc_stats: config.malloc_stats,
+                pathconfig_warnings: config.pathconfig_warnings,
+                pycache_prefix: config.pycache_prefix,
+                show_alloc_count: config.show_alloc_count,
+                show_ref_count: config.show_ref_count,
+                skip_first_source_line: config.skip_first_source_line,
+                tracemalloc: config.tracemalloc,
                 ..PythonInterpreterConfig::default()
             },
             raw_allocator: Some(config.raw_allocator),
             oxidized_importer: config.use_custom_importlib,
             filesystem_importer: config.filesystem_importer,
-            packed_resources: Some(config.packed_resources),
+            packed_resources: if config.packed_resources.is_empty() {
+                None
+            } else {
+                Some(config.packed_resources)
+            },
+            packed_resources_files: config.packed_resources_files,
+            packed_resources_public_key: config.packed_resources_public_key,
+            watch_packed_resources_files: config.watch_packed_resources_files,
+            packed_resources_key: None,
+            in_memory_module_file: config.in_memory_module_file,
+            in_memory_package_path: config.in_memory_package_path,
+            lazy_modules: config.lazy_modules,
             extra_extension_modules: Some(config.extra_extension_modules),
             argvb: config.argvb,
+            sys_argv_skip: config.sys_argv_skip,
+            sys_argv_insert: config.sys_argv_insert,
+            environment_allowlist: config.environment_allowlist,
             sys_frozen: config.sys_frozen,
             sys_meipass: config.sys_meipass,
             terminfo_resolution: config.terminfo_resolution,
+            ssl_certificate_resolution: config.ssl_certificate_resolution,
+            tcl_library: config.tcl_library,
+            repl_line_editing: config.repl_line_editing,
+            repl_history_file: config.repl_history_file,
             write_modules_directory_env: config.write_modules_directory_env,
+            startup_profile_env: config.startup_profile_env,
+            startup_profile_path: None,
+            packed_resources_key_env: config.packed_resources_key_env,
+            stdio_redirect: config.stdio_redirect,
+            site_packages_overlay: config.site_packages_overlay,
+            zip_archives: config.zip_archives,
+            allocate_console_args: config.allocate_console_args,
             run: config.run,
+            multiprocessing_auto_dispatch: config.multiprocessing_auto_dispatch,
+            multiprocessing_start_method: config.multiprocessing_start_method,
         }
     }
 }
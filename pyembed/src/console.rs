@@ -0,0 +1,160 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Manage the console of executables lacking one.
+
+use std::io::Write;
+
+#[cfg(windows)]
+use {
+    std::ffi::CString,
+    winapi::um::{
+        consoleapi::AllocConsole,
+        debugapi::OutputDebugStringW,
+        processenv::SetStdHandle,
+        winbase::{STD_ERROR_HANDLE, STD_INPUT_HANDLE, STD_OUTPUT_HANDLE},
+        wincon::{AttachConsole, GetConsoleWindow, ATTACH_PARENT_PROCESS},
+        winnt::HANDLE,
+    },
+};
+
+/// Bind a standard file descriptor to a console device if it isn't valid.
+#[cfg(windows)]
+fn bind_console_fd(fd: libc::c_int, path: &str, flags: libc::c_int) -> Result<(), String> {
+    // Processes using the GUI subsystem start without standard handles. But
+    // the parent process may have given us some, e.g. by redirecting output
+    // to a file. Those are left alone.
+    if unsafe { libc::get_osfhandle(fd) } >= 0 {
+        return Ok(());
+    }
+
+    let c_path = CString::new(path).unwrap();
+
+    let std_handle = match fd {
+        0 => STD_INPUT_HANDLE,
+        1 => STD_OUTPUT_HANDLE,
+        _ => STD_ERROR_HANDLE,
+    };
+
+    unsafe {
+        let console_fd = libc::open(c_path.as_ptr(), flags);
+        if console_fd < 0 {
+            return Err(format!(
+                "unable to open {}: {}",
+                path,
+                std::io::Error::last_os_error()
+            ));
+        }
+
+        if console_fd != fd {
+            let res = libc::dup2(console_fd, fd);
+            libc::close(console_fd);
+
+            if res < 0 {
+                return Err(format!(
+                    "unable to bind file descriptor {} to console: {}",
+                    fd,
+                    std::io::Error::last_os_error()
+                ));
+            }
+        }
+
+        SetStdHandle(std_handle, libc::get_osfhandle(fd) as HANDLE);
+    }
+
+    Ok(())
+}
+
+/// Attach the process to a console, allocating a new one if needed.
+///
+/// The console of the parent process is preferred, so output appears in
+/// the terminal the executable was launched from. Standard file descriptors
+/// which aren't valid are bound to the console.
+///
+/// Returns whether the process was attached to a console. A process
+/// already having a console isn't attached again.
+#[cfg(windows)]
+pub(crate) fn attach_console() -> Result<bool, String> {
+    unsafe {
+        if !GetConsoleWindow().is_null() {
+            return Ok(false);
+        }
+
+        if AttachConsole(ATTACH_PARENT_PROCESS) == 0 && AllocConsole() == 0 {
+            return Err(format!(
+                "unable to allocate console: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+    }
+
+    bind_console_fd(0, "CONIN$", libc::O_RDONLY)?;
+    bind_console_fd(1, "CONOUT$", libc::O_WRONLY)?;
+    bind_console_fd(2, "CONOUT$", libc::O_WRONLY)?;
+
+    Ok(true)
+}
+
+/// Attach the process to a console, allocating a new one if needed.
+///
+/// Consoles only exist on Windows. So this does nothing.
+#[cfg(not(windows))]
+pub(crate) fn attach_console() -> Result<bool, String> {
+    Ok(false)
+}
+
+#[cfg(windows)]
+fn output_debug_string(value: &str) {
+    let mut wide = value.encode_utf16().collect::<Vec<_>>();
+    wide.push(0);
+
+    unsafe {
+        OutputDebugStringW(wide.as_ptr());
+    }
+}
+
+#[cfg(not(windows))]
+fn output_debug_string(_value: &str) {}
+
+/// A `Write` sending output to the debugger of the process.
+///
+/// Output is passed to `OutputDebugStringW()`. A UTF-8 sequence split
+/// across writes is held back until it is complete. Invalid UTF-8 is
+/// replaced.
+#[derive(Default)]
+pub(crate) struct DebuggerWriter {
+    pending: Vec<u8>,
+}
+
+impl Write for DebuggerWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+
+        let complete = match std::str::from_utf8(&self.pending) {
+            Ok(_) => self.pending.len(),
+            // The data ends in the middle of a sequence.
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => self.pending.len(),
+        };
+
+        let data = self.pending.drain(..complete).collect::<Vec<_>>();
+        if !data.is_empty() {
+            output_debug_string(&String::from_utf8_lossy(&data));
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for DebuggerWriter {
+    fn drop(&mut self) {
+        if !self.pending.is_empty() {
+            output_debug_string(&String::from_utf8_lossy(&self.pending));
+        }
+    }
+}
@@ -9,24 +9,34 @@ This module defines a Python meta path importer and associated functionality
 for importing Python modules from memory.
 */
 
-#[cfg(not(library_mode = "extension"))]
-use cpython::NoArgs;
 use {
-    super::conversion::pyobject_to_pathbuf,
+    super::conversion::{path_to_pyobject, pyobject_to_pathbuf},
+    super::pkg_resources::register_pkg_resources_with_module,
+    super::profiling,
     super::python_resources::{
-        pyobject_to_resource, resource_to_pyobject, OptimizeLevel, OxidizedResource,
-        PythonResourcesState,
+        pyobject_to_resource, resource_blob_data, resource_flavor_to_str, resource_locations,
+        resource_provenance_distribution, resource_to_pyobject, InMemoryModuleFile, OptimizeLevel,
+        OxidizedResource, PythonResourcesState,
     },
     super::resource_scanning::find_resources_in_path,
     cpython::buffer::PyBuffer,
-    cpython::exc::{FileNotFoundError, IOError, ImportError, ValueError},
+    cpython::exc::{
+        FileNotFoundError, IOError, ImportError, IsADirectoryError, KeyError, NotADirectoryError,
+        ValueError,
+    },
     cpython::{
-        py_class, py_fn, ObjectProtocol, PyBytes, PyCapsule, PyClone, PyDict, PyErr, PyList,
-        PyModule, PyObject, PyResult, PyString, PyTuple, Python, PythonObject, ToPyObject,
+        py_class, py_class_prop_getter, py_fn, NoArgs, ObjectProtocol, PyBytes, PyCapsule, PyClone,
+        PyDict, PyErr, PyList, PyModule, PyObject, PyResult, PyString, PyTuple, Python,
+        PythonObject, ToPyObject,
     },
     python3_sys as pyffi,
-    python_packed_resources::data::ResourceFlavor,
-    std::sync::Arc,
+    python_packed_resources::data::{Resource, ResourceField, ResourceFlavor},
+    python_packed_resources::parser::verify_packed_resources,
+    std::borrow::Cow,
+    std::collections::HashMap,
+    std::path::{Path, PathBuf},
+    std::sync::{Arc, Mutex, Weak},
+    std::time::{Instant, SystemTime},
 };
 #[cfg(windows)]
 use {
@@ -178,6 +188,8 @@ fn load_dynamic_library(
                 ),
             ));
         }
+
+        return Err(PyErr::fetch(py));
     }
 
     // Cast to owned type to help prevent refcount/memory leaks.
@@ -273,6 +285,8 @@ pub(crate) struct ImporterState {
     decode_source: PyObject,
     /// `builtins.exec` function.
     exec_fn: PyObject,
+    /// `sys.audit` function, if the interpreter supports audit hooks.
+    audit_fn: Option<PyObject>,
     /// Bytecode optimization level currently in effect.
     optimize_level: OptimizeLevel,
     /// Holds state about importable resources.
@@ -300,6 +314,109 @@ pub(crate) struct ImporterState {
     /// We need to hold a reference to this instance because resources_state
     /// was constructed from a &[u8] backed by it.
     _resources_mmap: Option<Box<memmap::Mmap>>,
+
+    /// Resources files that are reloaded when they change.
+    watched_resources: Option<Mutex<WatchedResources>>,
+}
+
+/// Resources data loaded from a watched resources file.
+enum WatchedResourcesData {
+    /// The file is memory mapped.
+    Mapped(memmap::Mmap),
+
+    /// The file was copied into memory and verified.
+    Copied(Vec<u8>),
+}
+
+impl WatchedResourcesData {
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            WatchedResourcesData::Mapped(mapped) => &mapped[..],
+            WatchedResourcesData::Copied(data) => data.as_slice(),
+        }
+    }
+}
+
+/// Resources files watched for changes.
+pub(crate) struct WatchedResources {
+    /// Paths to the resources files, highest priority first, and their
+    /// modification time and size when they were last loaded.
+    files: Vec<(PathBuf, Option<(Option<SystemTime>, u64)>)>,
+
+    /// Resources data that isn't watched and is loaded below the files.
+    base: Vec<&'static [u8]>,
+
+    /// Public key files are verified against before they are loaded.
+    public_key: Option<Vec<u8>>,
+
+    /// Resources data of reloaded files, keyed by the index borrowing from it.
+    ///
+    /// Readers pin the index while they use resources. Data is released once
+    /// its index is no longer pinned.
+    loaded: Vec<(
+        Weak<HashMap<Cow<'static, str>, Resource<'static, u8>>>,
+        Vec<WatchedResourcesData>,
+    )>,
+}
+
+impl WatchedResources {
+    /// Construct an instance watching `files`.
+    ///
+    /// Call before the files are first read so changes made meanwhile are
+    /// picked up.
+    pub(crate) fn new(
+        files: Vec<PathBuf>,
+        base: Vec<&'static [u8]>,
+        public_key: Option<Vec<u8>>,
+    ) -> Self {
+        Self {
+            files: files
+                .into_iter()
+                .map(|path| {
+                    let signature = file_signature(&path);
+                    (path, signature)
+                })
+                .collect(),
+            base,
+            public_key,
+            loaded: vec![],
+        }
+    }
+
+    /// Read a watched file, verifying it if a public key is set.
+    fn read(&self, path: &Path) -> Result<WatchedResourcesData, String> {
+        if let Some(public_key) = &self.public_key {
+            // A memory mapped file changes along with the file. So the
+            // file is copied into memory and the copy is verified.
+            let data =
+                std::fs::read(path).map_err(|e| format!("unable to read resources file: {}", e))?;
+
+            let mut signature_path = path.as_os_str().to_owned();
+            signature_path.push(".sig");
+            let signature = std::fs::read(&signature_path)
+                .map_err(|e| format!("unable to read signature of resources file: {}", e))?;
+
+            verify_packed_resources(&data, &signature, public_key)
+                .map_err(|e| format!("unable to verify resources file: {}", e))?;
+
+            Ok(WatchedResourcesData::Copied(data))
+        } else {
+            let f = std::fs::File::open(path)
+                .map_err(|e| format!("unable to open resources file: {}", e))?;
+
+            let mapped = unsafe { memmap::Mmap::map(&f) }
+                .map_err(|e| format!("unable to memory map resources file: {}", e))?;
+
+            Ok(WatchedResourcesData::Mapped(mapped))
+        }
+    }
+}
+
+/// Obtain the modification time and size of a file.
+fn file_signature(path: &Path) -> Option<(Option<SystemTime>, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+
+    Some((metadata.modified().ok(), metadata.len()))
 }
 
 impl ImporterState {
@@ -396,6 +513,9 @@ impl ImporterState {
             PyObject::from_owned_ptr(py, ptr).unchecked_cast_into()
         };
 
+        // `sys.audit()` was added in Python 3.8.
+        let audit_fn = sys_module.get(py, "audit").ok();
+
         Ok(ImporterState {
             imp_module,
             sys_module,
@@ -407,14 +527,31 @@ impl ImporterState {
             module_spec_type,
             decode_source,
             exec_fn,
+            audit_fn,
             optimize_level,
             resources_state: capsule,
             resources_state_owned,
             _resources_py_object: resources_py_object,
             _resources_mmap: resources_mmap,
+            watched_resources: None,
         })
     }
 
+    /// Raise a PEP 578 audit event.
+    ///
+    /// Hooks installed via `sys.addaudithook()` are called with `event` and
+    /// `args`. They may abort the operation by raising.
+    fn audit(&self, py: Python, event: &str, args: Vec<PyObject>) -> PyResult<()> {
+        if let Some(audit_fn) = &self.audit_fn {
+            let mut call_args = vec![event.to_py_object(py).into_object()];
+            call_args.extend(args);
+
+            audit_fn.call(py, PyTuple::new(py, &call_args), None)?;
+        }
+
+        Ok(())
+    }
+
     /// Obtain the `PythonResourcesState` associated with this instance.
     #[inline]
     pub fn get_resources_state<'a>(&self) -> &PythonResourcesState<'a, u8> {
@@ -445,6 +582,134 @@ impl ImporterState {
 
         unsafe { &mut *(ptr as *mut PythonResourcesState<u8>) }
     }
+
+    /// Obtain a handle on the current resources index.
+    ///
+    /// Resources looked up through the handle remain valid while it is held,
+    /// even if the index is modified meanwhile, e.g. by another thread running
+    /// while we call into Python. Obtaining the handle only increments a
+    /// reference count; readers never lock.
+    #[inline]
+    pub(crate) fn pin_resources<'a>(&self) -> Arc<HashMap<Cow<'a, str>, Resource<'a, u8>>> {
+        self.get_resources_state().resources.clone()
+    }
+
+    /// Reload the watched resources files if any changed since they were last
+    /// loaded.
+    ///
+    /// The resources index is replaced wholesale, so resources added at run-time
+    /// are discarded. If the new files can't be loaded, a `RuntimeWarning` is
+    /// emitted and the existing index is kept.
+    ///
+    /// Returns whether the index was replaced.
+    fn reload_watched_resources(&self, py: Python) -> PyResult<bool> {
+        let watched = match &self.watched_resources {
+            Some(watched) => watched,
+            None => return Ok(false),
+        };
+
+        // The lock is released before warning, as emitting a warning may
+        // import modules and call back into us.
+        let res = {
+            let mut watched = watched.lock().unwrap();
+            let mut signatures = Vec::with_capacity(watched.files.len());
+
+            for (path, _) in &watched.files {
+                // A file may be missing momentarily while it is being
+                // rewritten. Try again on a subsequent call.
+                match file_signature(path) {
+                    Some(signature) => signatures.push(Some(signature)),
+                    None => return Ok(false),
+                }
+            }
+
+            if watched
+                .files
+                .iter()
+                .zip(signatures.iter())
+                .all(|((_, old), new)| old == new)
+            {
+                return Ok(false);
+            }
+
+            // Record the signatures even if loading fails so we don't retry
+            // loading bad files until they change again.
+            for ((_, old), new) in watched.files.iter_mut().zip(signatures) {
+                *old = new;
+            }
+
+            self.load_watched_resources(&mut watched)
+        };
+
+        match res {
+            Ok(()) => Ok(true),
+            Err((path, msg)) => {
+                self.warn_reload_failure(py, &path, &msg)?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Load watched resources files, replacing the current resources index.
+    fn load_watched_resources(
+        &self,
+        watched: &mut WatchedResources,
+    ) -> Result<(), (PathBuf, String)> {
+        let mut data = Vec::with_capacity(watched.files.len());
+
+        for (path, _) in &watched.files {
+            data.push(watched.read(path).map_err(|e| (path.clone(), e))?);
+        }
+
+        // See oxidized_finder_new() for why we create slices this way. The
+        // data doesn't move along with the Mmap or Vec holding it.
+        let mut segments = data
+            .iter()
+            .map(|data| {
+                let data = data.as_slice();
+                unsafe { std::slice::from_raw_parts::<'static, u8>(data.as_ptr(), data.len()) }
+            })
+            .collect::<Vec<_>>();
+        segments.extend(watched.base.iter().copied());
+
+        let key = self.get_resources_state().packed_resources_key.clone();
+        let mut new_state = PythonResourcesState::<'static, u8>::default();
+        new_state
+            .load(&segments, key.as_deref())
+            .map_err(|e| (watched.files[0].0.clone(), e))?;
+
+        // Only the index is replaced. Readers holding the previous index keep
+        // it alive. Data backing an index is released once no reader holds it.
+        let state = self.get_resources_state_mut();
+        state.resources = new_state.resources;
+        state.segments = new_state.segments;
+
+        watched
+            .loaded
+            .retain(|(index, _)| index.upgrade().is_some());
+        watched
+            .loaded
+            .push((Arc::downgrade(&state.resources), data));
+
+        Ok(())
+    }
+
+    fn warn_reload_failure(&self, py: Python, path: &Path, msg: &str) -> PyResult<()> {
+        let warnings = py.import("warnings")?;
+        let runtime_warning = py.import("builtins")?.get(py, "RuntimeWarning")?;
+
+        warnings.call(
+            py,
+            "warn",
+            (
+                format!("unable to reload {}: {}", path.display(), msg),
+                runtime_warning,
+            ),
+            None,
+        )?;
+
+        Ok(())
+    }
 }
 
 impl Drop for ImporterState {
@@ -483,7 +748,18 @@ py_class!(class OxidizedFinder |py| {
     // Start of importlib.abc.MetaPathFinder interface.
 
     def find_spec(&self, fullname: &PyString, path: &PyObject, target: Option<PyObject> = None) -> PyResult<PyObject> {
-        self.find_spec_impl(py, fullname, path, target)
+        let start = Instant::now();
+        let res = self.find_spec_impl(py, fullname, path, target);
+
+        if profiling::enabled() {
+            if let Ok(spec) = &res {
+                if *spec != py.None() {
+                    profiling::record_find(&fullname.to_string_lossy(py), start);
+                }
+            }
+        }
+
+        res
     }
 
     def find_module(&self, fullname: &PyObject, path: &PyObject) -> PyResult<PyObject> {
@@ -526,6 +802,10 @@ py_class!(class OxidizedFinder |py| {
         self.get_source_impl(py, fullname)
     }
 
+    def is_package(&self, fullname: &PyString) -> PyResult<PyObject> {
+        self.is_package_impl(py, fullname)
+    }
+
     // Start of importlib.abc.ExecutionLoader interface.
 
     def get_filename(&self, fullname: &PyString) -> PyResult<PyObject> {
@@ -551,13 +831,18 @@ py_class!(class OxidizedFinder |py| {
         self.iter_modules_impl(py, prefix)
     }
 
+    // pkg_resources integration.
+    def register_pkg_resources(&self) -> PyResult<PyObject> {
+        self.register_pkg_resources_impl(py)
+    }
+
     // Additional methods provided for convenience.
-    def __new__(_cls, resources_data: Option<PyObject> = None, resources_file: Option<PyObject> = None, relative_path_origin: Option<PyObject> = None) -> PyResult<OxidizedFinder> {
-        oxidized_finder_new(py, resources_data, resources_file, relative_path_origin)
+    def __new__(_cls, resources_data: Option<PyObject> = None, resources_file: Option<PyObject> = None, relative_path_origin: Option<PyObject> = None, in_memory_module_file: Option<String> = None, in_memory_package_path: bool = true, lazy_modules: Option<Vec<String>> = None, watch_resources_file: bool = false) -> PyResult<OxidizedFinder> {
+        oxidized_finder_new(py, resources_data, resources_file, relative_path_origin, in_memory_module_file, in_memory_package_path, lazy_modules, watch_resources_file)
     }
 
-    def indexed_resources(&self) -> PyResult<PyObject> {
-        self.indexed_resources_impl(py)
+    def indexed_resources(&self, flavor: Option<String> = None, location: Option<String> = None, package: Option<String> = None, distribution: Option<String> = None) -> PyResult<PyObject> {
+        self.indexed_resources_impl(py, flavor, location, package, distribution)
     }
 
     def add_resource(&self, resource: OxidizedResource) -> PyResult<PyObject> {
@@ -568,9 +853,17 @@ py_class!(class OxidizedFinder |py| {
         self.add_resources_impl(py, resources)
     }
 
+    def remove_resource(&self, name: &PyString) -> PyResult<PyObject> {
+        self.remove_resource_impl(py, name)
+    }
+
     def serialize_indexed_resources(&self, ignore_builtin: bool = true, ignore_frozen: bool = true) -> PyResult<PyObject> {
         self.serialize_indexed_resources_impl(py, ignore_builtin, ignore_frozen)
     }
+
+    def resources_memory_usage(&self) -> PyResult<PyObject> {
+        self.resources_memory_usage_impl(py)
+    }
 });
 
 // importlib.abc.MetaPathFinder interface.
@@ -583,23 +876,42 @@ impl OxidizedFinder {
         target: Option<PyObject>,
     ) -> PyResult<PyObject> {
         let state = self.state(py);
+        state.reload_watched_resources(py)?;
+        let resources = state.pin_resources();
+
         let key = fullname.to_string(py)?;
 
-        let module = match state
-            .get_resources_state()
-            .resolve_importable_module(&key, state.optimize_level)
-        {
+        let module = match resources.get(&*key).and_then(|resource| {
+            state
+                .get_resources_state()
+                .importable_module(resource, state.optimize_level)
+        }) {
             Some(module) => module,
             None => return Ok(py.None()),
         };
 
         match module.flavor {
-            ResourceFlavor::Extension | ResourceFlavor::Module => module.resolve_module_spec(
-                py,
-                &state.module_spec_type,
-                self.as_object(),
-                state.optimize_level,
-            ),
+            ResourceFlavor::Extension | ResourceFlavor::Module => {
+                // Lazy modules are loaded by a `LazyLoader` wrapping us. It
+                // defers executing the module until one of its attributes is
+                // accessed. Extension modules are executed when created, so
+                // they can't be loaded lazily.
+                let loader = if module.flavor == &ResourceFlavor::Module
+                    && state.get_resources_state().is_lazy_module(&key)
+                {
+                    let lazy_loader = py.import("importlib.util")?.get(py, "LazyLoader")?;
+                    lazy_loader.call(py, (self.as_object(),), None)?
+                } else {
+                    self.as_object().clone_ref(py)
+                };
+
+                module.resolve_module_spec(
+                    py,
+                    &state.module_spec_type,
+                    &loader,
+                    state.optimize_level,
+                )
+            }
             ResourceFlavor::BuiltinExtensionModule => {
                 // BuiltinImporter.find_spec() always returns None if `path` is defined.
                 // And it doesn't use `target`. So don't proxy these values.
@@ -617,6 +929,8 @@ impl OxidizedFinder {
     }
 
     fn invalidate_caches_impl(&self, py: Python) -> PyResult<PyObject> {
+        self.state(py).reload_watched_resources(py)?;
+
         Ok(py.None())
     }
 
@@ -642,10 +956,11 @@ impl OxidizedFinder {
 impl OxidizedFinder {
     fn create_module_impl(&self, py: Python, spec: &PyObject) -> PyResult<PyObject> {
         let state = self.state(py);
+        let resources = state.pin_resources();
         let name = spec.getattr(py, "name")?;
         let key = name.extract::<String>(py)?;
 
-        let entry = match state.get_resources_state().resources.get(&*key) {
+        let entry = match resources.get(&*key) {
             Some(entry) => entry,
             None => return Ok(py.None()),
         };
@@ -653,6 +968,12 @@ impl OxidizedFinder {
         match entry.flavor {
             // Extension modules need special module creation logic.
             ResourceFlavor::Extension => {
+                state.audit(
+                    py,
+                    "oxidized_importer.load",
+                    vec![name.clone_ref(py), spec.getattr(py, "origin")?],
+                )?;
+
                 // We need a custom implementation of create_module() for in-memory shared
                 // library extensions because if we wait until `exec_module()` to
                 // initialize the module object, this can confuse some CPython
@@ -663,6 +984,14 @@ impl OxidizedFinder {
                 // initialization into `exec_module()`.
                 if let Some(library_data) = &entry.in_memory_extension_module_shared_library {
                     let sys_modules = state.sys_module.as_object().getattr(py, "modules")?;
+                    let library_data = resource_blob_data(
+                        py,
+                        entry,
+                        ResourceField::InMemoryExtensionModuleSharedLibrary,
+                        None,
+                        library_data,
+                        state.get_resources_state().packed_resources_key.as_deref(),
+                    )?;
 
                     extension_module_shared_library_create_module(
                         state.get_resources_state(),
@@ -671,7 +1000,7 @@ impl OxidizedFinder {
                         spec,
                         name,
                         &key,
-                        library_data,
+                        &library_data,
                     )
                 } else {
                     // Call `imp.create_dynamic()` for dynamic extension modules.
@@ -689,13 +1018,15 @@ impl OxidizedFinder {
 
     fn exec_module_impl(&self, py: Python, module: &PyObject) -> PyResult<PyObject> {
         let state = self.state(py);
+        let resources = state.pin_resources();
         let name = module.getattr(py, "__name__")?;
         let key = name.extract::<String>(py)?;
 
-        let mut entry = match state
-            .get_resources_state()
-            .resolve_importable_module(&key, state.optimize_level)
-        {
+        let mut entry = match resources.get(&*key).and_then(|resource| {
+            state
+                .get_resources_state()
+                .importable_module(resource, state.optimize_level)
+        }) {
             Some(entry) => entry,
             None => {
                 // Raising here might make more sense, as `find_spec()` shouldn't have returned
@@ -704,7 +1035,16 @@ impl OxidizedFinder {
             }
         };
 
-        if let Some(bytecode) = entry.resolve_bytecode(
+        let origin = entry.resolve_origin(py)?.unwrap_or_else(|| py.None());
+        state.audit(
+            py,
+            "oxidized_importer.load",
+            vec![name.clone_ref(py), origin],
+        )?;
+
+        let load_start = Instant::now();
+
+        let res = if let Some(bytecode) = entry.resolve_bytecode(
             py,
             state.optimize_level,
             &state.decode_source,
@@ -712,28 +1052,45 @@ impl OxidizedFinder {
         )? {
             let code = state.marshal_loads.call(py, (bytecode,), None)?;
             let dict = module.getattr(py, "__dict__")?;
+            profiling::record_load(&key, load_start);
 
-            state
-                .call_with_frames_removed
-                .call(py, (&state.exec_fn, code, dict), None)
+            profiling::time_exec(&key, || {
+                state
+                    .call_with_frames_removed
+                    .call(py, (&state.exec_fn, code, dict), None)
+            })
         } else if entry.flavor == &ResourceFlavor::BuiltinExtensionModule {
-            state
-                .builtin_importer
-                .call_method(py, "exec_module", (module,), None)
+            profiling::time_exec(&key, || {
+                state
+                    .builtin_importer
+                    .call_method(py, "exec_module", (module,), None)
+            })
         } else if entry.flavor == &ResourceFlavor::FrozenModule {
-            state
-                .frozen_importer
-                .call_method(py, "exec_module", (module,), None)
+            profiling::time_exec(&key, || {
+                state
+                    .frozen_importer
+                    .call_method(py, "exec_module", (module,), None)
+            })
         } else if entry.flavor == &ResourceFlavor::Extension {
             // `ExtensionFileLoader.exec_module()` simply calls `imp.exec_dynamic()`.
             let exec_dynamic = state.imp_module.as_object().getattr(py, "exec_dynamic")?;
 
-            state
-                .call_with_frames_removed
-                .call(py, (&exec_dynamic, module), None)
+            profiling::time_exec(&key, || {
+                state
+                    .call_with_frames_removed
+                    .call(py, (&exec_dynamic, module), None)
+            })
         } else {
             Ok(py.None())
+        };
+
+        // pkg_resources only knows about distributions and resources on
+        // sys.path. Teach it about ours as soon as it is loaded.
+        if res.is_ok() && key == "pkg_resources" {
+            register_pkg_resources_with_module(py, self.as_object(), module)?;
         }
+
+        res
     }
 }
 
@@ -747,25 +1104,37 @@ impl OxidizedFinder {
     /// found. The path is expected to be constructed using a module’s __file__
     /// attribute or an item from a package’s __path__.
     fn get_data_impl(&self, py: Python, path: &PyString) -> PyResult<PyObject> {
-        self.state(py)
+        let state = self.state(py);
+
+        state.audit(
+            py,
+            "oxidized_importer.get_data",
+            vec![path.as_object().clone_ref(py)],
+        )?;
+
+        state
             .get_resources_state()
             .resolve_resource_data_from_path(py, path)
     }
 }
 
 // importlib.abc.InspectLoader interface.
+//
+// Per the interface, ImportError is raised for modules we don't know about.
 impl OxidizedFinder {
     fn get_code_impl(&self, py: Python, fullname: &PyString) -> PyResult<PyObject> {
         let state = self.state(py);
+        let resources = state.pin_resources();
         let key = fullname.to_string(py)?;
 
-        let mut module = match state
-            .get_resources_state()
-            .resolve_importable_module(&key, state.optimize_level)
-        {
-            Some(module) => module,
-            None => return Ok(py.None()),
-        };
+        let mut module = resources
+            .get(&*key)
+            .and_then(|resource| {
+                state
+                    .get_resources_state()
+                    .importable_module(resource, state.optimize_level)
+            })
+            .ok_or_else(|| PyErr::new::<ImportError, _>(py, ("unknown module", &key)))?;
 
         if let Some(bytecode) = module.resolve_bytecode(
             py,
@@ -785,15 +1154,17 @@ impl OxidizedFinder {
 
     fn get_source_impl(&self, py: Python, fullname: &PyString) -> PyResult<PyObject> {
         let state = self.state(py);
+        let resources = state.pin_resources();
         let key = fullname.to_string(py)?;
 
-        let module = match state
-            .get_resources_state()
-            .resolve_importable_module(&key, state.optimize_level)
-        {
-            Some(module) => module,
-            None => return Ok(py.None()),
-        };
+        let module = resources
+            .get(&*key)
+            .and_then(|resource| {
+                state
+                    .get_resources_state()
+                    .importable_module(resource, state.optimize_level)
+            })
+            .ok_or_else(|| PyErr::new::<ImportError, _>(py, ("unknown module", &key)))?;
 
         let source = module.resolve_source(py, &state.decode_source, &state.io_module)?;
 
@@ -803,6 +1174,29 @@ impl OxidizedFinder {
             py.None()
         })
     }
+
+    fn is_package_impl(&self, py: Python, fullname: &PyString) -> PyResult<PyObject> {
+        let state = self.state(py);
+        let resources = state.pin_resources();
+        let key = fullname.to_string(py)?;
+
+        let module = resources
+            .get(&*key)
+            .and_then(|resource| {
+                state
+                    .get_resources_state()
+                    .importable_module(resource, state.optimize_level)
+            })
+            .ok_or_else(|| PyErr::new::<ImportError, _>(py, ("unknown module", &key)))?;
+
+        if module.flavor == &ResourceFlavor::FrozenModule {
+            state
+                .imp_module
+                .call(py, "is_frozen_package", (fullname,), None)
+        } else {
+            Ok(module.is_package.to_py_object(py).into_object())
+        }
+    }
 }
 
 // importlib.abc.ExecutionLoader interface.
@@ -815,13 +1209,18 @@ impl OxidizedFinder {
     /// source file, regardless of whether a bytecode was used to load the module.
     fn get_filename_impl(&self, py: Python, fullname: &PyString) -> PyResult<PyObject> {
         let state = self.state(py);
+        let resources = state.pin_resources();
         let key = fullname.to_string(py)?;
 
         let make_error = |msg: &str| -> PyErr { PyErr::new::<ImportError, _>(py, (msg, &key)) };
 
-        let module = state
-            .get_resources_state()
-            .resolve_importable_module(&key, state.optimize_level)
+        let module = resources
+            .get(&*key)
+            .and_then(|resource| {
+                state
+                    .get_resources_state()
+                    .importable_module(resource, state.optimize_level)
+            })
             .ok_or_else(|| make_error("unknown module"))?;
 
         module
@@ -835,12 +1234,14 @@ impl OxidizedFinder {
 impl OxidizedFinder {
     fn get_resource_reader_impl(&self, py: Python, fullname: &PyString) -> PyResult<PyObject> {
         let state = self.state(py);
+        let resources = state.pin_resources();
         let key = fullname.to_string(py)?;
 
-        let entry = match state
-            .get_resources_state()
-            .resolve_importable_module(&key, state.optimize_level)
-        {
+        let entry = match resources.get(&*key).and_then(|resource| {
+            state
+                .get_resources_state()
+                .importable_module(resource, state.optimize_level)
+        }) {
             Some(entry) => entry,
             None => return Ok(py.None()),
         };
@@ -886,7 +1287,7 @@ impl OxidizedFinder {
             (None, None)
         };
 
-        super::package_metadata::find_distributions(py, state.clone(), path, name)
+        super::package_metadata::find_distributions(py, state.clone(), name, path)
     }
 }
 
@@ -907,6 +1308,22 @@ impl OxidizedFinder {
     }
 }
 
+// pkg_resources support.
+impl OxidizedFinder {
+    /// def register_pkg_resources()
+    ///
+    /// Register with `pkg_resources`. This happens automatically when this
+    /// finder loads `pkg_resources`. Otherwise it needs to be called after
+    /// `pkg_resources` is imported.
+    fn register_pkg_resources_impl(&self, py: Python) -> PyResult<PyObject> {
+        let pkg_resources = py.import("pkg_resources")?;
+
+        register_pkg_resources_with_module(py, self.as_object(), pkg_resources.as_object())?;
+
+        Ok(py.None())
+    }
+}
+
 impl OxidizedFinder {
     /// Construct an instance from a module and resources state.
     #[cfg(not(library_mode = "extension"))]
@@ -914,32 +1331,38 @@ impl OxidizedFinder {
         py: Python,
         m: &PyModule,
         resources_state: &PythonResourcesState<'a, u8>,
+        watched_resources: Option<WatchedResources>,
     ) -> PyResult<OxidizedFinder> {
         let bootstrap_module = py.import("_frozen_importlib")?;
 
-        let importer = OxidizedFinder::create_instance(
+        let mut state = ImporterState::new(
             py,
-            Arc::new(Box::new(ImporterState::new(
-                py,
-                &m,
-                &bootstrap_module,
-                resources_state,
-                false,
-                None,
-                None,
-            )?)),
+            &m,
+            &bootstrap_module,
+            resources_state,
+            false,
+            None,
+            None,
         )?;
+        state.watched_resources = watched_resources.map(Mutex::new);
+
+        let importer = OxidizedFinder::create_instance(py, Arc::new(Box::new(state)))?;
 
         Ok(importer)
     }
 }
 
 /// OxidizedFinder.__new__(resources_data=None)
+#[allow(clippy::too_many_arguments)]
 fn oxidized_finder_new(
     py: Python,
     resources_data: Option<PyObject>,
     resources_file: Option<PyObject>,
     relative_path_origin: Option<PyObject>,
+    in_memory_module_file: Option<String>,
+    in_memory_package_path: bool,
+    lazy_modules: Option<Vec<String>>,
+    watch_resources_file: bool,
 ) -> PyResult<OxidizedFinder> {
     // We need to obtain an ImporterState instance. This requires handles on a
     // few items...
@@ -974,6 +1397,34 @@ fn oxidized_finder_new(
         resources_state.origin = pyobject_to_pathbuf(py, py_origin)?;
     }
 
+    if let Some(value) = in_memory_module_file {
+        resources_state.in_memory_module_file = match value.as_str() {
+            "none" => InMemoryModuleFile::None,
+            "uri" => InMemoryModuleFile::Uri,
+            "path" => InMemoryModuleFile::Path,
+            _ => {
+                return Err(PyErr::new::<ValueError, _>(
+                    py,
+                    format!(
+                        "in_memory_module_file must be one of none, uri, path; got {}",
+                        value
+                    ),
+                ))
+            }
+        };
+    }
+    resources_state.in_memory_package_path = in_memory_package_path;
+    resources_state.lazy_modules = lazy_modules.unwrap_or_default();
+
+    if watch_resources_file && resources_file.is_none() {
+        return Err(PyErr::new::<ValueError, _>(
+            py,
+            "watch_resources_file requires resources_file",
+        ));
+    }
+
+    let mut watched_resources = None;
+
     // If we received a PyObject defining resources data, try to resolve it.
     let (raw_resources_data, mapped) = if let Some(resources) = &resources_data {
         let buffer = PyBuffer::get(py, resources)?;
@@ -986,6 +1437,16 @@ fn oxidized_finder_new(
     } else if let Some(resources_file) = resources_file {
         let path = pyobject_to_pathbuf(py, resources_file)?;
 
+        // Capture the signature before reading so changes made while we
+        // load are picked up.
+        if watch_resources_file {
+            watched_resources = Some(Mutex::new(WatchedResources::new(
+                vec![path.clone()],
+                vec![],
+                None,
+            )));
+        }
+
         let f = std::fs::File::open(&path).map_err(|e| {
             PyErr::new::<IOError, _>(py, format!("unable to open resources file: {}", e))
         })?;
@@ -1006,21 +1467,21 @@ fn oxidized_finder_new(
     };
 
     resources_state
-        .load(raw_resources_data)
+        .load(&raw_resources_data.into_iter().collect::<Vec<_>>(), None)
         .map_err(|err| PyErr::new::<ValueError, _>(py, err))?;
 
-    let importer = OxidizedFinder::create_instance(
+    let mut state = ImporterState::new(
         py,
-        Arc::new(Box::new(ImporterState::new(
-            py,
-            &m,
-            &bootstrap_module,
-            &resources_state,
-            true,
-            resources_data,
-            mapped,
-        )?)),
+        &m,
+        &bootstrap_module,
+        &resources_state,
+        true,
+        resources_data,
+        mapped,
     )?;
+    state.watched_resources = watched_resources;
+
+    let importer = OxidizedFinder::create_instance(py, Arc::new(Box::new(state)))?;
 
     // We effectively transferred ownership of resources_state just above.
     // So forget about it here.
@@ -1030,19 +1491,85 @@ fn oxidized_finder_new(
 }
 
 impl OxidizedFinder {
-    fn indexed_resources_impl(&self, py: Python) -> PyResult<PyObject> {
-        let resources_state: &PythonResourcesState<u8> = self.state(py).get_resources_state();
+    fn indexed_resources_impl(
+        &self,
+        py: Python,
+        flavor: Option<String>,
+        location: Option<String>,
+        package: Option<String>,
+        distribution: Option<String>,
+    ) -> PyResult<PyObject> {
+        if let Some(flavor) = &flavor {
+            if ![
+                "none",
+                "module",
+                "builtin",
+                "frozen",
+                "extension",
+                "shared_library",
+            ]
+            .contains(&flavor.as_str())
+            {
+                return Err(PyErr::new::<ValueError, _>(
+                    py,
+                    format!("unknown resource flavor: {}", flavor),
+                ));
+            }
+        }
+
+        if let Some(location) = &location {
+            if !["builtin", "frozen", "in-memory", "filesystem-relative"]
+                .contains(&location.as_str())
+            {
+                return Err(PyErr::new::<ValueError, _>(
+                    py,
+                    format!("unknown resource location: {}", location),
+                ));
+            }
+        }
 
-        let mut resources = resources_state
-            .resources
+        let state = self.state(py);
+        let resources_state: &PythonResourcesState<u8> = state.get_resources_state();
+        let index = state.pin_resources();
+
+        let mut resources = index
             .values()
+            .filter(|r| match &flavor {
+                Some(flavor) => resource_flavor_to_str(&r.flavor) == flavor.as_str(),
+                None => true,
+            })
+            .filter(|r| match &location {
+                Some(location) => resource_locations(r).contains(&location.as_str()),
+                None => true,
+            })
+            .filter(|r| match &package {
+                Some(package) => {
+                    r.name == package.as_str()
+                        || (r.name.starts_with(package.as_str())
+                            && r.name[package.len()..].starts_with('.'))
+                }
+                None => true,
+            })
+            .filter(|r| match &distribution {
+                Some(distribution) => match resource_provenance_distribution(r) {
+                    Some((name, _)) => name == distribution.as_str(),
+                    None => false,
+                },
+                None => true,
+            })
             .collect::<Vec<&python_packed_resources::data::Resource<u8>>>();
 
         resources.sort_by_key(|r| &r.name);
 
         let objects: Result<Vec<PyObject>, PyErr> = resources
             .iter()
-            .map(|r| resource_to_pyobject(py, r))
+            .map(|r| {
+                let resource = resources_state
+                    .decode_resource(r)
+                    .map_err(|e| PyErr::new::<ValueError, _>(py, e))?;
+
+                resource_to_pyobject(py, &resource)
+            })
             .collect();
 
         Ok(objects?.to_py_object(py).into_object())
@@ -1076,6 +1603,24 @@ impl OxidizedFinder {
         Ok(py.None())
     }
 
+    fn remove_resource_impl(&self, py: Python, name: &PyString) -> PyResult<PyObject> {
+        let resources_state: &mut PythonResourcesState<u8> =
+            self.state(py).get_resources_state_mut();
+
+        let key = name.to_string(py)?;
+
+        match resources_state.remove_resource(&key) {
+            Some(resource) => {
+                let resource = resources_state
+                    .decode_resource(&resource)
+                    .map_err(|e| PyErr::new::<ValueError, _>(py, e))?;
+
+                resource_to_pyobject(py, &resource)
+            }
+            None => Err(PyErr::new::<KeyError, _>(py, key.to_string())),
+        }
+    }
+
     fn serialize_indexed_resources_impl(
         &self,
         py: Python,
@@ -1090,6 +1635,16 @@ impl OxidizedFinder {
 
         Ok(PyBytes::new(py, &data).into_object())
     }
+
+    fn resources_memory_usage_impl(&self, py: Python) -> PyResult<PyObject> {
+        let usage = self.state(py).get_resources_state().memory_usage();
+
+        let result = PyDict::new(py);
+        result.set_item(py, "mapped_bytes", usage.mapped_bytes)?;
+        result.set_item(py, "resident_bytes", usage.resident_bytes)?;
+
+        Ok(result.into_object())
+    }
 }
 
 // Implements in-memory reading of resource data.
@@ -1114,6 +1669,11 @@ py_class!(class OxidizedResourceReader |py| {
     def contents(&self) -> PyResult<PyObject> {
         self.contents_impl(py)
     }
+
+    // importlib.resources.files() support.
+    def files(&self) -> PyResult<PyObject> {
+        self.files_impl(py)
+    }
 });
 
 impl OxidizedResourceReader {
@@ -1124,6 +1684,15 @@ impl OxidizedResourceReader {
         let state = self.state(py);
         let package = self.package(py);
 
+        state.audit(
+            py,
+            "oxidized_importer.open_resource",
+            vec![
+                package.to_py_object(py).into_object(),
+                resource.as_object().clone_ref(py),
+            ],
+        )?;
+
         if let Some(file) = state.get_resources_state().get_package_resource_file(
             py,
             &package,
@@ -1139,24 +1708,38 @@ impl OxidizedResourceReader {
     ///
     /// If the resource does not concretely exist on the file system, raise
     /// FileNotFoundError.
-    fn resource_path_impl(&self, py: Python, _resource: &PyString) -> PyResult<PyObject> {
-        Err(PyErr::new::<FileNotFoundError, _>(
-            py,
-            "in-memory resources do not have filesystem paths",
-        ))
+    fn resource_path_impl(&self, py: Python, resource: &PyString) -> PyResult<PyObject> {
+        let state = self.state(py);
+        let package = self.package(py);
+
+        if let Some(path) = state
+            .get_resources_state()
+            .package_resource_path(&package, &resource.to_string(py)?)
+        {
+            path_to_pyobject(py, &path)
+        } else {
+            Err(PyErr::new::<FileNotFoundError, _>(
+                py,
+                "in-memory resources do not have filesystem paths",
+            ))
+        }
     }
 
     /// Returns True if the named name is considered a resource. FileNotFoundError
     /// is raised if name does not exist.
+    ///
+    /// Directories of resources exist but aren't resources.
     fn is_resource_impl(&self, py: Python, name: &PyString) -> PyResult<PyObject> {
         let state = self.state(py);
         let package = self.package(py);
+        let name = name.to_string(py)?;
+        let resources_state = state.get_resources_state();
 
-        if state
-            .get_resources_state()
-            .is_package_resource(&package, &name.to_string(py)?)
+        if resources_state.is_package_resource(&package, &name) {
+            Ok(py.True().into_object())
+        } else if !name.is_empty() && resources_state.is_package_resource_directory(&package, &name)
         {
-            Ok(py.True().as_object().clone_ref(py))
+            Ok(py.False().into_object())
         } else {
             Err(PyErr::new::<FileNotFoundError, _>(py, "resource not found"))
         }
@@ -1172,21 +1755,72 @@ impl OxidizedResourceReader {
     /// For instance, returning subdirectory names is allowed so that when it is known that the
     /// package and resources are stored on the file system then those subdirectory names can be
     /// used directly.
+    ///
+    /// Like the standard library's filesystem reader, we return the names of
+    /// resources and directories of resources directly in the package.
     fn contents_impl(&self, py: Python) -> PyResult<PyObject> {
         let state = self.state(py);
         let package = self.package(py);
 
-        state
+        let names = state
             .get_resources_state()
-            .package_resource_names(py, &package)
+            .package_resource_directory_entries(&package, "")
+            .iter()
+            .map(|name| name.to_py_object(py).into_object())
+            .collect::<Vec<_>>();
+
+        Ok(PyList::new(py, &names).into_object())
+    }
+
+    /// Returns a Traversable for the package.
+    fn files_impl(&self, py: Python) -> PyResult<PyObject> {
+        let state = self.state(py);
+        let package = self.package(py);
+
+        Ok(PyOxidizerTraversable::create_instance(
+            py,
+            state.clone(),
+            package.clone(),
+            String::new(),
+        )?
+        .into_object())
     }
 }
 
+/// Resolve a path relative to a resource path.
+///
+/// Resource paths use `/` as a separator, with an empty string denoting the
+/// package itself. `.` and `..` components are resolved. `None` is returned
+/// if the path leaves the package.
+fn join_resource_path(base: &str, child: &str) -> Option<String> {
+    let mut components = base
+        .split('/')
+        .filter(|c| !c.is_empty())
+        .collect::<Vec<_>>();
+
+    for component in child.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop()?;
+            }
+            _ => components.push(component),
+        }
+    }
+
+    Some(components.join("/"))
+}
+
 // Path-like object facilitating Python resource access.
 //
 // This implements importlib.abc.Traversable.
+//
+// Instances refer to a path of resources in a package. Resources of a
+// package can be in nested directories. Directories don't cross package
+// boundaries, like with ResourceReader.
 py_class!(class PyOxidizerTraversable |py| {
     data state: Arc<Box<ImporterState>>;
+    data package: String;
     data path: String;
 
     // Yield Traversable objects in self.
@@ -1200,8 +1834,8 @@ py_class!(class PyOxidizerTraversable |py| {
     }
 
     // Read contents of self as text.
-    def read_text(&self) -> PyResult<PyObject> {
-        self.read_text_impl(py)
+    def read_text(&self, encoding: Option<PyObject> = None) -> PyResult<PyObject> {
+        self.read_text_impl(py, encoding)
     }
 
     // Return True if self is a dir.
@@ -1215,13 +1849,13 @@ py_class!(class PyOxidizerTraversable |py| {
     }
 
     // Return Traversable child in self.
-    def joinpath(&self, child: &PyObject) -> PyResult<PyObject> {
-        self.joinpath_impl(py, child)
+    def joinpath(&self, *args) -> PyResult<PyObject> {
+        self.joinpath_impl(py, args)
     }
 
     /// Return Traversable child in self.
     def __truediv__(&self, child: &PyObject) -> PyResult<PyObject> {
-        self.joinpath_impl(py, child)
+        self.joinpath_impl(py, &PyTuple::new(py, &[child.clone_ref(py)]))
     }
 
     // mode may be 'r' or 'rb' to open as text or binary. Return a handle
@@ -1232,56 +1866,201 @@ py_class!(class PyOxidizerTraversable |py| {
     def open(&self, *args, **kwargs) -> PyResult<PyObject> {
         self.open_impl(py, args, kwargs)
     }
+
+    // The base name of this object without any parent references.
+    @property def name(&self) -> PyResult<PyObject> {
+        self.name_impl(py)
+    }
+
+    def __repr__(&self) -> PyResult<String> {
+        Ok(format!(
+            "<PyOxidizerTraversable package={:?} path={:?}>",
+            self.package(py),
+            self.path(py)
+        ))
+    }
 });
 
 impl PyOxidizerTraversable {
-    fn iterdir_impl(&self, _py: Python) -> PyResult<PyObject> {
-        unimplemented!();
+    fn iterdir_impl(&self, py: Python) -> PyResult<PyObject> {
+        let state = self.state(py);
+        let package = self.package(py);
+        let path = self.path(py);
+        let resources_state = state.get_resources_state();
+
+        if !resources_state.is_package_resource_directory(package, path) {
+            return Err(PyErr::new::<NotADirectoryError, _>(
+                py,
+                format!("not a directory: {}", path),
+            ));
+        }
+
+        let children = resources_state
+            .package_resource_directory_entries(package, path)
+            .iter()
+            .map(|name| {
+                Ok(PyOxidizerTraversable::create_instance(
+                    py,
+                    state.clone(),
+                    package.clone(),
+                    join_resource_path(path, name).unwrap_or_default(),
+                )?
+                .into_object())
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+
+        PyList::new(py, &children)
+            .as_object()
+            .call_method(py, "__iter__", NoArgs, None)
+    }
+
+    /// Open the resource for binary reading.
+    fn open_binary(&self, py: Python) -> PyResult<PyObject> {
+        let state = self.state(py);
+        let package = self.package(py);
+        let path = self.path(py);
+        let resources_state = state.get_resources_state();
+
+        state.audit(
+            py,
+            "oxidized_importer.open_resource",
+            vec![
+                package.to_py_object(py).into_object(),
+                path.to_py_object(py).into_object(),
+            ],
+        )?;
+
+        if let Some(file) = resources_state.get_package_resource_file(py, package, path)? {
+            Ok(file)
+        } else if resources_state.is_package_resource_directory(package, path) {
+            Err(PyErr::new::<IsADirectoryError, _>(
+                py,
+                format!("is a directory: {}", path),
+            ))
+        } else {
+            Err(PyErr::new::<FileNotFoundError, _>(
+                py,
+                format!("resource not found: {}", path),
+            ))
+        }
     }
 
-    fn read_bytes_impl(&self, _py: Python) -> PyResult<PyObject> {
-        unimplemented!();
+    fn read_bytes_impl(&self, py: Python) -> PyResult<PyObject> {
+        let fh = self.open_binary(py)?;
+        let data = fh.call_method(py, "read", NoArgs, None)?;
+        fh.call_method(py, "close", NoArgs, None)?;
+
+        Ok(data)
     }
 
-    fn read_text_impl(&self, _py: Python) -> PyResult<PyObject> {
-        unimplemented!();
+    fn read_text_impl(&self, py: Python, encoding: Option<PyObject>) -> PyResult<PyObject> {
+        let kwargs = PyDict::new(py);
+        kwargs.set_item(py, "encoding", encoding.unwrap_or_else(|| py.None()))?;
+
+        let fh = self.open_impl(
+            py,
+            &PyTuple::new(py, &["r".to_py_object(py).into_object()]),
+            Some(&kwargs),
+        )?;
+        let data = fh.call_method(py, "read", NoArgs, None)?;
+        fh.call_method(py, "close", NoArgs, None)?;
+
+        Ok(data)
     }
 
     fn is_dir_impl(&self, py: Python) -> PyResult<PyObject> {
         let state = self.state(py);
-        let path = self.path(py);
 
-        // We are a directory if the current path is a known package.
-        // TODO We may need to expand this definition in the future to cover
-        // virtual subdirectories in addressable resources. But this will require
-        // changes to the resources data format to capture said annotations.
-        if let Some(entry) = state
+        Ok(state
             .get_resources_state()
-            .resolve_importable_module(&path, state.optimize_level)
-        {
-            if entry.is_package {
-                return Ok(py.True().into_object());
-            }
-        }
+            .is_package_resource_directory(self.package(py), self.path(py))
+            .to_py_object(py)
+            .into_object())
+    }
 
-        Ok(py.False().into_object())
+    fn is_file_impl(&self, py: Python) -> PyResult<PyObject> {
+        let state = self.state(py);
+
+        Ok(state
+            .get_resources_state()
+            .is_package_resource(self.package(py), self.path(py))
+            .to_py_object(py)
+            .into_object())
     }
 
-    fn is_file_impl(&self, _py: Python) -> PyResult<PyObject> {
-        unimplemented!();
+    fn joinpath_impl(&self, py: Python, args: &PyTuple) -> PyResult<PyObject> {
+        let mut path = self.path(py).clone();
+
+        for child in args.as_slice(py) {
+            let child = child.str(py)?.to_string(py)?.to_string();
+
+            path = join_resource_path(&path, &child).ok_or_else(|| {
+                PyErr::new::<ValueError, _>(py, format!("path outside of package: {}", child))
+            })?;
+        }
+
+        Ok(PyOxidizerTraversable::create_instance(
+            py,
+            self.state(py).clone(),
+            self.package(py).clone(),
+            path,
+        )?
+        .into_object())
     }
 
-    fn joinpath_impl(&self, _py: Python, _child: &PyObject) -> PyResult<PyObject> {
-        unimplemented!();
+    fn open_impl(&self, py: Python, args: &PyTuple, kwargs: Option<&PyDict>) -> PyResult<PyObject> {
+        let kwargs = match kwargs {
+            Some(kwargs) => kwargs.copy(py)?,
+            None => PyDict::new(py),
+        };
+
+        let mut args = args
+            .as_slice(py)
+            .iter()
+            .map(|arg| arg.clone_ref(py))
+            .collect::<Vec<_>>();
+
+        let mode = if !args.is_empty() {
+            args.remove(0)
+        } else if let Some(mode) = kwargs.get_item(py, "mode") {
+            kwargs.del_item(py, "mode")?;
+            mode
+        } else {
+            "r".to_py_object(py).into_object()
+        };
+
+        let mode = mode.extract::<String>(py)?;
+
+        if mode == "rb" {
+            self.open_binary(py)
+        } else if mode == "r" || mode == "rt" {
+            let fh = self.open_binary(py)?;
+            args.insert(0, fh);
+
+            self.state(py).io_module.call(
+                py,
+                "TextIOWrapper",
+                PyTuple::new(py, &args),
+                Some(&kwargs),
+            )
+        } else {
+            Err(PyErr::new::<ValueError, _>(
+                py,
+                format!("invalid mode: {}; only 'r' and 'rb' are supported", mode),
+            ))
+        }
     }
 
-    fn open_impl(
-        &self,
-        _py: Python,
-        _args: &PyTuple,
-        _kwargs: Option<&PyDict>,
-    ) -> PyResult<PyObject> {
-        unimplemented!();
+    fn name_impl(&self, py: Python) -> PyResult<PyObject> {
+        let path = self.path(py);
+
+        let name = if path.is_empty() {
+            self.package(py).rsplit('.').next().unwrap_or_default()
+        } else {
+            path.rsplit('/').next().unwrap_or_default()
+        };
+
+        Ok(name.to_py_object(py).into_object())
     }
 }
 
@@ -1480,12 +2259,14 @@ fn module_init(py: Python, m: &PyModule) -> PyResult<()> {
 ///
 /// This is called after PyInit_* to finish the initialization of the
 /// module. Its state struct is updated. A new instance of the meta path
-/// importer is constructed and registered on sys.meta_path.
+/// importer is constructed and registered on sys.meta_path. If given,
+/// `watched_resources` are reloaded by the importer when they change.
 #[cfg(not(library_mode = "extension"))]
 pub(crate) fn initialize_importer<'a>(
     py: Python,
     m: &PyModule,
     resources_state: &PythonResourcesState<'a, u8>,
+    watched_resources: Option<WatchedResources>,
 ) -> PyResult<()> {
     let mut state = get_module_state(py, m)?;
 
@@ -1495,7 +2276,8 @@ pub(crate) fn initialize_importer<'a>(
     // importer is able to handle builtin and frozen modules, the existing meta path
     // importers are removed. The assumption here is that we're called very early
     // during startup and the 2 default meta path importers are installed.
-    let unified_importer = OxidizedFinder::new_from_module_and_resources(py, m, resources_state)?;
+    let unified_importer =
+        OxidizedFinder::new_from_module_and_resources(py, m, resources_state, watched_resources)?;
 
     let meta_path_object = sys_module.get(py, "meta_path")?;
 
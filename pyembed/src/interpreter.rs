@@ -5,21 +5,34 @@
 //! Manage an embedded Python interpreter.
 
 use {
-    super::config::{MemoryAllocatorBackend, OxidizedPythonInterpreterConfig, TerminfoResolution},
+    super::config::{
+        CertificateResolution, MemoryAllocatorBackend, OxidizedPythonInterpreterConfig,
+        PythonRunMode, StdioRedirect, TerminfoResolution,
+    },
+    super::console::{attach_console, DebuggerWriter},
     super::conversion::{osstr_to_pyobject, osstring_to_bytes},
     super::importer::{
-        initialize_importer, PyInit_oxidized_importer, OXIDIZED_IMPORTER_NAME,
+        initialize_importer, PyInit_oxidized_importer, WatchedResources, OXIDIZED_IMPORTER_NAME,
         OXIDIZED_IMPORTER_NAME_STR,
     },
-    super::osutils::resolve_terminfo_dirs,
+    super::osutils::{
+        expand_origin, resolve_ssl_cert_paths, resolve_tcl_tk_libraries, resolve_terminfo_dirs,
+    },
+    super::profiling,
     super::pyalloc::{make_raw_rust_memory_allocator, RawAllocator},
-    super::python_resources::PythonResourcesState,
+    super::python_eval::{run_and_handle_error, PythonRunResult},
+    super::python_resources::{PythonResourcesState, ResourcesMemoryUsage},
+    super::signals::{interpreter_started, interpreter_stopping, InterruptHandle},
+    super::site_packages::install_site_packages_overlay,
+    super::stdio::{OutputCapture, StandardStream},
+    super::zip_archives::install_zip_archives,
     cpython::{
-        GILGuard, NoArgs, ObjectProtocol, PyDict, PyErr, PyList, PyObject, PyString, Python,
-        ToPyObject,
+        py_fn, GILGuard, NoArgs, ObjectProtocol, PyClone, PyDict, PyErr, PyList, PyObject,
+        PyResult, PyString, Python, ToPyObject,
     },
     lazy_static::lazy_static,
     python3_sys as pyffi,
+    python_packed_resources::parser::verify_packed_resources,
     std::collections::BTreeSet,
     std::convert::TryInto,
     std::env,
@@ -28,10 +41,15 @@ use {
     std::fs,
     std::io::Write,
     std::path::PathBuf,
+    std::time::Instant,
 };
 
 #[cfg(feature = "jemalloc-sys")]
 use super::pyalloc::make_raw_jemalloc_allocator;
+#[cfg(feature = "libmimalloc-sys")]
+use super::pyalloc::make_raw_mimalloc_allocator;
+#[cfg(feature = "snmalloc-sys")]
+use super::pyalloc::make_raw_snmalloc_allocator;
 use python3_sys::PyMemAllocatorEx;
 
 lazy_static! {
@@ -48,6 +66,26 @@ fn raw_jemallocator() -> pyffi::PyMemAllocatorEx {
     panic!("jemalloc is not available in this build configuration");
 }
 
+#[cfg(feature = "libmimalloc-sys")]
+fn raw_mimallocator() -> pyffi::PyMemAllocatorEx {
+    make_raw_mimalloc_allocator()
+}
+
+#[cfg(not(feature = "libmimalloc-sys"))]
+fn raw_mimallocator() -> pyffi::PyMemAllocatorEx {
+    panic!("mimalloc is not available in this build configuration");
+}
+
+#[cfg(feature = "snmalloc-sys")]
+fn raw_snmallocator() -> pyffi::PyMemAllocatorEx {
+    make_raw_snmalloc_allocator()
+}
+
+#[cfg(not(feature = "snmalloc-sys"))]
+fn raw_snmallocator() -> pyffi::PyMemAllocatorEx {
+    panic!("snmalloc is not available in this build configuration");
+}
+
 /// Format a PyErr in a crude manner.
 ///
 /// This is meant to be called during interpreter initialization. We can't
@@ -154,6 +192,21 @@ impl From<RawAllocator> for InterpreterRawAllocator {
     }
 }
 
+/// The raw allocator backend installed by an interpreter in this process.
+///
+/// Python can still reference memory obtained from the raw allocator after
+/// the interpreter is finalized. So once an interpreter installs a raw
+/// allocator, it is kept alive and installed for the remainder of the process
+/// and later interpreters must use the same backend. The same applies to
+/// whether the backend is also installed for the `PYMEM_DOMAIN_MEM` domain.
+///
+/// Access is serialized by `GLOBAL_INTERPRETER_GUARD`.
+static mut INSTALLED_RAW_ALLOCATOR: Option<(
+    MemoryAllocatorBackend,
+    bool,
+    Option<InterpreterRawAllocator>,
+)> = None;
+
 #[derive(Debug, PartialEq)]
 enum InterpreterState {
     NotStarted,
@@ -169,6 +222,14 @@ enum InterpreterState {
 /// this. Calling `new()` will block waiting for this lock. The lock is
 /// released when the instance is dropped.
 ///
+/// Dropping an instance finalizes the interpreter and tears down the
+/// importer state referencing packed resources data. A new instance can then
+/// be constructed to start a fresh interpreter in the same process, e.g. to
+/// reload Python code. As with any use of `Py_FinalizeEx()`, extension
+/// modules that don't support reinitialization may misbehave in the new
+/// interpreter. The raw allocator backend can't change between interpreters
+/// in a process.
+///
 /// Instances must only be constructed through [`MainPythonInterpreter::new()`](#method.new).
 ///
 /// This type and its various functionality is a glorified wrapper around the
@@ -180,7 +241,6 @@ pub struct MainPythonInterpreter<'python, 'interpreter: 'python, 'resources: 'in
     config: OxidizedPythonInterpreterConfig<'resources>,
     interpreter_state: InterpreterState,
     interpreter_guard: Option<std::sync::MutexGuard<'interpreter, ()>>,
-    raw_allocator: Option<InterpreterRawAllocator>,
     gil: Option<GILGuard>,
     py: Option<Python<'python>>,
     /// Holds parsed resources state.
@@ -202,6 +262,17 @@ pub struct MainPythonInterpreter<'python, 'interpreter: 'python, 'resources: 'in
     /// in this field. We also store the object in a box so it is on the
     /// heap and not dynamic.
     resources_state: Option<Box<PythonResourcesState<'resources, u8>>>,
+    /// Memory maps of packed resources files backing `resources_state`.
+    ///
+    /// Declared after `resources_state` so they are unmapped after it is
+    /// dropped.
+    packed_resources_mmaps: Vec<memmap::Mmap>,
+    /// Private copies of signed packed resources files backing `resources_state`.
+    packed_resources_copies: Vec<Vec<u8>>,
+    /// Standard streams whose output is being captured.
+    output_captures: Vec<OutputCapture>,
+    /// Handle to interrupt this interpreter while it is running.
+    interrupt_handle: Option<InterruptHandle>,
 }
 
 impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpreter, 'resources> {
@@ -218,26 +289,102 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
                 }
             }
             TerminfoResolution::Static(ref v) => {
-                env::set_var("TERMINFO_DIRS", v);
+                env::set_var("TERMINFO_DIRS", expand_origin(v));
             }
             TerminfoResolution::None => {}
         }
 
+        match config.ssl_certificate_resolution {
+            CertificateResolution::Dynamic => {
+                let (cert_file, cert_dir) = resolve_ssl_cert_paths();
+
+                if let Some(v) = cert_file {
+                    env::set_var("SSL_CERT_FILE", &v);
+                }
+                if let Some(v) = cert_dir {
+                    env::set_var("SSL_CERT_DIR", &v);
+                }
+            }
+            CertificateResolution::Static(ref v) => {
+                if env::var_os("SSL_CERT_FILE").is_none() {
+                    env::set_var("SSL_CERT_FILE", expand_origin(v));
+                }
+            }
+            CertificateResolution::None => {}
+        }
+
+        if let Some(ref tcl_library) = config.tcl_library {
+            let (tcl_library, tk_library) = resolve_tcl_tk_libraries(tcl_library);
+
+            if let Some(v) = tcl_library {
+                env::set_var("TCL_LIBRARY", &v);
+            }
+            if let Some(v) = tk_library {
+                env::set_var("TK_LIBRARY", &v);
+            }
+        }
+
         let mut res = MainPythonInterpreter {
             config,
             interpreter_guard: None,
             interpreter_state: InterpreterState::NotStarted,
-            raw_allocator: None,
             gil: None,
             py: None,
             resources_state: None,
+            packed_resources_mmaps: Vec::new(),
+            packed_resources_copies: Vec::new(),
+            output_captures: Vec::new(),
+            interrupt_handle: None,
         };
 
         res.init()?;
+        res.configure_stdio()
+            .map_err(NewInterpreterError::Dynamic)?;
 
         Ok(res)
     }
 
+    /// Configure the standard streams of the process after initialization.
+    ///
+    /// A console is allocated if the process received an argument asking
+    /// for one. Otherwise output is redirected as configured.
+    fn configure_stdio(&mut self) -> Result<(), String> {
+        let allocate_console = env::args_os().skip(1).any(|arg| {
+            self.config
+                .allocate_console_args
+                .iter()
+                .any(|a| arg.to_str() == Some(a.as_str()))
+        });
+
+        if allocate_console {
+            return self.allocate_console();
+        }
+
+        match self.config.stdio_redirect.clone() {
+            StdioRedirect::None => {}
+            StdioRedirect::File(path) => {
+                let path = expand_origin(&path);
+                let file = fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .map_err(|e| format!("unable to open {}: {}", path, e))?;
+                let stderr_file = file
+                    .try_clone()
+                    .map_err(|e| format!("unable to open {}: {}", path, e))?;
+
+                self.capture_output(StandardStream::Stdout, Box::new(file))?;
+                self.capture_output(StandardStream::Stderr, Box::new(stderr_file))?;
+            }
+            StdioRedirect::Debugger => {
+                self.capture_output(StandardStream::Stdout, Box::new(DebuggerWriter::default()))?;
+                self.capture_output(StandardStream::Stderr, Box::new(DebuggerWriter::default()))?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Initialize the interpreter.
     ///
     /// This mutates global state in the Python interpreter according to the
@@ -271,6 +418,29 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
 
         self.interpreter_state = InterpreterState::Initializing;
 
+        let startup_profile_path = match &self.config.startup_profile_path {
+            Some(path) => Some(path.clone()),
+            None => self
+                .config
+                .startup_profile_env
+                .as_ref()
+                .and_then(env::var_os)
+                .map(PathBuf::from),
+        };
+        if let Some(path) = startup_profile_path {
+            profiling::start(path);
+        }
+        let phase_start = Instant::now();
+
+        // Modules in zip archives are read from the archives whenever they
+        // are imported. So they can't be verified up front.
+        if self.config.packed_resources_public_key.is_some() && !self.config.zip_archives.is_empty()
+        {
+            return Err(NewInterpreterError::Simple(
+                "zip_archives can't be used with packed_resources_public_key",
+            ));
+        }
+
         let exe = env::current_exe()
             .map_err(|_| NewInterpreterError::Simple("could not obtain current exe"))?;
         let origin = exe
@@ -297,29 +467,84 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
             }
         };
 
-        // Override the raw allocator if one is configured.
-        if let Some(raw_allocator) = &self.config.raw_allocator {
-            match raw_allocator.backend {
-                MemoryAllocatorBackend::System => {}
-                MemoryAllocatorBackend::Jemalloc => {
-                    self.raw_allocator = Some(InterpreterRawAllocator::from(raw_jemallocator()));
-                }
-                MemoryAllocatorBackend::Rust => {
-                    self.raw_allocator = Some(InterpreterRawAllocator::from(
+        // Override the raw allocator if one is configured. Python's default
+        // raw allocator is the system allocator.
+        let (backend, mem_domain) = match &self.config.raw_allocator {
+            Some(raw_allocator) => (raw_allocator.backend, raw_allocator.mem_domain),
+            None => (MemoryAllocatorBackend::System, false),
+        };
+
+        match unsafe { &INSTALLED_RAW_ALLOCATOR } {
+            Some((installed, installed_mem_domain, _))
+                if *installed == backend && *installed_mem_domain == mem_domain => {}
+            Some(_) => {
+                return Err(NewInterpreterError::Simple(
+                    "raw allocator configuration cannot change after an interpreter was started",
+                ));
+            }
+            None => {
+                let allocator = match backend {
+                    MemoryAllocatorBackend::System => None,
+                    MemoryAllocatorBackend::Jemalloc => {
+                        Some(InterpreterRawAllocator::from(raw_jemallocator()))
+                    }
+                    MemoryAllocatorBackend::Mimalloc => {
+                        Some(InterpreterRawAllocator::from(raw_mimallocator()))
+                    }
+                    MemoryAllocatorBackend::Snmalloc => {
+                        Some(InterpreterRawAllocator::from(raw_snmallocator()))
+                    }
+                    MemoryAllocatorBackend::Rust => Some(InterpreterRawAllocator::from(
                         make_raw_rust_memory_allocator(),
-                    ));
+                    )),
+                };
+
+                if let Some(allocator) = &allocator {
+                    unsafe {
+                        pyffi::PyMem_SetAllocator(
+                            pyffi::PyMemAllocatorDomain::PYMEM_DOMAIN_RAW,
+                            allocator.as_ptr() as *mut _,
+                        );
+                    }
+                }
+
+                // The system backend has no allocator to install. For it, the
+                // `PYMEM_DOMAIN_MEM` domain uses the (system) raw allocator.
+                if mem_domain {
+                    let mut system_raw = pyffi::PyMemAllocatorEx {
+                        ctx: std::ptr::null_mut(),
+                        malloc: None,
+                        calloc: None,
+                        realloc: None,
+                        free: None,
+                    };
+
+                    let mem_allocator = match &allocator {
+                        Some(allocator) => allocator.as_ptr() as *mut _,
+                        None => unsafe {
+                            pyffi::PyMem_GetAllocator(
+                                pyffi::PyMemAllocatorDomain::PYMEM_DOMAIN_RAW,
+                                &mut system_raw,
+                            );
+                            &mut system_raw as *mut _
+                        },
+                    };
+
+                    unsafe {
+                        pyffi::PyMem_SetAllocator(
+                            pyffi::PyMemAllocatorDomain::PYMEM_DOMAIN_MEM,
+                            mem_allocator,
+                        );
+                    }
                 }
-            }
 
-            if let Some(allocator) = &self.raw_allocator {
                 unsafe {
-                    pyffi::PyMem_SetAllocator(
-                        pyffi::PyMemAllocatorDomain::PYMEM_DOMAIN_RAW,
-                        allocator.as_ptr() as *mut _,
-                    );
+                    INSTALLED_RAW_ALLOCATOR = Some((backend, mem_domain, allocator));
                 }
             }
+        }
 
+        if let Some(raw_allocator) = &self.config.raw_allocator {
             if raw_allocator.debug {
                 unsafe {
                     pyffi::PyMem_SetupDebugHooks();
@@ -327,6 +552,9 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
             }
         }
 
+        profiling::phase("pre_initialize", phase_start);
+        let phase_start = Instant::now();
+
         let mut py_config: pyffi::PyConfig = (&self.config)
             .try_into()
             .map_err(NewInterpreterError::Dynamic)?;
@@ -349,17 +577,123 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
         // inject our custom importer.
 
         let py = unsafe { Python::assume_gil_acquired() };
+        profiling::phase("initialize_core", phase_start);
 
         if self.config.oxidized_importer {
+            let phase_start = Instant::now();
+
             self.resources_state = Some(Box::new(
                 PythonResourcesState::new_from_env()
                     .map_err(|err| NewInterpreterError::Simple(err))?,
             ));
 
             if let Some(ref mut resources_state) = self.resources_state {
+                resources_state.in_memory_module_file = self.config.in_memory_module_file;
+                resources_state.in_memory_package_path = self.config.in_memory_package_path;
+                resources_state.lazy_modules = self.config.lazy_modules.clone();
+
+                let packed_resources_key = resolve_packed_resources_key(&self.config)?;
+                let mut segments = Vec::new();
+
+                let paths = self
+                    .config
+                    .packed_resources_files
+                    .iter()
+                    .map(|path| expand_origin(path))
+                    .collect::<Vec<_>>();
+
+                // Created before the files are read so changes made meanwhile
+                // are picked up. The embedded data is borrowed for as long as
+                // the resources state borrows it.
+                let watched_resources =
+                    if self.config.watch_packed_resources_files && !paths.is_empty() {
+                        let base = match self.config.packed_resources {
+                            Some(data) => vec![unsafe {
+                                std::slice::from_raw_parts::<'static, u8>(data.as_ptr(), data.len())
+                            }],
+                            None => vec![],
+                        };
+
+                        Some(WatchedResources::new(
+                            paths.iter().map(PathBuf::from).collect(),
+                            base,
+                            self.config
+                                .packed_resources_public_key
+                                .map(|key| key.to_vec()),
+                        ))
+                    } else {
+                        None
+                    };
+
+                for path in paths {
+                    // A memory mapped file changes along with the file. So
+                    // signed files are copied into memory and the copy is
+                    // verified and used.
+                    if let Some(public_key) = self.config.packed_resources_public_key {
+                        let data = fs::read(&path).map_err(|e| {
+                            NewInterpreterError::Dynamic(format!(
+                                "unable to read packed resources file {}: {}",
+                                path, e
+                            ))
+                        })?;
+
+                        let signature_path = format!("{}.sig", path);
+                        let signature = fs::read(&signature_path).map_err(|e| {
+                            NewInterpreterError::Dynamic(format!(
+                                "unable to read signature of packed resources file {}: {}",
+                                path, e
+                            ))
+                        })?;
+
+                        verify_packed_resources(&data, &signature, public_key).map_err(|e| {
+                            NewInterpreterError::Dynamic(format!(
+                                "unable to verify packed resources file {}: {}",
+                                path, e
+                            ))
+                        })?;
+
+                        // The heap buffer doesn't move along with the Vec.
+                        segments.push(unsafe {
+                            std::slice::from_raw_parts::<'resources, u8>(data.as_ptr(), data.len())
+                        });
+                        self.packed_resources_copies.push(data);
+
+                        continue;
+                    }
+
+                    let f = fs::File::open(&path).map_err(|e| {
+                        NewInterpreterError::Dynamic(format!(
+                            "unable to open packed resources file {}: {}",
+                            path, e
+                        ))
+                    })?;
+
+                    let mapped = unsafe { memmap::Mmap::map(&f) }.map_err(|e| {
+                        NewInterpreterError::Dynamic(format!(
+                            "unable to memory map packed resources file {}: {}",
+                            path, e
+                        ))
+                    })?;
+
+                    prefetch_packed_resources(&mapped, packed_resources_key.as_deref());
+
+                    // The mapped memory doesn't move along with the Mmap. It
+                    // is retained for as long as the resources state lives.
+                    segments.push(unsafe {
+                        std::slice::from_raw_parts::<'resources, u8>(mapped.as_ptr(), mapped.len())
+                    });
+                    self.packed_resources_mmaps.push(mapped);
+                }
+
+                if let Some(data) = self.config.packed_resources {
+                    segments.push(data);
+                }
+
                 resources_state
-                    .load(self.config.packed_resources)
+                    .load(&segments, packed_resources_key.as_deref())
                     .map_err(|err| NewInterpreterError::Simple(err))?;
+                profiling::phase("load_resources", phase_start);
+                let phase_start = Instant::now();
 
                 let oxidized_importer = py.import(OXIDIZED_IMPORTER_NAME_STR).map_err(|err| {
                     NewInterpreterError::new_from_pyerr(
@@ -369,19 +703,22 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
                     )
                 })?;
 
-                initialize_importer(py, &oxidized_importer, resources_state).map_err(|err| {
-                    NewInterpreterError::new_from_pyerr(
-                        py,
-                        err,
-                        "initialization of oxidized importer",
-                    )
-                })?;
+                initialize_importer(py, &oxidized_importer, resources_state, watched_resources)
+                    .map_err(|err| {
+                        NewInterpreterError::new_from_pyerr(
+                            py,
+                            err,
+                            "initialization of oxidized importer",
+                        )
+                    })?;
+                profiling::phase("initialize_importer", phase_start);
             }
         }
 
         // Now proceed with the Python main initialization. This will initialize
         // importlib. And if the custom importlib bytecode was registered above,
         // our extension module will get imported and initialized.
+        let phase_start = Instant::now();
         let status = unsafe { pyffi::_Py_InitializeMain() };
 
         if unsafe { pyffi::PyStatus_Exception(status) } != 0 {
@@ -390,6 +727,8 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
                 "initializing Python main",
             ));
         }
+        profiling::phase("initialize_main", phase_start);
+        let phase_start = Instant::now();
 
         // When the main initialization ran, it initialized the "external"
         // importer (importlib._bootstrap_external). Our meta path importer
@@ -431,8 +770,11 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
         // will be derived from wchar_t on Windows and char* on POSIX. We can
         // convert these to Python str instances using a platform-specific
         // mechanism.
-        let args_objs = env::args_os()
-            .map(|os_arg| osstr_to_pyobject(py, &os_arg, None))
+        let sys_argv = self.config.resolve_sys_argv(env::args_os().collect());
+
+        let args_objs = sys_argv
+            .iter()
+            .map(|os_arg| osstr_to_pyobject(py, os_arg, None))
             .collect::<Result<Vec<PyObject>, &'static str>>()?;
 
         // This will steal the pointer to the elements and mem::forget them.
@@ -449,7 +791,8 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
         }
 
         if self.config.argvb {
-            let args_objs: Vec<PyObject> = env::args_os()
+            let args_objs: Vec<PyObject> = sys_argv
+                .into_iter()
                 .map(|os_arg| osstring_to_bytes(py, os_arg))
                 .collect();
 
@@ -502,6 +845,82 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
             }
         }
 
+        if let Some(allowlist) = &self.config.environment_allowlist {
+            restrict_environment(py, allowlist).map_err(|err| {
+                NewInterpreterError::new_from_pyerr(py, err, "restricting os.environ")
+            })?;
+        }
+
+        if let Some(overlay) = &self.config.site_packages_overlay {
+            install_site_packages_overlay(py, &expand_origin(&overlay.path), overlay.priority)
+                .map_err(|err| {
+                    NewInterpreterError::new_from_pyerr(py, err, "installing site-packages overlay")
+                })?;
+        }
+
+        if !self.config.zip_archives.is_empty() {
+            let exe = env::current_exe()
+                .map(|exe| exe.display().to_string())
+                .unwrap_or_default();
+
+            let paths = self
+                .config
+                .zip_archives
+                .iter()
+                .map(|path| expand_origin(&path.replace("$EXECUTABLE", &exe)))
+                .collect::<Vec<_>>();
+
+            install_zip_archives(py, &paths).map_err(|err| {
+                NewInterpreterError::new_from_pyerr(py, err, "installing zip archives")
+            })?;
+        }
+
+        if let Some(method) = self.config.multiprocessing_start_method {
+            let multiprocessing = py.import("multiprocessing").map_err(|err| {
+                NewInterpreterError::new_from_pyerr(py, err, "importing multiprocessing")
+            })?;
+
+            let kwargs = PyDict::new(py);
+            kwargs.set_item(py, "force", true).map_err(|err| {
+                NewInterpreterError::new_from_pyerr(py, err, "constructing kwargs")
+            })?;
+
+            multiprocessing
+                .call(py, "set_start_method", (method.as_str(),), Some(&kwargs))
+                .map_err(|err| {
+                    NewInterpreterError::new_from_pyerr(
+                        py,
+                        err,
+                        "multiprocessing.set_start_method()",
+                    )
+                })?;
+        }
+
+        if self.config.repl_line_editing {
+            let history_file = self
+                .config
+                .repl_history_file
+                .as_ref()
+                .map(|path| expand_origin(path));
+
+            install_interactive_hook(py, history_file).map_err(|err| {
+                NewInterpreterError::new_from_pyerr(py, err, "installing sys.__interactivehook__")
+            })?;
+        }
+
+        // Interrupts can't be scheduled once finalization starts. atexit
+        // callbacks run at the start of finalization, including when
+        // Py_RunMain() finalizes the interpreter.
+        let atexit = py
+            .import("atexit")
+            .map_err(|err| NewInterpreterError::new_from_pyerr(py, err, "importing atexit"))?;
+        atexit
+            .call(py, "register", (py_fn!(py, stop_interrupts()),), None)
+            .map_err(|err| NewInterpreterError::new_from_pyerr(py, err, "atexit.register()"))?;
+
+        self.interrupt_handle = Some(InterruptHandle::new(interpreter_started()));
+        profiling::phase("configure", phase_start);
+
         Ok(())
     }
 
@@ -542,15 +961,49 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
         })
     }
 
+    /// Obtain a handle to raise `KeyboardInterrupt` in this interpreter.
+    ///
+    /// The handle can be sent to other threads. This allows the host
+    /// application to forward signals like `SIGINT` and `SIGTERM`, e.g. while
+    /// `run_as_main()` executes. Combine with
+    /// `PythonInterpreterConfig.install_signal_handlers = Some(false)` to
+    /// prevent Python from installing its own signal handlers so the host
+    /// application solely owns signal handling.
+    ///
+    /// The handle stops working once this interpreter is finalized.
+    pub fn interrupt_handle(&self) -> Result<InterruptHandle, &'static str> {
+        match self.interpreter_state {
+            InterpreterState::Initialized => self
+                .interrupt_handle
+                .ok_or("interpreter not fully initialized"),
+            InterpreterState::Finalized => Err("interpreter is finalized"),
+            _ => Err("interpreter not initialized"),
+        }
+    }
+
+    /// Obtain memory used by packed resources data.
+    ///
+    /// Packed resources data embedded in the executable or loaded from
+    /// `packed_resources_files` is only paged in as resources are used. The
+    /// returned value reports how much of it is resident in memory, allowing
+    /// applications to measure the memory cost of their resources.
+    pub fn resources_memory_usage(&self) -> Result<ResourcesMemoryUsage, &'static str> {
+        match &self.resources_state {
+            Some(state) => Ok(state.memory_usage()),
+            None => Err("interpreter not initialized"),
+        }
+    }
+
     /// Runs the Python interpreter in the context of a main() function.
     ///
     /// This will execute whatever is configured by
     /// `OxidizedPythonInterpreterConfig.run` and return an integer suitable
     /// for use as a process exit code.
     ///
-    /// The `PythonRunMode::Eval`, `PythonRunMode::File`, and
-    /// `PythonRunMode::Module`, and `PythonRunMode::Repl` run modes are
-    /// evaluated via `Py_RunMain()`. `PythonRunMode::None` simply returns 0.
+    /// All run modes except `PythonRunMode::None` and `PythonRunMode::Server`
+    /// are evaluated via `Py_RunMain()`. `PythonRunMode::None` simply returns
+    /// 0. `PythonRunMode::Server` serves the application until the process
+    /// is interrupted and leaves the interpreter running.
     ///
     /// `Py_RunMain` is the most robust mechanism to run code, files, or
     /// modules, as `Py_RunMain()` invokes the same APIs that `python` would.
@@ -570,17 +1023,387 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
             let res = unsafe { pyffi::Py_RunMain() };
 
             // Py_RunMain() finalizes the interpreter. So drop our refs and state.
-            self.interpreter_guard = None;
-            self.interpreter_state = InterpreterState::Finalized;
-            self.resources_state = None;
-            self.py = None;
-            self.gil = None;
+            self.release_finalized_state();
 
             res
+        } else if let PythonRunMode::Server { .. } = &self.config.run {
+            let run = self.config.run.clone();
+
+            let py = match self.acquire_gil() {
+                Ok(py) => py,
+                Err(msg) => {
+                    eprintln!("{}", msg);
+                    return 1;
+                }
+            };
+
+            match run_and_handle_error(py, &run) {
+                PythonRunResult::Ok {} => 0,
+                PythonRunResult::Err {} => 1,
+                PythonRunResult::Exit { code } => code,
+            }
         } else {
             0
         }
     }
+
+    /// Send everything written to a standard stream to a `Write` implementation.
+    ///
+    /// The stream's file descriptor is redirected, so output written by C
+    /// extensions and other native code is captured along with output from
+    /// Python. `sys.stdout` or `sys.stderr` is replaced by a line buffered
+    /// stream writing to the redirected file descriptor so Python output is
+    /// delivered promptly.
+    ///
+    /// The writer is called from a background thread. It must not call into
+    /// Python, as capturing ends by waiting on that thread while the GIL may
+    /// be held.
+    ///
+    /// Capturing ends when `stop_capturing_output()` is called or after the
+    /// interpreter is finalized when dropping it. Capturing a stream again
+    /// replaces the previous writer.
+    pub fn capture_output(
+        &mut self,
+        stream: StandardStream,
+        writer: Box<dyn Write + Send>,
+    ) -> Result<(), String> {
+        self.stop_capturing_output(stream)?;
+
+        let py = self.acquire_gil()?;
+        flush_python_stream(py, stream)?;
+
+        let capture = OutputCapture::new(stream, writer)?;
+
+        let sys = py
+            .import("sys")
+            .map_err(|_| "unable to import sys".to_string())?;
+        let io = py
+            .import("io")
+            .map_err(|_| "unable to import io".to_string())?;
+        let current = sys
+            .get(py, stream.sys_attr())
+            .map_err(|_| format!("unable to obtain sys.{}", stream.sys_attr()))?;
+
+        let kwargs = PyDict::new(py);
+        let res: Result<(), PyErr> = (|| {
+            if current == py.None() {
+                kwargs.set_item(py, "encoding", "utf-8")?;
+                kwargs.set_item(py, "errors", "backslashreplace")?;
+            } else {
+                kwargs.set_item(py, "encoding", current.getattr(py, "encoding")?)?;
+                kwargs.set_item(py, "errors", current.getattr(py, "errors")?)?;
+            }
+            kwargs.set_item(py, "buffering", 1)?;
+            kwargs.set_item(py, "closefd", false)?;
+
+            let replacement = io.call(py, "open", (stream.fd(), "w"), Some(&kwargs))?;
+            sys.add(py, stream.sys_attr(), replacement)
+        })();
+        res.map_err(|_| format!("unable to replace sys.{}", stream.sys_attr()))?;
+
+        self.output_captures.push(capture);
+
+        Ok(())
+    }
+
+    /// Attach the process to a console, allocating one if needed.
+    ///
+    /// This is meant for executables using the Windows GUI subsystem, which
+    /// start without a console. The console of the parent process is used if
+    /// there is one. Standard streams lacking a file descriptor are bound to
+    /// the console and `sys.stdin`, `sys.stdout` and `sys.stderr` are
+    /// replaced if they are `None`.
+    ///
+    /// Does nothing if the process already has a console or on platforms
+    /// other than Windows.
+    pub fn allocate_console(&mut self) -> Result<(), String> {
+        if !attach_console()? {
+            return Ok(());
+        }
+
+        let py = self.acquire_gil()?;
+
+        let sys = py
+            .import("sys")
+            .map_err(|_| "unable to import sys".to_string())?;
+        let io = py
+            .import("io")
+            .map_err(|_| "unable to import io".to_string())?;
+
+        for (fd, name, mode) in &[(0, "stdin", "r"), (1, "stdout", "w"), (2, "stderr", "w")] {
+            let current = sys
+                .get(py, name)
+                .map_err(|_| format!("unable to obtain sys.{}", name))?;
+            if current != py.None() {
+                continue;
+            }
+
+            let kwargs = PyDict::new(py);
+            let res: Result<(), PyErr> = (|| {
+                kwargs.set_item(py, "encoding", "utf-8")?;
+                if *mode == "w" {
+                    kwargs.set_item(py, "errors", "backslashreplace")?;
+                    kwargs.set_item(py, "buffering", 1)?;
+                }
+                kwargs.set_item(py, "closefd", false)?;
+
+                let stream = io.call(py, "open", (*fd, *mode), Some(&kwargs))?;
+                sys.add(py, name, stream.clone_ref(py))?;
+                sys.add(py, &format!("__{}__", name), stream)
+            })();
+            res.map_err(|_| format!("unable to replace sys.{}", name))?;
+        }
+
+        Ok(())
+    }
+
+    /// Stop capturing output of a standard stream.
+    ///
+    /// The stream's original file descriptor and Python stream are restored
+    /// after all captured output has been delivered to the writer. Does
+    /// nothing if the stream isn't captured.
+    pub fn stop_capturing_output(&mut self, stream: StandardStream) -> Result<(), String> {
+        let index = match self
+            .output_captures
+            .iter()
+            .position(|capture| capture.stream() == stream)
+        {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+
+        if self.interpreter_state == InterpreterState::Initialized {
+            let py = self.acquire_gil()?;
+            flush_python_stream(py, stream)?;
+
+            let sys = py
+                .import("sys")
+                .map_err(|_| "unable to import sys".to_string())?;
+            let original = sys
+                .get(py, stream.sys_original_attr())
+                .map_err(|_| format!("unable to obtain sys.{}", stream.sys_original_attr()))?;
+            sys.add(py, stream.sys_attr(), original)
+                .map_err(|_| format!("unable to restore sys.{}", stream.sys_attr()))?;
+        }
+
+        // Dropping restores the file descriptor and waits for the remaining output.
+        self.output_captures.remove(index);
+
+        Ok(())
+    }
+
+    /// Release state belonging to a finalized interpreter.
+    ///
+    /// This must be called after the interpreter is finalized. Afterwards, a
+    /// new interpreter can be started in this process.
+    fn release_finalized_state(&mut self) {
+        self.interrupt_handle = None;
+
+        // Finalization destroyed the thread state the GIL guard refers to.
+        // Releasing the guard would access freed memory.
+        if let Some(gil) = self.gil.take() {
+            std::mem::forget(gil);
+        }
+        self.py = None;
+        self.interpreter_state = InterpreterState::Finalized;
+
+        // The importer pointing at the resources state was destroyed along
+        // with the interpreter.
+        self.resources_state = None;
+        self.packed_resources_mmaps.clear();
+        self.packed_resources_copies.clear();
+
+        // Finalization flushed Python's streams. So all output has been
+        // written and capturing can end.
+        self.output_captures.clear();
+
+        if let Err(msg) = profiling::finish() {
+            eprintln!("error writing startup profile: {}", msg);
+        }
+
+        // Release the global lock last so a new interpreter can't start
+        // before teardown is complete.
+        self.interpreter_guard = None;
+    }
+}
+
+/// Python source defining a `sys.__interactivehook__` enabling line editing.
+///
+/// This mirrors `site.enablerlcompleter()`, which isn't called when `site`
+/// isn't imported or the interpreter is isolated.
+const INTERACTIVE_HOOK_SOURCE: &str = r#"
+import sys
+
+def __interactivehook__():
+    try:
+        import readline
+        import rlcompleter
+    except ImportError:
+        return
+
+    import atexit
+    import os
+
+    # libedit and GNU readline have different syntax for key bindings.
+    readline_doc = getattr(readline, '__doc__', '') or ''
+    if 'libedit' in readline_doc:
+        readline.parse_and_bind('bind ^I rl_complete')
+    else:
+        readline.parse_and_bind('tab: complete')
+
+    try:
+        readline.read_init_file()
+    except OSError:
+        pass
+
+    if readline.get_current_history_length() == 0:
+        path = os.path.expanduser(history_file or '~/.python_history')
+        try:
+            readline.read_history_file(path)
+        except OSError:
+            pass
+
+        def write_history():
+            try:
+                readline.write_history_file(path)
+            except OSError:
+                pass
+
+        atexit.register(write_history)
+
+if not hasattr(sys, '__interactivehook__'):
+    sys.__interactivehook__ = __interactivehook__
+"#;
+
+/// Resolve the key to decrypt packed resources data with.
+///
+/// An explicit `packed_resources_key` wins. Otherwise the key is read from
+/// the file named by the environment variable `packed_resources_key_env`
+/// refers to, if it is set.
+fn resolve_packed_resources_key(
+    config: &OxidizedPythonInterpreterConfig,
+) -> Result<Option<Vec<u8>>, NewInterpreterError> {
+    if let Some(key) = config.packed_resources_key {
+        return Ok(Some(key.to_vec()));
+    }
+
+    let path = match config
+        .packed_resources_key_env
+        .as_ref()
+        .and_then(|name| env::var_os(name))
+    {
+        Some(path) => PathBuf::from(path),
+        None => return Ok(None),
+    };
+
+    let key = fs::read(&path).map_err(|e| {
+        NewInterpreterError::Dynamic(format!(
+            "unable to read packed resources key from {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+
+    Ok(Some(key))
+}
+
+/// Ask the operating system to read ahead the prefetch ranges of memory mapped packed resources data.
+///
+/// Writers record ranges holding data of modules imported at startup, so
+/// reading them ahead of time turns scattered page faults into sequential I/O.
+/// Data that can't be parsed is left alone, as loading it reports the error.
+#[cfg(unix)]
+fn prefetch_packed_resources(data: &[u8], key: Option<&[u8]>) {
+    let resources = match key {
+        Some(key) => python_packed_resources::parser::load_resources_encrypted(data, key),
+        None => python_packed_resources::parser::load_resources(data),
+    };
+    let resources = match resources {
+        Ok(resources) => resources,
+        Err(_) => return,
+    };
+
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+
+    for (offset, length) in resources.prefetch_ranges() {
+        // madvise() requires a page aligned address. Mappings start on a page.
+        let start = offset - offset % page_size;
+        let end = std::cmp::min(offset + length, data.len());
+
+        if start < end {
+            unsafe {
+                libc::madvise(
+                    data.as_ptr().add(start) as *mut libc::c_void,
+                    end - start,
+                    libc::MADV_WILLNEED,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn prefetch_packed_resources(_data: &[u8], _key: Option<&[u8]>) {}
+
+/// Define `sys.__interactivehook__` unless one is already defined.
+fn install_interactive_hook(py: Python, history_file: Option<String>) -> PyResult<()> {
+    let globals = PyDict::new(py);
+    globals.set_item(py, "__builtins__", py.import("builtins")?)?;
+    globals.set_item(py, "history_file", history_file)?;
+
+    py.run(INTERACTIVE_HOOK_SOURCE, Some(&globals), None)
+}
+
+/// Python source removing environment variables not in `allowlist` from `os.environ`.
+///
+/// Deleting from `os.environ` itself would call `unsetenv()`. So entries are
+/// removed from the mapping backing it, leaving the process environment and
+/// the environment inherited by subprocesses alone.
+const RESTRICT_ENVIRONMENT_SOURCE: &str = r#"
+import os
+
+if os.name == 'nt':
+    allowed = {name.upper() for name in allowlist}
+else:
+    allowed = set(allowlist)
+
+for key in list(os.environ._data):
+    if os.environ.decodekey(key) not in allowed:
+        del os.environ._data[key]
+"#;
+
+/// Only let Python code see environment variables named in `allowlist`.
+fn restrict_environment(py: Python, allowlist: &[String]) -> PyResult<()> {
+    let globals = PyDict::new(py);
+    globals.set_item(py, "__builtins__", py.import("builtins")?)?;
+    globals.set_item(py, "allowlist", allowlist.to_vec())?;
+
+    py.run(RESTRICT_ENVIRONMENT_SOURCE, Some(&globals), None)
+}
+
+/// atexit callback preventing interrupts from being scheduled.
+fn stop_interrupts(py: Python) -> PyResult<PyObject> {
+    interpreter_stopping();
+
+    Ok(py.None())
+}
+
+/// Flush the Python stream for a standard stream, if it exists.
+fn flush_python_stream(py: Python, stream: StandardStream) -> Result<(), String> {
+    let sys = py
+        .import("sys")
+        .map_err(|_| "unable to import sys".to_string())?;
+    let value = sys
+        .get(py, stream.sys_attr())
+        .map_err(|_| format!("unable to obtain sys.{}", stream.sys_attr()))?;
+
+    if value != py.None() {
+        value
+            .call_method(py, "flush", NoArgs, None)
+            .map_err(|_| format!("unable to flush sys.{}", stream.sys_attr()))?;
+    }
+
+    Ok(())
 }
 
 static mut ORIGINAL_BUILTIN_EXTENSIONS: Option<Vec<pyffi::_inittab>> = None;
@@ -628,9 +1451,14 @@ fn set_pyimport_inittab(config: &OxidizedPythonInterpreterConfig) {
         });
     }
 
-    // Add additional extension modules from the config.
+    // Add additional extension modules from the config. Python uses the
+    // first entry matching a name. So remove existing entries to allow
+    // replacing modules.
     if let Some(extra_extension_modules) = &config.extra_extension_modules {
         for extension in extra_extension_modules {
+            extensions
+                .retain(|entry| unsafe { CStr::from_ptr(entry.name) } != extension.name.as_c_str());
+
             let ptr = extension.init_func as *const ();
             extensions.push(pyffi::_inittab {
                 name: extension.name.as_ptr() as *mut _,
@@ -699,17 +1527,33 @@ impl<'python, 'interpreter, 'resources> Drop
     for MainPythonInterpreter<'python, 'interpreter, 'resources>
 {
     fn drop(&mut self) {
-        if let Some(key) = &self.config.write_modules_directory_env {
-            if let Ok(path) = env::var(key) {
-                let path = PathBuf::from(path);
-                let py = self.acquire_gil().unwrap();
-
-                if let Err(msg) = write_modules_to_directory(py, &path) {
-                    eprintln!("error writing modules file: {}", msg);
+        match self.interpreter_state {
+            InterpreterState::Initialized => {
+                if let Some(key) = &self.config.write_modules_directory_env {
+                    if let Ok(path) = env::var(key) {
+                        let path = PathBuf::from(path);
+                        let py = self.acquire_gil().unwrap();
+
+                        if let Err(msg) = write_modules_to_directory(py, &path) {
+                            eprintln!("error writing modules file: {}", msg);
+                        }
+                    }
                 }
+
+                // Py_FinalizeEx() must be called with the GIL held.
+                let _ = self.acquire_gil();
+                interpreter_stopping();
+                let _ = unsafe { pyffi::Py_FinalizeEx() };
+            }
+            // Initialization failed part way through. Finalize whatever was
+            // initialized. The GIL is still held from initialization.
+            InterpreterState::Initializing => {
+                let _ = unsafe { pyffi::Py_FinalizeEx() };
             }
+            // Never started or already finalized by run_as_main().
+            InterpreterState::NotStarted | InterpreterState::Finalized => {}
         }
 
-        let _ = unsafe { pyffi::Py_FinalizeEx() };
+        self.release_finalized_state();
     }
 }
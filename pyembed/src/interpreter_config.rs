@@ -6,13 +6,13 @@
 
 use {
     super::config::{
-        CheckHashPYCsMode, OxidizedPythonInterpreterConfig, PythonInterpreterConfig,
-        PythonInterpreterProfile, PythonRunMode,
+        CheckHashPYCsMode, ExtensionModule, OxidizedPythonInterpreterConfig,
+        PythonInterpreterConfig, PythonInterpreterProfile, PythonRunMode,
     },
     libc::{c_int, size_t, wchar_t},
     python3_sys as pyffi,
     std::convert::TryInto,
-    std::ffi::{CStr, CString, OsStr},
+    std::ffi::{CStr, CString, OsStr, OsString},
     std::path::Path,
 };
 
@@ -226,21 +226,232 @@ fn set_legacy_windows_stdio(config: &mut pyffi::PyConfig, value: bool) {
     config.legacy_windows_stdio = if value { 1 } else { 0 };
 }
 
+/// Obtain the Python code a `multiprocessing` worker process was started to run.
+///
+/// `multiprocessing` starts processes by invoking `sys.executable`. The `spawn`
+/// start method in frozen applications passes `--multiprocessing-fork`
+/// followed by `key=value` arguments. Otherwise, and for the `forkserver` and
+/// resource tracker processes, interpreter flags are followed by `-c <code>`.
+///
+/// Returns `None` if the arguments are not such an invocation.
+pub(crate) fn multiprocessing_worker_code(args: &[OsString]) -> Option<String> {
+    if args.len() > 1 && args[1] == "--multiprocessing-fork" {
+        return Some(
+            "from multiprocessing.spawn import freeze_support; freeze_support()".to_string(),
+        );
+    }
+
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        let arg = arg.to_str()?;
+
+        if arg == "-c" {
+            let code = args.next()?.to_str()?;
+
+            // Only take over `-c` for code multiprocessing generates so
+            // applications remain free to interpret their arguments.
+            return if code.starts_with("from multiprocessing.") {
+                Some(code.to_string())
+            } else {
+                None
+            };
+        } else if arg == "-W" || arg == "-X" {
+            // These flags take a value as the next argument.
+            args.next();
+        } else if !arg.starts_with('-') {
+            return None;
+        }
+    }
+
+    None
+}
+
+/// Obtain a Python expression evaluating to a string.
+///
+/// The string is embedded hex encoded so its content never needs escaping.
+fn python_string_expr(value: &str) -> String {
+    let encoded = value
+        .as_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+
+    format!("bytes.fromhex('{}').decode('utf-8')", encoded)
+}
+
+/// Obtain Python code evaluating multiple strings of Python code in order.
+///
+/// Each string is compiled and executed separately, so `from __future__`
+/// imports and syntax errors only affect the string containing them.
+pub(crate) fn eval_many_code(code: &[String]) -> String {
+    code.iter()
+        .map(|code| {
+            format!(
+                "exec(compile({}, '<string>', 'exec'))",
+                python_string_expr(code)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Python code running a Jupyter kernel or installing its kernel spec.
+///
+/// `%NAME%` and `%DISPLAY_NAME%` are replaced by expressions evaluating to
+/// the default kernel name and display name.
+const JUPYTER_KERNEL_SOURCE: &str = r#"
+import sys
+
+if sys.argv[1:2] == ['install-kernel']:
+    import argparse
+    import json
+    import os
+    import tempfile
+
+    parser = argparse.ArgumentParser(
+        prog='%s install-kernel' % os.path.basename(sys.argv[0]),
+        description='Install the Jupyter kernel spec of this application.',
+    )
+    parser.add_argument('--name', default=%NAME%)
+    parser.add_argument('--display-name', default=%DISPLAY_NAME%)
+    parser.add_argument('--prefix', help='install to PREFIX instead of for the current user')
+    args = parser.parse_args(sys.argv[2:])
+
+    from jupyter_client.kernelspec import KernelSpecManager
+
+    with tempfile.TemporaryDirectory() as source:
+        with open(os.path.join(source, 'kernel.json'), 'w') as fh:
+            json.dump({
+                'argv': [os.path.abspath(sys.executable), '-f', '{connection_file}'],
+                'display_name': args.display_name,
+                'language': 'python',
+            }, fh, indent=1)
+
+        dest = KernelSpecManager().install_kernel_spec(
+            source, args.name, user=args.prefix is None, prefix=args.prefix,
+        )
+
+    print('Installed kernelspec %s in %s' % (args.name, dest))
+else:
+    from ipykernel import kernelapp
+
+    kernelapp.launch_new_instance()
+"#;
+
+/// Obtain Python code running a Jupyter kernel.
+///
+/// Running the process with an `install-kernel` argument installs the kernel
+/// spec instead.
+pub(crate) fn jupyter_kernel_code(name: &str, display_name: &str) -> String {
+    JUPYTER_KERNEL_SOURCE
+        .replace("%NAME%", &python_string_expr(name))
+        .replace("%DISPLAY_NAME%", &python_string_expr(display_name))
+}
+
+/// Obtain Python code running a module as the main program.
+///
+/// This is what running a zipapp archive does, except `sys.path` isn't
+/// modified. No names are bound in `__main__` before the module runs in it.
+pub(crate) fn main_module_code(module: &str) -> String {
+    format!(
+        "__import__('runpy')._run_module_as_main({}, alter_argv=False)",
+        python_string_expr(module)
+    )
+}
+
 impl<'a> OxidizedPythonInterpreterConfig<'a> {
+    /// Make an extension module implemented in the current binary importable.
+    ///
+    /// `init_func` is the module's initialization function, conventionally
+    /// named `PyInit_<name>`. e.g. the function defined by
+    /// `cpython::py_module_initializer!`.
+    ///
+    /// The module is added to the table of built-in modules when the
+    /// interpreter is initialized, so it can be imported without being
+    /// packaged. It replaces a built-in module or an extension module added
+    /// earlier having the same name.
+    pub fn add_extension_module(
+        &mut self,
+        name: &str,
+        init_func: unsafe extern "C" fn() -> *mut pyffi::PyObject,
+    ) -> Result<(), String> {
+        let name = CString::new(name)
+            .map_err(|_| format!("extension module name contains a NUL byte: {}", name))?;
+
+        self.extra_extension_modules
+            .get_or_insert_with(Vec::new)
+            .push(ExtensionModule { name, init_func });
+
+        Ok(())
+    }
+
+    /// Apply `sys_argv_skip` and `sys_argv_insert` to the arguments of the process.
+    fn apply_sys_argv_config(&self, args: Vec<OsString>) -> Vec<OsString> {
+        let mut args = args.into_iter();
+        let mut res = Vec::new();
+        res.extend(args.next());
+        res.extend(self.sys_argv_insert.iter().map(OsString::from));
+        res.extend(args.skip(self.sys_argv_skip));
+
+        res
+    }
+
+    /// Resolve the value of `sys.argv` from the arguments of the process.
+    ///
+    /// Leading arguments are removed and fixed arguments inserted as
+    /// configured. Run modes can add arguments or consume the first argument.
+    pub(crate) fn resolve_sys_argv(&self, args: Vec<OsString>) -> Vec<OsString> {
+        if self.multiprocessing_auto_dispatch && multiprocessing_worker_code(&args).is_some() {
+            return args;
+        }
+
+        let args = self.apply_sys_argv_config(args);
+
+        match &self.run {
+            PythonRunMode::ModuleWithArgs { args: extra, .. } => {
+                let mut args = args.into_iter();
+                let mut res = Vec::new();
+                res.extend(args.next());
+                res.extend(extra.iter().map(OsString::from));
+                res.extend(args);
+
+                res
+            }
+            PythonRunMode::FileFromArgs if args.len() > 1 => args.into_iter().skip(1).collect(),
+            _ => args,
+        }
+    }
+
+    /// Python code to run instead of `run` if this is a `multiprocessing` worker process.
+    pub(crate) fn multiprocessing_worker_code(&self) -> Option<String> {
+        if self.multiprocessing_auto_dispatch {
+            multiprocessing_worker_code(&std::env::args_os().collect::<Vec<_>>())
+        } else {
+            None
+        }
+    }
+
     /// Whether the run configuration should execute via Py_RunMain().
     pub(crate) fn uses_py_runmain(&self) -> bool {
         if self.interpreter_config.run_command.is_some()
             || self.interpreter_config.run_filename.is_some()
             || self.interpreter_config.run_module.is_some()
+            || self.multiprocessing_worker_code().is_some()
         {
             true
         } else {
             match &self.run {
                 PythonRunMode::Eval { .. } => true,
+                PythonRunMode::EvalMany { .. } => true,
                 PythonRunMode::File { .. } => true,
+                PythonRunMode::FileFromArgs => true,
                 PythonRunMode::Module { .. } => true,
+                PythonRunMode::ModuleWithArgs { .. } => true,
+                PythonRunMode::MainModule { .. } => true,
+                PythonRunMode::JupyterKernel { .. } => true,
                 PythonRunMode::Repl => true,
                 PythonRunMode::None => false,
+                PythonRunMode::Server { .. } => false,
             }
         }
     }
@@ -612,9 +823,16 @@ impl<'a> TryInto<pyffi::PyConfig> for &'a OxidizedPythonInterpreterConfig<'a> {
             }
         }
 
+        if let Some(code) = self.multiprocessing_worker_code() {
+            set_config_string_from_str(&config, &config.run_command, &code, "setting run_command")?;
+
+            return Ok(config);
+        }
+
         match &self.run {
             PythonRunMode::None => {}
             PythonRunMode::Repl => {}
+            PythonRunMode::Server { .. } => {}
             PythonRunMode::Eval { code } => {
                 if self.interpreter_config.run_command.is_none() {
                     set_config_string_from_str(
@@ -625,6 +843,36 @@ impl<'a> TryInto<pyffi::PyConfig> for &'a OxidizedPythonInterpreterConfig<'a> {
                     )?;
                 }
             }
+            PythonRunMode::EvalMany { code } => {
+                if self.interpreter_config.run_command.is_none() {
+                    set_config_string_from_str(
+                        &config,
+                        &config.run_command,
+                        &eval_many_code(code),
+                        "setting run_command",
+                    )?;
+                }
+            }
+            PythonRunMode::MainModule { module } => {
+                if self.interpreter_config.run_command.is_none() {
+                    set_config_string_from_str(
+                        &config,
+                        &config.run_command,
+                        &main_module_code(module),
+                        "setting run_command",
+                    )?;
+                }
+            }
+            PythonRunMode::JupyterKernel { name, display_name } => {
+                if self.interpreter_config.run_command.is_none() {
+                    set_config_string_from_str(
+                        &config,
+                        &config.run_command,
+                        &jupyter_kernel_code(name, display_name),
+                        "setting run_command",
+                    )?;
+                }
+            }
             PythonRunMode::File { path } => {
                 if self.interpreter_config.run_filename.is_none() {
                     set_config_string_from_path(
@@ -635,7 +883,24 @@ impl<'a> TryInto<pyffi::PyConfig> for &'a OxidizedPythonInterpreterConfig<'a> {
                     )?;
                 }
             }
-            PythonRunMode::Module { module } => {
+            PythonRunMode::FileFromArgs => {
+                // Without a path, Py_RunMain() runs a REPL.
+                if let Some(path) = self
+                    .apply_sys_argv_config(std::env::args_os().collect())
+                    .into_iter()
+                    .nth(1)
+                {
+                    if self.interpreter_config.run_filename.is_none() {
+                        set_config_string_from_path(
+                            &config,
+                            &config.run_filename,
+                            Path::new(&path),
+                            "setting run_filename",
+                        )?;
+                    }
+                }
+            }
+            PythonRunMode::Module { module } | PythonRunMode::ModuleWithArgs { module, .. } => {
                 if self.interpreter_config.run_module.is_none() {
                     set_config_string_from_str(
                         &config,
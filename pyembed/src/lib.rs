@@ -41,8 +41,9 @@ as possible.** This is because we want to minimize bloat in produced binaries.
 At this time, we have required direct dependencies on published versions of the
 `anyhow`, `lazy_static`, `libc`, `memmap`, `python-packed-resources`, and `uuid`
 crates. On Windows, this list is extended by `memory-module-sys` and `winapi`,
-which are required to support loading DLLs from memory. We also have an optional
-direct dependency on the `jemalloc-sys` crate.
+which are required to support loading DLLs from memory. We also have optional
+direct dependencies on the `jemalloc-sys`, `libmimalloc-sys`, and `snmalloc-sys`
+crates and on the `hyper` and `tokio` crates.
 
 This crate requires linking against a library providing CPython C symbols.
 (This dependency is via the `python3-sys` crate.) On Windows, this library
@@ -56,6 +57,17 @@ from Python is a run-time configuration option controlled by the
 `PythonConfig` type and having `jemalloc` compiled into the binary does not
 mean it is being used!
 
+The optional `mimalloc` and `snmalloc` features likewise control support for
+using [mimalloc](https://github.com/microsoft/mimalloc) and
+[snmalloc](https://github.com/microsoft/snmalloc) as Python's memory allocator.
+
+The optional `capi` feature enables the `capi` module, which exports a C API
+for creating and running interpreters from other languages. The C
+declarations are in `include/pyembed.h`.
+
+The optional `server` feature enables `PythonRunMode::Server`, which serves
+a WSGI or ASGI application with an HTTP server built on `hyper`.
+
 There exist mutually exclusive `build-mode-*` features to control how the
 `build.rs` build script works.
 
@@ -83,8 +95,13 @@ That crate's build script will attempt to find a `libpython` from the
 
 */
 
+#[cfg(all(feature = "capi", not(library_mode = "extension")))]
+#[allow(clippy::missing_safety_doc)]
+pub mod capi;
 #[cfg(not(library_mode = "extension"))]
 mod config;
+#[cfg(not(library_mode = "extension"))]
+mod console;
 mod conversion;
 #[allow(clippy::transmute_ptr_to_ptr, clippy::zero_ptr)]
 mod importer;
@@ -98,6 +115,9 @@ mod memory_dll;
 mod osutils;
 #[allow(clippy::transmute_ptr_to_ptr, clippy::zero_ptr)]
 mod package_metadata;
+mod pkg_resources;
+#[cfg_attr(library_mode = "extension", allow(dead_code))]
+mod profiling;
 #[cfg(not(library_mode = "extension"))]
 mod pyalloc;
 #[cfg(not(library_mode = "extension"))]
@@ -110,22 +130,46 @@ mod python_resource_types;
 mod python_resources;
 mod resource_scanning;
 #[cfg(not(library_mode = "extension"))]
+#[cfg_attr(not(feature = "server"), allow(dead_code))]
+mod server;
+#[cfg(not(library_mode = "extension"))]
+mod signals;
+#[cfg(not(library_mode = "extension"))]
+mod site_packages;
+#[cfg(not(library_mode = "extension"))]
+mod stdio;
+#[cfg(not(library_mode = "extension"))]
 pub mod technotes;
 #[cfg(test)]
 mod test;
+#[cfg(not(library_mode = "extension"))]
+mod zip_archives;
 
 #[cfg(not(library_mode = "extension"))]
 #[allow(unused_imports)]
 pub use crate::config::{
-    Allocator, CheckHashPYCsMode, CoerceCLocale, ExtensionModule, OptimizationLevel,
-    OxidizedPythonInterpreterConfig, PythonConfig, PythonInterpreterConfig,
-    PythonInterpreterProfile, PythonRawAllocator, PythonRunMode, TerminfoResolution,
+    Allocator, CertificateResolution, CheckHashPYCsMode, CoerceCLocale, ExtensionModule,
+    MultiprocessingStartMethod, OptimizationLevel, OxidizedPythonInterpreterConfig, PythonConfig,
+    PythonInterpreterConfig, PythonInterpreterProfile, PythonRawAllocator, PythonRunMode,
+    ServerInterface, SitePackagesOverlay, SitePackagesOverlayPriority, StdioRedirect,
+    TerminfoResolution,
 };
 
+#[allow(unused_imports)]
+pub use crate::python_resources::{InMemoryModuleFile, ResourcesMemoryUsage};
+
 #[cfg(not(library_mode = "extension"))]
 #[allow(unused_imports)]
 pub use crate::interpreter::{MainPythonInterpreter, NewInterpreterError};
 
+#[cfg(not(library_mode = "extension"))]
+#[allow(unused_imports)]
+pub use crate::signals::InterruptHandle;
+
+#[cfg(not(library_mode = "extension"))]
+#[allow(unused_imports)]
+pub use crate::stdio::{CallbackWriter, StandardStream};
+
 #[cfg(not(library_mode = "extension"))]
 #[allow(unused_imports)]
 pub use crate::python_eval::{
@@ -14,6 +14,7 @@ use {
     memory_module_sys::{
         MemoryFreeLibrary, MemoryGetProcAddress, MemoryLoadLibraryEx, HCUSTOMMODULE,
     },
+    python_packed_resources::data::{Resource, ResourceField},
     std::collections::HashMap,
     std::ffi::{c_void, CStr},
     std::sync::atomic::{AtomicUsize, Ordering},
@@ -119,9 +120,9 @@ extern "C" fn default_free(
 
 /// LoadLibraryA() implementation that looks in a `PythonResourcesState`.
 ///
-/// If an existing module was already loaded from memory, we return a reference to it.
-/// Otherwise we look for DLL data in memory and attempt to load from there.
-/// Otherwise we fall back to LoadLibraryA().
+/// We look for DLL data in memory. If an existing module was already loaded
+/// from that data, we return a reference to it. Otherwise we attempt to load
+/// from there. Otherwise we fall back to LoadLibraryA().
 #[no_mangle]
 extern "C" fn custom_load_library(filename: LPCSTR, user_data: *mut c_void) -> HCUSTOMMODULE {
     assert!(!user_data.is_null());
@@ -130,18 +131,6 @@ extern "C" fn custom_load_library(filename: LPCSTR, user_data: *mut c_void) -> H
     // that aren't UTF-8!
     let name = unsafe { CStr::from_ptr(filename) }.to_string_lossy();
 
-    // Return an already loaded memory module if we have one.
-    // This is in a block so the lock on `MEMORY_MODULES` is released since there
-    // is opportunity for deadlock via recursion below.
-    {
-        let memory_state = MEMORY_MODULES.lock().unwrap();
-        if let Some(module) = memory_state.modules.get(name.as_ref()) {
-            module.ref_count.fetch_add(1, Ordering::Acquire);
-
-            return module.ptr;
-        }
-    }
-
     // Look for a loadable memory module in our resources data structure.
     let resources_state = unsafe {
         (user_data as *const PythonResourcesState<u8>)
@@ -149,16 +138,58 @@ extern "C" fn custom_load_library(filename: LPCSTR, user_data: *mut c_void) -> H
             .unwrap()
     };
 
-    if let Some(entry) = resources_state.resources.get(name.as_ref()) {
+    if let Some(entry) = resolve_shared_library_resource(resources_state, &name) {
         if let Some(library_data) = &entry.in_memory_shared_library {
-            let res = unsafe { load_library_memory(resources_state, library_data) };
+            // Loaded modules are indexed by resource name, so different
+            // spellings of a library name share a single module.
+            //
+            // Return an already loaded memory module if we have one.
+            // This is in a block so the lock on `MEMORY_MODULES` is released since there
+            // is opportunity for deadlock via recursion below.
+            {
+                let memory_state = MEMORY_MODULES.lock().unwrap();
+                if let Some(module) = memory_state.modules.get(entry.name.as_ref()) {
+                    module.ref_count.fetch_add(1, Ordering::Acquire);
+
+                    return module.ptr;
+                }
+            }
+
+            // The library is copied when it is loaded. So decrypted data
+            // isn't needed afterwards.
+            let library_data = match python_packed_resources::parser::decode_blob(
+                entry,
+                ResourceField::InMemorySharedLibrary,
+                None,
+                library_data,
+                resources_state.packed_resources_key.as_deref(),
+            ) {
+                Ok(data) => data,
+                Err(_) => return std::ptr::null() as HCUSTOMMODULE,
+            };
+
+            let res = unsafe { load_library_memory(resources_state, &library_data) };
 
             // If we loaded a module, store its state. Otherwise return its failure (NULL).
             if !res.is_null() {
                 let mut memory_state = MEMORY_MODULES.lock().unwrap();
 
+                // Another thread may have loaded the library while the lock was
+                // released. Keep the existing module so the library is only
+                // loaded once.
+                if let Some(module) = memory_state.modules.get(entry.name.as_ref()) {
+                    module.ref_count.fetch_add(1, Ordering::Acquire);
+                    let existing = module.ptr;
+
+                    // Unlock to avoid potential for deadlock due to recursion.
+                    std::mem::drop(memory_state);
+                    unsafe { MemoryFreeLibrary(res) };
+
+                    return existing;
+                }
+
                 memory_state.modules.insert(
-                    name.to_string(),
+                    entry.name.to_string(),
                     MemoryModule {
                         ptr: res,
                         ref_count: AtomicUsize::new(1),
@@ -182,6 +213,34 @@ extern "C" fn custom_load_library(filename: LPCSTR, user_data: *mut c_void) -> H
     }
 }
 
+/// Find the resource holding a shared library requested by `LoadLibraryA()`.
+///
+/// Import tables refer to libraries by filename, e.g. `libcrypto-1_1.dll`.
+/// Shared library resources may be named without the extension. Names are
+/// compared case-insensitively, like Windows does.
+fn resolve_shared_library_resource<'a>(
+    resources_state: &'a PythonResourcesState<u8>,
+    name: &str,
+) -> Option<&'a Resource<'a, u8>> {
+    if let Some(entry) = resources_state.resources.get(name) {
+        return Some(entry);
+    }
+
+    let lower = name.to_lowercase();
+    let stem = if lower.ends_with(".dll") {
+        &lower[..lower.len() - 4]
+    } else {
+        &lower
+    };
+
+    resources_state.resources.values().find(|entry| {
+        entry.in_memory_shared_library.is_some() && {
+            let entry_name = entry.name.to_lowercase();
+            entry_name == lower || entry_name == stem
+        }
+    })
+}
+
 /// Custom GetProcAddress() implementation that knows to look in memory-loaded modules.
 #[no_mangle]
 extern "C" fn custom_get_proc_address(
@@ -212,26 +271,30 @@ extern "C" fn custom_free_library(module: HCUSTOMMODULE, _user_data: *mut c_void
         .iter()
         .position(|ptr| ptr == &module)
     {
-        memory_state.module_ptrs.remove(index);
-
         let mut free_module = None;
 
         for (name, module_state) in &memory_state.modules {
             if module_state.ptr == module {
                 if module_state.ref_count.fetch_sub(1, Ordering::Acquire) == 1 {
                     free_module = Some(name.to_string());
-                    break;
                 }
+                break;
             }
         }
 
+        // The library is only unloaded once nothing references it. Until then,
+        // `custom_get_proc_address()` needs to find it.
         if let Some(free_module) = free_module {
+            memory_state.module_ptrs.remove(index);
             memory_state.modules.remove(&free_module);
 
             // Unlock to avoid potential for deadlock due to recursion.
             std::mem::drop(memory_state);
-            return unsafe { MemoryFreeLibrary(module) };
+            unsafe { MemoryFreeLibrary(module) };
         }
+
+        // The library was loaded from memory. `FreeLibrary()` doesn't know it.
+        return;
     }
 
     unsafe {
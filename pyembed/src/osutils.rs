@@ -23,6 +23,27 @@ const TERMINFO_DIRS_REDHAT: &str = "/etc/terminfo:/usr/share/terminfo";
 /// terminfo directories for macOS.
 const TERMINFO_DIRS_MACOS: &str = "/usr/share/terminfo";
 
+/// Files holding a bundle of CA certificates on various operating systems.
+///
+/// Order matters: the first existing file is used.
+const CA_BUNDLE_FILES: &[&str] = &[
+    // Debian, Ubuntu, Gentoo, Arch.
+    "/etc/ssl/certs/ca-certificates.crt",
+    // Fedora, RHEL 7+.
+    "/etc/pki/ca-trust/extracted/pem/tls-ca-bundle.pem",
+    // Fedora, RHEL 6.
+    "/etc/pki/tls/certs/ca-bundle.crt",
+    // OpenSUSE.
+    "/etc/ssl/ca-bundle.pem",
+    // Alpine, macOS.
+    "/etc/ssl/cert.pem",
+    // FreeBSD.
+    "/usr/local/share/certs/ca-root-nss.crt",
+];
+
+/// Directories holding hashed CA certificates on various operating systems.
+const CA_CERTIFICATE_DIRS: &[&str] = &["/etc/ssl/certs", "/etc/pki/tls/certs"];
+
 lazy_static! {
     static ref TERMINFO_DIRS_COMMON: Vec<PathBuf> = {
         vec![
@@ -101,6 +122,85 @@ fn resolve_os_info() -> OsInfo {
     OsInfo { os, linux_distro }
 }
 
+/// Replace `$ORIGIN` in a value with the directory of the current executable.
+pub fn expand_origin(value: &str) -> String {
+    if !value.contains("$ORIGIN") {
+        return value.to_string();
+    }
+
+    match std::env::current_exe() {
+        Ok(exe) => match exe.parent() {
+            Some(origin) => value.replace("$ORIGIN", &origin.display().to_string()),
+            None => value.to_string(),
+        },
+        Err(_) => value.to_string(),
+    }
+}
+
+/// Attempt to resolve CA certificate locations for OpenSSL.
+///
+/// Returns values the `SSL_CERT_FILE` and `SSL_CERT_DIR` environment variables
+/// should be set to. A value is None if its environment variable is already set
+/// or no well-known location exists.
+pub fn resolve_ssl_cert_paths() -> (Option<String>, Option<String>) {
+    // Windows' certificate store is used by the ssl module instead.
+    if let OsVariant::Windows = *TARGET_OS {
+        return (None, None);
+    }
+
+    let find = |env: &str, paths: &[&str]| {
+        if std::env::var_os(env).is_some() {
+            None
+        } else {
+            paths
+                .iter()
+                .find(|p| Path::new(p).exists())
+                .map(|p| p.to_string())
+        }
+    };
+
+    (
+        find("SSL_CERT_FILE", CA_BUNDLE_FILES),
+        find("SSL_CERT_DIR", CA_CERTIFICATE_DIRS),
+    )
+}
+
+/// Attempt to resolve values for the `TCL_LIBRARY` and `TK_LIBRARY` environment variables.
+///
+/// `tcl_library` is the path to the Tcl library directory, such as `tcl8.6`.
+/// `$ORIGIN` in it is expanded. The Tk library is the sibling directory for the
+/// same version, such as `tk8.6`. A value is None if its environment variable is
+/// already set or, for Tk, no such directory exists.
+pub fn resolve_tcl_tk_libraries(tcl_library: &str) -> (Option<String>, Option<String>) {
+    let tcl_library = PathBuf::from(expand_origin(tcl_library));
+
+    let tk_library = match tcl_library.file_name().and_then(|name| name.to_str()) {
+        Some(name) if name.starts_with("tcl") => {
+            let path = tcl_library.with_file_name(format!("tk{}", &name[3..]));
+
+            if path.is_dir() {
+                Some(path.display().to_string())
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+
+    (
+        if std::env::var_os("TCL_LIBRARY").is_some() {
+            None
+        } else {
+            Some(tcl_library.display().to_string())
+        },
+        if std::env::var_os("TK_LIBRARY").is_some() {
+            None
+        } else {
+            tk_library
+        },
+    )
+}
+
 /// Attempt to resolve the value for the `TERMINFO_DIRS` environment variable.
 ///
 /// Returns Some() value that `TERMINFO_DIRS` should be set to or None if
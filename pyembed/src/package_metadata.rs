@@ -4,12 +4,12 @@
 
 use {
     super::importer::ImporterState,
-    cpython::exc::{IOError, NotImplementedError},
+    cpython::exc::{IOError, ValueError},
     cpython::{
         py_class, py_class_prop_getter, NoArgs, ObjectProtocol, PyBytes, PyErr, PyList, PyObject,
         PyResult, PyString, Python, PythonObject, ToPyObject,
     },
-    python_packed_resources::data::Resource,
+    python_packed_resources::data::{Resource, ResourceField},
     std::borrow::Cow,
     std::collections::HashMap,
     std::path::Path,
@@ -25,6 +25,10 @@ py_class!(class OxidizedDistribution |py| {
         self.read_text_impl(py, filename)
     }
 
+    def locate_file(&self, path: PyObject) -> PyResult<PyObject> {
+        self.locate_file_impl(py, path)
+    }
+
     @property def metadata(&self) -> PyResult<PyObject> {
         self.metadata_impl(py)
     }
@@ -50,13 +54,15 @@ impl OxidizedDistribution {
     fn read_text_impl(&self, py: Python, filename: &PyString) -> PyResult<PyObject> {
         let state: &Arc<Box<ImporterState>> = self.state(py);
         let package: &str = self.package(py);
+        let resources = state.pin_resources();
         let resources_state = state.get_resources_state();
 
         let filename = filename.to_string_lossy(py);
 
         let data = resolve_package_distribution_resource(
-            &resources_state.resources,
+            &resources,
             &resources_state.origin,
+            resources_state.packed_resources_key.as_deref(),
             package,
             &filename,
         )
@@ -81,6 +87,20 @@ impl OxidizedDistribution {
         text_wrapper.call_method(py, "read", NoArgs, None)
     }
 
+    /// Resolve the path of a file of the distribution.
+    ///
+    /// Paths are resolved relative to the directory of the resources
+    /// origin. Files of in-memory packages don't exist there.
+    fn locate_file_impl(&self, py: Python, path: PyObject) -> PyResult<PyObject> {
+        let state: &Arc<Box<ImporterState>> = self.state(py);
+        let origin = state.get_resources_state().origin.display().to_string();
+
+        let pathlib = py.import("pathlib")?;
+        let origin = pathlib.call(py, "Path", (origin,), None)?;
+
+        origin.call_method(py, "joinpath", (path,), None)
+    }
+
     /// Return the parsed metadata for this Distribution.
     ///
     /// The returned object will have keys that name the various bits of
@@ -88,11 +108,13 @@ impl OxidizedDistribution {
     fn metadata_impl(&self, py: Python) -> PyResult<PyObject> {
         let state: &Arc<Box<ImporterState>> = self.state(py);
         let package: &str = self.package(py);
+        let resources = state.pin_resources();
         let resources_state = state.get_resources_state();
 
         let data = resolve_package_distribution_resource(
-            &resources_state.resources,
+            &resources,
             &resources_state.origin,
+            resources_state.packed_resources_key.as_deref(),
             package,
             "METADATA",
         )
@@ -104,8 +126,9 @@ impl OxidizedDistribution {
             data
         } else {
             resolve_package_distribution_resource(
-                &resources_state.resources,
+                &resources,
                 &resources_state.origin,
+                resources_state.packed_resources_key.as_deref(),
                 package,
                 "PKG-INFO",
             )
@@ -139,8 +162,74 @@ impl OxidizedDistribution {
         entry_point.call_method(py, "_from_text", (text,), None)
     }
 
+    /// Return files of the distribution as `importlib.metadata.PackagePath`.
+    ///
+    /// Like `importlib.metadata`, files are listed by the `RECORD` file of
+    /// `.dist-info` directories or by the `SOURCES.txt` file of `.egg-info`
+    /// directories. `None` is returned if neither exists.
     fn files_impl(&self, py: Python) -> PyResult<PyObject> {
-        Err(PyErr::new::<NotImplementedError, _>(py, NoArgs))
+        let record = self.read_text_impl(py, &"RECORD".to_py_object(py))?;
+
+        let rows = if record != py.None() {
+            let lines = record.call_method(py, "splitlines", NoArgs, None)?;
+            let reader = py.import("csv")?.call(py, "reader", (lines,), None)?;
+
+            reader
+                .iter(py)?
+                .map(|row| row?.extract::<Vec<String>>(py))
+                .collect::<PyResult<Vec<_>>>()?
+        } else {
+            let sources = self.read_text_impl(py, &"SOURCES.txt".to_py_object(py))?;
+
+            if sources == py.None() {
+                return Ok(py.None());
+            }
+
+            sources
+                .extract::<String>(py)?
+                .lines()
+                .map(|line| vec![line.to_string()])
+                .collect::<Vec<_>>()
+        };
+
+        let importlib_metadata = py.import("importlib.metadata")?;
+        let package_path = importlib_metadata.get(py, "PackagePath")?;
+        let file_hash = importlib_metadata.get(py, "FileHash")?;
+
+        let files = rows
+            .iter()
+            .filter(|row| !row.is_empty())
+            .map(|row| {
+                let path = package_path.call(py, (&row[0],), None)?;
+
+                let hash = match row.get(1) {
+                    Some(value) if !value.is_empty() => file_hash.call(py, (value,), None)?,
+                    _ => py.None(),
+                };
+
+                let size = match row.get(2) {
+                    Some(value) if !value.is_empty() => value
+                        .parse::<u64>()
+                        .map_err(|_| {
+                            PyErr::new::<ValueError, _>(
+                                py,
+                                format!("invalid file size in RECORD: {}", value),
+                            )
+                        })?
+                        .to_py_object(py)
+                        .into_object(),
+                    _ => py.None(),
+                };
+
+                path.setattr(py, "hash", hash)?;
+                path.setattr(py, "size", size)?;
+                path.setattr(py, "dist", self.as_object())?;
+
+                Ok(path)
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+
+        Ok(PyList::new(py, &files).into_object())
     }
 
     fn requires_impl(&self, py: Python) -> PyResult<PyObject> {
@@ -176,13 +265,20 @@ impl OxidizedDistribution {
 }
 
 /// Find package metadata distributions given search criteria.
+///
+/// `name` is the `name` of a `DistributionFinder.Context`. `None` finds all
+/// distributions. `path` is ignored, as packed resources aren't associated
+/// with `sys.path` entries.
 pub(crate) fn find_distributions(
     py: Python,
     state: Arc<Box<ImporterState>>,
     name: Option<PyObject>,
     _path: Option<PyObject>,
 ) -> PyResult<PyObject> {
-    let resources = &state.get_resources_state().resources;
+    let resources = state.pin_resources();
+
+    // A default context has a name of None.
+    let name = name.filter(|name| *name != py.None());
 
     let distributions = if let Some(name) = name {
         // Python normalizes the name. We do the same.
@@ -227,13 +323,23 @@ pub(crate) fn find_distributions(
 fn resolve_package_distribution_resource<'a>(
     resources: &'a HashMap<Cow<'a, str>, Resource<'a, u8>>,
     origin: &Path,
+    key: Option<&[u8]>,
     package: &str,
     name: &str,
 ) -> anyhow::Result<Option<Cow<'a, [u8]>>> {
     if let Some(entry) = resources.get(package) {
         if let Some(resources) = &entry.in_memory_distribution_resources {
             if let Some(data) = resources.get(name) {
-                return Ok(Some(Cow::Borrowed(data.as_ref())));
+                let data = python_packed_resources::parser::decode_blob(
+                    entry,
+                    ResourceField::InMemoryDistributionResource,
+                    Some(name),
+                    data,
+                    key,
+                )
+                .map_err(|e| anyhow::anyhow!("error decoding {}: {}", name, e))?;
+
+                return Ok(Some(data));
             }
         }
 
@@ -0,0 +1,207 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Make `pkg_resources` work with resources indexed by `OxidizedFinder`.
+
+use cpython::{ObjectProtocol, PyDict, PyObject, PyResult, Python};
+
+/// Python source integrating `OxidizedFinder` with `pkg_resources`.
+///
+/// `pkg_resources` builds its working set by scanning `sys.path` entries,
+/// which packed distributions aren't part of. So they are added to the
+/// working set after the fact. Resource APIs are serviced by a provider
+/// registered for the loader type of modules imported by `OxidizedFinder`.
+const PKG_RESOURCES_SOURCE: &str = r#"
+import os
+import sys
+
+
+class OxidizedMetadataProvider:
+    """pkg_resources metadata provider for an OxidizedDistribution."""
+
+    def __init__(self, distribution):
+        self.distribution = distribution
+
+    def has_metadata(self, name):
+        return self.distribution.read_text(name) is not None
+
+    def get_metadata(self, name):
+        text = self.distribution.read_text(name)
+        if text is None:
+            raise FileNotFoundError('metadata file not found: %s' % name)
+
+        return text
+
+    def get_metadata_lines(self, name):
+        return pkg_resources.yield_lines(self.get_metadata(name))
+
+    def metadata_isdir(self, name):
+        return False
+
+    def metadata_listdir(self, name):
+        return []
+
+    def run_script(self, script_name, namespace):
+        raise pkg_resources.ResolutionError(
+            'scripts of packed distributions cannot be run: %s' % script_name)
+
+
+class OxidizedResourceProvider(OxidizedMetadataProvider):
+    """pkg_resources resource provider for modules imported by OxidizedFinder.
+
+    Resource names are relative to the package containing the module.
+    In-memory resources are extracted to the resource cache of the
+    ResourceManager when a filename is requested.
+    """
+
+    def __init__(self, module):
+        self.module = module
+        self.loader = getattr(module, '__loader__', None)
+
+        if hasattr(module, '__path__'):
+            self.package = module.__name__
+        else:
+            self.package = module.__name__.rpartition('.')[0]
+
+        reader = None
+        if self.package and self.loader is not None:
+            reader = self.loader.get_resource_reader(self.package)
+
+        self.files = reader.files() if reader is not None else None
+
+    # Modules don't have metadata.
+    def has_metadata(self, name):
+        return False
+
+    def get_metadata(self, name):
+        raise FileNotFoundError('metadata file not found: %s' % name)
+
+    def _traversable(self, resource_name):
+        if self.files is None:
+            return None
+
+        try:
+            return self.files.joinpath(resource_name)
+        except ValueError:
+            return None
+
+    def has_resource(self, resource_name):
+        path = self._traversable(resource_name)
+        return path is not None and (path.is_file() or path.is_dir())
+
+    def resource_isdir(self, resource_name):
+        path = self._traversable(resource_name)
+        return path is not None and path.is_dir()
+
+    def resource_listdir(self, resource_name):
+        if not self.resource_isdir(resource_name):
+            return []
+
+        return sorted(child.name for child in self._traversable(resource_name).iterdir())
+
+    def get_resource_stream(self, manager, resource_name):
+        path = self._traversable(resource_name)
+        if path is None:
+            raise FileNotFoundError('resource not found: %s' % resource_name)
+
+        return path.open('rb')
+
+    def get_resource_string(self, manager, resource_name):
+        with self.get_resource_stream(manager, resource_name) as fh:
+            return fh.read()
+
+    def get_resource_filename(self, manager, resource_name):
+        parts = resource_name.strip('/').split('/')
+
+        # Resources installed next to the module are used as-is.
+        module_file = getattr(self.module, '__file__', None)
+        if module_file:
+            path = os.path.join(os.path.dirname(module_file), *parts)
+            if os.path.exists(path):
+                return path
+
+        archive_name = '%s-%s' % (os.path.basename(sys.executable), self.package)
+
+        if self.resource_isdir(resource_name):
+            for child in self.resource_listdir(resource_name):
+                self.get_resource_filename(manager, '/'.join(parts + [child]))
+
+            return manager.get_cache_path(archive_name, parts)
+
+        data = self.get_resource_string(manager, resource_name)
+        path = manager.get_cache_path(archive_name, parts)
+
+        if os.path.isfile(path):
+            with open(path, 'rb') as fh:
+                if fh.read() == data:
+                    return path
+
+        temp_path = '%s.%d.tmp' % (path, os.getpid())
+        with open(temp_path, 'wb') as fh:
+            fh.write(data)
+        os.replace(temp_path, path)
+
+        manager.postprocess(path, path)
+
+        return path
+
+
+def register(module, finder):
+    global pkg_resources
+    pkg_resources = module
+
+    pkg_resources.register_loader_type(type(finder), OxidizedResourceProvider)
+
+    # Distributions are activated when added to the working set, which
+    # inserts their location into sys.path. So they don't have a location
+    # and are added under a separate working set entry.
+    entry = sys.executable
+
+    for distribution in finder.find_distributions():
+        metadata = OxidizedMetadataProvider(distribution)
+
+        name = distribution.metadata['Name']
+        if not name:
+            continue
+
+        if metadata.has_metadata('METADATA'):
+            cls = pkg_resources.DistInfoDistribution
+        else:
+            cls = pkg_resources.Distribution
+
+        dist = cls(
+            location=None,
+            metadata=metadata,
+            project_name=name,
+            version=distribution.version,
+            precedence=pkg_resources.EGG_DIST,
+        )
+
+        # Distributions found on sys.path take precedence.
+        pkg_resources.working_set.add(dist, entry=entry)
+"#;
+
+/// Register an `OxidizedFinder` with a `pkg_resources` module.
+///
+/// Distributions known to the finder are added to the working set of
+/// `pkg_resources` and a resource provider for modules imported by it is
+/// registered.
+pub(crate) fn register_pkg_resources_with_module(
+    py: Python,
+    finder: &PyObject,
+    pkg_resources: &PyObject,
+) -> PyResult<()> {
+    let globals = PyDict::new(py);
+    globals.set_item(py, "__builtins__", py.import("builtins")?)?;
+    globals.set_item(py, "__name__", "_oxidized_importer_pkg_resources")?;
+
+    py.run(PKG_RESOURCES_SOURCE, Some(&globals), None)?;
+
+    let register = globals
+        .get_item(py, "register")
+        .expect("register should be defined");
+    register.call(py, (pkg_resources, finder), None)?;
+
+    Ok(())
+}
@@ -0,0 +1,275 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Record timings of interpreter initialization and module imports.
+
+use {
+    lazy_static::lazy_static,
+    std::path::PathBuf,
+    std::sync::atomic::{AtomicBool, Ordering},
+    std::sync::Mutex,
+    std::time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use std::sync::Once;
+
+/// Timing of a phase of interpreter initialization.
+struct Phase {
+    name: &'static str,
+    start: Duration,
+    duration: Duration,
+}
+
+/// Timings of importing a module via `OxidizedFinder`.
+#[derive(Default)]
+struct Import {
+    module: String,
+    /// Time spent in `find_spec()`.
+    find: Duration,
+    /// Time spent obtaining code to execute, e.g. unmarshaling bytecode.
+    load: Duration,
+    /// Time spent in `exec_module()`, including imports it triggered.
+    exec: Duration,
+    /// Time spent in `exec_module()`, excluding imports it triggered.
+    exec_self: Duration,
+}
+
+/// An import whose module is executing.
+struct ActiveImport {
+    index: usize,
+    start: Instant,
+    children: Duration,
+}
+
+struct StartupProfile {
+    path: PathBuf,
+    /// Process recording the profile. Forked children don't write it.
+    pid: u32,
+    start: Instant,
+    phases: Vec<Phase>,
+    imports: Vec<Import>,
+    active: Vec<ActiveImport>,
+}
+
+impl StartupProfile {
+    /// Obtain the index of the record for a module, adding one if needed.
+    fn import_index(&mut self, module: &str) -> usize {
+        // Recently imported modules are the likely match.
+        match self.imports.iter().rposition(|i| i.module == module) {
+            Some(index) => index,
+            None => {
+                self.imports.push(Import {
+                    module: module.to_string(),
+                    ..Import::default()
+                });
+                self.imports.len() - 1
+            }
+        }
+    }
+
+    fn to_json(&self) -> String {
+        let phases = self
+            .phases
+            .iter()
+            .map(|phase| {
+                format!(
+                    "    {{\"name\": {}, \"start_us\": {}, \"duration_us\": {}}}",
+                    json_string(phase.name),
+                    phase.start.as_micros(),
+                    phase.duration.as_micros()
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let imports = self
+            .imports
+            .iter()
+            .map(|import| {
+                format!(
+                    "    {{\"module\": {}, \"find_us\": {}, \"load_us\": {}, \"exec_us\": {}, \"exec_self_us\": {}}}",
+                    json_string(&import.module),
+                    import.find.as_micros(),
+                    import.load.as_micros(),
+                    import.exec.as_micros(),
+                    import.exec_self.as_micros()
+                )
+            })
+            .collect::<Vec<_>>();
+
+        format!(
+            "{{\n  \"phases\": [\n{}\n  ],\n  \"imports\": [\n{}\n  ]\n}}\n",
+            phases.join(",\n"),
+            imports.join(",\n")
+        )
+    }
+}
+
+/// Encode a string as a JSON string literal.
+fn json_string(value: &str) -> String {
+    let mut res = String::with_capacity(value.len() + 2);
+    res.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            c if (c as u32) < 0x20 => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+
+    res.push('"');
+    res
+}
+
+lazy_static! {
+    static ref PROFILE: Mutex<Option<StartupProfile>> = Mutex::new(None);
+}
+
+/// Whether a profile is being recorded. Checked before taking the lock.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Whether a profile is being recorded.
+pub(crate) fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+#[cfg(unix)]
+static REGISTER_FORK_HANDLER: Once = Once::new();
+
+/// Stop recording in a forked child.
+///
+/// Only touches an atomic, as other threads of the parent may have held
+/// the profile lock when the process forked.
+#[cfg(unix)]
+extern "C" fn after_fork_child() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+/// Start recording a profile to be written to a file.
+pub(crate) fn start(path: PathBuf) {
+    #[cfg(unix)]
+    REGISTER_FORK_HANDLER.call_once(|| unsafe {
+        libc::pthread_atfork(None, None, Some(after_fork_child));
+    });
+
+    *PROFILE.lock().unwrap() = Some(StartupProfile {
+        path,
+        pid: std::process::id(),
+        start: Instant::now(),
+        phases: Vec::new(),
+        imports: Vec::new(),
+        active: Vec::new(),
+    });
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Stop recording and write the profile as JSON, if one was recorded.
+///
+/// Does nothing in a process forked from the one that started recording,
+/// so children don't overwrite the profile of their parent.
+pub(crate) fn finish() -> Result<(), String> {
+    let was_enabled = ENABLED.swap(false, Ordering::Relaxed);
+
+    if !was_enabled {
+        return Ok(());
+    }
+
+    if let Some(profile) = PROFILE.lock().unwrap().take() {
+        if profile.pid != std::process::id() {
+            return Ok(());
+        }
+
+        std::fs::write(&profile.path, profile.to_json())
+            .map_err(|e| format!("unable to write {}: {}", profile.path.display(), e))?;
+    }
+
+    Ok(())
+}
+
+/// Record an initialization phase that began at `start` and just ended.
+pub(crate) fn phase(name: &'static str, start: Instant) {
+    if !enabled() {
+        return;
+    }
+
+    if let Some(profile) = PROFILE.lock().unwrap().as_mut() {
+        profile.phases.push(Phase {
+            name,
+            start: start.saturating_duration_since(profile.start),
+            duration: start.elapsed(),
+        });
+    }
+}
+
+/// Record time spent finding a module.
+pub(crate) fn record_find(module: &str, start: Instant) {
+    if !enabled() {
+        return;
+    }
+
+    if let Some(profile) = PROFILE.lock().unwrap().as_mut() {
+        let index = profile.import_index(module);
+        profile.imports[index].find += start.elapsed();
+    }
+}
+
+/// Record time spent obtaining the code of a module.
+pub(crate) fn record_load(module: &str, start: Instant) {
+    if !enabled() {
+        return;
+    }
+
+    if let Some(profile) = PROFILE.lock().unwrap().as_mut() {
+        let index = profile.import_index(module);
+        profile.imports[index].load += start.elapsed();
+    }
+}
+
+/// Record the time spent executing a module by calling `f`.
+///
+/// Time spent executing modules imported by `f` is tracked, so the time
+/// spent in the module itself can be derived.
+pub(crate) fn time_exec<T>(module: &str, f: impl FnOnce() -> T) -> T {
+    if !enabled() {
+        return f();
+    }
+
+    exec_started(module);
+    let res = f();
+    exec_finished();
+
+    res
+}
+
+/// Record that execution of a module began.
+fn exec_started(module: &str) {
+    if let Some(profile) = PROFILE.lock().unwrap().as_mut() {
+        let index = profile.import_index(module);
+
+        profile.active.push(ActiveImport {
+            index,
+            start: Instant::now(),
+            children: Duration::default(),
+        });
+    }
+}
+
+/// Record that execution of the most recently started module ended.
+fn exec_finished() {
+    if let Some(profile) = PROFILE.lock().unwrap().as_mut() {
+        if let Some(active) = profile.active.pop() {
+            let elapsed = active.start.elapsed();
+
+            let import = &mut profile.imports[active.index];
+            import.exec += elapsed;
+            import.exec_self += elapsed.checked_sub(active.children).unwrap_or_default();
+
+            if let Some(parent) = profile.active.last_mut() {
+                parent.children += elapsed;
+            }
+        }
+    }
+}
@@ -12,7 +12,17 @@ use {
 };
 
 #[cfg(feature = "jemalloc-sys")]
-use {jemalloc_sys as jemallocffi, std::ptr::null_mut};
+use jemalloc_sys as jemallocffi;
+#[cfg(feature = "libmimalloc-sys")]
+use libmimalloc_sys as mimallocffi;
+#[cfg(feature = "snmalloc-sys")]
+use snmalloc_sys as snmallocffi;
+#[cfg(any(
+    feature = "jemalloc-sys",
+    feature = "libmimalloc-sys",
+    feature = "snmalloc-sys"
+))]
+use std::ptr::null_mut;
 
 const MIN_ALIGN: usize = 16;
 
@@ -220,3 +230,107 @@ pub fn make_raw_jemalloc_allocator() -> pyffi::PyMemAllocatorEx {
         free: Some(raw_jemalloc_free),
     }
 }
+
+// mimalloc and snmalloc expose the standard malloc() family. Their realloc()
+// handles NULL pointers and free() handles NULL, so only zero sizes need
+// special handling.
+
+#[cfg(feature = "libmimalloc-sys")]
+extern "C" fn raw_mimalloc_malloc(_ctx: *mut c_void, size: size_t) -> *mut c_void {
+    let size = match size {
+        0 => 1,
+        val => val,
+    };
+
+    unsafe { mimallocffi::mi_malloc(size) }
+}
+
+#[cfg(feature = "libmimalloc-sys")]
+extern "C" fn raw_mimalloc_calloc(_ctx: *mut c_void, nelem: size_t, elsize: size_t) -> *mut c_void {
+    let (nelem, elsize) = match nelem * elsize {
+        0 => (1, 1),
+        _ => (nelem, elsize),
+    };
+
+    unsafe { mimallocffi::mi_calloc(nelem, elsize) }
+}
+
+#[cfg(feature = "libmimalloc-sys")]
+extern "C" fn raw_mimalloc_realloc(
+    _ctx: *mut c_void,
+    ptr: *mut c_void,
+    new_size: size_t,
+) -> *mut c_void {
+    let new_size = match new_size {
+        0 => 1,
+        val => val,
+    };
+
+    unsafe { mimallocffi::mi_realloc(ptr, new_size) }
+}
+
+#[cfg(feature = "libmimalloc-sys")]
+extern "C" fn raw_mimalloc_free(_ctx: *mut c_void, ptr: *mut c_void) {
+    unsafe { mimallocffi::mi_free(ptr) }
+}
+
+#[cfg(feature = "libmimalloc-sys")]
+pub fn make_raw_mimalloc_allocator() -> pyffi::PyMemAllocatorEx {
+    pyffi::PyMemAllocatorEx {
+        ctx: null_mut(),
+        malloc: Some(raw_mimalloc_malloc),
+        calloc: Some(raw_mimalloc_calloc),
+        realloc: Some(raw_mimalloc_realloc),
+        free: Some(raw_mimalloc_free),
+    }
+}
+
+#[cfg(feature = "snmalloc-sys")]
+extern "C" fn raw_snmalloc_malloc(_ctx: *mut c_void, size: size_t) -> *mut c_void {
+    let size = match size {
+        0 => 1,
+        val => val,
+    };
+
+    unsafe { snmallocffi::sn_malloc(size) }
+}
+
+#[cfg(feature = "snmalloc-sys")]
+extern "C" fn raw_snmalloc_calloc(_ctx: *mut c_void, nelem: size_t, elsize: size_t) -> *mut c_void {
+    let (nelem, elsize) = match nelem * elsize {
+        0 => (1, 1),
+        _ => (nelem, elsize),
+    };
+
+    unsafe { snmallocffi::sn_calloc(nelem, elsize) }
+}
+
+#[cfg(feature = "snmalloc-sys")]
+extern "C" fn raw_snmalloc_realloc(
+    _ctx: *mut c_void,
+    ptr: *mut c_void,
+    new_size: size_t,
+) -> *mut c_void {
+    let new_size = match new_size {
+        0 => 1,
+        val => val,
+    };
+
+    unsafe { snmallocffi::sn_realloc(ptr, new_size) }
+}
+
+#[cfg(feature = "snmalloc-sys")]
+extern "C" fn raw_snmalloc_free(_ctx: *mut c_void, ptr: *mut c_void) {
+    unsafe { snmallocffi::sn_free(ptr) }
+}
+
+#[cfg(feature = "snmalloc-sys")]
+pub fn make_raw_snmalloc_allocator() -> pyffi::PyMemAllocatorEx {
+    pyffi::PyMemAllocatorEx {
+        ctx: null_mut(),
+        malloc: Some(raw_snmalloc_malloc),
+        calloc: Some(raw_snmalloc_calloc),
+        realloc: Some(raw_snmalloc_realloc),
+        free: Some(raw_snmalloc_free),
+    }
+}
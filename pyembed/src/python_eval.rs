@@ -7,6 +7,8 @@
 use {
     super::config::PythonRunMode,
     super::conversion::path_to_cstring,
+    super::interpreter_config::{jupyter_kernel_code, main_module_code},
+    super::server::run_server,
     cpython::exc::{RuntimeError, SystemExit, ValueError},
     cpython::{
         NoArgs, ObjectProtocol, PyClone, PyErr, PyModule, PyObject, PyResult, Python, PythonObject,
@@ -247,9 +249,34 @@ pub fn run(py: Python, run_mode: &PythonRunMode) -> PyResult<PyObject> {
     match run_mode {
         PythonRunMode::None => Ok(py.None()),
         PythonRunMode::Repl => run_repl(py),
-        PythonRunMode::Module { module } => run_module_as_main(py, module),
+        // Extra arguments were added to sys.argv during initialization.
+        PythonRunMode::Module { module } | PythonRunMode::ModuleWithArgs { module, .. } => {
+            run_module_as_main(py, module)
+        }
+        PythonRunMode::MainModule { module } => run_code(py, &main_module_code(module)),
         PythonRunMode::Eval { code } => run_code(py, code),
+        PythonRunMode::EvalMany { code } => {
+            let mut res = py.None();
+            for code in code {
+                res = run_code(py, code)?;
+            }
+
+            Ok(res)
+        }
         PythonRunMode::File { path } => run_file(py, path),
+        PythonRunMode::FileFromArgs => match std::env::args_os().nth(1) {
+            Some(path) => run_file(py, Path::new(&path)),
+            None => run_repl(py),
+        },
+        PythonRunMode::JupyterKernel { name, display_name } => {
+            run_code(py, &jupyter_kernel_code(name, display_name))
+        }
+        PythonRunMode::Server {
+            application,
+            interface,
+            address,
+            max_request_body_size,
+        } => run_server(py, application, *interface, address, *max_request_body_size),
     }
 }
 
@@ -234,7 +234,11 @@ impl OxidizedResourceCollector {
 
         let mut resources = Vec::new();
 
-        for resource in prepared.resources.values() {
+        for resource in prepared
+            .resolve_resources()
+            .map_err(|e| PyErr::new::<ValueError, _>(py, format!("error oxidizing: {}", e)))?
+            .values()
+        {
             resources.push(resource_to_pyobject(py, resource)?);
         }
 
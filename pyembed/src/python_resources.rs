@@ -12,7 +12,7 @@ use {
         pyobject_optional_resources_map_to_pathbuf, pyobject_to_owned_bytes_optional,
         pyobject_to_pathbuf_optional,
     },
-    anyhow::Result,
+    anyhow::{anyhow, Result},
     cpython::exc::{ImportError, OSError, TypeError, ValueError},
     cpython::{
         py_class, py_class_call_slot_impl_with_ref, py_class_prop_getter, py_class_prop_setter,
@@ -20,13 +20,14 @@ use {
         PyString, PyTuple, Python, PythonObject, ToPyObject,
     },
     python3_sys as pyffi,
-    python_packed_resources::data::{Resource, ResourceFlavor},
+    python_packed_resources::data::{Resource, ResourceField, ResourceFlavor},
     std::borrow::Cow,
     std::cell::RefCell,
     std::collections::HashMap,
     std::ffi::CStr,
     std::iter::FromIterator,
     std::path::{Path, PathBuf},
+    std::sync::Arc,
 };
 
 /// Python bytecode optimization level.
@@ -37,6 +38,32 @@ pub(crate) enum OptimizeLevel {
     Two,
 }
 
+/// Prefix of `__file__` URIs of modules imported from memory.
+const IN_MEMORY_URI_PREFIX: &str = "oxidized:";
+
+/// Defines how `__file__` is set for modules imported from memory.
+///
+/// Modules imported from memory don't have a file. But some code derives
+/// paths from `__file__` and fails if it isn't set.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InMemoryModuleFile {
+    /// `__file__` isn't set.
+    None,
+
+    /// `__file__` is a URI like `oxidized:foo/bar.py`.
+    ///
+    /// Paths derived from it are recognizably not filesystem paths.
+    Uri,
+
+    /// `__file__` is a path under the current executable like
+    /// `/path/to/exe/foo/bar.py`.
+    ///
+    /// This is consistent with `__path__` of packages. The path doesn't
+    /// exist. But `get_data()` of the importer serves module source and
+    /// resources for paths derived from it.
+    Path,
+}
+
 /// Determines whether an entry represents an importable Python module.
 ///
 /// Should only be called on module flavors.
@@ -46,7 +73,8 @@ where
 {
     assert_eq!(entry.flavor, ResourceFlavor::Module);
 
-    entry.in_memory_source.is_some()
+    entry.is_namespace_package
+        || entry.in_memory_source.is_some()
         || entry.relative_path_module_source.is_some()
         || match optimize_level {
             OptimizeLevel::Zero => {
@@ -61,6 +89,27 @@ where
         }
 }
 
+/// Obtain the data of a blob held by a resource, decoding it if needed.
+///
+/// `data` is the data `resource` holds for `field`, or for the blob named
+/// `name` for fields holding a mapping of names to blobs. `key` decrypts
+/// encrypted data.
+pub(crate) fn resource_blob_data<'b>(
+    py: Python,
+    resource: &Resource<u8>,
+    field: ResourceField,
+    name: Option<&str>,
+    data: &'b [u8],
+    key: Option<&[u8]>,
+) -> PyResult<Cow<'b, [u8]>> {
+    python_packed_resources::parser::decode_blob(resource, field, name, data, key).map_err(|e| {
+        PyErr::new::<OSError, _>(
+            py,
+            format!("error decoding data of {}: {}", resource.name, e),
+        )
+    })
+}
+
 /// Holds state for an importable Python module.
 ///
 /// This essentially is an abstraction over raw `Resource` entries that
@@ -78,6 +127,15 @@ where
     /// Path from which relative paths should be interpreted.
     origin: &'a Path,
 
+    /// How `__file__` is set if the module is imported from memory.
+    in_memory_module_file: InMemoryModuleFile,
+
+    /// Whether `__path__` of packages imported from memory is set.
+    in_memory_package_path: bool,
+
+    /// Key to decrypt encrypted data of the resource with.
+    packed_resources_key: Option<&'a [u8]>,
+
     /// The resource/module flavor.
     pub flavor: &'a ResourceFlavor,
     /// Whether this module is a package.
@@ -97,7 +155,16 @@ impl<'a> ImportablePythonModule<'a, u8> {
         io_module: &PyModule,
     ) -> PyResult<Option<PyObject>> {
         let bytes = if let Some(data) = &self.resource.in_memory_source {
-            Some(PyBytes::new(py, data))
+            let data = resource_blob_data(
+                py,
+                self.resource,
+                ResourceField::InMemorySource,
+                None,
+                data,
+                self.packed_resources_key,
+            )?;
+
+            Some(PyBytes::new(py, &data))
         } else if let Some(relative_path) = &self.resource.relative_path_module_source {
             let path = self.origin.join(relative_path);
 
@@ -130,7 +197,8 @@ impl<'a> ImportablePythonModule<'a, u8> {
     /// module in this scenario, but it isn't known to the resources data structure
     /// (e.g. the case of frozen modules).
     ///
-    /// The returned `PyObject` will be an instance of `memoryview`.
+    /// The returned `PyObject` will be an instance of `memoryview`, unless the
+    /// bytecode had to be decrypted.
     pub fn resolve_bytecode(
         &mut self,
         py: Python,
@@ -138,11 +206,35 @@ impl<'a> ImportablePythonModule<'a, u8> {
         decode_source: &PyObject,
         io_module: &PyModule,
     ) -> PyResult<Option<PyObject>> {
-        if let Some(data) = match optimize_level {
-            OptimizeLevel::Zero => &self.resource.in_memory_bytecode,
-            OptimizeLevel::One => &self.resource.in_memory_bytecode_opt1,
-            OptimizeLevel::Two => &self.resource.in_memory_bytecode_opt2,
-        } {
+        let (field, data) = match optimize_level {
+            OptimizeLevel::Zero => (
+                ResourceField::InMemoryBytecode,
+                &self.resource.in_memory_bytecode,
+            ),
+            OptimizeLevel::One => (
+                ResourceField::InMemoryBytecodeOpt1,
+                &self.resource.in_memory_bytecode_opt1,
+            ),
+            OptimizeLevel::Two => (
+                ResourceField::InMemoryBytecodeOpt2,
+                &self.resource.in_memory_bytecode_opt2,
+            ),
+        };
+
+        if let Some(data) = data {
+            let data = match resource_blob_data(
+                py,
+                self.resource,
+                field,
+                None,
+                data,
+                self.packed_resources_key,
+            )? {
+                Cow::Borrowed(data) => data,
+                // Decrypted bytecode only lives as long as this function.
+                Cow::Owned(data) => return Ok(Some(PyBytes::new(py, &data).into_object())),
+            };
+
             let ptr = unsafe {
                 pyffi::PyMemoryView_FromMemory(
                     data.as_ptr() as _,
@@ -178,7 +270,14 @@ impl<'a> ImportablePythonModule<'a, u8> {
             let builtins = py.import("builtins")?;
             let marshal = py.import("marshal")?;
 
-            let code = builtins.call(py, "compile", (source, &self.resource.name, "exec"), None)?;
+            // Tools like coverage map code objects to files by their filename.
+            let filename = if let Some(path) = self.origin_path() {
+                path_to_pyobject(py, &path)?
+            } else {
+                self.resource.name.to_py_object(py).into_object()
+            };
+
+            let code = builtins.call(py, "compile", (source, filename, "exec"), None)?;
             let bytecode = marshal.call(py, "dumps", (code,), None)?;
 
             Ok(Some(bytecode))
@@ -209,6 +308,16 @@ impl<'a> ImportablePythonModule<'a, u8> {
             kwargs.set_item(py, "origin", origin)?;
         }
 
+        // PEP 420 namespace packages don't have a loader. Python's import
+        // machinery treats a spec without a loader but with
+        // `submodule_search_locations` as a namespace package and installs
+        // its own namespace loader on the module.
+        let loader = if self.resource.is_namespace_package {
+            py.None()
+        } else {
+            loader.clone_ref(py)
+        };
+
         let spec = module_spec_type.call(py, (name, loader), Some(&kwargs))?;
 
         if origin.is_some() {
@@ -264,7 +373,7 @@ impl<'a> ImportablePythonModule<'a, u8> {
                 vec![]
             };
 
-            if locations.is_empty() {
+            if locations.is_empty() && self.in_memory_package_path {
                 let mut path = self.current_exe.to_path_buf();
                 path.extend(self.resource.name.split('.'));
 
@@ -284,10 +393,49 @@ impl<'a> ImportablePythonModule<'a, u8> {
         Ok(if let Some(path) = self.origin_path() {
             Some(path_to_pyobject(py, &path)?)
         } else {
-            None
+            self.in_memory_origin(py)?
         })
     }
 
+    /// Resolve the origin of a module imported from memory.
+    ///
+    /// The value depends on `in_memory_module_file`. It refers to a `.py`
+    /// file at the location the module would have in a filesystem tree
+    /// whose root is the current executable.
+    fn in_memory_origin(&self, py: Python) -> PyResult<Option<PyObject>> {
+        if self.flavor != &ResourceFlavor::Module || self.resource.is_namespace_package {
+            return Ok(None);
+        }
+
+        let mut parts = self
+            .resource
+            .name
+            .split('.')
+            .map(|part| part.to_string())
+            .collect::<Vec<_>>();
+
+        if self.is_package {
+            parts.push("__init__.py".to_string());
+        } else if let Some(last) = parts.last_mut() {
+            last.push_str(".py");
+        }
+
+        match self.in_memory_module_file {
+            InMemoryModuleFile::None => Ok(None),
+            InMemoryModuleFile::Uri => Ok(Some(
+                format!("{}{}", IN_MEMORY_URI_PREFIX, parts.join("/"))
+                    .to_py_object(py)
+                    .into_object(),
+            )),
+            InMemoryModuleFile::Path => {
+                let mut path = self.current_exe.to_path_buf();
+                path.extend(parts);
+
+                Ok(Some(path_to_pyobject(py, &path)?))
+            }
+        }
+    }
+
     /// Resolve the value of a `ModuleSpec` `cached` attribute.
     ///
     /// The value gets turned into `__cached__`.
@@ -345,6 +493,71 @@ impl<'a> ImportablePythonModule<'a, u8> {
     }
 }
 
+/// Memory used by packed resources data.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ResourcesMemoryUsage {
+    /// Size in bytes of the packed resources data resources were loaded from.
+    pub mapped_bytes: usize,
+
+    /// Bytes of packed resources data currently resident in memory.
+    ///
+    /// `None` if residency can't be determined on this platform.
+    pub resident_bytes: Option<usize>,
+}
+
+/// Obtain the number of bytes of `data` resident in physical memory.
+#[cfg(unix)]
+fn resident_bytes(data: &[u8]) -> Option<usize> {
+    if data.is_empty() {
+        return Some(0);
+    }
+
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if page_size <= 0 {
+        return None;
+    }
+    let page_size = page_size as usize;
+
+    // mincore() requires a page aligned address.
+    let start = data.as_ptr() as usize;
+    let end = start + data.len();
+    let aligned_start = start - start % page_size;
+    let pages = (end - aligned_start + page_size - 1) / page_size;
+
+    let mut residency = vec![0u8; pages];
+
+    let res = unsafe {
+        libc::mincore(
+            aligned_start as *mut libc::c_void,
+            end - aligned_start,
+            residency.as_mut_ptr() as *mut _,
+        )
+    };
+    if res != 0 {
+        return None;
+    }
+
+    // Only count the parts of the first and last pages covered by the data.
+    Some(
+        residency
+            .iter()
+            .enumerate()
+            .filter(|(_, state)| **state & 1 == 1)
+            .map(|(i, _)| {
+                let page_start = std::cmp::max(aligned_start + i * page_size, start);
+                let page_end = std::cmp::min(aligned_start + (i + 1) * page_size, end);
+
+                page_end - page_start
+            })
+            .sum(),
+    )
+}
+
+#[cfg(not(unix))]
+fn resident_bytes(_data: &[u8]) -> Option<usize> {
+    None
+}
+
 /// Defines Python resources available for import.
 #[derive(Debug)]
 pub(crate) struct PythonResourcesState<'a, X>
@@ -359,8 +572,38 @@ where
     /// Probably the directory of `current_exe`.
     pub origin: PathBuf,
 
+    /// How `__file__` is set for modules imported from memory.
+    pub in_memory_module_file: InMemoryModuleFile,
+
+    /// Whether `__path__` is set for packages imported from memory.
+    ///
+    /// If set, `__path__` contains a path under `current_exe`, which
+    /// doesn't exist. Otherwise it is empty.
+    pub in_memory_package_path: bool,
+
+    /// Names of modules to import lazily.
+    ///
+    /// Submodules of packages named here are imported lazily as well.
+    pub lazy_modules: Vec<String>,
+
     /// Named resources available for loading.
-    pub resources: HashMap<Cow<'a, str>, Resource<'a, X>>,
+    ///
+    /// The index is shared so readers can hold on to it without locking.
+    /// Modifying it copies the index if it is held elsewhere, so resources
+    /// borrowed from a held index remain valid.
+    pub resources: Arc<HashMap<Cow<'a, str>, Resource<'a, X>>>,
+
+    /// Segments of packed resources data resources were loaded from.
+    ///
+    /// Resources borrow from these segments. So their data is only paged in
+    /// when used if a segment is memory mapped.
+    pub segments: Vec<&'a [X]>,
+
+    /// Key decrypting encrypted data of resources.
+    ///
+    /// Resources hold encrypted data as stored. It is decrypted each time it
+    /// is accessed, so decrypted data isn't kept in memory.
+    pub packed_resources_key: Option<Vec<u8>>,
 }
 
 impl<'a> Default for PythonResourcesState<'a, u8> {
@@ -368,7 +611,12 @@ impl<'a> Default for PythonResourcesState<'a, u8> {
         Self {
             current_exe: PathBuf::new(),
             origin: PathBuf::new(),
-            resources: HashMap::new(),
+            in_memory_module_file: InMemoryModuleFile::None,
+            in_memory_package_path: true,
+            lazy_modules: vec![],
+            resources: Arc::new(HashMap::new()),
+            segments: vec![],
+            packed_resources_key: None,
         }
     }
 }
@@ -385,23 +633,64 @@ impl<'a> PythonResourcesState<'a, u8> {
         Ok(Self {
             current_exe: exe,
             origin,
+            in_memory_module_file: InMemoryModuleFile::None,
+            in_memory_package_path: true,
+            lazy_modules: vec![],
             resources: Default::default(),
+            segments: vec![],
+            packed_resources_key: None,
         })
     }
 
     /// Load state from the environment and by parsing data structures.
-    pub fn load(&mut self, resources_data: Option<&'a [u8]>) -> Result<(), &'static str> {
+    ///
+    /// `resources_data` holds segments of packed resources data in priority
+    /// order. A resource in a segment replaces resources of the same name in
+    /// later segments. `key` decrypts segments holding encrypted data. It is
+    /// retained to decrypt data when it is accessed.
+    pub fn load(
+        &mut self,
+        resources_data: &[&'a [u8]],
+        key: Option<&[u8]>,
+    ) -> Result<(), &'static str> {
+        // Lower priority segments are loaded first so higher priority ones
+        // replace their resources.
+        for data in resources_data.iter().rev() {
+            self.load_resources(*data, key)?;
+        }
+
+        self.packed_resources_key = key.map(|key| key.to_vec());
+
+        self.segments.extend(resources_data.iter().copied());
+
         // Loading of builtin and frozen knows to mutate existing entries rather
         // than replace. So do these last.
-        if let Some(data) = resources_data {
-            self.load_resources(data)?;
-        }
         self.load_interpreter_builtin_modules()?;
         self.load_interpreter_frozen_modules()?;
 
         Ok(())
     }
 
+    /// Obtain memory used by packed resources data this instance was loaded from.
+    ///
+    /// Resources added after loading are not accounted for.
+    pub fn memory_usage(&self) -> ResourcesMemoryUsage {
+        let mut usage = ResourcesMemoryUsage {
+            mapped_bytes: 0,
+            resident_bytes: if cfg!(unix) { Some(0) } else { None },
+        };
+
+        for segment in &self.segments {
+            usage.mapped_bytes += segment.len();
+            usage.resident_bytes = match (usage.resident_bytes, resident_bytes(segment)) {
+                (Some(total), Some(resident)) => Some(total + resident),
+                _ => None,
+            };
+        }
+
+        usage
+    }
+
     /// Add a resource to the instance.
     ///
     /// Memory in the resource must live for at least as long as the lifetime of
@@ -410,28 +699,57 @@ impl<'a> PythonResourcesState<'a, u8> {
         &mut self,
         resource: Resource<'resource, u8>,
     ) -> Result<(), &'static str> {
-        self.resources.insert(resource.name.clone(), resource);
+        Arc::make_mut(&mut self.resources).insert(resource.name.clone(), resource);
 
         Ok(())
     }
 
+    /// Remove a resource from the instance.
+    ///
+    /// Returns the removed resource, if there was one.
+    pub fn remove_resource(&mut self, name: &str) -> Option<Resource<'a, u8>> {
+        if !self.resources.contains_key(name) {
+            return None;
+        }
+
+        Arc::make_mut(&mut self.resources).remove(name)
+    }
+
+    /// Whether a module should be imported lazily.
+    pub fn is_lazy_module(&self, name: &str) -> bool {
+        self.lazy_modules.iter().any(|lazy| {
+            name == lazy || (name.starts_with(lazy.as_str()) && name[lazy.len()..].starts_with('.'))
+        })
+    }
+
     /// Attempt to resolve an importable Python module.
     pub fn resolve_importable_module(
         &self,
         name: &str,
         optimize_level: OptimizeLevel,
     ) -> Option<ImportablePythonModule<u8>> {
-        let resource = match self.resources.get(name) {
-            Some(entry) => entry,
-            None => return None,
-        };
+        self.importable_module(self.resources.get(name)?, optimize_level)
+    }
 
+    /// Attempt to obtain an importable Python module from a resource.
+    ///
+    /// Unlike `resolve_importable_module()`, the resource is looked up by the
+    /// caller. This allows looking it up in a held index, so it remains valid
+    /// while the module is used.
+    pub fn importable_module<'b>(
+        &'b self,
+        resource: &'b Resource<'a, u8>,
+        optimize_level: OptimizeLevel,
+    ) -> Option<ImportablePythonModule<'b, u8>> {
         match resource.flavor {
             ResourceFlavor::Module => {
                 if is_module_importable(resource, optimize_level) {
                     Some(ImportablePythonModule {
                         resource,
                         current_exe: &self.current_exe,
+                        in_memory_module_file: self.in_memory_module_file,
+                        in_memory_package_path: self.in_memory_package_path,
+                        packed_resources_key: self.packed_resources_key.as_deref(),
                         origin: &self.origin,
                         flavor: &resource.flavor,
                         is_package: resource.is_package,
@@ -443,6 +761,9 @@ impl<'a> PythonResourcesState<'a, u8> {
             ResourceFlavor::Extension => Some(ImportablePythonModule {
                 resource,
                 current_exe: &self.current_exe,
+                in_memory_module_file: self.in_memory_module_file,
+                in_memory_package_path: self.in_memory_package_path,
+                packed_resources_key: self.packed_resources_key.as_deref(),
                 origin: &self.origin,
                 flavor: &resource.flavor,
                 is_package: resource.is_package,
@@ -450,6 +771,9 @@ impl<'a> PythonResourcesState<'a, u8> {
             ResourceFlavor::BuiltinExtensionModule => Some(ImportablePythonModule {
                 resource,
                 current_exe: &self.current_exe,
+                in_memory_module_file: self.in_memory_module_file,
+                in_memory_package_path: self.in_memory_package_path,
+                packed_resources_key: self.packed_resources_key.as_deref(),
                 origin: &self.origin,
                 flavor: &resource.flavor,
                 is_package: resource.is_package,
@@ -457,6 +781,9 @@ impl<'a> PythonResourcesState<'a, u8> {
             ResourceFlavor::FrozenModule => Some(ImportablePythonModule {
                 resource,
                 current_exe: &self.current_exe,
+                in_memory_module_file: self.in_memory_module_file,
+                in_memory_package_path: self.in_memory_package_path,
+                packed_resources_key: self.packed_resources_key.as_deref(),
                 origin: &self.origin,
                 flavor: &resource.flavor,
                 is_package: resource.is_package,
@@ -476,7 +803,11 @@ impl<'a> PythonResourcesState<'a, u8> {
         package: &str,
         resource_name: &str,
     ) -> PyResult<Option<PyObject>> {
-        let entry = match self.resources.get(package) {
+        // Python code may run while the entry is used. Hold the index so the
+        // entry remains valid if the index is modified meanwhile.
+        let index = self.resources.clone();
+
+        let entry = match index.get(package) {
             Some(entry) => entry,
             None => return Ok(None),
         };
@@ -486,7 +817,15 @@ impl<'a> PythonResourcesState<'a, u8> {
                 let io_module = py.import("io")?;
                 let bytes_io = io_module.get(py, "BytesIO")?;
 
-                let data = PyBytes::new(py, data);
+                let data = resource_blob_data(
+                    py,
+                    entry,
+                    ResourceField::InMemoryResourcesData,
+                    Some(resource_name),
+                    data,
+                    self.packed_resources_key.as_deref(),
+                )?;
+                let data = PyBytes::new(py, &data);
                 return Ok(Some(bytes_io.call(py, (data,), None)?));
             }
         }
@@ -527,31 +866,77 @@ impl<'a> PythonResourcesState<'a, u8> {
         false
     }
 
-    /// Obtain the resources available in a Python package, as a Python list.
-    ///
-    /// The names are returned in sorted order.
-    pub fn package_resource_names(&self, py: Python, package: &str) -> PyResult<PyObject> {
+    /// Obtain the names of all resources in a Python package.
+    fn package_resource_names_iter<'b>(&'b self, package: &str) -> Vec<&'b str> {
         let entry = match self.resources.get(package) {
             Some(entry) => entry,
-            None => return Ok(PyList::new(py, &[]).into_object()),
+            None => return vec![],
         };
 
-        let mut names = if let Some(resources) = &entry.in_memory_package_resources {
-            resources.keys().collect()
-        } else if let Some(resources) = &entry.relative_path_package_resources {
-            resources.keys().collect()
+        let mut names = vec![];
+
+        if let Some(resources) = &entry.in_memory_package_resources {
+            names.extend(resources.keys().map(|name| name.as_ref()));
+        }
+
+        if let Some(resources) = &entry.relative_path_package_resources {
+            names.extend(resources.keys().map(|name| name.as_ref()));
+        }
+
+        names
+    }
+
+    /// Obtain the entries of a directory of resources in a Python package.
+    ///
+    /// Resource names are POSIX paths. So resources can be in nested
+    /// directories. `directory` is the path of a directory in the package,
+    /// with an empty string denoting the package itself. Entries are file and
+    /// directory names, returned in sorted order.
+    pub fn package_resource_directory_entries(
+        &self,
+        package: &str,
+        directory: &str,
+    ) -> Vec<String> {
+        let prefix = if directory.is_empty() {
+            "".to_string()
         } else {
-            vec![]
+            format!("{}/", directory)
         };
 
-        names.sort();
+        let mut entries = self
+            .package_resource_names_iter(package)
+            .into_iter()
+            .filter(|name| name.starts_with(&prefix))
+            .filter_map(|name| name[prefix.len()..].split('/').next())
+            .map(|entry| entry.to_string())
+            .collect::<Vec<_>>();
 
-        let names = names
-            .iter()
-            .map(|x| x.to_py_object(py).into_object())
-            .collect::<Vec<PyObject>>();
+        entries.sort();
+        entries.dedup();
 
-        Ok(PyList::new(py, &names).into_object())
+        entries
+    }
+
+    /// Determines whether a path in a Python package is a directory of resources.
+    ///
+    /// The package itself, denoted by an empty string, is always a directory.
+    pub fn is_package_resource_directory(&self, package: &str, directory: &str) -> bool {
+        directory.is_empty()
+            || !self
+                .package_resource_directory_entries(package, directory)
+                .is_empty()
+    }
+
+    /// Obtain the filesystem path of a resource in a Python package.
+    ///
+    /// Only resources installed relative to the origin have a path.
+    pub fn package_resource_path(&self, package: &str, resource_name: &str) -> Option<PathBuf> {
+        self.resources
+            .get(package)?
+            .relative_path_package_resources
+            .as_ref()?
+            .get(resource_name)
+            .map(|path| self.origin.join(path))
     }
 
     /// Attempt to resolve a PyBytes for resource data given a relative path.
@@ -569,6 +954,8 @@ impl<'a> PythonResourcesState<'a, u8> {
         // in-memory resources. This emulates behavior of zipimporter, which
         // does something similar.
         //
+        // `oxidized:` URIs are recognized as in-memory resources as well.
+        //
         // Paths prefixed with the current resources origin are recognized as
         // path-relative resources. We need to service these paths because we
         // hand out a __path__ that points to the package directory and someone
@@ -600,10 +987,21 @@ impl<'a> PythonResourcesState<'a, u8> {
         // this functionality some day. But it should likely never be the default
         // because it goes against the spirit of requiring all resources to be
         // known ahead-of-time.
-        let native_path = PathBuf::from(path.to_string_lossy(py).to_string());
+        let path_string = path.to_string_lossy(py).to_string();
+
+        // `__file__` of modules imported from memory may be an `oxidized:` URI.
+        let uri_path = if path_string.starts_with(IN_MEMORY_URI_PREFIX) {
+            Some(PathBuf::from(&path_string[IN_MEMORY_URI_PREFIX.len()..]))
+        } else {
+            None
+        };
+
+        let native_path = PathBuf::from(path_string);
 
         let (relative_path, check_in_memory, check_relative_path) =
-            if let Ok(relative_path) = native_path.strip_prefix(&self.current_exe) {
+            if let Some(relative_path) = &uri_path {
+                (relative_path.as_path(), true, false)
+            } else if let Ok(relative_path) = native_path.strip_prefix(&self.current_exe) {
                 (relative_path, true, false)
             } else if let Ok(relative_path) = native_path.strip_prefix(&self.origin) {
                 (relative_path, false, true)
@@ -614,6 +1012,17 @@ impl<'a> PythonResourcesState<'a, u8> {
                 ));
             };
 
+        // Paths may also refer to the source file of a module, e.g. its `__file__`
+        // or a `.py` file in the `__path__` of a package.
+        if let Some(data) = self.resolve_module_source_data_from_path(
+            py,
+            relative_path,
+            check_in_memory,
+            check_relative_path,
+        )? {
+            return Ok(data);
+        }
+
         // There is also an additional wrinkle with resolving resources from paths.
         // And that is the boundary between the package name and the resource name.
         // The relative path to the resource logically consists of a package name
@@ -642,6 +1051,9 @@ impl<'a> PythonResourcesState<'a, u8> {
             ));
         }
 
+        // Hold the index while calling into Python. See `get_package_resource_file()`.
+        let index = self.resources.clone();
+
         let mut name_parts = vec![components[components.len() - 1]
             .as_os_str()
             .to_string_lossy()];
@@ -658,11 +1070,20 @@ impl<'a> PythonResourcesState<'a, u8> {
             let resource_name = name_parts.join("/");
             let resource_name_ref: &str = &resource_name;
 
-            if let Some(entry) = self.resources.get(package_name_ref) {
+            if let Some(entry) = index.get(package_name_ref) {
                 if check_in_memory {
                     if let Some(resources) = &entry.in_memory_package_resources {
                         if let Some(data) = resources.get(resource_name_ref) {
-                            return Ok(PyBytes::new(py, data).into_object());
+                            let data = resource_blob_data(
+                                py,
+                                entry,
+                                ResourceField::InMemoryResourcesData,
+                                Some(resource_name_ref),
+                                data,
+                                self.packed_resources_key.as_deref(),
+                            )?;
+
+                            return Ok(PyBytes::new(py, &data).into_object());
                         }
                     }
                 }
@@ -702,6 +1123,80 @@ impl<'a> PythonResourcesState<'a, u8> {
         ))
     }
 
+    /// Attempt to resolve the source code of a module given a relative path to its file.
+    ///
+    /// The path is relative to the current executable or origin, like paths
+    /// derived from `__path__` or `__file__` are. `Ok(None)` is returned if
+    /// the path doesn't refer to the source of a known module.
+    fn resolve_module_source_data_from_path(
+        &self,
+        py: Python,
+        relative_path: &Path,
+        check_in_memory: bool,
+        check_relative_path: bool,
+    ) -> PyResult<Option<PyObject>> {
+        let mut parts = relative_path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+
+        let file_name = match parts.pop() {
+            Some(file_name) if file_name.ends_with(".py") => file_name,
+            _ => return Ok(None),
+        };
+
+        if file_name != "__init__.py" {
+            parts.push(file_name[0..file_name.len() - 3].to_string());
+        }
+
+        // Hold the index while calling into Python. See `get_package_resource_file()`.
+        let index = self.resources.clone();
+
+        let entry = match index.get(parts.join(".").as_str()) {
+            Some(entry) if entry.flavor == ResourceFlavor::Module => entry,
+            _ => return Ok(None),
+        };
+
+        // Packages are only addressed by their `__init__.py`.
+        if entry.is_package != (file_name == "__init__.py") {
+            return Ok(None);
+        }
+
+        if check_in_memory {
+            if let Some(source) = &entry.in_memory_source {
+                let source = resource_blob_data(
+                    py,
+                    entry,
+                    ResourceField::InMemorySource,
+                    None,
+                    source,
+                    self.packed_resources_key.as_deref(),
+                )?;
+
+                return Ok(Some(PyBytes::new(py, &source).into_object()));
+            }
+        }
+
+        if check_relative_path {
+            if let Some(source_path) = &entry.relative_path_module_source {
+                if source_path.as_ref() == relative_path {
+                    let io_module = py.import("io")?;
+
+                    let fh = io_module.call(
+                        py,
+                        "FileIO",
+                        (path_to_pyobject(py, &self.origin.join(relative_path))?, "r"),
+                        None,
+                    )?;
+
+                    return Ok(Some(fh.call_method(py, "read", NoArgs, None)?));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
     /// Obtain a PyList of pkgutil.ModuleInfo for known resources.
     ///
     /// This is intended to be used as the implementation for Finder.iter_modules().
@@ -711,8 +1206,12 @@ impl<'a> PythonResourcesState<'a, u8> {
         prefix: Option<String>,
         optimize_level: OptimizeLevel,
     ) -> PyResult<PyObject> {
-        let infos: PyResult<Vec<PyObject>> = self
-            .resources
+        // Creating Python objects may run Python code, e.g. via the garbage
+        // collector. Hold the index so iteration isn't affected if the index is
+        // modified meanwhile.
+        let index = self.resources.clone();
+
+        let infos: PyResult<Vec<PyObject>> = index
             .values()
             .filter(|r| match r.flavor {
                 ResourceFlavor::Module => is_module_importable(r, optimize_level),
@@ -759,10 +1258,12 @@ impl<'a> PythonResourcesState<'a, u8> {
 
             // Module can be defined by embedded resources data. If exists, just
             // update the big.
-            if let Some(mut entry) = self.resources.get_mut(name_str) {
+            let resources = Arc::make_mut(&mut self.resources);
+
+            if let Some(mut entry) = resources.get_mut(name_str) {
                 entry.flavor = ResourceFlavor::BuiltinExtensionModule;
             } else {
-                self.resources.insert(
+                resources.insert(
                     Cow::Owned(name_str.to_string()),
                     Resource {
                         flavor: ResourceFlavor::BuiltinExtensionModule,
@@ -795,10 +1296,12 @@ impl<'a> PythonResourcesState<'a, u8> {
 
             // Module can be defined by embedded resources data. If exists, just
             // update the big.
-            if let Some(mut entry) = self.resources.get_mut(name_str) {
+            let resources = Arc::make_mut(&mut self.resources);
+
+            if let Some(mut entry) = resources.get_mut(name_str) {
                 entry.flavor = ResourceFlavor::FrozenModule;
             } else {
-                self.resources.insert(
+                resources.insert(
                     Cow::Owned(name_str.to_string()),
                     Resource {
                         flavor: ResourceFlavor::FrozenModule,
@@ -813,22 +1316,39 @@ impl<'a> PythonResourcesState<'a, u8> {
     }
 
     /// Load resources by parsing a blob.
-    fn load_resources(&mut self, data: &'a [u8]) -> Result<(), &'static str> {
-        let resources = python_packed_resources::parser::load_resources(data)?;
+    fn load_resources(&mut self, data: &'a [u8], key: Option<&[u8]>) -> Result<(), &'static str> {
+        // Blobs are decoded when they are accessed.
+        let resources = python_packed_resources::parser::load_resources_encoded(data, key)?;
+
+        let index = Arc::make_mut(&mut self.resources);
 
         // Reserve space for expected number of incoming items so we can avoid extra
         // allocations.
-        self.resources.reserve(resources.expected_resources_count());
+        index.reserve(resources.expected_resources_count());
 
         for resource in resources {
             let resource = resource?;
 
-            self.resources.insert(resource.name.clone(), resource);
+            index.insert(resource.name.clone(), resource);
         }
 
         Ok(())
     }
 
+    /// Obtain a copy of a resource holding decoded data.
+    ///
+    /// Resources are loaded without decoding their blobs. Consumers that
+    /// aren't aware of this, such as Python code, are given decoded copies.
+    pub fn decode_resource(
+        &self,
+        resource: &Resource<'a, u8>,
+    ) -> Result<Resource<'a, u8>, &'static str> {
+        python_packed_resources::parser::decode_resource(
+            resource.clone(),
+            self.packed_resources_key.as_deref(),
+        )
+    }
+
     /// Serialize resources contained in this data structure.
     ///
     /// `ignore_built` and `ignore_frozen` specify whether to ignore built-in
@@ -846,10 +1366,12 @@ impl<'a> PythonResourcesState<'a, u8> {
                 ResourceFlavor::FrozenModule => !ignore_frozen,
                 _ => true,
             })
-            .collect::<Vec<&Resource<u8>>>();
+            .map(|resource| self.decode_resource(resource))
+            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .map_err(|e| anyhow!("error decoding resources: {}", e))?;
 
         // Sort so behavior is deterministic.
-        resources.sort_by_key(|v| &v.name);
+        resources.sort_by(|a, b| a.name.cmp(&b.name));
 
         let mut buffer = Vec::new();
 
@@ -859,6 +1381,144 @@ impl<'a> PythonResourcesState<'a, u8> {
     }
 }
 
+/// Obtain the string name of a resource flavor.
+pub(crate) fn resource_flavor_to_str(flavor: &ResourceFlavor) -> &'static str {
+    match flavor {
+        ResourceFlavor::None => "none",
+        ResourceFlavor::Module => "module",
+        ResourceFlavor::BuiltinExtensionModule => "builtin",
+        ResourceFlavor::FrozenModule => "frozen",
+        ResourceFlavor::Extension => "extension",
+        ResourceFlavor::SharedLibrary => "shared_library",
+    }
+}
+
+/// Obtain the locations the data of a resource is loaded from.
+///
+/// Values are `builtin`, `frozen`, `in-memory` and `filesystem-relative`.
+pub(crate) fn resource_locations(resource: &Resource<u8>) -> Vec<&'static str> {
+    let mut locations = vec![];
+
+    match resource.flavor {
+        ResourceFlavor::BuiltinExtensionModule => locations.push("builtin"),
+        ResourceFlavor::FrozenModule => locations.push("frozen"),
+        _ => {}
+    }
+
+    if !resource_in_memory_data(resource).is_empty() {
+        locations.push("in-memory");
+    }
+
+    if resource.relative_path_module_source.is_some()
+        || resource.relative_path_module_bytecode.is_some()
+        || resource.relative_path_module_bytecode_opt1.is_some()
+        || resource.relative_path_module_bytecode_opt2.is_some()
+        || resource
+            .relative_path_extension_module_shared_library
+            .is_some()
+        || resource.relative_path_package_resources.is_some()
+        || resource.relative_path_distribution_resources.is_some()
+    {
+        locations.push("filesystem-relative");
+    }
+
+    locations
+}
+
+/// Obtain the in-memory data of a resource.
+///
+/// Entries are named after the field holding them. Package and distribution
+/// resources are named `<field>/<resource name>`. Entries are sorted by name.
+pub(crate) fn resource_in_memory_data<'a>(resource: &'a Resource<u8>) -> Vec<(String, &'a [u8])> {
+    let mut res = vec![];
+
+    for (field, value) in vec![
+        ("in_memory_source", &resource.in_memory_source),
+        ("in_memory_bytecode", &resource.in_memory_bytecode),
+        ("in_memory_bytecode_opt1", &resource.in_memory_bytecode_opt1),
+        ("in_memory_bytecode_opt2", &resource.in_memory_bytecode_opt2),
+        (
+            "in_memory_extension_module_shared_library",
+            &resource.in_memory_extension_module_shared_library,
+        ),
+        (
+            "in_memory_shared_library",
+            &resource.in_memory_shared_library,
+        ),
+    ] {
+        if let Some(data) = value {
+            res.push((field.to_string(), data.as_ref()));
+        }
+    }
+
+    for (field, value) in vec![
+        (
+            "in_memory_package_resources",
+            &resource.in_memory_package_resources,
+        ),
+        (
+            "in_memory_distribution_resources",
+            &resource.in_memory_distribution_resources,
+        ),
+    ] {
+        if let Some(resources) = value {
+            for (name, data) in resources.iter() {
+                res.push((format!("{}/{}", field, name), data.as_ref()));
+            }
+        }
+    }
+
+    res.sort_by(|a, b| a.0.cmp(&b.0));
+
+    res
+}
+
+/// Obtain the bytecode optimization levels a resource has bytecode for.
+pub(crate) fn resource_bytecode_optimize_levels(resource: &Resource<u8>) -> Vec<i32> {
+    let mut levels = vec![];
+
+    if resource.in_memory_bytecode.is_some() || resource.relative_path_module_bytecode.is_some() {
+        levels.push(0);
+    }
+    if resource.in_memory_bytecode_opt1.is_some()
+        || resource.relative_path_module_bytecode_opt1.is_some()
+    {
+        levels.push(1);
+    }
+    if resource.in_memory_bytecode_opt2.is_some()
+        || resource.relative_path_module_bytecode_opt2.is_some()
+    {
+        levels.push(2);
+    }
+
+    levels
+}
+
+/// Obtain the name and version of the distribution a resource came from.
+///
+/// This is derived from `distribution:<name>==<version>` and
+/// `conda:<name>==<version>` provenance values.
+pub(crate) fn resource_provenance_distribution<'a>(
+    resource: &'a Resource<u8>,
+) -> Option<(&'a str, &'a str)> {
+    let provenance = resource.provenance.as_ref()?;
+
+    let spec = if provenance.starts_with("distribution:") {
+        &provenance["distribution:".len()..]
+    } else if provenance.starts_with("conda:") {
+        &provenance["conda:".len()..]
+    } else {
+        return None;
+    };
+
+    let mut parts = spec.splitn(2, "==");
+
+    match (parts.next(), parts.next()) {
+        (Some(name), Some(version)) => Some((name, version)),
+        _ => None,
+    }
+}
+
 py_class!(pub class OxidizedResource |py| {
     data resource: RefCell<Resource<'static, u8>>;
 
@@ -873,14 +1533,7 @@ py_class!(pub class OxidizedResource |py| {
     }
 
     @property def flavor(&self) -> PyResult<&'static str> {
-        Ok(match self.resource(py).borrow().flavor {
-            ResourceFlavor::None => "none",
-            ResourceFlavor::Module => "module",
-            ResourceFlavor::BuiltinExtensionModule => "builtin",
-            ResourceFlavor::FrozenModule => "frozen",
-            ResourceFlavor::Extension => "extension",
-            ResourceFlavor::SharedLibrary => "shared_library",
-        })
+        Ok(resource_flavor_to_str(&self.resource(py).borrow().flavor))
     }
 
     @flavor.setter def set_flavor(&self, value: Option<&str>) -> PyResult<()> {
@@ -1241,6 +1894,50 @@ py_class!(pub class OxidizedResource |py| {
         }
     }
 
+    @property def provenance(&self) -> PyResult<Option<String>> {
+        Ok(self.resource(py).borrow().provenance.as_ref().map(|x| x.to_string()))
+    }
+
+    @provenance.setter def set_provenance(&self, value: Option<Option<String>>) -> PyResult<()> {
+        if let Some(value) = value {
+            self.resource(py).borrow_mut().provenance = value.map(Cow::Owned);
+
+            Ok(())
+        } else {
+            Err(PyErr::new::<TypeError, _>(py, "cannot delete provenance"))
+        }
+    }
+
+    // Read-only properties derived from other fields.
+
+    @property def locations(&self) -> PyResult<Vec<&'static str>> {
+        Ok(resource_locations(&self.resource(py).borrow()))
+    }
+
+    @property def in_memory_size(&self) -> PyResult<usize> {
+        Ok(resource_in_memory_data(&self.resource(py).borrow()).iter().map(|(_, data)| data.len()).sum())
+    }
+
+    @property def in_memory_sha256(&self) -> PyResult<PyObject> {
+        let sha256 = py.import("hashlib")?.get(py, "sha256")?;
+        let res = PyDict::new(py);
+
+        for (name, data) in resource_in_memory_data(&self.resource(py).borrow()) {
+            let digest = sha256.call(py, (PyBytes::new(py, data),), None)?.call_method(py, "hexdigest", NoArgs, None)?;
+            res.set_item(py, name, digest)?;
+        }
+
+        Ok(res.into_object())
+    }
+
+    @property def bytecode_optimize_levels(&self) -> PyResult<Vec<i32>> {
+        Ok(resource_bytecode_optimize_levels(&self.resource(py).borrow()))
+    }
+
+    @property def provenance_distribution(&self) -> PyResult<Option<(String, String)>> {
+        Ok(resource_provenance_distribution(&self.resource(py).borrow()).map(|(name, version)| (name.to_string(), version.to_string())))
+    }
+
 });
 
 /// Convert a Resource to an OxidizedResource.
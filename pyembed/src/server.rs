@@ -0,0 +1,485 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Serve WSGI and ASGI applications over HTTP.
+
+HTTP is handled in Rust by `hyper`. Each request is handed to a Python
+handler object implementing the WSGI or ASGI protocol on a blocking thread
+holding the GIL. The handler buffers the response and returns it as a
+`(status, headers, body)` tuple.
+
+ASGI applications run on an event loop in a dedicated Python thread. The
+handler submits requests to that loop, so requests to ASGI applications are
+processed concurrently.
+
+Messages of the server are emitted by the `pyembed.server` logger of the
+`logging` module.
+*/
+
+use {
+    super::config::ServerInterface,
+    cpython::exc::RuntimeError,
+    cpython::{PyDict, PyErr, PyModule, PyObject, PyResult, Python},
+};
+
+#[cfg(feature = "server")]
+use {
+    cpython::{exc::ValueError, NoArgs, ObjectProtocol, PyBytes, PythonObject},
+    hyper::body::HttpBody,
+    hyper::header::CONTENT_LENGTH,
+    hyper::server::conn::AddrStream,
+    hyper::service::{make_service_fn, service_fn},
+    hyper::{Body, Request, Response, Server, StatusCode},
+    python3_sys as pyffi,
+    std::convert::Infallible,
+    std::net::SocketAddr,
+    std::sync::Arc,
+};
+
+/// Python code implementing the WSGI and ASGI protocols.
+const SERVER_SOURCE: &str = r#"
+import asyncio
+import importlib
+import io
+import sys
+import threading
+import urllib.parse
+
+
+def load_application(spec):
+    module_name, _, attrs = spec.partition(':')
+    app = importlib.import_module(module_name)
+    for attr in (attrs or 'application').split('.'):
+        app = getattr(app, attr)
+
+    return app
+
+
+class WsgiHandler:
+    def __init__(self, app, host, port):
+        self.app = app
+        self.host = host
+        self.port = str(port)
+
+    def startup(self):
+        pass
+
+    def shutdown(self):
+        pass
+
+    def __call__(self, method, path, query, headers, body, client_host, client_port):
+        environ = {
+            'REQUEST_METHOD': method,
+            'SCRIPT_NAME': '',
+            'PATH_INFO': urllib.parse.unquote(path, 'latin-1'),
+            'QUERY_STRING': query,
+            'SERVER_NAME': self.host,
+            'SERVER_PORT': self.port,
+            'SERVER_PROTOCOL': 'HTTP/1.1',
+            'REMOTE_ADDR': client_host,
+            'REMOTE_PORT': str(client_port),
+            'wsgi.version': (1, 0),
+            'wsgi.url_scheme': 'http',
+            'wsgi.input': io.BytesIO(body),
+            'wsgi.errors': sys.stderr,
+            'wsgi.multithread': True,
+            'wsgi.multiprocess': False,
+            'wsgi.run_once': False,
+        }
+
+        for name, value in headers:
+            key = name.upper().replace('-', '_')
+            if key not in ('CONTENT_TYPE', 'CONTENT_LENGTH'):
+                key = 'HTTP_' + key
+            value = value.decode('latin-1')
+            if key in environ:
+                value = environ[key] + ',' + value
+            environ[key] = value
+
+        response = []
+        chunks = []
+
+        # The response is buffered, so nothing has been sent when an
+        # application calls start_response() again with exc_info.
+        def start_response(status, response_headers, exc_info=None):
+            if response and not exc_info:
+                raise RuntimeError('start_response() already called')
+            response[:] = [status, response_headers]
+            return chunks.append
+
+        result = self.app(environ, start_response)
+        try:
+            for chunk in result:
+                chunks.append(chunk)
+        finally:
+            if hasattr(result, 'close'):
+                result.close()
+
+        if not response:
+            raise RuntimeError('application did not call start_response()')
+
+        status, response_headers = response
+        return (
+            int(status.split(' ', 1)[0]),
+            [(name, value.encode('latin-1')) for name, value in response_headers],
+            b''.join(chunks),
+        )
+
+
+class AsgiLifespan:
+    def __init__(self, app):
+        self.app = app
+        self.task = None
+
+    async def startup(self):
+        self.receive_queue = asyncio.Queue()
+        self.send_queue = asyncio.Queue()
+        self.task = asyncio.ensure_future(self.run())
+
+        await self.receive_queue.put({'type': 'lifespan.startup'})
+        message = await self.send_queue.get()
+        if message['type'] == 'lifespan.startup.failed':
+            raise RuntimeError('application startup failed: %s' % message.get('message', ''))
+
+    async def shutdown(self):
+        if self.task is None or self.task.done():
+            return
+
+        await self.receive_queue.put({'type': 'lifespan.shutdown'})
+        message = await self.send_queue.get()
+        if message['type'] == 'lifespan.shutdown.failed':
+            print('application shutdown failed: %s' % message.get('message', ''), file=sys.stderr)
+
+    async def run(self):
+        scope = {'type': 'lifespan', 'asgi': {'version': '3.0', 'spec_version': '2.0'}}
+        try:
+            await self.app(scope, self.receive_queue.get, self.send_queue.put)
+        except Exception:
+            # Applications not supporting the lifespan protocol raise.
+            pass
+        finally:
+            self.send_queue.put_nowait({'type': 'lifespan.ended'})
+
+
+class AsgiHandler:
+    def __init__(self, app, host, port):
+        self.app = app
+        self.server = (host, port)
+        self.lifespan = AsgiLifespan(app)
+        self.loop = asyncio.new_event_loop()
+        self.thread = threading.Thread(target=self.loop.run_forever, name='asgi', daemon=True)
+        self.thread.start()
+
+    def startup(self):
+        asyncio.run_coroutine_threadsafe(self.lifespan.startup(), self.loop).result()
+
+    def shutdown(self):
+        try:
+            asyncio.run_coroutine_threadsafe(self.lifespan.shutdown(), self.loop).result()
+        finally:
+            self.loop.call_soon_threadsafe(self.loop.stop)
+            self.thread.join()
+            self.loop.close()
+
+    def __call__(self, method, path, query, headers, body, client_host, client_port):
+        scope = {
+            'type': 'http',
+            'asgi': {'version': '3.0', 'spec_version': '2.1'},
+            'http_version': '1.1',
+            'method': method,
+            'scheme': 'http',
+            'path': urllib.parse.unquote(path),
+            'raw_path': path.encode('latin-1'),
+            'query_string': query.encode('latin-1'),
+            'root_path': '',
+            'headers': [(name.encode('latin-1'), value) for name, value in headers],
+            'client': (client_host, client_port),
+            'server': self.server,
+        }
+
+        return asyncio.run_coroutine_threadsafe(self.request(scope, body), self.loop).result()
+
+    async def request(self, scope, body):
+        complete = asyncio.Event()
+        received = False
+        response = {'status': None, 'headers': []}
+        chunks = []
+
+        async def receive():
+            nonlocal received
+            if not received:
+                received = True
+                return {'type': 'http.request', 'body': body, 'more_body': False}
+
+            await complete.wait()
+            return {'type': 'http.disconnect'}
+
+        async def send(message):
+            if message['type'] == 'http.response.start':
+                response['status'] = message['status']
+                response['headers'] = [
+                    (bytes(name).decode('latin-1'), bytes(value))
+                    for name, value in message.get('headers', [])
+                ]
+            elif message['type'] == 'http.response.body':
+                chunks.append(bytes(message.get('body', b'')))
+                if not message.get('more_body', False):
+                    complete.set()
+
+        try:
+            await self.app(scope, receive, send)
+        finally:
+            complete.set()
+
+        if response['status'] is None:
+            raise RuntimeError('application did not send a response')
+
+        return response['status'], response['headers'], b''.join(chunks)
+"#;
+
+/// Create a module holding the server support code.
+fn server_module(py: Python) -> PyResult<PyModule> {
+    let module = PyModule::new(py, "_pyembed_server")?;
+    let globals: PyDict = module.dict(py);
+    globals.set_item(py, "__builtins__", py.import("builtins")?)?;
+    py.run(SERVER_SOURCE, Some(&globals), None)?;
+
+    Ok(module)
+}
+
+/// Create a handler for requests to an application.
+///
+/// The handler is called with the request method, path, query string,
+/// headers as a list of `(name, value)` tuples with `bytes` values, body,
+/// client host and client port. It returns a `(status, headers, body)`
+/// tuple describing the response.
+///
+/// `application` names the application object as `module:attribute`.
+/// The handler's `startup()` method must be called before it handles
+/// requests and `shutdown()` when it will handle no more.
+pub(crate) fn create_handler(
+    py: Python,
+    application: &str,
+    interface: ServerInterface,
+    host: &str,
+    port: u16,
+) -> PyResult<PyObject> {
+    let module = server_module(py)?;
+    let app = module.call(py, "load_application", (application,), None)?;
+
+    let factory = match interface {
+        ServerInterface::Wsgi => "WsgiHandler",
+        ServerInterface::Asgi => "AsgiHandler",
+    };
+
+    module.call(py, factory, (app, host, port), None)
+}
+
+/// Serve an application over HTTP until the process is interrupted.
+#[cfg(feature = "server")]
+pub(crate) fn run_server(
+    py: Python,
+    application: &str,
+    interface: ServerInterface,
+    address: &str,
+    max_request_body_size: usize,
+) -> PyResult<PyObject> {
+    let addr: SocketAddr = address.parse().map_err(|e| {
+        PyErr::new::<ValueError, _>(py, format!("invalid server address {}: {}", address, e))
+    })?;
+
+    let handler = create_handler(
+        py,
+        application,
+        interface,
+        &addr.ip().to_string(),
+        addr.port(),
+    )?;
+    handler.call_method(py, "startup", NoArgs, None)?;
+
+    let handler = Arc::new(handler);
+    let res = py.allow_threads(|| serve(addr, handler.clone(), max_request_body_size));
+
+    // Python's signal handler also saw the Ctrl+C stopping the server. Don't
+    // let the pending KeyboardInterrupt surface in code run afterwards.
+    if unsafe { pyffi::PyErr_CheckSignals() } != 0 {
+        PyErr::fetch(py);
+    }
+
+    handler.call_method(py, "shutdown", NoArgs, None)?;
+    res.map_err(|msg| PyErr::new::<RuntimeError, _>(py, msg))?;
+
+    Ok(py.None())
+}
+
+#[cfg(not(feature = "server"))]
+pub(crate) fn run_server(
+    py: Python,
+    _application: &str,
+    _interface: ServerInterface,
+    _address: &str,
+    _max_request_body_size: usize,
+) -> PyResult<PyObject> {
+    Err(PyErr::new::<RuntimeError, _>(
+        py,
+        "server is not available in this build configuration",
+    ))
+}
+
+#[cfg(feature = "server")]
+fn serve(
+    addr: SocketAddr,
+    handler: Arc<PyObject>,
+    max_request_body_size: usize,
+) -> Result<(), String> {
+    let mut runtime = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+
+    runtime.block_on(async move {
+        let make_service = make_service_fn(move |conn: &AddrStream| {
+            let handler = handler.clone();
+            let remote = conn.remote_addr();
+
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| {
+                    handle_request(handler.clone(), remote, req, max_request_body_size)
+                }))
+            }
+        });
+
+        let server = Server::try_bind(&addr)
+            .map_err(|e| format!("unable to listen on {}: {}", addr, e))?
+            .serve(make_service);
+
+        {
+            let gil = Python::acquire_gil();
+            log(gil.python(), "info", &format!("serving on http://{}", addr));
+        }
+
+        server
+            .with_graceful_shutdown(async {
+                tokio::signal::ctrl_c().await.ok();
+            })
+            .await
+            .map_err(|e| e.to_string())
+    })
+}
+
+/// Emit a message with the `pyembed.server` logger at the given level.
+#[cfg(feature = "server")]
+fn log(py: Python, level: &str, message: &str) {
+    let res = py
+        .import("logging")
+        .and_then(|logging| logging.call(py, "getLogger", ("pyembed.server",), None))
+        .and_then(|logger| logger.call_method(py, level, (message,), None));
+
+    if let Err(err) = res {
+        err.print(py);
+    }
+}
+
+#[cfg(feature = "server")]
+fn status_response(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::from(status.to_string()));
+    *response.status_mut() = status;
+
+    response
+}
+
+#[cfg(feature = "server")]
+async fn handle_request(
+    handler: Arc<PyObject>,
+    remote: SocketAddr,
+    req: Request<Body>,
+    max_request_body_size: usize,
+) -> Result<Response<Body>, Infallible> {
+    let (parts, mut body) = req.into_parts();
+
+    // Reject bodies we won't accept before reading them. The length isn't
+    // trusted though: the body is capped as it is read.
+    if let Some(length) = parts.headers.get(CONTENT_LENGTH) {
+        match length.to_str().ok().and_then(|v| v.parse::<u64>().ok()) {
+            Some(length) if length > max_request_body_size as u64 => {
+                return Ok(status_response(StatusCode::PAYLOAD_TOO_LARGE));
+            }
+            Some(_) => {}
+            None => return Ok(status_response(StatusCode::BAD_REQUEST)),
+        }
+    }
+
+    let mut data = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(_) => return Ok(status_response(StatusCode::BAD_REQUEST)),
+        };
+
+        if data.len() + chunk.len() > max_request_body_size {
+            return Ok(status_response(StatusCode::PAYLOAD_TOO_LARGE));
+        }
+
+        data.extend_from_slice(&chunk);
+    }
+
+    let res = tokio::task::spawn_blocking(move || {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+
+        let headers = parts
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.as_str().to_string(),
+                    PyBytes::new(py, value.as_bytes()).into_object(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let res = handler
+            .call(
+                py,
+                (
+                    parts.method.as_str(),
+                    parts.uri.path(),
+                    parts.uri.query().unwrap_or(""),
+                    headers,
+                    PyBytes::new(py, &data),
+                    remote.ip().to_string(),
+                    remote.port(),
+                ),
+                None,
+            )
+            .and_then(|res| res.extract::<(u16, Vec<(String, PyBytes)>, PyBytes)>(py));
+
+        match res {
+            Ok((status, headers, body)) => {
+                let mut builder = Response::builder().status(status);
+                for (name, value) in headers {
+                    builder = builder.header(name.as_str(), value.data(py));
+                }
+
+                builder
+                    .body(Body::from(body.data(py).to_vec()))
+                    .map_err(|e| {
+                        log(
+                            py,
+                            "error",
+                            &format!("invalid response from application: {}", e),
+                        );
+                    })
+            }
+            Err(err) => {
+                err.print(py);
+                Err(())
+            }
+        }
+    })
+    .await;
+
+    Ok(match res {
+        Ok(Ok(response)) => response,
+        _ => status_response(StatusCode::INTERNAL_SERVER_ERROR),
+    })
+}
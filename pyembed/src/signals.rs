@@ -0,0 +1,134 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Deliver interrupts from the host application to the Python interpreter.
+
+use {
+    lazy_static::lazy_static,
+    libc::{c_int, c_void},
+    python3_sys as pyffi,
+    std::sync::Mutex,
+};
+
+#[cfg(unix)]
+use std::{cell::RefCell, sync::MutexGuard, sync::Once};
+
+/// Tracks which interpreter, if any, can receive interrupts.
+#[derive(Default)]
+struct InterruptState {
+    /// Generation of the most recently started interpreter.
+    generation: u64,
+
+    /// Whether the interpreter of `generation` is running.
+    running: bool,
+}
+
+lazy_static! {
+    /// Serializes scheduling interrupts against interpreter finalization.
+    ///
+    /// Python frees the state backing pending calls when it is finalized.
+    /// So an interrupt must never be scheduled once finalization begins.
+    static ref INTERRUPT_STATE: Mutex<InterruptState> = Mutex::new(InterruptState::default());
+}
+
+#[cfg(unix)]
+thread_local! {
+    /// Lock on `INTERRUPT_STATE` held by a thread calling `fork()`.
+    static FORK_GUARD: RefCell<Option<MutexGuard<'static, InterruptState>>> = RefCell::new(None);
+}
+
+#[cfg(unix)]
+static REGISTER_FORK_HANDLERS: Once = Once::new();
+
+/// Acquire the interrupt state lock before forking.
+///
+/// Another thread may be scheduling an interrupt while the process forks.
+/// The child would then inherit the lock in its locked state and deadlock
+/// when its interpreter is finalized. Holding the lock across `fork()`
+/// guarantees both processes start with it released.
+#[cfg(unix)]
+extern "C" fn prepare_fork() {
+    let guard = INTERRUPT_STATE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    FORK_GUARD.with(|fork_guard| *fork_guard.borrow_mut() = Some(guard));
+}
+
+/// Release the lock acquired by `prepare_fork()` in the parent and child.
+#[cfg(unix)]
+extern "C" fn after_fork() {
+    FORK_GUARD.with(|fork_guard| fork_guard.borrow_mut().take());
+}
+
+/// Record that a new interpreter was initialized, returning its generation.
+pub(crate) fn interpreter_started() -> u64 {
+    #[cfg(unix)]
+    REGISTER_FORK_HANDLERS.call_once(|| unsafe {
+        libc::pthread_atfork(Some(prepare_fork), Some(after_fork), Some(after_fork));
+    });
+
+    let mut state = INTERRUPT_STATE.lock().unwrap();
+    state.generation += 1;
+    state.running = true;
+
+    state.generation
+}
+
+/// Record that the running interpreter is about to be finalized.
+///
+/// Waits for an interrupt being scheduled concurrently to finish.
+pub(crate) fn interpreter_stopping() {
+    INTERRUPT_STATE.lock().unwrap().running = false;
+}
+
+extern "C" fn raise_keyboard_interrupt(_arg: *mut c_void) -> c_int {
+    unsafe {
+        pyffi::PyErr_SetNone(pyffi::PyExc_KeyboardInterrupt);
+    }
+
+    -1
+}
+
+/// Raises `KeyboardInterrupt` in a running Python interpreter.
+///
+/// Instances are obtained from
+/// [`MainPythonInterpreter::interrupt_handle()`](struct.MainPythonInterpreter.html#method.interrupt_handle)
+/// and can be sent to other threads. This allows a host application owning
+/// signal handling, e.g. after disabling Python's signal handlers via
+/// `install_signal_handlers`, to forward `SIGINT`, `SIGTERM`, or any other
+/// event into Python.
+///
+/// `interrupt()` acquires a lock. So it must not be called from a signal
+/// handler. Forward signals from a thread waiting for them instead.
+#[derive(Clone, Copy, Debug)]
+pub struct InterruptHandle {
+    generation: u64,
+}
+
+impl InterruptHandle {
+    pub(crate) fn new(generation: u64) -> Self {
+        Self { generation }
+    }
+
+    /// Raise `KeyboardInterrupt` in the main thread of the interpreter.
+    ///
+    /// The exception is raised the next time the main thread evaluates
+    /// Python code. This works whether or not Python installed its own
+    /// signal handlers.
+    ///
+    /// Returns whether the interrupt was scheduled. It isn't if the
+    /// interpreter this handle belongs to is no longer running.
+    pub fn interrupt(&self) -> bool {
+        let state = INTERRUPT_STATE.lock().unwrap();
+
+        if !state.running || state.generation != self.generation {
+            return false;
+        }
+
+        unsafe {
+            pyffi::Py_AddPendingCall(Some(raise_keyboard_interrupt), std::ptr::null_mut()) == 0
+        }
+    }
+}
@@ -0,0 +1,164 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Layer a writable site-packages directory on top of packed resources.
+
+use {
+    super::config::SitePackagesOverlayPriority,
+    cpython::{ObjectProtocol, PyDict, PyResult, Python},
+};
+
+/// Python source installing a site-packages overlay.
+///
+/// The directory is tagged with the Python version and platform it was
+/// created for. Packages installed into it may contain bytecode and
+/// extension modules only working with them. So a directory created for
+/// something else is ignored.
+const SITE_PACKAGES_OVERLAY_SOURCE: &str = r#"
+import importlib.machinery
+import os
+import sys
+import warnings
+
+TAG_FILENAME = '.pyembed-compatibility'
+
+
+def compatibility_tag():
+    return '%s-%s-%dbit' % (
+        sys.implementation.cache_tag,
+        sys.platform,
+        64 if sys.maxsize > 2 ** 32 else 32,
+    )
+
+
+class SitePackagesOverlayFinder:
+    """Meta path finder for modules in a site-packages overlay.
+
+    Only finds top-level modules in the directory and submodules of
+    packages found in it.
+    """
+
+    def __init__(self, path, namespace_packages):
+        self.path = path
+        self.namespace_packages = namespace_packages
+        self._prefix = os.path.join(os.path.normcase(path), '')
+
+    def _contains(self, path):
+        return os.path.join(os.path.normcase(os.path.abspath(path)), '').startswith(
+            self._prefix)
+
+    def find_spec(self, fullname, path=None, target=None):
+        if path is None:
+            path = [self.path]
+        else:
+            path = [p for p in path if self._contains(p)]
+            if not path:
+                return None
+
+        spec = importlib.machinery.PathFinder.find_spec(fullname, path, target)
+
+        # Namespace package portions would hide regular packages found by
+        # finders after this one.
+        if spec is not None and spec.loader is None and not self.namespace_packages:
+            return None
+
+        return spec
+
+    def find_distributions(self, context=None):
+        # PathFinder finds distributions on sys.path, which the directory
+        # is part of. And only one of the finders for a directory should
+        # report them.
+        if (not self.namespace_packages
+                or importlib.machinery.PathFinder in sys.meta_path):
+            return iter(())
+
+        from importlib.metadata import DistributionFinder
+
+        if context is None:
+            context = DistributionFinder.Context()
+
+        context = DistributionFinder.Context(name=context.name, path=[self.path])
+
+        return importlib.machinery.PathFinder.find_distributions(context)
+
+    def invalidate_caches(self):
+        importlib.machinery.PathFinder.invalidate_caches()
+
+
+def install(path, before):
+    path = os.path.abspath(os.path.expandvars(os.path.expanduser(path)))
+    tag_path = os.path.join(path, TAG_FILENAME)
+    tag = compatibility_tag()
+
+    try:
+        with open(tag_path, 'r', encoding='utf-8') as fh:
+            existing_tag = fh.read().strip()
+    except FileNotFoundError:
+        existing_tag = None
+    except OSError as e:
+        warnings.warn('ignoring site-packages overlay %s: %s' % (path, e), RuntimeWarning)
+        return
+
+    if existing_tag is None:
+        try:
+            os.makedirs(path, exist_ok=True)
+            with open(tag_path, 'w', encoding='utf-8') as fh:
+                fh.write(tag + '\n')
+        except OSError as e:
+            warnings.warn('unable to create site-packages overlay %s: %s' % (path, e),
+                          RuntimeWarning)
+            return
+    elif existing_tag != tag:
+        warnings.warn('ignoring site-packages overlay %s: it was created for %s, not %s'
+                      % (path, existing_tag, tag), RuntimeWarning)
+        return
+
+    index = None
+    for i, finder in enumerate(sys.meta_path):
+        if type(finder).__name__ == 'OxidizedFinder':
+            index = i if before else i + 1
+            break
+
+    if index is None:
+        if importlib.machinery.PathFinder in sys.meta_path:
+            index = sys.meta_path.index(importlib.machinery.PathFinder)
+        else:
+            index = len(sys.meta_path)
+
+    sys.meta_path.insert(index, SitePackagesOverlayFinder(path, not before))
+
+    if before:
+        # Namespace packages in the directory are found after everything else.
+        sys.meta_path.append(SitePackagesOverlayFinder(path, True))
+        sys.path.insert(0, path)
+    else:
+        sys.path.append(path)
+"#;
+
+/// Layer a site-packages overlay at `path` on the import path.
+///
+/// Warnings are emitted instead of errors if the directory can't be used,
+/// as applications should keep working without the overlay.
+pub(crate) fn install_site_packages_overlay(
+    py: Python,
+    path: &str,
+    priority: SitePackagesOverlayPriority,
+) -> PyResult<()> {
+    let globals = PyDict::new(py);
+    globals.set_item(py, "__builtins__", py.import("builtins")?)?;
+    globals.set_item(py, "__name__", "_pyembed_site_packages")?;
+
+    py.run(SITE_PACKAGES_OVERLAY_SOURCE, Some(&globals), None)?;
+
+    let install = globals
+        .get_item(py, "install")
+        .expect("install should be defined");
+    install.call(
+        py,
+        (path, priority == SitePackagesOverlayPriority::Before),
+        None,
+    )?;
+
+    Ok(())
+}
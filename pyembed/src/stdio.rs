@@ -0,0 +1,207 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Capture output written to the standard streams.
+
+use {libc::c_int, std::io::Write, std::thread::JoinHandle};
+
+/// A standard output stream of the process.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StandardStream {
+    Stdout,
+    Stderr,
+}
+
+impl StandardStream {
+    /// The file descriptor of the stream.
+    pub(crate) fn fd(self) -> c_int {
+        match self {
+            StandardStream::Stdout => 1,
+            StandardStream::Stderr => 2,
+        }
+    }
+
+    /// Name of the `sys` module attribute holding the Python stream.
+    pub(crate) fn sys_attr(self) -> &'static str {
+        match self {
+            StandardStream::Stdout => "stdout",
+            StandardStream::Stderr => "stderr",
+        }
+    }
+
+    /// Name of the `sys` module attribute holding the original Python stream.
+    pub(crate) fn sys_original_attr(self) -> &'static str {
+        match self {
+            StandardStream::Stdout => "__stdout__",
+            StandardStream::Stderr => "__stderr__",
+        }
+    }
+}
+
+/// A `Write` implementation passing written data to a callback.
+///
+/// This allows receiving captured output without defining a type.
+pub struct CallbackWriter<F: FnMut(&[u8]) + Send> {
+    callback: F,
+}
+
+impl<F: FnMut(&[u8]) + Send> CallbackWriter<F> {
+    pub fn new(callback: F) -> Self {
+        Self { callback }
+    }
+}
+
+impl<F: FnMut(&[u8]) + Send> Write for CallbackWriter<F> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        (self.callback)(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn create_pipe() -> Result<(c_int, c_int), String> {
+    let mut fds: [c_int; 2] = [0; 2];
+
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        Err(format!(
+            "unable to create pipe: {}",
+            std::io::Error::last_os_error()
+        ))
+    } else {
+        Ok((fds[0], fds[1]))
+    }
+}
+
+#[cfg(windows)]
+fn create_pipe() -> Result<(c_int, c_int), String> {
+    let mut fds: [c_int; 2] = [0; 2];
+
+    if unsafe { libc::pipe(fds.as_mut_ptr(), 65536, libc::O_BINARY) } != 0 {
+        Err(format!(
+            "unable to create pipe: {}",
+            std::io::Error::last_os_error()
+        ))
+    } else {
+        Ok((fds[0], fds[1]))
+    }
+}
+
+/// Redirects the file descriptor of a standard stream into a `Write`.
+///
+/// The file descriptor is pointed at a pipe which a background thread drains
+/// into the writer. Dropping the instance restores the original file
+/// descriptor and waits for the thread to deliver the remaining output.
+///
+/// Processes using the Windows GUI subsystem may start without a valid
+/// file descriptor for the stream. It is created on capture and closed
+/// again when capturing ends.
+pub(crate) struct OutputCapture {
+    stream: StandardStream,
+    /// Duplicate of the original file descriptor. -1 if there was none.
+    original_fd: c_int,
+    reader: Option<JoinHandle<()>>,
+}
+
+impl OutputCapture {
+    pub(crate) fn new(
+        stream: StandardStream,
+        mut writer: Box<dyn Write + Send>,
+    ) -> Result<Self, String> {
+        let fd = stream.fd();
+        let (read_fd, write_fd) = create_pipe()?;
+
+        let original_fd = unsafe {
+            // Don't let output buffered by C stdio before now get captured.
+            libc::fflush(std::ptr::null_mut());
+
+            // A missing file descriptor has nothing to restore. So it isn't
+            // an error.
+            let original_fd = libc::dup(fd);
+            let err = std::io::Error::last_os_error();
+            if original_fd < 0 && err.raw_os_error() != Some(libc::EBADF) {
+                libc::close(read_fd);
+                libc::close(write_fd);
+                return Err(format!(
+                    "unable to duplicate file descriptor {}: {}",
+                    fd, err
+                ));
+            }
+
+            if libc::dup2(write_fd, fd) < 0 {
+                let err = std::io::Error::last_os_error();
+                if original_fd >= 0 {
+                    libc::close(original_fd);
+                }
+                libc::close(read_fd);
+                libc::close(write_fd);
+                return Err(format!(
+                    "unable to redirect file descriptor {}: {}",
+                    fd, err
+                ));
+            }
+
+            libc::close(write_fd);
+
+            original_fd
+        };
+
+        let reader = std::thread::spawn(move || {
+            let mut buffer = [0u8; 8192];
+
+            loop {
+                let count = unsafe {
+                    libc::read(read_fd, buffer.as_mut_ptr() as *mut _, buffer.len() as _)
+                };
+                if count <= 0 {
+                    break;
+                }
+
+                // Keep draining the pipe if the writer fails so writes to the
+                // file descriptor never block on a full pipe.
+                if writer.write_all(&buffer[..count as usize]).is_ok() {
+                    let _ = writer.flush();
+                }
+            }
+
+            unsafe {
+                libc::close(read_fd);
+            }
+        });
+
+        Ok(Self {
+            stream,
+            original_fd,
+            reader: Some(reader),
+        })
+    }
+
+    pub(crate) fn stream(&self) -> StandardStream {
+        self.stream
+    }
+}
+
+impl Drop for OutputCapture {
+    fn drop(&mut self) {
+        unsafe {
+            libc::fflush(std::ptr::null_mut());
+
+            // This closes the last write end of the pipe. So the reader sees
+            // end of file once it has delivered everything written.
+            if self.original_fd >= 0 {
+                libc::dup2(self.original_fd, self.stream.fd());
+                libc::close(self.original_fd);
+            } else {
+                libc::close(self.stream.fd());
+            }
+        }
+
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+    }
+}
@@ -0,0 +1,94 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+"""Benchmark importing modules from OxidizedFinder from multiple threads.
+
+Each thread imports its own set of modules, so the only shared state is the
+finder and the interpreter. Compare the total time across thread counts to
+see how much imports serialize beyond the GIL and CPython's import locks.
+
+This is a manual benchmark and isn't run by the test suite. Run it with a
+Python interpreter having ``oxidized_importer`` installed, e.g.
+``python3 bench_importer_threads.py``.
+"""
+
+import importlib
+import marshal
+import sys
+import threading
+import time
+
+from oxidized_importer import OxidizedFinder, OxidizedResource
+
+MODULES_PER_THREAD = 500
+THREAD_COUNTS = (1, 2, 4, 8)
+
+
+def make_finder(count, lazy):
+    finder = OxidizedFinder(lazy_modules=["bench"] if lazy else None)
+
+    resource = OxidizedResource()
+    resource.name = "bench"
+    resource.flavor = "module"
+    resource.is_package = True
+    resource.in_memory_source = b""
+    finder.add_resource(resource)
+
+    for i in range(count):
+        resource = OxidizedResource()
+        resource.name = "bench.module_%d" % i
+        resource.flavor = "module"
+        resource.in_memory_bytecode = marshal.dumps(
+            compile("value = %d\n" % i, "module_%d.py" % i, "exec")
+        )
+        finder.add_resource(resource)
+
+    return finder
+
+
+def run(thread_count, lazy):
+    finder = make_finder(MODULES_PER_THREAD * thread_count, lazy)
+    sys.meta_path.insert(0, finder)
+
+    def worker(offset):
+        for i in range(offset, offset + MODULES_PER_THREAD):
+            m = importlib.import_module("bench.module_%d" % i)
+            # Force execution of lazy modules.
+            m.value
+
+    threads = [
+        threading.Thread(target=worker, args=(i * MODULES_PER_THREAD,))
+        for i in range(thread_count)
+    ]
+
+    try:
+        start = time.perf_counter()
+        for t in threads:
+            t.start()
+        for t in threads:
+            t.join()
+        elapsed = time.perf_counter() - start
+    finally:
+        sys.meta_path.remove(finder)
+        for name in list(sys.modules):
+            if name == "bench" or name.startswith("bench."):
+                del sys.modules[name]
+
+    imports = MODULES_PER_THREAD * thread_count
+    print(
+        "%s, %d threads: %d imports in %.3fs (%.1f us/import)"
+        % (
+            "lazy" if lazy else "eager",
+            thread_count,
+            imports,
+            elapsed,
+            elapsed / imports * 1e6,
+        )
+    )
+
+
+if __name__ == "__main__":
+    for lazy in (False, True):
+        for thread_count in THREAD_COUNTS:
+            run(thread_count, lazy)
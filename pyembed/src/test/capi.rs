@@ -0,0 +1,90 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::capi::*,
+    std::ffi::{CStr, CString},
+};
+
+#[test]
+fn test_interpreter_run_code() {
+    unsafe {
+        let config = pyembed_config_new();
+        assert_eq!(pyembed_config_set_oxidized_importer(config, 1), 0);
+
+        let mut error = std::ptr::null_mut();
+        let interp = pyembed_interpreter_new(config, &mut error);
+        pyembed_config_free(config);
+        assert!(!interp.is_null());
+        assert!(error.is_null());
+
+        let code = CString::new("import sys; value = 42").unwrap();
+        assert_eq!(
+            pyembed_interpreter_run_code(interp, code.as_ptr(), &mut error),
+            0
+        );
+
+        let code = CString::new("assert value == 42").unwrap();
+        assert_eq!(
+            pyembed_interpreter_run_code(interp, code.as_ptr(), &mut error),
+            0
+        );
+
+        let code = CString::new("raise ValueError('failure')").unwrap();
+        assert_eq!(
+            pyembed_interpreter_run_code(interp, code.as_ptr(), &mut error),
+            1
+        );
+
+        let code = CString::new("raise SystemExit(3)").unwrap();
+        assert_eq!(
+            pyembed_interpreter_run_code(interp, code.as_ptr(), &mut error),
+            3
+        );
+        assert!(error.is_null());
+
+        let handle = pyembed_interpreter_interrupt_handle(interp);
+        assert!(!handle.is_null());
+
+        pyembed_interpreter_free(interp);
+
+        assert_eq!(pyembed_interrupt_handle_interrupt(handle), -1);
+        pyembed_interrupt_handle_free(handle);
+    }
+}
+
+#[test]
+fn test_config_errors() {
+    unsafe {
+        assert_eq!(pyembed_config_set_run_repl(std::ptr::null_mut()), -1);
+
+        let config = pyembed_config_new();
+        assert_eq!(pyembed_config_set_run_module(config, std::ptr::null()), -1);
+        assert_eq!(
+            pyembed_config_set_packed_resources(config, std::ptr::null(), 1),
+            -1
+        );
+        pyembed_config_free(config);
+
+        let mut error = std::ptr::null_mut();
+        let interp = pyembed_interpreter_new(std::ptr::null(), &mut error);
+        assert!(interp.is_null());
+        assert_eq!(CStr::from_ptr(error).to_str().unwrap(), "config is NULL");
+        pyembed_string_free(error);
+
+        let mut error = std::ptr::null_mut();
+        let code = CString::new("pass").unwrap();
+        assert_eq!(
+            pyembed_interpreter_run_code(std::ptr::null_mut(), code.as_ptr(), &mut error),
+            -1
+        );
+        assert_eq!(
+            CStr::from_ptr(error).to_str().unwrap(),
+            "interpreter is NULL"
+        );
+        pyembed_string_free(error);
+
+        assert_eq!(pyembed_interpreter_run_as_main(std::ptr::null_mut()), -1);
+    }
+}
@@ -156,3 +156,19 @@ fn importer_module_loading_py() -> Result<()> {
 fn importer_resource_reading_py() -> Result<()> {
     run_py_test("test_importer_resource_reading.py")
 }
+
+/// Run test_importer_pkg_resources.py.
+#[test]
+fn importer_pkg_resources_py() -> Result<()> {
+    run_py_test("test_importer_pkg_resources.py")
+}
+
+/// Run bench_importer_threads.py.
+///
+/// This is a benchmark, not a test. Run it with
+/// `cargo test bench_importer_threads_py -- --ignored --nocapture`.
+#[test]
+#[ignore]
+fn bench_importer_threads_py() -> Result<()> {
+    run_py_test("bench_importer_threads.py")
+}
@@ -3,11 +3,41 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
-    crate::{MainPythonInterpreter, OxidizedPythonInterpreterConfig, PythonInterpreterProfile},
+    crate::interpreter_config::{
+        eval_many_code, jupyter_kernel_code, main_module_code, multiprocessing_worker_code,
+    },
+    crate::server::create_handler,
+    crate::{
+        run, CallbackWriter, MainPythonInterpreter, MultiprocessingStartMethod,
+        OxidizedPythonInterpreterConfig, PythonInterpreterProfile, PythonRunMode, ServerInterface,
+        SitePackagesOverlay, SitePackagesOverlayPriority, StandardStream, StdioRedirect,
+    },
     anyhow::Result,
-    cpython::ObjectProtocol,
+    cpython::{
+        exc::KeyboardInterrupt, py_module_initializer, NoArgs, ObjectProtocol, PyBytes, PyObject,
+        Python, PythonObject,
+    },
+    python_packed_resources::{
+        data::{Resource, ResourceFlavor},
+        writer::{
+            packed_resources_public_key, sign_packed_resources, write_packed_resources_v1,
+            write_packed_resources_v1_with_options, WriteOptions,
+        },
+    },
+    std::borrow::Cow,
+    std::ffi::OsString,
+    std::sync::{Arc, Mutex},
+    tempdir::TempDir,
 };
 
+/// Create a temporary directory for files used by a test.
+///
+/// The directory is deleted when the returned value is dropped, even if the
+/// test fails.
+fn test_dir(name: &str) -> Result<TempDir> {
+    Ok(TempDir::new(&format!("pyembed-test-{}", name))?)
+}
+
 #[test]
 fn test_default_interpreter() -> Result<()> {
     let config = OxidizedPythonInterpreterConfig::default();
@@ -56,3 +86,1101 @@ fn test_isolated_interpreter() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_multiprocessing_start_method() -> Result<()> {
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config.multiprocessing_start_method = Some(MultiprocessingStartMethod::Spawn);
+
+    let mut interp = MainPythonInterpreter::new(config)?;
+
+    let py = interp.acquire_gil().unwrap();
+    let multiprocessing = py.import("multiprocessing").unwrap();
+    let method = multiprocessing
+        .call(py, "get_start_method", NoArgs, None)
+        .unwrap();
+    assert_eq!(method.extract::<String>(py).unwrap(), "spawn");
+
+    Ok(())
+}
+
+#[test]
+fn test_multiprocessing_worker_code() {
+    let args = |values: &[&str]| values.iter().map(OsString::from).collect::<Vec<_>>();
+
+    assert_eq!(multiprocessing_worker_code(&args(&["app"])), None);
+    assert_eq!(multiprocessing_worker_code(&args(&["app", "foo"])), None);
+    assert_eq!(
+        multiprocessing_worker_code(&args(&["app", "-c", "print('hello')"])),
+        None
+    );
+
+    assert_eq!(
+        multiprocessing_worker_code(&args(&[
+            "app",
+            "--multiprocessing-fork",
+            "tracker_fd=5",
+            "pipe_handle=7"
+        ])),
+        Some("from multiprocessing.spawn import freeze_support; freeze_support()".to_string())
+    );
+
+    assert_eq!(
+        multiprocessing_worker_code(&args(&[
+            "app",
+            "-I",
+            "-X",
+            "dev",
+            "-c",
+            "from multiprocessing.resource_tracker import main;main(5)"
+        ])),
+        Some("from multiprocessing.resource_tracker import main;main(5)".to_string())
+    );
+
+    assert_eq!(
+        multiprocessing_worker_code(&args(&[
+            "app",
+            "-c",
+            "from multiprocessing.spawn import spawn_main; spawn_main(tracker_fd=5, pipe_handle=7)",
+            "--multiprocessing-fork"
+        ])),
+        Some(
+            "from multiprocessing.spawn import spawn_main; spawn_main(tracker_fd=5, pipe_handle=7)"
+                .to_string()
+        )
+    );
+}
+
+#[test]
+fn test_resolve_sys_argv() {
+    let args = |values: &[&str]| values.iter().map(OsString::from).collect::<Vec<_>>();
+
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    assert_eq!(
+        config.resolve_sys_argv(args(&["app", "foo"])),
+        args(&["app", "foo"])
+    );
+
+    config.run = PythonRunMode::ModuleWithArgs {
+        module: "mymodule".to_string(),
+        args: vec!["serve".to_string(), "--verbose".to_string()],
+    };
+    assert_eq!(
+        config.resolve_sys_argv(args(&["app", "foo"])),
+        args(&["app", "serve", "--verbose", "foo"])
+    );
+
+    // multiprocessing workers receive their arguments unaltered.
+    assert_eq!(
+        config.resolve_sys_argv(args(&["app", "--multiprocessing-fork"])),
+        args(&["app", "--multiprocessing-fork"])
+    );
+
+    config.run = PythonRunMode::FileFromArgs;
+    assert_eq!(
+        config.resolve_sys_argv(args(&["app", "script.py", "foo"])),
+        args(&["script.py", "foo"])
+    );
+    assert_eq!(config.resolve_sys_argv(args(&["app"])), args(&["app"]));
+
+    config.sys_argv_skip = 1;
+    assert_eq!(
+        config.resolve_sys_argv(args(&["app", "--wrapper", "script.py", "foo"])),
+        args(&["script.py", "foo"])
+    );
+
+    config.run = PythonRunMode::Repl;
+    config.sys_argv_skip = 2;
+    config.sys_argv_insert = vec!["--fixed".to_string()];
+    assert_eq!(
+        config.resolve_sys_argv(args(&["app", "a", "b", "foo"])),
+        args(&["app", "--fixed", "foo"])
+    );
+    assert_eq!(
+        config.resolve_sys_argv(args(&["app", "a"])),
+        args(&["app", "--fixed"])
+    );
+}
+
+#[test]
+fn test_eval_many() -> Result<()> {
+    let code = vec![
+        "values = ['\\'quoted\\'']".to_string(),
+        // Would be a syntax error if the code were concatenated.
+        "from __future__ import annotations\nvalues.append(len(values))".to_string(),
+    ];
+
+    let config = OxidizedPythonInterpreterConfig::default();
+    let mut interp = MainPythonInterpreter::new(config)?;
+    let py = interp.acquire_gil().unwrap();
+
+    // The same code Py_RunMain() evaluates.
+    py.run(&eval_many_code(&code), None, None).unwrap();
+    let main = py.import("__main__").unwrap();
+    assert_eq!(
+        main.get(py, "values").unwrap().to_string(),
+        "[\"'quoted'\", 1]"
+    );
+
+    run(
+        py,
+        &PythonRunMode::EvalMany {
+            code: vec!["values = []".to_string(), "values.append(1)".to_string()],
+        },
+    )
+    .unwrap();
+    assert_eq!(main.get(py, "values").unwrap().to_string(), "[1]");
+
+    Ok(())
+}
+
+#[test]
+fn test_repl_line_editing() -> Result<()> {
+    for enabled in &[true, false] {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.repl_line_editing = *enabled;
+
+        let mut interp = MainPythonInterpreter::new(config)?;
+
+        let py = interp.acquire_gil().unwrap();
+        let sys = py.import("sys").unwrap();
+        assert_eq!(sys.hasattr(py, "__interactivehook__").unwrap(), *enabled);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_startup_profile() -> Result<()> {
+    let dir = test_dir("profile")?;
+    let path = dir.path().join("profile.json");
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config.startup_profile_path = Some(path.clone());
+
+    {
+        let mut interp = MainPythonInterpreter::new(config)?;
+        let py = interp.acquire_gil().unwrap();
+        py.import("json").unwrap();
+    }
+
+    let profile = std::fs::read_to_string(&path)?;
+
+    assert!(profile.contains("\"phases\": ["));
+    assert!(profile.contains("\"name\": \"initialize_core\""));
+    assert!(profile.contains("\"imports\": ["));
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_startup_profile_fork() -> Result<()> {
+    let dir = test_dir("profile-fork")?;
+    let path = dir.path().join("profile.json");
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config.startup_profile_path = Some(path.clone());
+
+    {
+        let mut interp = MainPythonInterpreter::new(config)?;
+        let _py = interp.acquire_gil().unwrap();
+
+        let pid = unsafe { libc::fork() };
+
+        if pid == 0 {
+            // The child must neither record into nor write the parent's profile.
+            let ok = !crate::profiling::enabled()
+                && crate::profiling::finish().is_ok()
+                && !path.exists();
+
+            unsafe { libc::_exit(if ok { 0 } else { 1 }) };
+        }
+
+        assert!(pid > 0);
+
+        let mut status = 0;
+        assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+        assert!(libc::WIFEXITED(status));
+        assert_eq!(libc::WEXITSTATUS(status), 0);
+
+        assert!(crate::profiling::enabled());
+    }
+
+    let profile = std::fs::read_to_string(&path)?;
+
+    assert!(profile.contains("\"name\": \"initialize_core\""));
+
+    Ok(())
+}
+
+/// Call a server handler with a request and obtain the response.
+fn call_server_handler(py: Python, handler: &PyObject) -> (u16, Vec<(String, PyBytes)>, Vec<u8>) {
+    let headers = vec![(
+        "x-test".to_string(),
+        PyBytes::new(py, b"value").into_object(),
+    )];
+
+    let (status, headers, body) = handler
+        .call(
+            py,
+            (
+                "POST",
+                "/hello%20world",
+                "a=1",
+                headers,
+                PyBytes::new(py, b"request body"),
+                "127.0.0.1",
+                40000,
+            ),
+            None,
+        )
+        .unwrap()
+        .extract::<(u16, Vec<(String, PyBytes)>, PyBytes)>(py)
+        .unwrap();
+
+    (status, headers, body.data(py).to_vec())
+}
+
+#[test]
+fn test_server_handlers() -> Result<()> {
+    let config = OxidizedPythonInterpreterConfig::default();
+    let mut interp = MainPythonInterpreter::new(config)?;
+    let py = interp.acquire_gil().unwrap();
+
+    py.run(
+        r#"
+import sys, types
+
+app = types.ModuleType('pyembed_test_app')
+sys.modules['pyembed_test_app'] = app
+
+def wsgi(environ, start_response):
+    start_response('201 Created', [('Content-Type', 'text/plain')])
+    return [
+        environ['PATH_INFO'].encode('latin-1'), b' ',
+        environ['QUERY_STRING'].encode('latin-1'), b' ',
+        environ['HTTP_X_TEST'].encode('latin-1'), b' ',
+        environ['wsgi.input'].read(),
+    ]
+
+async def asgi(scope, receive, send):
+    if scope['type'] == 'lifespan':
+        while True:
+            message = await receive()
+            app.lifespan.append(message['type'])
+            await send({'type': message['type'] + '.complete'})
+            if message['type'] == 'lifespan.shutdown':
+                return
+
+    request = await receive()
+    await send({'type': 'http.response.start', 'status': 202,
+                'headers': [(b'content-type', b'text/plain')]})
+    await send({'type': 'http.response.body', 'body': scope['path'].encode(), 'more_body': True})
+    await send({'type': 'http.response.body',
+                'body': b' ' + dict(scope['headers'])[b'x-test'] + b' ' + request['body']})
+
+app.wsgi = wsgi
+app.asgi = asgi
+app.lifespan = []
+"#,
+        None,
+        None,
+    )
+    .unwrap();
+
+    let handler = create_handler(
+        py,
+        "pyembed_test_app:wsgi",
+        ServerInterface::Wsgi,
+        "127.0.0.1",
+        8000,
+    )
+    .unwrap();
+    handler.call_method(py, "startup", NoArgs, None).unwrap();
+
+    let (status, headers, body) = call_server_handler(py, &handler);
+    assert_eq!(status, 201);
+    assert_eq!(headers.len(), 1);
+    assert_eq!(headers[0].0, "Content-Type");
+    assert_eq!(headers[0].1.data(py), b"text/plain");
+    assert_eq!(body, b"/hello world a=1 value request body".to_vec());
+
+    handler.call_method(py, "shutdown", NoArgs, None).unwrap();
+
+    let handler = create_handler(
+        py,
+        "pyembed_test_app:asgi",
+        ServerInterface::Asgi,
+        "127.0.0.1",
+        8000,
+    )
+    .unwrap();
+
+    handler.call_method(py, "startup", NoArgs, None).unwrap();
+
+    let (status, headers, body) = call_server_handler(py, &handler);
+    assert_eq!(status, 202);
+    assert_eq!(headers[0].0, "content-type");
+    assert_eq!(body, b"/hello world value request body".to_vec());
+
+    handler.call_method(py, "shutdown", NoArgs, None).unwrap();
+
+    let lifespan = py
+        .eval("sys.modules['pyembed_test_app'].lifespan", None, None)
+        .unwrap();
+    assert_eq!(
+        lifespan.extract::<Vec<String>>(py).unwrap(),
+        vec![
+            "lifespan.startup".to_string(),
+            "lifespan.shutdown".to_string()
+        ]
+    );
+
+    assert!(create_handler(
+        py,
+        "pyembed_test_app:missing",
+        ServerInterface::Wsgi,
+        "127.0.0.1",
+        8000
+    )
+    .is_err());
+
+    Ok(())
+}
+
+py_module_initializer!(pyembed_test_module, |py, m| {
+    m.add(py, "value", 1)?;
+    Ok(())
+});
+
+py_module_initializer!(pyembed_test_module_replacement, |py, m| {
+    m.add(py, "value", 2)?;
+    Ok(())
+});
+
+#[test]
+fn test_add_extension_module() -> Result<()> {
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config
+        .add_extension_module("pyembed_test_module", PyInit_pyembed_test_module)
+        .unwrap();
+    assert!(config
+        .add_extension_module("invalid\0name", PyInit_pyembed_test_module)
+        .is_err());
+
+    {
+        let mut interp = MainPythonInterpreter::new(config.clone())?;
+        let py = interp.acquire_gil().unwrap();
+
+        let module = py.import("pyembed_test_module").unwrap();
+        assert_eq!(module.get(py, "value").unwrap().to_string(), "1");
+    }
+
+    // Adding a module with an existing name replaces it.
+    config
+        .add_extension_module(
+            "pyembed_test_module",
+            PyInit_pyembed_test_module_replacement,
+        )
+        .unwrap();
+
+    let mut interp = MainPythonInterpreter::new(config)?;
+    let py = interp.acquire_gil().unwrap();
+
+    let module = py.import("pyembed_test_module").unwrap();
+    assert_eq!(module.get(py, "value").unwrap().to_string(), "2");
+
+    Ok(())
+}
+
+#[test]
+fn test_jupyter_kernel_code() -> Result<()> {
+    let code = jupyter_kernel_code("myapp", "My 'App'");
+    assert!(!code.contains("%NAME%"));
+    assert!(!code.contains("%DISPLAY_NAME%"));
+
+    let mut interp = MainPythonInterpreter::new(OxidizedPythonInterpreterConfig::default())?;
+    let py = interp.acquire_gil().unwrap();
+
+    let builtins = py.import("builtins").unwrap();
+    builtins
+        .call(py, "compile", (code, "<string>", "exec"), None)
+        .unwrap();
+
+    Ok(())
+}
+
+#[test]
+fn test_main_module() -> Result<()> {
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config.oxidized_importer = true;
+
+    let mut interp = MainPythonInterpreter::new(config)?;
+    let py = interp.acquire_gil().unwrap();
+
+    py.run(
+        r#"
+import sys
+from oxidized_importer import OxidizedResource
+
+finder = [f for f in sys.meta_path if type(f).__name__ == 'OxidizedFinder'][0]
+
+state = b'import sys\nsys.main_state = (__name__, __package__, __spec__.name, sys.argv[0])\n'
+
+for name, is_package, source in (
+    ('__main__', False, state),
+    ('mainpkg', True, b''),
+    ('mainpkg.helper', False, b'value = 42\n'),
+    ('mainpkg.__main__', False, b'from . import helper\n' + state),
+):
+    resource = OxidizedResource()
+    resource.name = name
+    resource.flavor = 'module'
+    resource.is_package = is_package
+    resource.in_memory_source = source
+    finder.add_resource(resource)
+
+sys.argv[0] = 'myapp'
+"#,
+        None,
+        None,
+    )
+    .unwrap();
+
+    let sys = py.import("sys").unwrap();
+
+    run(
+        py,
+        &PythonRunMode::MainModule {
+            module: "mainpkg".to_string(),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        sys.get(py, "main_state").unwrap().to_string(),
+        "('__main__', 'mainpkg', 'mainpkg.__main__', 'myapp')"
+    );
+
+    // What Py_RunMain() evaluates for a zipapp style __main__ module.
+    py.run(&main_module_code("__main__"), None, None).unwrap();
+    assert_eq!(
+        sys.get(py, "main_state").unwrap().to_string(),
+        "('__main__', '', '__main__', 'myapp')"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_restart_interpreter() -> Result<()> {
+    for _ in 0..3 {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.oxidized_importer = true;
+
+        let mut interp = MainPythonInterpreter::new(config)?;
+
+        let py = interp.acquire_gil().unwrap();
+        let sys = py.import("sys").unwrap();
+
+        // State from a previous interpreter doesn't survive finalization.
+        assert!(!sys.hasattr(py, "restart_marker").unwrap());
+        sys.setattr(py, "restart_marker", py.True()).unwrap();
+
+        // The importer of the new interpreter is functional.
+        let meta_path = sys.get(py, "meta_path").unwrap();
+        let importer = meta_path.get_item(py, 0).unwrap();
+        assert_eq!(importer.get_type(py).name(py), "OxidizedFinder");
+        py.import("json").unwrap();
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_interrupt_handle() -> Result<()> {
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config.interpreter_config.install_signal_handlers = Some(false);
+
+    let mut interp = MainPythonInterpreter::new(config)?;
+    let handle = interp.interrupt_handle().unwrap();
+
+    let py = interp.acquire_gil().unwrap();
+    let interrupter = std::thread::spawn(move || handle.interrupt());
+
+    let err = py.run("while True: pass", None, None).unwrap_err();
+    assert!(interrupter.join().unwrap());
+    assert!(err.matches(py, py.get_type::<KeyboardInterrupt>()));
+
+    drop(interp);
+
+    // The handle doesn't reach interpreters other than its own.
+    let interp = MainPythonInterpreter::new(OxidizedPythonInterpreterConfig::default())?;
+    assert!(!handle.interrupt());
+    drop(interp);
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_interrupt_handle_fork() -> Result<()> {
+    use {
+        std::sync::atomic::{AtomicBool, Ordering},
+        std::time::{Duration, Instant},
+    };
+
+    let interp = MainPythonInterpreter::new(OxidizedPythonInterpreterConfig::default())?;
+    let handle = interp.interrupt_handle().unwrap();
+    drop(interp);
+
+    let interp = MainPythonInterpreter::new(OxidizedPythonInterpreterConfig::default())?;
+
+    // The stale handle schedules nothing but repeatedly takes the lock
+    // guarding interrupts while this thread forks.
+    let stop = Arc::new(AtomicBool::new(false));
+    let interrupter = {
+        let stop = stop.clone();
+        std::thread::spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                assert!(!handle.interrupt());
+            }
+        })
+    };
+
+    for _ in 0..50 {
+        let pid = unsafe { libc::fork() };
+
+        if pid == 0 {
+            // Deadlocks if the lock was inherited in its locked state.
+            crate::signals::interpreter_stopping();
+            unsafe { libc::_exit(0) };
+        }
+
+        assert!(pid > 0);
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut status = 0;
+
+        loop {
+            let res = unsafe { libc::waitpid(pid, &mut status, libc::WNOHANG) };
+
+            if res == pid {
+                break;
+            }
+
+            assert_eq!(res, 0);
+
+            if Instant::now() > deadline {
+                unsafe { libc::kill(pid, libc::SIGKILL) };
+                panic!("forked child did not exit");
+            }
+
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(libc::WIFEXITED(status));
+        assert_eq!(libc::WEXITSTATUS(status), 0);
+    }
+
+    stop.store(true, Ordering::Relaxed);
+    interrupter.join().unwrap();
+    drop(interp);
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_capture_output() -> Result<()> {
+    let captured = Arc::new(Mutex::new(Vec::<u8>::new()));
+    let captured_writer = captured.clone();
+
+    let config = OxidizedPythonInterpreterConfig::default();
+    let mut interp = MainPythonInterpreter::new(config)?;
+
+    interp
+        .capture_output(
+            StandardStream::Stderr,
+            Box::new(CallbackWriter::new(move |data: &[u8]| {
+                captured_writer.lock().unwrap().extend_from_slice(data)
+            })),
+        )
+        .unwrap();
+
+    {
+        let py = interp.acquire_gil().unwrap();
+        py.run(
+            "import os, sys; sys.stderr.write('from python\\n'); os.write(2, b'from fd\\n')",
+            None,
+            None,
+        )
+        .unwrap();
+    }
+
+    interp
+        .stop_capturing_output(StandardStream::Stderr)
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8(captured.lock().unwrap().clone())?,
+        "from python\nfrom fd\n"
+    );
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_stdio_redirect_file() -> Result<()> {
+    let dir = test_dir("stdio")?;
+    let path = dir.path().join("stdio.log");
+
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config.stdio_redirect = StdioRedirect::File(path.display().to_string());
+
+    {
+        let mut interp = MainPythonInterpreter::new(config)?;
+        let py = interp.acquire_gil().unwrap();
+        py.run(
+            "import sys; print('to stdout'); sys.stderr.write('to stderr\\n')",
+            None,
+            None,
+        )
+        .unwrap();
+    }
+
+    let output = std::fs::read_to_string(&path)?;
+
+    // Each stream is drained by its own thread. So lines may be reordered.
+    assert_eq!(output.len(), "to stdout\nto stderr\n".len());
+    assert!(output.contains("to stdout\n"));
+    assert!(output.contains("to stderr\n"));
+
+    Ok(())
+}
+
+#[test]
+fn test_site_packages_overlay() -> Result<()> {
+    let dir = test_dir("overlay")?;
+    let path = dir.path().join("overlay");
+    std::fs::create_dir_all(path.join("overlay_plugin"))?;
+    std::fs::write(
+        path.join("overlay_plugin").join("__init__.py"),
+        "VALUE = 42\n",
+    )?;
+
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config.site_packages_overlay = Some(SitePackagesOverlay {
+        path: path.display().to_string(),
+        priority: SitePackagesOverlayPriority::Before,
+    });
+
+    {
+        let mut interp = MainPythonInterpreter::new(config.clone())?;
+        let py = interp.acquire_gil().unwrap();
+        let plugin = py.import("overlay_plugin").unwrap();
+        assert_eq!(
+            plugin.get(py, "VALUE").unwrap().extract::<i32>(py).unwrap(),
+            42
+        );
+    }
+
+    assert!(path.join(".pyembed-compatibility").exists());
+
+    // A directory created for another interpreter is ignored.
+    std::fs::write(
+        path.join(".pyembed-compatibility"),
+        "cpython-00-other-64bit\n",
+    )?;
+
+    {
+        let mut interp = MainPythonInterpreter::new(config)?;
+        let py = interp.acquire_gil().unwrap();
+        assert!(py.import("overlay_plugin").is_err());
+    }
+
+    Ok(())
+}
+
+/// Write packed resources data holding modules with the given source.
+fn write_packed_resources_file(path: &std::path::Path, modules: &[(&str, &str)]) -> Result<()> {
+    let resources = modules
+        .iter()
+        .map(|(name, source)| Resource {
+            flavor: ResourceFlavor::Module,
+            name: Cow::Borrowed(*name),
+            in_memory_source: Some(Cow::Borrowed(source.as_bytes())),
+            ..Resource::default()
+        })
+        .collect::<Vec<_>>();
+
+    let mut data = Vec::new();
+    write_packed_resources_v1(&resources, &mut data, None)?;
+    std::fs::write(path, data)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_packed_resources_files() -> Result<()> {
+    let dir = test_dir("segments")?;
+
+    let first = dir.path().join("first.resources");
+    let second = dir.path().join("second.resources");
+    write_packed_resources_file(
+        &first,
+        &[("segment_module", "VALUE = 1\n"), ("first_module", "")],
+    )?;
+    write_packed_resources_file(
+        &second,
+        &[("segment_module", "VALUE = 2\n"), ("second_module", "")],
+    )?;
+
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config.oxidized_importer = true;
+    config.packed_resources_files = vec![first.display().to_string(), second.display().to_string()];
+
+    {
+        let mut interp = MainPythonInterpreter::new(config.clone())?;
+        let py = interp.acquire_gil().unwrap();
+
+        // The first file takes precedence.
+        let module = py.import("segment_module").unwrap();
+        assert_eq!(
+            module.get(py, "VALUE").unwrap().extract::<i32>(py).unwrap(),
+            1
+        );
+
+        py.import("first_module").unwrap();
+        py.import("second_module").unwrap();
+    }
+
+    config.packed_resources_files = vec!["$ORIGIN/missing.resources".to_string()];
+    assert!(MainPythonInterpreter::new(config).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_packed_resources_files_signed() -> Result<()> {
+    let dir = test_dir("signed")?;
+    let path = dir.path().join("signed.resources");
+    let signature_path = format!("{}.sig", path.display());
+    write_packed_resources_file(&path, &[("signed_module", "")])?;
+
+    let secret_key = [42u8; 32];
+    let public_key = packed_resources_public_key(&secret_key)?;
+
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config.oxidized_importer = true;
+    config.packed_resources_files = vec![path.display().to_string()];
+    config.packed_resources_public_key = Some(public_key.as_slice());
+
+    // A missing signature is an error.
+    assert!(MainPythonInterpreter::new(config.clone()).is_err());
+
+    std::fs::write(
+        &signature_path,
+        sign_packed_resources(b"other", &secret_key)?,
+    )?;
+    assert!(MainPythonInterpreter::new(config.clone()).is_err());
+
+    std::fs::write(
+        &signature_path,
+        sign_packed_resources(&std::fs::read(&path)?, &secret_key)?,
+    )?;
+
+    // Zip archives can't be verified.
+    let mut zip_config = config.clone();
+    zip_config.zip_archives = vec![dir.path().join("app.zip").display().to_string()];
+    assert!(MainPythonInterpreter::new(zip_config).is_err());
+
+    {
+        let mut interp = MainPythonInterpreter::new(config)?;
+
+        // The verified copy is used, not the file.
+        write_packed_resources_file(&path, &[("other_module", "")])?;
+
+        let py = interp.acquire_gil().unwrap();
+        py.import("signed_module").unwrap();
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_watch_packed_resources_files() -> Result<()> {
+    let dir = test_dir("watched")?;
+    let path = dir.path().join("watched.resources");
+    let new_path = dir.path().join("watched.resources.new");
+    let signature_path = format!("{}.sig", path.display());
+    write_packed_resources_file(&path, &[("watched_module", "VALUE = 1\n")])?;
+
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config.oxidized_importer = true;
+    config.packed_resources_files = vec![path.display().to_string()];
+    config.watch_packed_resources_files = true;
+
+    {
+        let mut interp = MainPythonInterpreter::new(config.clone())?;
+        let py = interp.acquire_gil().unwrap();
+        py.import("watched_module").unwrap();
+
+        // The file is replaced, not rewritten in place.
+        write_packed_resources_file(
+            &new_path,
+            &[
+                ("watched_module", "VALUE = 2\n"),
+                ("added_module", "VALUE = 3\n"),
+            ],
+        )?;
+        std::fs::rename(&new_path, &path)?;
+
+        py.import("importlib")
+            .unwrap()
+            .call(py, "invalidate_caches", NoArgs, None)
+            .unwrap();
+        let module = py.import("added_module").unwrap();
+        assert_eq!(
+            module.get(py, "VALUE").unwrap().extract::<i32>(py).unwrap(),
+            3
+        );
+    }
+
+    let secret_key = [42u8; 32];
+    let public_key = packed_resources_public_key(&secret_key)?;
+    std::fs::write(
+        &signature_path,
+        sign_packed_resources(&std::fs::read(&path)?, &secret_key)?,
+    )?;
+    config.packed_resources_public_key = Some(public_key.as_slice());
+
+    {
+        let mut interp = MainPythonInterpreter::new(config)?;
+        let py = interp.acquire_gil().unwrap();
+        py.import("added_module").unwrap();
+
+        // A file whose signature doesn't verify isn't loaded.
+        write_packed_resources_file(&new_path, &[("unsigned_module", "")])?;
+        std::fs::rename(&new_path, &path)?;
+
+        py.import("importlib")
+            .unwrap()
+            .call(py, "invalidate_caches", NoArgs, None)
+            .unwrap();
+        assert!(py.import("unsigned_module").is_err());
+        py.import("added_module").unwrap();
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_packed_resources_key() -> Result<()> {
+    let dir = test_dir("encrypted")?;
+    let path = dir.path().join("encrypted.resources");
+
+    let key = [42u8; 32];
+    let resources = vec![Resource {
+        flavor: ResourceFlavor::Module,
+        name: Cow::Borrowed("encrypted_module"),
+        in_memory_source: Some(Cow::Borrowed(&b"VALUE = 42\n"[..])),
+        ..Resource::default()
+    }];
+    let mut data = Vec::new();
+    write_packed_resources_v1_with_options(
+        resources.as_slice(),
+        &mut data,
+        &WriteOptions {
+            encryption_key: Some(key.to_vec()),
+            ..WriteOptions::default()
+        },
+    )?;
+    std::fs::write(&path, data)?;
+
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config.oxidized_importer = true;
+    config.packed_resources_files = vec![path.display().to_string()];
+
+    assert!(MainPythonInterpreter::new(config.clone()).is_err());
+
+    let wrong_key = [7u8; 32];
+    config.packed_resources_key = Some(&wrong_key[..]);
+    assert!(MainPythonInterpreter::new(config.clone()).is_err());
+
+    config.packed_resources_key = Some(&key[..]);
+    {
+        let mut interp = MainPythonInterpreter::new(config)?;
+        let py = interp.acquire_gil().unwrap();
+        let module = py.import("encrypted_module").unwrap();
+        assert_eq!(
+            module.get(py, "VALUE").unwrap().extract::<i32>(py).unwrap(),
+            42
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_packed_resources_key_env() -> Result<()> {
+    let dir = test_dir("encrypted-env")?;
+    let path = dir.path().join("encrypted.resources");
+    let key_path = dir.path().join("encryption.key");
+
+    let key = [42u8; 32];
+    let resources = vec![Resource {
+        flavor: ResourceFlavor::Module,
+        name: Cow::Borrowed("encrypted_env_module"),
+        in_memory_source: Some(Cow::Borrowed(&b"VALUE = 42\n"[..])),
+        ..Resource::default()
+    }];
+    let mut data = Vec::new();
+    write_packed_resources_v1_with_options(
+        resources.as_slice(),
+        &mut data,
+        &WriteOptions {
+            encryption_key: Some(key.to_vec()),
+            ..WriteOptions::default()
+        },
+    )?;
+    std::fs::write(&path, data)?;
+    std::fs::write(&key_path, &key[..])?;
+
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config.oxidized_importer = true;
+    config.packed_resources_files = vec![path.display().to_string()];
+    config.packed_resources_key_env = Some("PYEMBED_TEST_PACKED_RESOURCES_KEY".to_string());
+
+    std::env::set_var("PYEMBED_TEST_PACKED_RESOURCES_KEY", &key_path);
+    {
+        let mut interp = MainPythonInterpreter::new(config)?;
+        let py = interp.acquire_gil().unwrap();
+        let module = py.import("encrypted_env_module").unwrap();
+        assert_eq!(
+            module.get(py, "VALUE").unwrap().extract::<i32>(py).unwrap(),
+            42
+        );
+    }
+    std::env::remove_var("PYEMBED_TEST_PACKED_RESOURCES_KEY");
+
+    Ok(())
+}
+
+#[test]
+fn test_resources_memory_usage() -> Result<()> {
+    let dir = test_dir("usage")?;
+    let path = dir.path().join("usage.resources");
+    let source = "VALUE = 1\n".repeat(10000);
+    write_packed_resources_file(&path, &[("usage_module", source.as_str())])?;
+    let size = std::fs::metadata(&path)?.len() as usize;
+
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config.oxidized_importer = true;
+    config.packed_resources_files = vec![path.display().to_string()];
+
+    {
+        let mut interp = MainPythonInterpreter::new(config)?;
+        let usage = interp.resources_memory_usage().unwrap();
+        assert_eq!(usage.mapped_bytes, size);
+
+        let py = interp.acquire_gil().unwrap();
+        py.import("usage_module").unwrap();
+
+        let usage = interp.resources_memory_usage().unwrap();
+        assert_eq!(usage.mapped_bytes, size);
+        if cfg!(unix) {
+            // Importing the module read its source, paging it in.
+            assert!(usage.resident_bytes.unwrap() > 0);
+            assert!(usage.resident_bytes.unwrap() <= size);
+        } else {
+            assert_eq!(usage.resident_bytes, None);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_zip_archives() -> Result<()> {
+    let dir = test_dir("archive")?;
+    let path = dir.path().join("archive.zip");
+
+    {
+        let config = OxidizedPythonInterpreterConfig::default();
+        let mut interp = MainPythonInterpreter::new(config)?;
+        let py = interp.acquire_gil().unwrap();
+        let zipfile = py.import("zipfile").unwrap();
+        let zf = zipfile
+            .call(py, "ZipFile", (path.display().to_string(), "w"), None)
+            .unwrap();
+        zf.call_method(
+            py,
+            "writestr",
+            ("zip_plugin/__init__.py", "VALUE = 42\n"),
+            None,
+        )
+        .unwrap();
+        zf.call_method(py, "writestr", ("zip_plugin/data.txt", "data"), None)
+            .unwrap();
+        zf.call_method(py, "close", NoArgs, None).unwrap();
+    }
+
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config.zip_archives = vec![
+        path.display().to_string(),
+        "$ORIGIN/missing-archive.zip".to_string(),
+    ];
+
+    {
+        let mut interp = MainPythonInterpreter::new(config)?;
+        let py = interp.acquire_gil().unwrap();
+        let plugin = py.import("zip_plugin").unwrap();
+        assert_eq!(
+            plugin.get(py, "VALUE").unwrap().extract::<i32>(py).unwrap(),
+            42
+        );
+
+        let data = py
+            .import("importlib.resources")
+            .unwrap()
+            .call(py, "read_text", ("zip_plugin", "data.txt"), None)
+            .unwrap();
+        assert_eq!(data.extract::<String>(py).unwrap(), "data");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_environment_allowlist() -> Result<()> {
+    std::env::set_var("PYEMBED_TEST_ALLOWED", "1");
+    std::env::set_var("PYEMBED_TEST_DENIED", "1");
+
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config.environment_allowlist = Some(vec!["PYEMBED_TEST_ALLOWED".to_string()]);
+
+    {
+        let mut interp = MainPythonInterpreter::new(config)?;
+        let py = interp.acquire_gil().unwrap();
+        let environ = py.import("os").unwrap().get(py, "environ").unwrap();
+
+        assert!(environ
+            .call_method(py, "__contains__", ("PYEMBED_TEST_ALLOWED",), None)
+            .unwrap()
+            .is_true(py)
+            .unwrap());
+        assert!(!environ
+            .call_method(py, "__contains__", ("PYEMBED_TEST_DENIED",), None)
+            .unwrap()
+            .is_true(py)
+            .unwrap());
+    }
+
+    // The process environment is left alone.
+    assert_eq!(std::env::var("PYEMBED_TEST_DENIED").unwrap(), "1");
+
+    std::env::remove_var("PYEMBED_TEST_ALLOWED");
+    std::env::remove_var("PYEMBED_TEST_DENIED");
+
+    Ok(())
+}
@@ -41,6 +41,10 @@ class TestImporterBuiltins(unittest.TestCase):
         importer = self.get_importer()
         self.assertIsNone(importer.get_source("_io"))
 
+    def test_is_package(self):
+        importer = self.get_importer()
+        self.assertFalse(importer.is_package("_io"))
+
     def test_get_filename(self):
         importer = self.get_importer()
 
@@ -44,6 +44,10 @@ class TestImporterConstruction(unittest.TestCase):
     def test_origin(self):
         OxidizedFinder(relative_path_origin="/path/to/origin")
 
+    def test_watch_resources_file_without_file(self):
+        with self.assertRaisesRegex(ValueError, "requires resources_file"):
+            OxidizedFinder(watch_resources_file=True)
+
 
 if __name__ == "__main__":
     # Reset command arguments so test runner isn't confused.
@@ -193,6 +193,116 @@ class TestImporterMetadata(unittest.TestCase):
         self.assertIsInstance(requires, list)
         self.assertEqual(requires, ["foo"])
 
+    def test_find_distributions_context(self):
+        self._write_metadata()
+        f = self._finder_from_td()
+
+        dists = f.find_distributions(
+            importlib.metadata.DistributionFinder.Context(name="my_package")
+        )
+        self.assertEqual(len(dists), 1)
+        self.assertEqual(dists[0].version, "1.0")
+
+        # Names are normalized.
+        dists = f.find_distributions(
+            importlib.metadata.DistributionFinder.Context(name="My-Package")
+        )
+        self.assertEqual(len(dists), 1)
+
+        dists = f.find_distributions(
+            importlib.metadata.DistributionFinder.Context(name="other")
+        )
+        self.assertEqual(len(dists), 0)
+
+        # A context without a name finds all distributions.
+        dists = f.find_distributions(importlib.metadata.DistributionFinder.Context())
+        self.assertEqual(len(dists), 1)
+
+    def test_importlib_metadata_api(self):
+        self._write_metadata()
+
+        entry_points_path = self.td / "my_package-1.0.dist-info" / "entry_points.txt"
+        with entry_points_path.open("w", encoding="utf-8") as fh:
+            fh.write("[oxidized_importer_test]\n")
+            fh.write("script = my_package:module\n")
+
+        f = self._finder_from_td()
+
+        sys.meta_path.insert(0, f)
+        try:
+            self.assertEqual(importlib.metadata.version("my_package"), "1.0")
+            self.assertEqual(importlib.metadata.version("My-Package"), "1.0")
+            self.assertEqual(
+                importlib.metadata.metadata("my_package")["Name"], "my_package"
+            )
+
+            eps = importlib.metadata.entry_points()["oxidized_importer_test"]
+            self.assertEqual([ep.name for ep in eps], ["script"])
+        finally:
+            sys.meta_path.remove(f)
+
+    def test_files_missing(self):
+        self._write_metadata()
+        f = self._finder_from_td()
+
+        dists = f.find_distributions()
+        self.assertIsNone(dists[0].files)
+
+    def test_files_record(self):
+        self._write_metadata()
+
+        record_path = self.td / "my_package-1.0.dist-info" / "RECORD"
+        with record_path.open("w", encoding="utf-8") as fh:
+            fh.write("my_package/__init__.py,sha256=abcd,42\n")
+            fh.write("my_package-1.0.dist-info/RECORD,,\n")
+
+        f = self._finder_from_td()
+        dists = f.find_distributions()
+
+        files = dists[0].files
+        self.assertIsInstance(files, list)
+        self.assertEqual(len(files), 2)
+
+        self.assertIsInstance(files[0], importlib.metadata.PackagePath)
+        self.assertEqual(str(files[0]), "my_package/__init__.py")
+        self.assertEqual(files[0].hash.mode, "sha256")
+        self.assertEqual(files[0].hash.value, "abcd")
+        self.assertEqual(files[0].size, 42)
+        self.assertIs(files[0].dist, dists[0])
+
+        self.assertIsNone(files[1].hash)
+        self.assertIsNone(files[1].size)
+
+    def test_files_sources(self):
+        pkginfo_path = self.td / "my_package-1.0.egg-info" / "PKG-INFO"
+        pkginfo_path.parent.mkdir()
+
+        with pkginfo_path.open("w", encoding="utf-8") as fh:
+            fh.write("Name: my_package\n")
+            fh.write("Version: 1.0\n")
+
+        sources_path = self.td / "my_package-1.0.egg-info" / "SOURCES.txt"
+        with sources_path.open("w", encoding="utf-8") as fh:
+            fh.write("setup.py\n")
+            fh.write("my_package/__init__.py\n")
+
+        f = self._finder_from_td()
+        dists = f.find_distributions()
+
+        files = dists[0].files
+        self.assertEqual(
+            [str(p) for p in files], ["setup.py", "my_package/__init__.py"]
+        )
+        self.assertIsNone(files[0].hash)
+        self.assertIsNone(files[0].size)
+
+    def test_locate_file(self):
+        self._write_metadata()
+        f = self._finder_from_td()
+
+        dists = f.find_distributions()
+        self.assertIsInstance(dists[0].locate_file("foo"), pathlib.Path)
+
 
 if __name__ == "__main__":
     # Reset command arguments so test runner isn't confused.
@@ -49,7 +49,10 @@ class TestImporterModule(unittest.TestCase):
                 "get_source",
                 "indexed_resources",
                 "invalidate_caches",
+                "is_package",
                 "iter_modules",
+                "register_pkg_resources",
+                "remove_resource",
                 "serialize_indexed_resources",
             },
         )
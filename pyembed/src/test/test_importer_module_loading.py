@@ -13,6 +13,7 @@ import unittest
 
 from oxidized_importer import (
     OxidizedFinder,
+    OxidizedResource,
     OxidizedResourceCollector,
     find_resources_in_path,
 )
@@ -42,12 +43,12 @@ class TestImporterModuleLoading(unittest.TestCase):
 
         return package_path
 
-    def _finder_from_td(self):
+    def _finder_from_td(self, **kwargs):
         collector = OxidizedResourceCollector(policy="in-memory-only")
         for r in find_resources_in_path(self.td):
             collector.add_in_memory(r)
 
-        f = OxidizedFinder()
+        f = OxidizedFinder(**kwargs)
         f.add_resources(collector.oxidize()[0])
 
         return f
@@ -143,6 +144,357 @@ class TestImporterModuleLoading(unittest.TestCase):
         with self.assertRaises(ImportError):
             f.get_filename("my_package")
 
+    def test_namespace_package(self):
+        p = self.td / "my_namespace" / "my_package"
+        p.mkdir(parents=True)
+
+        with (p / "__init__.py").open("wb"):
+            pass
+
+        f = self._finder_from_td()
+
+        spec = f.find_spec("my_namespace", None)
+        self.assertIsInstance(spec, importlib.machinery.ModuleSpec)
+        self.assertEqual(spec.name, "my_namespace")
+        self.assertIsNone(spec.loader)
+        self.assertIsNone(spec.origin)
+        self.assertEqual(
+            spec.submodule_search_locations,
+            [os.path.join(sys.argv[0], "my_namespace")],
+        )
+
+        m = importlib.util.module_from_spec(spec)
+        self.assertEqual(m.__name__, "my_namespace")
+        self.assertEqual(m.__path__, [os.path.join(sys.argv[0], "my_namespace")])
+        self.assertFalse(hasattr(m, "__file__"))
+
+        spec = f.find_spec("my_namespace.my_package", None)
+        self.assertIsInstance(spec, importlib.machinery.ModuleSpec)
+        self.assertIsInstance(spec.loader, OxidizedFinder)
+
+    def test_inspect_loader_missing(self):
+        f = OxidizedFinder()
+
+        with self.assertRaises(ImportError):
+            f.get_code("my_package")
+
+        with self.assertRaises(ImportError):
+            f.get_source("my_package")
+
+        with self.assertRaises(ImportError):
+            f.is_package("my_package")
+
+    def test_in_memory_module_data(self):
+        p = self._make_package("my_package")
+
+        with (p / "__init__.py").open("wb") as fh:
+            fh.write(b"import io\n")
+
+        with (p / "my_module.py").open("wb") as fh:
+            fh.write(b"import os\n")
+
+        f = self._finder_from_td()
+
+        self.assertTrue(f.is_package("my_package"))
+        self.assertFalse(f.is_package("my_package.my_module"))
+
+        self.assertEqual(f.get_source("my_package.my_module"), "import os\n")
+
+        # get_data() resolves source files relative to __path__.
+        package_path = f.find_spec("my_package", None).submodule_search_locations[0]
+
+        self.assertEqual(
+            f.get_data(os.path.join(package_path, "__init__.py")), b"import io\n"
+        )
+        self.assertEqual(
+            f.get_data(os.path.join(package_path, "my_module.py")), b"import os\n"
+        )
+
+        with self.assertRaises(OSError):
+            f.get_data(os.path.join(package_path, "missing.py"))
+
+    def test_in_memory_module_file_uri(self):
+        p = self._make_package("my_package")
+
+        with (p / "my_module.py").open("wb") as fh:
+            fh.write(b"import os\n")
+
+        f = self._finder_from_td(in_memory_module_file="uri")
+
+        spec = f.find_spec("my_package", None)
+        self.assertEqual(spec.origin, "oxidized:my_package/__init__.py")
+        self.assertTrue(spec.has_location)
+        self.assertEqual(
+            spec.submodule_search_locations, [os.path.join(sys.argv[0], "my_package")],
+        )
+
+        spec = f.find_spec("my_package.my_module", None)
+        self.assertEqual(spec.origin, "oxidized:my_package/my_module.py")
+
+        m = importlib.util.module_from_spec(spec)
+        self.assertEqual(m.__file__, "oxidized:my_package/my_module.py")
+        self.assertEqual(f.get_filename("my_package.my_module"), m.__file__)
+        self.assertEqual(f.get_data(m.__file__), b"import os\n")
+
+    def test_in_memory_module_file_path(self):
+        p = self._make_package("my_package")
+
+        with (p / "my_module.py").open("wb") as fh:
+            fh.write(b"import os\n")
+
+        f = self._finder_from_td(in_memory_module_file="path")
+
+        spec = f.find_spec("my_package", None)
+        self.assertEqual(
+            spec.origin, os.path.join(sys.argv[0], "my_package", "__init__.py")
+        )
+
+        spec = f.find_spec("my_package.my_module", None)
+        self.assertEqual(
+            spec.origin, os.path.join(sys.argv[0], "my_package", "my_module.py")
+        )
+
+        m = importlib.util.module_from_spec(spec)
+        self.assertEqual(f.get_data(m.__file__), b"import os\n")
+
+    def test_in_memory_module_file_none(self):
+        self._make_package("my_package")
+
+        f = self._finder_from_td(in_memory_module_file="none")
+
+        spec = f.find_spec("my_package", None)
+        self.assertIsNone(spec.origin)
+        self.assertFalse(spec.has_location)
+
+        with self.assertRaises(ImportError):
+            f.get_filename("my_package")
+
+        with self.assertRaises(ValueError):
+            OxidizedFinder(in_memory_module_file="file")
+
+    def test_in_memory_package_path_disabled(self):
+        self._make_package("my_package")
+
+        f = self._finder_from_td(in_memory_package_path=False)
+
+        spec = f.find_spec("my_package", None)
+        self.assertEqual(spec.submodule_search_locations, [])
+
+        m = importlib.util.module_from_spec(spec)
+        self.assertEqual(m.__path__, [])
+
+    def test_lazy_modules(self):
+        p = self._make_package("my_package")
+
+        with (p / "__init__.py").open("wb") as fh:
+            fh.write(b"value = 42\n")
+
+        with (p / "my_module.py").open("wb") as fh:
+            fh.write(b"value = 43\n")
+
+        with (self.td / "my_other_module.py").open("wb") as fh:
+            fh.write(b"value = 44\n")
+
+        f = self._finder_from_td(lazy_modules=["my_package"])
+
+        for name, value in (("my_package", 42), ("my_package.my_module", 43)):
+            spec = f.find_spec(name, None)
+            self.assertIsInstance(spec.loader, importlib.util.LazyLoader)
+
+            m = importlib.util.module_from_spec(spec)
+            spec.loader.exec_module(m)
+
+            # The module isn't executed until an attribute is accessed.
+            self.assertNotIn("value", object.__getattribute__(m, "__dict__"))
+            self.assertEqual(m.value, value)
+            self.assertIs(m.__loader__, f)
+
+        spec = f.find_spec("my_other_module", None)
+        self.assertIs(spec.loader, f)
+
+        f = self._finder_from_td(lazy_modules=["my_pack"])
+        self.assertIs(f.find_spec("my_package", None).loader, f)
+
+    @unittest.skipIf(not hasattr(sys, "addaudithook"), "audit hooks not supported")
+    def test_audit_events(self):
+        p = self._make_package("my_package")
+
+        with (p / "my_module.py").open("wb") as fh:
+            fh.write(b"import os\n")
+
+        with (p / "resource.txt").open("wb") as fh:
+            fh.write(b"resource")
+
+        f = self._finder_from_td()
+
+        events = []
+
+        def hook(event, args):
+            if event.startswith("oxidized_importer.") and events is not None:
+                events.append((event, args))
+
+        sys.addaudithook(hook)
+
+        try:
+            spec = f.find_spec("my_package.my_module", None)
+            m = importlib.util.module_from_spec(spec)
+            f.exec_module(m)
+
+            reader = f.get_resource_reader("my_package")
+            with reader.open_resource("resource.txt") as fh:
+                fh.read()
+
+            self.assertEqual(
+                reader.files().joinpath("resource.txt").read_bytes(), b"resource"
+            )
+
+            path = os.path.join(sys.argv[0], "my_package", "resource.txt")
+            f.get_data(path)
+        finally:
+            recorded = list(events)
+            # Hooks can't be removed. So stop recording.
+            events = None
+
+        self.assertEqual(
+            recorded,
+            [
+                ("oxidized_importer.load", ("my_package.my_module", None)),
+                ("oxidized_importer.open_resource", ("my_package", "resource.txt")),
+                ("oxidized_importer.open_resource", ("my_package", "resource.txt")),
+                ("oxidized_importer.get_data", (path,)),
+            ],
+        )
+
+    def test_watch_resources_file(self):
+        source_dir = self.td / "src"
+        source_dir.mkdir()
+        resources_path = self.td / "resources"
+
+        def write_resources(source):
+            with (source_dir / "my_module.py").open("wb") as fh:
+                fh.write(source)
+
+            collector = OxidizedResourceCollector(policy="in-memory-only")
+            for r in find_resources_in_path(source_dir):
+                collector.add_in_memory(r)
+
+            f = OxidizedFinder()
+            f.add_resources(collector.oxidize()[0])
+            data = f.serialize_indexed_resources()
+
+            # Replace the file atomically, like build tools should.
+            temp_path = self.td / "resources.tmp"
+            with temp_path.open("wb") as fh:
+                fh.write(data)
+            os.replace(temp_path, resources_path)
+
+        write_resources(b"value = 42\n")
+
+        f = OxidizedFinder(resources_file=resources_path, watch_resources_file=True)
+        self.assertEqual(f.get_source("my_module"), "value = 42\n")
+
+        write_resources(b"value = 43 + 1\n")
+
+        self.assertIsNotNone(f.find_spec("my_module", None))
+        self.assertEqual(f.get_source("my_module"), "value = 43 + 1\n")
+
+        write_resources(b"value = 44 + 2 + 3\n")
+
+        f.invalidate_caches()
+        self.assertEqual(f.get_source("my_module"), "value = 44 + 2 + 3\n")
+
+        # A bad file keeps existing resources.
+        with resources_path.open("wb") as fh:
+            fh.write(b"bad")
+
+        with self.assertWarnsRegex(RuntimeWarning, "unable to reload"):
+            f.invalidate_caches()
+
+        self.assertEqual(f.get_source("my_module"), "value = 44 + 2 + 3\n")
+
+        # Without watching, changes are ignored.
+        write_resources(b"value = 42\n")
+        f = OxidizedFinder(resources_file=resources_path)
+        write_resources(b"value = 43 + 1\n")
+        f.invalidate_caches()
+        self.assertEqual(f.get_source("my_module"), "value = 42\n")
+
+    @unittest.skipUnless(hasattr(os, "fork"), "requires os.fork()")
+    def test_fork(self):
+        with (self.td / "fork_module.py").open("wb") as fh:
+            fh.write(b"value = 42\n")
+
+        resources_path = self.td / "resources"
+        with resources_path.open("wb") as fh:
+            fh.write(self._finder_from_td().serialize_indexed_resources())
+
+        f = OxidizedFinder(resources_file=resources_path)
+
+        # The mapping outlives the file and is inherited by children.
+        resources_path.unlink()
+
+        r, w = os.pipe()
+        pid = os.fork()
+
+        if pid == 0:
+            try:
+                os.close(r)
+
+                spec = f.find_spec("fork_module", None)
+                module = importlib.util.module_from_spec(spec)
+                spec.loader.exec_module(module)
+
+                # Modifying the child's resources leaves the parent's alone.
+                resource = OxidizedResource()
+                resource.name = "child_module"
+                resource.flavor = "module"
+                resource.in_memory_source = b"value = 43\n"
+                f.add_resource(resource)
+
+                source = f.get_source("child_module")
+
+                with os.fdopen(w, "wb") as fh:
+                    fh.write(b"%d %s" % (module.value, source.encode("utf-8")))
+            finally:
+                os._exit(0)
+
+        os.close(w)
+        with os.fdopen(r, "rb") as fh:
+            data = fh.read()
+
+        _, status = os.waitpid(pid, 0)
+        self.assertEqual(status, 0)
+        self.assertEqual(data, b"42 value = 43\n")
+
+        self.assertIsNone(f.find_spec("child_module", None))
+        self.assertEqual(f.get_source("fork_module"), "value = 42\n")
+
+    def test_relative_path_module(self):
+        lib = self.td / "lib"
+        lib.mkdir()
+
+        source_path = lib / "my_module.py"
+        with source_path.open("wb") as fh:
+            fh.write(b"import io\n")
+
+        resource = OxidizedResource()
+        resource.name = "my_module"
+        resource.flavor = "module"
+        resource.relative_path_module_source = "lib/my_module.py"
+
+        f = OxidizedFinder(relative_path_origin=self.td)
+        f.add_resource(resource)
+
+        self.assertFalse(f.is_package("my_module"))
+        self.assertEqual(f.get_filename("my_module"), str(source_path))
+        self.assertEqual(f.get_source("my_module"), "import io\n")
+        self.assertEqual(f.get_data(str(source_path)), b"import io\n")
+
+        # Code objects refer to the source file, so tools like coverage and
+        # tracebacks can find it.
+        code = f.get_code("my_module")
+        self.assertEqual(code.co_filename, str(source_path))
+
 
 if __name__ == "__main__":
     # Reset command arguments so test runner isn't confused.
@@ -0,0 +1,147 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+import os
+import pathlib
+import sys
+import tempfile
+import unittest
+
+from oxidized_importer import (
+    OxidizedFinder,
+    OxidizedResourceCollector,
+    find_resources_in_path,
+)
+
+try:
+    import pkg_resources
+except ImportError:
+    pkg_resources = None
+
+
+@unittest.skipIf(pkg_resources is None, "pkg_resources not available")
+class TestImporterPkgResources(unittest.TestCase):
+    def setUp(self):
+        self.raw_temp_dir = tempfile.TemporaryDirectory(
+            prefix="oxidized_importer-test-"
+        )
+        self.td = pathlib.Path(self.raw_temp_dir.name)
+        self.old_meta_path = list(sys.meta_path)
+
+    def tearDown(self):
+        sys.meta_path[:] = self.old_meta_path
+
+        for name in list(sys.modules):
+            if name.startswith("oxidized_pkg_resources_"):
+                del sys.modules[name]
+
+        self.raw_temp_dir.cleanup()
+        del self.raw_temp_dir
+        del self.td
+
+    def _write_distribution(self, name):
+        dist_info = self.td / ("%s-1.0.dist-info" % name)
+        dist_info.mkdir()
+
+        with (dist_info / "METADATA").open("w", encoding="utf-8") as fh:
+            fh.write("Name: %s\n" % name)
+            fh.write("Version: 1.0\n")
+
+        with (dist_info / "entry_points.txt").open("w", encoding="utf-8") as fh:
+            fh.write("[%s]\n" % name)
+            fh.write("script = %s:main\n" % name)
+
+    def _make_package(self, name):
+        package_path = self.td / name
+        package_path.mkdir()
+
+        with (package_path / "__init__.py").open("wb"):
+            pass
+
+        with (package_path / "module.py").open("wb"):
+            pass
+
+        with (package_path / "resource.txt").open("wb") as fh:
+            fh.write(b"resource")
+
+        (package_path / "data").mkdir()
+        with (package_path / "data" / "child.txt").open("wb") as fh:
+            fh.write(b"child")
+
+        return package_path
+
+    def _finder_from_td(self):
+        collector = OxidizedResourceCollector(policy="in-memory-only")
+        for r in find_resources_in_path(self.td):
+            collector.add_in_memory(r)
+
+        f = OxidizedFinder()
+        f.add_resources(collector.oxidize()[0])
+
+        return f
+
+    def test_distribution(self):
+        self._write_distribution("oxidized_pkg_resources_dist")
+        f = self._finder_from_td()
+        f.register_pkg_resources()
+
+        dist = pkg_resources.get_distribution("oxidized_pkg_resources_dist")
+        self.assertEqual(dist.version, "1.0")
+
+        eps = list(pkg_resources.iter_entry_points("oxidized_pkg_resources_dist"))
+        self.assertEqual(len(eps), 1)
+        self.assertEqual(eps[0].name, "script")
+        self.assertEqual(eps[0].module_name, "oxidized_pkg_resources_dist")
+
+        # Registration doesn't touch sys.path.
+        self.assertNotIn(sys.executable, sys.path)
+
+    def test_resources(self):
+        self._make_package("oxidized_pkg_resources_package")
+        f = self._finder_from_td()
+        f.register_pkg_resources()
+
+        sys.meta_path.insert(0, f)
+
+        import oxidized_pkg_resources_package.module
+
+        for name in (
+            "oxidized_pkg_resources_package",
+            "oxidized_pkg_resources_package.module",
+        ):
+            self.assertEqual(
+                pkg_resources.resource_string(name, "resource.txt"), b"resource"
+            )
+
+        package = "oxidized_pkg_resources_package"
+
+        self.assertTrue(pkg_resources.resource_exists(package, "resource.txt"))
+        self.assertTrue(pkg_resources.resource_exists(package, "data"))
+        self.assertFalse(pkg_resources.resource_exists(package, "missing"))
+        self.assertTrue(pkg_resources.resource_isdir(package, "data"))
+        self.assertFalse(pkg_resources.resource_isdir(package, "resource.txt"))
+        self.assertEqual(
+            pkg_resources.resource_listdir(package, ""), ["data", "resource.txt"]
+        )
+        self.assertEqual(pkg_resources.resource_listdir(package, "data"), ["child.txt"])
+
+        with pkg_resources.resource_stream(package, "data/child.txt") as fh:
+            self.assertEqual(fh.read(), b"child")
+
+        manager = pkg_resources.ResourceManager()
+        manager.set_extraction_path(str(self.td / "extracted"))
+
+        path = manager.resource_filename(package, "data/child.txt")
+        with open(path, "rb") as fh:
+            self.assertEqual(fh.read(), b"child")
+
+        path = manager.resource_filename(package, "data")
+        self.assertTrue(os.path.isdir(path))
+        self.assertEqual(os.listdir(path), ["child.txt"])
+
+
+if __name__ == "__main__":
+    # Reset command arguments so test runner isn't confused.
+    sys.argv[1:] = []
+    unittest.main(exit=False)
@@ -2,6 +2,7 @@
 # License, v. 2.0. If a copy of the MPL was not distributed with this
 # file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+import importlib.resources
 import io
 import pathlib
 import sys
@@ -120,11 +121,116 @@ class TestImporterResourceReading(unittest.TestCase):
         self.assertTrue(r.is_resource("child0/a.txt"))
         self.assertTrue(r.is_resource("child1/b.txt"))
 
-        self.assertEqual(r.contents(), ["child0/a.txt", "child1/b.txt"])
+        # Directories are listed, not the resources in them.
+        self.assertEqual(r.contents(), ["child0", "child1"])
+        self.assertFalse(r.is_resource("child0"))
 
         self.assertEqual(r.open_resource("child0/a.txt").getvalue(), b"a")
         self.assertEqual(r.open_resource("child1/b.txt").getvalue(), b"b")
 
+    def _make_nested_resources(self):
+        p = self._make_package("my_package")
+
+        with (p / "resource.txt").open("wb") as fh:
+            fh.write(b"my resource")
+
+        (p / "child" / "grandchild").mkdir(parents=True)
+
+        with (p / "child" / "a.txt").open("wb") as fh:
+            fh.write(b"a")
+        with (p / "child" / "grandchild" / "b.txt").open("wb") as fh:
+            fh.write(b"b")
+
+    def test_files(self):
+        self._make_nested_resources()
+
+        f = self._finder_from_td()
+        root = f.get_resource_reader("my_package").files()
+
+        self.assertEqual(root.name, "my_package")
+        self.assertTrue(root.is_dir())
+        self.assertFalse(root.is_file())
+
+        self.assertEqual(
+            sorted(p.name for p in root.iterdir()), ["child", "resource.txt"]
+        )
+
+        child = root.joinpath("child")
+        self.assertTrue(child.is_dir())
+        self.assertFalse(child.is_file())
+        self.assertEqual(
+            sorted(p.name for p in child.iterdir()), ["a.txt", "grandchild"]
+        )
+
+        resource = root.joinpath("child", "grandchild", "b.txt")
+        self.assertEqual(resource.name, "b.txt")
+        self.assertTrue(resource.is_file())
+        self.assertFalse(resource.is_dir())
+        self.assertEqual(resource.read_bytes(), b"b")
+
+        resource = root.joinpath("child/a.txt")
+        self.assertEqual(resource.read_bytes(), b"a")
+        self.assertEqual(resource.read_text(), "a")
+        self.assertEqual(resource.read_text(encoding="utf-8"), "a")
+
+        self.assertTrue(root.joinpath("child/../resource.txt").is_file())
+        self.assertTrue(root.joinpath(pathlib.PurePosixPath("child/a.txt")).is_file())
+
+        missing = root.joinpath("missing")
+        self.assertFalse(missing.is_file())
+        self.assertFalse(missing.is_dir())
+
+        with self.assertRaises(FileNotFoundError):
+            missing.read_bytes()
+
+        with self.assertRaises(IsADirectoryError):
+            child.read_bytes()
+
+        with self.assertRaises(NotADirectoryError):
+            list(resource.iterdir())
+
+        with self.assertRaises(ValueError):
+            root.joinpath("..")
+
+    def test_files_open(self):
+        self._make_nested_resources()
+
+        f = self._finder_from_td()
+        resource = f.get_resource_reader("my_package").files().joinpath("resource.txt")
+
+        with resource.open("rb") as fh:
+            self.assertEqual(fh.read(), b"my resource")
+
+        with resource.open() as fh:
+            self.assertIsInstance(fh, io.TextIOWrapper)
+            self.assertEqual(fh.read(), "my resource")
+
+        with resource.open("r", encoding="utf-8") as fh:
+            self.assertEqual(fh.read(), "my resource")
+
+        with resource.open(mode="rt", newline="") as fh:
+            self.assertEqual(fh.read(), "my resource")
+
+        with self.assertRaises(ValueError):
+            resource.open("w")
+
+    @unittest.skipIf(
+        not hasattr(importlib.resources, "files"),
+        "importlib.resources.files() not available",
+    )
+    def test_importlib_resources_files(self):
+        self._make_nested_resources()
+
+        f = self._finder_from_td()
+
+        sys.meta_path.insert(0, f)
+        try:
+            files = importlib.resources.files("my_package")
+            self.assertEqual(files.joinpath("child", "a.txt").read_bytes(), b"a")
+        finally:
+            sys.meta_path.remove(f)
+            sys.modules.pop("my_package", None)
+
 
 if __name__ == "__main__":
     # Reset command arguments so test runner isn't confused.
@@ -2,10 +2,14 @@
 # License, v. 2.0. If a copy of the MPL was not distributed with this
 # file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+import hashlib
 import importlib.machinery
+import importlib.util
 import marshal
 import pathlib
 import sys
+import tempfile
+import threading
 import unittest
 
 from oxidized_importer import (
@@ -52,6 +56,7 @@ class TestImporterResources(unittest.TestCase):
         self.assertIsNone(resource.relative_path_extension_module_shared_library)
         self.assertIsNone(resource.relative_path_package_resources)
         self.assertIsNone(resource.relative_path_distribution_resources)
+        self.assertIsNone(resource.provenance)
 
     def test_resources_frozen(self):
         f = OxidizedFinder()
@@ -538,6 +543,108 @@ class TestImporterResources(unittest.TestCase):
         with self.assertRaises(TypeError):
             resource.relative_path_distribution_resources = {"foo": None}
 
+    def test_resource_provenance(self):
+        resource = OxidizedResource()
+
+        resource.provenance = "distribution:foo==1.0"
+        self.assertEqual(resource.provenance, "distribution:foo==1.0")
+
+        resource.provenance = None
+        self.assertIsNone(resource.provenance)
+
+        with self.assertRaises(TypeError):
+            del resource.provenance
+
+        with self.assertRaises(TypeError):
+            resource.provenance = b"foo"
+
+    def test_resource_metadata(self):
+        resource = OxidizedResource()
+        resource.name = "foo"
+        resource.flavor = "module"
+
+        self.assertEqual(resource.locations, [])
+        self.assertEqual(resource.in_memory_size, 0)
+        self.assertEqual(resource.in_memory_sha256, {})
+        self.assertEqual(resource.bytecode_optimize_levels, [])
+        self.assertIsNone(resource.provenance_distribution)
+
+        resource.in_memory_source = b"import io"
+        resource.in_memory_bytecode_opt2 = b"bytecode"
+        resource.in_memory_package_resources = {"data.txt": b"data"}
+        resource.relative_path_module_bytecode = "foo.pyc"
+
+        self.assertEqual(resource.locations, ["in-memory", "filesystem-relative"])
+        self.assertEqual(resource.in_memory_size, 21)
+        self.assertEqual(
+            resource.in_memory_sha256,
+            {
+                "in_memory_bytecode_opt2": hashlib.sha256(b"bytecode").hexdigest(),
+                "in_memory_package_resources/data.txt": hashlib.sha256(
+                    b"data"
+                ).hexdigest(),
+                "in_memory_source": hashlib.sha256(b"import io").hexdigest(),
+            },
+        )
+        self.assertEqual(resource.bytecode_optimize_levels, [0, 2])
+
+        resource.provenance = "distribution:foo==1.0"
+        self.assertEqual(resource.provenance_distribution, ("foo", "1.0"))
+        resource.provenance = "conda:bar==2.0"
+        self.assertEqual(resource.provenance_distribution, ("bar", "2.0"))
+        resource.provenance = "path:/foo.py"
+        self.assertIsNone(resource.provenance_distribution)
+
+        with self.assertRaises(AttributeError):
+            resource.in_memory_size = 42
+
+    def test_resource_metadata_builtin(self):
+        f = OxidizedFinder()
+
+        resource = [r for r in f.indexed_resources() if r.name == "_io"][0]
+        self.assertEqual(resource.locations, ["builtin"])
+
+    def test_indexed_resources_filter(self):
+        f = OxidizedFinder()
+
+        for name, provenance in (
+            ("foo", "distribution:foo==1.0"),
+            ("foo.bar", "distribution:foo==1.0"),
+            ("foobar", "distribution:foobar==1.0"),
+            ("baz", None),
+        ):
+            resource = OxidizedResource()
+            resource.name = name
+            resource.flavor = "module"
+            resource.in_memory_source = b"import io"
+            resource.provenance = provenance
+            f.add_resource(resource)
+
+        resource = OxidizedResource()
+        resource.name = "relative"
+        resource.flavor = "module"
+        resource.relative_path_module_source = "relative.py"
+        f.add_resource(resource)
+
+        def names(**kwargs):
+            return [r.name for r in f.indexed_resources(**kwargs)]
+
+        self.assertEqual(
+            names(location="in-memory"), ["baz", "foo", "foo.bar", "foobar"]
+        )
+        self.assertEqual(names(location="filesystem-relative"), ["relative"])
+        self.assertIn("_io", names(flavor="builtin"))
+        self.assertNotIn("_io", names(flavor="module"))
+        self.assertEqual(names(package="foo"), ["foo", "foo.bar"])
+        self.assertEqual(names(distribution="foo"), ["foo", "foo.bar"])
+        self.assertEqual(names(package="foo", distribution="foobar"), [])
+
+        with self.assertRaisesRegex(ValueError, "unknown resource flavor"):
+            f.indexed_resources(flavor="bad")
+
+        with self.assertRaisesRegex(ValueError, "unknown resource location"):
+            f.indexed_resources(location="bad")
+
     def test_add_resource_bad_type(self):
         f = OxidizedFinder()
 
@@ -586,6 +693,115 @@ class TestImporterResources(unittest.TestCase):
         resources = [r for r in f.indexed_resources() if r.name in ("foo_a", "foo_b")]
         self.assertEqual(len(resources), 2)
 
+    def test_add_resource_replace(self):
+        f = OxidizedFinder()
+
+        resource = OxidizedResource()
+        resource.name = "my_module"
+        resource.flavor = "module"
+        resource.in_memory_source = b"value = 1"
+        f.add_resource(resource)
+
+        resource.in_memory_source = b"value = 2"
+        self.assertEqual(f.get_source("my_module"), "value = 1")
+
+        f.add_resource(resource)
+        self.assertEqual(f.get_source("my_module"), "value = 2")
+
+        resources = [r for r in f.indexed_resources() if r.name == "my_module"]
+        self.assertEqual(len(resources), 1)
+
+    def test_add_resource_import(self):
+        f = OxidizedFinder()
+
+        resource = OxidizedResource()
+        resource.name = "my_plugin"
+        resource.flavor = "module"
+        resource.in_memory_bytecode = marshal.dumps(
+            compile(b"value = 42", "my_plugin.py", "exec")
+        )
+        f.add_resource(resource)
+
+        spec = f.find_spec("my_plugin", None)
+        m = importlib.util.module_from_spec(spec)
+        spec.loader.exec_module(m)
+        self.assertEqual(m.value, 42)
+
+    def test_remove_resource(self):
+        f = OxidizedFinder()
+
+        resource = OxidizedResource()
+        resource.name = "my_module"
+        resource.flavor = "module"
+        resource.in_memory_source = b"import io"
+        f.add_resource(resource)
+
+        removed = f.remove_resource("my_module")
+        self.assertIsInstance(removed, OxidizedResource)
+        self.assertEqual(removed.name, "my_module")
+        self.assertEqual(removed.in_memory_source, b"import io")
+
+        self.assertEqual(
+            [r for r in f.indexed_resources() if r.name == "my_module"], []
+        )
+        self.assertIsNone(f.find_spec("my_module", None))
+
+        with self.assertRaises(KeyError):
+            f.remove_resource("my_module")
+
+    def test_concurrent_modification(self):
+        f = OxidizedFinder()
+
+        for i in range(100):
+            resource = OxidizedResource()
+            resource.name = "module_%d" % i
+            resource.flavor = "module"
+            resource.in_memory_source = b"value = %d\n" % i
+            f.add_resource(resource)
+
+        errors = []
+        done = threading.Event()
+
+        def read():
+            try:
+                while not done.is_set():
+                    for i in range(100):
+                        name = "module_%d" % i
+                        self.assertIsNotNone(f.find_spec(name, None))
+                        self.assertEqual(f.get_source(name), "value = %d\n" % i)
+                        f.get_code(name)
+            except Exception as e:
+                errors.append(e)
+
+        def write():
+            try:
+                for i in range(1000):
+                    resource = OxidizedResource()
+                    resource.name = "other_%d" % i
+                    resource.flavor = "module"
+                    resource.in_memory_source = b"value = %d\n" % i
+                    f.add_resource(resource)
+                    f.remove_resource(resource.name)
+            except Exception as e:
+                errors.append(e)
+            finally:
+                done.set()
+
+        old_interval = sys.getswitchinterval()
+        sys.setswitchinterval(1e-6)
+        try:
+            threads = [threading.Thread(target=read) for _ in range(4)]
+            threads.append(threading.Thread(target=write))
+
+            for t in threads:
+                t.start()
+            for t in threads:
+                t.join()
+        finally:
+            sys.setswitchinterval(old_interval)
+
+        self.assertEqual(errors, [])
+
     def test_serialize_simple(self):
         f = OxidizedFinder()
 
@@ -615,6 +831,53 @@ class TestImporterResources(unittest.TestCase):
         self.assertEqual(modules["my_module"].in_memory_source, b"import io")
         self.assertEqual(modules["module_b"].in_memory_bytecode, b"dummy bytecode")
 
+    def test_resources_memory_usage(self):
+        f = OxidizedFinder()
+        self.assertEqual(
+            f.resources_memory_usage(),
+            {
+                "mapped_bytes": 0,
+                "resident_bytes": None if sys.platform == "win32" else 0,
+            },
+        )
+
+        m = OxidizedResource()
+        m.name = "my_module"
+        m.flavor = "module"
+        m.in_memory_source = b"import io" * 1000
+        f.add_resource(m)
+
+        serialized = f.serialize_indexed_resources()
+
+        f2 = OxidizedFinder(resources_data=serialized)
+        usage = f2.resources_memory_usage()
+        self.assertEqual(usage["mapped_bytes"], len(serialized))
+
+        if sys.platform == "win32":
+            self.assertIsNone(usage["resident_bytes"])
+        else:
+            # The data was just written, so all of it is resident.
+            self.assertEqual(usage["resident_bytes"], len(serialized))
+
+        with tempfile.TemporaryDirectory() as td:
+            path = pathlib.Path(td) / "resources"
+            path.write_bytes(serialized)
+
+            f3 = OxidizedFinder(resources_file=path)
+            usage = f3.resources_memory_usage()
+            self.assertEqual(usage["mapped_bytes"], len(serialized))
+
+            if sys.platform != "win32":
+                self.assertLessEqual(usage["resident_bytes"], len(serialized))
+
+            self.assertEqual(
+                f3.indexed_resources(flavor="module")[0].in_memory_source,
+                b"import io" * 1000,
+            )
+
+            # Release the mapping so the file can be deleted on Windows.
+            del f3
+
 
 if __name__ == "__main__":
     # Reset command arguments so test runner isn't confused.
@@ -0,0 +1,144 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Import modules and resources from zip archives.
+
+use cpython::{ObjectProtocol, PyDict, PyResult, Python};
+
+/// Python source installing a meta path finder for zip archives.
+///
+/// Archives are read by `zipimport`, which also handles archives appended
+/// to other files. So zipapp and pex style archives work, including ones
+/// appended to the executable. The finder is used whether or not the
+/// filesystem importer is enabled.
+const ZIP_ARCHIVES_SOURCE: &str = r#"
+import importlib.machinery
+import importlib.util
+import os
+import sys
+import warnings
+import zipimport
+
+
+class ZipArchiveFinder:
+    """Meta path finder for modules in zip archives.
+
+    Top-level modules are searched in the root of each archive. Submodules
+    are searched in the entries of their package's __path__ inside an
+    archive.
+    """
+
+    def __init__(self, archives):
+        self.archives = archives
+        self._importers = {}
+
+    def _importer(self, path):
+        try:
+            return self._importers[path]
+        except KeyError:
+            pass
+
+        try:
+            importer = zipimport.zipimporter(path)
+        except zipimport.ZipImportError:
+            importer = None
+
+        self._importers[path] = importer
+        return importer
+
+    def _in_archive(self, path):
+        return any(path == archive or path.startswith(os.path.join(archive, ''))
+                   for archive in self.archives)
+
+    def find_spec(self, fullname, path=None, target=None):
+        if path is None:
+            path = self.archives
+        else:
+            path = [p for p in path if isinstance(p, str) and self._in_archive(p)]
+
+        for entry in path:
+            importer = self._importer(entry)
+            if importer is None:
+                continue
+
+            if hasattr(importer, 'find_spec'):
+                spec = importer.find_spec(fullname, target)
+                if spec is not None and spec.loader is not None:
+                    return spec
+
+                continue
+
+            loader, _portions = importer.find_loader(fullname)
+            if loader is not None:
+                return importlib.util.spec_from_loader(
+                    fullname, loader, is_package=loader.is_package(fullname))
+
+        return None
+
+    def find_distributions(self, context=None):
+        from importlib.metadata import DistributionFinder
+
+        if context is None:
+            context = DistributionFinder.Context()
+
+        context = DistributionFinder.Context(name=context.name, path=list(self.archives))
+
+        return importlib.machinery.PathFinder.find_distributions(context)
+
+    def invalidate_caches(self):
+        self._importers.clear()
+        zipimport._zip_directory_cache.clear()
+
+
+def install(paths):
+    archives = []
+
+    for path in paths:
+        path = os.path.abspath(os.path.expanduser(path))
+
+        try:
+            zipimport.zipimporter(path)
+        except zipimport.ZipImportError as e:
+            warnings.warn('ignoring zip archive %s: %s' % (path, e), RuntimeWarning)
+            continue
+
+        archives.append(path)
+
+    if not archives:
+        return
+
+    index = None
+    for i, finder in enumerate(sys.meta_path):
+        if type(finder).__name__ == 'OxidizedFinder':
+            index = i + 1
+            break
+
+    if index is None:
+        if importlib.machinery.PathFinder in sys.meta_path:
+            index = sys.meta_path.index(importlib.machinery.PathFinder)
+        else:
+            index = len(sys.meta_path)
+
+    sys.meta_path.insert(index, ZipArchiveFinder(archives))
+"#;
+
+/// Install a meta path finder importing from the zip archives at `paths`.
+///
+/// Warnings are emitted instead of errors for archives that can't be read,
+/// as applications should keep working without them. The finder is placed
+/// after `OxidizedFinder`, so packed resources take precedence.
+pub(crate) fn install_zip_archives(py: Python, paths: &[String]) -> PyResult<()> {
+    let globals = PyDict::new(py);
+    globals.set_item(py, "__builtins__", py.import("builtins")?)?;
+    globals.set_item(py, "__name__", "_pyembed_zip_archives")?;
+
+    py.run(ZIP_ARCHIVES_SOURCE, Some(&globals), None)?;
+
+    let install = globals
+        .get_item(py, "install")
+        .expect("install should be defined");
+    install.call(py, (paths.to_vec(),), None)?;
+
+    Ok(())
+}
@@ -38,6 +38,41 @@ pub fn is_executable(_metadata: &std::fs::Metadata) -> bool {
     false
 }
 
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    Ok(())
+}
+
+#[cfg(windows)]
+fn set_mode(_path: &Path, _mode: u32) -> Result<()> {
+    Ok(())
+}
+
+/// Filesystem attributes to apply to a file when it is written.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FileAttributes {
+    /// Permissions mode of the file, e.g. `0o644`.
+    ///
+    /// Ignored on Windows.
+    pub mode: Option<u32>,
+
+    /// Modification time of the file in seconds since the UNIX epoch.
+    pub mtime: Option<u64>,
+
+    /// Name of the user that should own the file.
+    ///
+    /// This is a hint for tools packaging the written files. It isn't
+    /// applied when writing files.
+    pub owner: Option<String>,
+
+    /// Name of the group that should own the file.
+    ///
+    /// This is a hint for tools packaging the written files. It isn't
+    /// applied when writing files.
+    pub group: Option<String>,
+}
+
 /// Represents file content, agnostic of storage location.
 #[derive(Clone, Debug, PartialEq)]
 pub struct FileContent {
@@ -64,6 +99,9 @@ impl TryFrom<&Path> for FileContent {
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct FileManifest {
     files: BTreeMap<PathBuf, FileContent>,
+
+    /// Attributes of files in `files`.
+    attributes: BTreeMap<PathBuf, FileAttributes>,
 }
 
 impl FileManifest {
@@ -90,9 +128,30 @@ impl FileManifest {
             self.add_file(key.as_path(), value)?;
         }
 
+        for (key, value) in &other.attributes {
+            self.attributes.insert(key.clone(), value.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Set the filesystem attributes of a file in this manifest.
+    pub fn set_file_attributes(&mut self, path: &Path, attributes: &FileAttributes) -> Result<()> {
+        if !self.files.contains_key(path) {
+            return Err(anyhow!("file not in manifest: {}", path.display()));
+        }
+
+        self.attributes
+            .insert(path.to_path_buf(), attributes.clone());
+
         Ok(())
     }
 
+    /// Obtain the filesystem attributes of a file in this manifest.
+    pub fn get_file_attributes(&self, path: &Path) -> Option<&FileAttributes> {
+        self.attributes.get(path)
+    }
+
     /// All relative directories contained within files in this manifest.
     ///
     /// The root directory is not represented in the return value.
@@ -138,21 +197,53 @@ impl FileManifest {
     }
 
     /// Write the contents of the install manifest to a filesystem path.
+    ///
+    /// Each file is written next to its destination and renamed over it, so
+    /// running programs that memory map an existing file keep seeing its old
+    /// content rather than a partially written file.
     pub fn write_to_path(&self, path: &Path) -> Result<()> {
         for (p, c) in &self.files {
             let dest_path = path.join(p);
             let parent = dest_path
                 .parent()
                 .ok_or_else(|| anyhow!("unable to resolve parent directory"))?;
+            let file_name = dest_path
+                .file_name()
+                .ok_or_else(|| anyhow!("unable to resolve file name"))?;
 
             std::fs::create_dir_all(parent)
                 .context("creating parent directory for FileManifest")?;
 
-            let mut fh = std::fs::File::create(&dest_path)?;
-            fh.write_all(&c.data)?;
-            if c.executable {
-                set_executable(&mut fh)?;
+            let mut temp_name = file_name.to_os_string();
+            temp_name.push(".pyoxidizer-new");
+            let temp_path = parent.join(temp_name);
+
+            {
+                let mut fh = std::fs::File::create(&temp_path)
+                    .with_context(|| format!("creating {}", temp_path.display()))?;
+                fh.write_all(&c.data)?;
+                if c.executable {
+                    set_executable(&mut fh)?;
+                }
+            }
+
+            if let Some(attributes) = self.attributes.get(p) {
+                if let Some(mode) = attributes.mode {
+                    set_mode(&temp_path, mode)?;
+                }
+
+                // Must be set after the file is closed, as writes update it.
+                if let Some(mtime) = attributes.mtime {
+                    filetime::set_file_mtime(
+                        &temp_path,
+                        filetime::FileTime::from_unix_time(mtime as i64, 0),
+                    )
+                    .with_context(|| format!("setting mtime of {}", dest_path.display()))?;
+                }
             }
+
+            std::fs::rename(&temp_path, &dest_path)
+                .with_context(|| format!("renaming to {}", dest_path.display()))?;
         }
 
         Ok(())
@@ -205,6 +296,90 @@ mod tests {
         assert!(res.is_err());
     }
 
+    #[test]
+    fn test_write_attributes() -> Result<()> {
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+
+        let mut v = FileManifest::default();
+        let f = FileContent {
+            data: vec![42],
+            executable: false,
+        };
+
+        v.add_file(&PathBuf::from("dir/foo"), &f)?;
+        v.add_file(&PathBuf::from("bar"), &f)?;
+        assert!(v
+            .set_file_attributes(&PathBuf::from("missing"), &FileAttributes::default())
+            .is_err());
+
+        let attributes = FileAttributes {
+            mode: Some(0o600),
+            mtime: Some(1_000_000_000),
+            owner: Some("root".to_string()),
+            group: None,
+        };
+        v.set_file_attributes(&PathBuf::from("dir/foo"), &attributes)?;
+
+        let mut other = FileManifest::default();
+        other.add_manifest(&v)?;
+        assert_eq!(
+            other.get_file_attributes(&PathBuf::from("dir/foo")),
+            Some(&attributes)
+        );
+        assert_eq!(other.get_file_attributes(&PathBuf::from("bar")), None);
+
+        other.write_to_path(td.path())?;
+
+        let metadata = std::fs::metadata(td.path().join("dir/foo"))?;
+        assert_eq!(
+            filetime::FileTime::from_last_modification_time(&metadata).unix_seconds(),
+            1_000_000_000
+        );
+        #[cfg(unix)]
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_write_to_path_replaces_files() -> Result<()> {
+        use std::io::Read;
+
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+
+        let mut v = FileManifest::default();
+        v.add_file(
+            &PathBuf::from("foo"),
+            &FileContent {
+                data: vec![1],
+                executable: false,
+            },
+        )?;
+        v.write_to_path(td.path())?;
+
+        // A handle on the old file keeps seeing its content.
+        let mut old = std::fs::File::open(td.path().join("foo"))?;
+
+        let mut v = FileManifest::default();
+        v.add_file(
+            &PathBuf::from("foo"),
+            &FileContent {
+                data: vec![2, 3],
+                executable: false,
+            },
+        )?;
+        v.write_to_path(td.path())?;
+
+        let mut data = Vec::new();
+        old.read_to_end(&mut data)?;
+        assert_eq!(data, vec![1]);
+        assert_eq!(std::fs::read(td.path().join("foo"))?, vec![2, 3]);
+        assert!(!td.path().join("foo.pyoxidizer-new").exists());
+
+        Ok(())
+    }
+
     #[test]
     fn test_relative_directories() {
         let mut v = FileManifest::default();
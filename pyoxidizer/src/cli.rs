@@ -42,6 +42,23 @@ This command will invoke Rust's build system tool (Cargo) to build
 the project.
 ";
 
+const LOCK_ABOUT: &str = "\
+Record the packages installed by a PyOxidizer project.
+
+The PATH argument is a filesystem path to a directory containing an
+existing PyOxidizer enabled project.
+
+Targets in the configuration file are resolved without being built.
+Packages installed from requirements and lock files are downloaded and
+their URLs and hashes are recorded in a pyoxidizer.lock file next to the
+configuration file. Downloaded files are stored in a wheel cache in the
+build directory.
+
+Subsequent builds install the packages recorded in pyoxidizer.lock from
+the wheel cache, so they use identical files and don't need network
+access once the wheel cache is populated.
+";
+
 const INIT_RUST_PROJECT_ABOUT: &str = "\
 Create a new Rust project embedding Python.
 
@@ -61,6 +78,53 @@ they were created with.
 On success, instructions on potential next steps are printed.
 ";
 
+const PYTHON_DISTRIBUTION_BUILD_ABOUT: &str = "\
+Build a Python distribution archive from CPython source.
+
+The SOURCE_PATH argument is a CPython source tarball or a directory holding
+extracted CPython source. CPython is configured with a shared libpython,
+compiled and installed. The installation is written as a distribution
+archive to DESTINATION_PATH.
+
+Arguments given with --configure-arg are passed to CPython's configure
+script. e.g. `--configure-arg=--with-lto`.
+
+The produced archive can be used from configuration files via
+`PythonDistribution(local_path=...)` or
+`default_python_distribution(local_path=...)`.
+
+Only Linux glibc targets are supported. A C compiler and the development
+packages of libraries CPython uses must be installed.
+";
+
+const RESOURCES_DELTA_ABOUT: &str = "\
+Write a binary delta between two packed resources files.
+
+The BASE_PATH argument is a packed resources file as previously shipped.
+The TARGET_PATH argument is the new version of that file. A delta that
+reconstructs TARGET_PATH from BASE_PATH is written to DESTINATION_PATH.
+
+When only some resources changed, the delta is much smaller than the new
+file. Deltas are applied with `pyoxidizer resources apply-delta` or with
+the `python_packed_resources::delta::apply_delta()` Rust function. Both
+verify that the delta is applied to the file it was produced from.
+";
+
+const RESOURCES_INSPECT_ABOUT: &str = "\
+Inspect packed resources data.
+
+The PATH argument is a packed resources file or an executable or library
+with embedded packed resources data. The data is located, its index is
+parsed and verified and every resource is listed along with its flags and
+the size and location of its data.
+
+Data encrypted with `packed_resources_encryption_key` can only be inspected
+by passing the key with --encryption-key.
+
+With --extract, the data of the resource with the given name is written to
+the directory given by --dest-dir instead.
+";
+
 const RUN_BUILD_SCRIPT_ABOUT: &str = "\
 Runs a crate build script to generate Python artifacts.
 
@@ -197,6 +261,114 @@ pub fn run_cli() -> Result<()> {
                         .help("Target to resolve"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("lock")
+                .about("Record packages installed by a PyOxidizer project")
+                .long_about(LOCK_ABOUT)
+                .arg(
+                    Arg::with_name("target_triple")
+                        .long("target-triple")
+                        .takes_value(true)
+                        .help("Rust target triple to lock packages for"),
+                )
+                .arg(
+                    Arg::with_name("path")
+                        .long("path")
+                        .takes_value(true)
+                        .default_value(".")
+                        .value_name("PATH")
+                        .help("Directory containing project to lock"),
+                )
+                .arg(
+                    Arg::with_name("targets")
+                        .value_name("TARGET")
+                        .multiple(true)
+                        .help("Target to resolve"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("resources")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .about("Work with packed resources data")
+                .subcommand(
+                    SubCommand::with_name("delta")
+                        .about("Write a binary delta between two packed resources files")
+                        .long_about(RESOURCES_DELTA_ABOUT)
+                        .arg(
+                            Arg::with_name("base_path")
+                                .required(true)
+                                .value_name("BASE_PATH")
+                                .help("Path to previous packed resources file"),
+                        )
+                        .arg(
+                            Arg::with_name("target_path")
+                                .required(true)
+                                .value_name("TARGET_PATH")
+                                .help("Path to new packed resources file"),
+                        )
+                        .arg(
+                            Arg::with_name("dest_path")
+                                .required(true)
+                                .value_name("DESTINATION_PATH")
+                                .help("Path of the delta to write"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("inspect")
+                        .about("Inspect packed resources data")
+                        .long_about(RESOURCES_INSPECT_ABOUT)
+                        .arg(
+                            Arg::with_name("encryption_key")
+                                .long("encryption-key")
+                                .takes_value(true)
+                                .value_name("KEY_PATH")
+                                .help("Path to key the data is encrypted with"),
+                        )
+                        .arg(
+                            Arg::with_name("extract")
+                                .long("extract")
+                                .takes_value(true)
+                                .value_name("NAME")
+                                .help("Name of resource to extract"),
+                        )
+                        .arg(
+                            Arg::with_name("dest_dir")
+                                .long("dest-dir")
+                                .takes_value(true)
+                                .default_value(".")
+                                .value_name("DIR")
+                                .help("Directory to extract resource data to"),
+                        )
+                        .arg(
+                            Arg::with_name("path")
+                                .required(true)
+                                .value_name("PATH")
+                                .help("Path to packed resources file or binary to inspect"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("apply-delta")
+                        .about("Apply a binary delta to a packed resources file")
+                        .arg(
+                            Arg::with_name("base_path")
+                                .required(true)
+                                .value_name("BASE_PATH")
+                                .help("Path to packed resources file the delta was produced from"),
+                        )
+                        .arg(
+                            Arg::with_name("delta_path")
+                                .required(true)
+                                .value_name("DELTA_PATH")
+                                .help("Path to delta to apply"),
+                        )
+                        .arg(
+                            Arg::with_name("dest_path")
+                                .required(true)
+                                .value_name("DESTINATION_PATH")
+                                .help("Path of the packed resources file to write"),
+                        ),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("run")
                 .setting(AppSettings::TrailingVarArg)
@@ -225,8 +397,54 @@ pub fn run_cli() -> Result<()> {
                         .takes_value(true)
                         .help("Build target to run"),
                 )
+                .arg(
+                    Arg::with_name("watch_resources")
+                        .long("watch-resources")
+                        .help("Reload packed resources files when they change"),
+                )
                 .arg(Arg::with_name("extra").multiple(true)),
         )
+        .subcommand(
+            SubCommand::with_name("python-distribution-build")
+                .about("Build a Python distribution archive from CPython source")
+                .long_about(PYTHON_DISTRIBUTION_BUILD_ABOUT)
+                .arg(
+                    Arg::with_name("target_triple")
+                        .long("target-triple")
+                        .takes_value(true)
+                        .help("Rust target triple to build for"),
+                )
+                .arg(
+                    Arg::with_name("configure_args")
+                        .long("configure-arg")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .allow_hyphen_values(true)
+                        .value_name("ARG")
+                        .help("Extra argument to pass to configure"),
+                )
+                .arg(
+                    Arg::with_name("jobs")
+                        .long("jobs")
+                        .short("j")
+                        .takes_value(true)
+                        .default_value("1")
+                        .help("Number of parallel compiler jobs"),
+                )
+                .arg(
+                    Arg::with_name("source_path")
+                        .required(true)
+                        .value_name("SOURCE_PATH")
+                        .help("Path to a CPython source archive or directory"),
+                )
+                .arg(
+                    Arg::with_name("dest_path")
+                        .required(true)
+                        .value_name("DESTINATION_PATH")
+                        .help("Path of the distribution archive to write"),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("python-distribution-extract")
                 .about("Extract a Python distribution archive to a directory")
@@ -246,6 +464,11 @@ pub fn run_cli() -> Result<()> {
         .subcommand(
             SubCommand::with_name("python-distribution-info")
                 .about("Show information about a Python distribution archive")
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("Print information as JSON"),
+                )
                 .arg(
                     Arg::with_name("path")
                         .required(true)
@@ -310,6 +533,24 @@ pub fn run_cli() -> Result<()> {
             )
         }
 
+        ("lock", Some(args)) => {
+            let target_triple = args.value_of("target_triple");
+            let path = args.value_of("path").unwrap();
+            let resolve_targets = if let Some(values) = args.values_of("targets") {
+                Some(values.map(|x| x.to_string()).collect())
+            } else {
+                None
+            };
+
+            projectmgmt::lock(
+                &logger_context.logger,
+                Path::new(path),
+                target_triple,
+                resolve_targets,
+                verbose,
+            )
+        }
+
         ("init-config-file", Some(args)) => {
             let code = args.value_of("python-code");
             let pip_install = if args.is_present("pip-install") {
@@ -336,6 +577,26 @@ pub fn run_cli() -> Result<()> {
             projectmgmt::init_rust_project(&project_path)
         }
 
+        ("python-distribution-build", Some(args)) => {
+            let target_triple = args.value_of("target_triple");
+            let configure_args: Vec<&str> = args
+                .values_of("configure_args")
+                .unwrap_or_default()
+                .collect();
+            let jobs = args.value_of("jobs").unwrap();
+            let source_path = args.value_of("source_path").unwrap();
+            let dest_path = args.value_of("dest_path").unwrap();
+
+            projectmgmt::python_distribution_build(
+                &logger_context.logger,
+                Path::new(source_path),
+                Path::new(dest_path),
+                target_triple,
+                &configure_args,
+                jobs,
+            )
+        }
+
         ("python-distribution-extract", Some(args)) => {
             let dist_path = args.value_of("dist_path").unwrap();
             let dest_path = args.value_of("dest_path").unwrap();
@@ -345,8 +606,9 @@ pub fn run_cli() -> Result<()> {
 
         ("python-distribution-info", Some(args)) => {
             let dist_path = args.value_of("path").unwrap();
+            let json = args.is_present("json");
 
-            projectmgmt::python_distribution_info(dist_path)
+            projectmgmt::python_distribution_info(dist_path, json)
         }
 
         ("python-distribution-licenses", Some(args)) => {
@@ -355,6 +617,48 @@ pub fn run_cli() -> Result<()> {
             projectmgmt::python_distribution_licenses(path)
         }
 
+        ("resources", Some(args)) => match args.subcommand() {
+            ("delta", Some(args)) => {
+                let base_path = args.value_of("base_path").unwrap();
+                let target_path = args.value_of("target_path").unwrap();
+                let dest_path = args.value_of("dest_path").unwrap();
+
+                projectmgmt::resources_delta(
+                    Path::new(base_path),
+                    Path::new(target_path),
+                    Path::new(dest_path),
+                )
+            }
+
+            ("inspect", Some(args)) => {
+                let path = args.value_of("path").unwrap();
+                let encryption_key = args.value_of("encryption_key").map(Path::new);
+                let extract = args.value_of("extract");
+                let dest_dir = args.value_of("dest_dir").unwrap();
+
+                projectmgmt::resources_inspect(
+                    Path::new(path),
+                    encryption_key,
+                    extract,
+                    Path::new(dest_dir),
+                )
+            }
+
+            ("apply-delta", Some(args)) => {
+                let base_path = args.value_of("base_path").unwrap();
+                let delta_path = args.value_of("delta_path").unwrap();
+                let dest_path = args.value_of("dest_path").unwrap();
+
+                projectmgmt::resources_apply_delta(
+                    Path::new(base_path),
+                    Path::new(delta_path),
+                    Path::new(dest_path),
+                )
+            }
+
+            _ => Err(anyhow!("invalid sub-command")),
+        },
+
         ("run-build-script", Some(args)) => {
             let build_script = args.value_of("build-script-name").unwrap();
             let target = args.value_of("target");
@@ -367,6 +671,7 @@ pub fn run_cli() -> Result<()> {
             let release = args.is_present("release");
             let path = args.value_of("path").unwrap();
             let target = args.value_of("target");
+            let watch_resources = args.is_present("watch_resources");
             let extra: Vec<&str> = args.values_of("extra").unwrap_or_default().collect();
 
             projectmgmt::run(
@@ -375,6 +680,7 @@ pub fn run_cli() -> Result<()> {
                 target_triple,
                 release,
                 target,
+                watch_resources,
                 &extra,
                 verbose,
             )
@@ -47,6 +47,7 @@ lazy_static! {
 
     /// Target triples for macOS.
     pub static ref MACOS_TARGET_TRIPLES: Vec<&'static str> = vec![
+        "aarch64-apple-darwin",
         "x86_64-apple-darwin",
     ];
 
@@ -18,6 +18,7 @@ pub mod environment;
 pub mod logging;
 pub mod project_building;
 pub mod project_layout;
+pub mod project_lock;
 pub mod projectmgmt;
 pub mod py_packaging;
 pub mod python_distributions;
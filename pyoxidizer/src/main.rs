@@ -36,6 +36,7 @@ mod environment;
 mod logging;
 mod project_building;
 mod project_layout;
+mod project_lock;
 mod projectmgmt;
 mod py_packaging;
 mod python_distributions;
@@ -5,10 +5,13 @@
 use {
     crate::environment::{canonicalize_path, MINIMUM_RUST_VERSION},
     crate::project_layout::initialize_project,
-    crate::py_packaging::binary::{EmbeddedPythonContext, PythonBinaryBuilder},
+    crate::project_lock::LockMode,
+    crate::py_packaging::binary::{EmbeddedPythonContext, PythonBinaryBuilder, WindowsSubsystem},
+    crate::py_packaging::lockfile::VerifiedArtifact,
     crate::starlark::eval::{eval_starlark_config_file, EvalResult},
     crate::starlark::target::ResolvedTarget,
     anyhow::{anyhow, Context, Result},
+    serde::Serialize,
     slog::warn,
     std::env,
     std::fs::create_dir_all,
@@ -83,6 +86,38 @@ pub struct BuiltExecutable {
     pub binary_data: EmbeddedPythonContext,
 }
 
+/// Describes the inputs of a built executable for auditing.
+#[derive(Debug, Serialize)]
+pub struct BuildManifest {
+    /// File name of the executable.
+    pub exe_name: String,
+
+    /// Rust target triple the executable was built for.
+    pub target_triple: String,
+
+    /// Downloaded artifacts whose hashes were verified.
+    pub verified_artifacts: Vec<VerifiedArtifact>,
+}
+
+impl BuildManifest {
+    /// Filename of the manifest of an executable.
+    pub fn filename(exe_name: &str) -> String {
+        format!("{}.build-manifest.json", exe_name)
+    }
+
+    /// Write the manifest as JSON to a directory.
+    ///
+    /// Returns the path to the written file.
+    pub fn write_to_dir(&self, dir: &Path) -> Result<PathBuf> {
+        let path = dir.join(Self::filename(&self.exe_name));
+
+        std::fs::write(&path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("writing {}", path.display()))?;
+
+        Ok(path)
+    }
+}
+
 /// Build an executable embedding Python using an existing Rust project.
 ///
 /// The path to the produced executable is returned.
@@ -115,6 +150,8 @@ pub fn build_executable_with_rust_project(
     }
     warn!(logger, "building with Rust {}", rust_version);
 
+    ensure_rust_target_installed(target)?;
+
     let target_base_path = build_path.join("target");
     let target_triple_base_path =
         target_base_path
@@ -151,6 +188,18 @@ pub fn build_executable_with_rust_project(
     if exe.requires_jemalloc() {
         features.push("jemalloc");
     }
+    if exe.requires_mimalloc() {
+        features.push("mimalloc");
+    }
+    if exe.requires_snmalloc() {
+        features.push("snmalloc");
+    }
+    if exe.requires_server() {
+        features.push("server");
+    }
+    if exe.windows_subsystem() == WindowsSubsystem::Windows {
+        features.push("windows-subsystem");
+    }
 
     let features = features.join(" ");
 
@@ -224,6 +273,21 @@ pub fn build_executable_with_rust_project(
     let exe_data = std::fs::read(&exe_path)?;
     let exe_name = exe_path.file_name().unwrap().to_string_lossy().to_string();
 
+    // musl binaries should be fully statically linked. Catch linker
+    // configurations that didn't produce one.
+    if target.contains("-linux-musl") {
+        let dependencies = elf_dynamic_dependencies(&exe_data)?;
+
+        if !dependencies.is_empty() {
+            warn!(
+                logger,
+                "{} is not fully statically linked; it depends on {}",
+                exe_name,
+                dependencies.join(", ")
+            );
+        }
+    }
+
     Ok(BuiltExecutable {
         exe_path: Some(exe_path),
         exe_name,
@@ -232,6 +296,52 @@ pub fn build_executable_with_rust_project(
     })
 }
 
+/// Ensure the Rust standard library for a target triple is installed.
+///
+/// Without it, Cargo fails with an error about the `std` crate missing.
+fn ensure_rust_target_installed(target: &str) -> Result<()> {
+    let output = std::process::Command::new("rustc")
+        .args(&["--print", "target-libdir", "--target", target])
+        .output()?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Rust does not support target {}: {}",
+            target,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let libdir = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+
+    if !libdir.exists() {
+        return Err(anyhow!(
+            "Rust target {} is not installed; install it by running `rustup target add {}`",
+            target,
+            target
+        ));
+    }
+
+    Ok(())
+}
+
+/// Resolve the dynamic loader and shared libraries an ELF binary depends on.
+///
+/// Returns an empty list for fully statically linked binaries.
+pub fn elf_dynamic_dependencies(data: &[u8]) -> Result<Vec<String>> {
+    let elf = goblin::elf::Elf::parse(data)?;
+
+    let mut res = vec![];
+
+    if let Some(interpreter) = elf.interpreter {
+        res.push(interpreter.to_string());
+    }
+
+    res.extend(elf.libraries.iter().map(|l| (*l).to_string()));
+
+    Ok(res)
+}
+
 /// Build a Python executable using a temporary Rust project.
 ///
 /// Returns the binary data constituting the built executable.
@@ -306,6 +416,8 @@ pub fn build_pyembed_artifacts(
             None
         },
         true,
+        LockMode::Use,
+        false,
     )?;
 
     // TODO should we honor only the specified target if one is given?
@@ -393,6 +505,14 @@ pub fn run_from_build(
 
     println!("cargo:rerun-if-changed={}", config_path.display());
 
+    let lock_path = config_path
+        .parent()
+        .context("resolving parent directory of config")?
+        .join(crate::project_lock::LOCK_FILENAME);
+    if lock_path.exists() {
+        println!("cargo:rerun-if-changed={}", lock_path.display());
+    }
+
     let dest_dir = match env::var("PYOXIDIZER_ARTIFACT_DIR") {
         Ok(ref v) => PathBuf::from(v),
         Err(_) => PathBuf::from(env::var("OUT_DIR").context("OUT_DIR")?),
@@ -521,6 +641,24 @@ mod tests {
         crate::testutil::*,
     };
 
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_elf_dynamic_dependencies() -> Result<()> {
+        // Test binaries link libc dynamically unless targeting musl.
+        let data = std::fs::read(std::env::current_exe()?)?;
+        let dependencies = elf_dynamic_dependencies(&data)?;
+
+        if cfg!(target_env = "musl") {
+            assert!(dependencies.is_empty());
+        } else {
+            assert!(dependencies.iter().any(|d| d.starts_with("libc.so")));
+        }
+
+        assert!(elf_dynamic_dependencies(b"not an elf").is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_empty_project() -> Result<()> {
         let logger = get_logger()?;
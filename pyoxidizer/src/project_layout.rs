@@ -279,6 +279,10 @@ pub fn update_new_cargo_toml(path: &Path, pyembed_location: &PyembedLocation) ->
     content.push_str("[features]\n");
     content.push_str("default = [\"build-mode-pyoxidizer-exe\"]\n");
     content.push_str("jemalloc = [\"jemallocator-global\", \"pyembed/jemalloc\"]\n");
+    content.push_str("mimalloc = [\"pyembed/mimalloc\"]\n");
+    content.push_str("snmalloc = [\"pyembed/snmalloc\"]\n");
+    content.push_str("server = [\"pyembed/server\"]\n");
+    content.push_str("windows-subsystem = []\n");
     content.push_str("build-mode-pyoxidizer-exe = [\"pyembed/build-mode-pyoxidizer-exe\"]\n");
     content
         .push_str("build-mode-prebuilt-artifacts = [\"pyembed/build-mode-prebuilt-artifacts\"]\n");
@@ -7,12 +7,20 @@
 use {
     crate::project_building::find_pyoxidizer_config_file_env,
     crate::project_layout::{initialize_project, write_new_pyoxidizer_config_file},
+    crate::project_lock::{LockMode, LOCK_FILENAME},
+    crate::py_packaging::distribution_info::DistributionInfo,
+    crate::py_packaging::source_build::build_distribution_from_source,
     crate::py_packaging::standalone_distribution::StandaloneDistribution,
     crate::starlark::eval::{eval_starlark_config_file, EvalResult},
     anyhow::{anyhow, Result},
+    python_packed_resources::{
+        data::{Resource, ResourceFlavor, HEADER_V1, HEADER_V3},
+        delta::{apply_delta, write_delta},
+        parser::{find_packed_resources, load_resources, load_resources_encrypted},
+    },
     std::fs::create_dir_all,
     std::io::{Cursor, Read},
-    std::path::Path,
+    std::path::{Path, PathBuf},
 };
 
 /// Attempt to resolve the default Rust target for a build.
@@ -22,6 +30,8 @@ pub fn default_target() -> Result<String> {
         Ok("x86_64-unknown-linux-gnu".to_string())
     } else if cfg!(target_os = "windows") {
         Ok("x86_64-pc-windows-msvc".to_string())
+    } else if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
+        Ok("aarch64-apple-darwin".to_string())
     } else if cfg!(target_os = "macos") {
         Ok("x86_64-apple-darwin".to_string())
     } else {
@@ -54,6 +64,8 @@ pub fn list_targets(logger: &slog::Logger, project_path: &Path) -> Result<()> {
         false,
         Some(Vec::new()),
         false,
+        LockMode::Use,
+        false,
     )?;
 
     if res.context.default_target.is_none() {
@@ -101,6 +113,8 @@ pub fn build(
         verbose,
         resolve_targets,
         false,
+        LockMode::Use,
+        false,
     )?;
 
     for target in res.context.targets_to_resolve() {
@@ -110,12 +124,60 @@ pub fn build(
     Ok(())
 }
 
+/// Record artifacts installed by a PyOxidizer project in its lock file.
+///
+/// Targets are resolved but not built. Installed packages are downloaded
+/// from package indexes and written to the wheel cache.
+pub fn lock(
+    logger: &slog::Logger,
+    project_path: &Path,
+    target_triple: Option<&str>,
+    resolve_targets: Option<Vec<String>>,
+    verbose: bool,
+) -> Result<()> {
+    let config_path = find_pyoxidizer_config_file_env(logger, project_path).ok_or_else(|| {
+        anyhow!(
+            "unable to find PyOxidizer config file at {}",
+            project_path.display()
+        )
+    })?;
+    let target_triple = resolve_target(target_triple)?;
+
+    let res: EvalResult = eval_starlark_config_file(
+        logger,
+        &config_path,
+        &target_triple,
+        false,
+        verbose,
+        resolve_targets,
+        false,
+        LockMode::Record,
+        false,
+    )?;
+
+    let lock_path = res.context.cwd.join(LOCK_FILENAME);
+
+    match &res.context.project_lock {
+        Some(lock) => {
+            lock.write_to_path(&lock_path)?;
+            println!("wrote {}", lock_path.display());
+        }
+        None => {
+            println!("no packages to lock; not writing {}", lock_path.display());
+        }
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn run(
     logger: &slog::Logger,
     project_path: &Path,
     target_triple: Option<&str>,
     release: bool,
     target: Option<&str>,
+    watch_resources: bool,
     _extra_args: &[&str],
     verbose: bool,
 ) -> Result<()> {
@@ -141,6 +203,8 @@ pub fn run(
         verbose,
         resolve_targets,
         false,
+        LockMode::Use,
+        watch_resources,
     )?;
 
     res.context.run_target(target)
@@ -209,6 +273,34 @@ pub fn init_rust_project(project_path: &Path) -> Result<()> {
     Ok(())
 }
 
+pub fn python_distribution_build(
+    logger: &slog::Logger,
+    source_path: &Path,
+    dest_path: &Path,
+    target_triple: Option<&str>,
+    configure_args: &[&str],
+    jobs: &str,
+) -> Result<()> {
+    let target_triple = resolve_target(target_triple)?;
+    let jobs = jobs
+        .parse::<usize>()
+        .ok()
+        .filter(|jobs| *jobs > 0)
+        .ok_or_else(|| anyhow!("--jobs must be a positive integer; got {}", jobs))?;
+
+    build_distribution_from_source(
+        logger,
+        source_path,
+        dest_path,
+        &target_triple,
+        &configure_args
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>(),
+        jobs,
+    )
+}
+
 pub fn python_distribution_extract(dist_path: &str, dest_path: &str) -> Result<()> {
     let mut fh = std::fs::File::open(Path::new(dist_path))?;
     let mut data = Vec::new();
@@ -223,7 +315,7 @@ pub fn python_distribution_extract(dist_path: &str, dest_path: &str) -> Result<(
     Ok(())
 }
 
-pub fn python_distribution_info(dist_path: &str) -> Result<()> {
+pub fn python_distribution_info(dist_path: &str, json: bool) -> Result<()> {
     let fh = std::fs::File::open(Path::new(dist_path))?;
     let reader = std::io::BufReader::new(fh);
 
@@ -232,6 +324,11 @@ pub fn python_distribution_info(dist_path: &str) -> Result<()> {
 
     let dist = StandaloneDistribution::from_tar_zst(reader, temp_dir_path)?;
 
+    if json {
+        println!("{}", DistributionInfo::from_distribution(&dist)?.to_json()?);
+        return Ok(());
+    }
+
     println!("High-Level Metadata");
     println!("===================");
     println!();
@@ -363,3 +460,341 @@ pub fn python_distribution_licenses(path: &str) -> Result<()> {
 
     Ok(())
 }
+
+/// Write a binary delta between two packed resources files.
+pub fn resources_delta(base_path: &Path, target_path: &Path, dest_path: &Path) -> Result<()> {
+    let base = std::fs::read(base_path)
+        .map_err(|e| anyhow!("unable to read {}: {}", base_path.display(), e))?;
+    let target = std::fs::read(target_path)
+        .map_err(|e| anyhow!("unable to read {}: {}", target_path.display(), e))?;
+
+    let mut delta = Vec::new();
+    let report = write_delta(&base, &target, &mut delta)?;
+    std::fs::write(dest_path, &delta)?;
+
+    println!(
+        "wrote {} byte delta to {}; {} bytes copied from base, {} bytes inserted",
+        report.delta_length,
+        dest_path.display(),
+        report.copied_bytes,
+        report.inserted_bytes
+    );
+
+    Ok(())
+}
+
+/// Apply a binary delta to a packed resources file.
+pub fn resources_apply_delta(base_path: &Path, delta_path: &Path, dest_path: &Path) -> Result<()> {
+    let base = std::fs::read(base_path)
+        .map_err(|e| anyhow!("unable to read {}: {}", base_path.display(), e))?;
+    let delta = std::fs::read(delta_path)
+        .map_err(|e| anyhow!("unable to read {}: {}", delta_path.display(), e))?;
+
+    let target = apply_delta(&base, &delta)
+        .map_err(|e| anyhow!("unable to apply {}: {}", delta_path.display(), e))?;
+
+    // Programs memory mapping the destination may be running. Replace the
+    // file rather than rewriting it in place.
+    let mut temp_path = dest_path.as_os_str().to_os_string();
+    temp_path.push(".pyoxidizer-new");
+    let temp_path = PathBuf::from(temp_path);
+    std::fs::write(&temp_path, &target)
+        .map_err(|e| anyhow!("unable to write {}: {}", temp_path.display(), e))?;
+    std::fs::rename(&temp_path, dest_path)
+        .map_err(|e| anyhow!("unable to rename to {}: {}", dest_path.display(), e))?;
+
+    println!("wrote {} bytes to {}", target.len(), dest_path.display());
+
+    Ok(())
+}
+
+fn resource_flavor_name(flavor: ResourceFlavor) -> &'static str {
+    match flavor {
+        ResourceFlavor::None => "none",
+        ResourceFlavor::Module => "module",
+        ResourceFlavor::BuiltinExtensionModule => "builtin extension module",
+        ResourceFlavor::FrozenModule => "frozen module",
+        ResourceFlavor::Extension => "extension module",
+        ResourceFlavor::SharedLibrary => "shared library",
+    }
+}
+
+fn print_resource(resource: &Resource<u8>) {
+    let mut flags = vec![resource_flavor_name(resource.flavor)];
+    if resource.is_package {
+        flags.push("package");
+    }
+    if resource.is_namespace_package {
+        flags.push("namespace package");
+    }
+
+    println!("{} ({})", resource.name, flags.join(", "));
+
+    let data_fields = [
+        ("in-memory source", &resource.in_memory_source),
+        ("in-memory bytecode", &resource.in_memory_bytecode),
+        ("in-memory bytecode opt1", &resource.in_memory_bytecode_opt1),
+        ("in-memory bytecode opt2", &resource.in_memory_bytecode_opt2),
+        (
+            "in-memory extension module",
+            &resource.in_memory_extension_module_shared_library,
+        ),
+        (
+            "in-memory shared library",
+            &resource.in_memory_shared_library,
+        ),
+    ];
+    for (label, data) in data_fields.iter() {
+        if let Some(data) = data {
+            println!("  {}: {} bytes", label, data.len());
+        }
+    }
+
+    let resources_fields = [
+        ("in-memory resource", &resource.in_memory_package_resources),
+        (
+            "in-memory distribution resource",
+            &resource.in_memory_distribution_resources,
+        ),
+    ];
+    for (label, resources) in resources_fields.iter() {
+        if let Some(resources) = resources {
+            let mut names = resources.keys().collect::<Vec<_>>();
+            names.sort();
+            for name in names {
+                println!("  {} {}: {} bytes", label, name, resources[name].len());
+            }
+        }
+    }
+
+    let path_fields = [
+        ("source path", &resource.relative_path_module_source),
+        ("bytecode path", &resource.relative_path_module_bytecode),
+        (
+            "bytecode opt1 path",
+            &resource.relative_path_module_bytecode_opt1,
+        ),
+        (
+            "bytecode opt2 path",
+            &resource.relative_path_module_bytecode_opt2,
+        ),
+        (
+            "extension module path",
+            &resource.relative_path_extension_module_shared_library,
+        ),
+    ];
+    for (label, path) in path_fields.iter() {
+        if let Some(path) = path {
+            println!("  {}: $ORIGIN/{}", label, path.display());
+        }
+    }
+
+    let paths_fields = [
+        ("resource path", &resource.relative_path_package_resources),
+        (
+            "distribution resource path",
+            &resource.relative_path_distribution_resources,
+        ),
+    ];
+    for (label, paths) in paths_fields.iter() {
+        if let Some(paths) = paths {
+            let mut names = paths.keys().collect::<Vec<_>>();
+            names.sort();
+            for name in names {
+                println!("  {} {}: $ORIGIN/{}", label, name, paths[name].display());
+            }
+        }
+    }
+
+    if let Some(names) = &resource.shared_library_dependency_names {
+        println!("  shared library dependencies: {}", names.join(", "));
+    }
+
+    if let Some(provenance) = &resource.provenance {
+        println!("  provenance: {}", provenance);
+    }
+}
+
+/// Resolve the path to write data of an extracted resource to.
+///
+/// Names come from the inspected data, so they may not escape `dest_dir`.
+fn extract_path(dest_dir: &Path, name: &str) -> Result<PathBuf> {
+    let relative = Path::new(name);
+
+    if relative.components().any(|c| match c {
+        std::path::Component::Normal(_) => false,
+        _ => true,
+    }) {
+        return Err(anyhow!("refusing to extract resource named {}", name));
+    }
+
+    Ok(dest_dir.join(relative))
+}
+
+fn write_extracted(path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+    std::fs::write(path, data)?;
+    println!("wrote {} bytes to {}", data.len(), path.display());
+
+    Ok(())
+}
+
+fn extract_resource(resource: &Resource<u8>, dest_dir: &Path) -> Result<()> {
+    let name = &resource.name;
+
+    let data_fields = [
+        (format!("{}.py", name), &resource.in_memory_source),
+        (format!("{}.bytecode", name), &resource.in_memory_bytecode),
+        (
+            format!("{}.opt-1.bytecode", name),
+            &resource.in_memory_bytecode_opt1,
+        ),
+        (
+            format!("{}.opt-2.bytecode", name),
+            &resource.in_memory_bytecode_opt2,
+        ),
+        (
+            format!("{}.extension-module", name),
+            &resource.in_memory_extension_module_shared_library,
+        ),
+        (
+            format!("{}.shared-library", name),
+            &resource.in_memory_shared_library,
+        ),
+    ];
+    for (filename, data) in data_fields.iter() {
+        if let Some(data) = data {
+            write_extracted(&extract_path(dest_dir, filename)?, data)?;
+        }
+    }
+
+    let resources_fields = [
+        ("resources", &resource.in_memory_package_resources),
+        ("distribution", &resource.in_memory_distribution_resources),
+    ];
+    for (subdir, resources) in resources_fields.iter() {
+        if let Some(resources) = resources {
+            let dir = extract_path(dest_dir, name)?.join(subdir);
+
+            for (key, data) in resources.iter() {
+                write_extracted(&extract_path(&dir, key)?, data)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Inspect packed resources data in a packed resources file or a binary.
+pub fn resources_inspect(
+    path: &Path,
+    encryption_key_path: Option<&Path>,
+    extract: Option<&str>,
+    dest_dir: &Path,
+) -> Result<()> {
+    let data =
+        std::fs::read(path).map_err(|e| anyhow!("unable to read {}: {}", path.display(), e))?;
+    let key = if let Some(key_path) = encryption_key_path {
+        Some(
+            std::fs::read(key_path)
+                .map_err(|e| anyhow!("unable to read {}: {}", key_path.display(), e))?,
+        )
+    } else {
+        None
+    };
+
+    // Parse packed resources files directly, so errors in them are reported.
+    let offset = if data.starts_with(HEADER_V1) || data.starts_with(HEADER_V3) {
+        0
+    } else {
+        find_packed_resources(&data, key.as_deref()).ok_or_else(|| {
+            anyhow!(
+                "unable to find packed resources data in {}{}",
+                path.display(),
+                if key.is_none() {
+                    "; pass --encryption-key if it is encrypted"
+                } else {
+                    ""
+                }
+            )
+        })?
+    };
+
+    let data = &data[offset..];
+    let resources = match &key {
+        Some(key) => load_resources_encrypted(data, key),
+        None => load_resources(data),
+    }
+    .map_err(|e| anyhow!("error parsing packed resources index: {}", e))?;
+
+    let data_length = resources.data_length();
+    if data_length > data.len() {
+        return Err(anyhow!(
+            "packed resources data is truncated: index declares {} bytes but only {} are present",
+            data_length,
+            data.len()
+        ));
+    }
+
+    println!(
+        "packed resources data at offset {} of {} ({} bytes)",
+        offset,
+        path.display(),
+        data_length
+    );
+    println!(
+        "{} resources; {} deduplicated, {} compressed and {} encrypted blobs",
+        resources.expected_resources_count(),
+        resources.referenced_blobs_count(),
+        resources.compressed_blobs_count(),
+        resources.encrypted_blobs_count()
+    );
+    if !resources.prefetch_ranges().is_empty() {
+        println!(
+            "{} bytes in {} ranges marked for prefetching",
+            resources
+                .prefetch_ranges()
+                .iter()
+                .map(|(_, length)| length)
+                .sum::<usize>(),
+            resources.prefetch_ranges().len()
+        );
+    }
+    println!();
+
+    let mut count = 0;
+    let mut extracted = false;
+
+    for resource in resources {
+        let resource = resource.map_err(|e| {
+            anyhow!(
+                "error parsing packed resources data after {} resources: {}",
+                count,
+                e
+            )
+        })?;
+        count += 1;
+
+        if let Some(name) = extract {
+            if resource.name == name {
+                extract_resource(&resource, dest_dir)?;
+                extracted = true;
+            }
+        } else {
+            print_resource(&resource);
+        }
+    }
+
+    if let Some(name) = extract {
+        if !extracted {
+            return Err(anyhow!("resource {} not found", name));
+        }
+    } else {
+        println!();
+        println!("verified index of {} resources", count);
+    }
+
+    Ok(())
+}
@@ -8,18 +8,22 @@ Defining and manipulating binaries embedding Python.
 
 use {
     super::config::EmbeddedPythonConfig,
+    super::dependency_report::DependencyReport,
+    super::lockfile::VerifiedArtifact,
+    super::package_index::PackageIndexes,
     super::pyembed::{derive_python_config, write_default_python_config_rs},
     crate::app_packaging::resource::FileManifest,
     anyhow::Result,
     python_packaging::policy::PythonPackagingPolicy,
     python_packaging::resource::{
-        PythonExtensionModule, PythonModuleBytecodeFromSource, PythonModuleSource,
-        PythonPackageDistributionResource, PythonPackageResource, PythonResource,
+        PythonEditableInstall, PythonExtensionModule, PythonModuleBytecodeFromSource,
+        PythonModuleSource, PythonPackageDistributionResource, PythonPackageResource,
+        PythonResource,
     },
     python_packaging::resource_collection::{ConcreteResourceLocation, PrePackagedResource},
     std::collections::HashMap,
     std::fs::File,
-    std::io::Write,
+    std::io::{Seek, SeekFrom, Write},
     std::path::{Path, PathBuf},
 };
 
@@ -32,6 +36,22 @@ pub enum LibpythonLinkMode {
     Dynamic,
 }
 
+/// The Windows subsystem an executable is built for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WindowsSubsystem {
+    /// The executable is a console application. Windows opens a console
+    /// window for it if it isn't launched from one.
+    Console,
+    /// The executable is a GUI application without a console.
+    Windows,
+}
+
+impl Default for WindowsSubsystem {
+    fn default() -> Self {
+        WindowsSubsystem::Console
+    }
+}
+
 /// Describes a generic way to build a Python binary.
 ///
 /// Binary here means an executable or library containing or linking to a
@@ -73,14 +93,101 @@ pub trait PythonBinaryBuilder {
     /// Runs `pip install` using the binary builder's settings.
     ///
     /// Returns resources discovered as part of performing an install.
+    /// Installed distributions are recorded in the dependency report.
+    /// Errors if a distribution was installed from a wheel the target can't use.
     fn pip_install(
-        &self,
+        &mut self,
         logger: &slog::Logger,
         verbose: bool,
         install_args: &[String],
         extra_envs: &HashMap<String, String>,
     ) -> Result<Vec<PythonResource>>;
 
+    /// Runs `pip download` using the binary builder's settings.
+    ///
+    /// Files are downloaded to `dest_dir`. Returns paths of downloaded files.
+    fn pip_download(
+        &self,
+        logger: &slog::Logger,
+        verbose: bool,
+        download_args: &[String],
+        dest_dir: &Path,
+        extra_envs: &HashMap<String, String>,
+    ) -> Result<Vec<PathBuf>>;
+
+    /// Record artifacts whose hashes were verified while obtaining resources.
+    fn add_verified_artifacts(&mut self, artifacts: &[VerifiedArtifact]);
+
+    /// Obtain artifacts whose hashes were verified while obtaining resources.
+    fn verified_artifacts(&self) -> &[VerifiedArtifact];
+
+    /// Obtain the report of distributions installed by packaging operations.
+    fn dependency_report(&self) -> &DependencyReport;
+
+    /// Obtain the package indexes pip installs packages from.
+    fn package_indexes(&self) -> &PackageIndexes;
+
+    /// Set the package indexes pip installs packages from.
+    fn set_package_indexes(&mut self, indexes: PackageIndexes);
+
+    /// Directory relative to the binary Tcl/Tk library files are installed into.
+    ///
+    /// Files are only installed if `tkinter` is packaged.
+    fn tcl_files_path(&self) -> &Option<String>;
+
+    /// Set the directory relative to the binary to install Tcl/Tk library files into.
+    fn set_tcl_files_path(&mut self, value: Option<String>);
+
+    /// Path relative to the binary to write packed resources data to.
+    ///
+    /// If not set, packed resources data is embedded in the binary.
+    fn packed_resources_path(&self) -> &Option<String>;
+
+    /// Set the path relative to the binary to write packed resources data to.
+    fn set_packed_resources_path(&mut self, value: Option<String>);
+
+    /// Path to an ed25519 secret key to sign the packed resources file with.
+    ///
+    /// The executable verifies the signature when loading the file.
+    fn packed_resources_signing_key(&self) -> &Option<PathBuf>;
+
+    /// Set the path to an ed25519 secret key to sign the packed resources file with.
+    fn set_packed_resources_signing_key(&mut self, value: Option<PathBuf>);
+
+    /// Path to a 32 byte key to encrypt packed resources data with.
+    ///
+    /// The application embedding Python must supply the key at run-time.
+    fn packed_resources_encryption_key(&self) -> &Option<PathBuf>;
+
+    /// Set the path to a 32 byte key to encrypt packed resources data with.
+    fn set_packed_resources_encryption_key(&mut self, value: Option<PathBuf>);
+
+    /// Number of bytes to align sections of packed resources data to.
+    ///
+    /// Typically the page size, so memory mapped packed resources files
+    /// touch as few pages as possible.
+    fn packed_resources_alignment(&self) -> Option<usize>;
+
+    /// Set the number of bytes to align sections of packed resources data to.
+    fn set_packed_resources_alignment(&mut self, value: Option<usize>);
+
+    /// Path to a startup profile ordering packed resources data.
+    ///
+    /// Modules imported by the profiled application are written first, in
+    /// the order they were imported.
+    fn packed_resources_import_profile(&self) -> &Option<PathBuf>;
+
+    /// Set the path to a startup profile ordering packed resources data.
+    fn set_packed_resources_import_profile(&mut self, value: Option<PathBuf>);
+
+    /// The Windows subsystem the executable is built for.
+    ///
+    /// Only has an effect when building for Windows.
+    fn windows_subsystem(&self) -> WindowsSubsystem;
+
+    /// Set the Windows subsystem the executable is built for.
+    fn set_windows_subsystem(&mut self, value: WindowsSubsystem);
+
     /// Reads Python resources from the filesystem.
     fn read_package_root(
         &self,
@@ -92,11 +199,22 @@ pub trait PythonBinaryBuilder {
     /// Read Python resources from a populated virtualenv directory.
     fn read_virtualenv(&self, logger: &slog::Logger, path: &Path) -> Result<Vec<PythonResource>>;
 
+    /// Read Python resources from a conda environment.
+    fn read_conda_env(&self, logger: &slog::Logger, path: &Path) -> Result<Vec<PythonResource>>;
+
+    /// Read Python resources from `.conda` or `.tar.bz2` conda package files.
+    fn read_conda_packages(
+        &self,
+        logger: &slog::Logger,
+        paths: &[PathBuf],
+    ) -> Result<Vec<PythonResource>>;
+
     /// Runs `python setup.py install` using the binary builder's settings.
     ///
     /// Returns resources discovered as part of performing an install.
+    /// Installed distributions are recorded in the dependency report.
     fn setup_py_install(
-        &self,
+        &mut self,
         logger: &slog::Logger,
         package_path: &Path,
         verbose: bool,
@@ -104,6 +222,20 @@ pub trait PythonBinaryBuilder {
         extra_global_arguments: &[String],
     ) -> Result<Vec<PythonResource>>;
 
+    /// Builds a package with its PEP 517 build backend using the binary builder's settings.
+    ///
+    /// Returns resources discovered in the built wheel, including extension
+    /// modules compiled from source. The built distribution is recorded in the
+    /// dependency report. Errors if the built wheel can't be used by the target.
+    fn pep517_build(
+        &mut self,
+        logger: &slog::Logger,
+        package_path: &Path,
+        verbose: bool,
+        build_requirements: &[String],
+        extra_envs: &HashMap<String, String>,
+    ) -> Result<Vec<PythonResource>>;
+
     /// Add a `PythonModuleSource` to the resources collection.
     ///
     /// The location to load the resource from is optional. If specified, it
@@ -162,6 +294,13 @@ pub trait PythonBinaryBuilder {
         location: Option<ConcreteResourceLocation>,
     ) -> Result<()>;
 
+    /// Add a `PythonEditableInstall` to make available.
+    ///
+    /// Nothing is embedded. Instead, the distribution's source tree is added
+    /// to `sys.path` and the filesystem importer is enabled, so modules are
+    /// imported from the source tree at run time.
+    fn add_python_editable_install(&mut self, editable: &PythonEditableInstall) -> Result<()>;
+
     /// Filter embedded resources against names in files.
     ///
     /// `files` is files to read names from.
@@ -177,6 +316,15 @@ pub trait PythonBinaryBuilder {
     /// Whether the binary requires the jemalloc library.
     fn requires_jemalloc(&self) -> bool;
 
+    /// Whether the binary requires the mimalloc library.
+    fn requires_mimalloc(&self) -> bool;
+
+    /// Whether the binary requires the snmalloc library.
+    fn requires_snmalloc(&self) -> bool;
+
+    /// Whether the binary requires the HTTP server of the `pyembed` crate.
+    fn requires_server(&self) -> bool;
+
     /// Obtain an `EmbeddedPythonContext` instance from this one.
     fn to_embedded_python_context(
         &self,
@@ -240,8 +388,11 @@ pub struct EmbeddedPythonContext {
     /// Newline delimited list of module names in resources.
     pub module_names: Vec<u8>,
 
-    /// Python resources to embed in the binary.
-    pub resources: Vec<u8>,
+    /// Packed resources data to embed in the binary.
+    ///
+    /// This is an anonymous temporary file, so the data isn't held in memory.
+    /// `None` if the data is written to a standalone file in `extra_files`.
+    pub resources: Option<File>,
 
     /// Extra files to install next to produced binary.
     pub extra_files: FileManifest,
@@ -262,7 +413,10 @@ impl EmbeddedPythonContext {
 
         let embedded_resources = dest_dir.join("packed-resources");
         let mut fh = File::create(&embedded_resources)?;
-        fh.write_all(&self.resources)?;
+        if let Some(mut resources) = self.resources.as_ref() {
+            resources.seek(SeekFrom::Start(0))?;
+            std::io::copy(&mut resources, &mut fh)?;
+        }
 
         let libpython = dest_dir.join(&self.linking_info.libpythonxy_filename);
         let mut fh = File::create(&libpython)?;
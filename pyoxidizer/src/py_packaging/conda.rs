@@ -0,0 +1,277 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Interaction with conda packages and environments.
+
+Conda packages are archives of files relative to the prefix of the
+environment they are installed into, with package metadata in an `info`
+directory. They come in 2 formats: `.tar.bz2` files are bzip2 compressed
+tar archives. `.conda` files are zip archives holding a zstandard
+compressed tar archive of the metadata (`info-*.tar.zst`) and one of the
+remaining content (`pkg-*.tar.zst`).
+
+Conda records packages installed into an environment as JSON files in the
+environment's `conda-meta` directory.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    python_packaging::resource::ResourceProvenance,
+    serde::{Deserialize, Serialize},
+    std::collections::HashMap,
+    std::io::Read,
+    std::path::{Path, PathBuf},
+};
+
+/// Metadata of a conda package, as stored in `info/index.json`.
+#[derive(Debug, Deserialize)]
+struct CondaPackageIndex {
+    name: String,
+    version: String,
+    build: String,
+}
+
+/// Record of a package installed into a conda environment.
+#[derive(Debug, Deserialize, Serialize)]
+struct CondaPackageRecord {
+    name: String,
+    version: String,
+    #[serde(default)]
+    files: Vec<String>,
+}
+
+/// Unpack a tar archive of conda package content.
+///
+/// Metadata in the `info` directory is unpacked in `info_dir`. Everything
+/// else is unpacked in `prefix`.
+fn unpack_package_tar<R: Read>(reader: R, prefix: &Path, info_dir: &Path) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+
+        let dest = if entry.path()?.starts_with("info") {
+            info_dir
+        } else {
+            prefix
+        };
+
+        entry.unpack_in(dest)?;
+    }
+
+    Ok(())
+}
+
+/// Extract a conda package into an environment prefix.
+///
+/// Like conda does, the package is recorded in the `conda-meta` directory of
+/// the prefix.
+pub fn extract_conda_package(path: &Path, prefix: &Path) -> Result<()> {
+    let filename = path
+        .file_name()
+        .ok_or_else(|| anyhow!("unable to resolve filename of {}", path.display()))?
+        .to_string_lossy()
+        .to_string();
+
+    let temp_dir = tempdir::TempDir::new("pyoxidizer-conda-package")?;
+    let info_dir = temp_dir.path();
+
+    std::fs::create_dir_all(prefix)?;
+
+    let fh = std::fs::File::open(path)?;
+
+    if filename.ends_with(".tar.bz2") {
+        unpack_package_tar(bzip2::read::BzDecoder::new(fh), prefix, info_dir)?;
+    } else if filename.ends_with(".conda") {
+        let mut archive =
+            zip::ZipArchive::new(fh).context("reading .conda file as a zip archive")?;
+
+        for i in 0..archive.len() {
+            let file = archive.by_index(i)?;
+            let name = file.name().to_string();
+
+            if (name.starts_with("info-") || name.starts_with("pkg-")) && name.ends_with(".tar.zst")
+            {
+                unpack_package_tar(zstd::stream::Decoder::new(file)?, prefix, info_dir)
+                    .with_context(|| format!("extracting {}", name))?;
+            }
+        }
+    } else {
+        return Err(anyhow!(
+            "{} is not a conda package; expected a .conda or .tar.bz2 file",
+            path.display()
+        ));
+    }
+
+    let index_path = info_dir.join("info").join("index.json");
+    let index: CondaPackageIndex = serde_json::from_slice(
+        &std::fs::read(&index_path)
+            .with_context(|| format!("{} has no info/index.json", filename))?,
+    )
+    .context("parsing info/index.json")?;
+
+    let files_path = info_dir.join("info").join("files");
+    let files = if files_path.exists() {
+        std::fs::read_to_string(&files_path)?
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(|l| l.to_string())
+            .collect()
+    } else {
+        vec![]
+    };
+
+    let record = CondaPackageRecord {
+        name: index.name,
+        version: index.version,
+        files,
+    };
+
+    let meta_dir = prefix.join("conda-meta");
+    std::fs::create_dir_all(&meta_dir)?;
+    std::fs::write(
+        meta_dir.join(format!(
+            "{}-{}-{}.json",
+            record.name, record.version, index.build
+        )),
+        serde_json::to_vec(&record)?,
+    )?;
+
+    Ok(())
+}
+
+/// Index files installed into a conda environment by the package that installed them.
+pub fn index_conda_meta(prefix: &Path) -> Result<HashMap<PathBuf, ResourceProvenance>> {
+    let mut res = HashMap::new();
+
+    let meta_dir = prefix.join("conda-meta");
+    if !meta_dir.is_dir() {
+        return Ok(res);
+    }
+
+    for entry in std::fs::read_dir(&meta_dir)? {
+        let path = entry?.path();
+
+        if path.extension().and_then(|x| x.to_str()) != Some("json") {
+            continue;
+        }
+
+        let record: CondaPackageRecord = serde_json::from_slice(&std::fs::read(&path)?)
+            .with_context(|| format!("parsing {}", path.display()))?;
+
+        let provenance = ResourceProvenance::CondaPackage {
+            name: record.name.clone(),
+            version: record.version.clone(),
+        };
+
+        for file in &record.files {
+            // Paths are always /-delimited, even on Windows.
+            let mut installed = prefix.to_path_buf();
+            installed.extend(file.split('/'));
+
+            res.insert(installed, provenance.clone());
+        }
+    }
+
+    Ok(res)
+}
+
+/// Resolve directories of a conda environment holding shared libraries.
+///
+/// Extension modules in conda packages typically link against shared
+/// libraries provided by other conda packages.
+pub fn conda_library_dirs(prefix: &Path) -> Vec<PathBuf> {
+    vec![prefix.join("lib"), prefix.join("Library").join("bin")]
+        .into_iter()
+        .filter(|p| p.is_dir())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_tar_bz2(path: &Path, files: &[(&str, &[u8])]) -> Result<()> {
+        let fh = std::fs::File::create(path)?;
+        let mut builder = tar::Builder::new(bzip2::write::BzEncoder::new(
+            fh,
+            bzip2::Compression::Default,
+        ));
+
+        for (name, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *data)?;
+        }
+
+        builder.into_inner()?.finish()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_tar_bz2() -> Result<()> {
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+        let package_path = td.path().join("foo-1.0-py37_0.tar.bz2");
+        let prefix = td.path().join("env");
+
+        write_tar_bz2(
+            &package_path,
+            &[
+                (
+                    "info/index.json",
+                    br#"{"name": "foo", "version": "1.0", "build": "py37_0"}"#,
+                ),
+                (
+                    "info/files",
+                    b"lib/python3.7/site-packages/foo.py\nlib/libfoo.so\n",
+                ),
+                ("lib/python3.7/site-packages/foo.py", b"import bar\n"),
+                ("lib/libfoo.so", b""),
+            ],
+        )?;
+
+        extract_conda_package(&package_path, &prefix)?;
+
+        assert!(prefix
+            .join("lib")
+            .join("python3.7")
+            .join("site-packages")
+            .join("foo.py")
+            .exists());
+        assert!(!prefix.join("info").exists());
+        assert!(prefix
+            .join("conda-meta")
+            .join("foo-1.0-py37_0.json")
+            .exists());
+
+        let index = index_conda_meta(&prefix)?;
+        assert_eq!(index.len(), 2);
+        assert_eq!(
+            index.get(&prefix.join("lib").join("libfoo.so")),
+            Some(&ResourceProvenance::CondaPackage {
+                name: "foo".to_string(),
+                version: "1.0".to_string(),
+            })
+        );
+
+        assert_eq!(conda_library_dirs(&prefix), vec![prefix.join("lib")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_unknown_format() -> Result<()> {
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+        let path = td.path().join("foo-1.0.zip");
+        std::fs::write(&path, b"")?;
+
+        assert!(extract_conda_package(&path, td.path()).is_err());
+
+        Ok(())
+    }
+}
@@ -12,7 +12,7 @@ pub fn default_raw_allocator(target_triple: &str) -> RawAllocator {
     //
     // We don't use Jemalloc by default in the test environment because it slows down
     // builds of test projects.
-    if target_triple == "x86_64-pc-windows-msvc" || cfg!(test) {
+    if !RawAllocator::Jemalloc.is_supported(target_triple) || cfg!(test) {
         RawAllocator::System
     } else {
         RawAllocator::Jemalloc
@@ -22,17 +22,97 @@ pub fn default_raw_allocator(target_triple: &str) -> RawAllocator {
 #[derive(Clone, Debug, PartialEq)]
 pub enum RawAllocator {
     Jemalloc,
+    Mimalloc,
     Rust,
+    Snmalloc,
     System,
 }
 
+impl RawAllocator {
+    /// Whether the allocator can be used on a target triple.
+    pub fn is_supported(&self, target_triple: &str) -> bool {
+        match self {
+            // The jemalloc-sys crate doesn't build for Windows targets.
+            RawAllocator::Jemalloc => !target_triple.contains("-windows-"),
+            // snmalloc only supports 64-bit architectures.
+            RawAllocator::Snmalloc => {
+                target_triple.starts_with("x86_64-") || target_triple.starts_with("aarch64-")
+            }
+            RawAllocator::Mimalloc | RawAllocator::Rust | RawAllocator::System => true,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum RunMode {
     Noop,
     Repl,
-    Module { module: String },
-    Eval { code: String },
-    File { path: String },
+    Module {
+        module: String,
+    },
+    ModuleWithArgs {
+        module: String,
+        args: Vec<String>,
+    },
+    MainModule {
+        module: String,
+    },
+    Eval {
+        code: String,
+    },
+    EvalMany {
+        code: Vec<String>,
+    },
+    File {
+        path: String,
+    },
+    FileFromArgs,
+    JupyterKernel {
+        name: String,
+        display_name: String,
+    },
+    Server {
+        application: String,
+        interface: ServerInterface,
+        address: String,
+        max_request_body_size: usize,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ServerInterface {
+    Wsgi,
+    Asgi,
+}
+
+/// Where output written to stdout and stderr goes at run-time.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StdioRedirect {
+    None,
+    File(String),
+    Debugger,
+}
+
+/// Where a site-packages overlay is searched relative to packed resources.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SitePackagesOverlayPriority {
+    Before,
+    After,
+}
+
+/// A writable directory layered on top of packed resources at run-time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SitePackagesOverlay {
+    pub path: String,
+    pub priority: SitePackagesOverlayPriority,
+}
+
+/// How `__file__` is set for modules imported from memory at run-time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InMemoryModuleFile {
+    None,
+    Uri,
+    Path,
 }
 
 /// How the `terminfo` database is resolved at run-time.
@@ -43,34 +123,208 @@ pub enum TerminfoResolution {
     Static(String),
 }
 
+/// How CA certificates trusted by the `ssl` module are located at run-time.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CertificateResolution {
+    Dynamic,
+    None,
+    Static(String),
+}
+
+/// Memory allocator Python is configured with by `PyPreConfig.allocator`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Allocator {
+    Default,
+    Debug,
+    Malloc,
+    MallocDebug,
+    PyMalloc,
+    PyMallocDebug,
+}
+
+/// Locale coercion performed by `PyPreConfig.coerce_c_locale`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CoerceCLocale {
+    LCCtype,
+    C,
+}
+
+/// How hash-based `.pyc` files are validated (`PyConfig.check_hash_pycs_mode`).
+#[derive(Clone, Debug, PartialEq)]
+pub enum CheckHashPYCsMode {
+    Always,
+    Never,
+    Default,
+}
+
+/// Start method of the `multiprocessing` module.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MultiprocessingStartMethod {
+    Fork,
+    ForkServer,
+    Spawn,
+}
+
+/// Configuration of the Python interpreter embedded in a binary.
+///
+/// Most fields correspond to a field of the `PyPreConfig` or `PyConfig`
+/// structs defined by PEP 587. `Option` fields default to `None`, which
+/// leaves the value Python derives from the interpreter profile: the
+/// isolated profile when `isolated` is set and the Python profile
+/// otherwise. Path configuration like `home` and `prefix` is derived from
+/// the location of the executable at run-time and isn't configurable.
 #[derive(Clone, Debug, PartialEq)]
 pub struct EmbeddedPythonConfig {
+    /// `PyConfig.bytes_warning`. Defaults to 0.
     pub bytes_warning: i32,
+    /// Inverse of `PyConfig.use_environment`. Defaults to true.
     pub ignore_environment: bool,
+    /// `PyConfig.inspect`. Defaults to false.
     pub inspect: bool,
+    /// `PyConfig.interactive`. Defaults to false.
     pub interactive: bool,
+    /// Use the isolated profile and set `PyConfig.isolated`. Defaults to true.
     pub isolated: bool,
+    /// `PyPreConfig.legacy_windows_fs_encoding`. Defaults to false.
     pub legacy_windows_fs_encoding: bool,
+    /// `PyConfig.legacy_windows_stdio`. Defaults to false.
     pub legacy_windows_stdio: bool,
+    /// `PyConfig.optimization_level`. Defaults to 0.
     pub optimize_level: i64,
+    /// `PyConfig.parser_debug`. Defaults to false.
     pub parser_debug: bool,
+    /// `PyConfig.stdio_encoding`. Defaults to `None`.
     pub stdio_encoding_name: Option<String>,
+    /// `PyConfig.stdio_errors`. Defaults to `None`.
     pub stdio_encoding_errors: Option<String>,
+    /// Inverse of `PyConfig.buffered_stdio`. Defaults to false.
     pub unbuffered_stdio: bool,
+    /// Whether to install the filesystem importer. Defaults to false.
     pub filesystem_importer: bool,
+    /// How `__file__` is set for modules imported from memory. Defaults to `None`.
+    pub in_memory_module_file: InMemoryModuleFile,
+    /// Whether `__path__` is set for packages imported from memory. Defaults to true.
+    pub in_memory_package_path: bool,
+    /// Names of modules to import lazily. Defaults to empty.
+    pub lazy_modules: Vec<String>,
+    /// `PyConfig.quiet`. Defaults to false.
     pub quiet: bool,
+    /// Allocator for Python's raw memory domain. Defaults to jemalloc where supported.
     pub raw_allocator: RawAllocator,
+    /// Whether to also use `raw_allocator` for Python's mem domain. Defaults to false.
+    pub raw_allocator_mem_domain: bool,
+    /// What to run when the interpreter starts. Defaults to a REPL.
     pub run_mode: RunMode,
+    /// `PyConfig.site_import`. Defaults to false.
     pub site_import: bool,
+    /// Whether to set `sys.frozen`. Defaults to false.
     pub sys_frozen: bool,
+    /// Whether to set `sys._MEIPASS`. Defaults to false.
     pub sys_meipass: bool,
+    /// `PyConfig.module_search_paths`. Defaults to empty.
     pub sys_paths: Vec<String>,
+    /// How the `terminfo` database is resolved. Defaults to not resolving it.
     pub terminfo_resolution: TerminfoResolution,
+    /// How CA certificates are located. Defaults to not locating them.
+    pub ssl_certificate_resolution: CertificateResolution,
+    /// Path to the Tcl library directory for `tkinter`. Defaults to None.
+    ///
+    /// Set by the executable builder when Tcl/Tk library files are installed.
+    pub tcl_library: Option<String>,
+    /// Whether to enable line editing and tab completion in REPLs. Defaults to true.
+    pub repl_line_editing: bool,
+    /// Path to the REPL history file. Defaults to None.
+    pub repl_history_file: Option<String>,
+    /// Whether `PYTHONHASHSEED` seeds hashing. Defaults to false.
     pub use_hash_seed: bool,
+    /// `PyConfig.user_site_directory`. Defaults to false.
     pub user_site_directory: bool,
+    /// `PyConfig.verbose`. Defaults to 0.
     pub verbose: i32,
+    /// The `gil` X option of free-threaded interpreters. Defaults to `None`.
+    pub gil_enabled: Option<bool>,
+    /// `PyConfig.write_bytecode`. Defaults to false.
     pub write_bytecode: bool,
+    /// Environment variable naming a directory to write loaded modules to. Defaults to `None`.
     pub write_modules_directory_env: Option<String>,
+    /// Environment variable naming a file to write a startup profile to. Defaults to `None`.
+    pub startup_profile_env: Option<String>,
+    /// Environment variable naming a file holding the packed resources encryption key. Defaults to `None`.
+    pub packed_resources_key_env: Option<String>,
+    /// Where to redirect output written to stdout and stderr. Defaults to `None`.
+    pub stdio_redirect: StdioRedirect,
+    /// Writable directory to layer on top of packed resources. Defaults to `None`.
+    pub site_packages_overlay: Option<SitePackagesOverlay>,
+    /// Zip archives to import modules and resources from. Defaults to empty.
+    pub zip_archives: Vec<String>,
+    /// Packed resources files to memory map at startup, in priority order. Defaults to empty.
+    pub packed_resources_files: Vec<String>,
+    /// ed25519 public key verifying packed resources files. Defaults to `None`.
+    pub packed_resources_public_key: Option<Vec<u8>>,
+    /// Whether to reload packed resources files when they change. Defaults to `false`.
+    pub watch_packed_resources_files: bool,
+    /// Number of process arguments after the program name removed from `sys.argv`. Defaults to 0.
+    pub sys_argv_skip: usize,
+    /// Arguments inserted into `sys.argv` after the program name. Defaults to empty.
+    pub sys_argv_insert: Vec<String>,
+    /// Names of environment variables visible in `os.environ`. Defaults to `None`.
+    pub environment_allowlist: Option<Vec<String>>,
+    /// Process arguments causing a console to be allocated. Defaults to empty.
+    pub allocate_console_args: Vec<String>,
+    /// Whether to run `multiprocessing` worker processes instead of `run_mode`. Defaults to true.
+    pub multiprocessing_auto_dispatch: bool,
+    /// Start method to configure `multiprocessing` with. Defaults to `None`.
+    pub multiprocessing_start_method: Option<MultiprocessingStartMethod>,
+    /// `PyPreConfig.allocator`. Defaults to `None`.
+    pub allocator: Option<Allocator>,
+    /// `PyPreConfig.configure_locale`. Defaults to `None`.
+    pub configure_locale: Option<bool>,
+    /// `PyPreConfig.coerce_c_locale`. Defaults to `None`.
+    pub coerce_c_locale: Option<CoerceCLocale>,
+    /// `PyPreConfig.coerce_c_locale_warn`. Defaults to `None`.
+    pub coerce_c_locale_warn: Option<bool>,
+    /// `PyPreConfig.dev_mode` and `PyConfig.dev_mode`. Defaults to `None`.
+    pub development_mode: Option<bool>,
+    /// `PyPreConfig.parse_argv` and `PyConfig.parse_argv`. Defaults to `None`.
+    pub parse_argv: Option<bool>,
+    /// `PyPreConfig.utf8_mode`. Defaults to `None`.
+    pub utf8_mode: Option<bool>,
+    /// `PyConfig.check_hash_pycs_mode`. Defaults to `None`.
+    pub check_hash_pycs_mode: Option<CheckHashPYCsMode>,
+    /// `PyConfig.configure_c_stdio`. Defaults to `None`.
+    pub configure_c_stdio: Option<bool>,
+    /// `PyConfig.dump_refs`. Defaults to `None`.
+    pub dump_refs: Option<bool>,
+    /// `PyConfig.faulthandler`. Defaults to `None`.
+    pub fault_handler: Option<bool>,
+    /// `PyConfig.filesystem_encoding`. Defaults to `None`.
+    pub filesystem_encoding: Option<String>,
+    /// `PyConfig.filesystem_errors`. Defaults to `None`.
+    pub filesystem_errors: Option<String>,
+    /// `PyConfig.hash_seed`. Setting it also sets `PyConfig.use_hash_seed`. Defaults to `None`.
+    pub hash_seed: Option<u64>,
+    /// `PyConfig.import_time`. Defaults to `None`.
+    pub import_time: Option<bool>,
+    /// `PyConfig.install_signal_handlers`. Defaults to `None`.
+    pub install_signal_handlers: Option<bool>,
+    /// `PyConfig.malloc_stats`. Defaults to `None`.
+    pub malloc_stats: Option<bool>,
+    /// `PyConfig.pathconfig_warnings`. Defaults to `None`.
+    pub pathconfig_warnings: Option<bool>,
+    /// `PyConfig.pycache_prefix`. Defaults to `None`.
+    pub pycache_prefix: Option<String>,
+    /// `PyConfig.show_alloc_count`. Defaults to `None`.
+    pub show_alloc_count: Option<bool>,
+    /// `PyConfig.show_ref_count`. Defaults to `None`.
+    pub show_ref_count: Option<bool>,
+    /// `PyConfig.skip_source_first_line`. Defaults to `None`.
+    pub skip_first_source_line: Option<bool>,
+    /// `PyConfig.tracemalloc`. Defaults to `None`.
+    pub tracemalloc: Option<bool>,
+    /// `PyConfig.warnoptions`. Defaults to empty.
+    pub warn_options: Vec<String>,
+    /// `PyConfig.xoptions`. Defaults to empty.
+    pub x_options: Vec<String>,
 }
 
 impl Default for EmbeddedPythonConfig {
@@ -91,17 +345,80 @@ impl Default for EmbeddedPythonConfig {
             unbuffered_stdio: false,
             use_hash_seed: false,
             verbose: 0,
+            gil_enabled: None,
             filesystem_importer: false,
+            in_memory_module_file: InMemoryModuleFile::None,
+            in_memory_package_path: true,
+            lazy_modules: Vec::new(),
             site_import: false,
             sys_frozen: false,
             sys_meipass: false,
             sys_paths: Vec::new(),
             raw_allocator: RawAllocator::System,
+            raw_allocator_mem_domain: false,
             run_mode: RunMode::Repl,
             terminfo_resolution: TerminfoResolution::None,
+            ssl_certificate_resolution: CertificateResolution::None,
+            tcl_library: None,
+            repl_line_editing: true,
+            repl_history_file: None,
             user_site_directory: false,
             write_bytecode: false,
             write_modules_directory_env: None,
+            startup_profile_env: None,
+            packed_resources_key_env: None,
+            stdio_redirect: StdioRedirect::None,
+            site_packages_overlay: None,
+            zip_archives: Vec::new(),
+            packed_resources_files: Vec::new(),
+            packed_resources_public_key: None,
+            watch_packed_resources_files: false,
+            sys_argv_skip: 0,
+            sys_argv_insert: Vec::new(),
+            environment_allowlist: None,
+            allocate_console_args: Vec::new(),
+            multiprocessing_auto_dispatch: true,
+            multiprocessing_start_method: None,
+            allocator: None,
+            configure_locale: None,
+            coerce_c_locale: None,
+            coerce_c_locale_warn: None,
+            development_mode: None,
+            parse_argv: None,
+            utf8_mode: None,
+            check_hash_pycs_mode: None,
+            configure_c_stdio: None,
+            dump_refs: None,
+            fault_handler: None,
+            filesystem_encoding: None,
+            filesystem_errors: None,
+            hash_seed: None,
+            import_time: None,
+            install_signal_handlers: None,
+            malloc_stats: None,
+            pathconfig_warnings: None,
+            pycache_prefix: None,
+            show_alloc_count: None,
+            show_ref_count: None,
+            skip_first_source_line: None,
+            tracemalloc: None,
+            warn_options: Vec::new(),
+            x_options: Vec::new(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_allocator_is_supported() {
+        assert!(RawAllocator::Jemalloc.is_supported("x86_64-unknown-linux-gnu"));
+        assert!(!RawAllocator::Jemalloc.is_supported("x86_64-pc-windows-msvc"));
+        assert!(RawAllocator::Mimalloc.is_supported("x86_64-pc-windows-msvc"));
+        assert!(RawAllocator::Mimalloc.is_supported("i686-pc-windows-msvc"));
+        assert!(RawAllocator::Snmalloc.is_supported("x86_64-apple-darwin"));
+        assert!(!RawAllocator::Snmalloc.is_supported("i686-pc-windows-msvc"));
+    }
+}
@@ -0,0 +1,424 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Reports describing package distributions installed by packaging tools.
+
+A report records which distributions were installed, their versions,
+whether they were installed from a wheel or built from a source
+distribution, which distributions required them, and how many bytes of
+resources they contributed.
+*/
+
+use {
+    super::packaging_tool::{normalize_distribution_name, requirement_name},
+    anyhow::{Context, Result},
+    python_packaging::package_metadata::PythonPackageMetadata,
+    python_packaging::resource::{PythonResource, ResourceProvenance},
+    serde::Serialize,
+    std::collections::{BTreeMap, BTreeSet},
+    std::path::{Path, PathBuf},
+};
+
+/// What a distribution was installed from.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DistributionKind {
+    /// A binary wheel.
+    Wheel,
+    /// A source distribution or source tree, which was built locally.
+    Sdist,
+}
+
+impl std::fmt::Display for DistributionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            DistributionKind::Wheel => "wheel",
+            DistributionKind::Sdist => "sdist",
+        })
+    }
+}
+
+/// A distribution in a `DependencyReport`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ReportedDistribution {
+    /// Name of the distribution, as declared by its metadata.
+    pub name: String,
+
+    /// Installed version of the distribution.
+    pub version: String,
+
+    /// What the distribution was installed from.
+    pub kind: DistributionKind,
+
+    /// Normalized names of distributions this distribution requires.
+    ///
+    /// Requirements only applying to extras are ignored.
+    pub requires: Vec<String>,
+
+    /// Normalized names of installed distributions requiring this distribution.
+    pub required_by: Vec<String>,
+
+    /// Number of resources installed by the distribution.
+    pub resource_count: usize,
+
+    /// Combined size in bytes of resources installed by the distribution.
+    pub size: u64,
+}
+
+/// Describes distributions installed by one or more packaging operations.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct DependencyReport {
+    /// Installed distributions, keyed by normalized name.
+    pub distributions: BTreeMap<String, ReportedDistribution>,
+}
+
+/// Obtain the normalized names of distributions pip built from source.
+///
+/// pip doesn't otherwise record whether an installed distribution came from
+/// a wheel. So this is derived from pip's output.
+pub fn source_built_distributions(pip_output: &[String]) -> BTreeSet<String> {
+    let mut res = BTreeSet::new();
+
+    for line in pip_output {
+        let line = line.trim();
+
+        for prefix in &["Building wheel for ", "Running setup.py install for "] {
+            if line.starts_with(prefix) {
+                if let Some(name) = line[prefix.len()..].split_whitespace().next() {
+                    res.insert(normalize_distribution_name(name));
+                }
+            }
+        }
+    }
+
+    res
+}
+
+/// Obtain names of distributions required by a distribution's metadata.
+fn metadata_requirements(metadata: &PythonPackageMetadata) -> Vec<String> {
+    let mut res = metadata
+        .find_all_headers("Requires-Dist")
+        .into_iter()
+        .filter(|requirement| match requirement.find(';') {
+            Some(pos) => !requirement[pos + 1..].contains("extra"),
+            None => true,
+        })
+        .map(requirement_name)
+        .filter(|name| !name.is_empty())
+        .collect::<Vec<_>>();
+
+    res.sort();
+    res.dedup();
+
+    res
+}
+
+impl DependencyReport {
+    /// Derive a report from resources found after a packaging operation.
+    ///
+    /// Distributions are discovered from their `METADATA` or `PKG-INFO`
+    /// distribution resources. `kind` resolves what a distribution with the
+    /// given normalized name was installed from.
+    pub fn from_resources(
+        resources: &[PythonResource],
+        kind: &dyn Fn(&str) -> DistributionKind,
+    ) -> Result<Self> {
+        let mut report = Self::default();
+
+        for resource in resources {
+            if let PythonResource::DistributionResource(r) = resource {
+                if r.name != "METADATA" && r.name != "PKG-INFO" {
+                    continue;
+                }
+
+                let metadata = PythonPackageMetadata::from_metadata(&r.data.resolve()?)
+                    .with_context(|| format!("parsing {} of {}", r.name, r.package))?;
+
+                let name = metadata.name().unwrap_or(&r.package).to_string();
+                let key = normalize_distribution_name(&name);
+
+                report.distributions.insert(
+                    key.clone(),
+                    ReportedDistribution {
+                        name,
+                        version: metadata.version().unwrap_or(&r.version).to_string(),
+                        kind: kind(&key),
+                        requires: metadata_requirements(&metadata),
+                        required_by: vec![],
+                        resource_count: 0,
+                        size: 0,
+                    },
+                );
+            }
+        }
+
+        for resource in resources {
+            let name = match resource.provenance() {
+                Some(ResourceProvenance::PackageDistribution { name, .. }) => name,
+                _ => continue,
+            };
+
+            if let Some(dist) = report
+                .distributions
+                .get_mut(&normalize_distribution_name(name))
+            {
+                dist.resource_count += 1;
+                dist.size += resource.data_size().unwrap_or(0);
+            }
+        }
+
+        report.link();
+
+        Ok(report)
+    }
+
+    /// Whether the report describes no distributions.
+    pub fn is_empty(&self) -> bool {
+        self.distributions.is_empty()
+    }
+
+    /// Add distributions from another report.
+    ///
+    /// Distributions in `other` replace distributions of the same name.
+    pub fn merge(&mut self, other: &DependencyReport) {
+        self.distributions.extend(
+            other
+                .distributions
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+
+        self.link();
+    }
+
+    /// Recompute `required_by` of all distributions.
+    fn link(&mut self) {
+        let mut required_by: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+        for (key, dist) in &self.distributions {
+            for requirement in &dist.requires {
+                required_by
+                    .entry(requirement.clone())
+                    .or_default()
+                    .push(key.clone());
+            }
+        }
+
+        for (key, dist) in self.distributions.iter_mut() {
+            dist.required_by = required_by.remove(key).unwrap_or_default();
+        }
+    }
+
+    /// Normalized names of distributions not required by other installed distributions.
+    ///
+    /// These are typically the distributions that were explicitly requested.
+    pub fn roots(&self) -> Vec<String> {
+        self.distributions
+            .iter()
+            .filter(|(_, dist)| dist.required_by.is_empty())
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Render the report as a dependency tree.
+    ///
+    /// Each root distribution is followed by the installed distributions it
+    /// requires, indented by depth. Distributions only reachable through a
+    /// dependency cycle are rendered as roots.
+    pub fn format_tree(&self) -> String {
+        let mut lines = vec![];
+        let mut visited = BTreeSet::new();
+
+        for root in self.roots() {
+            self.format_tree_entry(&root, 0, &mut vec![], &mut visited, &mut lines);
+        }
+
+        for key in self.distributions.keys() {
+            if !visited.contains(key) {
+                self.format_tree_entry(key, 0, &mut vec![], &mut visited, &mut lines);
+            }
+        }
+
+        lines.join("\n")
+    }
+
+    fn format_tree_entry(
+        &self,
+        key: &str,
+        depth: usize,
+        path: &mut Vec<String>,
+        visited: &mut BTreeSet<String>,
+        lines: &mut Vec<String>,
+    ) {
+        let dist = match self.distributions.get(key) {
+            Some(dist) => dist,
+            None => return,
+        };
+
+        let cycle = path.iter().any(|k| k == key);
+        lines.push(format!(
+            "{}{}=={} [{}, {} bytes]{}",
+            "  ".repeat(depth),
+            dist.name,
+            dist.version,
+            dist.kind,
+            dist.size,
+            if cycle { " (cycle)" } else { "" }
+        ));
+        visited.insert(key.to_string());
+
+        if cycle {
+            return;
+        }
+
+        path.push(key.to_string());
+        for requirement in &dist.requires {
+            self.format_tree_entry(requirement, depth + 1, path, visited, lines);
+        }
+        path.pop();
+    }
+
+    /// Filename of the dependency report of an executable.
+    pub fn filename(exe_name: &str) -> String {
+        format!("{}.dependencies.json", exe_name)
+    }
+
+    /// Write the report as JSON to a directory.
+    ///
+    /// Returns the path to the written file.
+    pub fn write_to_dir(&self, dir: &Path, exe_name: &str) -> Result<PathBuf> {
+        let path = dir.join(Self::filename(exe_name));
+
+        std::fs::write(&path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("writing {}", path.display()))?;
+
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        python_packaging::resource::{
+            DataLocation, PythonModuleSource, PythonPackageDistributionResource,
+            PythonPackageDistributionResourceFlavor,
+        },
+    };
+
+    fn metadata_resource(name: &str, version: &str, requires: &[&str]) -> PythonResource {
+        let mut data = format!(
+            "Metadata-Version: 2.1\nName: {}\nVersion: {}\n",
+            name, version
+        );
+        for requirement in requires {
+            data.push_str(&format!("Requires-Dist: {}\n", requirement));
+        }
+
+        PythonResource::DistributionResource(PythonPackageDistributionResource {
+            location: PythonPackageDistributionResourceFlavor::DistInfo,
+            package: name.to_string(),
+            version: version.to_string(),
+            name: "METADATA".to_string(),
+            data: DataLocation::Memory(data.into_bytes()),
+            provenance: None,
+        })
+    }
+
+    fn module_resource(name: &str, dist: &str, source: &str) -> PythonResource {
+        PythonResource::ModuleSource(PythonModuleSource {
+            name: name.to_string(),
+            source: DataLocation::Memory(source.as_bytes().to_vec()),
+            is_package: false,
+            cache_tag: "cpython-37".to_string(),
+            is_stdlib: false,
+            is_test: false,
+            provenance: Some(ResourceProvenance::PackageDistribution {
+                name: dist.to_string(),
+                version: "1.0".to_string(),
+            }),
+        })
+    }
+
+    #[test]
+    fn test_source_built_distributions() {
+        let output = vec![
+            "Collecting black==19.10b0".to_string(),
+            "  Building wheel for typed-ast (setup.py): started".to_string(),
+            "Running setup.py install for PyYAML ... done".to_string(),
+        ];
+
+        assert_eq!(
+            source_built_distributions(&output)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec!["pyyaml".to_string(), "typed-ast".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_from_resources() -> Result<()> {
+        let resources = vec![
+            metadata_resource(
+                "black",
+                "19.10b0",
+                &["click (>=6.5)", "typed-ast", "aiohttp ; extra == 'd'"],
+            ),
+            metadata_resource("Click", "7.0", &[]),
+            metadata_resource("typed_ast", "1.4.1", &[]),
+            module_resource("black", "black", "import click"),
+            module_resource("click", "Click", "pass"),
+            module_resource("click.core", "Click", "pass"),
+        ];
+
+        let report = DependencyReport::from_resources(&resources, &|name| {
+            if name == "typed-ast" {
+                DistributionKind::Sdist
+            } else {
+                DistributionKind::Wheel
+            }
+        })?;
+
+        assert_eq!(report.roots(), vec!["black".to_string()]);
+
+        let black = &report.distributions["black"];
+        assert_eq!(black.version, "19.10b0");
+        assert_eq!(
+            black.requires,
+            vec!["click".to_string(), "typed-ast".to_string()]
+        );
+        assert_eq!(black.size, 12);
+
+        let click = &report.distributions["click"];
+        assert_eq!(click.name, "Click");
+        assert_eq!(click.required_by, vec!["black".to_string()]);
+        assert_eq!(click.resource_count, 2);
+        assert_eq!(click.size, 8);
+
+        assert_eq!(
+            report.distributions["typed-ast"].kind,
+            DistributionKind::Sdist
+        );
+
+        assert_eq!(
+            report.format_tree(),
+            "black==19.10b0 [wheel, 12 bytes]\n  Click==7.0 [wheel, 8 bytes]\n  typed_ast==1.4.1 [sdist, 0 bytes]"
+        );
+
+        // Later reports replace distributions. Cycles are rendered once.
+        let mut merged = report.clone();
+        merged.merge(&DependencyReport::from_resources(
+            &[metadata_resource("click", "7.1", &["black"])],
+            &|_| DistributionKind::Wheel,
+        )?);
+        assert_eq!(merged.distributions["click"].version, "7.1");
+        assert!(merged.roots().is_empty());
+        assert!(merged
+            .format_tree()
+            .starts_with("black==19.10b0 [wheel, 12 bytes]\n  click==7.1 [wheel, 0 bytes]\n    black==19.10b0 [wheel, 12 bytes] (cycle)"));
+
+        Ok(())
+    }
+}
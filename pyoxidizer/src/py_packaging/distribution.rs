@@ -58,6 +58,73 @@ pub fn is_stdlib_test_package(name: &str) -> bool {
     false
 }
 
+/// Standard library packages rarely used by applications.
+///
+/// These are removed from distributions by default when trimming.
+pub const DEFAULT_TRIMMED_PACKAGES: &[&str] =
+    &["idlelib", "lib2to3", "tkinter", "turtle", "turtledemo"];
+
+/// Modules in the `encodings` package that are never removed by trimming.
+///
+/// The interpreter needs these to start.
+const REQUIRED_ENCODINGS: &[&str] = &["aliases", "ascii", "latin_1", "mbcs", "utf_8"];
+
+/// Describes resources to remove from a Python distribution.
+#[derive(Clone, Debug, Default)]
+pub struct DistributionTrimming {
+    /// Packages to remove, including their sub-packages and resources.
+    ///
+    /// Extension modules named after a package, with or without a leading
+    /// `_`, are removed too. e.g. removing `tkinter` removes `_tkinter`.
+    pub packages: Vec<String>,
+
+    /// Whether to keep test packages.
+    pub include_test: bool,
+
+    /// Modules in the `encodings` package to keep.
+    ///
+    /// `None` keeps all encodings. Encodings required by the interpreter are
+    /// always kept.
+    pub encodings: Option<Vec<String>>,
+}
+
+impl DistributionTrimming {
+    /// Whether a Python module or package is removed by this trimming.
+    pub fn is_package_trimmed(&self, name: &str) -> bool {
+        if !self.include_test && is_stdlib_test_package(name) {
+            return true;
+        }
+
+        for package in &self.packages {
+            if name == package || name.starts_with(&format!("{}.", package)) {
+                return true;
+            }
+        }
+
+        if let Some(encodings) = &self.encodings {
+            if name.starts_with("encodings.") {
+                let encoding = &name["encodings.".len()..];
+
+                return !REQUIRED_ENCODINGS.contains(&encoding)
+                    && !encodings.iter().any(|e| e == encoding);
+            }
+        }
+
+        false
+    }
+
+    /// Whether an extension module is removed by this trimming.
+    pub fn is_extension_module_trimmed(&self, name: &str) -> bool {
+        let package = if name.starts_with('_') {
+            &name[1..]
+        } else {
+            name
+        };
+
+        self.packages.iter().any(|p| p == name || p == package)
+    }
+}
+
 /// Denotes how a binary should link libpython.
 #[derive(Clone, Debug, PartialEq)]
 pub enum BinaryLibpythonLinkMode {
@@ -71,8 +138,19 @@ pub enum BinaryLibpythonLinkMode {
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum PythonDistributionLocation {
-    Local { local_path: String, sha256: String },
-    Url { url: String, sha256: String },
+    Local {
+        local_path: String,
+        sha256: String,
+    },
+    Url {
+        url: String,
+        sha256: String,
+    },
+    /// A Python installed on the machine running us.
+    Installed {
+        python_exe: String,
+        target_triple: String,
+    },
 }
 
 /// Describes an obtainable Python distribution.
@@ -86,6 +164,9 @@ pub struct PythonDistributionRecord {
 
     /// Whether the distribution can load prebuilt extension modules.
     pub supports_prebuilt_extension_modules: bool,
+
+    /// Whether the distribution is a free-threaded (no GIL) build.
+    pub free_threaded: bool,
 }
 
 /// Describes a generic Python distribution.
@@ -102,6 +183,9 @@ pub trait PythonDistribution {
     /// Obtain the cache tag to apply to Python bytecode modules.
     fn cache_tag(&self) -> &str;
 
+    /// Obtain the PEP 425 ABI tag of the distribution, if known.
+    fn python_abi_tag(&self) -> Option<&str>;
+
     /// Obtain file suffixes for various Python module flavors.
     fn python_module_suffixes(&self) -> Result<PythonModuleSuffixes>;
 
@@ -165,6 +249,12 @@ pub trait PythonDistribution {
         extra_python_paths: &[&Path],
     ) -> Result<HashMap<String, String>>;
 
+    /// Obtain a copy of this distribution with resources removed.
+    ///
+    /// Trimming a distribution before packaging avoids processing resources
+    /// that will never be used.
+    fn trim(&self, trimming: &DistributionTrimming) -> Box<dyn PythonDistribution>;
+
     /// Filter a collection of `PythonResource` through this distribution.
     ///
     /// We will throw away resources that aren't compatible with us. For
@@ -277,11 +367,26 @@ pub fn download_distribution(url: &str, sha256: &str, cache_dir: &Path) -> Resul
         }
     }
 
+    // file:// URLs allow mirrors on local or network filesystems.
+    if u.scheme() == "file" {
+        let path = u
+            .to_file_path()
+            .map_err(|_| anyhow!("unable to convert {} to a filesystem path", u))?;
+
+        return copy_local_distribution(&path, sha256, cache_dir);
+    }
+
     let mut data: Vec<u8> = Vec::new();
 
     println!("downloading {}", u);
     let client = get_http_client()?;
-    let mut response = client.get(u.as_str()).send()?;
+    let mut response = client.get(u.as_str()).send().with_context(|| {
+        format!(
+            "unable to download {}; to build without network access, place a copy of it in {} or use a local_path",
+            u,
+            cache_dir.display()
+        )
+    })?;
     response.read_to_end(&mut data)?;
 
     let mut hasher = Sha256::new();
@@ -331,6 +436,13 @@ pub fn copy_local_distribution(path: &PathBuf, sha256: &str, cache_dir: &Path) -
         }
     }
 
+    if !path.exists() {
+        return Err(anyhow!(
+            "Python distribution {} does not exist",
+            path.display()
+        ));
+    }
+
     let source_hash = sha256_path(&path);
 
     if source_hash != expected_hash {
@@ -368,6 +480,10 @@ pub fn resolve_python_distribution_archive(
         PythonDistributionLocation::Url { url, sha256 } => {
             download_distribution(url, sha256, cache_dir)
         }
+        PythonDistributionLocation::Installed { python_exe, .. } => Err(anyhow!(
+            "installed Python {} does not have a distribution archive",
+            python_exe
+        )),
     }
 }
 
@@ -390,6 +506,9 @@ pub fn resolve_python_distribution_from_location(
     let distribution_hash = match location {
         PythonDistributionLocation::Local { sha256, .. } => sha256,
         PythonDistributionLocation::Url { sha256, .. } => sha256,
+        PythonDistributionLocation::Installed { .. } => {
+            return Err(anyhow!("installed Python distributions are used in place"))
+        }
     };
 
     let distribution_path = distributions_dir.join(format!("python.{}", &distribution_hash[0..12]));
@@ -408,6 +527,9 @@ pub enum DistributionFlavor {
 
     /// Dynamically linked distributions coming from the `python-build-standalone` project.
     StandaloneDynamic,
+
+    /// Free-threaded (no GIL) distributions coming from the `python-build-standalone` project.
+    StandaloneFreethreaded,
 }
 
 impl Default for DistributionFlavor {
@@ -438,6 +560,20 @@ pub fn resolve_distribution(
         DistributionFlavor::StandaloneDynamic => Box::new(StandaloneDistribution::from_location(
             logger, &location, dest_dir,
         )?) as Box<dyn PythonDistribution>,
+
+        DistributionFlavor::StandaloneFreethreaded => {
+            let dist = StandaloneDistribution::from_location(logger, &location, dest_dir)?;
+
+            if !dist.is_free_threaded() {
+                return Err(anyhow!(
+                    "flavor=standalone_freethreaded requires a free-threaded distribution; Python {} with ABI tag {} is not free-threaded",
+                    dist.version,
+                    dist.python_abi_tag.as_ref().map_or("<none>", |x| x.as_str())
+                ));
+            }
+
+            Box::new(dist) as Box<dyn PythonDistribution>
+        }
     })
 }
 
@@ -492,6 +628,32 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_download_distribution_file_url() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let mirror_dir = temp_dir.path().join("mirror");
+        let cache_dir = temp_dir.path().join("cache");
+        create_dir_all(&mirror_dir)?;
+        create_dir_all(&cache_dir)?;
+
+        let source_path = mirror_dir.join("python.tar.zst");
+        fs::write(&source_path, b"distribution")?;
+        let sha256 = hex::encode(sha256_path(&source_path));
+
+        let url = Url::from_file_path(&source_path).unwrap();
+        let path = download_distribution(url.as_str(), &sha256, &cache_dir)?;
+        assert_eq!(path, cache_dir.join("python.tar.zst"));
+        assert_eq!(fs::read(&path)?, b"distribution");
+
+        // The cached copy is used when the mirror is unavailable.
+        fs::remove_file(&source_path)?;
+        download_distribution(url.as_str(), &sha256, &cache_dir)?;
+
+        assert!(download_distribution(url.as_str(), "00", temp_dir.path()).is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_all_standalone_distributions() -> Result<()> {
         assert!(!get_all_standalone_distributions()?.is_empty());
@@ -0,0 +1,196 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Machine readable descriptions of Python distributions.
+
+Authoring packaging policies, such as preferred extension module variants
+or license filtering, requires knowing what a distribution contains. The
+types in this module describe a distribution's content in a form that can
+be serialized to JSON.
+*/
+
+use {
+    super::distribution::{is_stdlib_test_package, PythonDistribution},
+    super::standalone_distribution::StandaloneDistribution,
+    anyhow::Result,
+    python_packaging::resource::PythonExtensionModule,
+    serde::Serialize,
+    std::collections::{BTreeMap, BTreeSet},
+};
+
+/// Describes a library an extension module links against.
+#[derive(Clone, Debug, Serialize)]
+pub struct LinkLibraryInfo {
+    /// Name of the library.
+    pub name: String,
+    /// Whether the library is provided by the operating system.
+    pub system: bool,
+    /// Whether the library is a macOS framework.
+    pub framework: bool,
+}
+
+/// Describes a variant of an extension module.
+#[derive(Clone, Debug, Serialize)]
+pub struct ExtensionModuleVariantInfo {
+    /// Name of the variant.
+    pub variant: String,
+    /// Whether the extension module is required to initialize the interpreter.
+    pub required: bool,
+    /// Whether the extension module is built into libpython by default.
+    pub builtin_default: bool,
+    /// SPDX license identifiers of libraries the variant links against.
+    pub licenses: Option<Vec<String>>,
+    /// Whether linked libraries are in the public domain.
+    pub license_public_domain: Option<bool>,
+    /// Libraries the variant links against.
+    pub link_libraries: Vec<LinkLibraryInfo>,
+    /// Whether the variant passes the `no-gpl` extension module filter.
+    pub no_gpl_allowed: bool,
+    /// Whether the extension module is known to not work on the target.
+    pub broken: bool,
+}
+
+/// Describes the content of a Python distribution.
+#[derive(Clone, Debug, Serialize)]
+pub struct DistributionInfo {
+    /// Rust target triple the distribution runs on.
+    pub target_triple: String,
+    /// Python version string. e.g. `3.7.7`.
+    pub version: String,
+    /// PEP 425 Python tag.
+    pub python_tag: String,
+    /// PEP 425 Python ABI tag.
+    pub python_abi_tag: Option<String>,
+    /// PEP 425 platform tag.
+    pub python_platform_tag: String,
+    /// SPDX license identifiers of Python itself.
+    pub licenses: Option<Vec<String>>,
+    /// Extension modules and their variants.
+    pub extension_modules: BTreeMap<String, Vec<ExtensionModuleVariantInfo>>,
+    /// Names of top-level packages and modules in the standard library.
+    pub stdlib_packages: Vec<String>,
+    /// Names of test packages in the standard library.
+    pub stdlib_test_packages: Vec<String>,
+    /// Resource files in the standard library, by package.
+    pub package_resources: BTreeMap<String, Vec<String>>,
+}
+
+impl DistributionInfo {
+    /// Describe a standalone distribution.
+    pub fn from_distribution(dist: &StandaloneDistribution) -> Result<Self> {
+        let policy = dist.create_packaging_policy()?;
+        let version = dist.python_major_minor_version();
+
+        let describe_variant = |em: &PythonExtensionModule| ExtensionModuleVariantInfo {
+            variant: em.variant.clone().unwrap_or_else(|| "default".to_string()),
+            required: em.required,
+            builtin_default: em.builtin_default,
+            licenses: em.licenses.clone(),
+            license_public_domain: em.license_public_domain,
+            link_libraries: em
+                .link_libraries
+                .iter()
+                .map(|l| LinkLibraryInfo {
+                    name: l.name.clone(),
+                    system: l.system,
+                    framework: l.framework,
+                })
+                .collect(),
+            no_gpl_allowed: policy.is_extension_module_license_allowed(em, &dist.target_triple),
+            broken: policy.is_extension_broken(&dist.target_triple, &em.name, &version),
+        };
+
+        let extension_modules = dist
+            .extension_modules
+            .iter()
+            .map(|(name, variants)| {
+                (
+                    name.clone(),
+                    variants.iter().map(&describe_variant).collect(),
+                )
+            })
+            .collect();
+
+        let stdlib_packages = dist
+            .py_modules
+            .keys()
+            .map(|name| name.split('.').next().unwrap().to_string())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        let stdlib_test_packages = dist
+            .py_modules
+            .keys()
+            .filter(|name| is_stdlib_test_package(name))
+            .filter(|name| {
+                !name
+                    .rsplitn(2, '.')
+                    .nth(1)
+                    .map_or(false, is_stdlib_test_package)
+            })
+            .cloned()
+            .collect();
+
+        let package_resources = dist
+            .resources
+            .iter()
+            .map(|(package, resources)| (package.clone(), resources.keys().cloned().collect()))
+            .collect();
+
+        Ok(Self {
+            target_triple: dist.target_triple.clone(),
+            version: dist.version.clone(),
+            python_tag: dist.python_tag.clone(),
+            python_abi_tag: dist.python_abi_tag.clone(),
+            python_platform_tag: dist.python_platform_tag.clone(),
+            licenses: dist.licenses.clone(),
+            extension_modules,
+            stdlib_packages,
+            stdlib_test_packages,
+            package_resources,
+        })
+    }
+
+    /// Serialize to a pretty-printed JSON document.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::testutil::*};
+
+    #[test]
+    fn test_distribution_info() -> Result<()> {
+        let dist = get_default_distribution()?;
+
+        let info = DistributionInfo::from_distribution(&dist)?;
+
+        assert_eq!(info.target_triple, env!("HOST"));
+        assert!(info.stdlib_packages.contains(&"json".to_string()));
+        assert!(!info.stdlib_packages.contains(&"json.decoder".to_string()));
+        assert!(info.stdlib_test_packages.contains(&"test".to_string()));
+        assert!(!info
+            .stdlib_test_packages
+            .iter()
+            .any(|p| p.starts_with("test.")));
+
+        let variants = info
+            .extension_modules
+            .get("_sqlite3")
+            .expect("_sqlite3 should be present");
+        assert!(!variants.is_empty());
+        assert!(variants
+            .iter()
+            .all(|v| v.link_libraries.iter().any(|l| l.name.contains("sqlite"))));
+
+        let json: serde_json::Value = serde_json::from_str(&info.to_json()?)?;
+        assert!(json["extension_modules"]["_sqlite3"].is_array());
+
+        Ok(())
+    }
+}
@@ -211,6 +211,7 @@ pub fn read_built_extensions(state_dir: &Path) -> Result<Vec<PythonExtensionModu
             licenses: None,
             license_texts: None,
             license_public_domain: None,
+            provenance: None,
         });
     }
 
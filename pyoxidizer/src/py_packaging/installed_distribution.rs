@@ -0,0 +1,179 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Using an already installed Python as a Python distribution.
+
+An installed CPython (e.g. a system Python, one installed by `pyenv`, or
+one installed from python.org) describes itself by running a script. The
+described installation can be used to build executables that dynamically
+link against its libpython, without obtaining a standalone distribution.
+*/
+
+use {
+    super::standalone_distribution::StandaloneDistribution,
+    anyhow::{anyhow, Context, Result},
+    serde::Deserialize,
+    slog::warn,
+    std::collections::BTreeMap,
+    std::path::{Path, PathBuf},
+};
+
+/// Script printing a JSON document describing a Python installation.
+const INTROSPECT_PYTHON_PY: &str = include_str!("introspect_python.py");
+
+/// Python versions an installed Python distribution can have.
+const SUPPORTED_PYTHON_VERSIONS: &[&str] = &["3.7", "3.8"];
+
+/// Describes an installed Python, as reported by the introspection script.
+#[derive(Clone, Debug, Deserialize)]
+pub struct InstalledPythonInfo {
+    /// Python version string. e.g. `3.7.7`.
+    pub version: String,
+    /// Path to the Python interpreter executable.
+    pub python_exe: PathBuf,
+    /// Installation prefix of the Python installation.
+    pub prefix: PathBuf,
+    /// Tag to apply to bytecode files.
+    pub cache_tag: String,
+    /// PEP 425 Python tag.
+    pub python_tag: String,
+    /// PEP 425 Python ABI tag.
+    pub python_abi_tag: Option<String>,
+    /// PEP 425 platform tag.
+    pub python_platform_tag: String,
+    /// Path to the Python standard library.
+    pub stdlib_path: PathBuf,
+    /// Path to Python header files.
+    pub include_path: PathBuf,
+    /// Directories holding installed packages.
+    ///
+    /// These may be within `stdlib_path` and are not part of the standard
+    /// library.
+    pub site_packages_paths: Vec<PathBuf>,
+    /// Path to the libpython shared library, if Python was built with one.
+    pub libpython_shared_library: Option<PathBuf>,
+    /// Names of extension modules compiled into libpython.
+    pub builtin_module_names: Vec<String>,
+    /// Extension modules installed as shared libraries.
+    ///
+    /// Keys are module names. Values are paths to shared libraries.
+    pub extension_modules: BTreeMap<String, PathBuf>,
+    /// Suffixes for Python module types.
+    ///
+    /// Keys are `source`, `bytecode`, `debug_bytecode`, `optimized_bytecode`
+    /// and `extension`.
+    pub python_suffixes: BTreeMap<String, Vec<String>>,
+}
+
+impl InstalledPythonInfo {
+    /// Obtain the X.Y Python version component. e.g. `3.7`.
+    pub fn major_minor_version(&self) -> String {
+        self.version
+            .splitn(3, '.')
+            .take(2)
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+}
+
+/// Describe an installed Python by running its interpreter.
+///
+/// `python_exe` can be a path or the name of an executable to find on
+/// `PATH`.
+pub fn introspect_installed_python(python_exe: &Path) -> Result<InstalledPythonInfo> {
+    let temp_dir = tempdir::TempDir::new("pyoxidizer-introspect-python")?;
+    let script_path = temp_dir.path().join("introspect_python.py");
+    std::fs::write(&script_path, INTROSPECT_PYTHON_PY)?;
+
+    let output = std::process::Command::new(python_exe)
+        .arg("-E")
+        .arg("-s")
+        .arg(&script_path)
+        .output()
+        .with_context(|| format!("running {}", python_exe.display()))?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "error introspecting {}: {}",
+            python_exe.display(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("parsing description of {}", python_exe.display()))
+}
+
+/// Resolve a Python distribution from an installed Python.
+///
+/// The installed Python must run on `target_triple` and must have been
+/// built with a libpython shared library, which produced executables link
+/// against.
+pub fn resolve_installed_distribution(
+    logger: &slog::Logger,
+    python_exe: &Path,
+    target_triple: &str,
+) -> Result<StandaloneDistribution> {
+    warn!(
+        logger,
+        "resolving Python distribution from {}",
+        python_exe.display()
+    );
+
+    let info = introspect_installed_python(python_exe)?;
+
+    let version = info.major_minor_version();
+    if !SUPPORTED_PYTHON_VERSIONS.contains(&version.as_str()) {
+        return Err(anyhow!(
+            "{} is Python {}; only Python {} is supported",
+            info.python_exe.display(),
+            info.version,
+            SUPPORTED_PYTHON_VERSIONS.join(" and ")
+        ));
+    }
+
+    if info.libpython_shared_library.is_none() {
+        return Err(anyhow!(
+            "{} was not built with a shared libpython; rebuild it with --enable-shared (e.g. PYTHON_CONFIGURE_OPTS=--enable-shared for pyenv)",
+            info.python_exe.display()
+        ));
+    }
+
+    warn!(
+        logger,
+        "using Python {} installed at {}",
+        info.version,
+        info.prefix.display()
+    );
+
+    StandaloneDistribution::from_installed_python(&info, target_triple)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_introspect_installed_python() -> Result<()> {
+        let python_exe = if cfg!(windows) { "python" } else { "python3" };
+
+        // Python isn't guaranteed to be installed.
+        let info = match introspect_installed_python(Path::new(python_exe)) {
+            Ok(info) => info,
+            Err(_) => return Ok(()),
+        };
+
+        assert!(info.python_exe.is_absolute());
+        assert!(info.stdlib_path.join("os.py").exists());
+        assert!(info.builtin_module_names.contains(&"sys".to_string()));
+        assert!(info.python_suffixes.contains_key("extension"));
+        assert_eq!(
+            info.cache_tag,
+            format!("cpython-{}", info.major_minor_version().replace(".", ""))
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+# This script is executed by an installed Python interpreter to describe
+# that Python installation so it can be used as a Python distribution.
+#
+# A JSON document is printed to stdout.
+
+import importlib.machinery
+import json
+import os
+import sys
+import sysconfig
+
+
+def relocate(config, path):
+    """Rebase a path configured at build time if the installation moved."""
+    prefix = config.get("prefix")
+
+    if path and prefix and not os.path.exists(path) and path.startswith(prefix):
+        return sys.base_prefix + path[len(prefix) :]
+
+    return path
+
+
+def resolve_libpython(config):
+    """Resolve the path to the libpython shared library, if there is one."""
+    if os.name == "nt":
+        candidates = [
+            os.path.join(sys.base_prefix, "python%d%d.dll" % sys.version_info[0:2])
+        ]
+    elif not config.get("Py_ENABLE_SHARED") and not config.get("PYTHONFRAMEWORK"):
+        return None
+    elif config.get("PYTHONFRAMEWORK"):
+        candidates = [
+            os.path.join(config["PYTHONFRAMEWORKPREFIX"], config["LDLIBRARY"])
+        ]
+    else:
+        candidates = [
+            os.path.join(relocate(config, config["LIBDIR"]), name)
+            for name in (config.get("INSTSONAME"), config.get("LDLIBRARY"))
+            if name
+        ]
+
+    for path in candidates:
+        if os.path.exists(path):
+            return path
+
+    return None
+
+
+def resolve_extension_modules(config):
+    """Find extension modules installed as shared libraries."""
+    if os.name == "nt":
+        path = os.path.join(sys.base_prefix, "DLLs")
+    else:
+        path = relocate(config, config.get("DESTSHARED"))
+
+    res = {}
+
+    if not path or not os.path.isdir(path):
+        return res
+
+    for filename in sorted(os.listdir(path)):
+        for suffix in importlib.machinery.EXTENSION_SUFFIXES:
+            if filename.endswith(suffix):
+                res.setdefault(filename[: -len(suffix)], os.path.join(path, filename))
+                break
+
+    return res
+
+
+def main():
+    config = sysconfig.get_config_vars()
+    paths = sysconfig.get_paths()
+
+    major, minor = sys.version_info[0:2]
+
+    info = {
+        "version": "%d.%d.%d" % sys.version_info[0:3],
+        "python_exe": sys.executable,
+        "prefix": sys.base_prefix,
+        "cache_tag": sys.implementation.cache_tag,
+        "python_tag": "cp%d%d" % (major, minor),
+        "python_abi_tag": "cp%d%d%s" % (major, minor, getattr(sys, "abiflags", "")),
+        "python_platform_tag": sysconfig.get_platform().replace("-", "_").replace(".", "_"),
+        "stdlib_path": paths["stdlib"],
+        "include_path": paths["include"],
+        "site_packages_paths": sorted(set([paths["purelib"], paths["platlib"]])),
+        "libpython_shared_library": resolve_libpython(config),
+        "builtin_module_names": sorted(sys.builtin_module_names),
+        "extension_modules": resolve_extension_modules(config),
+        "python_suffixes": {
+            "source": importlib.machinery.SOURCE_SUFFIXES,
+            "bytecode": importlib.machinery.BYTECODE_SUFFIXES,
+            "debug_bytecode": getattr(
+                importlib.machinery, "DEBUG_BYTECODE_SUFFIXES", [".pyc"]
+            ),
+            "optimized_bytecode": getattr(
+                importlib.machinery, "OPTIMIZED_BYTECODE_SUFFIXES", [".pyc"]
+            ),
+            "extension": importlib.machinery.EXTENSION_SUFFIXES,
+        },
+    }
+
+    sys.stdout.write(json.dumps(info))
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,1014 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Resolving pinned requirements from lock files of Python packaging tools.
+
+Lock files record the exact version and artifact hashes of every package in
+a project's dependency tree. The packages they describe are converted to a
+pip requirements file with hashes, which pip installs without resolving
+dependencies. So the tool producing the lock file doesn't need to be
+installed.
+
+pip requirements files in which every requirement is pinned and has hashes
+are treated as lock files too.
+
+Downloaded artifacts are verified against the hashes of the lock file
+before they are installed.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    serde::{Deserialize, Serialize},
+    sha2::{Digest, Sha256, Sha384, Sha512},
+    std::collections::{BTreeMap, BTreeSet, VecDeque},
+    std::path::{Path, PathBuf},
+};
+
+/// A package pinned to an exact version by a lock file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LockedRequirement {
+    /// Normalized name of the package.
+    pub name: String,
+    /// Exact version of the package.
+    pub version: String,
+    /// Hashes of the package's artifacts, in pip's `<algorithm>:<digest>` form.
+    pub hashes: Vec<String>,
+    /// PEP 508 environment markers controlling whether the package is installed.
+    pub markers: Option<String>,
+}
+
+impl LockedRequirement {
+    /// Obtain the line defining this requirement in a pip requirements file.
+    pub fn to_requirements_line(&self) -> String {
+        let mut line = format!("{}=={}", self.name, self.version);
+
+        if let Some(markers) = &self.markers {
+            line.push_str(&format!(" ; {}", markers));
+        }
+
+        for hash in &self.hashes {
+            line.push_str(&format!(" --hash={}", hash));
+        }
+
+        line
+    }
+}
+
+/// Packages pinned by a lock file and where to find them.
+#[derive(Clone, Debug, Default)]
+pub struct LockedRequirements {
+    /// Pinned packages.
+    pub requirements: Vec<LockedRequirement>,
+    /// URL of the package index to search instead of PyPI.
+    pub index_url: Option<String>,
+    /// URLs of package indexes to search in addition to PyPI.
+    pub extra_index_urls: Vec<String>,
+}
+
+impl LockedRequirements {
+    /// Obtain the content of a pip requirements file installing these packages.
+    pub fn to_requirements_file_content(&self) -> String {
+        let mut lines = self
+            .index_url
+            .iter()
+            .map(|url| format!("--index-url {}", url))
+            .chain(
+                self.extra_index_urls
+                    .iter()
+                    .map(|url| format!("--extra-index-url {}", url)),
+            )
+            .collect::<Vec<_>>();
+
+        lines.extend(
+            self.requirements
+                .iter()
+                .map(LockedRequirement::to_requirements_line),
+        );
+
+        lines.join("\n") + "\n"
+    }
+
+    /// Write a pip requirements file installing these packages.
+    pub fn write_requirements_file(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_requirements_file_content())
+            .with_context(|| format!("writing {}", path.display()))
+    }
+}
+
+/// Normalize a Python package name as described by PEP 503.
+pub fn normalize_package_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+
+    for c in name.chars() {
+        if c == '-' || c == '_' || c == '.' {
+            if !normalized.ends_with('-') {
+                normalized.push('-');
+            }
+        } else {
+            normalized.push(c.to_ascii_lowercase());
+        }
+    }
+
+    normalized
+}
+
+#[derive(Debug, Deserialize)]
+struct PoetryLock {
+    #[serde(default)]
+    package: Vec<PoetryPackage>,
+    #[serde(default)]
+    metadata: PoetryMetadata,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoetryMetadata {
+    /// Artifacts of each package, as recorded by lock files before version 2.
+    #[serde(default)]
+    files: BTreeMap<String, Vec<PoetryFile>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PoetryPackage {
+    name: String,
+    version: String,
+    /// Group of the package, as recorded by lock files before version 2.
+    category: Option<String>,
+    /// Groups of the package, as recorded by lock files since version 2.1.
+    groups: Option<Vec<String>>,
+    /// Environment markers. Either a string or a table keyed by group.
+    markers: Option<toml::Value>,
+    #[serde(default)]
+    dependencies: BTreeMap<String, toml::Value>,
+    #[serde(default)]
+    files: Vec<PoetryFile>,
+    source: Option<PoetrySource>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PoetryFile {
+    hash: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PoetrySource {
+    #[serde(rename = "type")]
+    source_type: String,
+    url: String,
+}
+
+/// Whether a dependency declaration only applies when an extra is requested.
+fn is_optional_dependency(value: &toml::Value) -> bool {
+    value
+        .get("optional")
+        .and_then(toml::Value::as_bool)
+        .unwrap_or(false)
+}
+
+/// Obtain the names of the direct dependencies of each group in a `pyproject.toml`.
+fn pyproject_group_dependencies(pyproject: &toml::Value) -> BTreeMap<String, Vec<String>> {
+    let mut groups = BTreeMap::new();
+
+    let poetry = match pyproject.get("tool").and_then(|tool| tool.get("poetry")) {
+        Some(poetry) => poetry,
+        None => return groups,
+    };
+
+    let mut add_group = |group: &str, dependencies: Option<&toml::Value>| {
+        if let Some(dependencies) = dependencies.and_then(toml::Value::as_table) {
+            groups
+                .entry(group.to_string())
+                .or_insert_with(Vec::new)
+                .extend(
+                    dependencies
+                        .iter()
+                        .filter(|(name, value)| *name != "python" && !is_optional_dependency(value))
+                        .map(|(name, _)| normalize_package_name(name)),
+                );
+        }
+    };
+
+    add_group("main", poetry.get("dependencies"));
+    add_group("dev", poetry.get("dev-dependencies"));
+
+    if let Some(group_tables) = poetry.get("group").and_then(toml::Value::as_table) {
+        for (group, table) in group_tables {
+            add_group(group, table.get("dependencies"));
+        }
+    }
+
+    groups
+}
+
+/// Resolve the groups of lock file packages by walking the dependency graph.
+///
+/// Lock files of version 2.0 don't record groups. So packages are assigned to
+/// the groups of the `pyproject.toml` dependencies they are required by.
+fn resolve_package_groups(
+    packages: &[PoetryPackage],
+    pyproject: &toml::Value,
+) -> BTreeMap<String, BTreeSet<String>> {
+    let dependencies = packages
+        .iter()
+        .map(|package| {
+            (
+                normalize_package_name(&package.name),
+                package
+                    .dependencies
+                    .iter()
+                    .filter(|(_, value)| !is_optional_dependency(value))
+                    .map(|(name, _)| normalize_package_name(name))
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let mut package_groups: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+
+    for (group, roots) in pyproject_group_dependencies(pyproject) {
+        let mut queue = roots.into_iter().collect::<VecDeque<_>>();
+
+        while let Some(name) = queue.pop_front() {
+            if package_groups
+                .entry(name.clone())
+                .or_insert_with(BTreeSet::new)
+                .insert(group.clone())
+            {
+                if let Some(deps) = dependencies.get(&name) {
+                    queue.extend(deps.iter().cloned());
+                }
+            }
+        }
+    }
+
+    package_groups
+}
+
+/// Resolve packages in the given groups of a `poetry.lock` file.
+///
+/// Older lock files record the groups of packages. For lock files that
+/// don't, the `pyproject.toml` next to the lock file is read to resolve them.
+pub fn read_poetry_lock(path: &Path, groups: &[String]) -> Result<LockedRequirements> {
+    let data =
+        std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let lock: PoetryLock =
+        toml::from_str(&data).with_context(|| format!("parsing {}", path.display()))?;
+
+    let records_groups = lock
+        .package
+        .iter()
+        .all(|p| p.groups.is_some() || p.category.is_some());
+
+    let resolved_groups = if records_groups {
+        BTreeMap::new()
+    } else {
+        let pyproject_path = path.with_file_name("pyproject.toml");
+        let pyproject_data = std::fs::read_to_string(&pyproject_path).with_context(|| {
+            format!(
+                "reading {} to resolve package groups",
+                pyproject_path.display()
+            )
+        })?;
+        let pyproject: toml::Value = toml::from_str(&pyproject_data)
+            .with_context(|| format!("parsing {}", pyproject_path.display()))?;
+
+        resolve_package_groups(&lock.package, &pyproject)
+    };
+
+    let mut locked = LockedRequirements::default();
+
+    for package in &lock.package {
+        let name = normalize_package_name(&package.name);
+
+        let package_groups = if let Some(groups) = &package.groups {
+            groups.iter().cloned().collect()
+        } else if let Some(category) = &package.category {
+            vec![category.clone()].into_iter().collect()
+        } else {
+            resolved_groups.get(&name).cloned().unwrap_or_default()
+        };
+
+        let selected = package_groups
+            .iter()
+            .filter(|g| groups.contains(g))
+            .collect::<Vec<_>>();
+
+        if selected.is_empty() {
+            continue;
+        }
+
+        if let Some(source) = &package.source {
+            match source.source_type.as_str() {
+                "legacy" => {
+                    if !locked.extra_index_urls.contains(&source.url) {
+                        locked.extra_index_urls.push(source.url.clone());
+                    }
+                }
+                source_type => {
+                    return Err(anyhow!(
+                        "{} is installed from a {} source, which is not supported; only packages from package indexes can be installed",
+                        package.name,
+                        source_type
+                    ));
+                }
+            }
+        }
+
+        let files = if package.files.is_empty() {
+            lock.metadata
+                .files
+                .get(&package.name)
+                .map(|files| files.as_slice())
+                .unwrap_or_default()
+        } else {
+            package.files.as_slice()
+        };
+
+        if files.is_empty() {
+            return Err(anyhow!(
+                "{} does not record hashes of {} {}",
+                path.display(),
+                package.name,
+                package.version
+            ));
+        }
+
+        // Markers keyed by group only apply when all selected groups have one.
+        let markers = match &package.markers {
+            Some(toml::Value::String(markers)) => Some(markers.clone()),
+            Some(toml::Value::Table(group_markers)) => selected
+                .iter()
+                .map(|g| group_markers.get(*g).and_then(toml::Value::as_str))
+                .collect::<Option<Vec<_>>>()
+                .map(|markers| {
+                    if markers.len() == 1 {
+                        markers[0].to_string()
+                    } else {
+                        markers
+                            .iter()
+                            .map(|m| format!("({})", m))
+                            .collect::<Vec<_>>()
+                            .join(" or ")
+                    }
+                }),
+            _ => None,
+        };
+
+        locked.requirements.push(LockedRequirement {
+            name,
+            version: package.version.clone(),
+            hashes: files.iter().map(|f| f.hash.clone()).collect(),
+            markers,
+        });
+    }
+
+    Ok(locked)
+}
+
+/// URL of the PyPI simple package index, which pip searches by default.
+const PYPI_SIMPLE_URL: &str = "https://pypi.org/simple";
+
+#[derive(Debug, Deserialize)]
+struct PipfileLock {
+    #[serde(rename = "_meta")]
+    meta: PipfileLockMeta,
+    /// Package categories. e.g. `default` and `develop`.
+    #[serde(flatten)]
+    categories: BTreeMap<String, BTreeMap<String, PipfileLockPackage>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PipfileLockMeta {
+    #[serde(default)]
+    sources: Vec<PipfileLockSource>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PipfileLockSource {
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PipfileLockPackage {
+    /// Version specifier. e.g. `==2.24.0`. Absent for packages not installed from an index.
+    version: Option<String>,
+    #[serde(default)]
+    hashes: Vec<String>,
+    markers: Option<String>,
+}
+
+/// Resolve packages in the given categories of a `Pipfile.lock` file.
+///
+/// Categories are named after the sections of the `Pipfile`. `default`
+/// holds `[packages]` and `develop` holds `[dev-packages]`.
+pub fn read_pipfile_lock(path: &Path, categories: &[String]) -> Result<LockedRequirements> {
+    let data = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    let lock: PipfileLock =
+        serde_json::from_slice(&data).with_context(|| format!("parsing {}", path.display()))?;
+
+    let mut locked = LockedRequirements::default();
+
+    locked.extra_index_urls = lock
+        .meta
+        .sources
+        .iter()
+        .map(|source| source.url.trim_end_matches('/').to_string())
+        .filter(|url| url != PYPI_SIMPLE_URL)
+        .collect();
+
+    for category in categories {
+        let packages = lock
+            .categories
+            .get(category)
+            .ok_or_else(|| anyhow!("{} does not have a {} category", path.display(), category))?;
+
+        for (name, package) in packages {
+            let name = normalize_package_name(name);
+
+            // A package can be in multiple categories.
+            if locked.requirements.iter().any(|r| r.name == name) {
+                continue;
+            }
+
+            let version = match &package.version {
+                Some(version) if version.starts_with("==") => version[2..].to_string(),
+                _ => {
+                    return Err(anyhow!(
+                        "{} is not pinned to a version from a package index, which is not supported",
+                        name
+                    ));
+                }
+            };
+
+            if package.hashes.is_empty() {
+                return Err(anyhow!(
+                    "{} does not record hashes of {} {}",
+                    path.display(),
+                    name,
+                    version
+                ));
+            }
+
+            locked.requirements.push(LockedRequirement {
+                name,
+                version,
+                hashes: package.hashes.clone(),
+                markers: package.markers.clone(),
+            });
+        }
+    }
+
+    Ok(locked)
+}
+
+/// Resolve packages in a pip requirements file using hash-checking mode.
+///
+/// Like pip's `--require-hashes`, every requirement must be pinned to an
+/// exact version with `==` and must have at least one `--hash`. Requirements
+/// files included with `-r` are read too.
+pub fn read_requirements_file(path: &Path) -> Result<LockedRequirements> {
+    let mut locked = LockedRequirements::default();
+    read_requirements_file_into(path, &mut locked)?;
+
+    Ok(locked)
+}
+
+fn read_requirements_file_into(path: &Path, locked: &mut LockedRequirements) -> Result<()> {
+    let data =
+        std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+
+    // Lines ending with a backslash continue on the next line.
+    let data = data.replace("\\\r\n", " ").replace("\\\n", " ");
+
+    for line in data.lines() {
+        // Comments start at a # at the beginning of a line or after whitespace.
+        let line = match line.find(" #") {
+            Some(pos) => &line[0..pos],
+            None if line.trim_start().starts_with('#') => "",
+            None => line,
+        }
+        .trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with('-') {
+            let mut parts = line.splitn(2, |c: char| c == '=' || c.is_whitespace());
+            let option = parts.next().unwrap_or_default();
+            let value = parts.next().unwrap_or_default().trim().to_string();
+
+            match option {
+                "-r" | "--requirement" => {
+                    let include_path = path.parent().unwrap_or_else(|| Path::new("")).join(&value);
+                    read_requirements_file_into(&include_path, locked)?;
+                }
+                "-i" | "--index-url" => {
+                    locked.index_url = Some(value);
+                }
+                "--extra-index-url" => {
+                    if !locked.extra_index_urls.contains(&value) {
+                        locked.extra_index_urls.push(value);
+                    }
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "{}: {} is not supported in hash-checking mode",
+                        path.display(),
+                        option
+                    ));
+                }
+            }
+
+            continue;
+        }
+
+        // Options like --hash follow the requirement specifier and markers.
+        let (requirement, options) = match line.find(" --") {
+            Some(pos) => (&line[0..pos], &line[pos..]),
+            None => (line, ""),
+        };
+
+        let mut requirement_parts = requirement.splitn(2, ';');
+        let specifier = requirement_parts.next().unwrap_or_default().trim();
+        let markers = requirement_parts
+            .next()
+            .map(|markers| markers.trim().to_string())
+            .filter(|markers| !markers.is_empty());
+
+        let (name, version) = match specifier.find("==") {
+            Some(pos) if !specifier[pos + 2..].starts_with('=') => (
+                specifier[0..pos].trim(),
+                specifier[pos + 2..].trim().to_string(),
+            ),
+            _ => {
+                return Err(anyhow!(
+                    "{}: {} must be pinned to a version with == in hash-checking mode",
+                    path.display(),
+                    specifier
+                ));
+            }
+        };
+
+        // Extras don't matter since dependencies are requirements themselves.
+        let name = normalize_package_name(name.split('[').next().unwrap_or_default().trim());
+
+        let mut hashes = vec![];
+        let mut tokens = options.split_whitespace();
+        while let Some(token) = tokens.next() {
+            if token.starts_with("--hash=") {
+                hashes.push(token["--hash=".len()..].to_string());
+            } else if token == "--hash" {
+                hashes.push(tokens.next().unwrap_or_default().to_string());
+            } else {
+                return Err(anyhow!(
+                    "{}: {} is not supported in hash-checking mode",
+                    path.display(),
+                    token
+                ));
+            }
+        }
+
+        if hashes.is_empty() {
+            return Err(anyhow!(
+                "{}: {} does not have a --hash, which is required in hash-checking mode",
+                path.display(),
+                specifier
+            ));
+        }
+
+        locked.requirements.push(LockedRequirement {
+            name,
+            version,
+            hashes,
+            markers,
+        });
+    }
+
+    Ok(())
+}
+
+/// A downloaded artifact whose hash matched a lock file.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct VerifiedArtifact {
+    /// Normalized name of the package the artifact belongs to.
+    pub name: String,
+    /// Version of the package the artifact belongs to.
+    pub version: String,
+    /// Filename of the artifact.
+    pub filename: String,
+    /// Hash of the artifact that matched, in pip's `<algorithm>:<digest>` form.
+    pub hash: String,
+}
+
+/// Compute a hash of data in pip's `<algorithm>:<digest>` form.
+pub fn artifact_hash(algorithm: &str, data: &[u8]) -> Result<String> {
+    let digest = match algorithm {
+        "sha256" => Sha256::digest(data).to_vec(),
+        "sha384" => Sha384::digest(data).to_vec(),
+        "sha512" => Sha512::digest(data).to_vec(),
+        _ => return Err(anyhow!("unsupported hash algorithm: {}", algorithm)),
+    };
+
+    Ok(format!("{}:{}", algorithm, hex::encode(digest)))
+}
+
+/// Verify downloaded artifacts against the hashes of locked requirements.
+///
+/// Every file in `paths` must have a hash matching a locked requirement.
+/// Returns a description of each verified artifact.
+pub fn verify_artifacts(
+    locked: &LockedRequirements,
+    paths: &[PathBuf],
+) -> Result<Vec<VerifiedArtifact>> {
+    let mut verified = vec![];
+
+    for path in paths {
+        let filename = path
+            .file_name()
+            .ok_or_else(|| anyhow!("unable to resolve filename of {}", path.display()))?
+            .to_string_lossy()
+            .to_string();
+        let data = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+
+        let mut matched = None;
+        let mut actual = BTreeSet::new();
+
+        for requirement in &locked.requirements {
+            for expected in &requirement.hashes {
+                let algorithm = expected.splitn(2, ':').next().unwrap_or_default();
+                let hash = artifact_hash(algorithm, &data)?;
+
+                if hash.eq_ignore_ascii_case(expected) {
+                    matched = Some((requirement, hash));
+                    break;
+                }
+
+                actual.insert(hash);
+            }
+
+            if matched.is_some() {
+                break;
+            }
+        }
+
+        match matched {
+            Some((requirement, hash)) => verified.push(VerifiedArtifact {
+                name: requirement.name.clone(),
+                version: requirement.version.clone(),
+                filename,
+                hash,
+            }),
+            None => {
+                return Err(anyhow!(
+                    "hash of {} ({}) does not match any hash in the lock file",
+                    filename,
+                    actual.into_iter().collect::<Vec<_>>().join(", ")
+                ));
+            }
+        }
+    }
+
+    Ok(verified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_package_name() {
+        assert_eq!(normalize_package_name("Foo_Bar"), "foo-bar");
+        assert_eq!(normalize_package_name("zope.interface"), "zope-interface");
+        assert_eq!(normalize_package_name("a-_.b"), "a-b");
+    }
+
+    #[test]
+    fn test_poetry_lock_categories() -> Result<()> {
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+        let path = td.path().join("poetry.lock");
+
+        std::fs::write(
+            &path,
+            indoc::indoc!(
+                r#"
+                [[package]]
+                name = "Six"
+                version = "1.15.0"
+                category = "main"
+
+                [[package]]
+                name = "pytest"
+                version = "6.0.1"
+                category = "dev"
+
+                [metadata]
+                content-hash = "abc"
+
+                [metadata.files]
+                Six = [
+                    {file = "six-1.15.0-py2.py3-none-any.whl", hash = "sha256:aaaa"},
+                    {file = "six-1.15.0.tar.gz", hash = "sha256:bbbb"},
+                ]
+                pytest = [
+                    {file = "pytest-6.0.1-py3-none-any.whl", hash = "sha256:cccc"},
+                ]
+                "#
+            ),
+        )?;
+
+        let locked = read_poetry_lock(&path, &["main".to_string()])?;
+        assert_eq!(
+            locked.requirements,
+            vec![LockedRequirement {
+                name: "six".to_string(),
+                version: "1.15.0".to_string(),
+                hashes: vec!["sha256:aaaa".to_string(), "sha256:bbbb".to_string()],
+                markers: None,
+            }]
+        );
+        assert_eq!(
+            locked.requirements[0].to_requirements_line(),
+            "six==1.15.0 --hash=sha256:aaaa --hash=sha256:bbbb"
+        );
+
+        let locked = read_poetry_lock(&path, &["main".to_string(), "dev".to_string()])?;
+        assert_eq!(locked.requirements.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_poetry_lock_pyproject_groups() -> Result<()> {
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+        let path = td.path().join("poetry.lock");
+
+        std::fs::write(
+            td.path().join("pyproject.toml"),
+            indoc::indoc!(
+                r#"
+                [tool.poetry.dependencies]
+                python = "^3.8"
+                requests = "^2.24"
+                ujson = {version = "*", optional = true}
+
+                [tool.poetry.group.test.dependencies]
+                pytest = "*"
+                "#
+            ),
+        )?;
+
+        std::fs::write(
+            &path,
+            indoc::indoc!(
+                r#"
+                [[package]]
+                name = "requests"
+                version = "2.24.0"
+                files = [{file = "requests-2.24.0-py2.py3-none-any.whl", hash = "sha256:1111"}]
+
+                [package.dependencies]
+                idna = ">=2.5"
+
+                [[package]]
+                name = "idna"
+                version = "2.10"
+                markers = 'python_version >= "3.8"'
+                files = [{file = "idna-2.10-py2.py3-none-any.whl", hash = "sha256:2222"}]
+
+                [[package]]
+                name = "ujson"
+                version = "3.1.0"
+                files = [{file = "ujson-3.1.0.tar.gz", hash = "sha256:3333"}]
+
+                [[package]]
+                name = "pytest"
+                version = "6.0.1"
+                files = [{file = "pytest-6.0.1-py3-none-any.whl", hash = "sha256:4444"}]
+
+                [package.source]
+                type = "legacy"
+                url = "https://pypi.example.com/simple"
+                reference = "internal"
+                "#
+            ),
+        )?;
+
+        let locked = read_poetry_lock(&path, &["main".to_string()])?;
+        assert_eq!(
+            locked
+                .requirements
+                .iter()
+                .map(|r| r.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["requests", "idna"]
+        );
+        assert_eq!(
+            locked.requirements[1].to_requirements_line(),
+            "idna==2.10 ; python_version >= \"3.8\" --hash=sha256:2222"
+        );
+        assert!(locked.extra_index_urls.is_empty());
+
+        let locked = read_poetry_lock(&path, &["test".to_string()])?;
+        assert_eq!(locked.requirements.len(), 1);
+        assert_eq!(
+            locked.extra_index_urls,
+            vec!["https://pypi.example.com/simple".to_string()]
+        );
+
+        let requirements_path = td.path().join("requirements.txt");
+        locked.write_requirements_file(&requirements_path)?;
+        assert_eq!(
+            std::fs::read_to_string(&requirements_path)?,
+            "--extra-index-url https://pypi.example.com/simple\npytest==6.0.1 --hash=sha256:4444\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_poetry_lock_unsupported_source() -> Result<()> {
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+        let path = td.path().join("poetry.lock");
+
+        std::fs::write(
+            &path,
+            indoc::indoc!(
+                r#"
+                [[package]]
+                name = "mylib"
+                version = "0.1.0"
+                groups = ["main"]
+                files = []
+
+                [package.source]
+                type = "directory"
+                url = "../mylib"
+                "#
+            ),
+        )?;
+
+        assert!(read_poetry_lock(&path, &["main".to_string()]).is_err());
+        assert!(read_poetry_lock(&path, &["dev".to_string()])?
+            .requirements
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pipfile_lock() -> Result<()> {
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+        let path = td.path().join("Pipfile.lock");
+
+        std::fs::write(
+            &path,
+            indoc::indoc!(
+                r#"
+                {
+                    "_meta": {
+                        "hash": {"sha256": "abc"},
+                        "pipfile-spec": 6,
+                        "requires": {"python_version": "3.8"},
+                        "sources": [
+                            {"name": "pypi", "url": "https://pypi.org/simple", "verify_ssl": true},
+                            {"name": "internal", "url": "https://pypi.example.com/simple/", "verify_ssl": true}
+                        ]
+                    },
+                    "default": {
+                        "Six": {
+                            "hashes": ["sha256:aaaa", "sha256:bbbb"],
+                            "index": "pypi",
+                            "markers": "python_version >= '2.7'",
+                            "version": "==1.15.0"
+                        }
+                    },
+                    "develop": {
+                        "six": {
+                            "hashes": ["sha256:aaaa", "sha256:bbbb"],
+                            "version": "==1.15.0"
+                        },
+                        "pytest": {
+                            "hashes": ["sha256:cccc"],
+                            "version": "==6.0.1"
+                        },
+                        "mylib": {
+                            "editable": true,
+                            "path": "."
+                        }
+                    }
+                }
+                "#
+            ),
+        )?;
+
+        let locked = read_pipfile_lock(&path, &["default".to_string()])?;
+        assert_eq!(
+            locked.extra_index_urls,
+            vec!["https://pypi.example.com/simple".to_string()]
+        );
+        assert_eq!(
+            locked.requirements,
+            vec![LockedRequirement {
+                name: "six".to_string(),
+                version: "1.15.0".to_string(),
+                hashes: vec!["sha256:aaaa".to_string(), "sha256:bbbb".to_string()],
+                markers: Some("python_version >= '2.7'".to_string()),
+            }]
+        );
+
+        assert!(read_pipfile_lock(&path, &["default".to_string(), "develop".to_string()]).is_err());
+        assert!(read_pipfile_lock(&path, &["packages".to_string()]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_requirements_file() -> Result<()> {
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+        let path = td.path().join("requirements.txt");
+
+        std::fs::write(
+            td.path().join("base.txt"),
+            "--extra-index-url https://pypi.example.com/simple\nsix==1.15.0 --hash=sha256:aaaa\n",
+        )?;
+        std::fs::write(
+            &path,
+            indoc::indoc!(
+                r#"
+                # Pinned dependencies.
+                -r base.txt
+                Foo_Bar[extra]==1.0 ; python_version >= "3.8" \
+                    --hash=sha256:bbbb \
+                    --hash sha256:cccc  # Comment.
+                "#
+            ),
+        )?;
+
+        let locked = read_requirements_file(&path)?;
+        assert_eq!(
+            locked.extra_index_urls,
+            vec!["https://pypi.example.com/simple".to_string()]
+        );
+        assert_eq!(
+            locked.requirements,
+            vec![
+                LockedRequirement {
+                    name: "six".to_string(),
+                    version: "1.15.0".to_string(),
+                    hashes: vec!["sha256:aaaa".to_string()],
+                    markers: None,
+                },
+                LockedRequirement {
+                    name: "foo-bar".to_string(),
+                    version: "1.0".to_string(),
+                    hashes: vec!["sha256:bbbb".to_string(), "sha256:cccc".to_string()],
+                    markers: Some("python_version >= \"3.8\"".to_string()),
+                }
+            ]
+        );
+
+        std::fs::write(&path, "six>=1.15.0 --hash=sha256:aaaa\n")?;
+        assert!(read_requirements_file(&path).is_err());
+        std::fs::write(&path, "six==1.15.0\n")?;
+        assert!(read_requirements_file(&path).is_err());
+        std::fs::write(&path, "-e .\n")?;
+        assert!(read_requirements_file(&path).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_artifacts() -> Result<()> {
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+        let path = td.path().join("six-1.15.0-py2.py3-none-any.whl");
+        std::fs::write(&path, b"wheel content")?;
+
+        let hash = artifact_hash("sha256", b"wheel content")?;
+        let digest = hash["sha256:".len()..].to_uppercase();
+
+        let mut locked = LockedRequirements::default();
+        locked.requirements.push(LockedRequirement {
+            name: "six".to_string(),
+            version: "1.15.0".to_string(),
+            hashes: vec!["sha512:0000".to_string(), format!("sha256:{}", digest)],
+            markers: None,
+        });
+
+        assert_eq!(
+            verify_artifacts(&locked, &[path.clone()])?,
+            vec![VerifiedArtifact {
+                name: "six".to_string(),
+                version: "1.15.0".to_string(),
+                filename: "six-1.15.0-py2.py3-none-any.whl".to_string(),
+                hash,
+            }]
+        );
+
+        std::fs::write(&path, b"tampered content")?;
+        assert!(verify_artifacts(&locked, &[path]).is_err());
+
+        Ok(())
+    }
+}
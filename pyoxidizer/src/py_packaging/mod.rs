@@ -9,13 +9,23 @@ This module tree holds functionality that is centered around Python.
 */
 
 pub mod binary;
+pub mod conda;
 pub mod config;
+pub mod dependency_report;
 pub mod distribution;
+pub mod distribution_info;
 pub mod distutils;
 pub mod filtering;
+pub mod installed_distribution;
 pub mod libpython;
+pub mod lockfile;
+pub mod package_index;
 pub mod packaging_tool;
 pub mod pyembed;
 pub mod resource;
+pub mod resources_layout;
+pub mod shared_library;
+pub mod source_build;
 pub mod standalone_builder;
 pub mod standalone_distribution;
+pub mod target_support;
@@ -8,15 +8,27 @@ Interaction with Python packaging tools (pip, setuptools, etc).
 
 use {
     super::binary::LibpythonLinkMode,
+    super::conda::{conda_library_dirs, extract_conda_package, index_conda_meta},
+    super::dependency_report::{source_built_distributions, DependencyReport, DistributionKind},
     super::distribution::{download_distribution, PythonDistribution},
     super::distutils::read_built_extensions,
+    super::package_index::redact_credentials,
+    super::shared_library::{index_shared_libraries, vendor_extension_module_libraries},
     super::standalone_distribution::resolve_python_paths,
     crate::python_distributions::GET_PIP_PY_19,
     anyhow::{anyhow, Context, Result},
-    python_packaging::filesystem_scanning::find_python_resources,
-    python_packaging::resource::PythonResource,
+    python_packaging::filesystem_scanning::{
+        find_python_resources, find_python_resources_in_egg, parse_path_extension,
+        PathExtensionEntry,
+    },
+    python_packaging::package_metadata::{parse_record_paths, PythonPackageMetadata},
+    python_packaging::policy::{PathExtensionImportsAction, PythonPackagingPolicy},
+    python_packaging::resource::{
+        DataLocation, PythonEditableInstall, PythonEggFile, PythonResource, ResourceProvenance,
+    },
+    python_packaging::wheel::PythonWheel,
     slog::warn,
-    std::collections::HashMap,
+    std::collections::{HashMap, HashSet},
     std::hash::BuildHasher,
     std::io::{BufRead, BufReader},
     std::path::{Path, PathBuf},
@@ -169,39 +181,568 @@ pub fn bootstrap_packaging_tools(
     Ok(())
 }
 
+/// Whether a resource found by `find_resources()` should be returned.
+fn is_installed_resource(resource: &PythonResource) -> bool {
+    match resource {
+        PythonResource::ModuleSource(_)
+        | PythonResource::Resource(_)
+        | PythonResource::DistributionResource(_)
+        | PythonResource::ExtensionModuleDynamicLibrary(_)
+        | PythonResource::EntryPoint(_)
+        | PythonResource::DataFile(_) => true,
+        _ => false,
+    }
+}
+
+/// Obtain resources from a zipped .egg file.
+fn extract_egg_resources(
+    logger: &slog::Logger,
+    dist: &dyn PythonDistribution,
+    egg: &PythonEggFile,
+    egg_path: &Path,
+) -> Result<Vec<PythonResource>> {
+    warn!(logger, "extracting resources from {}", egg_path.display());
+
+    Ok(
+        find_python_resources_in_egg(egg, dist.cache_tag(), &dist.python_module_suffixes()?)
+            .with_context(|| format!("extracting resources from {}", egg_path.display()))?
+            .into_iter()
+            .filter(is_installed_resource)
+            .map(|mut r| {
+                r.set_provenance(Some(ResourceProvenance::LocalPath(egg_path.to_path_buf())));
+                r
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Resolve the name and version of a distribution from its `.egg-info` directory.
+fn egg_info_distribution(path: &Path) -> Result<Option<(String, String)>> {
+    let pkg_info_path = path.join("PKG-INFO");
+    if !pkg_info_path.exists() {
+        return Ok(None);
+    }
+
+    let metadata = PythonPackageMetadata::from_metadata(&std::fs::read(&pkg_info_path)?)
+        .with_context(|| format!("parsing {}", pkg_info_path.display()))?;
+
+    Ok(match (metadata.name(), metadata.version()) {
+        (Some(name), Some(version)) => Some((name.to_string(), version.to_string())),
+        _ => None,
+    })
+}
+
+/// Index files installed by package distributions directly under a directory.
+///
+/// Files listed in the `RECORD` file of a `.dist-info` directory or the
+/// `installed-files.txt` file of an `.egg-info` directory are mapped to the
+/// distribution that installed them.
+fn index_distribution_files(root: &Path) -> Result<HashMap<PathBuf, ResourceProvenance>> {
+    let mut res = HashMap::new();
+
+    for entry in std::fs::read_dir(root)? {
+        let path = entry?.path();
+
+        let dir_name = match path.file_name().and_then(|x| x.to_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+
+        if dir_name.ends_with(".dist-info") {
+            let record_path = path.join("RECORD");
+            if !record_path.exists() {
+                continue;
+            }
+
+            let mut parts = dir_name[0..dir_name.len() - ".dist-info".len()].splitn(2, '-');
+            let name = parts.next().unwrap();
+            let version = match parts.next() {
+                Some(version) => version,
+                None => continue,
+            };
+
+            let provenance = ResourceProvenance::PackageDistribution {
+                name: name.to_string(),
+                version: version.to_string(),
+            };
+
+            for installed in parse_record_paths(&std::fs::read(&record_path)?)
+                .with_context(|| format!("parsing {}", record_path.display()))?
+            {
+                res.insert(root.join(installed), provenance.clone());
+            }
+        } else if dir_name.ends_with(".egg-info") && path.is_dir() {
+            let files_path = path.join("installed-files.txt");
+            if !files_path.exists() {
+                continue;
+            }
+
+            let (name, version) = match egg_info_distribution(&path)? {
+                Some(v) => v,
+                None => continue,
+            };
+
+            let provenance = ResourceProvenance::PackageDistribution { name, version };
+
+            // Paths are relative to the .egg-info directory.
+            for line in std::fs::read_to_string(&files_path)?.lines() {
+                let installed = if line.starts_with("../") {
+                    root.join(&line[3..])
+                } else {
+                    path.join(line)
+                };
+
+                res.insert(installed, provenance.clone());
+            }
+        }
+    }
+
+    Ok(res)
+}
+
+/// Read the names of top-level packages and modules from a metadata directory.
+fn read_top_level(metadata_dir: &Path) -> Result<Vec<String>> {
+    let top_level_path = metadata_dir.join("top_level.txt");
+
+    Ok(if top_level_path.exists() {
+        std::fs::read_to_string(&top_level_path)?
+            .lines()
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty())
+            .collect()
+    } else {
+        vec![]
+    })
+}
+
+/// Resolve the directory holding the packages of a PEP 660 editable install.
+///
+/// setuptools writes the directory into a `__editable__.<name>-<version>.pth`
+/// file. If that file only installs an import hook, the directory is derived
+/// from the project directory, taking the `src` layout into account.
+fn pep660_editable_path(
+    root: &Path,
+    name: &str,
+    project: &Path,
+    top_level: &[String],
+) -> Result<PathBuf> {
+    let normalized = normalize_distribution_name(name);
+
+    for entry in std::fs::read_dir(root)? {
+        let pth_path = entry?.path();
+
+        let file_name = match pth_path.file_name().and_then(|x| x.to_str()) {
+            Some(name)
+                if name.starts_with("__editable__.")
+                    && name.ends_with(".pth")
+                    && name.len() > "__editable__..pth".len() =>
+            {
+                name
+            }
+            _ => continue,
+        };
+
+        let stem = &file_name["__editable__.".len()..file_name.len() - ".pth".len()];
+        if normalize_distribution_name(stem.splitn(2, '-').next().unwrap()) != normalized {
+            continue;
+        }
+
+        for line in std::fs::read_to_string(&pth_path)?.lines() {
+            let line = line.trim();
+            if line.is_empty()
+                || line.starts_with('#')
+                || line.starts_with("import ")
+                || line.starts_with("import\t")
+            {
+                continue;
+            }
+
+            let path = root.join(line);
+            if path.is_dir() {
+                return Ok(path);
+            }
+        }
+    }
+
+    let provides = |dir: &Path, name: &str| {
+        dir.join(name).is_dir() || dir.join(format!("{}.py", name)).exists()
+    };
+
+    let src = project.join("src");
+    if !top_level.is_empty()
+        && !top_level.iter().any(|name| provides(project, name))
+        && top_level.iter().any(|name| provides(&src, name))
+    {
+        Ok(src)
+    } else {
+        Ok(project.to_path_buf())
+    }
+}
+
+/// Resolve a PEP 660 editable install from a `.dist-info` directory.
+///
+/// Returns `None` if the distribution isn't installed in editable mode.
+fn pep660_editable_install(root: &Path, dist_info: &Path) -> Result<Option<PythonEditableInstall>> {
+    let direct_url_path = dist_info.join("direct_url.json");
+    if !direct_url_path.exists() {
+        return Ok(None);
+    }
+
+    let direct_url: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&direct_url_path)?)
+            .with_context(|| format!("parsing {}", direct_url_path.display()))?;
+
+    if direct_url["dir_info"]["editable"].as_bool() != Some(true) {
+        return Ok(None);
+    }
+
+    let project = match direct_url["url"]
+        .as_str()
+        .and_then(|url| url::Url::parse(url).ok())
+        .and_then(|url| url.to_file_path().ok())
+    {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+
+    let metadata_path = dist_info.join("METADATA");
+    let metadata = PythonPackageMetadata::from_metadata(&std::fs::read(&metadata_path)?)
+        .with_context(|| format!("parsing {}", metadata_path.display()))?;
+    let (name, version) = match (metadata.name(), metadata.version()) {
+        (Some(name), Some(version)) => (name.to_string(), version.to_string()),
+        _ => {
+            return Err(anyhow!(
+                "unable to resolve distribution from {}",
+                dist_info.display()
+            ))
+        }
+    };
+
+    let top_level = read_top_level(dist_info)?;
+    let path = pep660_editable_path(root, &name, &project, &top_level)?;
+
+    Ok(Some(PythonEditableInstall {
+        name,
+        version,
+        path,
+        top_level,
+    }))
+}
+
+/// Find distributions installed in editable mode into a directory.
+///
+/// Legacy editable installs (`setup.py develop`) are represented by
+/// `.egg-link` files, whose first line is the directory holding the
+/// distribution's packages. That directory also holds an `.egg-info`
+/// directory describing the distribution. PEP 660 editable installs are
+/// represented by `.dist-info` directories whose `direct_url.json` marks
+/// the install as editable.
+fn find_editable_installs(root: &Path) -> Result<Vec<PythonEditableInstall>> {
+    let mut res = Vec::new();
+
+    for entry in std::fs::read_dir(root)? {
+        let link_path = entry?.path();
+
+        if link_path.is_dir() && link_path.extension().and_then(|x| x.to_str()) == Some("dist-info")
+        {
+            if let Some(editable) = pep660_editable_install(root, &link_path)? {
+                res.push(editable);
+            }
+            continue;
+        }
+
+        if link_path.extension().and_then(|x| x.to_str()) != Some("egg-link") {
+            continue;
+        }
+
+        let data = std::fs::read_to_string(&link_path)?;
+        let path = match data.lines().next() {
+            Some(line) if !line.trim().is_empty() => root.join(line.trim()),
+            _ => return Err(anyhow!("{} is empty", link_path.display())),
+        };
+
+        let egg_info = std::fs::read_dir(&path)
+            .with_context(|| format!("reading {} (from {})", path.display(), link_path.display()))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .find(|p| p.is_dir() && p.extension().and_then(|x| x.to_str()) == Some("egg-info"))
+            .ok_or_else(|| {
+                anyhow!(
+                    "{} has no .egg-info directory; cannot determine packages of editable install {}",
+                    path.display(),
+                    link_path.display()
+                )
+            })?;
+
+        let (name, version) = egg_info_distribution(&egg_info)?
+            .ok_or_else(|| anyhow!("unable to resolve distribution from {}", egg_info.display()))?;
+
+        let top_level = read_top_level(&egg_info)?;
+
+        res.push(PythonEditableInstall {
+            name,
+            version,
+            path,
+            top_level,
+        });
+    }
+
+    // Directory iteration order is arbitrary.
+    res.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(res)
+}
+
+/// Obtain resources of a distribution installed in editable mode.
+///
+/// Only resources in the distribution's top-level packages are returned, as
+/// the directory holding them typically contains other files (tests,
+/// `setup.py`, etc).
+fn editable_install_resources(
+    dist: &dyn PythonDistribution,
+    editable: &PythonEditableInstall,
+) -> Result<Vec<PythonResource>> {
+    let provenance = ResourceProvenance::PackageDistribution {
+        name: editable.name.clone(),
+        version: editable.version.clone(),
+    };
+
+    let mut res = Vec::new();
+
+    for r in find_python_resources(
+        &editable.path,
+        dist.cache_tag(),
+        &dist.python_module_suffixes()?,
+    ) {
+        let mut r = r?;
+
+        if !is_installed_resource(&r) || !r.is_in_packages(&editable.top_level) {
+            continue;
+        }
+
+        r.set_provenance(Some(provenance.clone()));
+        res.push(r.to_memory()?);
+    }
+
+    Ok(res)
+}
+
+/// Record where a resource found on the filesystem came from.
+fn set_filesystem_provenance(
+    resource: &mut PythonResource,
+    distribution_files: &HashMap<PathBuf, ResourceProvenance>,
+) {
+    let provenance = match resource.data_location() {
+        Some(DataLocation::Path(path)) => distribution_files
+            .get(path)
+            .cloned()
+            .unwrap_or_else(|| ResourceProvenance::LocalPath(path.clone())),
+        _ => return,
+    };
+
+    resource.set_provenance(Some(provenance));
+}
+
 /// Find resources installed as part of a packaging operation.
+///
+/// .egg files are only processed if the packaging policy extracts them.
+/// Directories added by .pth files are only scanned if the packaging policy
+/// honors path extensions. .pth lines executing code are reported according
+/// to the policy's path extension imports action. Shared libraries found
+/// during scanning that extension modules depend on are vendored into those
+/// extension modules. Distributions installed in editable mode are scanned
+/// for their top-level packages, unless the packaging policy references
+/// editable installs by their source tree paths.
 pub fn find_resources(
     logger: &slog::Logger,
     dist: &dyn PythonDistribution,
+    policy: &PythonPackagingPolicy,
+    path: &Path,
+    state_dir: Option<PathBuf>,
+) -> Result<Vec<PythonResource>> {
+    scan_resources(logger, dist, policy, path, state_dir, &[], &HashMap::new())
+}
+
+/// Find resources installed as part of a packaging operation.
+///
+/// This is like `find_resources()` but also searches `library_dirs` for
+/// shared libraries to vendor. Provenance in `installed_files` takes
+/// precedence over provenance derived from `.dist-info` directories.
+fn scan_resources(
+    logger: &slog::Logger,
+    dist: &dyn PythonDistribution,
+    policy: &PythonPackagingPolicy,
     path: &Path,
     state_dir: Option<PathBuf>,
+    library_dirs: &[PathBuf],
+    installed_files: &HashMap<PathBuf, ResourceProvenance>,
 ) -> Result<Vec<PythonResource>> {
     let mut res = Vec::new();
 
-    for r in find_python_resources(&path, dist.cache_tag(), &dist.python_module_suffixes()?) {
-        let r = r?;
+    let mut roots = vec![path.to_path_buf()];
+    let mut scanned_roots: Vec<PathBuf> = vec![];
+    let mut seen_eggs = HashSet::new();
 
-        match r {
-            PythonResource::ModuleSource(_) => {
-                res.push(r.to_memory()?);
-            }
+    while !roots.is_empty() {
+        let root = roots.remove(0);
+        scanned_roots.push(root.canonicalize()?);
 
-            PythonResource::Resource(_) => {
-                res.push(r.to_memory()?);
+        // Editable installs also add their directory via a .pth file. So scan
+        // them before .pth files are processed.
+        for editable in find_editable_installs(&root)? {
+            let canonical = editable.path.canonicalize()?;
+            if scanned_roots.contains(&canonical) {
+                continue;
             }
 
-            PythonResource::DistributionResource(_) => {
-                res.push(r.to_memory()?);
+            if policy.get_editable_installs_as_source_paths() {
+                warn!(
+                    logger,
+                    "referencing editable install of {} at {}",
+                    editable.name,
+                    editable.path.display()
+                );
+                res.push(PythonResource::EditableInstall(editable));
+            } else {
+                warn!(
+                    logger,
+                    "scanning editable install of {} at {}",
+                    editable.name,
+                    editable.path.display()
+                );
+                res.extend(editable_install_resources(dist, &editable)?);
             }
-
-            PythonResource::ExtensionModuleDynamicLibrary(_) => {
-                res.push(r.to_memory()?);
+            scanned_roots.push(canonical);
+        }
+        let mut distribution_files = index_distribution_files(&root)?;
+        distribution_files.extend(installed_files.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        for r in find_python_resources(&root, dist.cache_tag(), &dist.python_module_suffixes()?) {
+            let mut r = r?;
+
+            match r {
+                PythonResource::EggFile(egg) => {
+                    if !policy.get_extract_egg_files() {
+                        continue;
+                    }
+
+                    if let DataLocation::Path(egg_path) = &egg.data {
+                        seen_eggs.insert(egg_path.canonicalize()?);
+                        res.extend(extract_egg_resources(logger, dist, &egg, egg_path)?);
+                    }
+                }
+
+                PythonResource::PathExtension(pth) => {
+                    if !policy.get_honor_path_extensions() {
+                        continue;
+                    }
+
+                    let pth_path = match &pth.data {
+                        DataLocation::Path(p) => p.clone(),
+                        DataLocation::Memory(_) => continue,
+                    };
+                    let base = pth_path
+                        .parent()
+                        .ok_or_else(|| anyhow!("unable to determine parent directory"))?;
+
+                    for entry in parse_path_extension(&pth, base)
+                        .with_context(|| format!("reading {}", pth_path.display()))?
+                    {
+                        let entry = match entry {
+                            PathExtensionEntry::Path(path) => path,
+                            PathExtensionEntry::Import(line) => {
+                                match policy.get_path_extension_imports_action() {
+                                    PathExtensionImportsAction::Ignore => {}
+                                    PathExtensionImportsAction::Warn => {
+                                        warn!(
+                                            logger,
+                                            "{} executes `{}` at startup; this won't happen in the built application",
+                                            pth_path.display(),
+                                            line
+                                        );
+                                    }
+                                    PathExtensionImportsAction::Error => {
+                                        return Err(anyhow!(
+                                            "{} executes `{}` at startup, which can't be replicated in the built application",
+                                            pth_path.display(),
+                                            line
+                                        ));
+                                    }
+                                }
+
+                                continue;
+                            }
+                        };
+
+                        let canonical = entry.canonicalize()?;
+
+                        // Paths under a scanned directory have been or will be
+                        // found by that scan.
+                        if scanned_roots.iter().any(|p| canonical.starts_with(p))
+                            || roots.contains(&entry)
+                            || seen_eggs.contains(&canonical)
+                        {
+                            continue;
+                        }
+
+                        if entry.is_dir() {
+                            warn!(
+                                logger,
+                                "scanning {} (added by {})",
+                                entry.display(),
+                                pth_path.display()
+                            );
+                            roots.push(entry);
+                        } else if entry.extension().and_then(|x| x.to_str()) == Some("egg") {
+                            if !policy.get_extract_egg_files() {
+                                return Err(anyhow!(
+                                    "{} adds .egg file {}; extracting .egg files must be enabled to package it",
+                                    pth_path.display(),
+                                    entry.display()
+                                ));
+                            }
+
+                            let egg = PythonEggFile {
+                                data: DataLocation::Path(entry.clone()),
+                            };
+                            seen_eggs.insert(canonical);
+                            res.extend(extract_egg_resources(logger, dist, &egg, &entry)?);
+                        } else {
+                            return Err(anyhow!(
+                                "{} adds {}, which is neither a directory nor an .egg file",
+                                pth_path.display(),
+                                entry.display()
+                            ));
+                        }
+                    }
+                }
+
+                _ => {
+                    if is_installed_resource(&r) {
+                        set_filesystem_provenance(&mut r, &distribution_files);
+                        res.push(r.to_memory()?);
+                    }
+                }
             }
-
-            _ => {}
         }
     }
 
+    // Extension modules may link against shared libraries installed
+    // alongside them. Those need to be distributed as well.
+    let mut library_roots = scanned_roots.clone();
+    library_roots.extend(library_dirs.iter().cloned());
+    let libraries = index_shared_libraries(&library_roots);
+    let mut res = res
+        .into_iter()
+        .map(|r| match r {
+            PythonResource::ExtensionModuleDynamicLibrary(em) => {
+                Ok(PythonResource::ExtensionModuleDynamicLibrary(
+                    vendor_extension_module_libraries(logger, &em, &libraries)?,
+                ))
+            }
+            r => Ok(r),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
     if let Some(p) = state_dir {
         for ext in read_built_extensions(&p)? {
             res.push(PythonResource::ExtensionModuleStaticallyLinked(ext));
@@ -212,14 +753,17 @@ pub fn find_resources(
 }
 
 /// Run `pip install` and return found resources.
+///
+/// A report of the installed distributions is returned as well.
 pub fn pip_install<S: BuildHasher>(
     logger: &slog::Logger,
     dist: &dyn PythonDistribution,
+    policy: &PythonPackagingPolicy,
     libpython_link_mode: LibpythonLinkMode,
     verbose: bool,
     install_args: &[String],
     extra_envs: &HashMap<String, String, S>,
-) -> Result<Vec<PythonResource>> {
+) -> Result<(Vec<PythonResource>, DependencyReport)> {
     let temp_dir = tempdir::TempDir::new("pyoxidizer-pip-install")?;
 
     dist.ensure_pip(logger)?;
@@ -252,10 +796,176 @@ pub fn pip_install<S: BuildHasher>(
 
     pip_args.extend(install_args.iter().cloned());
 
+    let output = run_pip(logger, dist.python_exe_path(), &pip_args, &env)?;
+
+    let state_dir = match env.get("PYOXIDIZER_DISTUTILS_STATE_DIR") {
+        Some(p) => Some(PathBuf::from(p)),
+        None => None,
+    };
+
+    let resources = find_resources(logger, dist, policy, &target_dir, state_dir)?;
+    let report = pip_install_report(&resources, &output)?;
+
+    Ok((resources, report))
+}
+
+/// Derive a dependency report for resources installed by `pip install`.
+fn pip_install_report(resources: &[PythonResource], output: &[String]) -> Result<DependencyReport> {
+    let built = source_built_distributions(output);
+
+    DependencyReport::from_resources(resources, &|name| {
+        if built.contains(name) {
+            DistributionKind::Sdist
+        } else {
+            DistributionKind::Wheel
+        }
+    })
+}
+
+/// Run `pip install` for a distribution whose Python can't run on this machine.
+///
+/// pip is run by `host_dist` and told to install packages for the platform
+/// of `dist`, which is described by its PEP 425 `platform_tag`. Since
+/// nothing can be compiled for `dist`, only binary wheels are installed.
+#[allow(clippy::too_many_arguments)]
+pub fn pip_install_foreign<S: BuildHasher>(
+    logger: &slog::Logger,
+    host_dist: &dyn PythonDistribution,
+    dist: &dyn PythonDistribution,
+    platform_tag: &str,
+    policy: &PythonPackagingPolicy,
+    verbose: bool,
+    install_args: &[String],
+    extra_envs: &HashMap<String, String, S>,
+) -> Result<(Vec<PythonResource>, DependencyReport)> {
+    let temp_dir = tempdir::TempDir::new("pyoxidizer-pip-install")?;
+
+    host_dist.ensure_pip(logger)?;
+
+    let target_dir = temp_dir.path().join("install");
+
+    warn!(
+        logger,
+        "pip installing binary wheels for {} to {}",
+        platform_tag,
+        target_dir.display()
+    );
+
+    let mut pip_args: Vec<String> = vec![
+        "-m".to_string(),
+        "pip".to_string(),
+        "--disable-pip-version-check".to_string(),
+    ];
+
+    if verbose {
+        pip_args.push("--verbose".to_string());
+    }
+
+    pip_args.extend(vec![
+        "install".to_string(),
+        "--target".to_string(),
+        format!("{}", target_dir.display()),
+    ]);
+    pip_args.extend(foreign_platform_args(dist, platform_tag));
+    pip_args.extend(install_args.iter().cloned());
+
+    let output = run_pip(logger, host_dist.python_exe_path(), &pip_args, extra_envs)?;
+
+    let resources = find_resources(logger, dist, policy, &target_dir, None)?;
+    let report = pip_install_report(&resources, &output)?;
+
+    Ok((resources, report))
+}
+
+/// Obtain pip arguments selecting binary wheels for another platform.
+fn foreign_platform_args(dist: &dyn PythonDistribution, platform_tag: &str) -> Vec<String> {
+    let mut args = vec![
+        "--platform".to_string(),
+        platform_tag.replace("-", "_").replace(".", "_"),
+        "--python-version".to_string(),
+        dist.python_major_minor_version(),
+        "--implementation".to_string(),
+        "cp".to_string(),
+        "--only-binary=:all:".to_string(),
+    ];
+
+    // pip wouldn't otherwise know to select wheels for the free-threaded ABI.
+    if let Some(abi) = dist.python_abi_tag() {
+        if abi.ends_with('t') {
+            args.extend(vec!["--abi".to_string(), abi.to_string()]);
+        }
+    }
+
+    args
+}
+
+/// Run `pip download` and return paths of downloaded files.
+///
+/// pip is run by `host_dist`. If `foreign_platform_tag` is defined, binary
+/// wheels for that platform and the Python version of `dist` are downloaded.
+/// Otherwise `host_dist` should be `dist`.
+#[allow(clippy::too_many_arguments)]
+pub fn pip_download<S: BuildHasher>(
+    logger: &slog::Logger,
+    host_dist: &dyn PythonDistribution,
+    dist: &dyn PythonDistribution,
+    foreign_platform_tag: Option<&str>,
+    verbose: bool,
+    download_args: &[String],
+    dest_dir: &Path,
+    extra_envs: &HashMap<String, String, S>,
+) -> Result<Vec<PathBuf>> {
+    host_dist.ensure_pip(logger)?;
+
+    warn!(logger, "pip downloading to {}", dest_dir.display());
+
+    let mut pip_args: Vec<String> = vec![
+        "-m".to_string(),
+        "pip".to_string(),
+        "--disable-pip-version-check".to_string(),
+    ];
+
+    if verbose {
+        pip_args.push("--verbose".to_string());
+    }
+
+    pip_args.extend(vec![
+        "download".to_string(),
+        "--dest".to_string(),
+        format!("{}", dest_dir.display()),
+    ]);
+
+    if let Some(platform_tag) = foreign_platform_tag {
+        pip_args.extend(foreign_platform_args(dist, platform_tag));
+    }
+
+    pip_args.extend(download_args.iter().cloned());
+
+    run_pip(logger, host_dist.python_exe_path(), &pip_args, extra_envs)?;
+
+    let mut paths = std::fs::read_dir(dest_dir)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?;
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// Run pip with a Python interpreter, logging its output.
+///
+/// Returns lines of output, with credentials redacted.
+fn run_pip<S: BuildHasher>(
+    logger: &slog::Logger,
+    python_exe: &Path,
+    pip_args: &[String],
+    envs: &HashMap<String, String, S>,
+) -> Result<Vec<String>> {
+    let mut output = vec![];
+
     // TODO send stderr to stdout
-    let mut cmd = std::process::Command::new(&dist.python_exe_path())
-        .args(&pip_args)
-        .envs(&env)
+    let mut cmd = std::process::Command::new(python_exe)
+        .args(pip_args)
+        .envs(envs)
         .stdout(std::process::Stdio::piped())
         .spawn()?;
     {
@@ -266,7 +976,9 @@ pub fn pip_install<S: BuildHasher>(
         let reader = BufReader::new(stdout);
 
         for line in reader.lines() {
-            warn!(logger, "{}", line?);
+            let line = redact_credentials(&line?);
+            warn!(logger, "{}", line);
+            output.push(line);
         }
     }
 
@@ -275,29 +987,433 @@ pub fn pip_install<S: BuildHasher>(
         return Err(anyhow!("error running pip"));
     }
 
-    let state_dir = match env.get("PYOXIDIZER_DISTUTILS_STATE_DIR") {
-        Some(p) => Some(PathBuf::from(p)),
-        None => None,
+    Ok(output)
+}
+
+/// Python script writing the `pyproject.toml` of a source distribution archive to stdout.
+const SDIST_PYPROJECT_SCRIPT: &str = indoc::indoc!(
+    "import sys, tarfile, zipfile
+    path = sys.argv[1]
+    data = b''
+    if zipfile.is_zipfile(path):
+        with zipfile.ZipFile(path) as zf:
+            for name in zf.namelist():
+                if name.count('/') == 1 and name.endswith('/pyproject.toml'):
+                    data = zf.read(name)
+    else:
+        with tarfile.open(path) as tf:
+            for member in tf.getmembers():
+                if member.name.count('/') == 1 and member.name.endswith('/pyproject.toml'):
+                    data = tf.extractfile(member).read()
+    sys.stdout.buffer.write(data)
+    "
+);
+
+/// Parse the build requirements declared by a `pyproject.toml`.
+///
+/// As described by PEP 518, packages not declaring build requirements
+/// require `setuptools` and `wheel`.
+fn parse_build_requirements(pyproject: Option<&str>) -> Result<Vec<String>> {
+    let defaults = vec!["setuptools>=40.8.0".to_string(), "wheel".to_string()];
+
+    let pyproject = match pyproject {
+        Some(data) if !data.trim().is_empty() => data,
+        _ => return Ok(defaults),
+    };
+
+    let value: toml::Value = toml::from_str(pyproject).context("parsing pyproject.toml")?;
+
+    match value.get("build-system").and_then(|b| b.get("requires")) {
+        Some(toml::Value::Array(requires)) => requires
+            .iter()
+            .map(|r| {
+                r.as_str()
+                    .map(|r| r.to_string())
+                    .ok_or_else(|| anyhow!("build-system.requires must be a list of strings"))
+            })
+            .collect(),
+        Some(_) => Err(anyhow!("build-system.requires must be a list of strings")),
+        None => Ok(defaults),
+    }
+}
+
+/// Obtain the build requirements a package declares in its `pyproject.toml`.
+///
+/// `package_path` can be a source distribution archive, which is read by
+/// the distribution's Python interpreter, or a directory.
+fn declared_build_requirements(
+    dist: &dyn PythonDistribution,
+    package_path: &Path,
+) -> Result<Vec<String>> {
+    let pyproject = if package_path.is_dir() {
+        let path = package_path.join("pyproject.toml");
+
+        if path.exists() {
+            Some(std::fs::read_to_string(&path)?)
+        } else {
+            None
+        }
+    } else {
+        let output = std::process::Command::new(dist.python_exe_path())
+            .arg("-c")
+            .arg(SDIST_PYPROJECT_SCRIPT)
+            .arg(package_path)
+            .output()?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "error reading pyproject.toml from {}: {}",
+                package_path.display(),
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        Some(String::from_utf8(output.stdout)?)
     };
 
-    find_resources(logger, dist, &target_dir, state_dir)
+    parse_build_requirements(pyproject.as_deref())
+}
+
+/// Obtain the normalized name of the distribution a requirement refers to.
+pub fn requirement_name(requirement: &str) -> String {
+    normalize_distribution_name(
+        &requirement
+            .trim()
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_' || *c == '.')
+            .collect::<String>(),
+    )
+}
+
+/// Build a package with its PEP 517 build backend and return found resources.
+///
+/// `package_path` can be a source distribution archive or a directory
+/// containing a `pyproject.toml` or `setup.py`. `pip wheel` is used as the
+/// build frontend, so build requirements declared by the package (e.g.
+/// Cython) are installed into an isolated build environment.
+///
+/// The build runs with the distribution's Python interpreter, so extension
+/// modules are compiled against the distribution's headers using the
+/// compiler settings it was built with. If libpython is linked statically,
+/// object files of compiled extension modules are captured so the extension
+/// modules can be linked into the produced binary.
+///
+/// Capturing object files requires a modified distutils, which pip's
+/// isolated build environment would hide. So when it is in use, build
+/// isolation is disabled. Build requirements declared in the package's
+/// `pyproject.toml` and `build_requirements` are installed into a
+/// separate directory made available to the build instead. `setuptools`
+/// and `wheel` are always available.
+pub fn pep517_build<S: BuildHasher>(
+    logger: &slog::Logger,
+    dist: &dyn PythonDistribution,
+    libpython_link_mode: LibpythonLinkMode,
+    package_path: &Path,
+    verbose: bool,
+    build_requirements: &[String],
+    extra_envs: &HashMap<String, String, S>,
+) -> Result<Vec<PythonResource>> {
+    let temp_dir = tempdir::TempDir::new("pyoxidizer-pep517-build")?;
+
+    dist.ensure_pip(logger)?;
+
+    let requirements_dir = temp_dir.path().join("build-requirements");
+
+    let mut build_requirements = build_requirements.to_vec();
+
+    // pip would install declared build requirements into the isolated build
+    // environment. Without one, we install them ourselves.
+    if libpython_link_mode == LibpythonLinkMode::Static {
+        for requirement in declared_build_requirements(dist, package_path)? {
+            let name = requirement_name(&requirement);
+
+            if name != "setuptools"
+                && name != "wheel"
+                && !build_requirements
+                    .iter()
+                    .any(|r| requirement_name(r) == name)
+            {
+                build_requirements.push(requirement);
+            }
+        }
+    }
+
+    if libpython_link_mode == LibpythonLinkMode::Static && !build_requirements.is_empty() {
+        warn!(
+            logger,
+            "installing build requirements to {}",
+            requirements_dir.display()
+        );
+
+        let mut args = vec![
+            "-m".to_string(),
+            "pip".to_string(),
+            "--disable-pip-version-check".to_string(),
+            "install".to_string(),
+            "--target".to_string(),
+            format!("{}", requirements_dir.display()),
+        ];
+        args.extend(build_requirements.iter().cloned());
+
+        let output = std::process::Command::new(&dist.python_exe_path())
+            .args(&args)
+            .envs(extra_envs)
+            .output()?;
+        if !output.status.success() {
+            warn!(
+                logger,
+                "{}",
+                redact_credentials(&String::from_utf8_lossy(&output.stdout))
+            );
+            return Err(anyhow!("error installing build requirements"));
+        }
+    }
+
+    let mut env = dist.resolve_distutils(
+        logger,
+        libpython_link_mode,
+        temp_dir.path(),
+        &[&requirements_dir],
+    )?;
+
+    let build_isolation = !env.contains_key("PYOXIDIZER_DISTUTILS_STATE_DIR");
+
+    if !build_isolation {
+        // Newer setuptools releases default to a bundled copy of distutils.
+        env.insert("SETUPTOOLS_USE_DISTUTILS".to_string(), "stdlib".to_string());
+    }
+
+    for (key, value) in extra_envs.iter() {
+        env.insert(key.clone(), value.clone());
+    }
+
+    let wheel_dir = temp_dir.path().join("wheels");
+
+    warn!(
+        logger,
+        "building {} with its PEP 517 build backend",
+        package_path.display()
+    );
+
+    let mut pip_args: Vec<String> = vec![
+        "-m".to_string(),
+        "pip".to_string(),
+        "--disable-pip-version-check".to_string(),
+    ];
+
+    if verbose {
+        pip_args.push("--verbose".to_string());
+    }
+
+    // Wheels built from a local path may be cached, in which case the build
+    // backend wouldn't run and object files wouldn't be captured.
+    pip_args.extend(vec![
+        "wheel".to_string(),
+        "--use-pep517".to_string(),
+        "--no-deps".to_string(),
+        "--no-cache-dir".to_string(),
+        "--wheel-dir".to_string(),
+        format!("{}", wheel_dir.display()),
+    ]);
+
+    if !build_isolation {
+        pip_args.push("--no-build-isolation".to_string());
+    }
+
+    pip_args.push(format!("{}", package_path.display()));
+
+    // TODO send stderr to stdout
+    let mut cmd = std::process::Command::new(&dist.python_exe_path())
+        .args(&pip_args)
+        .envs(&env)
+        .stdout(std::process::Stdio::piped())
+        .spawn()?;
+    {
+        let stdout = cmd
+            .stdout
+            .as_mut()
+            .ok_or_else(|| anyhow!("unable to get stdout"))?;
+        let reader = BufReader::new(stdout);
+
+        for line in reader.lines() {
+            warn!(logger, "{}", redact_credentials(&line?));
+        }
+    }
+
+    let status = cmd.wait()?;
+    if !status.success() {
+        return Err(anyhow!("error running pip wheel"));
+    }
+
+    let suffixes = dist.python_module_suffixes()?;
+    let mut res = Vec::new();
+
+    for entry in std::fs::read_dir(&wheel_dir)? {
+        let path = entry?.path();
+
+        if path.extension().and_then(|ext| ext.to_str()) != Some("whl") {
+            continue;
+        }
+
+        warn!(logger, "reading resources from {}", path.display());
+
+        let wheel = PythonWheel::from_path(&path)?;
+        res.extend(
+            wheel
+                .python_resources(dist.cache_tag(), &suffixes)
+                .with_context(|| format!("reading resources from {}", path.display()))?
+                .into_iter()
+                .filter(is_installed_resource),
+        );
+    }
+
+    if let Some(p) = env.get("PYOXIDIZER_DISTUTILS_STATE_DIR") {
+        for ext in read_built_extensions(Path::new(p))? {
+            res.push(PythonResource::ExtensionModuleStaticallyLinked(ext));
+        }
+    }
+
+    dist.filter_compatible_python_resources(logger, &res)
+}
+
+/// Normalize a distribution name for comparisons, as described by PEP 503.
+pub fn normalize_distribution_name(name: &str) -> String {
+    let mut res = String::with_capacity(name.len());
+
+    for c in name.to_lowercase().chars() {
+        if c == '-' || c == '_' || c == '.' {
+            if !res.ends_with('-') {
+                res.push('-');
+            }
+        } else {
+            res.push(c);
+        }
+    }
+
+    res
+}
+
+/// Filter resources by the distribution that installed them.
+///
+/// If `include` is defined, only resources installed by a listed
+/// distribution are retained. Resources installed by a distribution listed
+/// in `exclude` are removed. The installing distribution is derived from
+/// resource provenance.
+pub fn filter_resources_by_distribution(
+    resources: Vec<PythonResource>,
+    include: Option<&[String]>,
+    exclude: &[String],
+) -> Vec<PythonResource> {
+    let include = include.map(|names| {
+        names
+            .iter()
+            .map(|n| normalize_distribution_name(n))
+            .collect::<HashSet<_>>()
+    });
+    let exclude = exclude
+        .iter()
+        .map(|n| normalize_distribution_name(n))
+        .collect::<HashSet<_>>();
+
+    resources
+        .into_iter()
+        .filter(|r| {
+            let name = match r.provenance() {
+                Some(ResourceProvenance::PackageDistribution { name, .. })
+                | Some(ResourceProvenance::CondaPackage { name, .. }) => {
+                    Some(normalize_distribution_name(name))
+                }
+                _ => None,
+            };
+
+            match (&include, &name) {
+                (Some(include), Some(name)) if !include.contains(name) => return false,
+                (Some(_), None) => return false,
+                _ => {}
+            }
+
+            match &name {
+                Some(name) => !exclude.contains(name),
+                None => true,
+            }
+        })
+        .collect()
 }
 
 /// Discover Python resources from a populated virtualenv directory.
 pub fn read_virtualenv(
     logger: &slog::Logger,
     dist: &dyn PythonDistribution,
+    policy: &PythonPackagingPolicy,
+    path: &Path,
+) -> Result<Vec<PythonResource>> {
+    let python_paths = resolve_python_paths(path, &dist.python_major_minor_version());
+
+    find_resources(logger, dist, policy, &python_paths.site_packages, None)
+}
+
+/// Discover Python resources in a conda environment.
+///
+/// Shared libraries installed into the environment by other conda packages
+/// are vendored into extension modules that depend on them.
+pub fn read_conda_env(
+    logger: &slog::Logger,
+    dist: &dyn PythonDistribution,
+    policy: &PythonPackagingPolicy,
     path: &Path,
 ) -> Result<Vec<PythonResource>> {
     let python_paths = resolve_python_paths(path, &dist.python_major_minor_version());
 
-    find_resources(logger, dist, &python_paths.site_packages, None)
+    if !python_paths.site_packages.is_dir() {
+        return Err(anyhow!(
+            "{} does not exist; conda packages must target Python {}",
+            python_paths.site_packages.display(),
+            dist.python_major_minor_version()
+        ));
+    }
+
+    warn!(
+        logger,
+        "scanning {} for resources",
+        python_paths.site_packages.display()
+    );
+
+    scan_resources(
+        logger,
+        dist,
+        policy,
+        &python_paths.site_packages,
+        None,
+        &conda_library_dirs(path),
+        &index_conda_meta(path)?,
+    )
+}
+
+/// Extract conda packages into a new environment and discover Python resources in it.
+///
+/// Packages providing shared libraries that extension modules depend on
+/// should be included so those libraries can be vendored.
+pub fn read_conda_packages(
+    logger: &slog::Logger,
+    dist: &dyn PythonDistribution,
+    policy: &PythonPackagingPolicy,
+    paths: &[PathBuf],
+) -> Result<Vec<PythonResource>> {
+    let temp_dir = tempdir::TempDir::new("pyoxidizer-conda-env")?;
+
+    for path in paths {
+        warn!(logger, "extracting conda package {}", path.display());
+        extract_conda_package(path, temp_dir.path())
+            .with_context(|| format!("extracting {}", path.display()))?;
+    }
+
+    read_conda_env(logger, dist, policy, temp_dir.path())
 }
 
 /// Run `setup.py install` against a path and return found resources.
 pub fn setup_py_install<S: BuildHasher>(
     logger: &slog::Logger,
     dist: &dyn PythonDistribution,
+    policy: &PythonPackagingPolicy,
     libpython_link_mode: LibpythonLinkMode,
     package_path: &Path,
     verbose: bool,
@@ -381,21 +1497,210 @@ pub fn setup_py_install<S: BuildHasher>(
         "scanning {} for resources",
         python_paths.site_packages.display()
     );
-    find_resources(logger, dist, &python_paths.site_packages, state_dir)
+    find_resources(logger, dist, policy, &python_paths.site_packages, state_dir)
 }
 
 #[cfg(test)]
 mod tests {
-    use {super::*, crate::testutil::*, std::ops::Deref};
+    use {
+        super::*, crate::testutil::*, python_packaging::resource::PythonModuleSource,
+        std::ops::Deref,
+    };
+
+    #[test]
+    fn test_parse_build_requirements() -> Result<()> {
+        let defaults = vec!["setuptools>=40.8.0".to_string(), "wheel".to_string()];
+
+        assert_eq!(parse_build_requirements(None)?, defaults);
+        assert_eq!(parse_build_requirements(Some(""))?, defaults);
+        assert_eq!(
+            parse_build_requirements(Some("[tool.black]\nline-length = 88\n"))?,
+            defaults
+        );
+        assert_eq!(
+            parse_build_requirements(Some(
+                "[build-system]\nrequires = [\"setuptools\", \"Cython>=0.29\"]\n"
+            ))?,
+            vec!["setuptools".to_string(), "Cython>=0.29".to_string()]
+        );
+        assert!(parse_build_requirements(Some("[build-system]\nrequires = \"Cython\"\n")).is_err());
+
+        assert_eq!(requirement_name("Cython>=0.29"), "cython");
+        assert_eq!(
+            requirement_name("setuptools_scm[toml] ; python_version >= '3'"),
+            "setuptools-scm"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_editable_installs() -> Result<()> {
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+        let site_packages = td.path().join("site-packages");
+        let project = td.path().join("project");
+        let egg_info = project.join("foo_bar.egg-info");
+        std::fs::create_dir_all(&site_packages)?;
+        std::fs::create_dir_all(&egg_info)?;
+
+        std::fs::write(
+            site_packages.join("foo-bar.egg-link"),
+            format!("{}\n.", project.display()),
+        )?;
+        std::fs::write(
+            egg_info.join("PKG-INFO"),
+            "Metadata-Version: 1.1\nName: foo-bar\nVersion: 1.0\n",
+        )?;
+        std::fs::write(egg_info.join("top_level.txt"), "foo\n")?;
+
+        let editables = find_editable_installs(&site_packages)?;
+        assert_eq!(editables.len(), 1);
+        assert_eq!(editables[0].path, project);
+        assert_eq!(editables[0].name, "foo-bar");
+        assert_eq!(editables[0].version, "1.0");
+        assert_eq!(editables[0].top_level, vec!["foo".to_string()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_pep660_editable_installs() -> Result<()> {
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+        let site_packages = td.path().join("site-packages");
+        let project = td.path().join("project");
+        let dist_info = site_packages.join("foo_bar-1.0.dist-info");
+        std::fs::create_dir_all(&dist_info)?;
+        std::fs::create_dir_all(project.join("src").join("foo"))?;
+
+        std::fs::write(
+            dist_info.join("METADATA"),
+            "Metadata-Version: 2.1\nName: foo-bar\nVersion: 1.0\n",
+        )?;
+        std::fs::write(dist_info.join("top_level.txt"), "foo\n")?;
+        std::fs::write(
+            dist_info.join("direct_url.json"),
+            serde_json::json!({
+                "url": url::Url::from_file_path(&project).unwrap().to_string(),
+                "dir_info": {"editable": true},
+            })
+            .to_string(),
+        )?;
+
+        // The pth file only installs an import hook. So the src layout is
+        // detected from the project directory.
+        std::fs::write(
+            site_packages.join("__editable__.foo_bar-1.0.pth"),
+            "import __editable___foo_bar_1_0_finder; __editable___foo_bar_1_0_finder.install()\n",
+        )?;
+
+        let editables = find_editable_installs(&site_packages)?;
+        assert_eq!(editables.len(), 1);
+        assert_eq!(editables[0].path, project.join("src"));
+        assert_eq!(editables[0].name, "foo-bar");
+        assert_eq!(editables[0].version, "1.0");
+        assert_eq!(editables[0].top_level, vec!["foo".to_string()]);
+
+        let lib = td.path().join("lib");
+        std::fs::create_dir_all(&lib)?;
+        std::fs::write(
+            site_packages.join("__editable__.foo_bar-1.0.pth"),
+            format!("{}\n", lib.display()),
+        )?;
+        assert_eq!(find_editable_installs(&site_packages)?[0].path, lib);
+
+        // Distributions that aren't installed in editable mode are ignored.
+        std::fs::write(
+            dist_info.join("direct_url.json"),
+            r#"{"url": "https://example.com/foo_bar-1.0.tar.gz", "archive_info": {}}"#,
+        )?;
+        assert!(find_editable_installs(&site_packages)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_egg_info_files() -> Result<()> {
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+        let egg_info = td.path().join("foo-1.0-py3.7.egg-info");
+        std::fs::create_dir_all(&egg_info)?;
+
+        std::fs::write(
+            egg_info.join("PKG-INFO"),
+            "Metadata-Version: 1.1\nName: foo\nVersion: 1.0\n",
+        )?;
+        std::fs::write(
+            egg_info.join("installed-files.txt"),
+            "../foo/__init__.py\nPKG-INFO\n",
+        )?;
+
+        let index = index_distribution_files(td.path())?;
+        let provenance = ResourceProvenance::PackageDistribution {
+            name: "foo".to_string(),
+            version: "1.0".to_string(),
+        };
+        assert_eq!(
+            index.get(&td.path().join("foo/__init__.py")),
+            Some(&provenance)
+        );
+        assert_eq!(index.get(&egg_info.join("PKG-INFO")), Some(&provenance));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_resources_by_distribution() {
+        let resource = |name: &str, distribution: Option<&str>| {
+            PythonResource::ModuleSource(PythonModuleSource {
+                name: name.to_string(),
+                source: DataLocation::Memory(vec![]),
+                is_package: false,
+                cache_tag: "cpython-37".to_string(),
+                is_stdlib: false,
+                is_test: false,
+                provenance: distribution.map(|d| ResourceProvenance::PackageDistribution {
+                    name: d.to_string(),
+                    version: "1.0".to_string(),
+                }),
+            })
+        };
+
+        let resources = vec![
+            resource("foo", Some("Foo_Bar")),
+            resource("baz", Some("baz")),
+            resource("local", None),
+        ];
+
+        let names = |resources: Vec<PythonResource>| {
+            resources.iter().map(|r| r.full_name()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(filter_resources_by_distribution(
+                resources.clone(),
+                Some(&["foo-bar".to_string()]),
+                &[]
+            )),
+            vec!["foo".to_string()]
+        );
+        assert_eq!(
+            names(filter_resources_by_distribution(
+                resources,
+                None,
+                &["FOO.bar".to_string()]
+            )),
+            vec!["baz".to_string(), "local".to_string()]
+        );
+    }
 
     #[test]
     fn test_install_black() -> Result<()> {
         let logger = get_logger()?;
         let distribution = get_default_distribution()?;
 
-        let resources: Vec<PythonResource> = pip_install(
+        let (resources, report) = pip_install(
             &logger,
             distribution.deref().as_ref(),
+            &distribution.create_packaging_policy()?,
             LibpythonLinkMode::Dynamic,
             false,
             &["black==19.10b0".to_string()],
@@ -405,6 +1710,20 @@ mod tests {
         assert!(resources.iter().any(|r| r.full_name() == "appdirs"));
         assert!(resources.iter().any(|r| r.full_name() == "black"));
 
+        let black = resources.iter().find(|r| r.full_name() == "black").unwrap();
+        assert_eq!(
+            black.provenance(),
+            Some(&ResourceProvenance::PackageDistribution {
+                name: "black".to_string(),
+                version: "19.10b0".to_string(),
+            })
+        );
+
+        assert_eq!(report.roots(), vec!["black".to_string()]);
+        let appdirs = &report.distributions["appdirs"];
+        assert_eq!(appdirs.required_by, vec!["black".to_string()]);
+        assert!(appdirs.size > 0);
+
         Ok(())
     }
 
@@ -415,9 +1734,10 @@ mod tests {
 
         let distribution = get_default_dynamic_distribution()?;
 
-        let resources: Vec<PythonResource> = pip_install(
+        let (resources, _) = pip_install(
             &logger,
             distribution.deref().as_ref(),
+            &distribution.create_packaging_policy()?,
             LibpythonLinkMode::Dynamic,
             false,
             &["cffi==1.14.0".to_string()],
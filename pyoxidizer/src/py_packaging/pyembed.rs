@@ -12,7 +12,37 @@ use std::fs::File;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
-use super::config::{EmbeddedPythonConfig, RawAllocator, RunMode, TerminfoResolution};
+use super::config::{
+    CertificateResolution, EmbeddedPythonConfig, RawAllocator, RunMode, ServerInterface,
+    StdioRedirect, TerminfoResolution,
+};
+
+/// Render an optional string as Rust source for an `Option<String>`.
+fn optional_string_source(value: &Option<String>) -> String {
+    match value {
+        Some(value) => format!("Some(r###\"{}\"###.to_string())", value),
+        None => "None".to_string(),
+    }
+}
+
+/// Render an optional enum as Rust source for an `Option` of the same named pyembed enum.
+fn optional_enum_source<T: std::fmt::Debug>(enum_name: &str, value: &Option<T>) -> String {
+    match value {
+        Some(value) => format!("Some(pyembed::{}::{:?})", enum_name, value),
+        None => "None".to_string(),
+    }
+}
+
+/// Render a list of strings as Rust source for a `Vec<String>`.
+fn string_vec_source(values: &[String]) -> String {
+    format!(
+        "vec![{}]",
+        values
+            .iter()
+            .map(|v| format!("r###\"{}\"###.to_string()", v))
+            .join(", ")
+    )
+}
 
 /// Obtain the Rust source code to construct a PythonConfig instance.
 pub fn derive_python_config(
@@ -42,15 +72,62 @@ pub fn derive_python_config(
          quiet: {},\n    \
          use_hash_seed: {},\n    \
          verbose: {},\n    \
+         gil_enabled: {:?},\n    \
          packed_resources: include_bytes!(r#\"{}\"#),\n    \
+         packed_resources_files: {},\n    \
+         packed_resources_public_key: {},\n    \
+         watch_packed_resources_files: {},\n    \
+         in_memory_module_file: pyembed::InMemoryModuleFile::{:?},\n    \
+         in_memory_package_path: {},\n    \
+         lazy_modules: {},\n    \
          extra_extension_modules: vec![],\n    \
          argvb: false,\n    \
+         sys_argv_skip: {},\n    \
+         sys_argv_insert: {},\n    \
+         environment_allowlist: {},\n    \
          sys_frozen: {},\n    \
          sys_meipass: {},\n    \
          raw_allocator: {},\n    \
          terminfo_resolution: {},\n    \
+         ssl_certificate_resolution: {},\n    \
+         tcl_library: {},\n    \
+         repl_line_editing: {},\n    \
+         repl_history_file: {},\n    \
          write_modules_directory_env: {},\n    \
-         run: {},\n\
+         startup_profile_env: {},\n    \
+         packed_resources_key_env: {},\n    \
+         stdio_redirect: {},\n    \
+         site_packages_overlay: {},\n    \
+         zip_archives: {},\n    \
+         allocate_console_args: {},\n    \
+         run: {},\n    \
+         multiprocessing_auto_dispatch: {},\n    \
+         multiprocessing_start_method: {},\n    \
+         allocator: {},\n    \
+         configure_locale: {:?},\n    \
+         coerce_c_locale: {},\n    \
+         coerce_c_locale_warn: {:?},\n    \
+         development_mode: {:?},\n    \
+         parse_argv: {:?},\n    \
+         utf8_mode: {:?},\n    \
+         check_hash_pycs_mode: {},\n    \
+         configure_c_stdio: {:?},\n    \
+         dump_refs: {:?},\n    \
+         fault_handler: {:?},\n    \
+         filesystem_encoding: {},\n    \
+         filesystem_errors: {},\n    \
+         hash_seed: {:?},\n    \
+         import_time: {:?},\n    \
+         install_signal_handlers: {:?},\n    \
+         malloc_stats: {:?},\n    \
+         pathconfig_warnings: {:?},\n    \
+         pycache_prefix: {},\n    \
+         show_alloc_count: {:?},\n    \
+         show_ref_count: {:?},\n    \
+         skip_first_source_line: {:?},\n    \
+         tracemalloc: {:?},\n    \
+         warn_options: {},\n    \
+         x_options: {},\n\
          }}",
         match &embedded.stdio_encoding_name {
             Some(value) => format_args!("Some(\"{}\")", value).to_string(),
@@ -83,25 +160,78 @@ pub fn derive_python_config(
         embedded.quiet,
         embedded.use_hash_seed,
         embedded.verbose,
+        embedded.gil_enabled,
         embedded_resources_path.display(),
+        string_vec_source(&embedded.packed_resources_files),
+        match &embedded.packed_resources_public_key {
+            Some(value) => format!("Some(&{:?}[..])", value),
+            None => "None".to_owned(),
+        },
+        embedded.watch_packed_resources_files,
+        embedded.in_memory_module_file,
+        embedded.in_memory_package_path,
+        string_vec_source(&embedded.lazy_modules),
+        embedded.sys_argv_skip,
+        string_vec_source(&embedded.sys_argv_insert),
+        match embedded.environment_allowlist {
+            Some(ref names) => format!("Some({})", string_vec_source(names)),
+            None => "None".to_string(),
+        },
         embedded.sys_frozen,
         embedded.sys_meipass,
-        match embedded.raw_allocator {
-            RawAllocator::Jemalloc => "pyembed::PythonRawAllocator::jemalloc()",
-            RawAllocator::Rust => "pyembed::PythonRawAllocator::rust()",
-            RawAllocator::System => "pyembed::PythonRawAllocator::system()",
-        },
+        format!(
+            "pyembed::PythonRawAllocator {{ mem_domain: {}, ..pyembed::PythonRawAllocator::{}() }}",
+            embedded.raw_allocator_mem_domain,
+            match embedded.raw_allocator {
+                RawAllocator::Jemalloc => "jemalloc",
+                RawAllocator::Mimalloc => "mimalloc",
+                RawAllocator::Rust => "rust",
+                RawAllocator::Snmalloc => "snmalloc",
+                RawAllocator::System => "system",
+            }
+        ),
         match embedded.terminfo_resolution {
             TerminfoResolution::Dynamic => "pyembed::TerminfoResolution::Dynamic".to_string(),
             TerminfoResolution::None => "pyembed::TerminfoResolution::None".to_string(),
-            TerminfoResolution::Static(ref v) => {
-                format!("pyembed::TerminfoResolution::Static(r###\"{}\"###", v)
-            }
+            TerminfoResolution::Static(ref v) => format!(
+                "pyembed::TerminfoResolution::Static(r###\"{}\"###.to_string())",
+                v
+            ),
         },
+        match embedded.ssl_certificate_resolution {
+            CertificateResolution::Dynamic => "pyembed::CertificateResolution::Dynamic".to_string(),
+            CertificateResolution::None => "pyembed::CertificateResolution::None".to_string(),
+            CertificateResolution::Static(ref v) => format!(
+                "pyembed::CertificateResolution::Static(r###\"{}\"###.to_string())",
+                v
+            ),
+        },
+        optional_string_source(&embedded.tcl_library),
+        embedded.repl_line_editing,
+        optional_string_source(&embedded.repl_history_file),
         match &embedded.write_modules_directory_env {
             Some(path) => "Some(\"".to_owned() + &path + "\".to_string())",
             _ => "None".to_owned(),
         },
+        optional_string_source(&embedded.startup_profile_env),
+        optional_string_source(&embedded.packed_resources_key_env),
+        match embedded.stdio_redirect {
+            StdioRedirect::None => "pyembed::StdioRedirect::None".to_string(),
+            StdioRedirect::File(ref v) => format!(
+                "pyembed::StdioRedirect::File(r###\"{}\"###.to_string())",
+                v
+            ),
+            StdioRedirect::Debugger => "pyembed::StdioRedirect::Debugger".to_string(),
+        },
+        match embedded.site_packages_overlay {
+            Some(ref overlay) => format!(
+                "Some(pyembed::SitePackagesOverlay {{ path: r###\"{}\"###.to_string(), priority: pyembed::SitePackagesOverlayPriority::{:?} }})",
+                overlay.path, overlay.priority
+            ),
+            None => "None".to_string(),
+        },
+        string_vec_source(&embedded.zip_archives),
+        string_vec_source(&embedded.allocate_console_args),
         match embedded.run_mode {
             RunMode::Noop => "pyembed::PythonRunMode::None".to_owned(),
             RunMode::Repl => "pyembed::PythonRunMode::Repl".to_owned(),
@@ -110,17 +240,89 @@ pub fn derive_python_config(
                     + module
                     + "\".to_string() }"
             }
+            RunMode::ModuleWithArgs {
+                ref module,
+                ref args,
+            } => format!(
+                "pyembed::PythonRunMode::ModuleWithArgs {{ module: \"{}\".to_string(), args: {} }}",
+                module,
+                string_vec_source(args)
+            ),
+            RunMode::MainModule { ref module } => format!(
+                "pyembed::PythonRunMode::MainModule {{ module: \"{}\".to_string() }}",
+                module
+            ),
             RunMode::Eval { ref code } => {
                 "pyembed::PythonRunMode::Eval { code: r###\"".to_owned()
                     + code
                     + "\"###.to_string() }"
             }
+            RunMode::EvalMany { ref code } => format!(
+                "pyembed::PythonRunMode::EvalMany {{ code: {} }}",
+                string_vec_source(code)
+            ),
             RunMode::File { ref path } => {
-                "pyembed::PythonRunMode::File { path: std::path::PathBuf::new(r###\"".to_owned()
+                "pyembed::PythonRunMode::File { path: std::path::PathBuf::from(r###\"".to_owned()
                     + path
                     + "\"###) }"
             }
+            RunMode::FileFromArgs => "pyembed::PythonRunMode::FileFromArgs".to_owned(),
+            RunMode::JupyterKernel {
+                ref name,
+                ref display_name,
+            } => format!(
+                "pyembed::PythonRunMode::JupyterKernel {{ name: r###\"{}\"###.to_string(), display_name: r###\"{}\"###.to_string() }}",
+                name, display_name
+            ),
+            RunMode::Server {
+                ref application,
+                ref interface,
+                ref address,
+                max_request_body_size,
+            } => format!(
+                "pyembed::PythonRunMode::Server {{ application: r###\"{}\"###.to_string(), interface: pyembed::ServerInterface::{}, address: r###\"{}\"###.to_string(), max_request_body_size: {} }}",
+                application,
+                match interface {
+                    ServerInterface::Wsgi => "Wsgi",
+                    ServerInterface::Asgi => "Asgi",
+                },
+                address,
+                max_request_body_size
+            ),
+        },
+        embedded.multiprocessing_auto_dispatch,
+        optional_enum_source(
+            "MultiprocessingStartMethod",
+            &embedded.multiprocessing_start_method,
+        ),
+        optional_enum_source("Allocator", &embedded.allocator),
+        embedded.configure_locale,
+        optional_enum_source("CoerceCLocale", &embedded.coerce_c_locale),
+        embedded.coerce_c_locale_warn,
+        embedded.development_mode,
+        embedded.parse_argv,
+        embedded.utf8_mode,
+        optional_enum_source("CheckHashPYCsMode", &embedded.check_hash_pycs_mode),
+        embedded.configure_c_stdio,
+        embedded.dump_refs,
+        embedded.fault_handler,
+        optional_string_source(&embedded.filesystem_encoding),
+        optional_string_source(&embedded.filesystem_errors),
+        embedded.hash_seed,
+        embedded.import_time,
+        embedded.install_signal_handlers,
+        embedded.malloc_stats,
+        embedded.pathconfig_warnings,
+        match &embedded.pycache_prefix {
+            Some(path) => format!("Some(std::path::PathBuf::from(r###\"{}\"###))", path),
+            None => "None".to_string(),
         },
+        embedded.show_alloc_count,
+        embedded.show_ref_count,
+        embedded.skip_first_source_line,
+        embedded.tracemalloc,
+        string_vec_source(&embedded.warn_options),
+        string_vec_source(&embedded.x_options),
     )
 }
 
@@ -11,8 +11,8 @@ use {
     anyhow::Result,
     python_packaging::module_util::{packages_from_module_name, resolve_path_for_module},
     python_packaging::resource::{
-        PythonExtensionModule, PythonModuleSource, PythonPackageDistributionResource,
-        PythonPackageResource,
+        PythonDataFile, PythonExtensionModule, PythonModuleSource,
+        PythonPackageDistributionResource, PythonPackageResource,
     },
 };
 
@@ -92,10 +92,24 @@ impl AddToFileManifest for PythonExtensionModule {
     }
 }
 
+impl AddToFileManifest for PythonDataFile {
+    fn add_to_file_manifest(&self, manifest: &mut FileManifest, prefix: &str) -> Result<()> {
+        manifest.add_file(
+            &self.resolve_path(prefix),
+            &FileContent {
+                data: self.data.resolve()?,
+                executable: self.executable,
+            },
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use {
-        super::*, itertools::Itertools, python_packaging::resource::DataLocation,
+        super::*,
+        itertools::Itertools,
+        python_packaging::resource::{DataFileInstallLocation, DataLocation},
         std::path::PathBuf,
     };
 
@@ -112,6 +126,7 @@ mod tests {
             cache_tag: DEFAULT_CACHE_TAG.to_string(),
             is_stdlib: false,
             is_test: false,
+            provenance: None,
         }
         .add_to_file_manifest(&mut m, ".")?;
 
@@ -122,6 +137,7 @@ mod tests {
             cache_tag: DEFAULT_CACHE_TAG.to_string(),
             is_stdlib: false,
             is_test: false,
+            provenance: None,
         }
         .add_to_file_manifest(&mut m, ".")?;
 
@@ -144,6 +160,7 @@ mod tests {
             cache_tag: DEFAULT_CACHE_TAG.to_string(),
             is_stdlib: false,
             is_test: false,
+            provenance: None,
         }
         .add_to_file_manifest(&mut m, ".")?;
 
@@ -165,6 +182,7 @@ mod tests {
             cache_tag: DEFAULT_CACHE_TAG.to_string(),
             is_stdlib: false,
             is_test: false,
+            provenance: None,
         }
         .add_to_file_manifest(&mut m, ".")?;
 
@@ -188,6 +206,7 @@ mod tests {
             cache_tag: DEFAULT_CACHE_TAG.to_string(),
             is_stdlib: false,
             is_test: false,
+            provenance: None,
         }
         .add_to_file_manifest(&mut m, ".")?;
 
@@ -202,4 +221,26 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_data_file_add_to_manifest() -> Result<()> {
+        let mut m = FileManifest::default();
+
+        PythonDataFile {
+            package: "foo".to_string(),
+            location: DataFileInstallLocation::Scripts,
+            relative_path: PathBuf::from("foo-cli"),
+            data: DataLocation::Memory(vec![42]),
+            executable: true,
+            provenance: None,
+        }
+        .add_to_file_manifest(&mut m, ".")?;
+
+        let entries = m.entries().collect_vec();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].0, &PathBuf::from("./bin/foo-cli"));
+        assert!(entries[0].1.executable);
+
+        Ok(())
+    }
 }
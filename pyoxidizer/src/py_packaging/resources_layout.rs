@@ -0,0 +1,150 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Functionality for laying out packed resources data.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    python_packed_resources::writer::ResourcesLayout,
+    std::collections::BTreeSet,
+    std::path::Path,
+};
+
+/// Modules Python imports while it initializes, in import order.
+///
+/// These are written first when packed resources data is aligned but no
+/// import profile is available. Modules not packaged for a platform are
+/// ignored.
+pub const STARTUP_MODULES: &[&str] = &[
+    "encodings",
+    "codecs",
+    "encodings.aliases",
+    "encodings.utf_8",
+    "encodings.latin_1",
+    "io",
+    "abc",
+    "site",
+    "os",
+    "stat",
+    "_collections_abc",
+    "posixpath",
+    "ntpath",
+    "genericpath",
+    "_sitebuiltins",
+];
+
+/// Obtain the modules imported in a startup profile, in import order.
+///
+/// `data` is the JSON written by binaries whose `startup_profile_env`
+/// environment variable is set.
+pub fn parse_import_profile(data: &[u8]) -> Result<Vec<String>> {
+    let profile: serde_json::Value =
+        serde_json::from_slice(data).context("parsing startup profile")?;
+
+    let imports = profile
+        .get("imports")
+        .and_then(|imports| imports.as_array())
+        .ok_or_else(|| anyhow!("startup profile has no imports list"))?;
+
+    let mut seen = BTreeSet::new();
+    let mut modules = Vec::new();
+
+    for import in imports {
+        let module = import
+            .get("module")
+            .and_then(|module| module.as_str())
+            .ok_or_else(|| anyhow!("startup profile import has no module name"))?;
+
+        if seen.insert(module.to_string()) {
+            modules.push(module.to_string());
+        }
+    }
+
+    Ok(modules)
+}
+
+/// Resolve the layout of packed resources data.
+///
+/// Modules imported in the startup profile at `import_profile` are written
+/// first. Without a profile, `STARTUP_MODULES` are written first if the
+/// data is aligned. Returns `None` if neither option is set, in which case
+/// the default layout is used.
+pub fn resolve_resources_layout(
+    alignment: Option<usize>,
+    import_profile: Option<&Path>,
+) -> Result<Option<ResourcesLayout>> {
+    let hot_resources = match import_profile {
+        Some(path) => {
+            let data = std::fs::read(path)
+                .map_err(|e| anyhow!("unable to read import profile {}: {}", path.display(), e))?;
+
+            parse_import_profile(&data)
+                .with_context(|| format!("reading import profile {}", path.display()))?
+        }
+        None if alignment.is_some() => STARTUP_MODULES.iter().map(|s| s.to_string()).collect(),
+        None => return Ok(None),
+    };
+
+    Ok(Some(ResourcesLayout {
+        hot_resources,
+        alignment,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_import_profile() -> Result<()> {
+        let profile = br#"{
+  "phases": [
+    {"name": "load_resources", "start_us": 10, "duration_us": 20}
+  ],
+  "imports": [
+    {"module": "encodings", "find_us": 1, "load_us": 2, "exec_us": 3, "exec_self_us": 3},
+    {"module": "foo.bar", "find_us": 1, "load_us": 2, "exec_us": 3, "exec_self_us": 3},
+    {"module": "encodings", "find_us": 1, "load_us": 0, "exec_us": 0, "exec_self_us": 0}
+  ]
+}
+"#;
+
+        assert_eq!(
+            parse_import_profile(profile)?,
+            vec!["encodings".to_string(), "foo.bar".to_string()]
+        );
+
+        assert!(parse_import_profile(b"{}").is_err());
+        assert!(parse_import_profile(b"{\"imports\": [{}]}").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_resources_layout() -> Result<()> {
+        assert_eq!(resolve_resources_layout(None, None)?, None);
+
+        let layout = resolve_resources_layout(Some(4096), None)?.unwrap();
+        assert_eq!(layout.alignment, Some(4096));
+        assert_eq!(layout.hot_resources.len(), STARTUP_MODULES.len());
+
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let path = temp_dir.path().join("profile.json");
+        std::fs::write(&path, b"{\"imports\": [{\"module\": \"foo\"}]}")?;
+
+        assert_eq!(
+            resolve_resources_layout(None, Some(&path))?,
+            Some(ResourcesLayout {
+                hot_resources: vec!["foo".to_string()],
+                alignment: None,
+            })
+        );
+
+        assert!(resolve_resources_layout(None, Some(&temp_dir.path().join("missing"))).is_err());
+
+        Ok(())
+    }
+}
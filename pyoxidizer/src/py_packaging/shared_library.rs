@@ -0,0 +1,418 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Vendoring of shared libraries that extension modules depend on.
+
+Binary wheels often contain extension modules linking against shared
+libraries that aren't part of the operating system (e.g. `libgfortran`).
+Tools like `auditwheel` and `delvewheel` copy these libraries into the
+wheel, typically in a `<package>.libs` directory. The functionality in
+this module finds these libraries so they can be distributed next to the
+extension module that needs them.
+*/
+
+use {
+    anyhow::{anyhow, Result},
+    python_packaging::filesystem_scanning::walk_tree_files,
+    python_packaging::resource::{DataLocation, LibraryDependency, PythonExtensionModule},
+    slog::warn,
+    std::collections::{BTreeMap, HashSet},
+    std::path::PathBuf,
+};
+
+/// ELF shared libraries that are provided by all Linux systems.
+///
+/// This mirrors the manylinux2014 policy.
+const ELF_SYSTEM_LIBRARIES: &[&str] = &[
+    "ld-linux-x86-64.so.2",
+    "ld-linux.so.2",
+    "libc.so.6",
+    "libcrypt.so.1",
+    "libdl.so.2",
+    "libgcc_s.so.1",
+    "libGL.so.1",
+    "libglib-2.0.so.0",
+    "libgobject-2.0.so.0",
+    "libgthread-2.0.so.0",
+    "libICE.so.6",
+    "libm.so.6",
+    "libnsl.so.1",
+    "libpthread.so.0",
+    "librt.so.1",
+    "libSM.so.6",
+    "libstdc++.so.6",
+    "libutil.so.1",
+    "libX11.so.6",
+    "libXext.so.6",
+    "libXrender.so.1",
+];
+
+/// Prefixes of Windows DLL names that are provided by the operating system
+/// or the Python distribution.
+const PE_SYSTEM_LIBRARY_PREFIXES: &[&str] = &[
+    "advapi32",
+    "api-ms-win-",
+    "bcrypt",
+    "comctl32",
+    "comdlg32",
+    "crypt32",
+    "gdi32",
+    "imm32",
+    "kernel32",
+    "msvcp",
+    "msvcrt",
+    "ntdll",
+    "ole32",
+    "oleaut32",
+    "python3",
+    "rpcrt4",
+    "secur32",
+    "setupapi",
+    "shell32",
+    "shlwapi",
+    "ucrtbase",
+    "user32",
+    "vcruntime",
+    "version",
+    "winmm",
+    "ws2_32",
+];
+
+/// Whether a shared library is provided by the operating system.
+///
+/// System libraries are expected to be present on the machine running the
+/// built application and are never distributed.
+pub fn is_system_library(name: &str) -> bool {
+    let lower = name.to_lowercase();
+
+    if lower.ends_with(".dll") {
+        PE_SYSTEM_LIBRARY_PREFIXES
+            .iter()
+            .any(|prefix| lower.starts_with(prefix))
+    } else if name.starts_with("/usr/lib/") || name.starts_with("/System/") {
+        true
+    } else {
+        ELF_SYSTEM_LIBRARIES.contains(&name) || name.starts_with("libpython")
+    }
+}
+
+/// Resolve the names of shared libraries a binary depends on.
+pub fn shared_library_dependencies(data: &[u8]) -> Result<Vec<String>> {
+    match goblin::Object::parse(data)? {
+        goblin::Object::Elf(elf) => Ok(elf.libraries.iter().map(|l| (*l).to_string()).collect()),
+        goblin::Object::PE(pe) => Ok(pe.libraries.iter().map(|l| (*l).to_string()).collect()),
+        goblin::Object::Mach(goblin::mach::Mach::Binary(macho)) => Ok(macho
+            .libs
+            .iter()
+            .filter(|l| **l != "self")
+            .map(|l| (*l).to_string())
+            .collect()),
+        _ => Err(anyhow!("unsupported binary format")),
+    }
+}
+
+/// Whether a binary contains code runnable on a Rust target triple.
+///
+/// Only the architecture of Mach-O binaries is checked, as macOS extension
+/// modules for different architectures have the same filename suffix. A fat
+/// (universal) binary is compatible if any of its architectures is.
+/// Other binary formats are always considered compatible.
+pub fn is_binary_compatible_with_target(data: &[u8], target_triple: &str) -> Result<bool> {
+    let wanted = if target_triple.starts_with("aarch64-apple-") {
+        goblin::mach::cputype::CPU_TYPE_ARM64
+    } else if target_triple.starts_with("x86_64-apple-") {
+        goblin::mach::cputype::CPU_TYPE_X86_64
+    } else {
+        return Ok(true);
+    };
+
+    match goblin::Object::parse(data)? {
+        goblin::Object::Mach(goblin::mach::Mach::Binary(macho)) => {
+            Ok(macho.header.cputype == wanted)
+        }
+        goblin::Object::Mach(goblin::mach::Mach::Fat(fat)) => {
+            for arch in fat.iter_arches() {
+                if arch?.cputype == wanted {
+                    return Ok(true);
+                }
+            }
+
+            Ok(false)
+        }
+        _ => Ok(true),
+    }
+}
+
+/// Rewrite the run-time search path of an ELF binary to `$ORIGIN`.
+///
+/// The existing `DT_RUNPATH` or `DT_RPATH` string is overwritten in place.
+/// So this only works if the binary already defines a search path at least
+/// as long as `$ORIGIN`, which is the case for binaries processed by
+/// `auditwheel`.
+///
+/// Returns whether the binary defines a search path that is now `$ORIGIN`.
+pub fn set_elf_origin_rpath(data: &mut [u8]) -> Result<bool> {
+    const ORIGIN: &[u8] = b"$ORIGIN";
+
+    let offsets = {
+        let elf = goblin::elf::Elf::parse(&*data)?;
+
+        let dynamic = match &elf.dynamic {
+            Some(dynamic) => dynamic,
+            None => return Ok(false),
+        };
+
+        let strtab_offset = match elf
+            .section_headers
+            .iter()
+            .find(|sh| sh.sh_addr as usize == dynamic.info.strtab)
+        {
+            Some(sh) => sh.sh_offset as usize,
+            None => return Ok(false),
+        };
+
+        dynamic
+            .dyns
+            .iter()
+            .filter(|d| {
+                d.d_tag == goblin::elf::dynamic::DT_RUNPATH
+                    || d.d_tag == goblin::elf::dynamic::DT_RPATH
+            })
+            .map(|d| strtab_offset + d.d_val as usize)
+            .collect::<Vec<_>>()
+    };
+
+    if offsets.is_empty() {
+        return Ok(false);
+    }
+
+    for offset in offsets {
+        let len = data[offset..]
+            .iter()
+            .position(|c| *c == 0)
+            .ok_or_else(|| anyhow!("unterminated string in ELF string table"))?;
+
+        if len < ORIGIN.len() {
+            return Ok(false);
+        }
+
+        data[offset..offset + ORIGIN.len()].copy_from_slice(ORIGIN);
+        for c in &mut data[offset + ORIGIN.len()..offset + len] {
+            *c = 0;
+        }
+    }
+
+    Ok(true)
+}
+
+/// Index shared library files under directories by their filename.
+pub fn index_shared_libraries(roots: &[PathBuf]) -> BTreeMap<String, PathBuf> {
+    let mut res = BTreeMap::new();
+
+    for root in roots {
+        for entry in walk_tree_files(root) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let lower = name.to_lowercase();
+
+            if lower.contains(".so") || lower.ends_with(".dll") || lower.ends_with(".dylib") {
+                res.entry(name)
+                    .or_insert_with(|| entry.path().to_path_buf());
+            }
+        }
+    }
+
+    res
+}
+
+/// Vendor shared libraries an extension module depends on.
+///
+/// Non-system libraries the extension module (transitively) depends on are
+/// looked up by filename in `libraries`, as obtained from
+/// `index_shared_libraries()`. Found libraries are registered as dynamic
+/// library dependencies of the returned extension module, which causes them
+/// to be installed next to it. ELF binaries have their run-time search path
+/// rewritten to `$ORIGIN` so the libraries are found there.
+///
+/// Libraries that can't be found are reported, as the built application will
+/// likely only run on machines having them installed.
+pub fn vendor_extension_module_libraries(
+    logger: &slog::Logger,
+    em: &PythonExtensionModule,
+    libraries: &BTreeMap<String, PathBuf>,
+) -> Result<PythonExtensionModule> {
+    let mut data = match &em.shared_library {
+        Some(location) => location.resolve()?,
+        None => return Ok(em.clone()),
+    };
+
+    let is_elf = data.starts_with(b"\x7fELF");
+    let is_macho = match goblin::Object::parse(&data)? {
+        goblin::Object::Mach(_) => true,
+        _ => false,
+    };
+
+    let mut res = em.clone();
+    let mut seen = em
+        .link_libraries
+        .iter()
+        .map(|l| l.name.clone())
+        .collect::<HashSet<_>>();
+    let mut pending = shared_library_dependencies(&data)?;
+    let mut vendored = false;
+
+    while let Some(name) = pending.pop() {
+        if !seen.insert(name.clone()) || is_system_library(&name) {
+            continue;
+        }
+
+        // Mach-O dependencies are referenced by path.
+        let filename = name.rsplit('/').next().unwrap_or(&name);
+
+        let path = libraries.get(filename).or_else(|| {
+            libraries
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(filename))
+                .map(|(_, v)| v)
+        });
+
+        let path = match path {
+            Some(path) => path,
+            None => {
+                warn!(
+                    logger,
+                    "extension module {} depends on {}, which could not be found; the built application will likely only run on machines having it installed",
+                    em.name,
+                    name
+                );
+                continue;
+            }
+        };
+
+        if is_macho {
+            warn!(
+                logger,
+                "extension module {} depends on {}, but rewriting Mach-O load commands is not supported; it will not be vendored",
+                em.name,
+                name
+            );
+            continue;
+        }
+
+        let mut library_data = std::fs::read(path)?;
+        pending.extend(shared_library_dependencies(&library_data)?);
+
+        if is_elf {
+            set_elf_origin_rpath(&mut library_data)?;
+        }
+
+        warn!(
+            logger,
+            "vendoring {} for extension module {}",
+            path.display(),
+            em.name
+        );
+
+        res.link_libraries.push(LibraryDependency {
+            name: filename.to_string(),
+            static_library: None,
+            dynamic_library: Some(DataLocation::Memory(library_data)),
+            framework: false,
+            system: false,
+        });
+        vendored = true;
+    }
+
+    if vendored && is_elf {
+        if !set_elf_origin_rpath(&mut data)? {
+            warn!(
+                logger,
+                "unable to rewrite run-time search path of extension module {}; vendored libraries may not be found at run-time",
+                em.name
+            );
+        }
+
+        res.shared_library = Some(DataLocation::Memory(data));
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_system_library() {
+        assert!(is_system_library("libc.so.6"));
+        assert!(is_system_library("libpython3.7m.so.1.0"));
+        assert!(is_system_library("KERNEL32.dll"));
+        assert!(is_system_library("api-ms-win-crt-runtime-l1-1-0.dll"));
+        assert!(is_system_library("VCRUNTIME140.dll"));
+        assert!(is_system_library("/usr/lib/libSystem.B.dylib"));
+
+        assert!(!is_system_library("libgfortran-2e0d59d6.so.5.0.0"));
+        assert!(!is_system_library("libopenblas.dll"));
+        assert!(!is_system_library(
+            "@loader_path/.dylibs/libgfortran.3.dylib"
+        ));
+    }
+
+    #[test]
+    fn test_is_binary_compatible_with_target() -> Result<()> {
+        // Minimal 64-bit Mach-O header without load commands.
+        let macho = |cputype: u32| -> Vec<u8> {
+            let mut data = Vec::new();
+            for value in &[0xfeed_facfu32, cputype, 0, 6, 0, 0, 0, 0] {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            data
+        };
+
+        let arm64 = macho(goblin::mach::cputype::CPU_TYPE_ARM64);
+        assert!(is_binary_compatible_with_target(
+            &arm64,
+            "aarch64-apple-darwin"
+        )?);
+        assert!(!is_binary_compatible_with_target(
+            &arm64,
+            "x86_64-apple-darwin"
+        )?);
+
+        let x86_64 = macho(goblin::mach::cputype::CPU_TYPE_X86_64);
+        assert!(is_binary_compatible_with_target(
+            &x86_64,
+            "x86_64-apple-darwin"
+        )?);
+        assert!(!is_binary_compatible_with_target(
+            &x86_64,
+            "aarch64-apple-darwin"
+        )?);
+
+        assert!(is_binary_compatible_with_target(
+            &arm64,
+            "x86_64-unknown-linux-gnu"
+        )?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_shared_libraries() -> Result<()> {
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+        let libs = td.path().join("numpy.libs");
+        std::fs::create_dir_all(&libs)?;
+        std::fs::write(libs.join("libgfortran-2e0d59d6.so.5.0.0"), b"")?;
+        std::fs::write(td.path().join("foo.py"), b"")?;
+
+        let index = index_shared_libraries(&[td.path().to_path_buf()]);
+        assert_eq!(index.len(), 1);
+        assert_eq!(
+            index.get("libgfortran-2e0d59d6.so.5.0.0"),
+            Some(&libs.join("libgfortran-2e0d59d6.so.5.0.0"))
+        );
+
+        Ok(())
+    }
+}
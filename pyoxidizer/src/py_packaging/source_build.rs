@@ -0,0 +1,253 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Building Python distributions from CPython source code.
+
+CPython is configured, compiled and installed into a staging directory with
+a shared libpython. The installation is packaged into a `.tar.zst`
+distribution archive holding a `python/install` directory and a
+`python/SOURCE_BUILD.json` file describing the build.
+
+When such an archive is extracted, the installation is used like an
+installed Python distribution: its interpreter describes it by running a
+script.
+*/
+
+use {
+    super::installed_distribution::introspect_installed_python,
+    super::standalone_distribution::StandaloneDistribution,
+    crate::environment::LINUX_TARGET_TRIPLES,
+    anyhow::{anyhow, Context, Result},
+    serde::{Deserialize, Serialize},
+    slog::warn,
+    std::path::{Path, PathBuf},
+    std::process::Command,
+};
+
+/// Name of the file in the `python` directory describing a source build.
+pub const SOURCE_BUILD_JSON: &str = "SOURCE_BUILD.json";
+
+/// Describes a Python distribution built from source.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SourceBuildInfo {
+    /// Rust target triple the distribution runs on.
+    pub target_triple: String,
+    /// Path to the Python interpreter, relative to the `python` directory.
+    pub python_exe: String,
+    /// Arguments passed to `configure` in addition to the defaults.
+    pub configure_args: Vec<String>,
+}
+
+/// Find the directory holding the `configure` script of CPython source.
+///
+/// `path` is either the source directory itself or a directory with a
+/// single source directory in it, as produced by extracting a source
+/// tarball.
+fn find_source_root(path: &Path) -> Result<PathBuf> {
+    if path.join("configure").exists() {
+        return Ok(path.to_path_buf());
+    }
+
+    let candidates = std::fs::read_dir(path)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|p| p.join("configure").exists())
+        .collect::<Vec<_>>();
+
+    match candidates.as_slice() {
+        [root] => Ok(root.clone()),
+        [] => Err(anyhow!(
+            "unable to find CPython configure script in {}",
+            path.display()
+        )),
+        _ => Err(anyhow!(
+            "{} holds multiple source directories",
+            path.display()
+        )),
+    }
+}
+
+/// Run a command, failing if it doesn't exit successfully.
+fn run_command(logger: &slog::Logger, command: &mut Command) -> Result<()> {
+    warn!(logger, "running {:?}", command);
+
+    let status = command
+        .status()
+        .with_context(|| format!("running {:?}", command))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("{:?} exited with {}", command, status))
+    }
+}
+
+/// Build a Python distribution archive from CPython source.
+///
+/// `source_path` is a CPython source tarball or an already extracted
+/// source directory. `configure_args` are passed to `configure` after the
+/// arguments required to produce a distribution. The distribution archive
+/// is written to `dest_path`.
+pub fn build_distribution_from_source(
+    logger: &slog::Logger,
+    source_path: &Path,
+    dest_path: &Path,
+    target_triple: &str,
+    configure_args: &[String],
+    jobs: usize,
+) -> Result<()> {
+    if !LINUX_TARGET_TRIPLES.contains(&target_triple) || target_triple.contains("-linux-musl") {
+        return Err(anyhow!(
+            "building Python distributions from source is only supported for Linux glibc targets; {} is not supported",
+            target_triple
+        ));
+    }
+
+    let temp_dir = tempdir::TempDir::new("pyoxidizer-python-source-build")?;
+
+    let source_root = if source_path.is_dir() {
+        find_source_root(source_path)?
+    } else {
+        let extract_dir = temp_dir.path().join("source");
+        std::fs::create_dir_all(&extract_dir)?;
+
+        run_command(
+            logger,
+            Command::new("tar")
+                .arg("-xf")
+                .arg(source_path)
+                .arg("-C")
+                .arg(&extract_dir),
+        )
+        .with_context(|| format!("extracting {}", source_path.display()))?;
+
+        find_source_root(&extract_dir)?
+    };
+
+    let python_dir = temp_dir.path().join("dist").join("python");
+    let install_dir = python_dir.join("install");
+
+    // libpython is found relative to binaries so the installation can be
+    // relocated. `$` is escaped for make and the shell.
+    run_command(
+        logger,
+        Command::new("./configure")
+            .current_dir(&source_root)
+            .arg(format!("--prefix={}", install_dir.display()))
+            .arg("--enable-shared")
+            .arg("LDFLAGS=-Wl,-rpath,\\$$ORIGIN/../lib")
+            .args(configure_args),
+    )
+    .context("configuring CPython")?;
+
+    run_command(
+        logger,
+        Command::new("make")
+            .current_dir(&source_root)
+            .arg(format!("-j{}", jobs)),
+    )
+    .context("building CPython")?;
+
+    run_command(
+        logger,
+        Command::new("make")
+            .current_dir(&source_root)
+            .arg("install"),
+    )
+    .context("installing CPython")?;
+
+    let info = SourceBuildInfo {
+        target_triple: target_triple.to_string(),
+        python_exe: "install/bin/python3".to_string(),
+        configure_args: configure_args.to_vec(),
+    };
+
+    // Ensure the interpreter works before packaging it.
+    introspect_installed_python(&python_dir.join(&info.python_exe))?;
+
+    std::fs::write(
+        python_dir.join(SOURCE_BUILD_JSON),
+        serde_json::to_vec_pretty(&info)?,
+    )?;
+
+    warn!(logger, "writing distribution to {}", dest_path.display());
+    write_distribution_archive(&python_dir, dest_path)
+}
+
+/// Write a `python` directory to a `.tar.zst` distribution archive.
+fn write_distribution_archive(python_dir: &Path, dest_path: &Path) -> Result<()> {
+    if let Some(parent) = dest_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let fh = std::fs::File::create(dest_path)
+        .with_context(|| format!("creating {}", dest_path.display()))?;
+    let mut builder = tar::Builder::new(zstd::stream::Encoder::new(fh, 0)?);
+    builder.follow_symlinks(false);
+    builder.append_dir_all("python", python_dir)?;
+    builder.into_inner()?.finish()?;
+
+    Ok(())
+}
+
+/// Whether an extracted distribution was built from source.
+pub fn is_source_build_directory(dist_dir: &Path) -> bool {
+    dist_dir.join("python").join(SOURCE_BUILD_JSON).exists()
+}
+
+/// Resolve an extracted distribution built from source.
+pub fn resolve_source_build_distribution(dist_dir: &Path) -> Result<StandaloneDistribution> {
+    let python_dir = dist_dir.join("python");
+    let json_path = python_dir.join(SOURCE_BUILD_JSON);
+
+    let info: SourceBuildInfo = serde_json::from_slice(&std::fs::read(&json_path)?)
+        .with_context(|| format!("parsing {}", json_path.display()))?;
+
+    let installed = introspect_installed_python(&python_dir.join(&info.python_exe))?;
+
+    StandaloneDistribution::from_installed_python(&installed, &info.target_triple)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_source_root() -> Result<()> {
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+
+        assert!(find_source_root(td.path()).is_err());
+
+        let root = td.path().join("Python-3.8.3");
+        std::fs::create_dir_all(&root)?;
+        std::fs::write(root.join("configure"), b"")?;
+
+        assert_eq!(find_source_root(td.path())?, root);
+        assert_eq!(find_source_root(&root)?, root);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_distribution_archive() -> Result<()> {
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+        let python_dir = td.path().join("python");
+        std::fs::create_dir_all(python_dir.join("install"))?;
+        std::fs::write(python_dir.join(SOURCE_BUILD_JSON), b"{}")?;
+
+        let archive_path = td.path().join("dist").join("python.tar.zst");
+        write_distribution_archive(&python_dir, &archive_path)?;
+
+        let extract_dir = td.path().join("extract");
+        let mut archive = tar::Archive::new(zstd::stream::Decoder::new(std::fs::File::open(
+            &archive_path,
+        )?)?);
+        archive.unpack(&extract_dir)?;
+
+        assert!(is_source_build_directory(&extract_dir));
+
+        Ok(())
+    }
+}
@@ -5,30 +5,46 @@
 use {
     super::binary::{
         EmbeddedPythonContext, LibpythonLinkMode, PythonBinaryBuilder, PythonLinkingInfo,
+        WindowsSubsystem,
     },
-    super::config::{EmbeddedPythonConfig, RawAllocator},
+    super::config::{EmbeddedPythonConfig, InMemoryModuleFile, RawAllocator, RunMode},
+    super::dependency_report::{DependencyReport, DistributionKind},
     super::distribution::{BinaryLibpythonLinkMode, PythonDistribution},
     super::filtering::{filter_btreemap, resolve_resource_names_from_files},
     super::libpython::{link_libpython, LibPythonBuildContext},
-    super::packaging_tool::{find_resources, pip_install, read_virtualenv, setup_py_install},
+    super::lockfile::VerifiedArtifact,
+    super::package_index::{ensure_keyring, PackageIndexes},
+    super::packaging_tool::{
+        find_resources, pep517_build, pip_download, pip_install, pip_install_foreign,
+        read_conda_env, read_conda_packages, read_virtualenv, setup_py_install,
+    },
+    super::resources_layout::resolve_resources_layout,
     super::standalone_distribution::StandaloneDistribution,
-    crate::app_packaging::resource::{FileContent, FileManifest},
+    super::target_support::check_wheel_resources,
+    crate::app_packaging::resource::{FileAttributes, FileContent, FileManifest},
     anyhow::{anyhow, Result},
     lazy_static::lazy_static,
     python_packaging::bytecode::BytecodeCompiler,
-    python_packaging::policy::{PythonPackagingPolicy, PythonResourcesPolicy},
+    python_packaging::policy::{
+        DiagnosticSeverity, InstalledResourceAttributes, PolicyValidationContext,
+        PythonPackagingPolicy, PythonResourcesPolicy, ResourceSizeBudgetAction,
+    },
     python_packaging::resource::{
-        BytecodeOptimizationLevel, DataLocation, PythonExtensionModule,
-        PythonModuleBytecodeFromSource, PythonModuleSource, PythonPackageDistributionResource,
-        PythonPackageResource, PythonResource,
+        DataLocation, PythonEditableInstall, PythonExtensionModule, PythonModuleBytecodeFromSource,
+        PythonModuleSource, PythonPackageDistributionResource, PythonPackageResource,
+        PythonResource,
     },
     python_packaging::resource_collection::{
         ConcreteResourceLocation, PrePackagedResource, PythonResourceCollector,
     },
-    slog::warn,
+    python_packed_resources::writer::{
+        packed_resources_public_key, sign_packed_resources, WriteOptions,
+    },
+    slog::{info, warn},
     std::collections::{BTreeMap, HashMap},
-    std::io::Write,
-    std::path::{Path, PathBuf},
+    std::convert::TryFrom,
+    std::io::{BufWriter, Read, Seek, SeekFrom, Write},
+    std::path::{Component, Path, PathBuf},
     std::sync::Arc,
     tempdir::TempDir,
 };
@@ -52,6 +68,24 @@ fn ignored_libraries_for_target(target_triple: &str) -> Vec<&'static str> {
     }
 }
 
+/// Obtain the path a file is installed to relative to the build output directory.
+///
+/// Resources installed to absolute paths are placed in the output directory
+/// with the root of the path removed, e.g. `/usr/lib/foo` is installed to
+/// `usr/lib/foo`. This allows using the output directory as a staging root.
+fn staged_install_path(path: &Path) -> PathBuf {
+    if path.has_root() {
+        path.components()
+            .filter_map(|c| match c {
+                Component::Normal(p) => Some(p),
+                _ => None,
+            })
+            .collect()
+    } else {
+        path.to_path_buf()
+    }
+}
+
 /// A self-contained Python executable before it is compiled.
 #[derive(Clone, Debug)]
 pub struct StandalonePythonExecutableBuilder {
@@ -67,6 +101,13 @@ pub struct StandalonePythonExecutableBuilder {
     /// The Python distribution being used to build this executable.
     distribution: Arc<Box<StandaloneDistribution>>,
 
+    /// Python distribution whose Python interpreter runs at build time.
+    ///
+    /// This is `distribution` unless its Python interpreter can't run on
+    /// the host, which is the case when cross-compiling to another CPU
+    /// architecture.
+    host_distribution: Arc<Box<StandaloneDistribution>>,
+
     /// How libpython should be linked.
     link_mode: LibpythonLinkMode,
 
@@ -94,12 +135,42 @@ pub struct StandalonePythonExecutableBuilder {
 
     /// Path to python executable that can be invoked at build time.
     python_exe: PathBuf,
+
+    /// Downloaded artifacts whose hashes were verified.
+    verified_artifacts: Vec<VerifiedArtifact>,
+    /// Package indexes pip installs packages from.
+    package_indexes: PackageIndexes,
+
+    /// Distributions installed by packaging operations.
+    dependency_report: DependencyReport,
+
+    /// Directory relative to the binary to install Tcl/Tk library files into.
+    tcl_files_path: Option<String>,
+
+    /// Path relative to the binary to write packed resources data to.
+    packed_resources_path: Option<String>,
+
+    /// Path to an ed25519 secret key to sign the packed resources file with.
+    packed_resources_signing_key: Option<PathBuf>,
+
+    /// Path to a key to encrypt packed resources data with.
+    packed_resources_encryption_key: Option<PathBuf>,
+
+    /// Number of bytes to align sections of packed resources data to.
+    packed_resources_alignment: Option<usize>,
+
+    /// Path to a startup profile ordering packed resources data.
+    packed_resources_import_profile: Option<PathBuf>,
+
+    /// Windows subsystem the executable is built for.
+    windows_subsystem: WindowsSubsystem,
 }
 
 impl StandalonePythonExecutableBuilder {
     #[allow(clippy::too_many_arguments)]
     pub fn from_distribution(
         distribution: Arc<Box<StandaloneDistribution>>,
+        host_distribution: Arc<Box<StandaloneDistribution>>,
         host_triple: String,
         target_triple: String,
         exe_name: String,
@@ -107,7 +178,7 @@ impl StandalonePythonExecutableBuilder {
         packaging_policy: PythonPackagingPolicy,
         config: EmbeddedPythonConfig,
     ) -> Result<Box<Self>> {
-        let python_exe = distribution.python_exe.clone();
+        let python_exe = host_distribution.python_exe.clone();
         let cache_tag = distribution.cache_tag.clone();
 
         let (supports_static_libpython, supports_dynamic_libpython) =
@@ -146,30 +217,114 @@ impl StandalonePythonExecutableBuilder {
         let supports_in_memory_dynamically_linked_extension_loading =
             distribution.supports_in_memory_dynamically_linked_extension_loading();
 
+        let packaging_policy = packaging_policy
+            .resolve_target_overlays(&target_triple)
+            .resolve_python_version_overlays(&distribution.version);
+
+        let errors = packaging_policy
+            .validate(&PolicyValidationContext {
+                target_triple: &target_triple,
+                python_version: &distribution.version,
+                extension_modules: distribution.extension_modules.values().collect(),
+                in_memory_shared_library_loading:
+                    supports_in_memory_dynamically_linked_extension_loading,
+            })?
+            .into_iter()
+            .filter(|d| d.severity == DiagnosticSeverity::Error)
+            .map(|d| d.message)
+            .collect::<Vec<_>>();
+
+        if !errors.is_empty() {
+            return Err(anyhow!(
+                "packaging policy is not valid: {}",
+                errors.join("; ")
+            ));
+        }
+
+        let mut resources_collector =
+            PythonResourceCollector::new(packaging_policy.get_resources_policy(), &cache_tag);
+        for (package, policy) in packaging_policy.iter_package_resources_policies() {
+            resources_collector.set_package_policy(package, policy);
+        }
+        if let Some(policy) = packaging_policy.get_extension_modules_policy() {
+            resources_collector.set_extension_module_policy(policy);
+        }
+        for package in packaging_policy.iter_strip_docstrings_packages() {
+            resources_collector.add_strip_docstrings_package(package);
+        }
+
         let mut builder = Box::new(Self {
             host_triple,
             target_triple,
             exe_name,
             distribution,
+            host_distribution,
             link_mode,
             supports_in_memory_dynamically_linked_extension_loading,
-            packaging_policy: packaging_policy.clone(),
-            resources_collector: PythonResourceCollector::new(
-                packaging_policy.get_resources_policy(),
-                &cache_tag,
-            ),
+            packaging_policy,
+            resources_collector,
             core_build_context: LibPythonBuildContext::default(),
             extension_build_contexts: BTreeMap::new(),
             config,
             python_exe,
+            verified_artifacts: vec![],
+            package_indexes: PackageIndexes::default(),
+            dependency_report: DependencyReport::default(),
+            tcl_files_path: None,
+            packed_resources_path: None,
+            packed_resources_signing_key: None,
+            packed_resources_encryption_key: None,
+            packed_resources_alignment: None,
+            packed_resources_import_profile: None,
+            windows_subsystem: WindowsSubsystem::default(),
         });
 
-        builder.add_distribution_resources(&packaging_policy)?;
+        builder.add_distribution_resources()?;
 
         Ok(builder)
     }
 
-    fn add_distribution_resources(&mut self, policy: &PythonPackagingPolicy) -> Result<()> {
+    /// Whether we are building for a CPU architecture the host can't run.
+    fn is_cross_architecture(&self) -> bool {
+        self.host_distribution.target_triple != self.distribution.target_triple
+    }
+
+    /// Obtain environment variables for running pip.
+    ///
+    /// Variables configuring package indexes are overridden by `extra_envs`.
+    fn pip_envs(
+        &self,
+        logger: &slog::Logger,
+        extra_envs: &HashMap<String, String>,
+    ) -> Result<HashMap<String, String>> {
+        if self.package_indexes.keyring {
+            ensure_keyring(logger, &**self.host_distribution)?;
+        }
+
+        let mut envs = self.package_indexes.pip_envs()?;
+        envs.extend(extra_envs.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        Ok(envs)
+    }
+
+    /// Error if we are building for a CPU architecture the host can't run.
+    ///
+    /// Compiling Python packages requires running the target distribution's
+    /// Python interpreter.
+    fn ensure_not_cross_architecture(&self, action: &str) -> Result<()> {
+        if self.is_cross_architecture() {
+            Err(anyhow!(
+                "{} is not supported when cross-compiling from {} to {}; use pip_install() to install binary wheels instead",
+                action,
+                self.host_triple,
+                self.target_triple
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn add_distribution_resources(&mut self) -> Result<()> {
         self.core_build_context.inittab_cflags = Some(self.distribution.inittab_cflags.clone());
 
         for (name, path) in &self.distribution.includes {
@@ -237,24 +392,41 @@ impl StandalonePythonExecutableBuilder {
         for ext in self.packaging_policy.resolve_python_extension_modules(
             self.distribution.extension_modules.values(),
             &self.target_triple,
+            &self.distribution.version,
         )? {
             self.add_python_extension_module(&ext, None)?;
         }
 
         for source in self.distribution.source_modules()? {
-            if policy.filter_python_resource(&source.clone().into()) {
+            if self
+                .packaging_policy
+                .filter_python_resource(&source.clone().into())
+            {
                 self.add_python_module_source(&source, None)?;
             }
 
-            let bytecode = source.as_bytecode_module(BytecodeOptimizationLevel::Zero);
+            let levels = self
+                .packaging_policy
+                .get_bytecode_optimize_levels_for_name(&source.name)
+                .to_vec();
 
-            if policy.filter_python_resource(&bytecode.clone().into()) {
-                self.add_python_module_bytecode_from_source(&bytecode, None)?;
+            for level in levels {
+                let bytecode = source.as_bytecode_module(level);
+
+                if self
+                    .packaging_policy
+                    .filter_python_resource(&bytecode.clone().into())
+                {
+                    self.add_python_module_bytecode_from_source(&bytecode, None)?;
+                }
             }
         }
 
         for resource in self.distribution.resource_datas()? {
-            if policy.filter_python_resource(&resource.clone().into()) {
+            if self
+                .packaging_policy
+                .filter_python_resource(&resource.clone().into())
+            {
                 self.add_python_package_resource(&resource, None)?;
             }
         }
@@ -367,61 +539,247 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
     }
 
     fn pip_install(
-        &self,
+        &mut self,
         logger: &slog::Logger,
         verbose: bool,
         install_args: &[String],
         extra_envs: &HashMap<String, String>,
     ) -> Result<Vec<PythonResource>> {
-        pip_install(
+        let envs = self.pip_envs(logger, extra_envs)?;
+
+        let (resources, report) = if self.is_cross_architecture() {
+            pip_install_foreign(
+                logger,
+                &**self.host_distribution,
+                &**self.distribution,
+                &self.distribution.python_platform_tag,
+                &self.packaging_policy,
+                verbose,
+                install_args,
+                &envs,
+            )?
+        } else {
+            pip_install(
+                logger,
+                &**self.distribution,
+                &self.packaging_policy,
+                self.link_mode,
+                verbose,
+                install_args,
+                &envs,
+            )?
+        };
+
+        check_wheel_resources(&resources, &self.distribution.wheel_target())?;
+
+        self.dependency_report.merge(&report);
+
+        Ok(resources)
+    }
+
+    fn pip_download(
+        &self,
+        logger: &slog::Logger,
+        verbose: bool,
+        download_args: &[String],
+        dest_dir: &Path,
+        extra_envs: &HashMap<String, String>,
+    ) -> Result<Vec<PathBuf>> {
+        let envs = self.pip_envs(logger, extra_envs)?;
+
+        let foreign_platform_tag = if self.is_cross_architecture() {
+            Some(self.distribution.python_platform_tag.as_str())
+        } else {
+            None
+        };
+
+        pip_download(
             logger,
+            &**self.host_distribution,
             &**self.distribution,
-            self.link_mode,
+            foreign_platform_tag,
             verbose,
-            install_args,
-            extra_envs,
+            download_args,
+            dest_dir,
+            &envs,
         )
     }
 
+    fn add_verified_artifacts(&mut self, artifacts: &[VerifiedArtifact]) {
+        self.verified_artifacts.extend(artifacts.iter().cloned());
+    }
+
+    fn verified_artifacts(&self) -> &[VerifiedArtifact] {
+        &self.verified_artifacts
+    }
+
+    fn dependency_report(&self) -> &DependencyReport {
+        &self.dependency_report
+    }
+
+    fn package_indexes(&self) -> &PackageIndexes {
+        &self.package_indexes
+    }
+
+    fn set_package_indexes(&mut self, indexes: PackageIndexes) {
+        self.package_indexes = indexes;
+    }
+
+    fn tcl_files_path(&self) -> &Option<String> {
+        &self.tcl_files_path
+    }
+
+    fn set_tcl_files_path(&mut self, value: Option<String>) {
+        self.tcl_files_path = value;
+    }
+
+    fn packed_resources_path(&self) -> &Option<String> {
+        &self.packed_resources_path
+    }
+
+    fn set_packed_resources_path(&mut self, value: Option<String>) {
+        self.packed_resources_path = value;
+    }
+
+    fn packed_resources_signing_key(&self) -> &Option<PathBuf> {
+        &self.packed_resources_signing_key
+    }
+
+    fn set_packed_resources_signing_key(&mut self, value: Option<PathBuf>) {
+        self.packed_resources_signing_key = value;
+    }
+
+    fn packed_resources_encryption_key(&self) -> &Option<PathBuf> {
+        &self.packed_resources_encryption_key
+    }
+
+    fn set_packed_resources_encryption_key(&mut self, value: Option<PathBuf>) {
+        self.packed_resources_encryption_key = value;
+    }
+
+    fn packed_resources_alignment(&self) -> Option<usize> {
+        self.packed_resources_alignment
+    }
+
+    fn set_packed_resources_alignment(&mut self, value: Option<usize>) {
+        self.packed_resources_alignment = value;
+    }
+
+    fn packed_resources_import_profile(&self) -> &Option<PathBuf> {
+        &self.packed_resources_import_profile
+    }
+
+    fn set_packed_resources_import_profile(&mut self, value: Option<PathBuf>) {
+        self.packed_resources_import_profile = value;
+    }
+
+    fn windows_subsystem(&self) -> WindowsSubsystem {
+        self.windows_subsystem
+    }
+
+    fn set_windows_subsystem(&mut self, value: WindowsSubsystem) {
+        self.windows_subsystem = value;
+    }
+
     fn read_package_root(
         &self,
         logger: &slog::Logger,
         path: &Path,
         packages: &[String],
     ) -> Result<Vec<PythonResource>> {
-        Ok(find_resources(&logger, &**self.distribution, path, None)?
-            .iter()
-            .filter_map(|x| {
-                if x.is_in_packages(packages) {
-                    Some(x.clone())
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<_>>())
+        Ok(find_resources(
+            &logger,
+            &**self.distribution,
+            &self.packaging_policy,
+            path,
+            None,
+        )?
+        .iter()
+        .filter_map(|x| {
+            if x.is_in_packages(packages) {
+                Some(x.clone())
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>())
     }
 
     fn read_virtualenv(&self, logger: &slog::Logger, path: &Path) -> Result<Vec<PythonResource>> {
-        read_virtualenv(logger, &**self.distribution, path)
+        read_virtualenv(logger, &**self.distribution, &self.packaging_policy, path)
     }
 
-    fn setup_py_install(
+    fn read_conda_env(&self, logger: &slog::Logger, path: &Path) -> Result<Vec<PythonResource>> {
+        read_conda_env(logger, &**self.distribution, &self.packaging_policy, path)
+    }
+
+    fn read_conda_packages(
         &self,
         logger: &slog::Logger,
+        paths: &[PathBuf],
+    ) -> Result<Vec<PythonResource>> {
+        read_conda_packages(logger, &**self.distribution, &self.packaging_policy, paths)
+    }
+
+    fn setup_py_install(
+        &mut self,
+        logger: &slog::Logger,
         package_path: &Path,
         verbose: bool,
         extra_envs: &HashMap<String, String>,
         extra_global_arguments: &[String],
     ) -> Result<Vec<PythonResource>> {
-        setup_py_install(
+        self.ensure_not_cross_architecture("setup.py install")?;
+
+        let resources = setup_py_install(
             logger,
             &**self.distribution,
+            &self.packaging_policy,
             self.link_mode,
             package_path,
             verbose,
             extra_envs,
             extra_global_arguments,
-        )
+        )?;
+
+        self.dependency_report
+            .merge(&DependencyReport::from_resources(&resources, &|_| {
+                DistributionKind::Sdist
+            })?);
+
+        Ok(resources)
+    }
+
+    fn pep517_build(
+        &mut self,
+        logger: &slog::Logger,
+        package_path: &Path,
+        verbose: bool,
+        build_requirements: &[String],
+        extra_envs: &HashMap<String, String>,
+    ) -> Result<Vec<PythonResource>> {
+        self.ensure_not_cross_architecture("building packages")?;
+
+        let envs = self.pip_envs(logger, extra_envs)?;
+
+        let resources = pep517_build(
+            logger,
+            &**self.distribution,
+            self.link_mode,
+            package_path,
+            verbose,
+            build_requirements,
+            &envs,
+        )?;
+
+        check_wheel_resources(&resources, &self.distribution.wheel_target())?;
+
+        self.dependency_report
+            .merge(&DependencyReport::from_resources(&resources, &|_| {
+                DistributionKind::Sdist
+            })?);
+
+        Ok(resources)
     }
 
     fn add_python_module_source(
@@ -429,9 +787,29 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         module: &PythonModuleSource,
         location: Option<ConcreteResourceLocation>,
     ) -> Result<()> {
+        // Convert to bytecode if the policy doesn't allow source.
+        if !self.packaging_policy.is_module_source_allowed(&module.name) {
+            for level in self
+                .packaging_policy
+                .get_bytecode_optimize_levels_for_name(&module.name)
+                .to_vec()
+            {
+                self.add_python_module_bytecode_from_source(
+                    &module.as_bytecode_module(level),
+                    location.clone(),
+                )?;
+            }
+
+            return Ok(());
+        }
+
         let location = match location {
             Some(location) => location,
-            None => match self.packaging_policy.get_resources_policy().clone() {
+            None => match self
+                .packaging_policy
+                .get_resources_policy_for_name(&module.name)
+                .clone()
+            {
                 PythonResourcesPolicy::InMemoryOnly
                 | PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative(_) => {
                     ConcreteResourceLocation::InMemory
@@ -439,6 +817,9 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
                 PythonResourcesPolicy::FilesystemRelativeOnly(prefix) => {
                     ConcreteResourceLocation::RelativePath(prefix)
                 }
+                PythonResourcesPolicy::FilesystemAbsolute(path) => {
+                    ConcreteResourceLocation::RelativePath(path.display().to_string())
+                }
             },
         };
 
@@ -453,7 +834,11 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
     ) -> Result<()> {
         let location = match location {
             Some(location) => location,
-            None => match self.packaging_policy.get_resources_policy().clone() {
+            None => match self
+                .packaging_policy
+                .get_resources_policy_for_name(&module.name)
+                .clone()
+            {
                 PythonResourcesPolicy::InMemoryOnly
                 | PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative(_) => {
                     ConcreteResourceLocation::InMemory
@@ -461,6 +846,9 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
                 PythonResourcesPolicy::FilesystemRelativeOnly(prefix) => {
                     ConcreteResourceLocation::RelativePath(prefix)
                 }
+                PythonResourcesPolicy::FilesystemAbsolute(path) => {
+                    ConcreteResourceLocation::RelativePath(path.display().to_string())
+                }
             },
         };
 
@@ -475,7 +863,11 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
     ) -> Result<()> {
         let location = match location {
             Some(location) => location,
-            None => match self.packaging_policy.get_resources_policy().clone() {
+            None => match self
+                .packaging_policy
+                .get_resources_policy_for_name(&resource.leaf_package)
+                .clone()
+            {
                 PythonResourcesPolicy::InMemoryOnly
                 | PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative(_) => {
                     ConcreteResourceLocation::InMemory
@@ -483,6 +875,9 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
                 PythonResourcesPolicy::FilesystemRelativeOnly(prefix) => {
                     ConcreteResourceLocation::RelativePath(prefix)
                 }
+                PythonResourcesPolicy::FilesystemAbsolute(path) => {
+                    ConcreteResourceLocation::RelativePath(path.display().to_string())
+                }
             },
         };
 
@@ -497,7 +892,11 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
     ) -> Result<()> {
         let location = match location {
             Some(location) => location,
-            None => match self.packaging_policy.get_resources_policy().clone() {
+            None => match self
+                .packaging_policy
+                .get_resources_policy_for_name(&resource.package)
+                .clone()
+            {
                 PythonResourcesPolicy::InMemoryOnly
                 | PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative(_) => {
                     ConcreteResourceLocation::InMemory
@@ -505,6 +904,9 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
                 PythonResourcesPolicy::FilesystemRelativeOnly(prefix) => {
                     ConcreteResourceLocation::RelativePath(prefix)
                 }
+                PythonResourcesPolicy::FilesystemAbsolute(path) => {
+                    ConcreteResourceLocation::RelativePath(path.display().to_string())
+                }
             },
         };
 
@@ -518,6 +920,17 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         extension_module: &PythonExtensionModule,
         location: Option<ConcreteResourceLocation>,
     ) -> Result<()> {
+        let disallowed = self
+            .packaging_policy
+            .disallowed_dynamic_libraries(extension_module);
+        if !extension_module.is_minimally_required() && !disallowed.is_empty() {
+            return Err(anyhow!(
+                "extension module {} links against dynamic libraries not allowed by the packaging policy: {}",
+                extension_module.name,
+                disallowed.join(", ")
+            ));
+        }
+
         // Whether we can load extension modules as standalone shared library files.
         let can_load_standalone = self.distribution.is_extension_module_file_loadable();
 
@@ -540,21 +953,28 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         // TODO consider allowing this if object files are present.
         let can_link_standalone = extension_module.shared_library.is_some();
 
+        let resources_policy = self
+            .packaging_policy
+            .get_extension_module_resources_policy(&extension_module.name)
+            .clone();
+
         // Whether the resources policy prefers in-memory loading.
-        let policy_want_memory = match self.packaging_policy.clone().get_resources_policy() {
+        let policy_want_memory = match &resources_policy {
             PythonResourcesPolicy::InMemoryOnly => true,
             PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative(_) => true,
             PythonResourcesPolicy::FilesystemRelativeOnly(_) => false,
+            PythonResourcesPolicy::FilesystemAbsolute(_) => false,
         };
 
         let relative_path = match location {
             Some(ConcreteResourceLocation::RelativePath(ref prefix)) => Some(prefix.clone()),
             Some(ConcreteResourceLocation::InMemory) => None,
-            None => match self.packaging_policy.clone().get_resources_policy() {
+            None => match &resources_policy {
                 PythonResourcesPolicy::FilesystemRelativeOnly(prefix) => Some(prefix.clone()),
                 PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative(prefix) => {
                     Some(prefix.clone())
                 }
+                PythonResourcesPolicy::FilesystemAbsolute(path) => Some(path.display().to_string()),
                 PythonResourcesPolicy::InMemoryOnly => None,
             },
         };
@@ -568,17 +988,17 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         let require_in_memory = if let Some(ConcreteResourceLocation::InMemory) = location {
             true
         } else {
-            self.packaging_policy.clone().get_resources_policy()
-                == &PythonResourcesPolicy::InMemoryOnly
+            resources_policy == PythonResourcesPolicy::InMemoryOnly
         };
 
         let require_filesystem = if let Some(ConcreteResourceLocation::RelativePath(_)) = location {
             true
         } else {
-            match *self.packaging_policy.clone().get_resources_policy() {
+            match resources_policy {
                 PythonResourcesPolicy::FilesystemRelativeOnly(_) => true,
                 PythonResourcesPolicy::InMemoryOnly => false,
                 PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative(_) => false,
+                PythonResourcesPolicy::FilesystemAbsolute(_) => true,
             }
         };
 
@@ -690,6 +1110,21 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         Ok(())
     }
 
+    fn add_python_editable_install(&mut self, editable: &PythonEditableInstall) -> Result<()> {
+        let path = editable
+            .path
+            .canonicalize()
+            .unwrap_or_else(|_| editable.path.clone());
+        let path = format!("{}", path.display());
+
+        if !self.config.sys_paths.contains(&path) {
+            self.config.sys_paths.push(path);
+        }
+        self.config.filesystem_importer = true;
+
+        Ok(())
+    }
+
     fn filter_resources_from_files(
         &mut self,
         logger: &slog::Logger,
@@ -719,29 +1154,101 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         self.config.raw_allocator == RawAllocator::Jemalloc
     }
 
+    fn requires_mimalloc(&self) -> bool {
+        self.config.raw_allocator == RawAllocator::Mimalloc
+    }
+
+    fn requires_snmalloc(&self) -> bool {
+        self.config.raw_allocator == RawAllocator::Snmalloc
+    }
+
+    fn requires_server(&self) -> bool {
+        match self.config.run_mode {
+            RunMode::Server { .. } => true,
+            RunMode::Noop
+            | RunMode::Repl
+            | RunMode::Module { .. }
+            | RunMode::ModuleWithArgs { .. }
+            | RunMode::MainModule { .. }
+            | RunMode::Eval { .. }
+            | RunMode::EvalMany { .. }
+            | RunMode::File { .. }
+            | RunMode::FileFromArgs
+            | RunMode::JupyterKernel { .. } => false,
+        }
+    }
+
     fn to_embedded_python_context(
         &self,
         logger: &slog::Logger,
         opt_level: &str,
     ) -> Result<EmbeddedPythonContext> {
-        let mut file_seen = false;
-        for module in self.resources_collector.find_dunder_file()? {
-            file_seen = true;
-            warn!(logger, "warning: {} contains __file__", module);
+        // Modules imported from memory only lack __file__ if configured so.
+        if self.config.in_memory_module_file == InMemoryModuleFile::None {
+            let mut file_seen = false;
+            for module in self.resources_collector.find_dunder_file()? {
+                file_seen = true;
+                warn!(logger, "warning: {} contains __file__", module);
+            }
+
+            if file_seen {
+                warn!(logger, "__file__ was encountered in some embedded modules");
+                warn!(
+                    logger,
+                    "__file__ is not set for modules imported from memory and this may create problems at run-time"
+                );
+                warn!(
+                    logger,
+                    "Set PythonInterpreterConfig.in_memory_module_file to 'uri' or 'path' to set it"
+                );
+            }
         }
 
-        if file_seen {
-            warn!(logger, "__file__ was encountered in some embedded modules");
-            warn!(
-                logger,
-                "PyOxidizer does not set __file__ and this may create problems at run-time"
-            );
-            warn!(
+        let excluded_resources = self.packaging_policy.filter_report();
+
+        for excluded in &excluded_resources {
+            info!(
                 logger,
-                "See https://github.com/indygreg/PyOxidizer/issues/69 for more"
+                "excluded {} {}: {}", excluded.resource_type, excluded.name, excluded.reason
             );
         }
 
+        // Data files read via importlib.resources or pkgutil.get_data() that
+        // aren't packaged work in a virtualenv but fail at run-time.
+        for (module, references) in self.resources_collector.find_package_data_references()? {
+            for reference in references {
+                if self.resources_collector.has_package_data(&reference) {
+                    continue;
+                }
+
+                let locations = reference.resource_locations();
+                let exclusion = excluded_resources.iter().find(|excluded| {
+                    excluded.resource_type == "package resource"
+                        && locations
+                            .iter()
+                            .any(|(package, name)| excluded.name == format!("{}.{}", package, name))
+                });
+
+                match exclusion {
+                    Some(excluded) => warn!(
+                        logger,
+                        "warning: {} reads package data {}/{}, which was excluded: {}",
+                        module,
+                        reference.package,
+                        reference.resource,
+                        excluded.reason
+                    ),
+                    None => warn!(
+                        logger,
+                        "warning: {} reads package data {}/{}, which is not packaged",
+                        module,
+                        reference.package,
+                        reference.resource
+                    ),
+                }
+            }
+        }
+
         let compiled_resources = {
             let mut compiler = BytecodeCompiler::new(&self.python_exe)?;
             self.resources_collector.compile_resources(&mut compiler)?
@@ -749,25 +1256,119 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
 
         let mut extra_files = FileManifest::default();
 
+        let installed_attributes = self.packaging_policy.get_installed_resource_attributes();
+
         for (path, location, executable) in &compiled_resources.extra_files {
+            let install_path = staged_install_path(path);
+
             extra_files.add_file(
-                path,
+                &install_path,
                 &FileContent {
                     data: location.resolve()?,
                     executable: *executable,
                 },
             )?;
+
+            if installed_attributes != &InstalledResourceAttributes::default() {
+                extra_files.set_file_attributes(
+                    &install_path,
+                    &FileAttributes {
+                        mode: if *executable {
+                            installed_attributes.executable_mode
+                        } else {
+                            installed_attributes.file_mode
+                        },
+                        mtime: installed_attributes.mtime,
+                        owner: installed_attributes.owner.clone(),
+                        group: installed_attributes.group.clone(),
+                    },
+                )?;
+            }
         }
 
         let mut module_names = Vec::new();
 
-        for name in compiled_resources.resources.keys() {
+        for name in compiled_resources.resource_names() {
             module_names.write_all(name.as_bytes())?;
             module_names.write_all(b"\n")?;
         }
 
-        let mut resources = Vec::new();
-        compiled_resources.write_packed_resources_v1(&mut resources)?;
+        let encryption_key = match &self.packed_resources_encryption_key {
+            Some(path) => Some(std::fs::read(path).map_err(|e| {
+                anyhow!(
+                    "unable to read packed resources encryption key {}: {}",
+                    path.display(),
+                    e
+                )
+            })?),
+            None => None,
+        };
+
+        let layout = resolve_resources_layout(
+            self.packed_resources_alignment,
+            self.packed_resources_import_profile.as_deref(),
+        )?;
+
+        // Resources are resolved one at a time and written to a temporary
+        // file, so their data is never held in memory all at once.
+        let mut resources = tempfile::tempfile()?;
+        let mut writer = BufWriter::new(&mut resources);
+        let (report, compression_report) = compiled_resources.write_packed_resources_v1(
+            &mut writer,
+            &WriteOptions {
+                interior_padding: None,
+                deduplicate: self.packaging_policy.get_deduplicate_resources(),
+                compression: self.packaging_policy.get_resources_compression().cloned(),
+                encryption_key,
+                layout: layout.unwrap_or_default(),
+            },
+        )?;
+        writer.flush()?;
+        drop(writer);
+
+        if report.duplicate_blobs > 0 {
+            info!(
+                logger,
+                "deduplicated {} resource blobs, saving {} bytes",
+                report.duplicate_blobs,
+                report.saved_bytes
+            );
+        }
+
+        if compression_report.compressed_blobs > 0 {
+            info!(
+                logger,
+                "compressed {} resource blobs, saving {} bytes",
+                compression_report.compressed_blobs,
+                compression_report.saved_bytes
+            );
+        }
+
+        let mut provenance_sizes = compiled_resources
+            .packed_resource_sizes_by_provenance()?
+            .into_iter()
+            .collect::<Vec<_>>();
+        provenance_sizes.sort_by(|a, b| b.1.cmp(&a.1));
+        for (provenance, size) in provenance_sizes {
+            info!(logger, "{} bytes of resources from {}", size, provenance);
+        }
+
+        let size_report = self.packaging_policy.check_resource_sizes(
+            &compiled_resources.packed_resource_sizes()?,
+            resources.metadata()?.len(),
+        );
+
+        if size_report.is_violation() {
+            for line in size_report.describe() {
+                warn!(logger, "{}", line);
+            }
+
+            if self.packaging_policy.get_resource_size_budget_action()
+                == ResourceSizeBudgetAction::Error
+            {
+                return Err(anyhow!("resource size budget exceeded"));
+            }
+        }
 
         let linking_info = self.resolve_python_linking_info(logger, opt_level)?;
 
@@ -783,8 +1384,121 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
             }
         }
 
+        let mut config = self.config.clone();
+
+        // The encryption key isn't embedded in the binary. Unless the
+        // configuration says otherwise, it is read from the file named by
+        // this environment variable at run-time.
+        if self.packed_resources_encryption_key.is_some()
+            && config.packed_resources_key_env.is_none()
+        {
+            config.packed_resources_key_env = Some("PYEMBED_PACKED_RESOURCES_KEY_FILE".to_string());
+        }
+
+        let uses_tkinter = self.extension_build_contexts.contains_key("_tkinter")
+            || self.iter_resources().any(|(name, _)| name == "_tkinter");
+
+        match (&self.tcl_files_path, uses_tkinter) {
+            (Some(tcl_files_path), true) => {
+                let tcl_files = self.distribution.tcl_files()?;
+
+                if tcl_files.is_empty() {
+                    warn!(
+                        logger,
+                        "warning: distribution does not provide Tcl/Tk library files for tkinter"
+                    );
+                }
+
+                for (rel_path, path) in tcl_files {
+                    extra_files.add_file(
+                        &Path::new(tcl_files_path).join(rel_path),
+                        &FileContent::try_from(path.as_path())?,
+                    )?;
+                }
+
+                if config.tcl_library.is_none() {
+                    if let Some(name) = self.distribution.tcl_library_dir_name() {
+                        config.tcl_library = Some(format!("$ORIGIN/{}/{}", tcl_files_path, name));
+                    }
+                }
+            }
+            (Some(_), false) => {
+                info!(
+                    logger,
+                    "tkinter is not packaged; not installing Tcl/Tk library files"
+                );
+            }
+            (None, true) => {
+                warn!(
+                    logger,
+                    "warning: tkinter is packaged but tcl_files_path is not set; tkinter will not find Tcl/Tk library files at run-time"
+                );
+            }
+            (None, false) => {}
+        }
+
+        // Packed resources written to a standalone file are memory mapped at
+        // run-time. Files already in the config take precedence, allowing
+        // them to override individual resources. The data embedded in the
+        // binary is empty, which pyembed ignores.
+        if self.packed_resources_signing_key.is_some() && self.packed_resources_path.is_none() {
+            return Err(anyhow!(
+                "packed_resources_signing_key requires packed_resources_path"
+            ));
+        }
+
+        if self.packed_resources_signing_key.is_some() && !config.zip_archives.is_empty() {
+            return Err(anyhow!(
+                "packed_resources_signing_key can't be used with zip_archives"
+            ));
+        }
+
+        let resources = if let Some(packed_resources_path) = &self.packed_resources_path {
+            // The file manifest holds file content in memory.
+            let mut data = Vec::new();
+            resources.seek(SeekFrom::Start(0))?;
+            resources.read_to_end(&mut data)?;
+
+            if let Some(key_path) = &self.packed_resources_signing_key {
+                let secret_key = std::fs::read(key_path).map_err(|e| {
+                    anyhow!(
+                        "unable to read packed resources signing key {}: {}",
+                        key_path.display(),
+                        e
+                    )
+                })?;
+
+                extra_files.add_file(
+                    Path::new(&format!("{}.sig", packed_resources_path)),
+                    &FileContent {
+                        data: sign_packed_resources(&data, &secret_key)?,
+                        executable: false,
+                    },
+                )?;
+
+                config.packed_resources_public_key =
+                    Some(packed_resources_public_key(&secret_key)?);
+            }
+
+            extra_files.add_file(
+                Path::new(packed_resources_path),
+                &FileContent {
+                    data,
+                    executable: false,
+                },
+            )?;
+
+            config
+                .packed_resources_files
+                .push(format!("$ORIGIN/{}", packed_resources_path));
+
+            None
+        } else {
+            Some(resources)
+        };
+
         Ok(EmbeddedPythonContext {
-            config: self.config.clone(),
+            config,
             linking_info,
             module_names,
             resources,
@@ -800,6 +1514,7 @@ pub mod tests {
     use {
         super::*,
         crate::py_packaging::distribution::{BinaryLibpythonLinkMode, DistributionFlavor},
+        crate::py_packaging::standalone_distribution::BROKEN_EXTENSIONS_MUSL,
         crate::python_distributions::PYTHON_DISTRIBUTIONS,
         crate::testutil::*,
         lazy_static::lazy_static,
@@ -809,6 +1524,18 @@ pub mod tests {
         std::iter::FromIterator,
     };
 
+    /// Read the packed resources data to embed in the binary.
+    fn embedded_resources_data(embedded: &EmbeddedPythonContext) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+
+        if let Some(mut resources) = embedded.resources.as_ref() {
+            resources.seek(SeekFrom::Start(0))?;
+            resources.read_to_end(&mut data)?;
+        }
+
+        Ok(data)
+    }
+
     lazy_static! {
         pub static ref WINDOWS_TARGET_TRIPLES: Vec<&'static str> =
             vec!["i686-pc-windows-msvc", "x86_64-pc-windows-msvc"];
@@ -830,6 +1557,7 @@ pub mod tests {
                 licenses: None,
                 license_texts: None,
                 license_public_domain: None,
+                provenance: None,
             };
 
         /// An extension module represented by only object files.
@@ -849,6 +1577,7 @@ pub mod tests {
                 licenses: None,
                 license_texts: None,
                 license_public_domain: None,
+                provenance: None,
         };
 
         /// An extension module with both a shared library and object files.
@@ -868,6 +1597,7 @@ pub mod tests {
                 licenses: None,
                 license_texts: None,
                 license_public_domain: None,
+                provenance: None,
         };
     }
 
@@ -917,6 +1647,7 @@ pub mod tests {
             let config = EmbeddedPythonConfig::default();
 
             StandalonePythonExecutableBuilder::from_distribution(
+                distribution.clone(),
                 distribution,
                 self.host_triple.clone(),
                 self.target_triple.clone(),
@@ -1026,13 +1757,38 @@ pub mod tests {
     fn test_write_embedded_files() -> Result<()> {
         let logger = get_logger()?;
         let embedded = get_embedded(&logger)?;
-        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let temp_dir = TempDir::new("pyoxidizer-test")?;
 
         embedded.write_files(temp_dir.path())?;
 
         Ok(())
     }
 
+    #[test]
+    fn test_add_python_editable_install() -> Result<()> {
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut builder = options.new_builder()?;
+
+        let editable = PythonEditableInstall {
+            name: "foo".to_string(),
+            version: "1.0".to_string(),
+            path: PathBuf::from("/nonexistent/foo/src"),
+            top_level: vec!["foo".to_string()],
+        };
+
+        builder.add_python_editable_install(&editable)?;
+        builder.add_python_editable_install(&editable)?;
+
+        assert!(builder.config.filesystem_importer);
+        assert_eq!(
+            builder.config.sys_paths,
+            vec![format!("{}", editable.path.display())]
+        );
+        assert!(!builder.iter_resources().any(|(name, _)| name == "foo"));
+
+        Ok(())
+    }
+
     #[test]
     fn test_minimal_extensions_present() -> Result<()> {
         let options = StandalonePythonExecutableBuilderOptions::default();
@@ -1549,9 +2305,12 @@ pub mod tests {
         let builder = options.new_builder()?;
 
         // All extensions for musl Linux are built-in because dynamic linking
-        // not possible.
+        // not possible. Extensions requiring dynamic loading are excluded.
         for name in builder.distribution.extension_modules.keys() {
-            assert!(builder.extension_build_contexts.keys().any(|e| name == e));
+            assert_eq!(
+                builder.extension_build_contexts.keys().any(|e| name == e),
+                !BROKEN_EXTENSIONS_MUSL.contains(name)
+            );
         }
 
         Ok(())
@@ -2796,4 +3555,305 @@ pub mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_filesystem_absolute_policy() -> Result<()> {
+        let options = StandalonePythonExecutableBuilderOptions {
+            extension_module_filter: ExtensionModuleFilter::Minimal,
+            resources_policy: PythonResourcesPolicy::FilesystemAbsolute(PathBuf::from(
+                "/usr/lib/testapp",
+            )),
+            ..StandalonePythonExecutableBuilderOptions::default()
+        };
+
+        let mut builder = options.new_builder()?;
+
+        builder.add_python_module_source(
+            &PythonModuleSource {
+                name: "foo".to_string(),
+                source: DataLocation::Memory(vec![]),
+                is_package: false,
+                cache_tag: builder.cache_tag().to_string(),
+                is_stdlib: false,
+                is_test: false,
+                provenance: None,
+            },
+            None,
+        )?;
+
+        let resources = builder.iter_resources().collect::<BTreeMap<_, _>>();
+        let foo = resources.get(&"foo".to_string()).unwrap();
+        assert!(foo.in_memory_source.is_none());
+        assert_eq!(
+            foo.relative_path_module_source,
+            Some(("/usr/lib/testapp".to_string(), DataLocation::Memory(vec![])))
+        );
+
+        assert_eq!(
+            staged_install_path(Path::new("/usr/lib/testapp/foo.py")),
+            PathBuf::from("usr/lib/testapp/foo.py")
+        );
+        assert_eq!(
+            staged_install_path(Path::new("lib/foo.py")),
+            PathBuf::from("lib/foo.py")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_installed_resource_attributes() -> Result<()> {
+        let logger = get_logger()?;
+
+        let options = StandalonePythonExecutableBuilderOptions {
+            extension_module_filter: ExtensionModuleFilter::Minimal,
+            resources_policy: PythonResourcesPolicy::FilesystemRelativeOnly("lib".to_string()),
+            ..StandalonePythonExecutableBuilderOptions::default()
+        };
+
+        let mut builder = options.new_builder()?;
+        builder
+            .packaging_policy
+            .set_installed_resource_attributes(InstalledResourceAttributes {
+                file_mode: Some(0o644),
+                executable_mode: Some(0o755),
+                mtime: Some(1_000_000_000),
+                owner: Some("root".to_string()),
+                group: None,
+            });
+
+        builder.add_python_module_source(
+            &PythonModuleSource {
+                name: "foo".to_string(),
+                source: DataLocation::Memory(vec![]),
+                is_package: false,
+                cache_tag: builder.cache_tag().to_string(),
+                is_stdlib: false,
+                is_test: false,
+                provenance: None,
+            },
+            None,
+        )?;
+
+        let embedded = builder.to_embedded_python_context(&logger, "0")?;
+
+        assert_eq!(
+            embedded
+                .extra_files
+                .get_file_attributes(Path::new("lib/foo.py")),
+            Some(&FileAttributes {
+                mode: Some(0o644),
+                mtime: Some(1_000_000_000),
+                owner: Some("root".to_string()),
+                group: None,
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tcl_files_path() -> Result<()> {
+        let logger = get_logger()?;
+
+        let options = StandalonePythonExecutableBuilderOptions {
+            extension_module_filter: ExtensionModuleFilter::All,
+            ..StandalonePythonExecutableBuilderOptions::default()
+        };
+
+        let mut builder = options.new_builder()?;
+        builder.set_tcl_files_path(Some("lib".to_string()));
+
+        let embedded = builder.to_embedded_python_context(&logger, "0")?;
+
+        for (rel_path, _) in builder.distribution.tcl_files()? {
+            assert!(embedded
+                .extra_files
+                .has_path(&Path::new("lib").join(rel_path)));
+        }
+
+        if let Some(name) = builder.distribution.tcl_library_dir_name() {
+            assert_eq!(
+                embedded.config.tcl_library,
+                Some(format!("$ORIGIN/lib/{}", name))
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_packed_resources_path() -> Result<()> {
+        let logger = get_logger()?;
+
+        let options = StandalonePythonExecutableBuilderOptions::default();
+
+        let mut builder = options.new_builder()?;
+        let embedded = builder.to_embedded_python_context(&logger, "0")?;
+        assert!(!embedded_resources_data(&embedded)?.is_empty());
+        assert!(embedded.config.packed_resources_files.is_empty());
+
+        builder.set_packed_resources_path(Some("app.resources".to_string()));
+
+        let embedded = builder.to_embedded_python_context(&logger, "0")?;
+        assert!(embedded.resources.is_none());
+        assert!(embedded.extra_files.has_path(Path::new("app.resources")));
+        assert_eq!(
+            embedded.config.packed_resources_files,
+            vec!["$ORIGIN/app.resources".to_string()]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_packed_resources_signing_key() -> Result<()> {
+        let logger = get_logger()?;
+        let temp_dir = TempDir::new("pyoxidizer-test")?;
+        let key_path = temp_dir.path().join("signing.key");
+        std::fs::write(&key_path, &[42u8; 32])?;
+
+        let options = StandalonePythonExecutableBuilderOptions::default();
+
+        let mut builder = options.new_builder()?;
+        builder.set_packed_resources_signing_key(Some(key_path));
+        assert!(builder.to_embedded_python_context(&logger, "0").is_err());
+
+        builder.set_packed_resources_path(Some("app.resources".to_string()));
+
+        let embedded = builder.to_embedded_python_context(&logger, "0")?;
+        assert_eq!(
+            embedded.config.packed_resources_public_key,
+            Some(packed_resources_public_key(&[42u8; 32])?)
+        );
+
+        builder.config.zip_archives = vec!["$ORIGIN/app.zip".to_string()];
+        assert!(builder.to_embedded_python_context(&logger, "0").is_err());
+
+        let files = embedded.extra_files.entries().collect::<BTreeMap<_, _>>();
+        let data = &files[&PathBuf::from("app.resources")].data;
+        let signature = &files[&PathBuf::from("app.resources.sig")].data;
+        python_packed_resources::parser::verify_packed_resources(
+            data,
+            signature,
+            embedded
+                .config
+                .packed_resources_public_key
+                .as_ref()
+                .unwrap(),
+        )
+        .map_err(|e| anyhow!(e))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_packed_resources_encryption_key() -> Result<()> {
+        let logger = get_logger()?;
+        let temp_dir = TempDir::new("pyoxidizer-test")?;
+        let key_path = temp_dir.path().join("encryption.key");
+        std::fs::write(&key_path, &[42u8; 32])?;
+
+        let options = StandalonePythonExecutableBuilderOptions::default();
+
+        let mut builder = options.new_builder()?;
+        builder.set_packed_resources_encryption_key(Some(key_path));
+
+        let embedded = builder.to_embedded_python_context(&logger, "0")?;
+        assert_eq!(
+            embedded.config.packed_resources_key_env,
+            Some("PYEMBED_PACKED_RESOURCES_KEY_FILE".to_string())
+        );
+        let data = embedded_resources_data(&embedded)?;
+
+        assert_eq!(
+            python_packed_resources::parser::load_resources(&data).err(),
+            Some("packed resources data is encrypted; a key is required")
+        );
+
+        let resources =
+            python_packed_resources::parser::load_resources_encrypted(&data, &[42u8; 32])
+                .map_err(|e| anyhow!(e))?
+                .collect::<Result<Vec<_>, &'static str>>()
+                .map_err(|e| anyhow!(e))?;
+        assert!(resources.iter().any(|r| r.name == "encodings"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_packed_resources_layout() -> Result<()> {
+        let logger = get_logger()?;
+        let temp_dir = TempDir::new("pyoxidizer-test")?;
+        let profile_path = temp_dir.path().join("profile.json");
+        std::fs::write(
+            &profile_path,
+            b"{\"phases\": [], \"imports\": [{\"module\": \"json\"}, {\"module\": \"encodings\"}]}",
+        )?;
+
+        let options = StandalonePythonExecutableBuilderOptions::default();
+
+        let mut builder = options.new_builder()?;
+        builder.set_packed_resources_alignment(Some(4096));
+        builder.set_packed_resources_import_profile(Some(profile_path));
+
+        let embedded = builder.to_embedded_python_context(&logger, "0")?;
+        let data = embedded_resources_data(&embedded)?;
+
+        let resources =
+            python_packed_resources::parser::load_resources(&data).map_err(|e| anyhow!(e))?;
+        assert!(!resources.prefetch_ranges().is_empty());
+        for (offset, _) in resources.prefetch_ranges() {
+            assert_eq!(offset % 4096, 0);
+        }
+
+        let names = resources
+            .map(|r| r.map(|r| r.name.to_string()))
+            .collect::<Result<Vec<_>, &'static str>>()
+            .map_err(|e| anyhow!(e))?;
+        assert_eq!(&names[0..2], &["json".to_string(), "encodings".to_string()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bytecode_only_converts_source() -> Result<()> {
+        let options = StandalonePythonExecutableBuilderOptions {
+            extension_module_filter: ExtensionModuleFilter::Minimal,
+            ..StandalonePythonExecutableBuilderOptions::default()
+        };
+
+        let mut builder = options.new_builder()?;
+        builder.packaging_policy.set_bytecode_only(true);
+        builder
+            .packaging_policy
+            .add_bytecode_only_source_package("keep_source");
+
+        for name in &["no_source", "keep_source"] {
+            builder.add_python_module_source(
+                &PythonModuleSource {
+                    name: name.to_string(),
+                    source: DataLocation::Memory(vec![]),
+                    is_package: false,
+                    cache_tag: builder.cache_tag().to_string(),
+                    is_stdlib: false,
+                    is_test: false,
+                    provenance: None,
+                },
+                None,
+            )?;
+        }
+
+        let resources = builder.iter_resources().collect::<BTreeMap<_, _>>();
+
+        let no_source = resources.get(&"no_source".to_string()).unwrap();
+        assert!(no_source.in_memory_source.is_none());
+        assert!(no_source.in_memory_bytecode.is_some());
+
+        let keep_source = resources.get(&"keep_source".to_string()).unwrap();
+        assert!(keep_source.in_memory_source.is_some());
+        assert!(keep_source.in_memory_bytecode.is_none());
+
+        Ok(())
+    }
 }
@@ -8,12 +8,19 @@ use {
     super::binary::{LibpythonLinkMode, PythonBinaryBuilder},
     super::config::EmbeddedPythonConfig,
     super::distribution::{
-        is_stdlib_test_package, resolve_python_distribution_from_location, BinaryLibpythonLinkMode,
-        DistributionExtractLock, PythonDistribution, PythonDistributionLocation,
+        default_distribution_location, is_stdlib_test_package,
+        resolve_python_distribution_from_location, BinaryLibpythonLinkMode,
+        DistributionExtractLock, DistributionFlavor, DistributionTrimming, PythonDistribution,
+        PythonDistributionLocation,
     },
     super::distutils::prepare_hacked_distutils,
+    super::installed_distribution::{resolve_installed_distribution, InstalledPythonInfo},
+    super::shared_library::is_binary_compatible_with_target,
+    super::source_build::{
+        is_source_build_directory, resolve_source_build_distribution, SOURCE_BUILD_JSON,
+    },
     super::standalone_builder::StandalonePythonExecutableBuilder,
-    crate::environment::{LINUX_TARGET_TRIPLES, MACOS_TARGET_TRIPLES},
+    crate::environment::{LINUX_TARGET_TRIPLES, MACOS_TARGET_TRIPLES, WINDOWS_TARGET_TRIPLES},
     anyhow::{anyhow, Context, Result},
     copy_dir::copy_dir,
     lazy_static::lazy_static,
@@ -26,6 +33,7 @@ use {
         DataLocation, LibraryDependency, PythonExtensionModule, PythonExtensionModuleVariants,
         PythonModuleSource, PythonPackageResource, PythonResource,
     },
+    python_packaging::wheel::{macos_platform_version, WheelTarget},
     serde::{Deserialize, Serialize},
     slog::{info, warn},
     std::collections::{BTreeMap, HashMap},
@@ -46,6 +54,11 @@ const PYTHON_EXE_BASENAME: &str = "python3";
 #[cfg(windows)]
 const PIP_EXE_BASENAME: &str = "pip3.exe";
 
+/// Oldest glibc version Linux glibc distributions run on.
+///
+/// python-build-standalone builds them in Debian 8.
+const LINUX_GLIBC_VERSION: (u32, u32) = (2, 19);
+
 #[cfg(unix)]
 const PIP_EXE_BASENAME: &str = "pip3";
 
@@ -60,6 +73,15 @@ lazy_static! {
         "nis".to_string(),
     ];
 
+    /// Distribution extensions requiring `dlopen()` on musl libc.
+    ///
+    /// Binaries targeting musl libc are fully statically linked and can't
+    /// load shared libraries. So these will never be packaged.
+    pub static ref BROKEN_EXTENSIONS_MUSL: Vec<String> = vec![
+        // ctypes calls dlopen() when imported.
+        "_ctypes".to_string(),
+    ];
+
     /// Distribution extensions with known problems on macOS.
     ///
     /// These will never be packaged.
@@ -69,6 +91,38 @@ lazy_static! {
         "_curses_panel".to_string(),
         "readline".to_string(),
     ];
+
+    /// Libraries and frameworks provided by macOS.
+    ///
+    /// Extension modules only linking against these are allowed by the
+    /// `no-gpl` extension module filter.
+    pub static ref SYSTEM_LIBRARIES_MACOS: Vec<String> = vec![
+        "CoreFoundation".to_string(),
+        "SystemConfiguration".to_string(),
+        "dl".to_string(),
+        "m".to_string(),
+        "pthread".to_string(),
+    ];
+
+    /// Libraries provided by Windows.
+    ///
+    /// Extension modules only linking against these are allowed by the
+    /// `no-gpl` extension module filter.
+    pub static ref SYSTEM_LIBRARIES_WINDOWS: Vec<String> = vec![
+        "advapi32".to_string(),
+        "cabinet".to_string(),
+        "crypt32".to_string(),
+        "iphlpapi".to_string(),
+        "kernel32".to_string(),
+        "msi".to_string(),
+        "ole32".to_string(),
+        "rpcrt4".to_string(),
+        "shell32".to_string(),
+        "user32".to_string(),
+        "version".to_string(),
+        "winmm".to_string(),
+        "ws2_32".to_string(),
+    ];
 }
 
 #[derive(Debug, Deserialize)]
@@ -314,6 +368,62 @@ pub struct LicenseInfo {
     pub license_text: String,
 }
 
+/// Python module sources and non-module resource files in a standard library.
+type StdlibFiles = (
+    BTreeMap<String, PathBuf>,
+    BTreeMap<String, BTreeMap<String, PathBuf>>,
+);
+
+/// Find Python module sources and resource files in a standard library directory.
+///
+/// Files under any directory in `exclude_dirs` are ignored.
+fn scan_stdlib(
+    stdlib_path: &Path,
+    cache_tag: &str,
+    module_suffixes: &PythonModuleSuffixes,
+    exclude_dirs: &[PathBuf],
+) -> Result<StdlibFiles> {
+    let mut py_modules: BTreeMap<String, PathBuf> = BTreeMap::new();
+    let mut resources: BTreeMap<String, BTreeMap<String, PathBuf>> = BTreeMap::new();
+
+    let is_excluded = |path: &Path| exclude_dirs.iter().any(|d| path.starts_with(d));
+
+    for entry in find_python_resources(stdlib_path, cache_tag, module_suffixes) {
+        match entry? {
+            PythonResource::Resource(resource) => {
+                let path = match resource.data {
+                    DataLocation::Path(path) => path,
+                    DataLocation::Memory(_) => {
+                        return Err(anyhow!("should not have received in-memory resource data"))
+                    }
+                };
+
+                if is_excluded(&path) {
+                    continue;
+                }
+
+                resources
+                    .entry(resource.leaf_package.clone())
+                    .or_insert_with(BTreeMap::new)
+                    .insert(resource.relative_name.clone(), path);
+            }
+            PythonResource::ModuleSource(source) => match source.source {
+                DataLocation::Path(path) => {
+                    if !is_excluded(&path) {
+                        py_modules.insert(source.name.clone(), path);
+                    }
+                }
+                DataLocation::Memory(_) => {
+                    return Err(anyhow!("should not have received in-memory source data"))
+                }
+            },
+            _ => {}
+        };
+    }
+
+    Ok((py_modules, resources))
+}
+
 /// Describes how libpython is linked in a standalone distribution.
 #[derive(Clone, Debug, PartialEq)]
 pub enum StandaloneDistributionLinkMode {
@@ -375,6 +485,9 @@ pub struct StandaloneDistribution {
     /// Path to Tcl library files.
     pub tcl_library_path: Option<PathBuf>,
 
+    /// Directories under `tcl_library_path` holding Tcl/Tk library files.
+    pub tcl_library_paths: Option<Vec<String>>,
+
     /// Object files providing the core Python implementation.
     ///
     /// Keys are relative paths. Values are filesystem paths.
@@ -431,6 +544,11 @@ pub struct StandaloneDistribution {
 
     /// Suffixes for Python module types.
     module_suffixes: PythonModuleSuffixes,
+
+    /// Whether the distribution is a Python installation not managed by us.
+    ///
+    /// We don't install packages (like pip) into these distributions.
+    externally_managed: bool,
 }
 
 impl StandaloneDistribution {
@@ -439,6 +557,14 @@ impl StandaloneDistribution {
         location: &PythonDistributionLocation,
         distributions_dir: &Path,
     ) -> Result<Self> {
+        if let PythonDistributionLocation::Installed {
+            python_exe,
+            target_triple,
+        } = location
+        {
+            return resolve_installed_distribution(logger, Path::new(python_exe), target_triple);
+        }
+
         let (archive_path, extract_path) =
             resolve_python_distribution_from_location(logger, location, distributions_dir)?;
 
@@ -489,7 +615,8 @@ impl StandaloneDistribution {
             // The content of the distribution could change between runs. But caching
             // the extraction does keep things fast.
             let test_path = extract_dir.join("python").join("PYTHON.json");
-            if !test_path.exists() {
+            let source_build_path = extract_dir.join("python").join(SOURCE_BUILD_JSON);
+            if !test_path.exists() && !source_build_path.exists() {
                 std::fs::create_dir_all(extract_dir)?;
                 let absolute_path = std::fs::canonicalize(extract_dir)?;
 
@@ -580,6 +707,10 @@ impl StandaloneDistribution {
     /// Obtain an instance by scanning a directory containing an extracted distribution.
     #[allow(clippy::cognitive_complexity)]
     pub fn from_directory(dist_dir: &Path) -> Result<Self> {
+        if is_source_build_directory(dist_dir) {
+            return resolve_source_build_distribution(dist_dir);
+        }
+
         let mut objs_core: BTreeMap<PathBuf, PathBuf> = BTreeMap::new();
         let mut links_core: Vec<LibraryDependency> = Vec::new();
         let mut extension_modules: BTreeMap<String, PythonExtensionModuleVariants> =
@@ -587,8 +718,6 @@ impl StandaloneDistribution {
         let mut includes: BTreeMap<String, PathBuf> = BTreeMap::new();
         let mut libraries: BTreeMap<String, DataLocation> = BTreeMap::new();
         let frozen_c: Vec<u8> = Vec::new();
-        let mut py_modules: BTreeMap<String, PathBuf> = BTreeMap::new();
-        let mut resources: BTreeMap<String, BTreeMap<String, PathBuf>> = BTreeMap::new();
         let mut license_infos: BTreeMap<String, Vec<LicenseInfo>> = BTreeMap::new();
 
         for entry in std::fs::read_dir(dist_dir)? {
@@ -766,6 +895,7 @@ impl StandaloneDistribution {
                         None
                     },
                     license_public_domain: entry.license_public_domain,
+                    provenance: None,
                 });
             }
 
@@ -795,40 +925,12 @@ impl StandaloneDistribution {
             return Err(anyhow!("stdlib path not defined in distribution"));
         };
 
-        for entry in find_python_resources(
+        let (py_modules, resources) = scan_stdlib(
             &stdlib_path,
             &pi.python_implementation_cache_tag,
             &module_suffixes,
-        ) {
-            match entry? {
-                PythonResource::Resource(resource) => {
-                    if !resources.contains_key(&resource.leaf_package) {
-                        resources.insert(resource.leaf_package.clone(), BTreeMap::new());
-                    }
-
-                    resources.get_mut(&resource.leaf_package).unwrap().insert(
-                        resource.relative_name.clone(),
-                        match resource.data {
-                            DataLocation::Path(path) => path,
-                            DataLocation::Memory(_) => {
-                                return Err(anyhow!(
-                                    "should not have received in-memory resource data"
-                                ))
-                            }
-                        },
-                    );
-                }
-                PythonResource::ModuleSource(source) => match source.source {
-                    DataLocation::Path(path) => {
-                        py_modules.insert(source.name.clone(), path);
-                    }
-                    DataLocation::Memory(_) => {
-                        return Err(anyhow!("should not have received in-memory source data"))
-                    }
-                },
-                _ => {}
-            };
-        }
+            &[],
+        )?;
 
         let venv_base = dist_dir.parent().unwrap().join("hacked_base");
 
@@ -863,9 +965,10 @@ impl StandaloneDistribution {
                 None => None,
             },
             tcl_library_path: match pi.tcl_library_path {
-                Some(ref path) => Some(PathBuf::from(path)),
+                Some(ref path) => Some(python_path.join(path)),
                 None => None,
             },
+            tcl_library_paths: pi.tcl_library_paths.clone(),
 
             extension_modules,
             frozen_c,
@@ -882,6 +985,147 @@ impl StandaloneDistribution {
             inittab_cflags: pi.build_info.inittab_cflags,
             cache_tag: pi.python_implementation_cache_tag,
             module_suffixes,
+            externally_managed: false,
+        })
+    }
+
+    /// Construct an instance from a description of an installed Python.
+    ///
+    /// The installed Python is used in place: its standard library, extension
+    /// modules and libpython shared library are referenced from where they
+    /// are installed. Since there are no object files to link, only
+    /// dynamically linking libpython is supported.
+    pub fn from_installed_python(info: &InstalledPythonInfo, target_triple: &str) -> Result<Self> {
+        let get_suffixes = |key: &str| -> Result<Vec<String>> {
+            info.python_suffixes
+                .get(key)
+                .cloned()
+                .ok_or_else(|| anyhow!("installed Python does not define {} suffixes", key))
+        };
+
+        let module_suffixes = PythonModuleSuffixes {
+            source: get_suffixes("source")?,
+            bytecode: get_suffixes("bytecode")?,
+            debug_bytecode: get_suffixes("debug_bytecode")?,
+            optimized_bytecode: get_suffixes("optimized_bytecode")?,
+            extension: get_suffixes("extension")?,
+        };
+
+        let mut extension_modules: BTreeMap<String, PythonExtensionModuleVariants> =
+            BTreeMap::new();
+
+        for name in &info.builtin_module_names {
+            let mut ems = PythonExtensionModuleVariants::default();
+            ems.push(PythonExtensionModule {
+                name: name.clone(),
+                init_fn: Some(format!("PyInit_{}", name)),
+                extension_file_suffix: "".to_string(),
+                shared_library: None,
+                object_file_data: vec![],
+                is_package: false,
+                link_libraries: vec![],
+                is_stdlib: true,
+                builtin_default: true,
+                required: true,
+                variant: Some("default".to_string()),
+                licenses: None,
+                license_texts: None,
+                license_public_domain: None,
+                provenance: None,
+            });
+
+            extension_modules.insert(name.clone(), ems);
+        }
+
+        for (name, path) in &info.extension_modules {
+            let filename = path
+                .file_name()
+                .map(|f| f.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let extension_file_suffix = module_suffixes
+                .extension
+                .iter()
+                .find(|suffix| filename.ends_with(suffix.as_str()))
+                .cloned()
+                .unwrap_or_default();
+
+            let mut ems = PythonExtensionModuleVariants::default();
+            ems.push(PythonExtensionModule {
+                name: name.clone(),
+                init_fn: Some(format!("PyInit_{}", name)),
+                extension_file_suffix,
+                shared_library: Some(DataLocation::Path(path.clone())),
+                object_file_data: vec![],
+                is_package: false,
+                link_libraries: vec![],
+                is_stdlib: true,
+                builtin_default: false,
+                required: false,
+                variant: Some("default".to_string()),
+                licenses: None,
+                license_texts: None,
+                license_public_domain: None,
+                provenance: None,
+            });
+
+            extension_modules.insert(name.clone(), ems);
+        }
+
+        let mut includes: BTreeMap<String, PathBuf> = BTreeMap::new();
+        for entry in walk_tree_files(&info.include_path) {
+            let full_path = entry.path();
+            let rel_path = full_path
+                .strip_prefix(&info.include_path)
+                .expect("unable to strip prefix");
+            includes.insert(
+                String::from(rel_path.to_str().expect("path to string")),
+                full_path.to_path_buf(),
+            );
+        }
+
+        let (py_modules, resources) = scan_stdlib(
+            &info.stdlib_path,
+            &info.cache_tag,
+            &module_suffixes,
+            &info.site_packages_paths,
+        )?;
+
+        Ok(Self {
+            base_dir: info.prefix.clone(),
+            target_triple: target_triple.to_string(),
+            python_tag: info.python_tag.clone(),
+            python_abi_tag: info.python_abi_tag.clone(),
+            python_platform_tag: info.python_platform_tag.clone(),
+            version: info.version.clone(),
+            python_exe: info.python_exe.clone(),
+            stdlib_path: info.stdlib_path.clone(),
+            link_mode: StandaloneDistributionLinkMode::Dynamic,
+            python_symbol_visibility: if target_triple.contains("pc-windows") {
+                "dllexport".to_string()
+            } else {
+                "global-default".to_string()
+            },
+            extension_module_loading: vec!["builtin".to_string(), "shared-library".to_string()],
+            licenses: None,
+            license_path: None,
+            tcl_library_path: None,
+            tcl_library_paths: None,
+            extension_modules,
+            frozen_c: vec![],
+            includes,
+            links_core: vec![],
+            libraries: BTreeMap::new(),
+            objs_core: BTreeMap::new(),
+            libpython_shared_library: info.libpython_shared_library.clone(),
+            py_modules,
+            resources,
+            license_infos: BTreeMap::new(),
+            venv_base: PathBuf::new(),
+            inittab_object: PathBuf::new(),
+            inittab_cflags: vec![],
+            cache_tag: info.cache_tag.clone(),
+            module_suffixes,
+            externally_managed: true,
         })
     }
 
@@ -890,7 +1134,11 @@ impl StandaloneDistribution {
     /// Returns a tuple of bools indicating whether this distribution can
     /// build a static libpython and a dynamically linked libpython.
     pub fn libpython_link_support(&self) -> (bool, bool) {
-        if self.target_triple.contains("pc-windows") {
+        if self.objs_core.is_empty() {
+            // Without object files, we can only link against an existing
+            // libpython shared library.
+            (false, self.libpython_shared_library.is_some())
+        } else if self.target_triple.contains("pc-windows") {
             // On Windows, support for libpython linkage is determined
             // by presence of a shared library in the distribution. This
             // isn't entirely semantically correct. Since we use `dllexport`
@@ -1011,11 +1259,141 @@ impl StandaloneDistribution {
         Ok((python_paths, extra_envs))
     }
 
+    /// Whether the distribution's Python interpreter can run on a machine.
+    ///
+    /// Interpreters can run on machines of other target triples having the
+    /// same CPU architecture and operating system (e.g. musl libc binaries
+    /// run on glibc Linux machines), on Windows x86_64 machines running
+    /// i686 binaries and on Apple Silicon machines running x86_64 binaries
+    /// via Rosetta.
+    pub fn python_exe_runs_on(&self, host_triple: &str) -> bool {
+        let target = self.target_triple.as_str();
+
+        if target == host_triple {
+            return true;
+        }
+
+        let host_arch = host_triple.split('-').next().unwrap_or("");
+        let target_arch = target.split('-').next().unwrap_or("");
+
+        let same_os = (LINUX_TARGET_TRIPLES.contains(&target)
+            && LINUX_TARGET_TRIPLES.contains(&host_triple))
+            || (MACOS_TARGET_TRIPLES.contains(&target)
+                && MACOS_TARGET_TRIPLES.contains(&host_triple))
+            || (WINDOWS_TARGET_TRIPLES.contains(&target)
+                && WINDOWS_TARGET_TRIPLES.contains(&host_triple));
+
+        same_os
+            && (host_arch == target_arch
+                || (host_arch == "x86_64" && target_arch == "i686")
+                || (host_arch == "aarch64" && target_arch == "x86_64"))
+    }
+
+    /// Describe the machines binaries embedding this distribution run on.
+    ///
+    /// The oldest supported macOS version is the deployment target in the
+    /// distribution's platform tag. e.g. `macosx-10.9-x86_64`.
+    pub fn wheel_target(&self) -> WheelTarget {
+        let mut python_version = self.python_major_minor_version();
+        if self.is_free_threaded() {
+            python_version.push('t');
+        }
+
+        let glibc_version = if self.target_triple.contains("-linux-gnu") {
+            Some(LINUX_GLIBC_VERSION)
+        } else {
+            None
+        };
+
+        WheelTarget {
+            target_triple: self.target_triple.clone(),
+            python_version,
+            glibc_version,
+            macos_version: macos_platform_version(
+                &self.python_platform_tag.replace("-", "_").replace(".", "_"),
+            ),
+        }
+    }
+
+    /// Whether a shared library extension module contains code for our target.
+    ///
+    /// Extension modules that can't be read are assumed to be compatible, so
+    /// errors are reported when they are used.
+    fn is_extension_module_for_target(&self, em: &PythonExtensionModule) -> bool {
+        // Free-threaded interpreters only load extensions built for their ABI,
+        // which have distinct file suffixes.
+        if self.is_free_threaded()
+            && !em.extension_file_suffix.is_empty()
+            && !self
+                .module_suffixes
+                .extension
+                .contains(&em.extension_file_suffix)
+        {
+            return false;
+        }
+
+        if !MACOS_TARGET_TRIPLES.contains(&self.target_triple.as_str()) {
+            return true;
+        }
+
+        match &em.shared_library {
+            Some(location) => match location.resolve() {
+                Ok(data) => {
+                    is_binary_compatible_with_target(&data, &self.target_triple).unwrap_or(true)
+                }
+                Err(_) => true,
+            },
+            None => true,
+        }
+    }
+
+    /// Whether the distribution is a free-threaded (no GIL) build.
+    ///
+    /// The ABI tag of free-threaded builds has a `t` suffix. e.g. `cp313t`.
+    pub fn is_free_threaded(&self) -> bool {
+        self.python_abi_tag
+            .as_ref()
+            .map_or(false, |tag| tag.ends_with('t'))
+    }
+
     /// Whether the distribution is capable of loading filed-based Python extension modules.
     pub fn is_extension_module_file_loadable(&self) -> bool {
         self.extension_module_loading
             .contains(&"shared-library".to_string())
     }
+
+    /// Name of the directory holding the Tcl library, such as `tcl8.6`.
+    ///
+    /// This is the directory `TCL_LIBRARY` should point to.
+    pub fn tcl_library_dir_name(&self) -> Option<String> {
+        self.tcl_library_paths.as_ref().and_then(|paths| {
+            paths
+                .iter()
+                .find(|p| p.starts_with("tcl") && p.contains('.'))
+                .cloned()
+        })
+    }
+
+    /// Obtain the Tcl/Tk library files needed by `tkinter`.
+    ///
+    /// Returns relative install paths and filesystem paths of files. The
+    /// relative paths begin with the directory names in `tcl_library_paths`.
+    pub fn tcl_files(&self) -> Result<Vec<(PathBuf, PathBuf)>> {
+        let mut res = vec![];
+
+        if let (Some(root), Some(paths)) = (&self.tcl_library_path, &self.tcl_library_paths) {
+            for path in paths {
+                for entry in walk_tree_files(&root.join(path)) {
+                    let full_path = entry.path();
+                    let rel_path = full_path.strip_prefix(root)?;
+
+                    res.push((rel_path.to_path_buf(), full_path.to_path_buf()));
+                }
+            }
+        }
+
+        Ok(res)
+    }
 }
 
 impl PythonDistribution for StandaloneDistribution {
@@ -1035,6 +1413,10 @@ impl PythonDistribution for StandaloneDistribution {
         &self.cache_tag
     }
 
+    fn python_abi_tag(&self) -> Option<&str> {
+        self.python_abi_tag.as_ref().map(|x| x.as_str())
+    }
+
     fn python_module_suffixes(&self) -> Result<PythonModuleSuffixes> {
         Ok(self.module_suffixes.clone())
     }
@@ -1046,15 +1428,37 @@ impl PythonDistribution for StandaloneDistribution {
     fn create_packaging_policy(&self) -> Result<PythonPackagingPolicy> {
         let mut policy = PythonPackagingPolicy::default();
 
-        for triple in LINUX_TARGET_TRIPLES.iter() {
+        // Only register entries for our own target so policy validation
+        // doesn't flag entries for other targets.
+        let triple = self.target_triple.as_str();
+
+        if LINUX_TARGET_TRIPLES.contains(&triple) {
             for ext in BROKEN_EXTENSIONS_LINUX.iter() {
-                policy.register_broken_extension(triple, ext);
+                policy.register_broken_extension(triple, ext, None)?;
+            }
+
+            if triple.contains("-linux-musl") {
+                for ext in BROKEN_EXTENSIONS_MUSL.iter() {
+                    policy.register_broken_extension(triple, ext, None)?;
+                }
             }
         }
 
-        for triple in MACOS_TARGET_TRIPLES.iter() {
+        if MACOS_TARGET_TRIPLES.contains(&triple) {
             for ext in BROKEN_EXTENSIONS_MACOS.iter() {
-                policy.register_broken_extension(triple, ext);
+                policy.register_broken_extension(triple, ext, None)?;
+            }
+
+            for library in SYSTEM_LIBRARIES_MACOS.iter() {
+                policy.register_known_system_library(triple, library)?;
+            }
+        }
+
+        // Linux system libraries (glibc) are LGPL licensed, so we don't
+        // register any for Linux.
+        if WINDOWS_TARGET_TRIPLES.contains(&triple) {
+            for library in SYSTEM_LIBRARIES_WINDOWS.iter() {
+                policy.register_known_system_library(triple, library)?;
             }
         }
 
@@ -1063,7 +1467,7 @@ impl PythonDistribution for StandaloneDistribution {
 
     fn as_python_executable_builder(
         &self,
-        _logger: &slog::Logger,
+        logger: &slog::Logger,
         host_triple: &str,
         target_triple: &str,
         name: &str,
@@ -1071,9 +1475,37 @@ impl PythonDistribution for StandaloneDistribution {
         policy: &PythonPackagingPolicy,
         config: &EmbeddedPythonConfig,
     ) -> Result<Box<dyn PythonBinaryBuilder>> {
+        // TODO can we avoid this clone?
+        let distribution = Arc::new(Box::new(self.clone()));
+
+        let host_distribution = if self.python_exe_runs_on(host_triple) {
+            distribution.clone()
+        } else {
+            warn!(
+                logger,
+                "Python distribution for {} can't run on {}; resolving distribution for {} to run Python at build time",
+                self.target_triple,
+                host_triple,
+                host_triple
+            );
+
+            let location =
+                default_distribution_location(&DistributionFlavor::Standalone, host_triple)?;
+            let distributions_dir = self
+                .base_dir
+                .parent()
+                .ok_or_else(|| anyhow!("unable to resolve distributions directory"))?;
+
+            Arc::new(Box::new(Self::from_location(
+                logger,
+                &location,
+                distributions_dir,
+            )?))
+        };
+
         let builder = StandalonePythonExecutableBuilder::from_distribution(
-            // TODO can we avoid this clone?
-            Arc::new(Box::new(self.clone())),
+            distribution,
+            host_distribution,
             host_triple.to_string(),
             target_triple.to_string(),
             name.to_string(),
@@ -1109,6 +1541,7 @@ impl PythonDistribution for StandaloneDistribution {
                     cache_tag: self.cache_tag.clone(),
                     is_stdlib: true,
                     is_test: is_stdlib_test_package(name),
+                    provenance: None,
                 })
             })
             .collect()
@@ -1125,6 +1558,7 @@ impl PythonDistribution for StandaloneDistribution {
                     data: DataLocation::Path(path.clone()),
                     is_stdlib: true,
                     is_test: is_stdlib_test_package(&package),
+                    provenance: None,
                 });
             }
         }
@@ -1134,6 +1568,30 @@ impl PythonDistribution for StandaloneDistribution {
 
     /// Ensure pip is available to run in the distribution.
     fn ensure_pip(&self, logger: &slog::Logger) -> Result<PathBuf> {
+        if self.externally_managed {
+            let status = std::process::Command::new(&self.python_exe)
+                .args(&["-m", "pip", "--version"])
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .status()?;
+
+            if !status.success() {
+                return Err(anyhow!(
+                    "pip is not installed for {}; please install it (e.g. `{} -m ensurepip --user`)",
+                    self.python_exe.display(),
+                    self.python_exe.display()
+                ));
+            }
+
+            let pip_path = self
+                .python_exe
+                .parent()
+                .map(|p| p.join(PIP_EXE_BASENAME))
+                .unwrap_or_else(|| PathBuf::from(PIP_EXE_BASENAME));
+
+            return Ok(pip_path);
+        }
+
         let dist_prefix = self.base_dir.join("python").join("install");
         let python_paths = resolve_python_paths(&dist_prefix, &self.version);
 
@@ -1166,6 +1624,24 @@ impl PythonDistribution for StandaloneDistribution {
         }
     }
 
+    fn trim(&self, trimming: &DistributionTrimming) -> Box<dyn PythonDistribution> {
+        let mut dist = self.clone();
+
+        dist.py_modules
+            .retain(|name, _| !trimming.is_package_trimmed(name));
+        dist.resources
+            .retain(|package, _| !trimming.is_package_trimmed(package));
+        dist.extension_modules
+            .retain(|name, _| !trimming.is_extension_module_trimmed(name));
+
+        if trimming.is_package_trimmed("tkinter") {
+            dist.tcl_library_path = None;
+            dist.tcl_library_paths = None;
+        }
+
+        Box::new(dist)
+    }
+
     fn filter_compatible_python_resources(
         &self,
         logger: &slog::Logger,
@@ -1176,13 +1652,17 @@ impl PythonDistribution for StandaloneDistribution {
             .filter(|resource| match resource {
                 // Extension modules defined as shared libraries are only compatible
                 // with some configurations.
-                PythonResource::ExtensionModuleDynamicLibrary { .. } => {
-                    if self.is_extension_module_file_loadable() {
-                        true
-                    } else {
+                PythonResource::ExtensionModuleDynamicLibrary(em) => {
+                    if !self.is_extension_module_file_loadable() {
                         warn!(logger, "ignoring extension module {} because it isn't loadable for the target configuration",
                             resource.full_name());
                         false
+                    } else if !self.is_extension_module_for_target(em) {
+                        warn!(logger, "ignoring extension module {} because it isn't built for {}",
+                            resource.full_name(), self.target_triple);
+                        false
+                    } else {
+                        true
                     }
                 }
 
@@ -1199,6 +1679,9 @@ impl PythonDistribution for StandaloneDistribution {
                 PythonResource::DistributionResource(_) => true,
                 PythonResource::EggFile(_) => false,
                 PythonResource::PathExtension(_) => false,
+                PythonResource::EntryPoint(_) => true,
+                PythonResource::DataFile(_) => true,
+                PythonResource::EditableInstall(_) => true,
             })
             .cloned()
             .collect())
@@ -1230,4 +1713,82 @@ pub mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_trim() -> Result<()> {
+        let distribution = get_default_distribution()?;
+
+        let trimmed = distribution.trim(&DistributionTrimming {
+            packages: vec!["lib2to3".to_string(), "tkinter".to_string()],
+            include_test: false,
+            encodings: Some(vec!["cp437".to_string()]),
+        });
+
+        for module in trimmed.source_modules()? {
+            assert!(!module.is_test);
+            assert!(!module.name.starts_with("lib2to3"));
+            assert!(!module.name.starts_with("tkinter"));
+
+            if module.name.starts_with("encodings.") {
+                assert!([
+                    "encodings.aliases",
+                    "encodings.ascii",
+                    "encodings.cp437",
+                    "encodings.latin_1",
+                    "encodings.mbcs",
+                    "encodings.utf_8"
+                ]
+                .contains(&module.name.as_str()));
+            }
+        }
+
+        for resource in trimmed.resource_datas()? {
+            assert!(!resource.is_test);
+            assert!(!resource.leaf_package.starts_with("lib2to3"));
+        }
+
+        assert!(trimmed
+            .iter_extension_modules()
+            .all(|em| em.name != "_tkinter"));
+        assert!(trimmed
+            .source_modules()?
+            .iter()
+            .any(|m| m.name == "encodings.utf_8"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_strip_docstrings() -> Result<()> {
+        let distribution = get_default_distribution()?;
+        let mut compiler = distribution.create_bytecode_compiler()?;
+
+        let source = indoc::indoc!(
+            r#"
+            """Module docstring."""
+            from __future__ import annotations
+
+            def foo():  # comment
+                """Function docstring."""
+                return "value"
+            "#
+        );
+
+        let stripped = compiler.strip_docstrings(source.as_bytes(), "foo")?;
+        assert_eq!(
+            String::from_utf8(stripped)?,
+            indoc::indoc!(
+                r#"
+
+                from __future__ import annotations
+
+                def foo():
+                    pass
+                    return "value"
+                "#
+            )
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,375 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Supported combinations of build hosts, targets, distributions and link modes.
+
+Not every distribution flavor and libpython link mode is available for every
+target. And only some targets can be built from a given host. Unsupported
+combinations typically fail deep in the build with obscure compiler or
+linker errors. So builds consult the matrix defined here up front and fail
+with an error describing what to do instead.
+
+Binary wheels are similarly validated against the target before their
+resources are accepted. Otherwise a wheel for another architecture, a newer
+glibc or a newer macOS would produce a binary with an extension module that
+crashes when imported.
+*/
+
+use {
+    super::distribution::{BinaryLibpythonLinkMode, DistributionFlavor},
+    crate::environment::{LINUX_TARGET_TRIPLES, MACOS_TARGET_TRIPLES, WINDOWS_TARGET_TRIPLES},
+    anyhow::{anyhow, Context, Result},
+    python_packaging::resource::{PythonPackageDistributionResourceFlavor, PythonResource},
+    python_packaging::wheel::{wheel_metadata_tags, WheelTarget},
+};
+
+/// Describes a supported build of one target triple on another.
+struct CrossCompileSupport {
+    /// Target triple of the machine building.
+    host_triple: &'static str,
+    /// Target triple being built.
+    target_triple: &'static str,
+    /// What the host needs to build for the target.
+    requirements: &'static str,
+}
+
+/// Supported builds for a target triple other than the host's.
+const CROSS_COMPILE_MATRIX: &[CrossCompileSupport] = &[
+    CrossCompileSupport {
+        host_triple: "x86_64-unknown-linux-gnu",
+        target_triple: "x86_64-unknown-linux-musl",
+        requirements: "the x86_64-unknown-linux-musl Rust target",
+    },
+    CrossCompileSupport {
+        host_triple: "x86_64-apple-darwin",
+        target_triple: "aarch64-apple-darwin",
+        requirements: "the aarch64-apple-darwin Rust target and an Xcode SDK supporting arm64; only binary wheels can be installed",
+    },
+    CrossCompileSupport {
+        host_triple: "aarch64-apple-darwin",
+        target_triple: "x86_64-apple-darwin",
+        requirements: "the x86_64-apple-darwin Rust target and Rosetta 2",
+    },
+    CrossCompileSupport {
+        host_triple: "x86_64-pc-windows-msvc",
+        target_triple: "i686-pc-windows-msvc",
+        requirements: "the i686-pc-windows-msvc Rust target",
+    },
+];
+
+/// Obtain a human readable name of the operating system of a target triple.
+fn target_os_name(triple: &str) -> &'static str {
+    if LINUX_TARGET_TRIPLES.contains(&triple) {
+        "Linux"
+    } else if MACOS_TARGET_TRIPLES.contains(&triple) {
+        "macOS"
+    } else if WINDOWS_TARGET_TRIPLES.contains(&triple) {
+        "Windows"
+    } else {
+        "an unsupported operating system"
+    }
+}
+
+/// Obtain the name of a distribution flavor as used in configuration files.
+fn flavor_name(flavor: &DistributionFlavor) -> &'static str {
+    match flavor {
+        DistributionFlavor::Standalone => "standalone",
+        DistributionFlavor::StandaloneStatic => "standalone_static",
+        DistributionFlavor::StandaloneDynamic => "standalone_dynamic",
+        DistributionFlavor::StandaloneFreethreaded => "standalone_freethreaded",
+    }
+}
+
+/// Describe what a host needs to build a target.
+///
+/// Returns `None` if the host can't build the target.
+pub fn cross_compile_requirements(host_triple: &str, target_triple: &str) -> Option<&'static str> {
+    if host_triple == target_triple {
+        return Some("nothing special");
+    }
+
+    CROSS_COMPILE_MATRIX
+        .iter()
+        .find(|s| s.host_triple == host_triple && s.target_triple == target_triple)
+        .map(|s| s.requirements)
+}
+
+/// Ensure a build configuration is supported.
+///
+/// Errors describe how to reach a supported configuration.
+pub fn check_build_configuration(
+    host_triple: &str,
+    target_triple: &str,
+    flavor: &DistributionFlavor,
+    link_mode: &BinaryLibpythonLinkMode,
+) -> Result<()> {
+    let known_target = LINUX_TARGET_TRIPLES.contains(&target_triple)
+        || MACOS_TARGET_TRIPLES.contains(&target_triple)
+        || WINDOWS_TARGET_TRIPLES.contains(&target_triple);
+
+    if !known_target {
+        let mut targets = LINUX_TARGET_TRIPLES
+            .iter()
+            .chain(MACOS_TARGET_TRIPLES.iter())
+            .chain(WINDOWS_TARGET_TRIPLES.iter())
+            .cloned()
+            .collect::<Vec<_>>();
+        targets.sort();
+
+        return Err(anyhow!(
+            "target {} is not supported; supported targets are {}",
+            target_triple,
+            targets.join(", ")
+        ));
+    }
+
+    if cross_compile_requirements(host_triple, target_triple).is_none() {
+        let supported = CROSS_COMPILE_MATRIX
+            .iter()
+            .filter(|s| s.host_triple == host_triple)
+            .map(|s| s.target_triple)
+            .collect::<Vec<_>>();
+
+        return Err(anyhow!(
+            "cross-compiling from {} ({}) to {} ({}) is not supported; build on a {} machine{}",
+            host_triple,
+            target_os_name(host_triple),
+            target_triple,
+            target_os_name(target_triple),
+            target_os_name(target_triple),
+            if supported.is_empty() {
+                "".to_string()
+            } else {
+                format!(
+                    " (this machine can build {} and {})",
+                    host_triple,
+                    supported.join(", ")
+                )
+            }
+        ));
+    }
+
+    let is_musl = target_triple.contains("-linux-musl");
+    let is_windows = WINDOWS_TARGET_TRIPLES.contains(&target_triple);
+
+    match flavor {
+        DistributionFlavor::StandaloneDynamic if is_musl => {
+            return Err(anyhow!(
+                "flavor=standalone_dynamic is not available for {} because musl libc binaries are fully statically linked; use flavor=standalone or flavor=standalone_static",
+                target_triple
+            ));
+        }
+        DistributionFlavor::StandaloneStatic if !is_musl && !is_windows => {
+            return Err(anyhow!(
+                "flavor=standalone_static is only available for Windows and musl libc targets; use flavor=standalone or flavor=standalone_dynamic for {}",
+                target_triple
+            ));
+        }
+        _ => {}
+    }
+
+    match link_mode {
+        BinaryLibpythonLinkMode::Dynamic if is_musl => Err(anyhow!(
+            "dynamically linking libpython is not supported for {} because musl libc binaries are fully statically linked; link libpython statically",
+            target_triple
+        )),
+        BinaryLibpythonLinkMode::Dynamic
+            if is_windows && *flavor == DistributionFlavor::StandaloneStatic =>
+        {
+            Err(anyhow!(
+                "dynamically linking libpython on Windows requires flavor=standalone_dynamic; flavor={} only supports static linking",
+                flavor_name(flavor)
+            ))
+        }
+        BinaryLibpythonLinkMode::Static
+            if is_windows && *flavor != DistributionFlavor::StandaloneStatic =>
+        {
+            Err(anyhow!(
+                "statically linking libpython on Windows requires flavor=standalone_static; flavor={} only supports dynamic linking",
+                flavor_name(flavor)
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Ensure distributions installed from binary wheels can be used by a target.
+///
+/// The tags in the `WHEEL` metadata file of each installed distribution are
+/// checked. Distributions that weren't installed from wheels are ignored.
+pub fn check_wheel_resources(resources: &[PythonResource], target: &WheelTarget) -> Result<()> {
+    for resource in resources {
+        if let PythonResource::DistributionResource(r) = resource {
+            if r.location != PythonPackageDistributionResourceFlavor::DistInfo || r.name != "WHEEL"
+            {
+                continue;
+            }
+
+            let data = r.data.resolve()?;
+            let tags = wheel_metadata_tags(&String::from_utf8_lossy(&data))
+                .with_context(|| format!("parsing WHEEL file of {}", r.package))?;
+
+            target.check_tags(&tags).with_context(|| {
+                format!(
+                    "{} {} was installed from a wheel that can't be used by {}",
+                    r.package, r.version, target.target_triple
+                )
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        python_packaging::resource::{DataLocation, PythonPackageDistributionResource},
+    };
+
+    #[test]
+    fn test_native_builds() -> Result<()> {
+        for triple in LINUX_TARGET_TRIPLES
+            .iter()
+            .chain(MACOS_TARGET_TRIPLES.iter())
+            .chain(WINDOWS_TARGET_TRIPLES.iter())
+        {
+            check_build_configuration(
+                triple,
+                triple,
+                &DistributionFlavor::Standalone,
+                &BinaryLibpythonLinkMode::Default,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cross_compile() -> Result<()> {
+        check_build_configuration(
+            "x86_64-apple-darwin",
+            "aarch64-apple-darwin",
+            &DistributionFlavor::Standalone,
+            &BinaryLibpythonLinkMode::Default,
+        )?;
+
+        let err = check_build_configuration(
+            "x86_64-apple-darwin",
+            "x86_64-pc-windows-msvc",
+            &DistributionFlavor::Standalone,
+            &BinaryLibpythonLinkMode::Default,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cross-compiling from x86_64-apple-darwin (macOS) to x86_64-pc-windows-msvc (Windows) is not supported; build on a Windows machine (this machine can build x86_64-apple-darwin and aarch64-apple-darwin)"
+        );
+
+        assert!(check_build_configuration(
+            "x86_64-unknown-linux-gnu",
+            "riscv64gc-unknown-linux-gnu",
+            &DistributionFlavor::Standalone,
+            &BinaryLibpythonLinkMode::Default,
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_flavors_and_link_modes() -> Result<()> {
+        let linux = "x86_64-unknown-linux-gnu";
+        let musl = "x86_64-unknown-linux-musl";
+        let windows = "x86_64-pc-windows-msvc";
+
+        assert!(check_build_configuration(
+            linux,
+            musl,
+            &DistributionFlavor::StandaloneDynamic,
+            &BinaryLibpythonLinkMode::Default
+        )
+        .is_err());
+        assert!(check_build_configuration(
+            linux,
+            musl,
+            &DistributionFlavor::Standalone,
+            &BinaryLibpythonLinkMode::Dynamic
+        )
+        .is_err());
+        assert!(check_build_configuration(
+            linux,
+            linux,
+            &DistributionFlavor::StandaloneStatic,
+            &BinaryLibpythonLinkMode::Default
+        )
+        .is_err());
+
+        check_build_configuration(
+            windows,
+            windows,
+            &DistributionFlavor::StandaloneStatic,
+            &BinaryLibpythonLinkMode::Static,
+        )?;
+        let err = check_build_configuration(
+            windows,
+            windows,
+            &DistributionFlavor::StandaloneStatic,
+            &BinaryLibpythonLinkMode::Dynamic,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "dynamically linking libpython on Windows requires flavor=standalone_dynamic; flavor=standalone_static only supports static linking"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_wheel_resources() -> Result<()> {
+        let target = WheelTarget {
+            target_triple: "x86_64-apple-darwin".to_string(),
+            python_version: "3.8".to_string(),
+            glibc_version: None,
+            macos_version: Some((10, 9)),
+        };
+
+        let wheel_resource = |tag: &str| {
+            PythonResource::DistributionResource(PythonPackageDistributionResource {
+                location: PythonPackageDistributionResourceFlavor::DistInfo,
+                package: "foo".to_string(),
+                version: "1.0".to_string(),
+                name: "WHEEL".to_string(),
+                data: DataLocation::Memory(
+                    format!("Wheel-Version: 1.0\nRoot-Is-Purelib: false\nTag: {}\n", tag)
+                        .into_bytes(),
+                ),
+                provenance: None,
+            })
+        };
+
+        check_wheel_resources(&[wheel_resource("cp38-cp38-macosx_10_9_x86_64")], &target)?;
+        check_wheel_resources(&[wheel_resource("py3-none-any")], &target)?;
+
+        let err = check_wheel_resources(&[wheel_resource("cp38-cp38-macosx_11_0_arm64")], &target)
+            .unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "foo 1.0 was installed from a wheel that can't be used by x86_64-apple-darwin: cp38-cp38-macosx_11_0_arm64 is for platform macosx_11_0_arm64, not x86_64-apple-darwin"
+        );
+
+        let err =
+            check_wheel_resources(&[wheel_resource("cp38-cp38-macosx_10_15_x86_64")], &target)
+                .unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "foo 1.0 was installed from a wheel that can't be used by x86_64-apple-darwin: cp38-cp38-macosx_10_15_x86_64 requires macOS 10.15 but binaries for x86_64-apple-darwin support macOS 10.9"
+        );
+
+        Ok(())
+    }
+}
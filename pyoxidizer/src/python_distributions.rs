@@ -33,6 +33,10 @@ impl PythonDistributionCollection {
                 continue;
             }
 
+            if dist.free_threaded != (*flavor == DistributionFlavor::StandaloneFreethreaded) {
+                continue;
+            }
+
             match flavor {
                 DistributionFlavor::Standalone => {
                     return Some(dist.clone());
@@ -47,6 +51,9 @@ impl PythonDistributionCollection {
                         return Some(dist.clone());
                     }
                 }
+                DistributionFlavor::StandaloneFreethreaded => {
+                    return Some(dist.clone());
+                }
             }
         }
 
@@ -71,6 +78,7 @@ lazy_static! {
                 },
                 target_triple: "x86_64-unknown-linux-gnu".to_string(),
                 supports_prebuilt_extension_modules: true,
+                free_threaded: false,
             },
 
             // Linux musl.
@@ -81,6 +89,7 @@ lazy_static! {
                 },
                 target_triple: "x86_64-unknown-linux-musl".to_string(),
                 supports_prebuilt_extension_modules: false,
+                free_threaded: false,
             },
 
             // The order here is important because we will choose the
@@ -98,6 +107,7 @@ lazy_static! {
                 },
                 target_triple: "i686-pc-windows-msvc".to_string(),
                 supports_prebuilt_extension_modules: true,
+                free_threaded: false,
             },
             PythonDistributionRecord {
                 location: PythonDistributionLocation::Url {
@@ -106,6 +116,7 @@ lazy_static! {
                 },
                 target_triple: "x86_64-pc-windows-msvc".to_string(),
                 supports_prebuilt_extension_modules: true,
+                free_threaded: false,
             },
 
             // Windows static.
@@ -116,6 +127,7 @@ lazy_static! {
                 },
                 target_triple: "i686-pc-windows-msvc".to_string(),
                 supports_prebuilt_extension_modules: false,
+                free_threaded: false,
             },
             PythonDistributionRecord {
                 location: PythonDistributionLocation::Url {
@@ -124,6 +136,7 @@ lazy_static! {
                 },
                 target_triple: "x86_64-pc-windows-msvc".to_string(),
                 supports_prebuilt_extension_modules: false,
+                free_threaded: false,
             },
 
             // macOS.
@@ -134,6 +147,7 @@ lazy_static! {
                 },
                 target_triple: "x86_64-apple-darwin".to_string(),
                 supports_prebuilt_extension_modules: true,
+                free_threaded: false,
             },
         ];
 
@@ -8,6 +8,7 @@ use {
     super::python_executable::PythonExecutable,
     super::target::{BuildContext, BuildTarget, ResolvedTarget},
     super::util::{optional_list_arg, required_bool_arg, required_str_arg, required_type_arg},
+    crate::project_lock::{LockMode, ProjectLock, LOCK_FILENAME},
     anyhow::{anyhow, Context, Result},
     path_dedot::ParseDot,
     slog::warn,
@@ -96,6 +97,23 @@ pub struct EnvironmentContext {
     ///
     /// This will change the default target to resolve.
     pub build_script_mode: bool,
+
+    /// How packages are installed with respect to the project lock.
+    pub lock_mode: LockMode,
+
+    /// The project lock.
+    ///
+    /// In `LockMode::Use`, this is the content of an existing `pyoxidizer.lock`.
+    /// In `LockMode::Record`, installs are recorded here.
+    pub project_lock: Option<ProjectLock>,
+
+    /// Path where downloaded packages are cached.
+    pub wheel_cache_path: PathBuf,
+
+    /// Whether executables reload packed resources files when they change.
+    ///
+    /// Set by `pyoxidizer run` so resources can be updated while iterating.
+    pub watch_packed_resources_files: bool,
 }
 
 impl EnvironmentContext {
@@ -110,6 +128,7 @@ impl EnvironmentContext {
         build_opt_level: &str,
         resolve_targets: Option<Vec<String>>,
         build_script_mode: bool,
+        lock_mode: LockMode,
     ) -> Result<EnvironmentContext> {
         let parent = config_path
             .parent()
@@ -123,6 +142,20 @@ impl EnvironmentContext {
 
         let build_path = parent.join("build");
 
+        let lock_path = parent.join(LOCK_FILENAME);
+        let project_lock = if lock_path.exists() {
+            let mut lock = ProjectLock::from_path(&lock_path)?;
+
+            // Installs for the target are recorded again.
+            if lock_mode == LockMode::Record {
+                lock.remove_target(build_target_triple);
+            }
+
+            Some(lock)
+        } else {
+            None
+        };
+
         Ok(EnvironmentContext {
             logger: logger.clone(),
             verbose,
@@ -140,6 +173,10 @@ impl EnvironmentContext {
             default_build_script_target: None,
             resolve_targets,
             build_script_mode,
+            lock_mode,
+            project_lock,
+            wheel_cache_path: build_path.join("wheel_cache"),
+            watch_packed_resources_files: false,
         })
     }
 
@@ -154,6 +191,7 @@ impl EnvironmentContext {
 
         self.build_path = path.clone();
         self.python_distributions_path = path.join("python_distributions");
+        self.wheel_cache_path = path.join("wheel_cache");
 
         Ok(())
     }
@@ -4,6 +4,7 @@
 
 use {
     super::env::{global_environment, EnvironmentContext},
+    crate::project_lock::LockMode,
     anyhow::{anyhow, Result},
     codemap::CodeMap,
     codemap_diagnostic::{Diagnostic, Level},
@@ -20,6 +21,7 @@ pub struct EvalResult {
 }
 
 /// Evaluate a Starlark configuration file, returning a low-level result.
+#[allow(clippy::too_many_arguments)]
 pub fn evaluate_file(
     logger: &slog::Logger,
     config_path: &Path,
@@ -28,8 +30,10 @@ pub fn evaluate_file(
     verbose: bool,
     resolve_targets: Option<Vec<String>>,
     build_script_mode: bool,
+    lock_mode: LockMode,
+    watch_packed_resources_files: bool,
 ) -> Result<EvalResult, Diagnostic> {
-    let context = EnvironmentContext::new(
+    let mut context = EnvironmentContext::new(
         logger,
         verbose,
         config_path,
@@ -40,6 +44,7 @@ pub fn evaluate_file(
         "0",
         resolve_targets,
         build_script_mode,
+        lock_mode,
     )
     .map_err(|e| Diagnostic {
         level: Level::Error,
@@ -47,6 +52,7 @@ pub fn evaluate_file(
         code: Some("environment".to_string()),
         spans: vec![],
     })?;
+    context.watch_packed_resources_files = watch_packed_resources_files;
 
     let mut env = global_environment(&context).map_err(|_| Diagnostic {
         level: Level::Error,
@@ -86,6 +92,7 @@ pub fn evaluate_file(
 }
 
 /// Evaluate a Starlark configuration file and return its result.
+#[allow(clippy::too_many_arguments)]
 pub fn eval_starlark_config_file(
     logger: &slog::Logger,
     path: &Path,
@@ -94,6 +101,8 @@ pub fn eval_starlark_config_file(
     verbose: bool,
     resolve_targets: Option<Vec<String>>,
     build_script_mode: bool,
+    lock_mode: LockMode,
+    watch_packed_resources_files: bool,
 ) -> Result<EvalResult> {
     crate::starlark::eval::evaluate_file(
         logger,
@@ -103,6 +112,8 @@ pub fn eval_starlark_config_file(
         verbose,
         resolve_targets,
         build_script_mode,
+        lock_mode,
+        watch_packed_resources_files,
     )
     .map_err(|d| anyhow!(d.message))
 }
@@ -6,8 +6,8 @@ use {
     super::env::EnvironmentContext,
     super::python_executable::PythonExecutable,
     super::python_resource::{
-        PythonBytecodeModule, PythonExtensionModule, PythonPackageDistributionResource,
-        PythonPackageResource, PythonSourceModule,
+        PythonBytecodeModule, PythonDataFile, PythonExtensionModule,
+        PythonPackageDistributionResource, PythonPackageResource, PythonSourceModule,
     },
     super::target::{BuildContext, BuildTarget, ResolvedTarget, RunMode},
     super::util::{
@@ -292,6 +292,32 @@ impl FileManifest {
                         .into()
                     })
             }
+            // Entry points are described by the `entry_points.txt` distribution
+            // resource. There is nothing to add.
+            "PythonEntryPoint" => Ok(()),
+            // Editable installs are imported from their source tree. There is
+            // nothing to install.
+            "PythonEditableInstall" => Ok(()),
+            "PythonDataFile" => {
+                let file = resource.downcast_apply(|m: &PythonDataFile| m.file.clone());
+                warn!(
+                    logger,
+                    "adding {} file {} of {} to {}",
+                    file.location,
+                    file.relative_path.display(),
+                    file.package,
+                    prefix
+                );
+                file.add_to_file_manifest(&mut self.manifest, &prefix)
+                    .map_err(|e| {
+                        RuntimeError {
+                            code: "PYOXIDIZER_BUILD",
+                            message: e.to_string(),
+                            label: "add_python_resource".to_string(),
+                        }
+                        .into()
+                    })
+            }
 
             "PythonExecutable" => {
                 let context = env.get("CONTEXT").expect("CONTEXT not defined");
@@ -555,6 +581,7 @@ mod tests {
             cache_tag: DEFAULT_CACHE_TAG.to_string(),
             is_stdlib: false,
             is_test: false,
+            provenance: None,
         }));
 
         let mut env = starlark_env();
@@ -604,6 +631,7 @@ mod tests {
                 data: DataLocation::Memory(vec![]),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             },
         });
 
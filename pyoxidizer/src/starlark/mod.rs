@@ -11,6 +11,7 @@ define Oxidized Python binaries.
 pub mod env;
 pub mod eval;
 pub mod file_resource;
+pub mod python_dependency_report;
 pub mod python_distribution;
 pub mod python_embedded_resources;
 pub mod python_executable;
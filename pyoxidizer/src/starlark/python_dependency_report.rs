@@ -0,0 +1,164 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::py_packaging::dependency_report::{DependencyReport, ReportedDistribution},
+    starlark::environment::Environment,
+    starlark::values::{default_compare, RuntimeError, TypedValue, Value, ValueError, ValueResult},
+    starlark::{any, immutable, not_supported},
+    std::any::Any,
+    std::cmp::Ordering,
+    std::collections::HashMap,
+};
+
+#[derive(Debug, Clone)]
+pub struct PythonDependencyReport {
+    pub report: DependencyReport,
+}
+
+impl TypedValue for PythonDependencyReport {
+    immutable!();
+    any!();
+    not_supported!(
+        binop, dir_attr, function, get_hash, indexable, iterable, sequence, set_attr, to_int
+    );
+
+    fn to_str(&self) -> String {
+        format!(
+            "PythonDependencyReport<distributions={}>",
+            self.report.distributions.len()
+        )
+    }
+
+    fn to_repr(&self) -> String {
+        self.to_str()
+    }
+
+    fn get_type(&self) -> &'static str {
+        "PythonDependencyReport"
+    }
+
+    fn to_bool(&self) -> bool {
+        !self.report.is_empty()
+    }
+
+    fn compare(&self, other: &dyn TypedValue, _recursion: u32) -> Result<Ordering, ValueError> {
+        default_compare(self, other)
+    }
+
+    fn get_attr(&self, attribute: &str) -> ValueResult {
+        let v = match attribute {
+            "distributions" => Value::from(
+                self.report
+                    .distributions
+                    .values()
+                    .map(|dist| {
+                        Value::new(PythonReportedDistribution {
+                            distribution: dist.clone(),
+                        })
+                    })
+                    .collect::<Vec<Value>>(),
+            ),
+            "roots" => Value::from(self.report.roots()),
+            "tree" => Value::new(self.report.format_tree()),
+            "json" => Value::new(serde_json::to_string_pretty(&self.report).map_err(|e| {
+                RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: e.to_string(),
+                    label: "json".to_string(),
+                }
+                .into()
+            })?),
+            attr => {
+                return Err(ValueError::OperationNotSupported {
+                    op: format!(".{}", attr),
+                    left: "PythonDependencyReport".to_string(),
+                    right: None,
+                })
+            }
+        };
+
+        Ok(v)
+    }
+
+    fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
+        Ok(match attribute {
+            "distributions" => true,
+            "roots" => true,
+            "tree" => true,
+            "json" => true,
+            _ => false,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PythonReportedDistribution {
+    pub distribution: ReportedDistribution,
+}
+
+impl TypedValue for PythonReportedDistribution {
+    immutable!();
+    any!();
+    not_supported!(
+        binop, dir_attr, function, get_hash, indexable, iterable, sequence, set_attr, to_int
+    );
+
+    fn to_str(&self) -> String {
+        format!(
+            "PythonReportedDistribution<name={}, version={}, kind={}>",
+            self.distribution.name, self.distribution.version, self.distribution.kind
+        )
+    }
+
+    fn to_repr(&self) -> String {
+        self.to_str()
+    }
+
+    fn get_type(&self) -> &'static str {
+        "PythonReportedDistribution"
+    }
+
+    fn to_bool(&self) -> bool {
+        true
+    }
+
+    fn compare(&self, other: &dyn TypedValue, _recursion: u32) -> Result<Ordering, ValueError> {
+        default_compare(self, other)
+    }
+
+    fn get_attr(&self, attribute: &str) -> ValueResult {
+        let v = match attribute {
+            "name" => Value::new(self.distribution.name.clone()),
+            "version" => Value::new(self.distribution.version.clone()),
+            "kind" => Value::new(self.distribution.kind.to_string()),
+            "requires" => Value::from(self.distribution.requires.clone()),
+            "required_by" => Value::from(self.distribution.required_by.clone()),
+            "resource_count" => Value::new(self.distribution.resource_count as i64),
+            "size" => Value::new(self.distribution.size as i64),
+            attr => {
+                return Err(ValueError::OperationNotSupported {
+                    op: format!(".{}", attr),
+                    left: "PythonReportedDistribution".to_string(),
+                    right: None,
+                })
+            }
+        };
+
+        Ok(v)
+    }
+
+    fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
+        Ok(match attribute {
+            "name" => true,
+            "version" => true,
+            "kind" => true,
+            "requires" => true,
+            "required_by" => true,
+            "resource_count" => true,
+            "size" => true,
+            _ => false,
+        })
+    }
+}
@@ -7,19 +7,24 @@ use {
     super::python_executable::PythonExecutable,
     super::python_resource::{PythonExtensionModule, PythonPackageResource, PythonSourceModule},
     super::util::{
-        optional_dict_arg, optional_str_arg, optional_type_arg, required_bool_arg, required_str_arg,
+        optional_dict_arg, optional_list_arg, optional_str_arg, optional_type_arg,
+        required_bool_arg, required_dict_arg, required_str_arg,
     },
+    crate::py_packaging::binary::WindowsSubsystem,
     crate::py_packaging::config::EmbeddedPythonConfig,
     crate::py_packaging::distribution::BinaryLibpythonLinkMode,
     crate::py_packaging::distribution::{
         default_distribution_location, is_stdlib_test_package, resolve_distribution,
-        DistributionFlavor, PythonDistribution as PythonDistributionTrait,
-        PythonDistributionLocation,
+        DistributionFlavor, DistributionTrimming, PythonDistribution as PythonDistributionTrait,
+        PythonDistributionLocation, DEFAULT_TRIMMED_PACKAGES,
     },
+    crate::py_packaging::target_support::check_build_configuration,
     anyhow::{anyhow, Result},
     itertools::Itertools,
     python_packaging::bytecode::{CompileMode, PythonBytecodeCompiler},
-    python_packaging::policy::{ExtensionModuleFilter, PythonResourcesPolicy},
+    python_packaging::policy::{
+        ExtensionModuleFilter, PythonPackagingPolicyOverlay, PythonResourcesPolicy,
+    },
     python_packaging::resource::BytecodeOptimizationLevel,
     starlark::environment::Environment,
     starlark::values::{
@@ -106,6 +111,92 @@ impl PythonDistribution {
     }
 }
 
+/// Parse a `PythonResourcesPolicy` from a string in a Starlark argument.
+fn resources_policy_from_str(
+    label: &str,
+    value: &str,
+) -> Result<PythonResourcesPolicy, ValueError> {
+    PythonResourcesPolicy::try_from(value).map_err(|e| {
+        RuntimeError {
+            code: "PYOXIDIZER_BUILD",
+            message: e.to_string(),
+            label: label.to_string(),
+        }
+        .into()
+    })
+}
+
+/// Convert a Starlark dict to a `PythonPackagingPolicyOverlay`.
+///
+/// Keys have the names of the `to_python_executable()` arguments they override,
+/// or are `extension_modules_policy` or `package_resources_policies`. `label`
+/// is the name of the argument the dict was passed as.
+fn policy_overlay_from_dict(
+    label: &str,
+    value: &Value,
+) -> Result<PythonPackagingPolicyOverlay, ValueError> {
+    let mut overlay = PythonPackagingPolicyOverlay::default();
+
+    for k in value.into_iter()? {
+        let key = required_str_arg(label, &k)?;
+        let v = value.at(k.clone())?;
+
+        match key.as_str() {
+            "resources_policy" => {
+                let policy = required_str_arg(&key, &v)?;
+                overlay.resources_policy = Some(resources_policy_from_str(label, &policy)?);
+            }
+            "extension_modules_policy" => {
+                let policy = required_str_arg(&key, &v)?;
+                overlay.extension_modules_policy = Some(resources_policy_from_str(label, &policy)?);
+            }
+            "package_resources_policies" => {
+                required_dict_arg(&key, "string", "string", &v)?;
+
+                for package in v.into_iter()? {
+                    let policy = required_str_arg(&key, &v.at(package.clone())?)?;
+                    overlay.package_resources_policies.insert(
+                        package.to_string(),
+                        resources_policy_from_str(label, &policy)?,
+                    );
+                }
+            }
+            "extension_module_filter" => {
+                let filter = required_str_arg(&key, &v)?;
+                overlay.extension_module_filter = Some(
+                    ExtensionModuleFilter::try_from(filter.as_str()).map_err(|e| {
+                        RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: e,
+                            label: "invalid policy value".to_string(),
+                        }
+                        .into()
+                    })?,
+                );
+            }
+            "include_sources" => {
+                overlay.include_distribution_sources = Some(required_bool_arg(&key, &v)?);
+            }
+            "include_resources" => {
+                overlay.include_distribution_resources = Some(required_bool_arg(&key, &v)?);
+            }
+            "include_test" => {
+                overlay.include_test = Some(required_bool_arg(&key, &v)?);
+            }
+            _ => {
+                return Err(RuntimeError {
+                    code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                    message: format!("unsupported overlay setting: {}", key),
+                    label: label.to_string(),
+                }
+                .into())
+            }
+        }
+    }
+
+    Ok(overlay)
+}
+
 impl TypedValue for PythonDistribution {
     immutable!();
     any!();
@@ -138,14 +229,20 @@ impl TypedValue for PythonDistribution {
 
 // Starlark functions.
 impl PythonDistribution {
-    /// default_python_distribution(flavor, build_target=None)
+    /// default_python_distribution(flavor, build_target=None, local_path=None, url=None, sha256=None)
     fn default_python_distribution(
         env: &Environment,
         flavor: &Value,
         build_target: &Value,
+        local_path: &Value,
+        url: &Value,
+        sha256: &Value,
     ) -> ValueResult {
         let flavor = required_str_arg("flavor", flavor)?;
         let build_target = optional_str_arg("build_target", build_target)?;
+        let local_path = optional_str_arg("local_path", local_path)?;
+        let url = optional_str_arg("url", url)?;
+        let sha256 = optional_str_arg("sha256", sha256)?;
 
         let build_target = match build_target {
             Some(t) => t,
@@ -156,6 +253,7 @@ impl PythonDistribution {
             "standalone" => DistributionFlavor::Standalone,
             "standalone_static" => DistributionFlavor::StandaloneStatic,
             "standalone_dynamic" => DistributionFlavor::StandaloneDynamic,
+            "standalone_freethreaded" => DistributionFlavor::StandaloneFreethreaded,
             v => {
                 return Err(RuntimeError {
                     code: "PYOXIDIZER_BUILD",
@@ -166,14 +264,62 @@ impl PythonDistribution {
             }
         };
 
-        let location = default_distribution_location(&flavor, &build_target).map_err(|e| {
-            RuntimeError {
-                code: "PYOXIDIZER_BUILD",
-                message: e.to_string(),
-                label: "default_python_distribution()".to_string(),
+        let location = match (local_path, url, sha256) {
+            (None, None, None) => {
+                let context = env.get("CONTEXT").expect("CONTEXT not defined");
+                let host_triple =
+                    context.downcast_apply(|x: &EnvironmentContext| x.build_host_triple.clone());
+
+                check_build_configuration(
+                    &host_triple,
+                    &build_target,
+                    &flavor,
+                    &BinaryLibpythonLinkMode::Default,
+                )
+                .and_then(|_| default_distribution_location(&flavor, &build_target))
+                .map_err(|e| {
+                    RuntimeError {
+                        code: "PYOXIDIZER_BUILD",
+                        message: e.to_string(),
+                        label: "default_python_distribution()".to_string(),
+                    }
+                    .into()
+                })?
             }
-            .into()
-        })?;
+            (Some(_), Some(_), _) => {
+                return Err(RuntimeError {
+                    code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                    message: "cannot define both local_path and url".to_string(),
+                    label: "default_python_distribution()".to_string(),
+                }
+                .into())
+            }
+            (Some(local_path), None, Some(sha256)) => {
+                let cwd = env.get("CWD").expect("CWD not defined").to_string();
+
+                PythonDistributionLocation::Local {
+                    local_path: Path::new(&cwd).join(local_path).display().to_string(),
+                    sha256,
+                }
+            }
+            (None, Some(url), Some(sha256)) => PythonDistributionLocation::Url { url, sha256 },
+            (_, _, None) => {
+                return Err(RuntimeError {
+                    code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                    message: "sha256 must be defined when local_path or url is defined".to_string(),
+                    label: "default_python_distribution()".to_string(),
+                }
+                .into())
+            }
+            (None, None, Some(_)) => {
+                return Err(RuntimeError {
+                    code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                    message: "sha256 requires local_path or url".to_string(),
+                    label: "default_python_distribution()".to_string(),
+                }
+                .into())
+            }
+        };
 
         let context = env.get("CONTEXT").expect("CONTEXT not defined");
         let dest_dir =
@@ -184,6 +330,46 @@ impl PythonDistribution {
         )))
     }
 
+    /// installed_python_distribution(python_exe=None, build_target=None)
+    fn installed_python_distribution(
+        env: &Environment,
+        python_exe: &Value,
+        build_target: &Value,
+    ) -> ValueResult {
+        let python_exe = optional_str_arg("python_exe", python_exe)?;
+        let build_target = optional_str_arg("build_target", build_target)?;
+
+        let python_exe = match python_exe {
+            Some(p) => p,
+            None => if cfg!(windows) {
+                "python.exe"
+            } else {
+                "python3"
+            }
+            .to_string(),
+        };
+
+        let build_target = match build_target {
+            Some(t) => t,
+            None => env.get("BUILD_TARGET_TRIPLE").unwrap().to_string(),
+        };
+
+        let location = PythonDistributionLocation::Installed {
+            python_exe,
+            target_triple: build_target,
+        };
+
+        let context = env.get("CONTEXT").expect("CONTEXT not defined");
+        let dest_dir =
+            context.downcast_apply(|x: &EnvironmentContext| x.python_distributions_path.clone());
+
+        Ok(Value::new(PythonDistribution::from_location(
+            DistributionFlavor::Standalone,
+            location,
+            &dest_dir,
+        )))
+    }
+
     /// PythonDistribution()
     fn from_args(
         env: &Environment,
@@ -220,6 +406,7 @@ impl PythonDistribution {
 
         let flavor = match flavor.as_ref() {
             "standalone" => DistributionFlavor::Standalone,
+            "standalone_freethreaded" => DistributionFlavor::StandaloneFreethreaded,
             v => {
                 return Err(RuntimeError {
                     code: "PYOXIDIZER_BUILD",
@@ -250,6 +437,17 @@ impl PythonDistribution {
     ///     include_sources=true,
     ///     include_resources=true,
     ///     include_test=false,
+    ///     target_overlays=None,
+    ///     python_version_overlays=None,
+    ///     editable_installs_as_source_paths=false,
+    ///     deduplicate_resources=false,
+    ///     tcl_files_path=None,
+    ///     packed_resources_path=None,
+    ///     packed_resources_signing_key=None,
+    ///     packed_resources_encryption_key=None,
+    ///     packed_resources_alignment=None,
+    ///     packed_resources_import_profile=None,
+    ///     windows_subsystem="console",
     /// )
     #[allow(
         clippy::ptr_arg,
@@ -268,6 +466,17 @@ impl PythonDistribution {
         include_sources: &Value,
         include_resources: &Value,
         include_test: &Value,
+        target_overlays: &Value,
+        python_version_overlays: &Value,
+        editable_installs_as_source_paths: &Value,
+        deduplicate_resources: &Value,
+        tcl_files_path: &Value,
+        packed_resources_path: &Value,
+        packed_resources_signing_key: &Value,
+        packed_resources_encryption_key: &Value,
+        packed_resources_alignment: &Value,
+        packed_resources_import_profile: &Value,
+        windows_subsystem: &Value,
     ) -> ValueResult {
         let name = required_str_arg("name", &name)?;
         let resources_policy = required_str_arg("resources_policy", &resources_policy)?;
@@ -283,12 +492,63 @@ impl PythonDistribution {
         let include_sources = required_bool_arg("include_sources", &include_sources)?;
         let include_resources = required_bool_arg("include_resources", &include_resources)?;
         let include_test = required_bool_arg("include_test", &include_test)?;
+        let editable_installs_as_source_paths = required_bool_arg(
+            "editable_installs_as_source_paths",
+            &editable_installs_as_source_paths,
+        )?;
+        let deduplicate_resources =
+            required_bool_arg("deduplicate_resources", &deduplicate_resources)?;
+        let tcl_files_path = optional_str_arg("tcl_files_path", &tcl_files_path)?;
+        let packed_resources_path =
+            optional_str_arg("packed_resources_path", &packed_resources_path)?;
+        let packed_resources_signing_key = optional_str_arg(
+            "packed_resources_signing_key",
+            &packed_resources_signing_key,
+        )?
+        .map(|path| {
+            let cwd = env.get("CWD").expect("CWD not defined").to_string();
+            Path::new(&cwd).join(path)
+        });
+        let packed_resources_encryption_key = optional_str_arg(
+            "packed_resources_encryption_key",
+            &packed_resources_encryption_key,
+        )?
+        .map(|path| {
+            let cwd = env.get("CWD").expect("CWD not defined").to_string();
+            Path::new(&cwd).join(path)
+        });
+        optional_type_arg(
+            "packed_resources_alignment",
+            "int",
+            &packed_resources_alignment,
+        )?;
+        let packed_resources_import_profile = optional_str_arg(
+            "packed_resources_import_profile",
+            &packed_resources_import_profile,
+        )?
+        .map(|path| {
+            let cwd = env.get("CWD").expect("CWD not defined").to_string();
+            Path::new(&cwd).join(path)
+        });
+        let windows_subsystem = required_str_arg("windows_subsystem", &windows_subsystem)?;
+        optional_dict_arg("target_overlays", "string", "dict", &target_overlays)?;
+        optional_dict_arg(
+            "python_version_overlays",
+            "string",
+            "dict",
+            &python_version_overlays,
+        )?;
 
         let context = env.get("CONTEXT").expect("CONTEXT not defined");
         let logger = context.downcast_apply(|x: &EnvironmentContext| x.logger.clone());
-        let (host_triple, target_triple) = context.downcast_apply(|x: &EnvironmentContext| {
-            (x.build_host_triple.clone(), x.build_target_triple.clone())
-        });
+        let (host_triple, target_triple, watch_packed_resources_files) =
+            context.downcast_apply(|x: &EnvironmentContext| {
+                (
+                    x.build_host_triple.clone(),
+                    x.build_target_triple.clone(),
+                    x.watch_packed_resources_files,
+                )
+            });
 
         let resources_policy =
             PythonResourcesPolicy::try_from(resources_policy.as_str()).map_err(|e| {
@@ -310,6 +570,34 @@ impl PythonDistribution {
                 .into()
             })?;
 
+        let windows_subsystem = match windows_subsystem.as_ref() {
+            "console" => WindowsSubsystem::Console,
+            "windows" => WindowsSubsystem::Windows,
+            _ => {
+                return Err(RuntimeError {
+                    code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                    message: "windows_subsystem must be 'console' or 'windows'".to_string(),
+                    label: "invalid value for windows_subsystem".to_string(),
+                }
+                .into());
+            }
+        };
+
+        let packed_resources_alignment = match packed_resources_alignment.get_type() {
+            "NoneType" => None,
+            _ => match packed_resources_alignment.to_int()? {
+                alignment if alignment > 0 => Some(alignment as usize),
+                _ => {
+                    return Err(RuntimeError {
+                        code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        message: "packed_resources_alignment must be greater than 0".to_string(),
+                        label: "invalid value for packed_resources_alignment".to_string(),
+                    }
+                    .into());
+                }
+            },
+        };
+
         let preferred_extension_module_variants =
             match preferred_extension_module_variants.get_type() {
                 "NoneType" => None,
@@ -328,6 +616,21 @@ impl PythonDistribution {
                 _ => panic!("type should have been validated above"),
             };
 
+        check_build_configuration(
+            &host_triple,
+            &target_triple,
+            &self.flavor,
+            &BinaryLibpythonLinkMode::Default,
+        )
+        .map_err(|e| {
+            RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: e.to_string(),
+                label: "to_python_executable()".to_string(),
+            }
+            .into()
+        })?;
+
         self.ensure_distribution_resolved(&logger).map_err(|e| {
             RuntimeError {
                 code: "PYOXIDIZER_BUILD",
@@ -351,6 +654,8 @@ impl PythonDistribution {
         policy.set_include_distribution_sources(include_sources);
         policy.set_include_distribution_resources(include_resources);
         policy.set_include_test(include_test);
+        policy.set_editable_installs_as_source_paths(editable_installs_as_source_paths);
+        policy.set_deduplicate_resources(deduplicate_resources);
 
         if let Some(variants) = preferred_extension_module_variants {
             for (ext, variant) in variants {
@@ -358,7 +663,45 @@ impl PythonDistribution {
             }
         }
 
-        let config = if config.get_type() == "NoneType" {
+        if target_overlays.get_type() == "dict" {
+            for k in target_overlays.into_iter()? {
+                let overlay =
+                    policy_overlay_from_dict("target_overlays", &target_overlays.at(k.clone())?)?;
+
+                policy
+                    .add_target_overlay(&k.to_string(), overlay)
+                    .map_err(|e| {
+                        RuntimeError {
+                            code: "PYOXIDIZER_BUILD",
+                            message: e.to_string(),
+                            label: "target_overlays".to_string(),
+                        }
+                        .into()
+                    })?;
+            }
+        }
+
+        if python_version_overlays.get_type() == "dict" {
+            for k in python_version_overlays.into_iter()? {
+                let overlay = policy_overlay_from_dict(
+                    "python_version_overlays",
+                    &python_version_overlays.at(k.clone())?,
+                )?;
+
+                policy
+                    .add_python_version_overlay(&k.to_string(), overlay)
+                    .map_err(|e| {
+                        RuntimeError {
+                            code: "PYOXIDIZER_BUILD",
+                            message: e.to_string(),
+                            label: "python_version_overlays".to_string(),
+                        }
+                        .into()
+                    })?;
+            }
+        }
+
+        let mut config = if config.get_type() == "NoneType" {
             let v = env
                 .get("PythonInterpreterConfig")
                 .expect("PythonInterpreterConfig not defined");
@@ -368,27 +711,39 @@ impl PythonDistribution {
             config.downcast_apply(|c: &EmbeddedPythonConfig| c.clone())
         };
 
-        Ok(Value::new(PythonExecutable {
-            exe: dist
-                .as_python_executable_builder(
-                    &logger,
-                    &host_triple,
-                    &target_triple,
-                    &name,
-                    // TODO make configurable
-                    BinaryLibpythonLinkMode::Default,
-                    &policy,
-                    &config,
-                )
-                .map_err(|e| {
-                    RuntimeError {
-                        code: "PYOXIDIZER_BUILD",
-                        message: e.to_string(),
-                        label: "to_python_executable()".to_string(),
-                    }
-                    .into()
-                })?,
-        }))
+        if watch_packed_resources_files {
+            config.watch_packed_resources_files = true;
+        }
+
+        let mut exe = dist
+            .as_python_executable_builder(
+                &logger,
+                &host_triple,
+                &target_triple,
+                &name,
+                // TODO make configurable
+                BinaryLibpythonLinkMode::Default,
+                &policy,
+                &config,
+            )
+            .map_err(|e| {
+                RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: e.to_string(),
+                    label: "to_python_executable()".to_string(),
+                }
+                .into()
+            })?;
+
+        exe.set_tcl_files_path(tcl_files_path);
+        exe.set_packed_resources_path(packed_resources_path);
+        exe.set_packed_resources_signing_key(packed_resources_signing_key);
+        exe.set_packed_resources_encryption_key(packed_resources_encryption_key);
+        exe.set_packed_resources_alignment(packed_resources_alignment);
+        exe.set_packed_resources_import_profile(packed_resources_import_profile);
+        exe.set_windows_subsystem(windows_subsystem);
+
+        Ok(Value::new(PythonExecutable { exe }))
     }
 
     /// PythonDistribution.extension_modules(filter="all", preferred_variants=None)
@@ -461,6 +816,67 @@ impl PythonDistribution {
         ))
     }
 
+    /// PythonDistribution.trim(packages=None, include_test=false, encodings=None)
+    pub fn trim(
+        &mut self,
+        env: &Environment,
+        packages: &Value,
+        include_test: &Value,
+        encodings: &Value,
+    ) -> ValueResult {
+        optional_list_arg("packages", "string", &packages)?;
+        let include_test = required_bool_arg("include_test", &include_test)?;
+        optional_list_arg("encodings", "string", &encodings)?;
+
+        let packages = match packages.get_type() {
+            "list" => packages
+                .into_iter()?
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>(),
+            "NoneType" => DEFAULT_TRIMMED_PACKAGES
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>(),
+            _ => panic!("should have validated type above"),
+        };
+        let encodings = match encodings.get_type() {
+            "list" => Some(
+                encodings
+                    .into_iter()?
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>(),
+            ),
+            "NoneType" => None,
+            _ => panic!("should have validated type above"),
+        };
+
+        let context = env.get("CONTEXT").expect("CONTEXT not defined");
+
+        let logger = context.downcast_apply(|x: &EnvironmentContext| x.logger.clone());
+
+        self.ensure_distribution_resolved(&logger).map_err(|e| {
+            RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: e.to_string(),
+                label: "resolve_distribution()".to_string(),
+            }
+            .into()
+        })?;
+
+        let trimmed = self
+            .distribution
+            .as_ref()
+            .unwrap()
+            .trim(&DistributionTrimming {
+                packages,
+                include_test,
+                encodings,
+            });
+        self.distribution = Some(Arc::new(trimmed));
+
+        Ok(Value::new(None))
+    }
+
     /// PythonDistribution.source_modules()
     pub fn source_modules(&mut self, env: &Environment) -> ValueResult {
         let context = env.get("CONTEXT").expect("CONTEXT not defined");
@@ -526,6 +942,13 @@ starlark_module! { python_distribution_module =>
         })
     }
 
+    #[allow(clippy::ptr_arg)]
+    PythonDistribution.trim(env env, this, packages=None, include_test=false, encodings=None) {
+        this.downcast_apply_mut(|dist: &mut PythonDistribution| {
+            dist.trim(&env, &packages, &include_test, &encodings)
+        })
+    }
+
     #[allow(non_snake_case, clippy::ptr_arg)]
     PythonDistribution.to_python_executable(
         env env,
@@ -538,7 +961,18 @@ starlark_module! { python_distribution_module =>
         preferred_extension_module_variants=None,
         include_sources=true,
         include_resources=false,
-        include_test=false
+        include_test=false,
+        target_overlays=None,
+        python_version_overlays=None,
+        editable_installs_as_source_paths=false,
+        deduplicate_resources=false,
+        tcl_files_path=None,
+        packed_resources_path=None,
+        packed_resources_signing_key=None,
+        packed_resources_encryption_key=None,
+        packed_resources_alignment=None,
+        packed_resources_import_profile=None,
+        windows_subsystem="console"
     ) {
         this.downcast_apply_mut(|dist: &mut PythonDistribution| {
             dist.to_python_executable_starlark(
@@ -552,13 +986,43 @@ starlark_module! { python_distribution_module =>
                 &include_sources,
                 &include_resources,
                 &include_test,
+                &target_overlays,
+                &python_version_overlays,
+                &editable_installs_as_source_paths,
+                &deduplicate_resources,
+                &tcl_files_path,
+                &packed_resources_path,
+                &packed_resources_signing_key,
+                &packed_resources_encryption_key,
+                &packed_resources_alignment,
+                &packed_resources_import_profile,
+                &windows_subsystem,
             )
         })
     }
 
     #[allow(clippy::ptr_arg)]
-    default_python_distribution(env env, flavor="standalone", build_target=None) {
-        PythonDistribution::default_python_distribution(&env, &flavor, &build_target)
+    default_python_distribution(
+        env env,
+        flavor="standalone",
+        build_target=None,
+        local_path=None,
+        url=None,
+        sha256=None
+    ) {
+        PythonDistribution::default_python_distribution(
+            &env,
+            &flavor,
+            &build_target,
+            &local_path,
+            &url,
+            &sha256,
+        )
+    }
+
+    #[allow(clippy::ptr_arg)]
+    installed_python_distribution(env env, python_exe=None, build_target=None) {
+        PythonDistribution::installed_python_distribution(&env, &python_exe, &build_target)
     }
 }
 
@@ -595,6 +1059,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_default_python_distribution_url() {
+        let dist = starlark_ok(
+            "default_python_distribution(url='https://mirror.example.com/python.tar.zst', sha256='deadbeef')",
+        );
+
+        let wanted = PythonDistributionLocation::Url {
+            url: "https://mirror.example.com/python.tar.zst".to_string(),
+            sha256: "deadbeef".to_string(),
+        };
+
+        dist.downcast_apply(|x: &PythonDistribution| assert_eq!(x.source, wanted));
+    }
+
+    #[test]
+    fn test_default_python_distribution_local_path() {
+        let dist = starlark_ok(
+            "default_python_distribution(local_path='python.tar.zst', sha256='deadbeef')",
+        );
+
+        let wanted = PythonDistributionLocation::Local {
+            local_path: std::env::current_dir()
+                .unwrap()
+                .join("python.tar.zst")
+                .display()
+                .to_string(),
+            sha256: "deadbeef".to_string(),
+        };
+
+        dist.downcast_apply(|x: &PythonDistribution| assert_eq!(x.source, wanted));
+    }
+
+    #[test]
+    fn test_default_python_distribution_no_sha256() {
+        let err = starlark_nok("default_python_distribution(local_path='python.tar.zst')");
+        assert_eq!(
+            err.message,
+            "sha256 must be defined when local_path or url is defined"
+        );
+    }
+
     #[test]
     #[cfg(windows)]
     fn test_default_python_distribution_dynamic_windows() {
@@ -613,6 +1118,19 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_installed_python_distribution() {
+        let dist = starlark_ok("installed_python_distribution('/usr/bin/python3')");
+        assert_eq!(dist.get_type(), "PythonDistribution");
+
+        let wanted = PythonDistributionLocation::Installed {
+            python_exe: "/usr/bin/python3".to_string(),
+            target_triple: crate::project_building::HOST.to_string(),
+        };
+
+        dist.downcast_apply(|x: &PythonDistribution| assert_eq!(x.source, wanted));
+    }
+
     #[test]
     fn test_python_distribution_no_args() {
         let err = starlark_nok("PythonDistribution()");
@@ -672,4 +1190,39 @@ mod tests {
 
         assert!(default_length < data_length);
     }
+
+    #[test]
+    fn test_trim() {
+        let mut env = starlark_env();
+
+        starlark_eval_in_env(&mut env, "dist = default_python_distribution()").unwrap();
+        let before = starlark_eval_in_env(&mut env, "dist.source_modules()")
+            .unwrap()
+            .length()
+            .unwrap();
+
+        starlark_eval_in_env(&mut env, "dist.trim(encodings=['cp437'])").unwrap();
+        let after = starlark_eval_in_env(&mut env, "dist.source_modules()")
+            .unwrap()
+            .length()
+            .unwrap();
+
+        assert!(after < before);
+
+        let lib2to3 = starlark_eval_in_env(
+            &mut env,
+            "[m for m in dist.source_modules() if m.name.startswith('lib2to3')]",
+        )
+        .unwrap();
+        assert_eq!(lib2to3.length().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_trim_bad_arg() {
+        let err = starlark_nok("default_python_distribution().trim(packages='tkinter')");
+        assert_eq!(
+            err.message,
+            "function expects a list for packages; got type string"
+        );
+    }
 }
@@ -4,10 +4,17 @@
 
 use crate::py_packaging::config::RunMode;
 use {
-    super::util::{optional_list_arg, optional_str_arg, required_bool_arg, required_type_arg},
+    super::util::{
+        optional_bool_arg, optional_list_arg, optional_str_arg, optional_type_arg,
+        required_bool_arg, required_list_arg, required_str_arg, required_type_arg,
+    },
     crate::py_packaging::config::{
-        default_raw_allocator, EmbeddedPythonConfig, RawAllocator, TerminfoResolution,
+        default_raw_allocator, Allocator, CertificateResolution, CheckHashPYCsMode, CoerceCLocale,
+        EmbeddedPythonConfig, InMemoryModuleFile, MultiprocessingStartMethod, RawAllocator,
+        ServerInterface, SitePackagesOverlay, SitePackagesOverlayPriority, StdioRedirect,
+        TerminfoResolution,
     },
+    python_packaging::resource::entry_point_run_code,
     starlark::environment::Environment,
     starlark::values::{
         default_compare, RuntimeError, TypedValue, Value, ValueError, ValueResult,
@@ -70,24 +77,83 @@ impl EmbeddedPythonConfig {
         stdio_encoding: &Value,
         unbuffered_stdio: &Value,
         filesystem_importer: &Value,
+        in_memory_module_file: &Value,
+        in_memory_package_path: &Value,
+        lazy_modules: &Value,
         quiet: &Value,
+        run_asgi_app: &Value,
+        run_entry_point: &Value,
         run_eval: &Value,
         run_file: &Value,
+        run_file_from_args: &Value,
+        run_jupyter_kernel: &Value,
+        run_jupyter_kernel_display_name: &Value,
+        run_main_module: &Value,
         run_module: &Value,
+        run_module_args: &Value,
         run_noop: &Value,
         run_repl: &Value,
+        run_wsgi_app: &Value,
+        server_address: &Value,
+        server_max_request_body_size: &Value,
         site_import: &Value,
         sys_frozen: &Value,
         sys_meipass: &Value,
         sys_paths: &Value,
         raw_allocator: &Value,
+        raw_allocator_mem_domain: &Value,
+        repl_history_file: &Value,
+        repl_line_editing: &Value,
         terminfo_resolution: &Value,
         terminfo_dirs: &Value,
+        ssl_certificate_resolution: &Value,
+        ssl_ca_bundle: &Value,
         use_hash_seed: &Value,
         user_site_directory: &Value,
         verbose: &Value,
+        gil_enabled: &Value,
         write_bytecode: &Value,
         write_modules_directory_env: &Value,
+        startup_profile_env: &Value,
+        packed_resources_key_env: &Value,
+        stdio_redirect: &Value,
+        stdio_redirect_path: &Value,
+        allocate_console_args: &Value,
+        site_packages_overlay: &Value,
+        site_packages_overlay_priority: &Value,
+        zip_archives: &Value,
+        packed_resources_files: &Value,
+        watch_packed_resources_files: &Value,
+        sys_argv_skip: &Value,
+        sys_argv_insert: &Value,
+        environment_allowlist: &Value,
+        allocator: &Value,
+        configure_locale: &Value,
+        coerce_c_locale: &Value,
+        coerce_c_locale_warn: &Value,
+        development_mode: &Value,
+        parse_argv: &Value,
+        utf8_mode: &Value,
+        check_hash_pycs_mode: &Value,
+        configure_c_stdio: &Value,
+        dump_refs: &Value,
+        fault_handler: &Value,
+        filesystem_encoding: &Value,
+        filesystem_errors: &Value,
+        hash_seed: &Value,
+        import_time: &Value,
+        install_signal_handlers: &Value,
+        malloc_stats: &Value,
+        pathconfig_warnings: &Value,
+        pycache_prefix: &Value,
+        show_alloc_count: &Value,
+        show_ref_count: &Value,
+        skip_first_source_line: &Value,
+        tracemalloc: &Value,
+        warn_options: &Value,
+        x_options: &Value,
+        multiprocessing_auto_dispatch: &Value,
+        multiprocessing_start_method: &Value,
     ) -> ValueResult {
         required_type_arg("bytes_warning", "int", &bytes_warning)?;
         let ignore_environment = required_bool_arg("ignore_environment", &ignore_environment)?;
@@ -103,35 +169,148 @@ impl EmbeddedPythonConfig {
         let stdio_encoding = optional_str_arg("stdio_encoding", &stdio_encoding)?;
         let unbuffered_stdio = required_bool_arg("unbuffered_stdio", &unbuffered_stdio)?;
         let filesystem_importer = required_bool_arg("filesystem_importer", &filesystem_importer)?;
+        let in_memory_module_file =
+            required_str_arg("in_memory_module_file", &in_memory_module_file)?;
+        let in_memory_package_path =
+            required_bool_arg("in_memory_package_path", &in_memory_package_path)?;
+        optional_list_arg("lazy_modules", "string", &lazy_modules)?;
         let quiet = required_bool_arg("quiet", &quiet)?;
-        let run_eval = optional_str_arg("run_eval", &run_eval)?;
+        let run_asgi_app = optional_str_arg("run_asgi_app", &run_asgi_app)?;
+        let run_entry_point = optional_str_arg("run_entry_point", &run_entry_point)?;
+        let run_eval = match run_eval.get_type() {
+            "list" => {
+                required_list_arg("run_eval", "string", &run_eval)?;
+                Some(
+                    run_eval
+                        .into_iter()?
+                        .map(|x| x.to_string())
+                        .collect::<Vec<_>>(),
+                )
+            }
+            _ => optional_str_arg("run_eval", &run_eval)?.map(|code| vec![code]),
+        };
         let run_file = optional_str_arg("run_file", &run_file)?;
+        let run_file_from_args = required_bool_arg("run_file_from_args", &run_file_from_args)?;
+        let run_jupyter_kernel = optional_str_arg("run_jupyter_kernel", &run_jupyter_kernel)?;
+        let run_jupyter_kernel_display_name = optional_str_arg(
+            "run_jupyter_kernel_display_name",
+            &run_jupyter_kernel_display_name,
+        )?;
+        let run_main_module = optional_str_arg("run_main_module", &run_main_module)?;
         let run_module = optional_str_arg("run_module", &run_module)?;
+        optional_list_arg("run_module_args", "string", &run_module_args)?;
         let run_noop = required_bool_arg("run_noop", &run_noop)?;
         let run_repl = required_bool_arg("run_repl", &run_repl)?;
+        let run_wsgi_app = optional_str_arg("run_wsgi_app", &run_wsgi_app)?;
+        let server_address = required_str_arg("server_address", &server_address)?;
+        required_type_arg(
+            "server_max_request_body_size",
+            "int",
+            &server_max_request_body_size,
+        )?;
         let sys_frozen = required_bool_arg("sys_frozen", &sys_frozen)?;
         let sys_meipass = required_bool_arg("sys_meipass", &sys_meipass)?;
         optional_list_arg("sys_paths", "string", &sys_paths)?;
         let raw_allocator = optional_str_arg("raw_allocator", &raw_allocator)?;
+        let raw_allocator_mem_domain =
+            required_bool_arg("raw_allocator_mem_domain", &raw_allocator_mem_domain)?;
+        let repl_history_file = optional_str_arg("repl_history_file", &repl_history_file)?;
+        let repl_line_editing = required_bool_arg("repl_line_editing", &repl_line_editing)?;
         let site_import = required_bool_arg("site_importer", &site_import)?;
         let terminfo_resolution = optional_str_arg("terminfo_resolution", &terminfo_resolution)?;
         let terminfo_dirs = optional_str_arg("terminfo_dirs", &terminfo_dirs)?;
+        let ssl_certificate_resolution =
+            optional_str_arg("ssl_certificate_resolution", &ssl_certificate_resolution)?;
+        let ssl_ca_bundle = optional_str_arg("ssl_ca_bundle", &ssl_ca_bundle)?;
         let use_hash_seed = required_bool_arg("use_hash_seed", &use_hash_seed)?;
         let user_site_directory = required_bool_arg("user_site_directory", &user_site_directory)?;
         required_type_arg("verbose", "int", &verbose)?;
+        optional_type_arg("gil_enabled", "bool", &gil_enabled)?;
         let write_bytecode = required_bool_arg("write_bytecode", &write_bytecode)?;
         let write_modules_directory_env =
             optional_str_arg("write_modules_directory_env", &write_modules_directory_env)?;
+        let startup_profile_env = optional_str_arg("startup_profile_env", &startup_profile_env)?;
+        let packed_resources_key_env =
+            optional_str_arg("packed_resources_key_env", &packed_resources_key_env)?;
+        let stdio_redirect = optional_str_arg("stdio_redirect", &stdio_redirect)?;
+        let stdio_redirect_path = optional_str_arg("stdio_redirect_path", &stdio_redirect_path)?;
+        optional_list_arg("allocate_console_args", "string", &allocate_console_args)?;
+        let site_packages_overlay =
+            optional_str_arg("site_packages_overlay", &site_packages_overlay)?;
+        let site_packages_overlay_priority = required_str_arg(
+            "site_packages_overlay_priority",
+            &site_packages_overlay_priority,
+        )?;
+        optional_list_arg("zip_archives", "string", &zip_archives)?;
+        optional_list_arg("packed_resources_files", "string", &packed_resources_files)?;
+        let watch_packed_resources_files = required_bool_arg(
+            "watch_packed_resources_files",
+            &watch_packed_resources_files,
+        )?;
+        required_type_arg("sys_argv_skip", "int", &sys_argv_skip)?;
+        optional_list_arg("sys_argv_insert", "string", &sys_argv_insert)?;
+        optional_list_arg("environment_allowlist", "string", &environment_allowlist)?;
+        let allocator = optional_str_arg("allocator", &allocator)?;
+        let configure_locale = optional_bool_arg("configure_locale", &configure_locale)?;
+        let coerce_c_locale = optional_str_arg("coerce_c_locale", &coerce_c_locale)?;
+        let coerce_c_locale_warn =
+            optional_bool_arg("coerce_c_locale_warn", &coerce_c_locale_warn)?;
+        let development_mode = optional_bool_arg("development_mode", &development_mode)?;
+        let parse_argv = optional_bool_arg("parse_argv", &parse_argv)?;
+        let utf8_mode = optional_bool_arg("utf8_mode", &utf8_mode)?;
+        let check_hash_pycs_mode = optional_str_arg("check_hash_pycs_mode", &check_hash_pycs_mode)?;
+        let configure_c_stdio = optional_bool_arg("configure_c_stdio", &configure_c_stdio)?;
+        let dump_refs = optional_bool_arg("dump_refs", &dump_refs)?;
+        let fault_handler = optional_bool_arg("fault_handler", &fault_handler)?;
+        let filesystem_encoding = optional_str_arg("filesystem_encoding", &filesystem_encoding)?;
+        let filesystem_errors = optional_str_arg("filesystem_errors", &filesystem_errors)?;
+        optional_type_arg("hash_seed", "int", &hash_seed)?;
+        let import_time = optional_bool_arg("import_time", &import_time)?;
+        let install_signal_handlers =
+            optional_bool_arg("install_signal_handlers", &install_signal_handlers)?;
+        let malloc_stats = optional_bool_arg("malloc_stats", &malloc_stats)?;
+        let pathconfig_warnings = optional_bool_arg("pathconfig_warnings", &pathconfig_warnings)?;
+        let pycache_prefix = optional_str_arg("pycache_prefix", &pycache_prefix)?;
+        let show_alloc_count = optional_bool_arg("show_alloc_count", &show_alloc_count)?;
+        let show_ref_count = optional_bool_arg("show_ref_count", &show_ref_count)?;
+        let skip_first_source_line =
+            optional_bool_arg("skip_first_source_line", &skip_first_source_line)?;
+        let tracemalloc = optional_bool_arg("tracemalloc", &tracemalloc)?;
+        optional_list_arg("warn_options", "string", &warn_options)?;
+        optional_list_arg("x_options", "string", &x_options)?;
+        let multiprocessing_auto_dispatch = required_bool_arg(
+            "multiprocessing_auto_dispatch",
+            &multiprocessing_auto_dispatch,
+        )?;
+        let multiprocessing_start_method = optional_str_arg(
+            "multiprocessing_start_method",
+            &multiprocessing_start_method,
+        )?;
 
         let build_target = env.get("BUILD_TARGET_TRIPLE").unwrap().to_str();
 
         let mut run_count = 0;
+        if run_asgi_app.is_some() {
+            run_count += 1;
+        }
+        if run_entry_point.is_some() {
+            run_count += 1;
+        }
         if run_eval.is_some() {
             run_count += 1;
         }
         if run_file.is_some() {
             run_count += 1;
         }
+        if run_file_from_args {
+            run_count += 1;
+        }
+        if run_jupyter_kernel.is_some() {
+            run_count += 1;
+        }
+        if run_main_module.is_some() {
+            run_count += 1;
+        }
         if run_module.is_some() {
             run_count += 1;
         }
@@ -141,6 +320,9 @@ impl EmbeddedPythonConfig {
         if run_repl {
             run_count += 1;
         }
+        if run_wsgi_app.is_some() {
+            run_count += 1;
+        }
 
         if run_count > 1 {
             return Err(RuntimeError {
@@ -151,12 +333,111 @@ impl EmbeddedPythonConfig {
             .into());
         }
 
-        let run_mode = if let Some(code) = run_eval {
+        let run_module_args = match run_module_args.get_type() {
+            "list" => Some(
+                run_module_args
+                    .into_iter()?
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>(),
+            ),
+            _ => None,
+        };
+
+        if run_jupyter_kernel_display_name.is_some() && run_jupyter_kernel.is_none() {
+            return Err(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: "run_jupyter_kernel_display_name requires run_jupyter_kernel".to_string(),
+                label: "PythonInterpreterConfig()".to_string(),
+            }
+            .into());
+        }
+
+        let server_max_request_body_size = server_max_request_body_size.to_int()?;
+        if server_max_request_body_size < 0 {
+            return Err(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: "server_max_request_body_size must not be negative".to_string(),
+                label: "invalid value for server_max_request_body_size".to_string(),
+            }
+            .into());
+        }
+
+        if server_address.parse::<std::net::SocketAddr>().is_err() {
+            return Err(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: format!(
+                    "invalid server_address {}; expected an address like 127.0.0.1:8000",
+                    server_address
+                ),
+                label: "PythonInterpreterConfig()".to_string(),
+            }
+            .into());
+        }
+
+        if run_module_args.is_some() && run_module.is_none() {
+            return Err(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: "run_module_args requires run_module".to_string(),
+                label: "PythonInterpreterConfig()".to_string(),
+            }
+            .into());
+        }
+
+        let run_mode = if let Some(entry_point) = run_entry_point {
+            // Accept both `name = module:attr` and `module:attr`.
+            let value = match entry_point.find('=') {
+                Some(idx) => &entry_point[idx + 1..],
+                None => entry_point.as_str(),
+            };
+
+            let code = entry_point_run_code(value).map_err(|e| {
+                RuntimeError {
+                    code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                    message: e.to_string(),
+                    label: "PythonInterpreterConfig()".to_string(),
+                }
+                .into()
+            })?;
+
             RunMode::Eval { code }
+        } else if let Some(mut code) = run_eval {
+            if code.len() == 1 {
+                RunMode::Eval {
+                    code: code.remove(0),
+                }
+            } else {
+                RunMode::EvalMany { code }
+            }
         } else if let Some(path) = run_file {
             RunMode::File { path }
+        } else if run_file_from_args {
+            RunMode::FileFromArgs
+        } else if let Some(name) = run_jupyter_kernel {
+            RunMode::JupyterKernel {
+                display_name: run_jupyter_kernel_display_name.unwrap_or_else(|| name.clone()),
+                name,
+            }
+        } else if let Some(module) = run_main_module {
+            RunMode::MainModule { module }
         } else if let Some(module) = run_module {
-            RunMode::Module { module }
+            match run_module_args {
+                Some(args) => RunMode::ModuleWithArgs { module, args },
+                None => RunMode::Module { module },
+            }
+        } else if let Some(application) = run_wsgi_app {
+            RunMode::Server {
+                application,
+                interface: ServerInterface::Wsgi,
+                address: server_address,
+                max_request_body_size: server_max_request_body_size as usize,
+            }
+        } else if let Some(application) = run_asgi_app {
+            RunMode::Server {
+                application,
+                interface: ServerInterface::Asgi,
+                address: server_address,
+                max_request_body_size: server_max_request_body_size as usize,
+            }
         } else if run_noop {
             RunMode::Noop
         } else {
@@ -173,7 +454,9 @@ impl EmbeddedPythonConfig {
         let raw_allocator = match raw_allocator {
             Some(x) => match x.as_ref() {
                 "jemalloc" => RawAllocator::Jemalloc,
+                "mimalloc" => RawAllocator::Mimalloc,
                 "rust" => RawAllocator::Rust,
+                "snmalloc" => RawAllocator::Snmalloc,
                 "system" => RawAllocator::System,
                 _ => {
                     return Err(RuntimeError {
@@ -187,6 +470,19 @@ impl EmbeddedPythonConfig {
             None => default_raw_allocator(&build_target),
         };
 
+        if !raw_allocator.is_supported(&build_target) {
+            return Err(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: format!(
+                    "raw_allocator {} is not supported on {}",
+                    format!("{:?}", raw_allocator).to_lowercase(),
+                    build_target
+                ),
+                label: "PythonInterpreterConfig()".to_string(),
+            }
+            .into());
+        }
+
         let terminfo_resolution = match terminfo_resolution {
             Some(x) => match x.as_ref() {
                 "dynamic" => TerminfoResolution::Dynamic,
@@ -213,6 +509,159 @@ impl EmbeddedPythonConfig {
             None => TerminfoResolution::None,
         };
 
+        let ssl_certificate_resolution = match ssl_certificate_resolution {
+            Some(x) => match x.as_ref() {
+                "dynamic" => CertificateResolution::Dynamic,
+                "static" => CertificateResolution::Static(if let Some(path) = ssl_ca_bundle {
+                    path
+                } else {
+                    return Err(RuntimeError {
+                        code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        message: "ssl_ca_bundle must be set when using static resolution"
+                            .to_string(),
+                        label: "ssl_ca_bundle must be set when using static resolution".to_string(),
+                    }
+                    .into());
+                }),
+                _ => {
+                    return Err(RuntimeError {
+                        code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        message: "ssl_certificate_resolution must be 'dynamic' or 'static'"
+                            .to_string(),
+                        label: "ssl_certificate_resolution must be 'dynamic' or 'static'"
+                            .to_string(),
+                    }
+                    .into());
+                }
+            },
+            None => CertificateResolution::None,
+        };
+
+        let stdio_redirect = match stdio_redirect {
+            Some(x) => match x.as_ref() {
+                "file" => StdioRedirect::File(if let Some(path) = stdio_redirect_path {
+                    path
+                } else {
+                    return Err(RuntimeError {
+                        code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        message: "stdio_redirect_path must be set when redirecting to a file"
+                            .to_string(),
+                        label: "PythonInterpreterConfig()".to_string(),
+                    }
+                    .into());
+                }),
+                "debugger" => StdioRedirect::Debugger,
+                _ => {
+                    return Err(RuntimeError {
+                        code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        message: "stdio_redirect must be 'file' or 'debugger'".to_string(),
+                        label: "invalid value for stdio_redirect".to_string(),
+                    }
+                    .into());
+                }
+            },
+            None => StdioRedirect::None,
+        };
+
+        let allocate_console_args = match allocate_console_args.get_type() {
+            "list" => allocate_console_args
+                .into_iter()
+                .unwrap()
+                .map(|x| x.to_string())
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let in_memory_module_file = match in_memory_module_file.as_ref() {
+            "none" => InMemoryModuleFile::None,
+            "uri" => InMemoryModuleFile::Uri,
+            "path" => InMemoryModuleFile::Path,
+            _ => {
+                return Err(RuntimeError {
+                    code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                    message: "in_memory_module_file must be 'none', 'uri', or 'path'".to_string(),
+                    label: "invalid value for in_memory_module_file".to_string(),
+                }
+                .into());
+            }
+        };
+
+        let site_packages_overlay_priority = match site_packages_overlay_priority.as_ref() {
+            "before" => SitePackagesOverlayPriority::Before,
+            "after" => SitePackagesOverlayPriority::After,
+            _ => {
+                return Err(RuntimeError {
+                    code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                    message: "site_packages_overlay_priority must be 'before' or 'after'"
+                        .to_string(),
+                    label: "invalid value for site_packages_overlay_priority".to_string(),
+                }
+                .into());
+            }
+        };
+
+        let site_packages_overlay = site_packages_overlay.map(|path| SitePackagesOverlay {
+            path,
+            priority: site_packages_overlay_priority,
+        });
+
+        let zip_archives = match zip_archives.get_type() {
+            "list" => zip_archives
+                .into_iter()
+                .unwrap()
+                .map(|x| x.to_string())
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let packed_resources_files = match packed_resources_files.get_type() {
+            "list" => packed_resources_files
+                .into_iter()
+                .unwrap()
+                .map(|x| x.to_string())
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let sys_argv_skip = sys_argv_skip.to_int()?;
+        if sys_argv_skip < 0 {
+            return Err(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: "sys_argv_skip must not be negative".to_string(),
+                label: "invalid value for sys_argv_skip".to_string(),
+            }
+            .into());
+        }
+
+        let lazy_modules = match lazy_modules.get_type() {
+            "list" => lazy_modules
+                .into_iter()
+                .unwrap()
+                .map(|x| x.to_string())
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let sys_argv_insert = match sys_argv_insert.get_type() {
+            "list" => sys_argv_insert
+                .into_iter()
+                .unwrap()
+                .map(|x| x.to_string())
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let environment_allowlist = match environment_allowlist.get_type() {
+            "list" => Some(
+                environment_allowlist
+                    .into_iter()
+                    .unwrap()
+                    .map(|x| x.to_string())
+                    .collect(),
+            ),
+            _ => None,
+        };
+
         let sys_paths = match sys_paths.get_type() {
             "list" => sys_paths
                 .into_iter()
@@ -224,6 +673,120 @@ impl EmbeddedPythonConfig {
 
         let filesystem_importer = filesystem_importer || !sys_paths.is_empty();
 
+        let gil_enabled = match gil_enabled.get_type() {
+            "bool" => Some(gil_enabled.to_bool()),
+            _ => None,
+        };
+
+        let allocator = match allocator {
+            Some(x) => Some(match x.as_ref() {
+                "default" => Allocator::Default,
+                "debug" => Allocator::Debug,
+                "malloc" => Allocator::Malloc,
+                "malloc_debug" => Allocator::MallocDebug,
+                "pymalloc" => Allocator::PyMalloc,
+                "pymalloc_debug" => Allocator::PyMallocDebug,
+                _ => {
+                    return Err(RuntimeError {
+                        code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        message: "allocator must be 'default', 'debug', 'malloc', \
+                                  'malloc_debug', 'pymalloc' or 'pymalloc_debug'"
+                            .to_string(),
+                        label: "invalid value for allocator".to_string(),
+                    }
+                    .into());
+                }
+            }),
+            None => None,
+        };
+
+        let coerce_c_locale = match coerce_c_locale {
+            Some(x) => Some(match x.as_ref() {
+                "LC_CTYPE" => CoerceCLocale::LCCtype,
+                "C" => CoerceCLocale::C,
+                _ => {
+                    return Err(RuntimeError {
+                        code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        message: "coerce_c_locale must be 'LC_CTYPE' or 'C'".to_string(),
+                        label: "invalid value for coerce_c_locale".to_string(),
+                    }
+                    .into());
+                }
+            }),
+            None => None,
+        };
+
+        let check_hash_pycs_mode = match check_hash_pycs_mode {
+            Some(x) => Some(match x.as_ref() {
+                "always" => CheckHashPYCsMode::Always,
+                "never" => CheckHashPYCsMode::Never,
+                "default" => CheckHashPYCsMode::Default,
+                _ => {
+                    return Err(RuntimeError {
+                        code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        message: "check_hash_pycs_mode must be 'always', 'never' or 'default'"
+                            .to_string(),
+                        label: "invalid value for check_hash_pycs_mode".to_string(),
+                    }
+                    .into());
+                }
+            }),
+            None => None,
+        };
+
+        let multiprocessing_start_method = match multiprocessing_start_method {
+            Some(x) => Some(match x.as_ref() {
+                "fork" => MultiprocessingStartMethod::Fork,
+                "forkserver" => MultiprocessingStartMethod::ForkServer,
+                "spawn" => MultiprocessingStartMethod::Spawn,
+                _ => {
+                    return Err(RuntimeError {
+                        code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        message: "multiprocessing_start_method must be 'fork', 'forkserver' \
+                                  or 'spawn'"
+                            .to_string(),
+                        label: "invalid value for multiprocessing_start_method".to_string(),
+                    }
+                    .into());
+                }
+            }),
+            None => None,
+        };
+
+        let hash_seed = match hash_seed.get_type() {
+            "int" => {
+                let seed = hash_seed.to_int()?;
+                if seed < 0 {
+                    return Err(RuntimeError {
+                        code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        message: "hash_seed must not be negative".to_string(),
+                        label: "invalid value for hash_seed".to_string(),
+                    }
+                    .into());
+                }
+                Some(seed as u64)
+            }
+            _ => None,
+        };
+
+        let warn_options = match warn_options.get_type() {
+            "list" => warn_options
+                .into_iter()
+                .unwrap()
+                .map(|x| x.to_string())
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let x_options = match x_options.get_type() {
+            "list" => x_options
+                .into_iter()
+                .unwrap()
+                .map(|x| x.to_string())
+                .collect(),
+            _ => Vec::new(),
+        };
+
         Ok(Value::new(EmbeddedPythonConfig {
             bytes_warning: bytes_warning.to_int().unwrap() as i32,
             ignore_environment,
@@ -239,18 +802,66 @@ impl EmbeddedPythonConfig {
             stdio_encoding_errors,
             unbuffered_stdio,
             filesystem_importer,
+            in_memory_module_file,
+            in_memory_package_path,
+            lazy_modules,
             site_import,
             sys_frozen,
             sys_meipass,
             sys_paths,
             raw_allocator,
+            raw_allocator_mem_domain,
             run_mode,
             terminfo_resolution,
+            ssl_certificate_resolution,
+            tcl_library: None,
+            repl_line_editing,
+            repl_history_file,
             use_hash_seed,
             user_site_directory,
             verbose: verbose.to_int().unwrap() as i32,
+            gil_enabled,
             write_bytecode,
             write_modules_directory_env,
+            startup_profile_env,
+            packed_resources_key_env,
+            stdio_redirect,
+            allocate_console_args,
+            site_packages_overlay,
+            zip_archives,
+            packed_resources_files,
+            packed_resources_public_key: None,
+            watch_packed_resources_files,
+            sys_argv_skip: sys_argv_skip as usize,
+            sys_argv_insert,
+            environment_allowlist,
+            allocator,
+            configure_locale,
+            coerce_c_locale,
+            coerce_c_locale_warn,
+            development_mode,
+            parse_argv,
+            utf8_mode,
+            check_hash_pycs_mode,
+            configure_c_stdio,
+            dump_refs,
+            fault_handler,
+            filesystem_encoding,
+            filesystem_errors,
+            hash_seed,
+            import_time,
+            install_signal_handlers,
+            malloc_stats,
+            pathconfig_warnings,
+            pycache_prefix,
+            show_alloc_count,
+            show_ref_count,
+            skip_first_source_line,
+            tracemalloc,
+            warn_options,
+            x_options,
+            multiprocessing_auto_dispatch,
+            multiprocessing_start_method,
         }))
     }
 }
@@ -271,24 +882,83 @@ starlark_module! { embedded_python_config_module =>
         stdio_encoding=None,
         unbuffered_stdio=false,
         filesystem_importer=false,
+        in_memory_module_file="none",
+        in_memory_package_path=true,
+        lazy_modules=None,
         quiet=false,
+        run_asgi_app=None,
+        run_entry_point=None,
         run_eval=None,
         run_file=None,
+        run_file_from_args=false,
+        run_jupyter_kernel=None,
+        run_jupyter_kernel_display_name=None,
+        run_main_module=None,
         run_module=None,
+        run_module_args=None,
         run_noop=false,
         run_repl=false,
+        run_wsgi_app=None,
+        server_address="127.0.0.1:8000",
+        server_max_request_body_size=10485760,
         site_import=false,
         sys_frozen=false,
         sys_meipass=false,
         sys_paths=None,
         raw_allocator=None,
+        raw_allocator_mem_domain=false,
+        repl_history_file=None,
+        repl_line_editing=true,
         terminfo_resolution="dynamic",
         terminfo_dirs=None,
+        ssl_certificate_resolution="dynamic",
+        ssl_ca_bundle=None,
         use_hash_seed=false,
         user_site_directory=false,
         verbose=0,
+        gil_enabled=None,
         write_bytecode=false,
-        write_modules_directory_env=None
+        write_modules_directory_env=None,
+        startup_profile_env=None,
+        packed_resources_key_env=None,
+        stdio_redirect=None,
+        stdio_redirect_path=None,
+        allocate_console_args=None,
+        site_packages_overlay=None,
+        site_packages_overlay_priority="after",
+        zip_archives=None,
+        packed_resources_files=None,
+        watch_packed_resources_files=false,
+        sys_argv_skip=0,
+        sys_argv_insert=None,
+        environment_allowlist=None,
+        allocator=None,
+        configure_locale=None,
+        coerce_c_locale=None,
+        coerce_c_locale_warn=None,
+        development_mode=None,
+        parse_argv=None,
+        utf8_mode=None,
+        check_hash_pycs_mode=None,
+        configure_c_stdio=None,
+        dump_refs=None,
+        fault_handler=None,
+        filesystem_encoding=None,
+        filesystem_errors=None,
+        hash_seed=None,
+        import_time=None,
+        install_signal_handlers=None,
+        malloc_stats=None,
+        pathconfig_warnings=None,
+        pycache_prefix=None,
+        show_alloc_count=None,
+        show_ref_count=None,
+        skip_first_source_line=None,
+        tracemalloc=None,
+        warn_options=None,
+        x_options=None,
+        multiprocessing_auto_dispatch=true,
+        multiprocessing_start_method=None
     ) {
         EmbeddedPythonConfig::starlark_new(
             &env,
@@ -304,24 +974,83 @@ starlark_module! { embedded_python_config_module =>
             &stdio_encoding,
             &unbuffered_stdio,
             &filesystem_importer,
+            &in_memory_module_file,
+            &in_memory_package_path,
+            &lazy_modules,
             &quiet,
+            &run_asgi_app,
+            &run_entry_point,
             &run_eval,
             &run_file,
+            &run_file_from_args,
+            &run_jupyter_kernel,
+            &run_jupyter_kernel_display_name,
+            &run_main_module,
             &run_module,
+            &run_module_args,
             &run_noop,
             &run_repl,
+            &run_wsgi_app,
+            &server_address,
+            &server_max_request_body_size,
             &site_import,
             &sys_frozen,
             &sys_meipass,
             &sys_paths,
             &raw_allocator,
+            &raw_allocator_mem_domain,
+            &repl_history_file,
+            &repl_line_editing,
             &terminfo_resolution,
             &terminfo_dirs,
+            &ssl_certificate_resolution,
+            &ssl_ca_bundle,
             &use_hash_seed,
             &user_site_directory,
             &verbose,
+            &gil_enabled,
             &write_bytecode,
-            &write_modules_directory_env
+            &write_modules_directory_env,
+            &startup_profile_env,
+            &packed_resources_key_env,
+            &stdio_redirect,
+            &stdio_redirect_path,
+            &allocate_console_args,
+            &site_packages_overlay,
+            &site_packages_overlay_priority,
+            &zip_archives,
+            &packed_resources_files,
+            &watch_packed_resources_files,
+            &sys_argv_skip,
+            &sys_argv_insert,
+            &environment_allowlist,
+            &allocator,
+            &configure_locale,
+            &coerce_c_locale,
+            &coerce_c_locale_warn,
+            &development_mode,
+            &parse_argv,
+            &utf8_mode,
+            &check_hash_pycs_mode,
+            &configure_c_stdio,
+            &dump_refs,
+            &fault_handler,
+            &filesystem_encoding,
+            &filesystem_errors,
+            &hash_seed,
+            &import_time,
+            &install_signal_handlers,
+            &malloc_stats,
+            &pathconfig_warnings,
+            &pycache_prefix,
+            &show_alloc_count,
+            &show_ref_count,
+            &skip_first_source_line,
+            &tracemalloc,
+            &warn_options,
+            &x_options,
+            &multiprocessing_auto_dispatch,
+            &multiprocessing_start_method
         )
     }
 }
@@ -348,20 +1077,68 @@ mod tests {
             quiet: false,
             use_hash_seed: false,
             verbose: 0,
+            gil_enabled: None,
             stdio_encoding_name: None,
             stdio_encoding_errors: None,
             unbuffered_stdio: false,
             filesystem_importer: false,
+            in_memory_module_file: InMemoryModuleFile::None,
+            in_memory_package_path: true,
+            lazy_modules: Vec::new(),
             site_import: false,
             sys_frozen: false,
             sys_meipass: false,
             sys_paths: Vec::new(),
             raw_allocator: default_raw_allocator(crate::project_building::HOST),
+            raw_allocator_mem_domain: false,
             run_mode: RunMode::Repl,
             terminfo_resolution: TerminfoResolution::Dynamic,
+            ssl_certificate_resolution: CertificateResolution::Dynamic,
+            tcl_library: None,
+            repl_line_editing: true,
+            repl_history_file: None,
             user_site_directory: false,
             write_bytecode: false,
             write_modules_directory_env: None,
+            startup_profile_env: None,
+            packed_resources_key_env: None,
+            stdio_redirect: StdioRedirect::None,
+            allocate_console_args: Vec::new(),
+            site_packages_overlay: None,
+            zip_archives: Vec::new(),
+            packed_resources_files: Vec::new(),
+            packed_resources_public_key: None,
+            watch_packed_resources_files: false,
+            sys_argv_skip: 0,
+            sys_argv_insert: Vec::new(),
+            environment_allowlist: None,
+            allocator: None,
+            configure_locale: None,
+            coerce_c_locale: None,
+            coerce_c_locale_warn: None,
+            development_mode: None,
+            parse_argv: None,
+            utf8_mode: None,
+            check_hash_pycs_mode: None,
+            configure_c_stdio: None,
+            dump_refs: None,
+            fault_handler: None,
+            filesystem_encoding: None,
+            filesystem_errors: None,
+            hash_seed: None,
+            import_time: None,
+            install_signal_handlers: None,
+            malloc_stats: None,
+            pathconfig_warnings: None,
+            pycache_prefix: None,
+            show_alloc_count: None,
+            show_ref_count: None,
+            skip_first_source_line: None,
+            tracemalloc: None,
+            warn_options: Vec::new(),
+            x_options: Vec::new(),
+            multiprocessing_auto_dispatch: true,
+            multiprocessing_start_method: None,
         };
 
         c.downcast_apply(|x: &EmbeddedPythonConfig| assert_eq!(x, &wanted));
@@ -404,60 +1181,209 @@ mod tests {
         c.downcast_apply(|x: &EmbeddedPythonConfig| {
             assert_eq!(x.raw_allocator, RawAllocator::System);
         });
-        let c = starlark_ok("PythonInterpreterConfig(raw_allocator='jemalloc')");
-        c.downcast_apply(|x: &EmbeddedPythonConfig| {
-            assert_eq!(x.raw_allocator, RawAllocator::Jemalloc);
-        });
+        if cfg!(windows) {
+            let err = starlark_nok("PythonInterpreterConfig(raw_allocator='jemalloc')");
+            assert!(err
+                .message
+                .starts_with("raw_allocator jemalloc is not supported on"));
+        } else {
+            let c = starlark_ok("PythonInterpreterConfig(raw_allocator='jemalloc')");
+            c.downcast_apply(|x: &EmbeddedPythonConfig| {
+                assert_eq!(x.raw_allocator, RawAllocator::Jemalloc);
+            });
+        }
         let c = starlark_ok("PythonInterpreterConfig(raw_allocator='rust')");
         c.downcast_apply(|x: &EmbeddedPythonConfig| {
             assert_eq!(x.raw_allocator, RawAllocator::Rust);
         });
+        let c = starlark_ok("PythonInterpreterConfig(raw_allocator='mimalloc')");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(x.raw_allocator, RawAllocator::Mimalloc);
+            assert!(!x.raw_allocator_mem_domain);
+        });
+        let c = starlark_ok(
+            "PythonInterpreterConfig(raw_allocator='snmalloc', raw_allocator_mem_domain=True)",
+        );
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(x.raw_allocator, RawAllocator::Snmalloc);
+            assert!(x.raw_allocator_mem_domain);
+        });
+
+        starlark_nok("PythonInterpreterConfig(raw_allocator='tcmalloc')");
     }
 
     #[test]
-    fn test_run_eval() {
-        let c = starlark_ok("PythonInterpreterConfig(run_eval='1')");
+    fn test_run_entry_point() {
+        let c = starlark_ok("PythonInterpreterConfig(run_entry_point='mycli = mypkg.cli:main')");
         c.downcast_apply(|x: &EmbeddedPythonConfig| {
             assert_eq!(
                 x.run_mode,
                 RunMode::Eval {
-                    code: "1".to_string()
+                    code: "import sys; import mypkg.cli; sys.exit(mypkg.cli.main())".to_string()
                 }
             );
         });
-    }
 
-    #[test]
-    fn test_run_file() {
-        let c = starlark_ok("PythonInterpreterConfig(run_file='hello.py')");
+        let c = starlark_ok("PythonInterpreterConfig(run_entry_point='mypkg.cli:main')");
         c.downcast_apply(|x: &EmbeddedPythonConfig| {
             assert_eq!(
                 x.run_mode,
-                RunMode::File {
-                    path: "hello.py".to_string(),
+                RunMode::Eval {
+                    code: "import sys; import mypkg.cli; sys.exit(mypkg.cli.main())".to_string()
                 }
             );
         });
+
+        let err = starlark_nok("PythonInterpreterConfig(run_entry_point='mypkg:')");
+        assert_eq!(err.message, "invalid attribute in object reference: mypkg:");
     }
 
     #[test]
-    fn test_run_module() {
-        let c = starlark_ok("PythonInterpreterConfig(run_module='main')");
+    fn test_run_eval() {
+        let c = starlark_ok("PythonInterpreterConfig(run_eval='1')");
         c.downcast_apply(|x: &EmbeddedPythonConfig| {
             assert_eq!(
                 x.run_mode,
-                RunMode::Module {
-                    module: "main".to_string()
+                RunMode::Eval {
+                    code: "1".to_string()
                 }
             );
         });
     }
 
     #[test]
-    fn test_run_noop() {
-        let c = starlark_ok("PythonInterpreterConfig(run_noop=True)");
+    fn test_run_eval_list() {
+        let c = starlark_ok("PythonInterpreterConfig(run_eval=['import setup', 'main()'])");
         c.downcast_apply(|x: &EmbeddedPythonConfig| {
-            assert_eq!(x.run_mode, RunMode::Noop);
+            assert_eq!(
+                x.run_mode,
+                RunMode::EvalMany {
+                    code: vec!["import setup".to_string(), "main()".to_string()]
+                }
+            );
+        });
+
+        let c = starlark_ok("PythonInterpreterConfig(run_eval=['1'])");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(
+                x.run_mode,
+                RunMode::Eval {
+                    code: "1".to_string()
+                }
+            );
+        });
+
+        starlark_nok("PythonInterpreterConfig(run_eval=[1])");
+    }
+
+    #[test]
+    fn test_run_file() {
+        let c = starlark_ok("PythonInterpreterConfig(run_file='hello.py')");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(
+                x.run_mode,
+                RunMode::File {
+                    path: "hello.py".to_string(),
+                }
+            );
+        });
+    }
+
+    #[test]
+    fn test_run_module() {
+        let c = starlark_ok("PythonInterpreterConfig(run_module='main')");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(
+                x.run_mode,
+                RunMode::Module {
+                    module: "main".to_string()
+                }
+            );
+        });
+    }
+
+    #[test]
+    fn test_run_main_module() {
+        let c = starlark_ok("PythonInterpreterConfig(run_main_module='__main__')");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(
+                x.run_mode,
+                RunMode::MainModule {
+                    module: "__main__".to_string()
+                }
+            );
+        });
+
+        starlark_nok("PythonInterpreterConfig(run_main_module='myapp', run_module='main')");
+    }
+
+    #[test]
+    fn test_run_file_from_args() {
+        let c = starlark_ok("PythonInterpreterConfig(run_file_from_args=True)");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(x.run_mode, RunMode::FileFromArgs);
+        });
+
+        starlark_nok("PythonInterpreterConfig(run_file_from_args=True, run_module='main')");
+    }
+
+    #[test]
+    fn test_run_jupyter_kernel() {
+        let c = starlark_ok("PythonInterpreterConfig(run_jupyter_kernel='myapp')");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(
+                x.run_mode,
+                RunMode::JupyterKernel {
+                    name: "myapp".to_string(),
+                    display_name: "myapp".to_string(),
+                }
+            );
+        });
+
+        let c = starlark_ok(
+            "PythonInterpreterConfig(run_jupyter_kernel='myapp', run_jupyter_kernel_display_name='My App')",
+        );
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(
+                x.run_mode,
+                RunMode::JupyterKernel {
+                    name: "myapp".to_string(),
+                    display_name: "My App".to_string(),
+                }
+            );
+        });
+
+        starlark_nok("PythonInterpreterConfig(run_jupyter_kernel='myapp', run_repl=True)");
+        let err = starlark_nok("PythonInterpreterConfig(run_jupyter_kernel_display_name='My App')");
+        assert_eq!(
+            err.message,
+            "run_jupyter_kernel_display_name requires run_jupyter_kernel"
+        );
+    }
+
+    #[test]
+    fn test_run_module_args() {
+        let c =
+            starlark_ok("PythonInterpreterConfig(run_module='main', run_module_args=['serve'])");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(
+                x.run_mode,
+                RunMode::ModuleWithArgs {
+                    module: "main".to_string(),
+                    args: vec!["serve".to_string()],
+                }
+            );
+        });
+
+        let err = starlark_nok("PythonInterpreterConfig(run_module_args=['serve'])");
+        assert_eq!(err.message, "run_module_args requires run_module");
+    }
+
+    #[test]
+    fn test_run_noop() {
+        let c = starlark_ok("PythonInterpreterConfig(run_noop=True)");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(x.run_mode, RunMode::Noop);
         });
     }
 
@@ -486,4 +1412,382 @@ mod tests {
             );
         });
     }
+
+    #[test]
+    fn test_repl_line_editing() {
+        let c = starlark_ok(
+            "PythonInterpreterConfig(repl_line_editing=False, repl_history_file='~/.myapp_history')",
+        );
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert!(!x.repl_line_editing);
+            assert_eq!(x.repl_history_file, Some("~/.myapp_history".to_string()));
+        });
+
+        starlark_nok("PythonInterpreterConfig(repl_line_editing=None)");
+    }
+
+    #[test]
+    fn test_run_server() {
+        let c = starlark_ok("PythonInterpreterConfig(run_wsgi_app='myapp.wsgi:application')");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(
+                x.run_mode,
+                RunMode::Server {
+                    application: "myapp.wsgi:application".to_string(),
+                    interface: ServerInterface::Wsgi,
+                    address: "127.0.0.1:8000".to_string(),
+                    max_request_body_size: 10485760,
+                }
+            );
+        });
+
+        let c = starlark_ok(
+            "PythonInterpreterConfig(run_asgi_app='myapp:app', server_address='0.0.0.0:80', server_max_request_body_size=1024)",
+        );
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(
+                x.run_mode,
+                RunMode::Server {
+                    application: "myapp:app".to_string(),
+                    interface: ServerInterface::Asgi,
+                    address: "0.0.0.0:80".to_string(),
+                    max_request_body_size: 1024,
+                }
+            );
+        });
+
+        starlark_nok("PythonInterpreterConfig(run_wsgi_app='a:b', run_asgi_app='c:d')");
+        starlark_nok("PythonInterpreterConfig(run_wsgi_app='a:b', run_repl=True)");
+
+        let err = starlark_nok("PythonInterpreterConfig(run_wsgi_app='a:b', server_address='x')");
+        assert_eq!(
+            err.message,
+            "invalid server_address x; expected an address like 127.0.0.1:8000"
+        );
+
+        starlark_nok(
+            "PythonInterpreterConfig(run_wsgi_app='a:b', server_max_request_body_size=-1)",
+        );
+    }
+
+    #[test]
+    fn test_startup_profile_env() {
+        let c = starlark_ok("PythonInterpreterConfig(startup_profile_env='MYAPP_PROFILE')");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(x.startup_profile_env, Some("MYAPP_PROFILE".to_string()));
+        });
+
+        starlark_nok("PythonInterpreterConfig(startup_profile_env=True)");
+    }
+
+    #[test]
+    fn test_packed_resources_key_env() {
+        let c = starlark_ok("PythonInterpreterConfig(packed_resources_key_env='MYAPP_KEY_FILE')");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(
+                x.packed_resources_key_env,
+                Some("MYAPP_KEY_FILE".to_string())
+            );
+        });
+
+        starlark_nok("PythonInterpreterConfig(packed_resources_key_env=True)");
+    }
+
+    #[test]
+    fn test_stdio_redirect() {
+        let c = starlark_ok(
+            "PythonInterpreterConfig(stdio_redirect='file', stdio_redirect_path='$ORIGIN/app.log')",
+        );
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(
+                x.stdio_redirect,
+                StdioRedirect::File("$ORIGIN/app.log".to_string())
+            );
+        });
+
+        let c = starlark_ok("PythonInterpreterConfig(stdio_redirect='debugger')");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(x.stdio_redirect, StdioRedirect::Debugger);
+        });
+
+        starlark_nok("PythonInterpreterConfig(stdio_redirect='file')");
+        starlark_nok("PythonInterpreterConfig(stdio_redirect='console')");
+    }
+
+    #[test]
+    fn test_allocate_console_args() {
+        let c = starlark_ok("PythonInterpreterConfig(allocate_console_args=['--verbose'])");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(x.allocate_console_args, vec!["--verbose".to_string()]);
+        });
+
+        starlark_nok("PythonInterpreterConfig(allocate_console_args='--verbose')");
+    }
+
+    #[test]
+    fn test_in_memory_module_file() {
+        let c = starlark_ok(
+            "PythonInterpreterConfig(in_memory_module_file='uri', in_memory_package_path=False)",
+        );
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(x.in_memory_module_file, InMemoryModuleFile::Uri);
+            assert!(!x.in_memory_package_path);
+        });
+
+        let c = starlark_ok("PythonInterpreterConfig(in_memory_module_file='path')");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(x.in_memory_module_file, InMemoryModuleFile::Path);
+            assert!(x.in_memory_package_path);
+        });
+
+        starlark_nok("PythonInterpreterConfig(in_memory_module_file='file')");
+        starlark_nok("PythonInterpreterConfig(in_memory_module_file=None)");
+        starlark_nok("PythonInterpreterConfig(in_memory_package_path='yes')");
+    }
+
+    #[test]
+    fn test_lazy_modules() {
+        let c = starlark_ok("PythonInterpreterConfig(lazy_modules=['email', 'xml.dom'])");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(
+                x.lazy_modules,
+                vec!["email".to_string(), "xml.dom".to_string()]
+            );
+        });
+
+        starlark_nok("PythonInterpreterConfig(lazy_modules='email')");
+    }
+
+    #[test]
+    fn test_site_packages_overlay() {
+        let c = starlark_ok("PythonInterpreterConfig(site_packages_overlay='$ORIGIN/plugins')");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(
+                x.site_packages_overlay,
+                Some(SitePackagesOverlay {
+                    path: "$ORIGIN/plugins".to_string(),
+                    priority: SitePackagesOverlayPriority::After,
+                })
+            );
+        });
+
+        let c = starlark_ok(
+            "PythonInterpreterConfig(site_packages_overlay='$ORIGIN/plugins', site_packages_overlay_priority='before')",
+        );
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(
+                x.site_packages_overlay.as_ref().unwrap().priority,
+                SitePackagesOverlayPriority::Before
+            );
+        });
+
+        starlark_nok("PythonInterpreterConfig(site_packages_overlay=True)");
+        starlark_nok("PythonInterpreterConfig(site_packages_overlay_priority='first')");
+    }
+
+    #[test]
+    fn test_zip_archives() {
+        let c = starlark_ok(
+            "PythonInterpreterConfig(zip_archives=['$EXECUTABLE', '$ORIGIN/plugins.pyz'])",
+        );
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(
+                x.zip_archives,
+                vec!["$EXECUTABLE".to_string(), "$ORIGIN/plugins.pyz".to_string()]
+            );
+        });
+
+        starlark_nok("PythonInterpreterConfig(zip_archives='$ORIGIN/plugins.pyz')");
+    }
+
+    #[test]
+    fn test_packed_resources_files() {
+        let c = starlark_ok(
+            "PythonInterpreterConfig(packed_resources_files=['$ORIGIN/patch.resources', '$ORIGIN/app.resources'])",
+        );
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(
+                x.packed_resources_files,
+                vec![
+                    "$ORIGIN/patch.resources".to_string(),
+                    "$ORIGIN/app.resources".to_string()
+                ]
+            );
+        });
+
+        starlark_nok("PythonInterpreterConfig(packed_resources_files='$ORIGIN/app.resources')");
+    }
+
+    #[test]
+    fn test_watch_packed_resources_files() {
+        let c = starlark_ok("PythonInterpreterConfig()");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert!(!x.watch_packed_resources_files);
+        });
+
+        let c = starlark_ok("PythonInterpreterConfig(watch_packed_resources_files=True)");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert!(x.watch_packed_resources_files);
+        });
+
+        starlark_nok("PythonInterpreterConfig(watch_packed_resources_files='yes')");
+    }
+
+    #[test]
+    fn test_sys_argv() {
+        let c =
+            starlark_ok("PythonInterpreterConfig(sys_argv_skip=2, sys_argv_insert=['--fixed'])");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(x.sys_argv_skip, 2);
+            assert_eq!(x.sys_argv_insert, vec!["--fixed".to_string()]);
+        });
+
+        starlark_nok("PythonInterpreterConfig(sys_argv_skip=-1)");
+        starlark_nok("PythonInterpreterConfig(sys_argv_insert='--fixed')");
+    }
+
+    #[test]
+    fn test_environment_allowlist() {
+        let c = starlark_ok("PythonInterpreterConfig(environment_allowlist=['HOME', 'LANG'])");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(
+                x.environment_allowlist,
+                Some(vec!["HOME".to_string(), "LANG".to_string()])
+            );
+        });
+
+        let c = starlark_ok("PythonInterpreterConfig(environment_allowlist=[])");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(x.environment_allowlist, Some(vec![]));
+        });
+
+        starlark_nok("PythonInterpreterConfig(environment_allowlist='HOME')");
+    }
+
+    #[test]
+    fn test_ssl_certificate_resolution() {
+        let c = starlark_ok("PythonInterpreterConfig(ssl_certificate_resolution=None)");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(x.ssl_certificate_resolution, CertificateResolution::None);
+        });
+
+        let c = starlark_ok(
+            "PythonInterpreterConfig(ssl_certificate_resolution='static', ssl_ca_bundle='$ORIGIN/cacert.pem')",
+        );
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(
+                x.ssl_certificate_resolution,
+                CertificateResolution::Static("$ORIGIN/cacert.pem".to_string())
+            );
+        });
+
+        let err = starlark_nok("PythonInterpreterConfig(ssl_certificate_resolution='static')");
+        assert_eq!(
+            err.message,
+            "ssl_ca_bundle must be set when using static resolution"
+        );
+    }
+
+    #[test]
+    fn test_gil_enabled() {
+        let c = starlark_ok("PythonInterpreterConfig(gil_enabled=False)");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| assert_eq!(x.gil_enabled, Some(false)));
+
+        let err = starlark_nok("PythonInterpreterConfig(gil_enabled='no')");
+        assert!(err.message.contains("gil_enabled"));
+    }
+
+    #[test]
+    fn test_pep587_bools() {
+        let c = starlark_ok(
+            "PythonInterpreterConfig(development_mode=True, utf8_mode=False, tracemalloc=True)",
+        );
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(x.development_mode, Some(true));
+            assert_eq!(x.utf8_mode, Some(false));
+            assert_eq!(x.tracemalloc, Some(true));
+            assert_eq!(x.fault_handler, None);
+        });
+
+        let err = starlark_nok("PythonInterpreterConfig(utf8_mode='yes')");
+        assert!(err.message.contains("utf8_mode"));
+    }
+
+    #[test]
+    fn test_allocator() {
+        let c = starlark_ok("PythonInterpreterConfig(allocator='pymalloc_debug')");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(x.allocator, Some(Allocator::PyMallocDebug));
+        });
+
+        let err = starlark_nok("PythonInterpreterConfig(allocator='foo')");
+        assert!(err.message.starts_with("allocator must be"));
+    }
+
+    #[test]
+    fn test_coerce_c_locale() {
+        let c = starlark_ok("PythonInterpreterConfig(coerce_c_locale='LC_CTYPE')");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(x.coerce_c_locale, Some(CoerceCLocale::LCCtype));
+        });
+
+        starlark_nok("PythonInterpreterConfig(coerce_c_locale='foo')");
+    }
+
+    #[test]
+    fn test_check_hash_pycs_mode() {
+        let c = starlark_ok("PythonInterpreterConfig(check_hash_pycs_mode='never')");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(x.check_hash_pycs_mode, Some(CheckHashPYCsMode::Never));
+        });
+
+        starlark_nok("PythonInterpreterConfig(check_hash_pycs_mode='sometimes')");
+    }
+
+    #[test]
+    fn test_hash_seed() {
+        let c = starlark_ok("PythonInterpreterConfig(hash_seed=42)");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| assert_eq!(x.hash_seed, Some(42)));
+
+        let err = starlark_nok("PythonInterpreterConfig(hash_seed=-1)");
+        assert_eq!(err.message, "hash_seed must not be negative");
+    }
+
+    #[test]
+    fn test_warn_x_options() {
+        let c = starlark_ok(
+            "PythonInterpreterConfig(warn_options=['error::DeprecationWarning'], x_options=['importtime'])",
+        );
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(x.warn_options, ["error::DeprecationWarning"]);
+            assert_eq!(x.x_options, ["importtime"]);
+        });
+    }
+
+    #[test]
+    fn test_pycache_prefix() {
+        let c = starlark_ok("PythonInterpreterConfig(pycache_prefix='/tmp/pycache')");
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert_eq!(x.pycache_prefix, Some("/tmp/pycache".to_string()));
+        });
+    }
+
+    #[test]
+    fn test_multiprocessing() {
+        let c = starlark_ok(
+            "PythonInterpreterConfig(multiprocessing_auto_dispatch=False, multiprocessing_start_method='spawn')",
+        );
+        c.downcast_apply(|x: &EmbeddedPythonConfig| {
+            assert!(!x.multiprocessing_auto_dispatch);
+            assert_eq!(
+                x.multiprocessing_start_method,
+                Some(MultiprocessingStartMethod::Spawn)
+            );
+        });
+
+        let err = starlark_nok("PythonInterpreterConfig(multiprocessing_start_method='thread')");
+        assert!(err
+            .message
+            .starts_with("multiprocessing_start_method must be"));
+    }
 }
@@ -4,10 +4,12 @@
 
 use {
     python_packaging::resource::{
-        BytecodeOptimizationLevel, PythonExtensionModule as RawPythonExtensionModule,
-        PythonModuleBytecodeFromSource, PythonModuleSource as RawSourceModule,
+        BytecodeOptimizationLevel, PythonDataFile as RawDataFile,
+        PythonEditableInstall as RawEditableInstall, PythonEntryPoint as RawEntryPoint,
+        PythonExtensionModule as RawPythonExtensionModule, PythonModuleBytecodeFromSource,
+        PythonModuleSource as RawSourceModule,
         PythonPackageDistributionResource as RawDistributionResource,
-        PythonPackageResource as RawPackageResource, PythonResource,
+        PythonPackageResource as RawPackageResource, PythonResource, ResourceProvenance,
     },
     starlark::environment::Environment,
     starlark::values::{
@@ -21,6 +23,14 @@ use {
     std::convert::{TryFrom, TryInto},
 };
 
+/// Convert the provenance of a resource to a Starlark value.
+fn provenance_value(provenance: &Option<ResourceProvenance>) -> Value {
+    match provenance {
+        Some(provenance) => Value::new(provenance.to_string()),
+        None => Value::new(None),
+    }
+}
+
 /// Where a resource should be loaded from.
 #[derive(Clone, Debug)]
 pub enum ResourceLocation {
@@ -135,6 +145,7 @@ impl TypedValue for PythonSourceModule {
             }
             "is_package" => Value::new(self.module.is_package),
             "location" => self.location.clone().into(),
+            "provenance" => provenance_value(&self.module.provenance),
             attr => {
                 return Err(ValueError::OperationNotSupported {
                     op: format!(".{}", attr),
@@ -153,6 +164,7 @@ impl TypedValue for PythonSourceModule {
             "source" => true,
             "is_package" => true,
             "location" => true,
+            "provenance" => true,
             _ => false,
         })
     }
@@ -219,6 +231,7 @@ impl TypedValue for PythonBytecodeModule {
                 BytecodeOptimizationLevel::Two => 2,
             }),
             "is_package" => Value::new(self.module.is_package),
+            "provenance" => provenance_value(&self.module.provenance),
             attr => {
                 return Err(ValueError::OperationNotSupported {
                     op: format!(".{}", attr),
@@ -238,6 +251,7 @@ impl TypedValue for PythonBytecodeModule {
             // "source" => true,
             "optimize_level" => true,
             "is_package" => true,
+            "provenance" => true,
             _ => false,
         })
     }
@@ -282,6 +296,7 @@ impl TypedValue for PythonPackageResource {
         let v = match attribute {
             "package" => Value::new(self.data.leaf_package.clone()),
             "name" => Value::new(self.data.relative_name.clone()),
+            "provenance" => provenance_value(&self.data.provenance),
             // TODO expose raw data
             attr => {
                 return Err(ValueError::OperationNotSupported {
@@ -299,6 +314,7 @@ impl TypedValue for PythonPackageResource {
         Ok(match attribute {
             "package" => true,
             "name" => true,
+            "provenance" => true,
             // TODO expose raw data
             _ => false,
         })
@@ -344,6 +360,7 @@ impl TypedValue for PythonPackageDistributionResource {
         let v = match attribute {
             "package" => Value::new(self.resource.package.clone()),
             "name" => Value::new(self.resource.name.clone()),
+            "provenance" => provenance_value(&self.resource.provenance),
             // TODO expose raw data
             attr => {
                 return Err(ValueError::OperationNotSupported {
@@ -361,6 +378,7 @@ impl TypedValue for PythonPackageDistributionResource {
         Ok(match attribute {
             "package" => true,
             "name" => true,
+            "provenance" => true,
             // TODO expose raw data
             _ => false,
         })
@@ -402,6 +420,7 @@ impl TypedValue for PythonExtensionModule {
     fn get_attr(&self, attribute: &str) -> ValueResult {
         let v = match attribute {
             "name" => Value::new(self.em.name.clone()),
+            "provenance" => provenance_value(&self.em.provenance),
             attr => {
                 return Err(ValueError::OperationNotSupported {
                     op: format!(".{}", attr),
@@ -417,6 +436,205 @@ impl TypedValue for PythonExtensionModule {
     fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
         Ok(match attribute {
             "name" => true,
+            "provenance" => true,
+            _ => false,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PythonEntryPoint {
+    pub ep: RawEntryPoint,
+}
+
+impl TypedValue for PythonEntryPoint {
+    immutable!();
+    any!();
+    not_supported!(
+        binop, dir_attr, function, get_hash, indexable, iterable, sequence, set_attr, to_int
+    );
+
+    fn to_str(&self) -> String {
+        format!(
+            "PythonEntryPoint<package={}, group={}, name={}>",
+            self.ep.package, self.ep.group, self.ep.name
+        )
+    }
+
+    fn to_repr(&self) -> String {
+        self.to_str()
+    }
+
+    fn get_type(&self) -> &'static str {
+        "PythonEntryPoint"
+    }
+
+    fn to_bool(&self) -> bool {
+        true
+    }
+
+    fn compare(&self, other: &dyn TypedValue, _recursion: u32) -> Result<Ordering, ValueError> {
+        default_compare(self, other)
+    }
+
+    fn get_attr(&self, attribute: &str) -> ValueResult {
+        let v = match attribute {
+            "package" => Value::new(self.ep.package.clone()),
+            "group" => Value::new(self.ep.group.clone()),
+            "name" => Value::new(self.ep.name.clone()),
+            "value" => Value::new(self.ep.value.clone()),
+            attr => {
+                return Err(ValueError::OperationNotSupported {
+                    op: format!(".{}", attr),
+                    left: "PythonEntryPoint".to_string(),
+                    right: None,
+                })
+            }
+        };
+
+        Ok(v)
+    }
+
+    fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
+        Ok(match attribute {
+            "package" => true,
+            "group" => true,
+            "name" => true,
+            "value" => true,
+            _ => false,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PythonDataFile {
+    pub file: RawDataFile,
+}
+
+impl TypedValue for PythonDataFile {
+    immutable!();
+    any!();
+    not_supported!(
+        binop, dir_attr, function, get_hash, indexable, iterable, sequence, set_attr, to_int
+    );
+
+    fn to_str(&self) -> String {
+        format!(
+            "PythonDataFile<package={}, location={}, path={}>",
+            self.file.package,
+            self.file.location,
+            self.file.relative_path.display()
+        )
+    }
+
+    fn to_repr(&self) -> String {
+        self.to_str()
+    }
+
+    fn get_type(&self) -> &'static str {
+        "PythonDataFile"
+    }
+
+    fn to_bool(&self) -> bool {
+        true
+    }
+
+    fn compare(&self, other: &dyn TypedValue, _recursion: u32) -> Result<Ordering, ValueError> {
+        default_compare(self, other)
+    }
+
+    fn get_attr(&self, attribute: &str) -> ValueResult {
+        let v = match attribute {
+            "package" => Value::new(self.file.package.clone()),
+            "location" => Value::new(self.file.location.to_string()),
+            "path" => Value::new(self.file.relative_path.display().to_string()),
+            "executable" => Value::new(self.file.executable),
+            "provenance" => provenance_value(&self.file.provenance),
+            attr => {
+                return Err(ValueError::OperationNotSupported {
+                    op: format!(".{}", attr),
+                    left: "PythonDataFile".to_string(),
+                    right: None,
+                })
+            }
+        };
+
+        Ok(v)
+    }
+
+    fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
+        Ok(match attribute {
+            "package" => true,
+            "location" => true,
+            "path" => true,
+            "executable" => true,
+            "provenance" => true,
+            _ => false,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PythonEditableInstall {
+    pub editable: RawEditableInstall,
+}
+
+impl TypedValue for PythonEditableInstall {
+    immutable!();
+    any!();
+    not_supported!(
+        binop, dir_attr, function, get_hash, indexable, iterable, sequence, set_attr, to_int
+    );
+
+    fn to_str(&self) -> String {
+        format!(
+            "PythonEditableInstall<name={}, version={}, path={}>",
+            self.editable.name,
+            self.editable.version,
+            self.editable.path.display()
+        )
+    }
+
+    fn to_repr(&self) -> String {
+        self.to_str()
+    }
+
+    fn get_type(&self) -> &'static str {
+        "PythonEditableInstall"
+    }
+
+    fn to_bool(&self) -> bool {
+        true
+    }
+
+    fn compare(&self, other: &dyn TypedValue, _recursion: u32) -> Result<Ordering, ValueError> {
+        default_compare(self, other)
+    }
+
+    fn get_attr(&self, attribute: &str) -> ValueResult {
+        let v = match attribute {
+            "name" => Value::new(self.editable.name.clone()),
+            "version" => Value::new(self.editable.version.clone()),
+            "path" => Value::new(self.editable.path.display().to_string()),
+            "top_level" => Value::from(self.editable.top_level.clone()),
+            attr => {
+                return Err(ValueError::OperationNotSupported {
+                    op: format!(".{}", attr),
+                    left: "PythonEditableInstall".to_string(),
+                    right: None,
+                })
+            }
+        };
+
+        Ok(v)
+    }
+
+    fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
+        Ok(match attribute {
+            "name" => true,
+            "version" => true,
+            "path" => true,
+            "top_level" => true,
             _ => false,
         })
     }
@@ -457,6 +675,14 @@ pub fn python_resource_to_value(resource: &PythonResource) -> Value {
         PythonResource::PathExtension(_) => {
             panic!("path extensions not supported");
         }
+
+        PythonResource::EntryPoint(ep) => Value::new(PythonEntryPoint { ep: ep.clone() }),
+
+        PythonResource::DataFile(file) => Value::new(PythonDataFile { file: file.clone() }),
+
+        PythonResource::EditableInstall(editable) => Value::new(PythonEditableInstall {
+            editable: editable.clone(),
+        }),
     }
 }
 
@@ -5,6 +5,7 @@
 use {
     super::env::{global_environment, EnvironmentContext},
     crate::logging::PrintlnDrain,
+    crate::project_lock::LockMode,
     codemap::CodeMap,
     codemap_diagnostic::Diagnostic,
     slog::Drain,
@@ -37,6 +38,7 @@ pub fn starlark_env() -> Environment {
         "0",
         None,
         false,
+        LockMode::Use,
     )
     .expect("unable to create EnvironmentContext");
 
@@ -71,6 +71,22 @@ pub fn required_bool_arg(name: &str, value: &Value) -> Result<bool, ValueError>
     }
 }
 
+pub fn optional_bool_arg(name: &str, value: &Value) -> Result<Option<bool>, ValueError> {
+    match value.get_type() {
+        "NoneType" => Ok(None),
+        "bool" => Ok(Some(value.to_bool())),
+        t => Err(RuntimeError {
+            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+            message: format!(
+                "function expects an optional bool for {}; got type {}",
+                name, t
+            ),
+            label: format!("expected type bool; got {}", t),
+        }
+        .into()),
+    }
+}
+
 pub fn required_list_arg(
     arg_name: &str,
     value_type: &str,
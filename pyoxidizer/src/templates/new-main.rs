@@ -1,3 +1,9 @@
+// Executables built for the Windows GUI subsystem don't open a console window.
+#![cfg_attr(
+    all(windows, feature = "windows-subsystem"),
+    windows_subsystem = "windows"
+)]
+
 use pyembed::MainPythonInterpreter;
 
 // Include an auto-generated file containing the default
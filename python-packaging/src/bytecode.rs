@@ -29,6 +29,12 @@ pub trait PythonBytecodeCompiler {
         optimize: BytecodeOptimizationLevel,
         output_mode: CompileMode,
     ) -> Result<Vec<u8>>;
+
+    /// Remove docstrings and comments from Python source.
+    ///
+    /// The returned source is UTF-8 and has the same line numbering as the
+    /// original.
+    fn strip_docstrings(&mut self, source: &[u8], filename: &str) -> Result<Vec<u8>>;
 }
 
 /// An entity to perform Python bytecode compilation.
@@ -142,6 +148,39 @@ impl PythonBytecodeCompiler for BytecodeCompiler {
 
         Ok(bytecode)
     }
+
+    fn strip_docstrings(
+        self: &mut BytecodeCompiler,
+        source: &[u8],
+        filename: &str,
+    ) -> Result<Vec<u8>> {
+        let stdin = self.command.stdin.as_mut().expect("failed to get stdin");
+        let stdout = self.command.stdout.as_mut().expect("failed to get stdout");
+
+        let mut reader = BufReader::new(stdout);
+
+        stdin.write_all(b"strip_docstrings\n")?;
+        stdin.write_all(filename.len().to_string().as_bytes())?;
+        stdin.write_all(b"\n")?;
+        stdin.write_all(source.len().to_string().as_bytes())?;
+        stdin.write_all(b"\n")?;
+        stdin.write_all(filename.as_bytes())?;
+        stdin.write_all(source)?;
+        stdin.flush()?;
+
+        let mut len_s = String::new();
+        reader.read_line(&mut len_s)?;
+
+        let len_s = len_s.trim_end();
+        let source_len = len_s
+            .parse::<u64>()
+            .map_err(|_| anyhow!("unable to strip docstrings from {}", filename))?;
+
+        let mut stripped: Vec<u8> = Vec::new();
+        reader.take(source_len).read_to_end(&mut stripped)?;
+
+        Ok(stripped)
+    }
 }
 
 impl Drop for BytecodeCompiler {
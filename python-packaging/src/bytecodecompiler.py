@@ -9,15 +9,103 @@
 
 import importlib._bootstrap_external
 import importlib.util
+import io
 import marshal
 import os
 import re
 import sys
+import tokenize
 
 
 RE_CODING = re.compile(b"^[ \t\f]*#.*?coding[:=][ \t]*([-_.a-zA-Z0-9]+)")
 
 
+def decode_source(source):
+    # Default source encoding is UTF-8. But per PEP 263, the first or second
+    # line of source can match a regular expression to define a custom
+    # encoding. We need to detect custom encodings and use it to decode
+    # the passed bytes to str.
+    encoding = "utf-8"
+
+    for line in source.splitlines()[0:2]:
+        m = RE_CODING.match(line)
+        if m:
+            encoding = m.group(1).decode("ascii")
+            break
+
+    # Someone has set us up the BOM! According to PEP 263 the file should
+    # be interpreted as UTF-8.
+    if source.startswith(b"\xef\xbb\xbf"):
+        encoding = "utf-8"
+        source = source[3:]
+
+    return source.decode(encoding)
+
+
+def strip_docstrings(source):
+    """Remove docstrings and comments from Python source code.
+
+    String literals forming a statement of their own and comments are
+    removed. Statements are replaced by ``pass`` where needed to keep blocks
+    valid. Line numbers are preserved so
+    tracebacks still point at the right lines.
+    """
+    lines = io.StringIO(source).readlines()
+    tokens = list(tokenize.generate_tokens(io.StringIO(source).readline))
+
+    ignored = (tokenize.COMMENT, tokenize.NL)
+    significant = [t for t in tokens if t.type not in ignored]
+
+    # (start, end, replacement) of text to replace.
+    edits = []
+
+    for t in tokens:
+        if t.type == tokenize.COMMENT:
+            edits.append((t.start, t.end, ""))
+
+    for i, t in enumerate(significant):
+        if t.type != tokenize.STRING:
+            continue
+
+        # f-strings may have side-effects when evaluated.
+        prefix = t.string[0 : t.string.index(t.string[-1])]
+        if "f" in prefix.lower():
+            continue
+
+        previous = significant[i - 1].type if i else tokenize.NEWLINE
+        following = significant[i + 1].type
+
+        if previous in (
+            tokenize.NEWLINE,
+            tokenize.INDENT,
+            tokenize.DEDENT,
+        ) and following in (tokenize.NEWLINE, tokenize.ENDMARKER):
+            # Module docstrings may precede `from __future__` imports, which
+            # must be the first statements. Other blocks can't be empty.
+            edits.append((t.start, t.end, "" if i == 0 else "pass"))
+
+    # Apply edits from the end so earlier positions remain valid.
+    for (start_row, start_col), (end_row, end_col), replacement in sorted(
+        edits, reverse=True
+    ):
+        start_line = lines[start_row - 1]
+        end_line = lines[end_row - 1]
+
+        prefix = start_line[0:start_col]
+
+        # Remove whitespace left behind by removed comments.
+        if not replacement:
+            prefix = prefix.rstrip(" \t\f")
+
+        replaced = [prefix + replacement]
+        replaced.extend(["\n"] * (end_row - start_row))
+        replaced[-1] += end_line[end_col:]
+
+        lines[start_row - 1 : end_row] = replaced
+
+    return "".join(lines)
+
+
 if marshal.version != 4:
     raise Exception("unexpected marshal version: %d" % marshal.version)
 
@@ -48,26 +136,8 @@ while True:
 
         name = os.fsdecode(name)
 
-        # Default source encoding is UTF-8. But per PEP 263, the first or second
-        # line of source can match a regular expression to define a custom
-        # encoding. We need to detect custom encodings and use it to decode
-        # the passed bytes to str.
-        encoding = "utf-8"
-
-        for line in source.splitlines()[0:2]:
-            m = RE_CODING.match(line)
-            if m:
-                encoding = m.group(1).decode("ascii")
-                break
-
-        # Someone has set us up the BOM! According to PEP 263 the file should
-        # be interpreted as UTF-8.
-        if source.startswith(b"\xef\xbb\xbf"):
-            encoding = "utf-8"
-            source = source[3:]
-
         source_bytes = source
-        source = source.decode(encoding)
+        source = decode_source(source)
 
         code = compile(source, name, "exec", optimize=optimize_level)
         bytecode = marshal.dumps(code)
@@ -87,6 +157,21 @@ while True:
         else:
             raise Exception("unknown output mode: %s" % output_mode)
 
+        stdout.write(b"%d\n" % len(out))
+        stdout.write(out)
+        stdout.flush()
+    elif command == b"strip_docstrings":
+        name_len = int(stdin.readline().rstrip())
+        source_len = int(stdin.readline().rstrip())
+
+        name = os.fsdecode(stdin.read(name_len))
+        source = decode_source(stdin.read(source_len))
+
+        try:
+            out = strip_docstrings(source).encode("utf-8")
+        except (tokenize.TokenError, IndentationError) as e:
+            raise Exception("unable to tokenize %s: %s" % (name, e))
+
         stdout.write(b"%d\n" % len(out))
         stdout.write(out)
         stdout.flush()
@@ -7,18 +7,19 @@ Scanning the filesystem for Python resources.
 */
 
 use {
-    crate::module_util::{is_package_from_path, PythonModuleSuffixes},
-    crate::package_metadata::PythonPackageMetadata,
+    crate::module_util::{is_package_from_path, packages_from_module_name, PythonModuleSuffixes},
+    crate::package_metadata::{parse_entry_points, PythonPackageMetadata},
     crate::resource::{
-        BytecodeOptimizationLevel, DataLocation, PythonEggFile, PythonExtensionModule,
-        PythonModuleBytecode, PythonModuleSource, PythonPackageDistributionResource,
-        PythonPackageDistributionResourceFlavor, PythonPackageResource, PythonPathExtension,
-        PythonResource,
+        BytecodeOptimizationLevel, DataLocation, PythonEggFile, PythonEntryPoint,
+        PythonExtensionModule, PythonModuleBytecode, PythonModuleSource,
+        PythonPackageDistributionResource, PythonPackageDistributionResourceFlavor,
+        PythonPackageResource, PythonPathExtension, PythonResource,
     },
-    anyhow::Result,
+    anyhow::{anyhow, Context, Result},
     std::collections::HashSet,
     std::ffi::OsStr,
-    std::path::{Path, PathBuf},
+    std::io::Cursor,
+    std::path::{Component, Path, PathBuf},
 };
 
 pub fn walk_tree_files(path: &Path) -> Box<dyn Iterator<Item = walkdir::DirEntry>> {
@@ -61,6 +62,17 @@ pub struct PythonResourceIterator {
     walkdir_result: Box<dyn Iterator<Item = walkdir::DirEntry>>,
     seen_packages: HashSet<String>,
     resources: Vec<ResourceFile>,
+    entry_points: Vec<PythonEntryPoint>,
+}
+
+/// Record a package as seen, along with all of its parents.
+///
+/// Parents may be PEP 420 namespace packages not having any modules of
+/// their own. Registering them allows resource files in their directories
+/// to be attributed to them.
+fn register_package(seen_packages: &mut HashSet<String>, package: String) {
+    seen_packages.extend(packages_from_module_name(&package).into_iter());
+    seen_packages.insert(package);
 }
 
 impl PythonResourceIterator {
@@ -90,6 +102,7 @@ impl PythonResourceIterator {
             walkdir_result: Box::new(filtered),
             seen_packages: HashSet::new(),
             resources: Vec::new(),
+            entry_points: Vec::new(),
         }
     }
 
@@ -150,6 +163,7 @@ impl PythonResourceIterator {
                     version: version.to_string(),
                     name,
                     data: DataLocation::Path(path.to_path_buf()),
+                    provenance: None,
                 }),
             ));
         }
@@ -229,7 +243,7 @@ impl PythonResourceIterator {
                     package = full_module_name.clone();
                 }
 
-                self.seen_packages.insert(package);
+                register_package(&mut self.seen_packages, package);
 
                 let module_components = full_module_name.split('.').collect::<Vec<_>>();
                 let final_name = module_components[module_components.len() - 1];
@@ -251,6 +265,7 @@ impl PythonResourceIterator {
                         licenses: None,
                         license_texts: None,
                         license_public_domain: None,
+                        provenance: None,
                     }),
                 ));
             }
@@ -284,7 +299,7 @@ impl PythonResourceIterator {
                 package = full_module_name.clone();
             }
 
-            self.seen_packages.insert(package);
+            register_package(&mut self.seen_packages, package);
 
             return Some(DirEntryItem::PythonResource(PythonResource::ModuleSource(
                 PythonModuleSource {
@@ -294,6 +309,7 @@ impl PythonResourceIterator {
                     cache_tag: self.cache_tag.clone(),
                     is_stdlib: false,
                     is_test: false,
+                    provenance: None,
                 },
             )));
         }
@@ -373,7 +389,7 @@ impl PythonResourceIterator {
                 package = full_module_name.clone();
             }
 
-            self.seen_packages.insert(package);
+            register_package(&mut self.seen_packages, package);
 
             return Some(DirEntryItem::PythonResource(
                 PythonResource::ModuleBytecode(PythonModuleBytecode::from_path(
@@ -417,6 +433,11 @@ impl Iterator for PythonResourceIterator {
         // We then emit those at the end, perhaps doing some post-processing along the
         // way.
         loop {
+            // Entry points are emitted after the distribution resource defining them.
+            if !self.entry_points.is_empty() {
+                return Some(Ok(PythonResource::EntryPoint(self.entry_points.remove(0))));
+            }
+
             let res = self.walkdir_result.next();
 
             // We're out of directory entries;
@@ -440,6 +461,27 @@ impl Iterator for PythonResourceIterator {
                     self.resources.push(resource);
                 }
                 DirEntryItem::PythonResource(resource) => {
+                    if let PythonResource::DistributionResource(dist) = &resource {
+                        if dist.name == "entry_points.txt" {
+                            // Like metadata, malformed entry points are ignored.
+                            if let Ok(entries) = dist
+                                .data
+                                .resolve()
+                                .and_then(|data| parse_entry_points(&data))
+                            {
+                                for (group, name, value) in entries {
+                                    self.entry_points.push(PythonEntryPoint {
+                                        package: dist.package.clone(),
+                                        version: dist.version.clone(),
+                                        group,
+                                        name,
+                                        value,
+                                    });
+                                }
+                            }
+                        }
+                    }
+
                     return Some(Ok(resource));
                 }
             }
@@ -527,6 +569,7 @@ impl Iterator for PythonResourceIterator {
                 data: DataLocation::Path(resource.full_path),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             })));
         }
     }
@@ -549,12 +592,164 @@ pub fn find_python_resources(
     PythonResourceIterator::new(root_path, cache_tag, suffixes)
 }
 
+/// Find Python resources in a zipped .egg file.
+///
+/// The egg is extracted to a temporary directory, which is then scanned
+/// like any other directory. Returned resources have their data loaded
+/// into memory, as the temporary directory doesn't outlive this function.
+pub fn find_python_resources_in_egg(
+    egg: &PythonEggFile,
+    cache_tag: &str,
+    suffixes: &PythonModuleSuffixes,
+) -> Result<Vec<PythonResource>> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(egg.data.resolve()?))
+        .context("reading .egg file as a zip archive")?;
+
+    let temp_dir = tempdir::TempDir::new("python-packaging-egg")?;
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+
+        if file.is_dir() {
+            continue;
+        }
+
+        let rel_path = PathBuf::from(file.name());
+        if !rel_path.components().all(|c| matches_normal_component(&c)) {
+            return Err(anyhow!(
+                ".egg file contains entry with unsupported path: {}",
+                file.name()
+            ));
+        }
+
+        let dest_path = temp_dir.path().join(&rel_path);
+        if let Some(parent) = dest_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut fh = std::fs::File::create(&dest_path)?;
+        std::io::copy(&mut file, &mut fh)
+            .with_context(|| format!("extracting {} from .egg file", file.name()))?;
+    }
+
+    find_python_resources(temp_dir.path(), cache_tag, suffixes)
+        .map(|r| r?.to_memory())
+        .collect::<Result<Vec<_>>>()
+}
+
+pub(crate) fn matches_normal_component(component: &Component) -> bool {
+    match component {
+        Component::Normal(_) => true,
+        _ => false,
+    }
+}
+
+/// An entry in a .pth file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathExtensionEntry {
+    /// A path added to `sys.path`.
+    ///
+    /// Relative entries are resolved against the directory containing
+    /// the .pth file.
+    Path(PathBuf),
+
+    /// A line executed by Python's `site` module at startup.
+    Import(String),
+}
+
+impl PathExtensionEntry {
+    /// Names of modules imported by an executable line.
+    ///
+    /// Only `import` statements at the start of `;` separated statements
+    /// are recognized.
+    pub fn imported_modules(&self) -> Vec<String> {
+        match self {
+            PathExtensionEntry::Path(_) => vec![],
+            PathExtensionEntry::Import(line) => line
+                .split(';')
+                .map(|statement| statement.trim())
+                .filter(|statement| is_import_line(statement))
+                .flat_map(|statement| {
+                    statement["import".len()..]
+                        .split(',')
+                        .filter_map(|name| name.split_whitespace().next())
+                        .map(|name| name.to_string())
+                        .collect::<Vec<_>>()
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Whether a .pth file line is executed by Python's `site` module.
+fn is_import_line(line: &str) -> bool {
+    line.starts_with("import ") || line.starts_with("import\t")
+}
+
+/// Parse the entries of a .pth file.
+///
+/// `base` is the directory containing the .pth file. Like Python's `site`
+/// module, blank lines, comments, and paths that don't exist are ignored.
+pub fn parse_path_extension(
+    pth: &PythonPathExtension,
+    base: &Path,
+) -> Result<Vec<PathExtensionEntry>> {
+    let data = pth.data.resolve()?;
+    let data = String::from_utf8(data).context(".pth file is not valid UTF-8")?;
+
+    let mut entries = vec![];
+
+    for line in data.lines() {
+        let line = line.trim_end();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let entry = if is_import_line(line) {
+            PathExtensionEntry::Import(line.to_string())
+        } else {
+            let path = base.join(line);
+
+            if !path.exists() {
+                continue;
+            }
+
+            PathExtensionEntry::Path(path)
+        };
+
+        if !entries.contains(&entry) {
+            entries.push(entry);
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Resolve the paths a .pth file adds to `sys.path`.
+///
+/// Lines executed by Python at startup can't be evaluated by us, so they
+/// are ignored.
+pub fn resolve_path_extension_paths(
+    pth: &PythonPathExtension,
+    base: &Path,
+) -> Result<Vec<PathBuf>> {
+    Ok(parse_path_extension(pth, base)?
+        .into_iter()
+        .filter_map(|entry| match entry {
+            PathExtensionEntry::Path(path) => Some(path),
+            PathExtensionEntry::Import(_) => None,
+        })
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use {
         super::*,
         lazy_static::lazy_static,
         std::fs::{create_dir_all, write},
+        std::io::Write,
     };
 
     const DEFAULT_CACHE_TAG: &str = "cpython-37";
@@ -600,6 +795,7 @@ mod tests {
                 cache_tag: DEFAULT_CACHE_TAG.to_string(),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             })
         );
         assert_eq!(
@@ -611,6 +807,7 @@ mod tests {
                 cache_tag: DEFAULT_CACHE_TAG.to_string(),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             })
         );
         assert_eq!(
@@ -622,6 +819,7 @@ mod tests {
                 cache_tag: DEFAULT_CACHE_TAG.to_string(),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             })
         );
         assert_eq!(
@@ -633,6 +831,7 @@ mod tests {
                 cache_tag: DEFAULT_CACHE_TAG.to_string(),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             })
         );
 
@@ -922,6 +1121,7 @@ mod tests {
                 cache_tag: DEFAULT_CACHE_TAG.to_string(),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             })
         );
         assert_eq!(
@@ -933,6 +1133,7 @@ mod tests {
                 cache_tag: DEFAULT_CACHE_TAG.to_string(),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             })
         );
 
@@ -996,6 +1197,7 @@ mod tests {
                 licenses: None,
                 license_texts: None,
                 license_public_domain: None,
+                provenance: None,
             })
         );
         assert_eq!(
@@ -1015,6 +1217,7 @@ mod tests {
                 licenses: None,
                 license_texts: None,
                 license_public_domain: None,
+                provenance: None,
             }),
         );
         assert_eq!(
@@ -1034,6 +1237,7 @@ mod tests {
                 licenses: None,
                 license_texts: None,
                 license_public_domain: None,
+                provenance: None,
             }),
         );
         assert_eq!(
@@ -1053,6 +1257,7 @@ mod tests {
                 licenses: None,
                 license_texts: None,
                 license_public_domain: None,
+                provenance: None,
             }),
         );
         assert_eq!(
@@ -1072,6 +1277,7 @@ mod tests {
                 licenses: None,
                 license_texts: None,
                 license_public_domain: None,
+                provenance: None,
             }),
         );
 
@@ -1133,6 +1339,7 @@ mod tests {
                 cache_tag: DEFAULT_CACHE_TAG.to_string(),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             })
         );
         assert_eq!(
@@ -1144,6 +1351,7 @@ mod tests {
                 cache_tag: DEFAULT_CACHE_TAG.to_string(),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             })
         );
 
@@ -1174,6 +1382,112 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_egg_file_extraction() -> Result<()> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default();
+        writer.start_file("EGG-INFO/PKG-INFO", options)?;
+        writer.start_file("foo/__init__.py", options)?;
+        writer.start_file("foo/bar.py", options)?;
+        writer.write_all(b"# foo.bar")?;
+        let data = writer.finish()?.into_inner();
+
+        let egg = PythonEggFile {
+            data: DataLocation::Memory(data),
+        };
+
+        let resources = find_python_resources_in_egg(&egg, DEFAULT_CACHE_TAG, &DEFAULT_SUFFIXES)?;
+        assert_eq!(resources.len(), 2);
+
+        assert_eq!(
+            resources[0],
+            PythonResource::ModuleSource(PythonModuleSource {
+                name: "foo".to_string(),
+                source: DataLocation::Memory(vec![]),
+                is_package: true,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+                provenance: None,
+            })
+        );
+        assert_eq!(
+            resources[1],
+            PythonResource::ModuleSource(PythonModuleSource {
+                name: "foo.bar".to_string(),
+                source: DataLocation::Memory(b"# foo.bar".to_vec()),
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+                provenance: None,
+            })
+        );
+
+        let egg = PythonEggFile {
+            data: DataLocation::Memory(b"not a zip file".to_vec()),
+        };
+        assert!(find_python_resources_in_egg(&egg, DEFAULT_CACHE_TAG, &DEFAULT_SUFFIXES).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_path_extension_paths() -> Result<()> {
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+        let tp = td.path();
+
+        let site_packages = tp.join("site-packages");
+        let src_path = tp.join("src");
+        create_dir_all(&site_packages)?;
+        create_dir_all(&src_path)?;
+
+        let pth = PythonPathExtension {
+            data: DataLocation::Memory(
+                b"# comment\n\nimport sys; sys.foo = True\n../src\nmissing\n../src\n".to_vec(),
+            ),
+        };
+
+        assert_eq!(
+            resolve_path_extension_paths(&pth, &site_packages)?,
+            vec![site_packages.join("../src")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_path_extension() -> Result<()> {
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+        let tp = td.path();
+
+        let site_packages = tp.join("site-packages");
+        let src_path = tp.join("src");
+        create_dir_all(&site_packages)?;
+        create_dir_all(&src_path)?;
+
+        let pth = PythonPathExtension {
+            data: DataLocation::Memory(
+                b"import _virtualenv\n../src\nimport os, foo.bar; foo.bar.install()\n".to_vec(),
+            ),
+        };
+
+        let entries = parse_path_extension(&pth, &site_packages)?;
+        assert_eq!(
+            entries,
+            vec![
+                PathExtensionEntry::Import("import _virtualenv".to_string()),
+                PathExtensionEntry::Path(site_packages.join("../src")),
+                PathExtensionEntry::Import("import os, foo.bar; foo.bar.install()".to_string()),
+            ]
+        );
+        assert_eq!(entries[0].imported_modules(), vec!["_virtualenv"]);
+        assert!(entries[1].imported_modules().is_empty());
+        assert_eq!(entries[2].imported_modules(), vec!["os", "foo.bar"]);
+
+        Ok(())
+    }
+
     /// Resource files without a package are not valid.
     #[test]
     fn test_root_resource_file() -> Result<()> {
@@ -1216,6 +1530,7 @@ mod tests {
                 cache_tag: DEFAULT_CACHE_TAG.to_string(),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             })
         );
 
@@ -1249,6 +1564,7 @@ mod tests {
                 cache_tag: DEFAULT_CACHE_TAG.to_string(),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             })
         );
         assert_eq!(
@@ -1259,6 +1575,7 @@ mod tests {
                 data: DataLocation::Path(resource_path),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             })
         );
 
@@ -1293,6 +1610,7 @@ mod tests {
                 cache_tag: DEFAULT_CACHE_TAG.to_string(),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             }),
         );
         assert_eq!(
@@ -1303,6 +1621,53 @@ mod tests {
                 data: DataLocation::Path(resource_path),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
+            })
+        );
+
+        Ok(())
+    }
+
+    /// Resource files in namespace packages are attributed to the namespace package.
+    #[test]
+    fn test_namespace_package_resource() -> Result<()> {
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+        let tp = td.path();
+
+        let namespace_dir = tp.join("foo");
+        let package_dir = namespace_dir.join("bar");
+        create_dir_all(&package_dir)?;
+
+        let module_path = package_dir.join("__init__.py");
+        write(&module_path, "")?;
+        let resource_path = namespace_dir.join("resource.txt");
+        write(&resource_path, "content")?;
+
+        let resources = PythonResourceIterator::new(tp, DEFAULT_CACHE_TAG, &DEFAULT_SUFFIXES)
+            .collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(resources.len(), 2);
+        assert_eq!(
+            resources[0],
+            PythonResource::ModuleSource(PythonModuleSource {
+                name: "foo.bar".to_string(),
+                source: DataLocation::Path(module_path),
+                is_package: true,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+                provenance: None,
+            })
+        );
+        assert_eq!(
+            resources[1],
+            PythonResource::Resource(PythonPackageResource {
+                leaf_package: "foo".to_string(),
+                relative_name: "resource.txt".to_string(),
+                data: DataLocation::Path(resource_path),
+                is_stdlib: false,
+                is_test: false,
+                provenance: None,
             })
         );
 
@@ -1397,6 +1762,7 @@ mod tests {
                 version: "1.2.3".to_string(),
                 name: "METADATA".to_string(),
                 data: DataLocation::Path(metadata_path),
+                provenance: None,
             })
         );
         assert_eq!(
@@ -1407,6 +1773,7 @@ mod tests {
                 version: "1.2.3".to_string(),
                 name: "file.txt".to_string(),
                 data: DataLocation::Path(resource_path),
+                provenance: None,
             })
         );
         assert_eq!(
@@ -1417,6 +1784,51 @@ mod tests {
                 version: "1.2.3".to_string(),
                 name: "subdir/sub.txt".to_string(),
                 data: DataLocation::Path(subdir_resource_path),
+                provenance: None,
+            })
+        );
+
+        Ok(())
+    }
+
+    /// Entry points are emitted after the entry_points.txt distribution resource.
+    #[test]
+    fn test_distinfo_entry_points() -> Result<()> {
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+        let tp = td.path();
+
+        let dist_path = tp.join("black-1.2.3.dist-info");
+        create_dir_all(&dist_path)?;
+        write(&dist_path.join("METADATA"), "Name: black\nVersion: 1.2.3\n")?;
+        let entry_points_path = dist_path.join("entry_points.txt");
+        write(
+            &entry_points_path,
+            "[console_scripts]\nblack = black:patched_main\n",
+        )?;
+
+        let resources = PythonResourceIterator::new(tp, DEFAULT_CACHE_TAG, &DEFAULT_SUFFIXES)
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(resources.len(), 3);
+
+        assert_eq!(
+            resources[1],
+            PythonResource::DistributionResource(PythonPackageDistributionResource {
+                location: PythonPackageDistributionResourceFlavor::DistInfo,
+                package: "black".to_string(),
+                version: "1.2.3".to_string(),
+                name: "entry_points.txt".to_string(),
+                data: DataLocation::Path(entry_points_path),
+                provenance: None,
+            })
+        );
+        assert_eq!(
+            resources[2],
+            PythonResource::EntryPoint(PythonEntryPoint {
+                package: "black".to_string(),
+                version: "1.2.3".to_string(),
+                group: "console_scripts".to_string(),
+                name: "black".to_string(),
+                value: "black:patched_main".to_string(),
             })
         );
 
@@ -1453,6 +1865,7 @@ mod tests {
                 version: "1.2.3".to_string(),
                 name: "PKG-INFO".to_string(),
                 data: DataLocation::Path(metadata_path),
+                provenance: None,
             })
         );
         assert_eq!(
@@ -1463,6 +1876,7 @@ mod tests {
                 version: "1.2.3".to_string(),
                 name: "file.txt".to_string(),
                 data: DataLocation::Path(resource_path),
+                provenance: None,
             })
         );
         assert_eq!(
@@ -1473,6 +1887,7 @@ mod tests {
                 version: "1.2.3".to_string(),
                 name: "subdir/sub.txt".to_string(),
                 data: DataLocation::Path(subdir_resource_path),
+                provenance: None,
             })
         );
 
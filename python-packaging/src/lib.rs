@@ -17,3 +17,4 @@ pub mod policy;
 pub mod python_source;
 pub mod resource;
 pub mod resource_collection;
+pub mod wheel;
@@ -2,6 +2,8 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use anyhow::{anyhow, Result};
+
 /// SPDX licenses in Python distributions that are not GPL.
 ///
 /// We store an allow list of licenses rather than trying to deny GPL licenses
@@ -15,3 +17,257 @@ pub const NON_GPL_LICENSES: &[&str] = &[
     "X11",
     "Zlib",
 ];
+
+/// Represents a parsed SPDX license expression.
+///
+/// See https://spdx.github.io/spdx-spec/appendix-IV-SPDX-license-expressions/
+/// for the grammar.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LicenseExpression {
+    /// A single license identifier, e.g. `MIT` or `GPL-2.0+`.
+    License(String),
+
+    /// A license with an exception, e.g. `Apache-2.0 WITH LLVM-exception`.
+    ///
+    /// Tuple is the license identifier and the exception identifier.
+    With(String, String),
+
+    /// All sub-expressions apply.
+    And(Vec<LicenseExpression>),
+
+    /// Any one of the sub-expressions can be chosen.
+    Or(Vec<LicenseExpression>),
+}
+
+impl LicenseExpression {
+    /// Parse an SPDX license expression from a string.
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut tokens = vec![];
+        let mut current = String::new();
+
+        for c in s.chars() {
+            if c == '(' || c == ')' || c.is_whitespace() {
+                if !current.is_empty() {
+                    tokens.push(current.clone());
+                    current.clear();
+                }
+
+                if !c.is_whitespace() {
+                    tokens.push(c.to_string());
+                }
+            } else {
+                current.push(c);
+            }
+        }
+
+        if !current.is_empty() {
+            tokens.push(current);
+        }
+
+        let mut parser = ExpressionParser {
+            tokens: &tokens,
+            position: 0,
+        };
+
+        let expression = parser.parse_or()?;
+
+        if parser.position != tokens.len() {
+            return Err(anyhow!(
+                "unexpected token {} in license expression {}",
+                tokens[parser.position],
+                s
+            ));
+        }
+
+        Ok(expression)
+    }
+
+    /// Evaluate whether this expression is satisfied given a license predicate.
+    ///
+    /// `allowed` is called with license identifiers and with the
+    /// `<license> WITH <exception>` form of exceptions. A license with an
+    /// exception is satisfied if either form is allowed.
+    pub fn evaluate<F>(&self, allowed: &F) -> bool
+    where
+        F: Fn(&str) -> bool,
+    {
+        match self {
+            LicenseExpression::License(license) => allowed(license.as_str()),
+            LicenseExpression::With(license, exception) => {
+                allowed(format!("{} WITH {}", license, exception).as_str())
+                    || allowed(license.as_str())
+            }
+            LicenseExpression::And(expressions) => expressions.iter().all(|e| e.evaluate(allowed)),
+            LicenseExpression::Or(expressions) => expressions.iter().any(|e| e.evaluate(allowed)),
+        }
+    }
+}
+
+/// Recursive descent parser for tokenized SPDX license expressions.
+struct ExpressionParser<'a> {
+    tokens: &'a [String],
+    position: usize,
+}
+
+impl<'a> ExpressionParser<'a> {
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        match self.tokens.get(self.position) {
+            Some(token) => token == keyword || token == &keyword.to_lowercase(),
+            None => false,
+        }
+    }
+
+    fn next_token(&mut self) -> Result<&'a str> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .ok_or_else(|| anyhow!("unexpected end of license expression"))?;
+        self.position += 1;
+
+        Ok(token.as_str())
+    }
+
+    fn parse_or(&mut self) -> Result<LicenseExpression> {
+        let mut expressions = vec![self.parse_and()?];
+
+        while self.peek_keyword("OR") {
+            self.position += 1;
+            expressions.push(self.parse_and()?);
+        }
+
+        Ok(if expressions.len() == 1 {
+            expressions.remove(0)
+        } else {
+            LicenseExpression::Or(expressions)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<LicenseExpression> {
+        let mut expressions = vec![self.parse_with()?];
+
+        while self.peek_keyword("AND") {
+            self.position += 1;
+            expressions.push(self.parse_with()?);
+        }
+
+        Ok(if expressions.len() == 1 {
+            expressions.remove(0)
+        } else {
+            LicenseExpression::And(expressions)
+        })
+    }
+
+    fn parse_with(&mut self) -> Result<LicenseExpression> {
+        let token = self.next_token()?;
+
+        if token == "(" {
+            let expression = self.parse_or()?;
+
+            if self.next_token()? != ")" {
+                return Err(anyhow!("expected ) in license expression"));
+            }
+
+            return Ok(expression);
+        }
+
+        if token == ")" || ["AND", "OR", "WITH"].contains(&token.to_uppercase().as_str()) {
+            return Err(anyhow!("expected license identifier; got {}", token));
+        }
+
+        if self.peek_keyword("WITH") {
+            self.position += 1;
+            let exception = self.next_token()?;
+
+            Ok(LicenseExpression::With(
+                token.to_string(),
+                exception.to_string(),
+            ))
+        } else {
+            Ok(LicenseExpression::License(token.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_simple() -> Result<()> {
+        assert_eq!(
+            LicenseExpression::parse("MIT")?,
+            LicenseExpression::License("MIT".to_string())
+        );
+        assert_eq!(
+            LicenseExpression::parse("Apache-2.0 WITH LLVM-exception")?,
+            LicenseExpression::With("Apache-2.0".to_string(), "LLVM-exception".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_compound() -> Result<()> {
+        assert_eq!(
+            LicenseExpression::parse("MIT OR GPL-2.0-only")?,
+            LicenseExpression::Or(vec![
+                LicenseExpression::License("MIT".to_string()),
+                LicenseExpression::License("GPL-2.0-only".to_string()),
+            ])
+        );
+
+        // AND binds tighter than OR.
+        assert_eq!(
+            LicenseExpression::parse("MIT AND Zlib or GPL-2.0-only")?,
+            LicenseExpression::Or(vec![
+                LicenseExpression::And(vec![
+                    LicenseExpression::License("MIT".to_string()),
+                    LicenseExpression::License("Zlib".to_string()),
+                ]),
+                LicenseExpression::License("GPL-2.0-only".to_string()),
+            ])
+        );
+
+        assert_eq!(
+            LicenseExpression::parse("MIT AND (Zlib OR GPL-2.0-only)")?,
+            LicenseExpression::And(vec![
+                LicenseExpression::License("MIT".to_string()),
+                LicenseExpression::Or(vec![
+                    LicenseExpression::License("Zlib".to_string()),
+                    LicenseExpression::License("GPL-2.0-only".to_string()),
+                ]),
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(LicenseExpression::parse("").is_err());
+        assert!(LicenseExpression::parse("MIT OR").is_err());
+        assert!(LicenseExpression::parse("(MIT").is_err());
+        assert!(LicenseExpression::parse("MIT)").is_err());
+        assert!(LicenseExpression::parse("AND MIT").is_err());
+    }
+
+    #[test]
+    fn test_evaluate() -> Result<()> {
+        let allowed = |license: &str| NON_GPL_LICENSES.contains(&license);
+
+        assert!(LicenseExpression::parse("MIT")?.evaluate(&allowed));
+        assert!(!LicenseExpression::parse("GPL-2.0-only")?.evaluate(&allowed));
+        assert!(LicenseExpression::parse("MIT OR GPL-2.0-only")?.evaluate(&allowed));
+        assert!(!LicenseExpression::parse("MIT AND GPL-2.0-only")?.evaluate(&allowed));
+        assert!(LicenseExpression::parse("MIT WITH some-exception")?.evaluate(&allowed));
+
+        let allowed = |license: &str| license == "GPL-2.0-only WITH Classpath-exception-2.0";
+        assert!(
+            LicenseExpression::parse("GPL-2.0-only WITH Classpath-exception-2.0")?
+                .evaluate(&allowed)
+        );
+        assert!(!LicenseExpression::parse("GPL-2.0-only")?.evaluate(&allowed));
+
+        Ok(())
+    }
+}
@@ -4,7 +4,12 @@
 
 /*! Utility functions related to Python modules. */
 
-use {std::collections::BTreeSet, std::path::Path, std::path::PathBuf};
+use {
+    std::collections::{BTreeSet, HashMap},
+    std::hash::BuildHasher,
+    std::path::Path,
+    std::path::PathBuf,
+};
 
 /// Represents file name suffixes for Python modules.
 #[derive(Clone, Debug, PartialEq)]
@@ -58,6 +63,29 @@ where
     package_names
 }
 
+/// Find the value registered against the most specific package containing a module.
+///
+/// Keys of `entries` are fully qualified package names. A key matches if it is
+/// equal to `module` or is one of its parent packages. The longest matching key
+/// wins.
+pub fn find_package_entry<'a, V, S: BuildHasher>(
+    entries: &'a HashMap<String, V, S>,
+    module: &str,
+) -> Option<&'a V> {
+    let mut search = module;
+
+    loop {
+        if let Some(value) = entries.get(search) {
+            return Some(value);
+        }
+
+        match search.rfind('.') {
+            Some(idx) => search = &search[0..idx],
+            None => return None,
+        }
+    }
+}
+
 /// Resolve the filesystem path for a module.
 ///
 /// Takes a path prefix, fully-qualified module name, whether the module is a package,
@@ -126,6 +154,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_find_package_entry() {
+        let mut entries = HashMap::new();
+        entries.insert("foo".to_string(), 1);
+        entries.insert("foo.bar".to_string(), 2);
+
+        assert_eq!(find_package_entry(&entries, "foo"), Some(&1));
+        assert_eq!(find_package_entry(&entries, "foo.baz"), Some(&1));
+        assert_eq!(find_package_entry(&entries, "foo.bar"), Some(&2));
+        assert_eq!(find_package_entry(&entries, "foo.bar.baz"), Some(&2));
+        assert_eq!(find_package_entry(&entries, "foobar"), None);
+        assert_eq!(find_package_entry(&entries, "bar.foo"), None);
+    }
+
     #[test]
     fn test_resolve_path_for_module() {
         assert_eq!(
@@ -5,7 +5,7 @@
 /*! Working with Python package metadata (i.e. .pkg-info directories) */
 
 use {
-    anyhow::{Context, Result},
+    anyhow::{anyhow, Context, Result},
     mailparse::parse_mail,
 };
 
@@ -62,6 +62,137 @@ impl PythonPackageMetadata {
     }
 }
 
+/// Parse the content of an `entry_points.txt` file.
+///
+/// The file is in INI format, with sections naming entry point groups and
+/// `name = value` entries. Returns `(group, name, value)` tuples in file
+/// order.
+pub fn parse_entry_points(data: &[u8]) -> Result<Vec<(String, String, String)>> {
+    let data = std::str::from_utf8(data).context("entry points file is not valid UTF-8")?;
+
+    let mut group = None;
+    let mut entries = vec![];
+
+    for (i, line) in data.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if line.starts_with('[') {
+            if !line.ends_with(']') {
+                return Err(anyhow!("line {}: malformed section header", i + 1));
+            }
+
+            group = Some(line[1..line.len() - 1].trim().to_string());
+            continue;
+        }
+
+        let group = group
+            .as_ref()
+            .ok_or_else(|| anyhow!("line {}: entry outside of a section", i + 1))?;
+
+        let mut parts = line.splitn(2, '=');
+        let name = parts.next().unwrap().trim();
+        let value = parts
+            .next()
+            .ok_or_else(|| anyhow!("line {}: expected `name = value`", i + 1))?
+            .trim();
+
+        if name.is_empty() || value.is_empty() {
+            return Err(anyhow!("line {}: expected `name = value`", i + 1));
+        }
+
+        entries.push((group.clone(), name.to_string(), value.to_string()));
+    }
+
+    Ok(entries)
+}
+
+/// Parse an entry point object reference into module and attribute names.
+///
+/// References have the form `module:attr [extras]`. The attribute is
+/// optional. Extras are ignored.
+pub fn parse_object_reference(value: &str) -> Result<(String, Option<String>)> {
+    let reference = if let Some(idx) = value.find('[') {
+        &value[0..idx]
+    } else {
+        value
+    };
+
+    let mut parts = reference.splitn(2, ':');
+    let module = parts.next().unwrap().trim();
+    let attr = parts.next().map(|attr| attr.trim());
+
+    let is_dotted_name = |s: &str| {
+        !s.is_empty()
+            && s.split('.').all(|part| {
+                !part.is_empty()
+                    && !part.chars().next().unwrap().is_ascii_digit()
+                    && part.chars().all(|c| c.is_alphanumeric() || c == '_')
+            })
+    };
+
+    if !is_dotted_name(module) {
+        return Err(anyhow!("invalid module in object reference: {}", value));
+    }
+
+    match attr {
+        Some(attr) if !is_dotted_name(attr) => {
+            Err(anyhow!("invalid attribute in object reference: {}", value))
+        }
+        Some(attr) => Ok((module.to_string(), Some(attr.to_string()))),
+        None => Ok((module.to_string(), None)),
+    }
+}
+
+/// Parse the paths of installed files from the content of a `RECORD` file.
+///
+/// `RECORD` files are CSV files whose first column is the path of an
+/// installed file, relative to the directory containing the `.dist-info`
+/// directory. Other columns are ignored.
+pub fn parse_record_paths(data: &[u8]) -> Result<Vec<String>> {
+    let data = std::str::from_utf8(data).context("RECORD file is not valid UTF-8")?;
+
+    let mut paths = vec![];
+
+    for (i, line) in data.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let path = if line.starts_with('"') {
+            let mut path = String::new();
+            let mut chars = line[1..].chars().peekable();
+
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        path.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => path.push(c),
+                    None => return Err(anyhow!("line {}: unterminated quoted path", i + 1)),
+                }
+            }
+
+            path
+        } else {
+            line.splitn(2, ',').next().unwrap().to_string()
+        };
+
+        if path.is_empty() {
+            return Err(anyhow!("line {}: empty path", i + 1));
+        }
+
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,4 +230,91 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_entry_points() -> Result<()> {
+        let data = concat!(
+            "[console_scripts]\n",
+            "black = black:patched_main\n",
+            "blackd = blackd:patched_main [d]\n",
+            "\n",
+            "# comment\n",
+            "[pygments.lexers]\n",
+            "mylexer=mypkg.lexers:MyLexer\n",
+        )
+        .as_bytes();
+
+        assert_eq!(
+            parse_entry_points(data)?,
+            vec![
+                (
+                    "console_scripts".to_string(),
+                    "black".to_string(),
+                    "black:patched_main".to_string()
+                ),
+                (
+                    "console_scripts".to_string(),
+                    "blackd".to_string(),
+                    "blackd:patched_main [d]".to_string()
+                ),
+                (
+                    "pygments.lexers".to_string(),
+                    "mylexer".to_string(),
+                    "mypkg.lexers:MyLexer".to_string()
+                ),
+            ]
+        );
+
+        assert!(parse_entry_points(b"foo = bar:baz\n").is_err());
+        assert!(parse_entry_points(b"[console_scripts]\nfoo\n").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_object_reference() -> Result<()> {
+        assert_eq!(
+            parse_object_reference("mypkg.cli:main")?,
+            ("mypkg.cli".to_string(), Some("main".to_string()))
+        );
+        assert_eq!(
+            parse_object_reference("blackd : patched_main [d]")?,
+            ("blackd".to_string(), Some("patched_main".to_string()))
+        );
+        assert_eq!(
+            parse_object_reference("mypkg.__main__")?,
+            ("mypkg.__main__".to_string(), None)
+        );
+        assert!(parse_object_reference("").is_err());
+        assert!(parse_object_reference("mypkg:").is_err());
+        assert!(parse_object_reference("my-pkg:main").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_record_paths() -> Result<()> {
+        let data = concat!(
+            "black.py,sha256=abc,1234\n",
+            "\"foo,bar/baz.py\",sha256=def,10\n",
+            "\"say \"\"hi\"\".txt\",,\n",
+            "\n",
+            "black-19.10b0.dist-info/RECORD,,\n",
+        );
+
+        assert_eq!(
+            parse_record_paths(data.as_bytes())?,
+            vec![
+                "black.py",
+                "foo,bar/baz.py",
+                "say \"hi\".txt",
+                "black-19.10b0.dist-info/RECORD"
+            ]
+        );
+
+        assert!(parse_record_paths(b"\"foo.py,,\n").is_err());
+        assert!(parse_record_paths(b",sha256=abc,1\n").is_err());
+
+        Ok(())
+    }
 }
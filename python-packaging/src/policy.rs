@@ -7,12 +7,21 @@ Functionality for defining how Python resources should be packaged.
 */
 
 use {
-    crate::licensing::NON_GPL_LICENSES,
-    crate::resource::{PythonExtensionModule, PythonExtensionModuleVariants, PythonResource},
-    anyhow::{anyhow, Result},
-    std::collections::HashMap,
+    crate::licensing::{LicenseExpression, NON_GPL_LICENSES},
+    crate::module_util::{find_package_entry, packages_from_module_name},
+    crate::resource::{
+        BytecodeOptimizationLevel, PythonExtensionModule, PythonExtensionModuleVariants,
+        PythonResource,
+    },
+    anyhow::{anyhow, Context, Result},
+    python_packed_resources::writer::BlobCompression,
+    regex::Regex,
+    std::borrow::Cow,
+    std::collections::{BTreeMap, BTreeSet, HashMap},
     std::convert::TryFrom,
     std::iter::FromIterator,
+    std::path::{Path, PathBuf},
+    std::sync::{Arc, Mutex},
 };
 
 /// Describes a policy for the location of Python resources.
@@ -35,6 +44,14 @@ pub enum PythonResourcesPolicy {
     /// in-memory loading works, it is used. Otherwise loading from a filesystem path
     /// relative to the produced binary is used.
     PreferInMemoryFallbackFilesystemRelative(String),
+
+    /// Only allow Python resources to be loaded from an absolute filesystem path.
+    ///
+    /// The `PathBuf` represents the directory resources are loaded from at
+    /// run-time, e.g. `/usr/lib/myapp/python`. Resources are installed into
+    /// the build output directory using this path with its root removed, so
+    /// the output directory can serve as a staging root for system packages.
+    FilesystemAbsolute(PathBuf),
 }
 
 impl TryFrom<&str> for PythonResourcesPolicy {
@@ -53,6 +70,20 @@ impl TryFrom<&str> for PythonResourcesPolicy {
             let prefix = &value["prefer-in-memory-fallback-filesystem-relative:".len()..];
 
             Ok(PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative(prefix.to_string()))
+        } else if value.starts_with("filesystem-absolute:") {
+            let path = &value["filesystem-absolute:".len()..];
+
+            // is_absolute() on Windows doesn't consider paths with a leading / absolute.
+            if path.starts_with('/') || Path::new(path).is_absolute() {
+                Ok(PythonResourcesPolicy::FilesystemAbsolute(PathBuf::from(
+                    path,
+                )))
+            } else {
+                Err(anyhow!(
+                    "filesystem-absolute policy requires an absolute path; got {}",
+                    path
+                ))
+            }
         } else {
             Err(anyhow!(
                 "invalid value for Python Resources Policy: {}",
@@ -72,6 +103,9 @@ impl Into<String> for &PythonResourcesPolicy {
             PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative(ref prefix) => {
                 format!("prefer-in-memory-fallback-filesystem-relative:{}", prefix)
             }
+            PythonResourcesPolicy::FilesystemAbsolute(ref path) => {
+                format!("filesystem-absolute:{}", path.display())
+            }
         }
     }
 }
@@ -83,6 +117,9 @@ pub enum ExtensionModuleFilter {
     All,
     NoLibraries,
     NoGPL,
+
+    /// Only the named extension modules and minimally required ones.
+    Explicit(Vec<String>),
 }
 
 impl TryFrom<&str> for ExtensionModuleFilter {
@@ -94,255 +131,3580 @@ impl TryFrom<&str> for ExtensionModuleFilter {
             "all" => Ok(ExtensionModuleFilter::All),
             "no-libraries" => Ok(ExtensionModuleFilter::NoLibraries),
             "no-gpl" => Ok(ExtensionModuleFilter::NoGPL),
+            t if t.starts_with("explicit:") => Ok(ExtensionModuleFilter::Explicit(
+                t["explicit:".len()..]
+                    .split(',')
+                    .map(|x| x.trim())
+                    .filter(|x| !x.is_empty())
+                    .map(|x| x.to_string())
+                    .collect(),
+            )),
             t => Err(format!("{} is not a valid extension module filter", t)),
         }
     }
 }
 
-/// Defines how Python resources should be packaged.
-#[derive(Clone, Debug)]
-pub struct PythonPackagingPolicy {
-    /// Which extension modules should be included.
-    extension_module_filter: ExtensionModuleFilter,
+/// Filesystem attributes of resources installed next to the produced binary.
+///
+/// These apply to resources materialized on the filesystem, e.g. by the
+/// `filesystem-relative-only:<prefix>` resources policy. Unset fields leave
+/// the corresponding attribute as produced by the build host.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InstalledResourceAttributes {
+    /// Permissions mode of installed files, e.g. `0o644`.
+    pub file_mode: Option<u32>,
 
-    /// Preferred variants of extension modules.
-    preferred_extension_module_variants: HashMap<String, String>,
+    /// Permissions mode of installed executable files, e.g. `0o755`.
+    pub executable_mode: Option<u32>,
 
-    /// Where resources should be packaged by default.
-    resources_policy: PythonResourcesPolicy,
+    /// Modification time of installed files in seconds since the UNIX epoch.
+    ///
+    /// Setting this helps make the installed layout reproducible.
+    pub mtime: Option<u64>,
 
-    /// Whether to include source module from the Python distribution.
-    include_distribution_sources: bool,
+    /// Name of the user that should own installed files.
+    ///
+    /// Ownership can't be changed without elevated privileges, so this is a
+    /// hint for tools packaging the installed files.
+    pub owner: Option<String>,
 
-    /// Whether to include package resource files.
-    include_distribution_resources: bool,
+    /// Name of the group that should own installed files.
+    ///
+    /// Like `owner`, this is a hint for packaging tools.
+    pub group: Option<String>,
+}
 
-    /// Whether to include test files.
-    include_test: bool,
+/// Describes how to react to resources exceeding a size budget.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResourceSizeBudgetAction {
+    /// Emit a warning.
+    Warn,
+
+    /// Fail the build.
+    Error,
 
-    /// Mapping of target triple to list of extensions that don't work for that triple.
+    /// Exclude oversized resources when filtering.
     ///
-    /// Policy constructors can populate this with known broken extensions to
-    /// prevent the policy from allowing an extension.
-    broken_extensions: HashMap<String, Vec<String>>,
+    /// Resources exceeding the per-resource budget are rejected by
+    /// `filter_python_resource()`. Exceeding the total budget is a warning.
+    Exclude,
 }
 
-impl Default for PythonPackagingPolicy {
-    fn default() -> Self {
-        PythonPackagingPolicy {
-            extension_module_filter: ExtensionModuleFilter::All,
-            preferred_extension_module_variants: HashMap::new(),
-            resources_policy: PythonResourcesPolicy::InMemoryOnly,
-            include_distribution_sources: true,
-            include_distribution_resources: false,
-            include_test: false,
-            broken_extensions: HashMap::new(),
+impl TryFrom<&str> for ResourceSizeBudgetAction {
+    type Error = String;
+
+    // `Self::Error` would be ambiguous with the `Error` variant.
+    fn try_from(value: &str) -> Result<Self, String> {
+        match value {
+            "warn" => Ok(ResourceSizeBudgetAction::Warn),
+            "error" => Ok(ResourceSizeBudgetAction::Error),
+            "exclude" => Ok(ResourceSizeBudgetAction::Exclude),
+            t => Err(format!("{} is not a valid resource size budget action", t)),
         }
     }
 }
 
-impl PythonPackagingPolicy {
-    /// Obtain the active extension module filter for this instance.
-    pub fn get_extension_module_filter(&self) -> &ExtensionModuleFilter {
-        &self.extension_module_filter
-    }
+/// Describes how to react to .pth file lines executing code.
+///
+/// Python's `site` module executes lines of .pth files beginning with
+/// `import`. These lines often install import hooks or otherwise alter
+/// import behavior. We can't execute them when scanning for resources and
+/// our importer doesn't process .pth files at run time, so their effects
+/// are lost.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathExtensionImportsAction {
+    /// Silently ignore the lines.
+    Ignore,
 
-    /// Set the extension module filter to use.
-    pub fn set_extension_module_filter(&mut self, filter: ExtensionModuleFilter) {
-        self.extension_module_filter = filter;
-    }
+    /// Emit a warning.
+    Warn,
 
-    /// Denote the preferred variant for an extension module.
-    ///
-    /// If set, the named variant will be chosen if it is present.
-    pub fn set_preferred_extension_module_variant(&mut self, extension: &str, variant: &str) {
-        self.preferred_extension_module_variants
-            .insert(extension.to_string(), variant.to_string());
-    }
+    /// Fail the build.
+    Error,
+}
 
-    /// Obtain the active resources policy for this instance.
-    pub fn get_resources_policy(&self) -> &PythonResourcesPolicy {
-        &self.resources_policy
-    }
+impl TryFrom<&str> for PathExtensionImportsAction {
+    type Error = String;
 
-    /// Set the resource loading policy.
-    pub fn set_resources_policy(&mut self, policy: PythonResourcesPolicy) {
-        self.resources_policy = policy;
+    // `Self::Error` would be ambiguous with the `Error` variant.
+    fn try_from(value: &str) -> Result<Self, String> {
+        match value {
+            "ignore" => Ok(PathExtensionImportsAction::Ignore),
+            "warn" => Ok(PathExtensionImportsAction::Warn),
+            "error" => Ok(PathExtensionImportsAction::Error),
+            t => Err(format!("{} is not a valid .pth imports action", t)),
+        }
     }
+}
 
-    /// Set whether we should include a Python distribution's module source code.
-    pub fn set_include_distribution_sources(&mut self, include: bool) {
-        self.include_distribution_sources = include;
+/// Describes violations of resource size budgets.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResourceSizeReport {
+    /// Resources exceeding the per-resource budget and their sizes.
+    pub oversized_resources: Vec<(String, u64)>,
+
+    /// The per-resource budget that was evaluated.
+    pub max_resource_size: Option<u64>,
+
+    /// Total size of all resources.
+    pub total_size: u64,
+
+    /// The total size budget that was evaluated.
+    pub max_total_size: Option<u64>,
+}
+
+impl ResourceSizeReport {
+    /// Whether the total size budget is exceeded.
+    pub fn total_size_exceeded(&self) -> bool {
+        match self.max_total_size {
+            Some(max) => self.total_size > max,
+            None => false,
+        }
     }
 
-    /// Set whether to include package resources from the Python distribution.
-    pub fn set_include_distribution_resources(&mut self, include: bool) {
-        self.include_distribution_resources = include;
+    /// Whether any budget is exceeded.
+    pub fn is_violation(&self) -> bool {
+        !self.oversized_resources.is_empty() || self.total_size_exceeded()
     }
 
-    /// Set whether we should include Python modules that define tests.
-    pub fn set_include_test(&mut self, include: bool) {
-        self.include_test = include;
+    /// Obtain human readable lines describing budget violations.
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = vec![];
+
+        if let Some(max) = self.max_resource_size {
+            for (name, size) in &self.oversized_resources {
+                lines.push(format!(
+                    "resource {} is {} bytes; exceeds budget of {} bytes",
+                    name, size, max
+                ));
+            }
+        }
+
+        if let Some(max) = self.max_total_size {
+            if self.total_size > max {
+                lines.push(format!(
+                    "resources total {} bytes; exceeds budget of {} bytes",
+                    self.total_size, max
+                ));
+            }
+        }
+
+        lines
     }
+}
 
-    /// Mark an extension as broken on a target platform, preventing it from being used.
-    pub fn register_broken_extension(&mut self, target_triple: &str, extension: &str) {
-        if !self.broken_extensions.contains_key(target_triple) {
-            self.broken_extensions
-                .insert(target_triple.to_string(), vec![]);
+/// The outcome of a resource filter callback.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FilterDecision {
+    /// The resource should be included.
+    Include,
+
+    /// The resource should be excluded.
+    Exclude,
+
+    /// No opinion. Defer to other callbacks and the policy's built-in rules.
+    Defer,
+}
+
+/// A category of Python resources.
+///
+/// Tags are assigned by `ResourceClassifier` implementations and can be used
+/// to exclude whole categories of resources from packaging.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ResourceTag {
+    /// Test code or test data.
+    Test,
+
+    /// Documentation.
+    Documentation,
+
+    /// Type stubs and typing markers, e.g. `.pyi` files and `py.typed`.
+    TypeStubs,
+
+    /// Translations and other locale data.
+    Locale,
+
+    /// Example code.
+    Examples,
+
+    /// A tag defined by a custom classifier.
+    Custom(String),
+}
+
+impl TryFrom<&str> for ResourceTag {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "test" => Ok(ResourceTag::Test),
+            "documentation" => Ok(ResourceTag::Documentation),
+            "type-stubs" => Ok(ResourceTag::TypeStubs),
+            "locale" => Ok(ResourceTag::Locale),
+            "examples" => Ok(ResourceTag::Examples),
+            t if t.starts_with("custom:") && t.len() > "custom:".len() => {
+                Ok(ResourceTag::Custom(t["custom:".len()..].to_string()))
+            }
+            t => Err(format!("{} is not a valid resource tag", t)),
         }
+    }
+}
 
-        self.broken_extensions
-            .get_mut(target_triple)
-            .unwrap()
-            .push(extension.to_string());
+impl std::fmt::Display for ResourceTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResourceTag::Test => write!(f, "test"),
+            ResourceTag::Documentation => write!(f, "documentation"),
+            ResourceTag::TypeStubs => write!(f, "type-stubs"),
+            ResourceTag::Locale => write!(f, "locale"),
+            ResourceTag::Examples => write!(f, "examples"),
+            ResourceTag::Custom(name) => write!(f, "custom:{}", name),
+        }
     }
+}
 
-    /// Determine if a Python resource is applicable to the current policy.
-    ///
-    /// Given a `PythonResource`, this answers the question of whether that
-    /// resource meets the inclusion requirements for the current policy.
-    ///
-    /// Returns true if the resource should be included, false otherwise.
-    pub fn filter_python_resource(&self, resource: &PythonResource) -> bool {
-        match resource {
-            PythonResource::ModuleSource(module) => {
-                if !self.include_test && module.is_test {
-                    false
-                } else {
-                    self.include_distribution_sources
-                }
+/// Assigns `ResourceTag`s to Python resources.
+pub trait ResourceClassifier {
+    /// Obtain the tags applying to a resource.
+    fn classify(&self, resource: &PythonResource) -> BTreeSet<ResourceTag>;
+}
+
+/// A `ResourceClassifier` tagging resources based on common naming conventions.
+///
+/// Tags are derived from the components of a resource's package and
+/// relative path. e.g. resources in `tests` packages are tagged as tests
+/// and `.mo` files are tagged as locale data.
+#[derive(Clone, Debug, Default)]
+pub struct DefaultResourceClassifier {}
+
+impl ResourceClassifier for DefaultResourceClassifier {
+    fn classify(&self, resource: &PythonResource) -> BTreeSet<ResourceTag> {
+        let mut tags = BTreeSet::new();
+
+        let (components, filename): (Vec<&str>, Option<&str>) = match resource {
+            PythonResource::ModuleSource(module) => (module.name.split('.').collect(), None),
+            PythonResource::ModuleBytecodeRequest(module) => {
+                (module.name.split('.').collect(), None)
             }
-            PythonResource::ModuleBytecodeRequest(module) => self.include_test || !module.is_test,
-            PythonResource::ModuleBytecode(_) => false,
+            PythonResource::ModuleBytecode(module) => (module.name.split('.').collect(), None),
             PythonResource::Resource(resource) => {
-                if self.include_distribution_resources {
-                    self.include_test || !resource.is_test
-                } else {
-                    false
+                let mut components = resource.leaf_package.split('.').collect::<Vec<_>>();
+                components.extend(resource.relative_name.split('/'));
+                let filename = components.pop();
+
+                (components, filename)
+            }
+            _ => (vec![], None),
+        };
+
+        for component in &components {
+            match *component {
+                "test" | "tests" => {
+                    tags.insert(ResourceTag::Test);
+                }
+                "doc" | "docs" => {
+                    tags.insert(ResourceTag::Documentation);
+                }
+                "example" | "examples" => {
+                    tags.insert(ResourceTag::Examples);
                 }
+                "locale" | "locales" => {
+                    tags.insert(ResourceTag::Locale);
+                }
+                _ => {}
             }
-            PythonResource::DistributionResource(_) => false,
-            PythonResource::ExtensionModuleDynamicLibrary(_) => false,
-            PythonResource::ExtensionModuleStaticallyLinked(_) => false,
-            PythonResource::PathExtension(_) => false,
-            PythonResource::EggFile(_) => false,
         }
-    }
 
-    /// Resolve Python extension modules that are compliant with the policy.
-    #[allow(clippy::if_same_then_else)]
-    pub fn resolve_python_extension_modules<'a>(
-        &self,
-        extensions_variants: impl Iterator<Item = &'a PythonExtensionModuleVariants>,
-        target_triple: &str,
-    ) -> Result<Vec<PythonExtensionModule>> {
-        let mut res = vec![];
+        if filename.is_none() {
+            if let Some(leaf) = components.last() {
+                if leaf.starts_with("test_") {
+                    tags.insert(ResourceTag::Test);
+                }
+            }
+        }
 
-        for variants in extensions_variants {
-            let name = &variants.default_variant().name;
+        if is_type_stubs_resource(resource) {
+            tags.insert(ResourceTag::TypeStubs);
+        }
 
-            // This extension is broken on this target. Ignore it.
-            if self
-                .broken_extensions
-                .get(target_triple)
-                .unwrap_or(&Vec::new())
-                .contains(name)
-            {
-                continue;
+        if let Some(filename) = filename {
+            if filename.ends_with(".mo") || filename.ends_with(".po") {
+                tags.insert(ResourceTag::Locale);
+            } else if filename.ends_with(".rst") || filename.ends_with(".md") {
+                tags.insert(ResourceTag::Documentation);
             }
+        }
 
-            // Always add minimally required extension modules, because things don't
-            // work if we don't do this.
-            let ext_variants =
-                PythonExtensionModuleVariants::from_iter(variants.iter().filter_map(|em| {
-                    if em.is_minimally_required() {
-                        Some(em.clone())
-                    } else {
-                        None
-                    }
-                }));
+        tags
+    }
+}
 
-            if !ext_variants.is_empty() {
-                res.push(
-                    ext_variants
-                        .choose_variant(&self.preferred_extension_module_variants)
-                        .clone(),
-                );
-            }
+/// Whether a resource only exists to provide type information.
+///
+/// This is the case for `.pyi` stub files, `py.typed` markers, and
+/// resources in PEP 561 stub-only packages (`<package>-stubs`).
+fn is_type_stubs_resource(resource: &PythonResource) -> bool {
+    match resource {
+        PythonResource::Resource(resource) => {
+            resource.relative_name.ends_with(".pyi")
+                || resource.relative_name == "py.typed"
+                || resource.relative_name.ends_with("/py.typed")
+                || resource
+                    .leaf_package
+                    .split('.')
+                    .any(|component| component.ends_with("-stubs"))
+        }
+        _ => false,
+    }
+}
 
-            match self.extension_module_filter {
-                // Nothing to do here since we added minimal extensions above.
-                ExtensionModuleFilter::Minimal => {}
+/// Holds a registered `ResourceClassifier`.
+///
+/// Exists so policies can remain `Clone` and `Debug`.
+#[derive(Clone)]
+struct Classifier(Arc<dyn ResourceClassifier>);
 
-                ExtensionModuleFilter::All => {
-                    res.push(
-                        variants
-                            .choose_variant(&self.preferred_extension_module_variants)
-                            .clone(),
-                    );
-                }
+impl std::fmt::Debug for Classifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Classifier")
+    }
+}
 
-                ExtensionModuleFilter::NoLibraries => {
-                    let ext_variants = PythonExtensionModuleVariants::from_iter(
-                        variants.iter().filter_map(|em| {
-                            if !em.requires_libraries() {
-                                Some(em.clone())
-                            } else {
-                                None
-                            }
-                        }),
-                    );
+/// Why a `PythonPackagingPolicy` excluded a resource.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExclusionReason {
+    /// A filter callback excluded the resource.
+    FilterCallback,
 
-                    if !ext_variants.is_empty() {
-                        res.push(
-                            ext_variants
-                                .choose_variant(&self.preferred_extension_module_variants)
-                                .clone(),
-                        );
-                    }
-                }
+    /// The resource name matched an exclude pattern or no include pattern.
+    ResourceNamePattern,
 
-                ExtensionModuleFilter::NoGPL => {
-                    let ext_variants = PythonExtensionModuleVariants::from_iter(
-                        variants.iter().filter_map(|em| {
-                            if em.link_libraries.is_empty() {
-                                Some(em.clone())
-                            // Public domain is always allowed.
-                            } else if em.license_public_domain == Some(true) {
-                                Some(em.clone())
-                            // Use explicit license list if one is defined.
-                            } else if let Some(ref licenses) = em.licenses {
-                                // We filter through an allow list because it is safer. (No new GPL
-                                // licenses can slip through.)
-                                if licenses
-                                    .iter()
-                                    .all(|license| NON_GPL_LICENSES.contains(&license.as_str()))
-                                {
-                                    Some(em.clone())
-                                } else {
-                                    None
-                                }
-                            } else {
-                                // In lack of evidence that it isn't GPL, assume GPL.
-                                // TODO consider improving logic here, like allowing known system
-                                // and framework libraries to be used.
-                                None
-                            }
-                        }),
-                    );
+    /// The resource exceeded the maximum resource size.
+    SizeBudget,
 
-                    if !ext_variants.is_empty() {
-                        res.push(
-                            ext_variants
-                                .choose_variant(&self.preferred_extension_module_variants)
-                                .clone(),
-                        );
-                    }
-                }
+    /// Module source isn't packaged in bytecode only mode.
+    BytecodeOnly,
+
+    /// The resource is test code or test data.
+    Test,
+
+    /// Module source from the Python distribution isn't included.
+    DistributionSources,
+
+    /// Package resources from the Python distribution aren't included.
+    DistributionResources,
+
+    /// Package distribution metadata (`.dist-info` files) isn't included.
+    DistributionMetadata,
+
+    /// The policy doesn't support this resource type.
+    UnsupportedResourceType,
+
+    /// The extension module is registered as broken on the target triple.
+    BrokenOnTarget(String),
+
+    /// The extension module wasn't selected by the extension module filter.
+    ExtensionModuleFilter(ExtensionModuleFilter),
+
+    /// The extension module links against libraries with disallowed licenses.
+    License,
+
+    /// The extension module links against dynamic libraries that aren't allowed.
+    DynamicLibraries(Vec<String>),
+
+    /// The resource has a tag that is excluded.
+    ResourceTag(ResourceTag),
+}
+
+impl std::fmt::Display for ExclusionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExclusionReason::FilterCallback => write!(f, "excluded by filter callback"),
+            ExclusionReason::ResourceNamePattern => {
+                write!(f, "excluded by resource name patterns")
+            }
+            ExclusionReason::SizeBudget => write!(f, "exceeds maximum resource size"),
+            ExclusionReason::BytecodeOnly => write!(f, "source excluded in bytecode only mode"),
+            ExclusionReason::Test => write!(f, "test resources are excluded"),
+            ExclusionReason::DistributionSources => {
+                write!(f, "distribution module sources are excluded")
             }
+            ExclusionReason::DistributionResources => {
+                write!(f, "distribution package resources are excluded")
+            }
+            ExclusionReason::DistributionMetadata => {
+                write!(f, "package distribution metadata is excluded")
+            }
+            ExclusionReason::UnsupportedResourceType => {
+                write!(f, "resource type is not supported")
+            }
+            ExclusionReason::BrokenOnTarget(target_triple) => {
+                write!(f, "known to be broken on {}", target_triple)
+            }
+            ExclusionReason::ExtensionModuleFilter(filter) => match filter {
+                ExtensionModuleFilter::Minimal => {
+                    write!(f, "not required by the minimal extension module filter")
+                }
+                ExtensionModuleFilter::NoLibraries => write!(
+                    f,
+                    "requires libraries excluded by the no-libraries extension module filter"
+                ),
+                ExtensionModuleFilter::Explicit(_) => {
+                    write!(f, "not named by the explicit extension module filter")
+                }
+                _ => write!(f, "excluded by the extension module filter"),
+            },
+            ExclusionReason::License => write!(f, "license is not allowed"),
+            ExclusionReason::DynamicLibraries(libraries) => write!(
+                f,
+                "links against dynamic libraries that are not allowed: {}",
+                libraries.join(", ")
+            ),
+            ExclusionReason::ResourceTag(tag) => write!(f, "resources tagged {} are excluded", tag),
         }
+    }
+}
 
-        Ok(res)
+/// Describes a resource excluded by a `PythonPackagingPolicy`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExcludedResource {
+    /// Fully qualified name of the resource.
+    pub name: String,
+
+    /// Human readable type of the resource. e.g. `module source`.
+    pub resource_type: String,
+
+    pub reason: ExclusionReason,
+}
+
+/// Obtain a human readable name for the type of a `PythonResource`.
+fn resource_type_name(resource: &PythonResource) -> &'static str {
+    match resource {
+        PythonResource::ModuleSource(_) => "module source",
+        PythonResource::ModuleBytecodeRequest(_) => "module bytecode request",
+        PythonResource::ModuleBytecode(_) => "module bytecode",
+        PythonResource::Resource(_) => "package resource",
+        PythonResource::DistributionResource(_) => "package distribution resource",
+        PythonResource::ExtensionModuleDynamicLibrary(_)
+        | PythonResource::ExtensionModuleStaticallyLinked(_) => "extension module",
+        PythonResource::PathExtension(_) => "path extension",
+        PythonResource::EggFile(_) => "egg file",
+        PythonResource::EntryPoint(_) => "entry point",
+        PythonResource::DataFile(_) => "data file",
+        PythonResource::EditableInstall(_) => "editable install",
+    }
+}
+
+/// A function deciding whether a Python resource should be included.
+pub type ResourceFilterCallback = dyn Fn(&PythonResource) -> FilterDecision;
+
+/// Holds a registered `ResourceFilterCallback`.
+///
+/// Exists so policies can remain `Clone` and `Debug`.
+#[derive(Clone)]
+struct FilterCallback(Arc<ResourceFilterCallback>);
+
+impl std::fmt::Debug for FilterCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FilterCallback")
+    }
+}
+
+/// Records resources excluded by a policy and why.
+///
+/// Keys are the resource name and resource type. Clones copy the entries
+/// recorded so far and record subsequent exclusions independently.
+#[derive(Debug, Default)]
+struct ExclusionLog(Mutex<BTreeMap<(String, String), ExclusionReason>>);
+
+impl Clone for ExclusionLog {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(self.0.lock().unwrap().clone()))
+    }
+}
+
+/// Convert a pattern matching fully qualified resource names to a regular expression.
+///
+/// Patterns are globs by default: `*` matches any sequence of characters
+/// (including `.`) and `?` matches a single character. Patterns prefixed
+/// with `re:` are interpreted as regular expressions verbatim.
+fn resource_name_pattern_to_regex(pattern: &str) -> Result<Regex> {
+    let expression = if pattern.starts_with("re:") {
+        pattern["re:".len()..].to_string()
+    } else {
+        let mut expression = "^".to_string();
+
+        for c in pattern.chars() {
+            match c {
+                '*' => expression.push_str(".*"),
+                '?' => expression.push('.'),
+                c => expression.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+
+        expression.push('$');
+        expression
+    };
+
+    Regex::new(&expression).context(format!("parsing resource name pattern {}", pattern))
+}
+
+/// Comparison operators for `PythonVersionConstraint`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VersionOperator {
+    Equal,
+    NotEqual,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+}
+
+/// A constraint on a Python version, e.g. `>=3.8` or `3.7`.
+///
+/// Only as many version components as are present in the constraint are
+/// compared. So `3.7` matches `3.7.7` and `<3.8` matches `3.7.7`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PythonVersionConstraint {
+    pub operator: VersionOperator,
+    pub version: Vec<u32>,
+}
+
+impl TryFrom<&str> for PythonVersionConstraint {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let value = value.trim();
+
+        // Longer operators must be checked first.
+        let (operator, version) = if value.starts_with("==") {
+            (VersionOperator::Equal, &value[2..])
+        } else if value.starts_with("!=") {
+            (VersionOperator::NotEqual, &value[2..])
+        } else if value.starts_with("<=") {
+            (VersionOperator::LessThanOrEqual, &value[2..])
+        } else if value.starts_with(">=") {
+            (VersionOperator::GreaterThanOrEqual, &value[2..])
+        } else if value.starts_with('<') {
+            (VersionOperator::LessThan, &value[1..])
+        } else if value.starts_with('>') {
+            (VersionOperator::GreaterThan, &value[1..])
+        } else {
+            (VersionOperator::Equal, value)
+        };
+
+        let version = parse_version(version.trim())
+            .context(format!("parsing Python version constraint {}", value))?;
+
+        Ok(PythonVersionConstraint { operator, version })
+    }
+}
+
+impl PythonVersionConstraint {
+    /// Whether a Python version string satisfies this constraint.
+    ///
+    /// Versions that cannot be parsed never match.
+    pub fn matches(&self, version: &str) -> bool {
+        let mut version = match parse_version(version) {
+            Ok(version) => version,
+            Err(_) => return false,
+        };
+
+        version.resize(self.version.len(), 0);
+        let ordering = version.cmp(&self.version);
+
+        match self.operator {
+            VersionOperator::Equal => ordering == std::cmp::Ordering::Equal,
+            VersionOperator::NotEqual => ordering != std::cmp::Ordering::Equal,
+            VersionOperator::LessThan => ordering == std::cmp::Ordering::Less,
+            VersionOperator::LessThanOrEqual => ordering != std::cmp::Ordering::Greater,
+            VersionOperator::GreaterThan => ordering == std::cmp::Ordering::Greater,
+            VersionOperator::GreaterThanOrEqual => ordering != std::cmp::Ordering::Less,
+        }
+    }
+}
+
+/// Parse a dotted version string like `3.8.1` into its numeric components.
+fn parse_version(version: &str) -> Result<Vec<u32>> {
+    if version.is_empty() {
+        return Err(anyhow!("empty version string"));
+    }
+
+    version
+        .split('.')
+        .map(|component| {
+            component
+                .parse::<u32>()
+                .context(format!("parsing version component {}", component))
+        })
+        .collect()
+}
+
+/// Describes an extension module known to be broken.
+#[derive(Clone, Debug)]
+struct BrokenExtension {
+    /// The pattern strings this entry was registered with.
+    ///
+    /// Tuple is the target triple pattern and the extension pattern.
+    patterns: (String, String),
+
+    /// Pattern of target triples the extension is broken on.
+    target_triple: Regex,
+
+    /// Pattern of extension module names.
+    extension: Regex,
+
+    /// Python versions the extension is broken on. `None` means all versions.
+    python_version: Option<PythonVersionConstraint>,
+}
+
+impl BrokenExtension {
+    fn matches(&self, target_triple: &str, extension: &str, python_version: &str) -> bool {
+        self.target_triple.is_match(target_triple)
+            && self.extension.is_match(extension)
+            && match &self.python_version {
+                Some(constraint) => constraint.matches(python_version),
+                None => true,
+            }
+    }
+}
+
+/// Remove duplicates from a list of optimization levels, preserving order.
+fn dedup_optimize_levels(levels: &[BytecodeOptimizationLevel]) -> Vec<BytecodeOptimizationLevel> {
+    let mut res: Vec<BytecodeOptimizationLevel> = vec![];
+
+    for level in levels {
+        if !res.contains(level) {
+            res.push(*level);
+        }
+    }
+
+    res
+}
+
+/// Severity of a `PolicyDiagnostic`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiagnosticSeverity {
+    /// The policy is usable but likely doesn't do what was intended.
+    Warning,
+
+    /// The policy will cause the build to fail.
+    Error,
+}
+
+/// Describes a problem with a `PythonPackagingPolicy`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PolicyDiagnostic {
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+}
+
+impl std::fmt::Display for PolicyDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.severity {
+            DiagnosticSeverity::Warning => write!(f, "warning: {}", self.message),
+            DiagnosticSeverity::Error => write!(f, "error: {}", self.message),
+        }
+    }
+}
+
+/// Describes the build a `PythonPackagingPolicy` is validated against.
+#[derive(Clone, Debug)]
+pub struct PolicyValidationContext<'a> {
+    /// The target triple being built for.
+    pub target_triple: &'a str,
+
+    /// The version of Python being built for.
+    pub python_version: &'a str,
+
+    /// Extension modules available to the build.
+    pub extension_modules: Vec<&'a PythonExtensionModuleVariants>,
+
+    /// Whether extension module shared libraries can be loaded from memory.
+    pub in_memory_shared_library_loading: bool,
+}
+
+/// Overrides of `PythonPackagingPolicy` settings for specific target triples.
+///
+/// Fields that are `None` leave the corresponding policy setting unchanged.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PythonPackagingPolicyOverlay {
+    pub extension_module_filter: Option<ExtensionModuleFilter>,
+    pub resources_policy: Option<PythonResourcesPolicy>,
+    pub extension_modules_policy: Option<PythonResourcesPolicy>,
+    /// Per-package resources policies added to those of the policy.
+    pub package_resources_policies: BTreeMap<String, PythonResourcesPolicy>,
+    pub include_distribution_sources: Option<bool>,
+    pub include_distribution_resources: Option<bool>,
+    pub include_test: Option<bool>,
+    pub bytecode_optimize_levels: Option<Vec<BytecodeOptimizationLevel>>,
+}
+
+/// A library known to be provided by the operating system of matching targets.
+#[derive(Clone, Debug)]
+struct KnownSystemLibrary {
+    /// Pattern of target triples the library is provided on.
+    target_triple: Regex,
+
+    /// Pattern of library names.
+    library: Regex,
+}
+
+/// A `PythonPackagingPolicyOverlay` registered for a target triple pattern.
+#[derive(Clone, Debug)]
+struct TargetOverlay {
+    /// Pattern of target triples the overlay applies to.
+    target_triple: Regex,
+
+    overlay: PythonPackagingPolicyOverlay,
+}
+
+/// A `PythonPackagingPolicyOverlay` registered for a Python version constraint.
+#[derive(Clone, Debug)]
+struct PythonVersionOverlay {
+    /// Python versions the overlay applies to.
+    python_version: PythonVersionConstraint,
+
+    overlay: PythonPackagingPolicyOverlay,
+}
+
+/// A single-valued setting of a `PythonPackagingPolicy`.
+///
+/// Used to track which settings were explicitly set, so `merge()` only
+/// overrides settings the merged policy actually defines.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum PolicySetting {
+    ExtensionModuleFilter,
+    ResourcesPolicy,
+    ExtensionModulesPolicy,
+    IncludeDistributionSources,
+    IncludeDistributionResources,
+    IncludeDistributionMetadata,
+    IncludeTest,
+    BytecodeOptimizeLevels,
+    BytecodeOnly,
+    DeduplicateResources,
+    ResourcesCompression,
+    ExcludeTypeStubs,
+    InstalledResourceAttributes,
+    ExtractEggFiles,
+    HonorPathExtensions,
+    PathExtensionImportsAction,
+    EditableInstallsAsSourcePaths,
+    AllowedLicenses,
+    AllowedDynamicLibraries,
+    MaxResourceSize,
+    MaxTotalResourcesSize,
+    ResourceSizeBudgetAction,
+}
+
+/// Defines how Python resources should be packaged.
+#[derive(Clone, Debug)]
+pub struct PythonPackagingPolicy {
+    /// Which extension modules should be included.
+    extension_module_filter: ExtensionModuleFilter,
+
+    /// Preferred variants of extension modules.
+    preferred_extension_module_variants: HashMap<String, String>,
+
+    /// Where resources should be packaged by default.
+    resources_policy: PythonResourcesPolicy,
+
+    /// Overrides of `resources_policy` for specific packages.
+    ///
+    /// Keys are package names. The entry for the most specific package
+    /// containing a resource is used.
+    package_resources_policies: HashMap<String, PythonResourcesPolicy>,
+
+    /// Where extension modules should be packaged.
+    ///
+    /// `None` means extension modules follow `resources_policy`. Overrides
+    /// in `package_resources_policies` take precedence.
+    extension_modules_policy: Option<PythonResourcesPolicy>,
+
+    /// Whether to include source module from the Python distribution.
+    include_distribution_sources: bool,
+
+    /// Whether to include package resource files.
+    include_distribution_resources: bool,
+
+    /// Overrides of `include_distribution_resources` for specific packages.
+    ///
+    /// Keys are package names. The entry for the most specific package
+    /// containing a resource is used.
+    package_include_distribution_resources: HashMap<String, bool>,
+
+    /// Whether to include package distribution metadata.
+    include_distribution_metadata: bool,
+
+    /// Whether to include test files.
+    include_test: bool,
+
+    /// Optimization levels of bytecode to generate for Python modules.
+    bytecode_optimize_levels: Vec<BytecodeOptimizationLevel>,
+
+    /// Overrides of `bytecode_optimize_levels` for specific packages.
+    ///
+    /// Keys are package names. The entry for the most specific package
+    /// containing a module is used.
+    package_bytecode_optimize_levels: HashMap<String, Vec<BytecodeOptimizationLevel>>,
+
+    /// Whether to package Python modules as bytecode only.
+    bytecode_only: bool,
+
+    /// Packages whose module source is retained when `bytecode_only` is set.
+    bytecode_only_source_packages: BTreeSet<String>,
+
+    /// Packages whose docstrings and comments are stripped.
+    strip_docstrings_packages: BTreeSet<String>,
+
+    /// Whether to store identical resource data only once in packed resources.
+    deduplicate_resources: bool,
+
+    /// How to compress large resource data in packed resources, if at all.
+    resources_compression: Option<BlobCompression>,
+
+    /// Whether to exclude type stubs and typing markers.
+    exclude_type_stubs: bool,
+
+    /// Packages whose type stubs are retained when `exclude_type_stubs` is set.
+    type_stubs_packages: BTreeSet<String>,
+
+    /// Filesystem attributes of resources installed next to the binary.
+    installed_resource_attributes: InstalledResourceAttributes,
+
+    /// Whether to extract resources from .egg files found when scanning for resources.
+    extract_egg_files: bool,
+
+    /// Whether to scan paths added by .pth files found when scanning for resources.
+    honor_path_extensions: bool,
+
+    /// How to react to .pth file lines executing code.
+    path_extension_imports_action: PathExtensionImportsAction,
+
+    /// Whether editable installs are referenced by their source tree paths.
+    editable_installs_as_source_paths: bool,
+
+    /// Patterns of resource names that should be included.
+    ///
+    /// If non-empty, resources whose names don't match any pattern are
+    /// rejected.
+    include_patterns: Vec<Regex>,
+
+    /// Patterns of resource names that should be excluded.
+    exclude_patterns: Vec<Regex>,
+
+    /// SPDX license identifiers allowed by `ExtensionModuleFilter::NoGPL`.
+    ///
+    /// Defaults to `NON_GPL_LICENSES`.
+    allowed_licenses: BTreeSet<String>,
+
+    /// SPDX license identifiers that are never allowed.
+    ///
+    /// Takes precedence over `allowed_licenses`.
+    denied_licenses: BTreeSet<String>,
+
+    /// Names of libraries extension modules may dynamically link against.
+    ///
+    /// `None` means all libraries are allowed.
+    allowed_dynamic_libraries: Option<BTreeSet<String>>,
+
+    /// Maximum size in bytes of an individual resource.
+    max_resource_size: Option<u64>,
+
+    /// Maximum size in bytes of all resources combined.
+    max_total_resources_size: Option<u64>,
+
+    /// What to do when a resource size budget is exceeded.
+    resource_size_budget_action: ResourceSizeBudgetAction,
+
+    /// Custom functions consulted by `filter_python_resource()`.
+    filter_callbacks: Vec<FilterCallback>,
+
+    /// Classifiers assigning tags to resources.
+    resource_classifiers: Vec<Classifier>,
+
+    /// Resources having any of these tags are excluded.
+    excluded_resource_tags: BTreeSet<ResourceTag>,
+
+    /// Extensions that don't work for a given target triple and Python version.
+    ///
+    /// Policy constructors can populate this with known broken extensions to
+    /// prevent the policy from allowing an extension.
+    broken_extensions: Vec<BrokenExtension>,
+
+    /// Libraries provided by the operating system for a given target triple.
+    ///
+    /// Extension modules only linking against these libraries don't need
+    /// license annotations to pass `ExtensionModuleFilter::NoGPL`.
+    known_system_libraries: Vec<KnownSystemLibrary>,
+
+    /// Overrides of settings applied when building for matching target triples.
+    target_overlays: Vec<TargetOverlay>,
+
+    /// Overrides of settings applied when building with matching Python versions.
+    python_version_overlays: Vec<PythonVersionOverlay>,
+
+    /// Single-valued settings explicitly set on this policy.
+    ///
+    /// Settings not in this set hold their default value and are not
+    /// copied by `merge()`.
+    explicit_settings: BTreeSet<PolicySetting>,
+
+    /// Resources excluded by this policy and why.
+    exclusions: ExclusionLog,
+}
+
+impl Default for PythonPackagingPolicy {
+    fn default() -> Self {
+        PythonPackagingPolicy {
+            extension_module_filter: ExtensionModuleFilter::All,
+            preferred_extension_module_variants: HashMap::new(),
+            resources_policy: PythonResourcesPolicy::InMemoryOnly,
+            package_resources_policies: HashMap::new(),
+            extension_modules_policy: None,
+            include_distribution_sources: true,
+            include_distribution_resources: false,
+            package_include_distribution_resources: HashMap::new(),
+            include_distribution_metadata: false,
+            include_test: false,
+            bytecode_optimize_levels: vec![BytecodeOptimizationLevel::Zero],
+            package_bytecode_optimize_levels: HashMap::new(),
+            bytecode_only: false,
+            bytecode_only_source_packages: BTreeSet::new(),
+            strip_docstrings_packages: BTreeSet::new(),
+            deduplicate_resources: false,
+            resources_compression: None,
+            exclude_type_stubs: false,
+            type_stubs_packages: BTreeSet::new(),
+            installed_resource_attributes: InstalledResourceAttributes::default(),
+            extract_egg_files: false,
+            honor_path_extensions: false,
+            path_extension_imports_action: PathExtensionImportsAction::Warn,
+            editable_installs_as_source_paths: false,
+            include_patterns: vec![],
+            exclude_patterns: vec![],
+            allowed_licenses: NON_GPL_LICENSES.iter().map(|x| x.to_string()).collect(),
+            denied_licenses: BTreeSet::new(),
+            allowed_dynamic_libraries: None,
+            max_resource_size: None,
+            max_total_resources_size: None,
+            resource_size_budget_action: ResourceSizeBudgetAction::Warn,
+            filter_callbacks: vec![],
+            resource_classifiers: vec![],
+            excluded_resource_tags: BTreeSet::new(),
+            broken_extensions: vec![],
+            known_system_libraries: vec![],
+            target_overlays: vec![],
+            python_version_overlays: vec![],
+            explicit_settings: BTreeSet::new(),
+            exclusions: ExclusionLog::default(),
+        }
+    }
+}
+
+impl PythonPackagingPolicy {
+    /// Construct a policy optimized for producing small binaries.
+    ///
+    /// Only minimally required extension modules are included. Module source,
+    /// package resources, and tests from the distribution are excluded and
+    /// bytecode is generated with optimization level 2, which strips
+    /// docstrings and assertions. Identical resource data is deduplicated,
+    /// large resource data is compressed and type stubs are excluded.
+    pub fn preset_minimal_size() -> Self {
+        let mut policy = Self::default();
+        policy.set_extension_module_filter(ExtensionModuleFilter::Minimal);
+        policy.set_resources_policy(PythonResourcesPolicy::InMemoryOnly);
+        policy.set_include_distribution_sources(false);
+        policy.set_include_distribution_resources(false);
+        policy.set_include_test(false);
+        policy.set_bytecode_optimize_levels(&[BytecodeOptimizationLevel::Two]);
+        policy.set_deduplicate_resources(true);
+        policy.set_resources_compression(Some(BlobCompression::default()));
+        policy.set_exclude_type_stubs(true);
+
+        policy
+    }
+
+    /// Construct a policy maximizing compatibility with Python code.
+    ///
+    /// All extension modules, module source, and package resources are
+    /// included. Resources that cannot be loaded from memory fall back to
+    /// being installed in a `lib` directory next to the binary.
+    pub fn preset_maximum_compat() -> Self {
+        let mut policy = Self::default();
+        policy.set_extension_module_filter(ExtensionModuleFilter::All);
+        policy.set_resources_policy(
+            PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative("lib".to_string()),
+        );
+        policy.set_include_distribution_sources(true);
+        policy.set_include_distribution_resources(true);
+        policy.set_include_test(false);
+        policy.set_bytecode_optimize_levels(&[BytecodeOptimizationLevel::Zero]);
+
+        policy
+    }
+
+    /// Construct a policy minimizing what can be inspected or tampered with.
+    ///
+    /// Only minimally required extension modules are included, limiting
+    /// native code. Resources are only loaded from memory, so nothing is read
+    /// from the filesystem at run-time. Modules are packaged as bytecode only,
+    /// so module source is excluded, as are tests. Bytecode is not optimized
+    /// so `assert` statements are preserved.
+    pub fn preset_security_hardened() -> Self {
+        let mut policy = Self::default();
+        policy.set_extension_module_filter(ExtensionModuleFilter::Minimal);
+        policy.set_resources_policy(PythonResourcesPolicy::InMemoryOnly);
+        policy.set_include_distribution_sources(false);
+        policy.set_include_distribution_resources(false);
+        policy.set_include_test(false);
+        policy.set_bytecode_optimize_levels(&[BytecodeOptimizationLevel::Zero]);
+        policy.set_bytecode_only(true);
+
+        policy
+    }
+
+    /// Merge another policy into this one.
+    ///
+    /// This allows layering policies, e.g. applying per-project tweaks on
+    /// top of a base organizational policy. Settings from `other` take
+    /// precedence:
+    ///
+    /// * Single-valued settings (extension module filter, resources and
+    ///   extension modules policies, include flags, bytecode settings,
+    ///   resource deduplication and compression, installed resource
+    ///   attributes, egg and path extension handling, allowed licenses and
+    ///   dynamic libraries, size budgets and the size budget action) are
+    ///   replaced by the value from `other` if `other` explicitly set them,
+    ///   e.g. by calling a setter or a preset constructor. Settings `other`
+    ///   left at their default are retained.
+    /// * Keyed settings (preferred extension module variants and per-package
+    ///   resources policies, resource inclusion, and optimization levels) are
+    ///   unioned. Entries from `other` replace entries for the same key.
+    /// * List and set settings (include and exclude patterns, denied
+    ///   licenses, filter callbacks, resource classifiers and excluded tags,
+    ///   broken extensions, known system libraries, and bytecode only source,
+    ///   docstring stripping and type stubs packages) are appended.
+    /// * Target and Python version overlays of `other` are appended. As
+    ///   overlays are applied in registration order, they take precedence
+    ///   over overlays of this policy matching the same target or version.
+    pub fn merge(&mut self, other: &PythonPackagingPolicy) {
+        let is_set = |setting| other.explicit_settings.contains(&setting);
+
+        if is_set(PolicySetting::ExtensionModuleFilter) {
+            self.extension_module_filter = other.extension_module_filter.clone();
+        }
+        self.preferred_extension_module_variants.extend(
+            other
+                .preferred_extension_module_variants
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+        if is_set(PolicySetting::ResourcesPolicy) {
+            self.resources_policy = other.resources_policy.clone();
+        }
+        self.package_resources_policies.extend(
+            other
+                .package_resources_policies
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+        if is_set(PolicySetting::ExtensionModulesPolicy) {
+            self.extension_modules_policy = other.extension_modules_policy.clone();
+        }
+        if is_set(PolicySetting::IncludeDistributionSources) {
+            self.include_distribution_sources = other.include_distribution_sources;
+        }
+        if is_set(PolicySetting::IncludeDistributionResources) {
+            self.include_distribution_resources = other.include_distribution_resources;
+        }
+        if is_set(PolicySetting::IncludeDistributionMetadata) {
+            self.include_distribution_metadata = other.include_distribution_metadata;
+        }
+        self.package_include_distribution_resources.extend(
+            other
+                .package_include_distribution_resources
+                .iter()
+                .map(|(k, v)| (k.clone(), *v)),
+        );
+        if is_set(PolicySetting::IncludeTest) {
+            self.include_test = other.include_test;
+        }
+        if is_set(PolicySetting::BytecodeOptimizeLevels) {
+            self.bytecode_optimize_levels = other.bytecode_optimize_levels.clone();
+        }
+        self.package_bytecode_optimize_levels.extend(
+            other
+                .package_bytecode_optimize_levels
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+        if is_set(PolicySetting::BytecodeOnly) {
+            self.bytecode_only = other.bytecode_only;
+        }
+        self.bytecode_only_source_packages
+            .extend(other.bytecode_only_source_packages.iter().cloned());
+        self.strip_docstrings_packages
+            .extend(other.strip_docstrings_packages.iter().cloned());
+        if is_set(PolicySetting::DeduplicateResources) {
+            self.deduplicate_resources = other.deduplicate_resources;
+        }
+        if is_set(PolicySetting::ResourcesCompression) {
+            self.resources_compression = other.resources_compression;
+        }
+        if is_set(PolicySetting::ExcludeTypeStubs) {
+            self.exclude_type_stubs = other.exclude_type_stubs;
+        }
+        self.type_stubs_packages
+            .extend(other.type_stubs_packages.iter().cloned());
+        if is_set(PolicySetting::InstalledResourceAttributes) {
+            self.installed_resource_attributes = other.installed_resource_attributes.clone();
+        }
+        if is_set(PolicySetting::ExtractEggFiles) {
+            self.extract_egg_files = other.extract_egg_files;
+        }
+        if is_set(PolicySetting::HonorPathExtensions) {
+            self.honor_path_extensions = other.honor_path_extensions;
+        }
+        if is_set(PolicySetting::PathExtensionImportsAction) {
+            self.path_extension_imports_action = other.path_extension_imports_action;
+        }
+        if is_set(PolicySetting::EditableInstallsAsSourcePaths) {
+            self.editable_installs_as_source_paths = other.editable_installs_as_source_paths;
+        }
+        self.include_patterns
+            .extend(other.include_patterns.iter().cloned());
+        self.exclude_patterns
+            .extend(other.exclude_patterns.iter().cloned());
+        if is_set(PolicySetting::AllowedLicenses) {
+            self.allowed_licenses = other.allowed_licenses.clone();
+        }
+        self.denied_licenses
+            .extend(other.denied_licenses.iter().cloned());
+        if is_set(PolicySetting::AllowedDynamicLibraries) {
+            self.allowed_dynamic_libraries = other.allowed_dynamic_libraries.clone();
+        }
+        if is_set(PolicySetting::MaxResourceSize) {
+            self.max_resource_size = other.max_resource_size;
+        }
+        if is_set(PolicySetting::MaxTotalResourcesSize) {
+            self.max_total_resources_size = other.max_total_resources_size;
+        }
+        if is_set(PolicySetting::ResourceSizeBudgetAction) {
+            self.resource_size_budget_action = other.resource_size_budget_action;
+        }
+        self.filter_callbacks
+            .extend(other.filter_callbacks.iter().cloned());
+        self.resource_classifiers
+            .extend(other.resource_classifiers.iter().cloned());
+        self.excluded_resource_tags
+            .extend(other.excluded_resource_tags.iter().cloned());
+        self.broken_extensions
+            .extend(other.broken_extensions.iter().cloned());
+        self.known_system_libraries
+            .extend(other.known_system_libraries.iter().cloned());
+        self.target_overlays
+            .extend(other.target_overlays.iter().cloned());
+        self.python_version_overlays
+            .extend(other.python_version_overlays.iter().cloned());
+        self.explicit_settings
+            .extend(other.explicit_settings.iter().cloned());
+    }
+
+    /// Obtain the active extension module filter for this instance.
+    pub fn get_extension_module_filter(&self) -> &ExtensionModuleFilter {
+        &self.extension_module_filter
+    }
+
+    /// Set the extension module filter to use.
+    pub fn set_extension_module_filter(&mut self, filter: ExtensionModuleFilter) {
+        self.extension_module_filter = filter;
+        self.explicit_settings
+            .insert(PolicySetting::ExtensionModuleFilter);
+    }
+
+    /// Denote the preferred variant for an extension module.
+    ///
+    /// If set, the named variant will be chosen if it is present.
+    pub fn set_preferred_extension_module_variant(&mut self, extension: &str, variant: &str) {
+        self.preferred_extension_module_variants
+            .insert(extension.to_string(), variant.to_string());
+    }
+
+    /// Obtain the active resources policy for this instance.
+    pub fn get_resources_policy(&self) -> &PythonResourcesPolicy {
+        &self.resources_policy
+    }
+
+    /// Set the resource loading policy.
+    pub fn set_resources_policy(&mut self, policy: PythonResourcesPolicy) {
+        self.resources_policy = policy;
+        self.explicit_settings
+            .insert(PolicySetting::ResourcesPolicy);
+    }
+
+    /// Set the resource loading policy for a specific package.
+    ///
+    /// The policy applies to the named package and all its sub-packages and
+    /// overrides the default resources policy for them. If multiple
+    /// registered packages contain a resource, the most specific one wins.
+    pub fn set_package_resources_policy(&mut self, package: &str, policy: PythonResourcesPolicy) {
+        self.package_resources_policies
+            .insert(package.to_string(), policy);
+    }
+
+    /// Obtain an iterator over per-package resources policy overrides.
+    pub fn iter_package_resources_policies(
+        &self,
+    ) -> impl Iterator<Item = (&String, &PythonResourcesPolicy)> {
+        self.package_resources_policies.iter()
+    }
+
+    /// Obtain the resources policy applying to a named resource.
+    ///
+    /// `name` is the fully qualified name of a module or package. Returns
+    /// the override for the most specific containing package, if one is
+    /// registered, or the default resources policy otherwise.
+    pub fn get_resources_policy_for_name(&self, name: &str) -> &PythonResourcesPolicy {
+        find_package_entry(&self.package_resources_policies, name).unwrap_or(&self.resources_policy)
+    }
+
+    /// Obtain the policy for where extension modules should be packaged, if set.
+    pub fn get_extension_modules_policy(&self) -> Option<&PythonResourcesPolicy> {
+        self.extension_modules_policy.as_ref()
+    }
+
+    /// Set where extension modules should be packaged.
+    ///
+    /// `None` means extension modules follow the resources policy. This
+    /// allows e.g. installing extension modules next to the binary while
+    /// loading other resources from memory.
+    pub fn set_extension_modules_policy(&mut self, policy: Option<PythonResourcesPolicy>) {
+        self.extension_modules_policy = policy;
+        self.explicit_settings
+            .insert(PolicySetting::ExtensionModulesPolicy);
+    }
+
+    /// Obtain the resources policy applying to a named extension module.
+    ///
+    /// Returns the override for the most specific containing package, if
+    /// one is registered, the extension modules policy, if set, or the
+    /// default resources policy otherwise.
+    pub fn get_extension_module_resources_policy(&self, name: &str) -> &PythonResourcesPolicy {
+        find_package_entry(&self.package_resources_policies, name)
+            .or_else(|| self.extension_modules_policy.as_ref())
+            .unwrap_or(&self.resources_policy)
+    }
+
+    /// Set whether we should include a Python distribution's module source code.
+    pub fn set_include_distribution_sources(&mut self, include: bool) {
+        self.include_distribution_sources = include;
+        self.explicit_settings
+            .insert(PolicySetting::IncludeDistributionSources);
+    }
+
+    /// Set whether to include package resources from the Python distribution.
+    pub fn set_include_distribution_resources(&mut self, include: bool) {
+        self.include_distribution_resources = include;
+        self.explicit_settings
+            .insert(PolicySetting::IncludeDistributionResources);
+    }
+
+    /// Set whether to include package resources for a specific package.
+    ///
+    /// The setting applies to the named package and all its sub-packages and
+    /// overrides `set_include_distribution_resources()` for them. If multiple
+    /// registered packages contain a resource, the most specific one wins.
+    pub fn set_include_distribution_resources_for(&mut self, package: &str, include: bool) {
+        self.package_include_distribution_resources
+            .insert(package.to_string(), include);
+    }
+
+    /// Whether package resources in the named package are included.
+    pub fn get_include_distribution_resources_for_name(&self, name: &str) -> bool {
+        *find_package_entry(&self.package_include_distribution_resources, name)
+            .unwrap_or(&self.include_distribution_resources)
+    }
+
+    /// Whether package distribution metadata is included.
+    pub fn get_include_distribution_metadata(&self) -> bool {
+        self.include_distribution_metadata
+    }
+
+    /// Set whether to include package distribution metadata.
+    ///
+    /// Distribution metadata are the files in `.dist-info` and `.egg-info`
+    /// directories, such as `METADATA`, `RECORD`, and `entry_points.txt`.
+    /// `importlib.metadata` APIs like `version()` need them. Excluded by
+    /// default: `filter_python_resource()` only accepts distribution
+    /// resources if this is enabled.
+    pub fn set_include_distribution_metadata(&mut self, include: bool) {
+        self.include_distribution_metadata = include;
+        self.explicit_settings
+            .insert(PolicySetting::IncludeDistributionMetadata);
+    }
+
+    /// Set whether we should include Python modules that define tests.
+    pub fn set_include_test(&mut self, include: bool) {
+        self.include_test = include;
+        self.explicit_settings.insert(PolicySetting::IncludeTest);
+    }
+
+    /// Obtain the default optimization levels of bytecode to generate.
+    pub fn get_bytecode_optimize_levels(&self) -> &[BytecodeOptimizationLevel] {
+        &self.bytecode_optimize_levels
+    }
+
+    /// Set the default optimization levels of bytecode to generate.
+    ///
+    /// A bytecode module is generated for each level. Duplicate levels are
+    /// ignored.
+    pub fn set_bytecode_optimize_levels(&mut self, levels: &[BytecodeOptimizationLevel]) {
+        self.bytecode_optimize_levels = dedup_optimize_levels(levels);
+        self.explicit_settings
+            .insert(PolicySetting::BytecodeOptimizeLevels);
+    }
+
+    /// Set the optimization levels of bytecode to generate for a specific package.
+    ///
+    /// The levels apply to the named package and all its sub-packages and
+    /// override the default levels for them. If multiple registered packages
+    /// contain a module, the most specific one wins.
+    pub fn set_package_bytecode_optimize_levels(
+        &mut self,
+        package: &str,
+        levels: &[BytecodeOptimizationLevel],
+    ) {
+        self.package_bytecode_optimize_levels
+            .insert(package.to_string(), dedup_optimize_levels(levels));
+    }
+
+    /// Obtain the optimization levels of bytecode to generate for a named module.
+    pub fn get_bytecode_optimize_levels_for_name(
+        &self,
+        name: &str,
+    ) -> &[BytecodeOptimizationLevel] {
+        find_package_entry(&self.package_bytecode_optimize_levels, name)
+            .unwrap_or(&self.bytecode_optimize_levels)
+    }
+
+    /// Set whether Python modules should be packaged as bytecode only.
+    ///
+    /// When set, module source is not packaged. Builders compile module
+    /// source to bytecode instead. Use `add_bytecode_only_source_package()`
+    /// to retain source for packages that need it, e.g. packages reading
+    /// their own source via `__file__`.
+    pub fn set_bytecode_only(&mut self, bytecode_only: bool) {
+        self.bytecode_only = bytecode_only;
+        self.explicit_settings.insert(PolicySetting::BytecodeOnly);
+    }
+
+    /// Retain module source for a package and its sub-packages in bytecode only mode.
+    pub fn add_bytecode_only_source_package(&mut self, package: &str) {
+        self.bytecode_only_source_packages
+            .insert(package.to_string());
+    }
+
+    /// Whether source for the named module may be packaged.
+    pub fn is_module_source_allowed(&self, name: &str) -> bool {
+        !self.bytecode_only
+            || self.bytecode_only_source_packages.contains(name)
+            || packages_from_module_name(name)
+                .iter()
+                .any(|package| self.bytecode_only_source_packages.contains(package))
+    }
+
+    /// Strip docstrings and comments from a package and its sub-packages.
+    ///
+    /// Packaged module source has docstrings and comments removed and
+    /// bytecode is compiled from the stripped source as if by `python -OO`,
+    /// which also removes `assert` statements. This makes `__doc__`
+    /// attributes `None`, so it breaks code relying on them.
+    pub fn add_strip_docstrings_package(&mut self, package: &str) {
+        self.strip_docstrings_packages.insert(package.to_string());
+    }
+
+    /// Obtain packages whose docstrings and comments are stripped.
+    pub fn iter_strip_docstrings_packages(&self) -> impl Iterator<Item = &String> {
+        self.strip_docstrings_packages.iter()
+    }
+
+    /// Whether docstrings and comments are stripped from the named module.
+    pub fn get_strip_docstrings_for_name(&self, name: &str) -> bool {
+        self.strip_docstrings_packages.contains(name)
+            || packages_from_module_name(name)
+                .iter()
+                .any(|package| self.strip_docstrings_packages.contains(package))
+    }
+
+    /// Whether identical resource data is stored only once in packed resources.
+    pub fn get_deduplicate_resources(&self) -> bool {
+        self.deduplicate_resources
+    }
+
+    /// Set whether identical resource data is stored only once in packed resources.
+    ///
+    /// Multiple packages often ship identical data files, such as license
+    /// texts or vendored certificate bundles. When enabled, the packed
+    /// resources data stores a single copy of such data and every resource
+    /// holding it refers to that copy.
+    pub fn set_deduplicate_resources(&mut self, deduplicate: bool) {
+        self.deduplicate_resources = deduplicate;
+        self.explicit_settings
+            .insert(PolicySetting::DeduplicateResources);
+    }
+
+    /// How large resource data is compressed in packed resources, if at all.
+    pub fn get_resources_compression(&self) -> Option<&BlobCompression> {
+        self.resources_compression.as_ref()
+    }
+
+    /// Set how large resource data is compressed in packed resources.
+    ///
+    /// When set, module source and the data of package and distribution
+    /// resources stored in memory are compressed if doing so makes them
+    /// smaller. This produces smaller binaries. But the data is decompressed
+    /// each time a resource is accessed, which slows access and requires
+    /// memory for the decompressed data. Loading the packed resources is
+    /// unaffected. Bytecode is never compressed. `None` disables compression.
+    pub fn set_resources_compression(&mut self, compression: Option<BlobCompression>) {
+        self.resources_compression = compression;
+        self.explicit_settings
+            .insert(PolicySetting::ResourcesCompression);
+    }
+
+    /// Whether type stubs and typing markers are excluded.
+    pub fn get_exclude_type_stubs(&self) -> bool {
+        self.exclude_type_stubs
+    }
+
+    /// Set whether to exclude type stubs and typing markers.
+    ///
+    /// `.pyi` files, `py.typed` markers, and resources in PEP 561 stub-only
+    /// packages (`<package>-stubs`) have no value at run-time unless the
+    /// application introspects type information. Use
+    /// `add_type_stubs_package()` to retain them for specific packages.
+    pub fn set_exclude_type_stubs(&mut self, exclude: bool) {
+        self.exclude_type_stubs = exclude;
+        self.explicit_settings
+            .insert(PolicySetting::ExcludeTypeStubs);
+    }
+
+    /// Retain type stubs for a package and its sub-packages when type stubs are excluded.
+    pub fn add_type_stubs_package(&mut self, package: &str) {
+        self.type_stubs_packages.insert(package.to_string());
+    }
+
+    /// Whether type stubs in the named package may be packaged.
+    pub fn is_type_stubs_allowed(&self, package: &str) -> bool {
+        !self.exclude_type_stubs
+            || self.type_stubs_packages.contains(package)
+            || packages_from_module_name(package)
+                .iter()
+                .any(|parent| self.type_stubs_packages.contains(parent))
+    }
+
+    /// Obtain the filesystem attributes of resources installed next to the binary.
+    pub fn get_installed_resource_attributes(&self) -> &InstalledResourceAttributes {
+        &self.installed_resource_attributes
+    }
+
+    /// Set the filesystem attributes of resources installed next to the binary.
+    pub fn set_installed_resource_attributes(&mut self, attributes: InstalledResourceAttributes) {
+        self.installed_resource_attributes = attributes;
+        self.explicit_settings
+            .insert(PolicySetting::InstalledResourceAttributes);
+    }
+
+    /// Whether resources are extracted from .egg files found when scanning for resources.
+    pub fn get_extract_egg_files(&self) -> bool {
+        self.extract_egg_files
+    }
+
+    /// Set whether to extract resources from .egg files found when scanning for resources.
+    ///
+    /// .egg files are zip archives. `filter_python_resource()` always rejects
+    /// them because they can't be loaded by our importer. When enabled, the
+    /// modules and resources inside are packaged instead, as if the egg had
+    /// been installed unzipped. Scanning fails if an egg isn't a valid zip
+    /// archive.
+    pub fn set_extract_egg_files(&mut self, extract: bool) {
+        self.extract_egg_files = extract;
+        self.explicit_settings
+            .insert(PolicySetting::ExtractEggFiles);
+    }
+
+    /// Whether paths added by .pth files are scanned for resources.
+    pub fn get_honor_path_extensions(&self) -> bool {
+        self.honor_path_extensions
+    }
+
+    /// Set whether to scan paths added by .pth files found when scanning for resources.
+    ///
+    /// `filter_python_resource()` always rejects .pth files because our
+    /// importer doesn't process them. When enabled, each directory listed
+    /// in a .pth file is scanned as well, so resources made importable by
+    /// the .pth file are packaged. Lines executing code (`import ...`) can't
+    /// be evaluated at build time and are handled according to
+    /// `set_path_extension_imports_action()`. .egg files listed in a
+    /// .pth file require `set_extract_egg_files(true)`; scanning fails
+    /// otherwise.
+    pub fn set_honor_path_extensions(&mut self, honor: bool) {
+        self.honor_path_extensions = honor;
+        self.explicit_settings
+            .insert(PolicySetting::HonorPathExtensions);
+    }
+
+    /// How .pth file lines executing code are reacted to.
+    pub fn get_path_extension_imports_action(&self) -> PathExtensionImportsAction {
+        self.path_extension_imports_action
+    }
+
+    /// Set how to react to .pth file lines executing code.
+    ///
+    /// Only relevant when path extensions are honored. Defaults to warning.
+    pub fn set_path_extension_imports_action(&mut self, action: PathExtensionImportsAction) {
+        self.path_extension_imports_action = action;
+        self.explicit_settings
+            .insert(PolicySetting::PathExtensionImportsAction);
+    }
+
+    /// Whether editable installs are referenced by their source tree paths.
+    pub fn get_editable_installs_as_source_paths(&self) -> bool {
+        self.editable_installs_as_source_paths
+    }
+
+    /// Set whether editable installs are referenced by their source tree paths.
+    ///
+    /// Distributions installed via `setup.py develop` or as PEP 660 editable
+    /// installs are normally packaged by reading their source tree. When
+    /// enabled, they are instead emitted as `PythonResource::EditableInstall`
+    /// resources, which add the source tree to `sys.path` of the built
+    /// binary. Edits to the source tree are then picked up without
+    /// rebuilding. The binary depends on the source tree existing at run
+    /// time, so this is only suitable for development builds.
+    pub fn set_editable_installs_as_source_paths(&mut self, enabled: bool) {
+        self.editable_installs_as_source_paths = enabled;
+        self.explicit_settings
+            .insert(PolicySetting::EditableInstallsAsSourcePaths);
+    }
+
+    /// Add a pattern of resource names to include.
+    ///
+    /// Once an include pattern is registered, only resources whose fully
+    /// qualified module or package name matches an include pattern pass
+    /// `filter_python_resource()`. Package resources are matched on their
+    /// package and relative name joined by a `.`, e.g. `foo.data/bar.txt`.
+    ///
+    /// Patterns are globs (e.g. `foo.*`) unless prefixed with `re:`, in which
+    /// case they are regular expressions.
+    pub fn add_include_pattern(&mut self, pattern: &str) -> Result<()> {
+        self.include_patterns
+            .push(resource_name_pattern_to_regex(pattern)?);
+
+        Ok(())
+    }
+
+    /// Add a pattern of resource names to exclude.
+    ///
+    /// Resources whose fully qualified module or package name matches an
+    /// exclude pattern are rejected by `filter_python_resource()`. Exclude
+    /// patterns take precedence over include patterns.
+    ///
+    /// Patterns use the same syntax as `add_include_pattern()`.
+    pub fn add_exclude_pattern(&mut self, pattern: &str) -> Result<()> {
+        self.exclude_patterns
+            .push(resource_name_pattern_to_regex(pattern)?);
+
+        Ok(())
+    }
+
+    /// Whether a resource name is allowed by registered include and exclude patterns.
+    fn filter_resource_name(&self, name: &str) -> bool {
+        if self.exclude_patterns.iter().any(|re| re.is_match(name)) {
+            false
+        } else if self.include_patterns.is_empty() {
+            true
+        } else {
+            self.include_patterns.iter().any(|re| re.is_match(name))
+        }
+    }
+
+    /// Obtain the SPDX license identifiers allowed by the `NoGPL` extension module filter.
+    pub fn get_allowed_licenses(&self) -> &BTreeSet<String> {
+        &self.allowed_licenses
+    }
+
+    /// Replace the set of SPDX license identifiers allowed by the `NoGPL` extension module filter.
+    pub fn set_allowed_licenses(&mut self, licenses: &[&str]) {
+        self.allowed_licenses = licenses.iter().map(|x| x.to_string()).collect();
+        self.explicit_settings
+            .insert(PolicySetting::AllowedLicenses);
+    }
+
+    /// Allow an additional SPDX license identifier in the `NoGPL` extension module filter.
+    pub fn add_allowed_license(&mut self, license: &str) {
+        self.allowed_licenses.insert(license.to_string());
+        self.explicit_settings
+            .insert(PolicySetting::AllowedLicenses);
+    }
+
+    /// Deny an SPDX license identifier.
+    ///
+    /// Denied licenses are rejected even if they are also allowed.
+    pub fn add_denied_license(&mut self, license: &str) {
+        self.denied_licenses.insert(license.to_string());
+    }
+
+    /// Whether a single SPDX license identifier is allowed by this policy.
+    pub fn is_license_allowed(&self, license: &str) -> bool {
+        !self.denied_licenses.contains(license) && self.allowed_licenses.contains(license)
+    }
+
+    /// Whether an SPDX license expression is satisfied by this policy.
+    ///
+    /// Expressions like `MIT OR GPL-2.0-only` are evaluated so that any
+    /// allowed choice satisfies the expression. A license with an exception
+    /// (e.g. `Apache-2.0 WITH LLVM-exception`) is allowed if either the full
+    /// expression or the base license is allowed. Values that can't be parsed
+    /// as expressions are compared verbatim.
+    pub fn is_license_expression_allowed(&self, expression: &str) -> bool {
+        match LicenseExpression::parse(expression) {
+            Ok(parsed) => parsed.evaluate(&|license: &str| self.is_license_allowed(license)),
+            Err(_) => self.is_license_allowed(expression),
+        }
+    }
+
+    /// Whether the licensing of an extension module is allowed by this policy.
+    ///
+    /// Extension modules without library dependencies or in the public domain
+    /// are always allowed. Otherwise every license expression annotated on the
+    /// extension module must be satisfied. Extension modules lacking license
+    /// annotations are rejected unless they only link against libraries
+    /// registered as known system libraries for `target_triple`.
+    #[allow(clippy::if_same_then_else)]
+    pub fn is_extension_module_license_allowed(
+        &self,
+        em: &PythonExtensionModule,
+        target_triple: &str,
+    ) -> bool {
+        if em.link_libraries.is_empty() {
+            true
+        // Public domain is always allowed.
+        } else if em.license_public_domain == Some(true) {
+            true
+        // Use explicit license list if one is defined.
+        } else if let Some(ref licenses) = em.licenses {
+            // We filter through an allow list because it is safer. (No new GPL
+            // licenses can slip through.)
+            licenses
+                .iter()
+                .all(|license| self.is_license_expression_allowed(license))
+        // Libraries provided by the operating system aren't distributed with
+        // the binary.
+        } else if em
+            .link_libraries
+            .iter()
+            .all(|library| self.is_known_system_library(target_triple, &library.name))
+        {
+            true
+        } else {
+            // In lack of evidence that it isn't GPL, assume GPL.
+            false
+        }
+    }
+
+    /// Obtain the libraries extension modules may dynamically link against.
+    ///
+    /// `None` means all libraries are allowed.
+    pub fn get_allowed_dynamic_libraries(&self) -> Option<&BTreeSet<String>> {
+        self.allowed_dynamic_libraries.as_ref()
+    }
+
+    /// Set the libraries extension modules may dynamically link against.
+    ///
+    /// Names are library names as passed to the linker, e.g. `m` for `libm`.
+    /// Extension modules requiring a library not in this list are not added.
+    /// `None` allows all libraries.
+    pub fn set_allowed_dynamic_libraries(&mut self, libraries: Option<&[&str]>) {
+        self.allowed_dynamic_libraries =
+            libraries.map(|libraries| libraries.iter().map(|x| x.to_string()).collect());
+        self.explicit_settings
+            .insert(PolicySetting::AllowedDynamicLibraries);
+    }
+
+    /// Allow extension modules to dynamically link against a library.
+    ///
+    /// If no allowlist is defined, this defines one containing only this library.
+    pub fn add_allowed_dynamic_library(&mut self, library: &str) {
+        self.allowed_dynamic_libraries
+            .get_or_insert_with(BTreeSet::new)
+            .insert(library.to_string());
+        self.explicit_settings
+            .insert(PolicySetting::AllowedDynamicLibraries);
+    }
+
+    /// Obtain libraries an extension module dynamically links against that aren't allowed.
+    ///
+    /// Libraries without a static library version are considered to be
+    /// dynamically linked. Returns an empty list if no allowlist is defined.
+    pub fn disallowed_dynamic_libraries(&self, em: &PythonExtensionModule) -> Vec<String> {
+        if let Some(allowed) = &self.allowed_dynamic_libraries {
+            em.link_libraries
+                .iter()
+                .filter(|library| {
+                    library.static_library.is_none() && !allowed.contains(&library.name)
+                })
+                .map(|library| library.name.clone())
+                .collect()
+        } else {
+            vec![]
+        }
+    }
+
+    /// Set the maximum size in bytes of an individual resource.
+    pub fn set_max_resource_size(&mut self, size: Option<u64>) {
+        self.max_resource_size = size;
+        self.explicit_settings
+            .insert(PolicySetting::MaxResourceSize);
+    }
+
+    /// Set the maximum size in bytes of all resources combined.
+    pub fn set_max_total_resources_size(&mut self, size: Option<u64>) {
+        self.max_total_resources_size = size;
+        self.explicit_settings
+            .insert(PolicySetting::MaxTotalResourcesSize);
+    }
+
+    /// Obtain the action to take when a resource size budget is exceeded.
+    pub fn get_resource_size_budget_action(&self) -> ResourceSizeBudgetAction {
+        self.resource_size_budget_action
+    }
+
+    /// Set the action to take when a resource size budget is exceeded.
+    pub fn set_resource_size_budget_action(&mut self, action: ResourceSizeBudgetAction) {
+        self.resource_size_budget_action = action;
+        self.explicit_settings
+            .insert(PolicySetting::ResourceSizeBudgetAction);
+    }
+
+    /// Evaluate resource sizes against the size budgets of this policy.
+    ///
+    /// `sizes` maps resource names to their sizes. `total_size` is the size of
+    /// all resources combined, which may include overhead not attributed to
+    /// individual resources.
+    pub fn check_resource_sizes(
+        &self,
+        sizes: &BTreeMap<String, u64>,
+        total_size: u64,
+    ) -> ResourceSizeReport {
+        let oversized_resources = if let Some(max) = self.max_resource_size {
+            sizes
+                .iter()
+                .filter(|(_, size)| **size > max)
+                .map(|(name, size)| (name.clone(), *size))
+                .collect()
+        } else {
+            vec![]
+        };
+
+        ResourceSizeReport {
+            oversized_resources,
+            max_resource_size: self.max_resource_size,
+            total_size,
+            max_total_size: self.max_total_resources_size,
+        }
+    }
+
+    /// Register a custom function to filter resources.
+    ///
+    /// Callbacks are consulted by `filter_python_resource()` in registration
+    /// order before any built-in rules. The first callback returning
+    /// `FilterDecision::Include` or `FilterDecision::Exclude` determines the
+    /// outcome. If all callbacks return `FilterDecision::Defer`, the policy's
+    /// built-in rules apply.
+    pub fn register_filter_callback(&mut self, callback: Box<ResourceFilterCallback>) {
+        self.filter_callbacks
+            .push(FilterCallback(Arc::from(callback)));
+    }
+
+    /// Register a classifier assigning tags to resources.
+    ///
+    /// Tags from all registered classifiers are combined. Resources having a
+    /// tag registered with `add_excluded_resource_tag()` are rejected by
+    /// `filter_python_resource()`. Resources tagged `ResourceTag::Test` are
+    /// treated like resources flagged as tests.
+    pub fn register_resource_classifier(&mut self, classifier: Box<dyn ResourceClassifier>) {
+        self.resource_classifiers
+            .push(Classifier(Arc::from(classifier)));
+    }
+
+    /// Exclude resources having a tag.
+    pub fn add_excluded_resource_tag(&mut self, tag: ResourceTag) {
+        self.excluded_resource_tags.insert(tag);
+    }
+
+    /// Obtain the tags applying to a resource.
+    ///
+    /// Resources flagged as tests are tagged `ResourceTag::Test`. Other tags
+    /// come from registered classifiers.
+    pub fn classify_python_resource(&self, resource: &PythonResource) -> BTreeSet<ResourceTag> {
+        let is_test = match resource {
+            PythonResource::ModuleSource(module) => module.is_test,
+            PythonResource::ModuleBytecodeRequest(module) => module.is_test,
+            PythonResource::ModuleBytecode(module) => module.is_test,
+            PythonResource::Resource(resource) => resource.is_test,
+            _ => false,
+        };
+
+        let mut tags = BTreeSet::new();
+
+        if is_test {
+            tags.insert(ResourceTag::Test);
+        }
+
+        for classifier in &self.resource_classifiers {
+            tags.extend(classifier.0.classify(resource));
+        }
+
+        tags
+    }
+
+    /// Mark an extension as broken on a target platform, preventing it from being used.
+    ///
+    /// `target_triple` and `extension` are patterns as accepted by
+    /// `add_include_pattern()`, so e.g. `*-pc-windows-*` and `_ssl*` are
+    /// allowed. `python_version` is an optional version constraint like
+    /// `>=3.8` restricting which Python versions the extension is broken on.
+    pub fn register_broken_extension(
+        &mut self,
+        target_triple: &str,
+        extension: &str,
+        python_version: Option<&str>,
+    ) -> Result<()> {
+        let python_version = match python_version {
+            Some(constraint) => Some(PythonVersionConstraint::try_from(constraint)?),
+            None => None,
+        };
+
+        self.broken_extensions.push(BrokenExtension {
+            patterns: (target_triple.to_string(), extension.to_string()),
+            target_triple: resource_name_pattern_to_regex(target_triple)?,
+            extension: resource_name_pattern_to_regex(extension)?,
+            python_version,
+        });
+
+        Ok(())
+    }
+
+    /// Register a library as provided by the operating system of matching targets.
+    ///
+    /// `target_triple` and `library` are patterns as accepted by
+    /// `add_include_pattern()`. Libraries are named as passed to the linker,
+    /// e.g. `kernel32` or `CoreFoundation`. Extension modules lacking license
+    /// annotations are normally rejected by `ExtensionModuleFilter::NoGPL`.
+    /// Those only linking against known system libraries are allowed instead.
+    pub fn register_known_system_library(
+        &mut self,
+        target_triple: &str,
+        library: &str,
+    ) -> Result<()> {
+        self.known_system_libraries.push(KnownSystemLibrary {
+            target_triple: resource_name_pattern_to_regex(target_triple)?,
+            library: resource_name_pattern_to_regex(library)?,
+        });
+
+        Ok(())
+    }
+
+    /// Whether a library is registered as provided by the operating system of a target.
+    pub fn is_known_system_library(&self, target_triple: &str, library: &str) -> bool {
+        self.known_system_libraries.iter().any(|known| {
+            known.target_triple.is_match(target_triple) && known.library.is_match(library)
+        })
+    }
+
+    /// Register settings overrides to apply when building for matching target triples.
+    ///
+    /// `target_triple` is a pattern as accepted by `add_include_pattern()`,
+    /// e.g. `*-pc-windows-*`. Overlays are applied by
+    /// `resolve_target_overlays()` in the order they were registered, so later
+    /// overlays take precedence over earlier ones.
+    pub fn add_target_overlay(
+        &mut self,
+        target_triple: &str,
+        overlay: PythonPackagingPolicyOverlay,
+    ) -> Result<()> {
+        self.target_overlays.push(TargetOverlay {
+            target_triple: resource_name_pattern_to_regex(target_triple)?,
+            overlay,
+        });
+
+        Ok(())
+    }
+
+    /// Obtain the policy to use when building for a target triple.
+    ///
+    /// Returns a copy of this policy with all overlays matching `target_triple`
+    /// applied. The returned policy has no target overlays and records
+    /// exclusions in a new `filter_report()`.
+    pub fn resolve_target_overlays(&self, target_triple: &str) -> Self {
+        let mut policy = self.clone();
+        policy.target_overlays.clear();
+        policy.exclusions = ExclusionLog::default();
+
+        for entry in &self.target_overlays {
+            if entry.target_triple.is_match(target_triple) {
+                policy.apply_overlay(&entry.overlay);
+            }
+        }
+
+        policy
+    }
+
+    /// Register settings overrides to apply when building with matching Python versions.
+    ///
+    /// `python_version` is a version constraint like `>=3.8` or `3.7`.
+    /// Overlays are applied by `resolve_python_version_overlays()` in the
+    /// order they were registered, so later overlays take precedence over
+    /// earlier ones.
+    ///
+    /// This allows a single policy to be shared by executables built with
+    /// different Python versions.
+    pub fn add_python_version_overlay(
+        &mut self,
+        python_version: &str,
+        overlay: PythonPackagingPolicyOverlay,
+    ) -> Result<()> {
+        self.python_version_overlays.push(PythonVersionOverlay {
+            python_version: PythonVersionConstraint::try_from(python_version)?,
+            overlay,
+        });
+
+        Ok(())
+    }
+
+    /// Obtain the policy to use when building with a Python version.
+    ///
+    /// Returns a copy of this policy with all overlays matching `python_version`
+    /// applied. The returned policy has no Python version overlays and records
+    /// exclusions in a new `filter_report()`.
+    pub fn resolve_python_version_overlays(&self, python_version: &str) -> Self {
+        let mut policy = self.clone();
+        policy.python_version_overlays.clear();
+        policy.exclusions = ExclusionLog::default();
+
+        for entry in &self.python_version_overlays {
+            if entry.python_version.matches(python_version) {
+                policy.apply_overlay(&entry.overlay);
+            }
+        }
+
+        policy
+    }
+
+    /// Apply the settings defined by an overlay to this instance.
+    fn apply_overlay(&mut self, overlay: &PythonPackagingPolicyOverlay) {
+        if let Some(filter) = &overlay.extension_module_filter {
+            self.set_extension_module_filter(filter.clone());
+        }
+        if let Some(resources_policy) = &overlay.resources_policy {
+            self.set_resources_policy(resources_policy.clone());
+        }
+        if let Some(extension_modules_policy) = &overlay.extension_modules_policy {
+            self.set_extension_modules_policy(Some(extension_modules_policy.clone()));
+        }
+        for (package, policy) in &overlay.package_resources_policies {
+            self.set_package_resources_policy(package, policy.clone());
+        }
+        if let Some(include) = overlay.include_distribution_sources {
+            self.set_include_distribution_sources(include);
+        }
+        if let Some(include) = overlay.include_distribution_resources {
+            self.set_include_distribution_resources(include);
+        }
+        if let Some(include) = overlay.include_test {
+            self.set_include_test(include);
+        }
+        if let Some(levels) = &overlay.bytecode_optimize_levels {
+            self.set_bytecode_optimize_levels(levels);
+        }
+    }
+
+    /// Whether an extension is registered as broken for a target and Python version.
+    pub fn is_extension_broken(
+        &self,
+        target_triple: &str,
+        extension: &str,
+        python_version: &str,
+    ) -> bool {
+        self.broken_extensions
+            .iter()
+            .any(|broken| broken.matches(target_triple, extension, python_version))
+    }
+
+    /// Determine if a Python resource is applicable to the current policy.
+    ///
+    /// Given a `PythonResource`, this answers the question of whether that
+    /// resource meets the inclusion requirements for the current policy.
+    ///
+    /// Returns true if the resource should be included, false otherwise.
+    /// Excluded resources are recorded in `filter_report()`.
+    pub fn filter_python_resource(&self, resource: &PythonResource) -> bool {
+        match self.explain_python_resource(resource) {
+            Some(reason) => {
+                self.record_exclusion(resource.full_name(), resource_type_name(resource), reason);
+                false
+            }
+            None => true,
+        }
+    }
+
+    /// Determine why a Python resource would be excluded by the current policy.
+    ///
+    /// Returns `None` if the resource would be included.
+    pub fn explain_python_resource(&self, resource: &PythonResource) -> Option<ExclusionReason> {
+        for callback in &self.filter_callbacks {
+            match (callback.0)(resource) {
+                FilterDecision::Include => return None,
+                FilterDecision::Exclude => return Some(ExclusionReason::FilterCallback),
+                FilterDecision::Defer => {}
+            }
+        }
+
+        let name = match resource {
+            PythonResource::ModuleSource(module) => Some(Cow::Borrowed(module.name.as_str())),
+            PythonResource::ModuleBytecodeRequest(module) => {
+                Some(Cow::Borrowed(module.name.as_str()))
+            }
+            PythonResource::ModuleBytecode(module) => Some(Cow::Borrowed(module.name.as_str())),
+            PythonResource::Resource(resource) => Some(Cow::Owned(format!(
+                "{}.{}",
+                resource.leaf_package, resource.relative_name
+            ))),
+            PythonResource::DistributionResource(resource) => {
+                Some(Cow::Borrowed(resource.package.as_str()))
+            }
+            PythonResource::ExtensionModuleDynamicLibrary(em) => {
+                Some(Cow::Borrowed(em.name.as_str()))
+            }
+            PythonResource::ExtensionModuleStaticallyLinked(em) => {
+                Some(Cow::Borrowed(em.name.as_str()))
+            }
+            PythonResource::PathExtension(_) => None,
+            PythonResource::EggFile(_) => None,
+            PythonResource::EntryPoint(_) => None,
+            PythonResource::DataFile(_) => None,
+            PythonResource::EditableInstall(_) => None,
+        };
+
+        if let Some(name) = name {
+            if !self.filter_resource_name(&name) {
+                return Some(ExclusionReason::ResourceNamePattern);
+            }
+        }
+
+        if self.resource_size_budget_action == ResourceSizeBudgetAction::Exclude {
+            if let Some(max) = self.max_resource_size {
+                // Resources whose size can't be determined will fail later when
+                // their data is resolved. So let them through.
+                if resource.data_size().unwrap_or(0) > max {
+                    return Some(ExclusionReason::SizeBudget);
+                }
+            }
+        }
+
+        let tags = self.classify_python_resource(resource);
+
+        if let Some(tag) = tags
+            .iter()
+            .find(|tag| self.excluded_resource_tags.contains(tag))
+        {
+            return Some(ExclusionReason::ResourceTag(tag.clone()));
+        }
+
+        if let PythonResource::Resource(package_resource) = resource {
+            if is_type_stubs_resource(resource)
+                && !self.is_type_stubs_allowed(&package_resource.leaf_package)
+            {
+                return Some(ExclusionReason::ResourceTag(ResourceTag::TypeStubs));
+            }
+        }
+
+        let is_excluded_test = !self.include_test && tags.contains(&ResourceTag::Test);
+
+        match resource {
+            PythonResource::ModuleSource(module) => {
+                if !self.is_module_source_allowed(&module.name) {
+                    Some(ExclusionReason::BytecodeOnly)
+                } else if is_excluded_test {
+                    Some(ExclusionReason::Test)
+                } else if !self.include_distribution_sources {
+                    Some(ExclusionReason::DistributionSources)
+                } else {
+                    None
+                }
+            }
+            PythonResource::ModuleBytecodeRequest(_) => {
+                if is_excluded_test {
+                    Some(ExclusionReason::Test)
+                } else {
+                    None
+                }
+            }
+            PythonResource::Resource(resource) => {
+                if !self.get_include_distribution_resources_for_name(&resource.leaf_package) {
+                    Some(ExclusionReason::DistributionResources)
+                } else if is_excluded_test {
+                    Some(ExclusionReason::Test)
+                } else {
+                    None
+                }
+            }
+            PythonResource::DistributionResource(_) => {
+                if !self.include_distribution_metadata {
+                    Some(ExclusionReason::DistributionMetadata)
+                } else {
+                    None
+                }
+            }
+            PythonResource::ModuleBytecode(_)
+            | PythonResource::ExtensionModuleDynamicLibrary(_)
+            | PythonResource::ExtensionModuleStaticallyLinked(_)
+            | PythonResource::PathExtension(_)
+            | PythonResource::EggFile(_)
+            | PythonResource::EntryPoint(_)
+            | PythonResource::DataFile(_)
+            | PythonResource::EditableInstall(_) => Some(ExclusionReason::UnsupportedResourceType),
+        }
+    }
+
+    fn record_exclusion(&self, name: String, resource_type: &str, reason: ExclusionReason) {
+        self.exclusions
+            .0
+            .lock()
+            .unwrap()
+            .insert((name, resource_type.to_string()), reason);
+    }
+
+    /// Obtain resources excluded by this policy and why.
+    ///
+    /// Resources excluded by `filter_python_resource()` and
+    /// `resolve_python_extension_modules()` are recorded. Clones of a policy
+    /// start with a copy of the record and don't share later entries. Entries
+    /// are sorted by resource name.
+    pub fn filter_report(&self) -> Vec<ExcludedResource> {
+        self.exclusions
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|((name, resource_type), reason)| ExcludedResource {
+                name: name.clone(),
+                resource_type: resource_type.clone(),
+                reason: reason.clone(),
+            })
+            .collect()
+    }
+
+    /// Validate the policy against a build, reporting conflicting settings.
+    ///
+    /// Problems that would otherwise surface as failures late in the build
+    /// are reported as `DiagnosticSeverity::Error`. Settings that have no
+    /// effect are reported as `DiagnosticSeverity::Warning`.
+    pub fn validate(&self, context: &PolicyValidationContext) -> Result<Vec<PolicyDiagnostic>> {
+        let mut res = vec![];
+
+        let mut warn = |message: String| {
+            res.push(PolicyDiagnostic {
+                severity: DiagnosticSeverity::Warning,
+                message,
+            })
+        };
+
+        for broken in &self.broken_extensions {
+            if !broken.target_triple.is_match(context.target_triple) {
+                warn(format!(
+                    "broken extension entry {} for target {} does not apply to target {}",
+                    broken.patterns.1, broken.patterns.0, context.target_triple
+                ));
+            }
+        }
+
+        let available = context
+            .extension_modules
+            .iter()
+            .map(|variants| variants.default_variant().name.clone())
+            .collect::<BTreeSet<_>>();
+
+        if let ExtensionModuleFilter::Explicit(names) = &self.extension_module_filter {
+            for name in names {
+                if !available.contains(name) {
+                    warn(format!(
+                        "extension module {} is not provided by the Python distribution",
+                        name
+                    ));
+                } else if self.is_extension_broken(
+                    context.target_triple,
+                    name,
+                    context.python_version,
+                ) {
+                    warn(format!(
+                        "extension module {} is known to be broken on {} and will not be added",
+                        name, context.target_triple
+                    ));
+                }
+            }
+        }
+
+        for name in self.preferred_extension_module_variants.keys() {
+            if !available.contains(name) {
+                warn(format!(
+                    "preferred variant for extension module {} has no effect because it is not provided by the Python distribution",
+                    name
+                ));
+            }
+        }
+
+        if self.resource_size_budget_action == ResourceSizeBudgetAction::Exclude
+            && self.max_resource_size.is_none()
+        {
+            warn(
+                "resource size budget action is exclude but no maximum resource size is set"
+                    .to_string(),
+            );
+        }
+
+        for em in self.resolve_python_extension_modules(
+            context.extension_modules.iter().cloned(),
+            context.target_triple,
+            context.python_version,
+        )? {
+            let disallowed = self.disallowed_dynamic_libraries(&em);
+
+            if !disallowed.is_empty() {
+                res.push(PolicyDiagnostic {
+                    severity: DiagnosticSeverity::Error,
+                    message: format!(
+                        "required extension module {} links against dynamic libraries that are not allowed: {}",
+                        em.name,
+                        disallowed.join(", ")
+                    ),
+                });
+            }
+
+            let can_link_builtin = em.builtin_default || !em.object_file_data.is_empty();
+            let can_load_memory = can_link_builtin
+                || (em.shared_library.is_some() && context.in_memory_shared_library_loading);
+
+            if self.get_extension_module_resources_policy(&em.name)
+                == &PythonResourcesPolicy::InMemoryOnly
+                && !can_load_memory
+            {
+                res.push(PolicyDiagnostic {
+                    severity: DiagnosticSeverity::Error,
+                    message: format!(
+                        "extension module {} cannot be loaded from memory on {} but the resources policy is in-memory-only",
+                        em.name, context.target_triple
+                    ),
+                });
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Resolve Python extension modules that are compliant with the policy.
+    #[allow(clippy::if_same_then_else)]
+    pub fn resolve_python_extension_modules<'a>(
+        &self,
+        extensions_variants: impl Iterator<Item = &'a PythonExtensionModuleVariants>,
+        target_triple: &str,
+        python_version: &str,
+    ) -> Result<Vec<PythonExtensionModule>> {
+        let mut res = vec![];
+
+        for variants in extensions_variants {
+            let variants_default = variants.default_variant();
+            let name = &variants_default.name;
+
+            // This extension is broken on this target. Ignore it.
+            if self.is_extension_broken(target_triple, name, python_version) {
+                self.record_exclusion(
+                    name.clone(),
+                    "extension module",
+                    ExclusionReason::BrokenOnTarget(target_triple.to_string()),
+                );
+                continue;
+            }
+
+            // Ignore variants requiring dynamic libraries that aren't allowed.
+            // Minimally required extensions are kept because things don't work
+            // without them.
+            let variants = &PythonExtensionModuleVariants::from_iter(
+                variants
+                    .iter()
+                    .filter(|em| {
+                        em.is_minimally_required()
+                            || self.disallowed_dynamic_libraries(em).is_empty()
+                    })
+                    .cloned(),
+            );
+
+            if variants.is_empty() {
+                self.record_exclusion(
+                    name.clone(),
+                    "extension module",
+                    ExclusionReason::DynamicLibraries(
+                        self.disallowed_dynamic_libraries(variants_default),
+                    ),
+                );
+                continue;
+            }
+
+            let resolved_count = res.len();
+
+            // Always add minimally required extension modules, because things don't
+            // work if we don't do this.
+            let ext_variants =
+                PythonExtensionModuleVariants::from_iter(variants.iter().filter_map(|em| {
+                    if em.is_minimally_required() {
+                        Some(em.clone())
+                    } else {
+                        None
+                    }
+                }));
+
+            if !ext_variants.is_empty() {
+                res.push(
+                    ext_variants
+                        .choose_variant(&self.preferred_extension_module_variants)
+                        .clone(),
+                );
+            }
+
+            match self.extension_module_filter {
+                // Nothing to do here since we added minimal extensions above.
+                ExtensionModuleFilter::Minimal => {}
+
+                ExtensionModuleFilter::All => {
+                    res.push(
+                        variants
+                            .choose_variant(&self.preferred_extension_module_variants)
+                            .clone(),
+                    );
+                }
+
+                ExtensionModuleFilter::NoLibraries => {
+                    let ext_variants = PythonExtensionModuleVariants::from_iter(
+                        variants.iter().filter_map(|em| {
+                            if !em.requires_libraries() {
+                                Some(em.clone())
+                            } else {
+                                None
+                            }
+                        }),
+                    );
+
+                    if !ext_variants.is_empty() {
+                        res.push(
+                            ext_variants
+                                .choose_variant(&self.preferred_extension_module_variants)
+                                .clone(),
+                        );
+                    }
+                }
+
+                ExtensionModuleFilter::NoGPL => {
+                    let ext_variants = PythonExtensionModuleVariants::from_iter(
+                        variants.iter().filter_map(|em| {
+                            if self.is_extension_module_license_allowed(em, target_triple) {
+                                Some(em.clone())
+                            } else {
+                                None
+                            }
+                        }),
+                    );
+
+                    if !ext_variants.is_empty() {
+                        res.push(
+                            ext_variants
+                                .choose_variant(&self.preferred_extension_module_variants)
+                                .clone(),
+                        );
+                    }
+                }
+
+                ExtensionModuleFilter::Explicit(ref names) => {
+                    // Minimally required extensions were added above.
+                    if ext_variants.is_empty() && names.contains(name) {
+                        res.push(
+                            variants
+                                .choose_variant(&self.preferred_extension_module_variants)
+                                .clone(),
+                        );
+                    }
+                }
+            }
+
+            if res.len() == resolved_count {
+                self.record_exclusion(
+                    name.clone(),
+                    "extension module",
+                    match &self.extension_module_filter {
+                        ExtensionModuleFilter::NoGPL => ExclusionReason::License,
+                        filter => ExclusionReason::ExtensionModuleFilter(filter.clone()),
+                    },
+                );
+            }
+        }
+
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::resource::{
+            DataLocation, LibraryDependency, PythonModuleBytecodeFromSource, PythonModuleSource,
+            PythonPackageDistributionResource, PythonPackageDistributionResourceFlavor,
+            PythonPackageResource,
+        },
+    };
+
+    fn extension_module(name: &str, licenses: Option<Vec<&str>>) -> PythonExtensionModule {
+        PythonExtensionModule {
+            name: name.to_string(),
+            init_fn: None,
+            extension_file_suffix: ".so".to_string(),
+            shared_library: None,
+            object_file_data: vec![],
+            is_package: false,
+            link_libraries: vec![LibraryDependency {
+                name: "lib".to_string(),
+                static_library: None,
+                dynamic_library: None,
+                framework: false,
+                system: false,
+            }],
+            is_stdlib: true,
+            builtin_default: false,
+            required: false,
+            variant: None,
+            licenses: licenses.map(|l| l.iter().map(|x| x.to_string()).collect()),
+            license_texts: None,
+            license_public_domain: None,
+            provenance: None,
+        }
+    }
+
+    fn source_module(name: &str) -> PythonResource {
+        PythonModuleSource {
+            name: name.to_string(),
+            source: DataLocation::Memory(vec![]),
+            is_package: false,
+            cache_tag: "cpython-38".to_string(),
+            is_stdlib: false,
+            is_test: false,
+            provenance: None,
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_package_resources_policy() {
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_package_resources_policy(
+            "numpy",
+            PythonResourcesPolicy::FilesystemRelativeOnly("lib".to_string()),
+        );
+        policy.set_package_resources_policy("numpy.core", PythonResourcesPolicy::InMemoryOnly);
+
+        assert_eq!(
+            policy.get_resources_policy_for_name("foo"),
+            &PythonResourcesPolicy::InMemoryOnly
+        );
+        assert_eq!(
+            policy.get_resources_policy_for_name("numpy"),
+            &PythonResourcesPolicy::FilesystemRelativeOnly("lib".to_string())
+        );
+        assert_eq!(
+            policy.get_resources_policy_for_name("numpy.linalg"),
+            &PythonResourcesPolicy::FilesystemRelativeOnly("lib".to_string())
+        );
+        assert_eq!(
+            policy.get_resources_policy_for_name("numpy.core.multiarray"),
+            &PythonResourcesPolicy::InMemoryOnly
+        );
+        assert_eq!(
+            policy.get_resources_policy_for_name("numpyx"),
+            &PythonResourcesPolicy::InMemoryOnly
+        );
+    }
+
+    #[test]
+    fn test_resource_name_patterns() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        policy.add_exclude_pattern("*.tests.*")?;
+        policy.add_exclude_pattern("docutils.writers.odf_odt")?;
+
+        assert!(policy.filter_python_resource(&source_module("foo")));
+        assert!(policy.filter_python_resource(&source_module("foo.tests")));
+        assert!(!policy.filter_python_resource(&source_module("foo.tests.test_bar")));
+        assert!(!policy.filter_python_resource(&source_module("docutils.writers.odf_odt")));
+        assert!(policy.filter_python_resource(&source_module("docutils.writers.odf_odtx")));
+
+        policy.add_include_pattern("foo")?;
+        policy.add_include_pattern("re:^foo\\.[a-z]+$")?;
+
+        assert!(policy.filter_python_resource(&source_module("foo")));
+        assert!(policy.filter_python_resource(&source_module("foo.bar")));
+        assert!(!policy.filter_python_resource(&source_module("foo.bar2")));
+        assert!(!policy.filter_python_resource(&source_module("bar")));
+
+        policy.set_include_distribution_resources(true);
+        let package_resource = |relative_name: &str| -> PythonResource {
+            PythonPackageResource {
+                leaf_package: "foo".to_string(),
+                relative_name: relative_name.to_string(),
+                data: DataLocation::Memory(vec![]),
+                is_stdlib: false,
+                is_test: false,
+                provenance: None,
+            }
+            .into()
+        };
+
+        // Package resources are matched on their fully qualified name, not
+        // just the name of their package.
+        assert!(policy.filter_python_resource(&package_resource("data")));
+        assert!(!policy.filter_python_resource(&package_resource("data.txt")));
+
+        policy.add_include_pattern("foo.*.txt")?;
+        policy.add_exclude_pattern("foo.secret.*")?;
+        assert!(policy.filter_python_resource(&package_resource("data.txt")));
+        assert!(!policy.filter_python_resource(&package_resource("secret.txt")));
+        assert!(policy.filter_python_resource(&source_module("foo")));
+
+        assert!(policy.add_exclude_pattern("re:(").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_package_include_distribution_resources() {
+        let resource = |package: &str| -> PythonResource {
+            PythonPackageResource {
+                leaf_package: package.to_string(),
+                relative_name: "data.txt".to_string(),
+                data: DataLocation::Memory(vec![]),
+                is_stdlib: true,
+                is_test: false,
+                provenance: None,
+            }
+            .into()
+        };
+
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_include_distribution_resources_for("lib2to3", true);
+        policy.set_include_distribution_resources_for("lib2to3.tests", false);
+
+        assert!(policy.filter_python_resource(&resource("lib2to3")));
+        assert!(policy.filter_python_resource(&resource("lib2to3.pgen2")));
+        assert!(!policy.filter_python_resource(&resource("lib2to3.tests.data")));
+        assert!(!policy.filter_python_resource(&resource("idlelib")));
+        assert!(!policy.filter_python_resource(&resource("lib2to3x")));
+
+        policy.set_include_distribution_resources(true);
+        policy.set_include_distribution_resources_for("idlelib", false);
+        assert!(policy.filter_python_resource(&resource("json")));
+        assert!(!policy.filter_python_resource(&resource("idlelib.Icons")));
+    }
+
+    #[test]
+    fn test_include_distribution_metadata() {
+        let resource: PythonResource = PythonPackageDistributionResource {
+            location: PythonPackageDistributionResourceFlavor::DistInfo,
+            package: "black".to_string(),
+            version: "1.0".to_string(),
+            name: "METADATA".to_string(),
+            data: DataLocation::Memory(vec![]),
+            provenance: None,
+        }
+        .into();
+
+        let mut policy = PythonPackagingPolicy::default();
+        assert!(!policy.get_include_distribution_metadata());
+        assert!(!policy.filter_python_resource(&resource));
+        assert_eq!(
+            policy.explain_python_resource(&resource),
+            Some(ExclusionReason::DistributionMetadata)
+        );
+
+        policy.set_include_distribution_metadata(true);
+        assert!(policy.filter_python_resource(&resource));
+    }
+
+    #[test]
+    fn test_license_allowlist() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_extension_module_filter(ExtensionModuleFilter::NoGPL);
+
+        let mit = extension_module("mit", Some(vec!["MIT"]));
+        let lgpl = extension_module("lgpl", Some(vec!["LGPL-2.1-only"]));
+        let unknown = extension_module("unknown", None);
+
+        assert!(policy.is_extension_module_license_allowed(&mit, "target"));
+        assert!(!policy.is_extension_module_license_allowed(&lgpl, "target"));
+        assert!(!policy.is_extension_module_license_allowed(&unknown, "target"));
+
+        policy.add_allowed_license("LGPL-2.1-only");
+        assert!(policy.is_extension_module_license_allowed(&lgpl, "target"));
+
+        policy.add_denied_license("LGPL-2.1-only");
+        assert!(!policy.is_extension_module_license_allowed(&lgpl, "target"));
+
+        policy.set_allowed_licenses(&["Apache-2.0"]);
+        assert!(!policy.is_extension_module_license_allowed(&mit, "target"));
+
+        let variants = vec![
+            PythonExtensionModuleVariants::from_iter(vec![mit.clone()]),
+            PythonExtensionModuleVariants::from_iter(vec![extension_module(
+                "apache",
+                Some(vec!["Apache-2.0"]),
+            )]),
+        ];
+
+        let resolved =
+            policy.resolve_python_extension_modules(variants.iter(), "target", "3.8.3")?;
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].name, "apache");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_known_system_libraries() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_extension_module_filter(ExtensionModuleFilter::NoGPL);
+        policy.register_known_system_library("*-apple-darwin", "CoreFoundation")?;
+        policy.register_known_system_library("*-apple-darwin", "System*")?;
+
+        let mut em = extension_module("_scproxy", None);
+        em.link_libraries[0].name = "CoreFoundation".to_string();
+        em.link_libraries[0].framework = true;
+        let mut other = em.link_libraries[0].clone();
+        other.name = "SystemConfiguration".to_string();
+        em.link_libraries.push(other);
+
+        assert!(policy.is_extension_module_license_allowed(&em, "x86_64-apple-darwin"));
+        assert!(!policy.is_extension_module_license_allowed(&em, "x86_64-unknown-linux-gnu"));
+
+        let variants = vec![PythonExtensionModuleVariants::from_iter(vec![em.clone()])];
+        let resolved = policy.resolve_python_extension_modules(
+            variants.iter(),
+            "x86_64-apple-darwin",
+            "3.8",
+        )?;
+        assert_eq!(resolved.len(), 1);
+        let resolved = policy.resolve_python_extension_modules(
+            variants.iter(),
+            "x86_64-unknown-linux-gnu",
+            "3.8",
+        )?;
+        assert!(resolved.is_empty());
+
+        // Any library not known to be a system library requires license evidence.
+        em.link_libraries
+            .push(extension_module("x", None).link_libraries[0].clone());
+        assert!(!policy.is_extension_module_license_allowed(&em, "x86_64-apple-darwin"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_license_expressions() {
+        let mut policy = PythonPackagingPolicy::default();
+
+        assert!(policy.is_license_expression_allowed("MIT OR GPL-2.0-only"));
+        assert!(!policy.is_license_expression_allowed("MIT AND GPL-2.0-only"));
+        assert!(policy.is_license_expression_allowed("(MIT OR Zlib) AND OpenSSL"));
+
+        policy.add_allowed_license("Apache-2.0");
+        assert!(policy.is_license_expression_allowed("Apache-2.0 WITH LLVM-exception"));
+
+        policy.add_denied_license("MIT");
+        assert!(!policy.is_license_expression_allowed("MIT OR GPL-2.0-only"));
+
+        let em = extension_module("em", Some(vec!["MIT OR Zlib", "Apache-2.0"]));
+        assert!(policy.is_extension_module_license_allowed(&em, "target"));
+    }
+
+    #[test]
+    fn test_resource_size_budget() {
+        let mut policy = PythonPackagingPolicy::default();
+
+        let mut sizes = BTreeMap::new();
+        sizes.insert("small".to_string(), 10);
+        sizes.insert("large".to_string(), 1000);
+
+        assert!(!policy.check_resource_sizes(&sizes, 1010).is_violation());
+
+        policy.set_max_resource_size(Some(100));
+        policy.set_max_total_resources_size(Some(2000));
+
+        let report = policy.check_resource_sizes(&sizes, 1010);
+        assert!(report.is_violation());
+        assert!(!report.total_size_exceeded());
+        assert_eq!(
+            report.oversized_resources,
+            vec![("large".to_string(), 1000)]
+        );
+        assert_eq!(
+            report.describe(),
+            vec!["resource large is 1000 bytes; exceeds budget of 100 bytes".to_string()]
+        );
+
+        let report = policy.check_resource_sizes(&BTreeMap::new(), 3000);
+        assert!(report.total_size_exceeded());
+
+        let large = PythonModuleSource {
+            name: "large".to_string(),
+            source: DataLocation::Memory(vec![0; 1000]),
+            is_package: false,
+            cache_tag: "cpython-38".to_string(),
+            is_stdlib: false,
+            is_test: false,
+            provenance: None,
+        };
+
+        assert!(policy.filter_python_resource(&large.clone().into()));
+        policy.set_resource_size_budget_action(ResourceSizeBudgetAction::Exclude);
+        assert!(!policy.filter_python_resource(&large.into()));
+        assert!(policy.filter_python_resource(&source_module("small")));
+    }
+
+    #[test]
+    fn test_filter_callbacks() {
+        let mut policy = PythonPackagingPolicy::default();
+        policy.add_exclude_pattern("excluded").unwrap();
+
+        policy.register_filter_callback(Box::new(|resource: &PythonResource| {
+            if resource.full_name() == "excluded" {
+                FilterDecision::Include
+            } else {
+                FilterDecision::Defer
+            }
+        }));
+        policy.register_filter_callback(Box::new(|resource: &PythonResource| {
+            if resource.full_name().starts_with("foo") {
+                FilterDecision::Exclude
+            } else {
+                FilterDecision::Defer
+            }
+        }));
+
+        assert!(policy.filter_python_resource(&source_module("bar")));
+        assert!(!policy.filter_python_resource(&source_module("foo")));
+        assert!(!policy.filter_python_resource(&source_module("foo.bar")));
+        assert!(policy.filter_python_resource(&source_module("excluded")));
+
+        // Callbacks survive cloning.
+        let cloned = policy.clone();
+        assert!(!cloned.filter_python_resource(&source_module("foo")));
+    }
+
+    #[test]
+    fn test_python_version_constraint() -> Result<()> {
+        let c = PythonVersionConstraint::try_from("3.7")?;
+        assert_eq!(c.operator, VersionOperator::Equal);
+        assert!(c.matches("3.7.7"));
+        assert!(!c.matches("3.8.3"));
+
+        let c = PythonVersionConstraint::try_from(">=3.8")?;
+        assert!(c.matches("3.8.0"));
+        assert!(c.matches("3.9"));
+        assert!(!c.matches("3.7.7"));
+
+        let c = PythonVersionConstraint::try_from("<3.8")?;
+        assert!(c.matches("3.7.7"));
+        assert!(!c.matches("3.8.3"));
+
+        assert!(!PythonVersionConstraint::try_from("!=3.8")?.matches("3.8.3"));
+        assert!(!PythonVersionConstraint::try_from("3.8")?.matches("garbage"));
+
+        assert!(PythonVersionConstraint::try_from(">=").is_err());
+        assert!(PythonVersionConstraint::try_from("3.x").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_target_overlays() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        policy.add_target_overlay(
+            "*-pc-windows-*",
+            PythonPackagingPolicyOverlay {
+                resources_policy: Some(PythonResourcesPolicy::FilesystemRelativeOnly(
+                    "lib".to_string(),
+                )),
+                ..PythonPackagingPolicyOverlay::default()
+            },
+        )?;
+        policy.add_target_overlay(
+            "x86_64-*",
+            PythonPackagingPolicyOverlay {
+                include_test: Some(true),
+                resources_policy: Some(PythonResourcesPolicy::InMemoryOnly),
+                ..PythonPackagingPolicyOverlay::default()
+            },
+        )?;
+
+        let resolved = policy.resolve_target_overlays("i686-pc-windows-msvc");
+        assert_eq!(
+            resolved.get_resources_policy(),
+            &PythonResourcesPolicy::FilesystemRelativeOnly("lib".to_string())
+        );
+        assert!(!resolved.include_test);
+        assert!(resolved.target_overlays.is_empty());
+
+        // Later overlays take precedence.
+        let resolved = policy.resolve_target_overlays("x86_64-pc-windows-msvc");
+        assert_eq!(
+            resolved.get_resources_policy(),
+            &PythonResourcesPolicy::InMemoryOnly
+        );
+        assert!(resolved.include_test);
+
+        let resolved = policy.resolve_target_overlays("aarch64-unknown-linux-gnu");
+        assert_eq!(
+            resolved.get_resources_policy(),
+            &PythonResourcesPolicy::InMemoryOnly
+        );
+        assert!(!resolved.include_test);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_target_overlays_extension_modules() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_package_resources_policy("foo", PythonResourcesPolicy::InMemoryOnly);
+
+        let mut package_resources_policies = BTreeMap::new();
+        package_resources_policies.insert(
+            "bar".to_string(),
+            PythonResourcesPolicy::FilesystemRelativeOnly("bar".to_string()),
+        );
+        policy.add_target_overlay(
+            "*-pc-windows-*",
+            PythonPackagingPolicyOverlay {
+                extension_modules_policy: Some(PythonResourcesPolicy::FilesystemRelativeOnly(
+                    "lib".to_string(),
+                )),
+                package_resources_policies,
+                ..PythonPackagingPolicyOverlay::default()
+            },
+        )?;
+
+        let resolved = policy.resolve_target_overlays("x86_64-pc-windows-msvc");
+        assert_eq!(
+            resolved.get_resources_policy(),
+            &PythonResourcesPolicy::InMemoryOnly
+        );
+        assert_eq!(
+            resolved.get_extension_module_resources_policy("_ssl"),
+            &PythonResourcesPolicy::FilesystemRelativeOnly("lib".to_string())
+        );
+        assert_eq!(
+            resolved.get_resources_policy_for_name("json"),
+            &PythonResourcesPolicy::InMemoryOnly
+        );
+        // Per-package policies take precedence over the extension modules policy.
+        assert_eq!(
+            resolved.get_extension_module_resources_policy("foo._speedups"),
+            &PythonResourcesPolicy::InMemoryOnly
+        );
+        assert_eq!(
+            resolved.get_extension_module_resources_policy("bar._speedups"),
+            &PythonResourcesPolicy::FilesystemRelativeOnly("bar".to_string())
+        );
+        assert_eq!(
+            resolved.get_resources_policy_for_name("bar.data"),
+            &PythonResourcesPolicy::FilesystemRelativeOnly("bar".to_string())
+        );
+
+        let resolved = policy.resolve_target_overlays("x86_64-unknown-linux-gnu");
+        assert_eq!(resolved.get_extension_modules_policy(), None);
+        assert_eq!(
+            resolved.get_extension_module_resources_policy("_ssl"),
+            &PythonResourcesPolicy::InMemoryOnly
+        );
+        assert_eq!(
+            resolved.get_resources_policy_for_name("bar.data"),
+            &PythonResourcesPolicy::InMemoryOnly
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_python_version_overlays() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        policy.add_python_version_overlay(
+            "<3.9",
+            PythonPackagingPolicyOverlay {
+                extension_module_filter: Some(ExtensionModuleFilter::NoGPL),
+                ..PythonPackagingPolicyOverlay::default()
+            },
+        )?;
+        policy.add_python_version_overlay(
+            ">=3.12",
+            PythonPackagingPolicyOverlay {
+                include_test: Some(true),
+                ..PythonPackagingPolicyOverlay::default()
+            },
+        )?;
+
+        assert!(policy
+            .add_python_version_overlay("~3", PythonPackagingPolicyOverlay::default())
+            .is_err());
+
+        let resolved = policy.resolve_python_version_overlays("3.8.5");
+        assert_eq!(
+            resolved.get_extension_module_filter(),
+            &ExtensionModuleFilter::NoGPL
+        );
+        assert!(!resolved.include_test);
+        assert!(resolved.python_version_overlays.is_empty());
+
+        let resolved = policy.resolve_python_version_overlays("3.12.1");
+        assert_eq!(
+            resolved.get_extension_module_filter(),
+            &ExtensionModuleFilter::All
+        );
+        assert!(resolved.include_test);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge() -> Result<()> {
+        let mut base = PythonPackagingPolicy::default();
+        base.set_extension_module_filter(ExtensionModuleFilter::NoGPL);
+        base.set_preferred_extension_module_variant("foo", "a");
+        base.set_preferred_extension_module_variant("bar", "a");
+        base.register_broken_extension("*", "_tkinter", None)?;
+        base.add_exclude_pattern("secret*")?;
+        base.set_max_resource_size(Some(100));
+
+        let mut project = PythonPackagingPolicy::default();
+        project.set_extension_module_filter(ExtensionModuleFilter::Minimal);
+        project.set_preferred_extension_module_variant("foo", "b");
+        project.register_broken_extension("*", "_ssl", None)?;
+        project.add_exclude_pattern("private*")?;
+        project.set_include_test(true);
+        project.set_extract_egg_files(true);
+        project.set_path_extension_imports_action(PathExtensionImportsAction::Error);
+        project.set_editable_installs_as_source_paths(true);
+        project.set_max_total_resources_size(None);
+
+        base.merge(&project);
+
+        assert_eq!(
+            base.get_extension_module_filter(),
+            &ExtensionModuleFilter::Minimal
+        );
+        assert_eq!(
+            base.preferred_extension_module_variants.get("foo"),
+            Some(&"b".to_string())
+        );
+        assert_eq!(
+            base.preferred_extension_module_variants.get("bar"),
+            Some(&"a".to_string())
+        );
+        assert!(base.is_extension_broken("x86_64-unknown-linux-gnu", "_tkinter", "3.8"));
+        assert!(base.is_extension_broken("x86_64-unknown-linux-gnu", "_ssl", "3.8"));
+        assert!(!base.filter_resource_name("secret.foo"));
+        assert!(!base.filter_resource_name("private.foo"));
+        assert!(base.include_test);
+        assert!(base.get_extract_egg_files());
+        assert!(!base.get_honor_path_extensions());
+        assert!(base.get_editable_installs_as_source_paths());
+        assert_eq!(
+            base.get_path_extension_imports_action(),
+            PathExtensionImportsAction::Error
+        );
+        assert_eq!(base.max_resource_size, Some(100));
+        assert_eq!(base.max_total_resources_size, None);
+
+        // Settings left at their default don't override explicit settings.
+        let mut base = PythonPackagingPolicy::preset_minimal_size();
+        let mut project = PythonPackagingPolicy::default();
+        project.set_include_test(true);
+        base.merge(&project);
+
+        assert_eq!(
+            base.get_extension_module_filter(),
+            &ExtensionModuleFilter::Minimal
+        );
+        assert!(!base.include_distribution_sources);
+        assert!(base.deduplicate_resources);
+        assert!(base.resources_compression.is_some());
+        assert!(base.include_test);
+
+        // But explicitly setting a default value does.
+        let mut project = PythonPackagingPolicy::default();
+        project.set_deduplicate_resources(false);
+        project.set_resources_compression(None);
+        base.merge(&project);
+
+        assert!(!base.deduplicate_resources);
+        assert!(base.resources_compression.is_none());
+        assert!(base.include_test);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_broken_extensions() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+
+        policy.register_broken_extension("x86_64-unknown-linux-gnu", "_crypt", None)?;
+        policy.register_broken_extension("*-pc-windows-*", "_ssl*", None)?;
+        policy.register_broken_extension("*-apple-darwin", "_tkinter", Some(">=3.8"))?;
+
+        assert!(policy.is_extension_broken("x86_64-unknown-linux-gnu", "_crypt", "3.8.3"));
+        assert!(!policy.is_extension_broken("i686-unknown-linux-gnu", "_crypt", "3.8.3"));
+        assert!(!policy.is_extension_broken("x86_64-unknown-linux-gnu", "_cryptx", "3.8.3"));
+
+        assert!(policy.is_extension_broken("x86_64-pc-windows-msvc", "_ssl", "3.8.3"));
+        assert!(policy.is_extension_broken("i686-pc-windows-msvc", "_sslx", "3.7.7"));
+        assert!(!policy.is_extension_broken("x86_64-pc-windows-msvc", "_hashlib", "3.8.3"));
+
+        assert!(policy.is_extension_broken("x86_64-apple-darwin", "_tkinter", "3.8.3"));
+        assert!(!policy.is_extension_broken("x86_64-apple-darwin", "_tkinter", "3.7.7"));
+
+        let variants = vec![
+            PythonExtensionModuleVariants::from_iter(vec![extension_module("_ssl", None)]),
+            PythonExtensionModuleVariants::from_iter(vec![extension_module("_json", None)]),
+        ];
+
+        let resolved = policy.resolve_python_extension_modules(
+            variants.iter(),
+            "x86_64-pc-windows-msvc",
+            "3.8.3",
+        )?;
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].name, "_json");
+
+        assert!(policy
+            .register_broken_extension("*", "_ssl", Some("three"))
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_presets() {
+        let policy = PythonPackagingPolicy::preset_minimal_size();
+        assert_eq!(
+            policy.get_extension_module_filter(),
+            &ExtensionModuleFilter::Minimal
+        );
+        assert_eq!(
+            policy.get_bytecode_optimize_levels(),
+            &[BytecodeOptimizationLevel::Two]
+        );
+        assert!(policy.get_deduplicate_resources());
+        assert!(policy.get_resources_compression().is_some());
+        assert!(policy.get_exclude_type_stubs());
+        assert!(!policy.filter_python_resource(&source_module("foo")));
+
+        let policy = PythonPackagingPolicy::preset_maximum_compat();
+        assert_eq!(
+            policy.get_extension_module_filter(),
+            &ExtensionModuleFilter::All
+        );
+        assert_eq!(
+            policy.get_resources_policy(),
+            &PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative("lib".to_string())
+        );
+        assert!(policy.filter_python_resource(&source_module("foo")));
+        assert!(!policy.get_deduplicate_resources());
+        assert!(policy.get_resources_compression().is_none());
+
+        let policy = PythonPackagingPolicy::preset_security_hardened();
+        assert_eq!(
+            policy.get_extension_module_filter(),
+            &ExtensionModuleFilter::Minimal
+        );
+        assert_eq!(
+            policy.get_resources_policy(),
+            &PythonResourcesPolicy::InMemoryOnly
+        );
+        assert_eq!(
+            policy.get_bytecode_optimize_levels(),
+            &[BytecodeOptimizationLevel::Zero]
+        );
+        assert!(!policy.include_distribution_sources);
+        assert!(!policy.include_test);
+        assert!(!policy.is_module_source_allowed("foo"));
+        assert_eq!(
+            policy.explain_python_resource(&source_module("foo")),
+            Some(ExclusionReason::BytecodeOnly)
+        );
+    }
+
+    #[test]
+    fn test_explicit_extension_module_filter() -> Result<()> {
+        assert_eq!(
+            ExtensionModuleFilter::try_from("explicit:_ssl, zlib,").unwrap(),
+            ExtensionModuleFilter::Explicit(vec!["_ssl".to_string(), "zlib".to_string()])
+        );
+        assert_eq!(
+            ExtensionModuleFilter::try_from("explicit:").unwrap(),
+            ExtensionModuleFilter::Explicit(vec![])
+        );
+
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_extension_module_filter(ExtensionModuleFilter::Explicit(vec![
+            "_ssl".to_string(),
+            "missing".to_string(),
+        ]));
+
+        let mut required = extension_module("_io", None);
+        required.required = true;
+
+        let variants = vec![
+            PythonExtensionModuleVariants::from_iter(vec![extension_module("_ssl", None)]),
+            PythonExtensionModuleVariants::from_iter(vec![extension_module("zlib", None)]),
+            PythonExtensionModuleVariants::from_iter(vec![required]),
+        ];
+
+        let resolved =
+            policy.resolve_python_extension_modules(variants.iter(), "target", "3.8.3")?;
+        assert_eq!(
+            resolved
+                .iter()
+                .map(|em| em.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["_ssl", "_io"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_allowed_dynamic_libraries() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+
+        let mut ssl = extension_module("_ssl", None);
+        ssl.link_libraries = vec![
+            LibraryDependency {
+                name: "ssl".to_string(),
+                static_library: None,
+                dynamic_library: None,
+                framework: false,
+                system: true,
+            },
+            LibraryDependency {
+                name: "crypto".to_string(),
+                static_library: Some(DataLocation::Memory(vec![])),
+                dynamic_library: None,
+                framework: false,
+                system: false,
+            },
+        ];
+        let mut math = extension_module("math", None);
+        math.link_libraries[0].name = "m".to_string();
+        let mut required = extension_module("_io", None);
+        required.required = true;
+
+        assert!(policy.disallowed_dynamic_libraries(&ssl).is_empty());
+
+        policy.set_allowed_dynamic_libraries(Some(&["c", "m"]));
+        assert_eq!(
+            policy.disallowed_dynamic_libraries(&ssl),
+            vec!["ssl".to_string()]
+        );
+        assert!(policy.disallowed_dynamic_libraries(&math).is_empty());
+
+        let variants = vec![
+            PythonExtensionModuleVariants::from_iter(vec![ssl]),
+            PythonExtensionModuleVariants::from_iter(vec![math]),
+            PythonExtensionModuleVariants::from_iter(vec![required]),
+        ];
+
+        let resolved = policy
+            .resolve_python_extension_modules(variants.iter(), "target", "3.8.3")?
+            .into_iter()
+            .map(|em| em.name)
+            .collect::<BTreeSet<_>>();
+        assert!(!resolved.contains("_ssl"));
+        assert!(resolved.contains("math"));
+        assert!(resolved.contains("_io"));
+
+        let diagnostics = policy.validate(&PolicyValidationContext {
+            target_triple: "target",
+            python_version: "3.8.3",
+            extension_modules: variants.iter().collect(),
+            in_memory_shared_library_loading: false,
+        })?;
+        assert!(diagnostics
+            .iter()
+            .any(|d| d.severity == DiagnosticSeverity::Error
+                && d.message.contains("_io links against dynamic libraries")));
+
+        policy.add_allowed_dynamic_library("ssl");
+        assert!(policy
+            .disallowed_dynamic_libraries(variants[0].default_variant())
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resource_classifiers() -> Result<()> {
+        let classifier = DefaultResourceClassifier::default();
+
+        let resource = |package: &str, relative_name: &str| -> PythonResource {
+            PythonPackageResource {
+                leaf_package: package.to_string(),
+                relative_name: relative_name.to_string(),
+                data: DataLocation::Memory(vec![]),
+                is_stdlib: false,
+                is_test: false,
+                provenance: None,
+            }
+            .into()
+        };
+
+        assert!(classifier.classify(&source_module("foo.bar")).is_empty());
+        assert_eq!(
+            classifier.classify(&source_module("foo.tests.test_bar")),
+            BTreeSet::from_iter(vec![ResourceTag::Test])
+        );
+        assert_eq!(
+            classifier.classify(&source_module("foo.test_bar")),
+            BTreeSet::from_iter(vec![ResourceTag::Test])
+        );
+        assert_eq!(
+            classifier.classify(&resource("foo", "__init__.pyi")),
+            BTreeSet::from_iter(vec![ResourceTag::TypeStubs])
+        );
+        assert_eq!(
+            classifier.classify(&resource("foo", "py.typed")),
+            BTreeSet::from_iter(vec![ResourceTag::TypeStubs])
+        );
+        assert_eq!(
+            classifier.classify(&resource("foo", "locale/de/LC_MESSAGES/foo.mo")),
+            BTreeSet::from_iter(vec![ResourceTag::Locale])
+        );
+        assert_eq!(
+            classifier.classify(&resource("foo.examples", "README.rst")),
+            BTreeSet::from_iter(vec![ResourceTag::Documentation, ResourceTag::Examples])
+        );
+        assert!(classifier
+            .classify(&resource("foo", "test_data.json"))
+            .is_empty());
+
+        struct VendoredClassifier {}
+
+        impl ResourceClassifier for VendoredClassifier {
+            fn classify(&self, resource: &PythonResource) -> BTreeSet<ResourceTag> {
+                let mut tags = BTreeSet::new();
+                if resource.full_name().contains("_vendor") {
+                    tags.insert(ResourceTag::Custom("vendored".to_string()));
+                }
+                tags
+            }
+        }
+
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_include_distribution_resources(true);
+        policy.register_resource_classifier(Box::new(DefaultResourceClassifier::default()));
+        policy.register_resource_classifier(Box::new(VendoredClassifier {}));
+        policy.add_excluded_resource_tag(ResourceTag::try_from("custom:vendored").unwrap());
+        policy.add_excluded_resource_tag(ResourceTag::Documentation);
+
+        assert!(policy.filter_python_resource(&source_module("foo.bar")));
+        assert_eq!(
+            policy.explain_python_resource(&source_module("foo.tests")),
+            Some(ExclusionReason::Test)
+        );
+        assert_eq!(
+            policy.explain_python_resource(&source_module("foo._vendor.six")),
+            Some(ExclusionReason::ResourceTag(ResourceTag::Custom(
+                "vendored".to_string()
+            )))
+        );
+        assert_eq!(
+            policy.explain_python_resource(&resource("foo", "docs/index.rst")),
+            Some(ExclusionReason::ResourceTag(ResourceTag::Documentation))
+        );
+        assert!(policy.filter_python_resource(&resource("foo", "__init__.pyi")));
+
+        policy.set_include_test(true);
+        assert!(policy.filter_python_resource(&source_module("foo.tests")));
+
+        assert!(ResourceTag::try_from("custom:").is_err());
+        assert!(ResourceTag::try_from("bogus").is_err());
+        assert_eq!(ResourceTag::TypeStubs.to_string(), "type-stubs");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exclude_type_stubs() {
+        let resource = |package: &str, relative_name: &str| -> PythonResource {
+            PythonPackageResource {
+                leaf_package: package.to_string(),
+                relative_name: relative_name.to_string(),
+                data: DataLocation::Memory(vec![]),
+                is_stdlib: false,
+                is_test: false,
+                provenance: None,
+            }
+            .into()
+        };
+
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_include_distribution_resources(true);
+        assert!(policy.filter_python_resource(&resource("foo", "__init__.pyi")));
+
+        policy.set_exclude_type_stubs(true);
+        policy.add_type_stubs_package("introspected");
+
+        assert_eq!(
+            policy.explain_python_resource(&resource("foo", "__init__.pyi")),
+            Some(ExclusionReason::ResourceTag(ResourceTag::TypeStubs))
+        );
+        assert!(!policy.filter_python_resource(&resource("foo", "py.typed")));
+        assert!(!policy.filter_python_resource(&resource("foo.bar", "sub/py.typed")));
+        assert!(!policy.filter_python_resource(&resource("foo-stubs", "data.json")));
+        assert!(policy.filter_python_resource(&resource("foo", "data.json")));
+        assert!(policy.filter_python_resource(&resource("foo", "not_py.typed")));
+        assert!(policy.filter_python_resource(&resource("introspected", "__init__.pyi")));
+        assert!(policy.filter_python_resource(&resource("introspected.sub", "py.typed")));
+        assert!(policy.filter_python_resource(&source_module("foo")));
+    }
+
+    #[test]
+    fn test_filter_report() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        policy.add_exclude_pattern("secret")?;
+        policy.register_broken_extension("target", "_tkinter", None)?;
+        policy.set_extension_module_filter(ExtensionModuleFilter::NoGPL);
+
+        let mut test_module = source_module("foo.tests");
+        if let PythonResource::ModuleSource(module) = &mut test_module {
+            module.is_test = true;
+        }
+
+        assert!(policy.filter_python_resource(&source_module("foo")));
+        assert!(!policy.filter_python_resource(&source_module("secret")));
+        assert!(!policy.filter_python_resource(&test_module));
+        assert_eq!(
+            policy.explain_python_resource(&source_module("secret")),
+            Some(ExclusionReason::ResourceNamePattern)
+        );
+
+        let variants = vec![
+            PythonExtensionModuleVariants::from_iter(vec![extension_module("_tkinter", None)]),
+            PythonExtensionModuleVariants::from_iter(vec![extension_module(
+                "_gdbm",
+                Some(vec!["GPL-3.0"]),
+            )]),
+            PythonExtensionModuleVariants::from_iter(vec![extension_module(
+                "zlib",
+                Some(vec!["Zlib"]),
+            )]),
+        ];
+        policy.resolve_python_extension_modules(variants.iter(), "target", "3.8.3")?;
+
+        let report = policy.filter_report();
+        assert_eq!(
+            report
+                .iter()
+                .map(|e| (e.name.as_str(), e.reason.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("_gdbm", ExclusionReason::License),
+                (
+                    "_tkinter",
+                    ExclusionReason::BrokenOnTarget("target".to_string())
+                ),
+                ("foo.tests", ExclusionReason::Test),
+                ("secret", ExclusionReason::ResourceNamePattern),
+            ]
+        );
+        assert_eq!(report[3].resource_type, "module source");
+        assert_eq!(report[1].reason.to_string(), "known to be broken on target");
+
+        // Clones don't record exclusions in the original's report.
+        let mut cloned = policy.clone();
+        assert_eq!(cloned.filter_report(), report);
+        cloned.add_exclude_pattern("other")?;
+        assert!(!cloned.filter_python_resource(&source_module("other")));
+        assert_eq!(cloned.filter_report().len(), 5);
+        assert_eq!(policy.filter_report().len(), 4);
+
+        // Policies resolved for a target record their own exclusions.
+        let resolved = policy.resolve_target_overlays("target");
+        assert!(resolved.filter_report().is_empty());
+        assert!(!resolved.filter_python_resource(&source_module("secret")));
+        assert_eq!(resolved.filter_report().len(), 1);
+        assert_eq!(policy.filter_report().len(), 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate() -> Result<()> {
+        let mut shared_only = extension_module("shared_only", None);
+        shared_only.link_libraries.clear();
+        shared_only.shared_library = Some(DataLocation::Memory(vec![]));
+        let mut object_files = extension_module("object_files", None);
+        object_files.object_file_data = vec![DataLocation::Memory(vec![])];
+
+        let variants = vec![
+            PythonExtensionModuleVariants::from_iter(vec![shared_only]),
+            PythonExtensionModuleVariants::from_iter(vec![object_files]),
+        ];
+
+        let mut context = PolicyValidationContext {
+            target_triple: "x86_64-pc-windows-msvc",
+            python_version: "3.8.3",
+            extension_modules: variants.iter().collect(),
+            in_memory_shared_library_loading: false,
+        };
+
+        let mut policy = PythonPackagingPolicy::default();
+        assert_eq!(
+            policy.validate(&context)?,
+            vec![PolicyDiagnostic {
+                severity: DiagnosticSeverity::Error,
+                message: "extension module shared_only cannot be loaded from memory on x86_64-pc-windows-msvc but the resources policy is in-memory-only".to_string(),
+            }]
+        );
+
+        context.in_memory_shared_library_loading = true;
+        assert!(policy.validate(&context)?.is_empty());
+
+        policy.register_broken_extension("*-apple-darwin", "_tkinter", None)?;
+        policy.register_broken_extension("*-pc-windows-*", "object_files", None)?;
+        policy.set_extension_module_filter(ExtensionModuleFilter::Explicit(vec![
+            "object_files".to_string(),
+            "missing".to_string(),
+        ]));
+        policy.set_preferred_extension_module_variant("other", "variant");
+        policy.set_resource_size_budget_action(ResourceSizeBudgetAction::Exclude);
+
+        let diagnostics = policy.validate(&context)?;
+        assert!(diagnostics
+            .iter()
+            .all(|d| d.severity == DiagnosticSeverity::Warning));
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "warning: broken extension entry _tkinter for target *-apple-darwin does not apply to target x86_64-pc-windows-msvc",
+                "warning: extension module object_files is known to be broken on x86_64-pc-windows-msvc and will not be added",
+                "warning: extension module missing is not provided by the Python distribution",
+                "warning: preferred variant for extension module other has no effect because it is not provided by the Python distribution",
+                "warning: resource size budget action is exclude but no maximum resource size is set",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bytecode_optimize_levels() {
+        let mut policy = PythonPackagingPolicy::default();
+        assert_eq!(
+            policy.get_bytecode_optimize_levels_for_name("foo"),
+            &[BytecodeOptimizationLevel::Zero]
+        );
+
+        policy.set_package_bytecode_optimize_levels(
+            "foo",
+            &[
+                BytecodeOptimizationLevel::Two,
+                BytecodeOptimizationLevel::One,
+                BytecodeOptimizationLevel::Two,
+            ],
+        );
+        policy.set_package_bytecode_optimize_levels("foo.bar", &[]);
+
+        assert_eq!(
+            policy.get_bytecode_optimize_levels_for_name("foo.baz"),
+            &[
+                BytecodeOptimizationLevel::Two,
+                BytecodeOptimizationLevel::One
+            ]
+        );
+        assert!(policy
+            .get_bytecode_optimize_levels_for_name("foo.bar.baz")
+            .is_empty());
+        assert_eq!(
+            policy.get_bytecode_optimize_levels_for_name("json"),
+            &[BytecodeOptimizationLevel::Zero]
+        );
+    }
+
+    #[test]
+    fn test_bytecode_only() {
+        let mut policy = PythonPackagingPolicy::default();
+        assert!(policy.is_module_source_allowed("foo"));
+
+        policy.set_bytecode_only(true);
+        policy.add_bytecode_only_source_package("needs_source");
+
+        assert!(!policy.is_module_source_allowed("foo"));
+        assert!(policy.is_module_source_allowed("needs_source"));
+        assert!(policy.is_module_source_allowed("needs_source.sub"));
+        assert!(!policy.is_module_source_allowed("needs_source_other"));
+
+        assert!(!policy.filter_python_resource(&source_module("foo")));
+        assert!(policy.filter_python_resource(&source_module("needs_source.sub")));
+        assert!(policy.filter_python_resource(
+            &PythonModuleBytecodeFromSource {
+                name: "foo".to_string(),
+                source: DataLocation::Memory(vec![]),
+                optimize_level: BytecodeOptimizationLevel::Zero,
+                is_package: false,
+                cache_tag: "cpython-38".to_string(),
+                is_stdlib: false,
+                is_test: false,
+                provenance: None,
+            }
+            .into()
+        ));
+    }
+
+    #[test]
+    fn test_strip_docstrings() {
+        let mut policy = PythonPackagingPolicy::default();
+        assert!(!policy.get_strip_docstrings_for_name("foo"));
+
+        policy.add_strip_docstrings_package("foo");
+
+        assert!(policy.get_strip_docstrings_for_name("foo"));
+        assert!(policy.get_strip_docstrings_for_name("foo.bar"));
+        assert!(!policy.get_strip_docstrings_for_name("foobar"));
+        assert_eq!(
+            policy.iter_strip_docstrings_packages().collect::<Vec<_>>(),
+            vec!["foo"]
+        );
+
+        let mut merged = PythonPackagingPolicy::default();
+        merged.merge(&policy);
+        assert!(merged.get_strip_docstrings_for_name("foo.bar"));
     }
 }
@@ -9,6 +9,50 @@ use {anyhow::Result, lazy_static::lazy_static};
 lazy_static! {
     static ref RE_CODING: regex::bytes::Regex =
         regex::bytes::Regex::new(r"^[ \t\f]*#.*?coding[:=][ \t]*([-_.a-zA-Z0-9]+)").unwrap();
+
+    /// Matches calls like `pkgutil.get_data(__name__, "data.json")` and
+    /// `importlib.resources.read_text("foo", "data.json")`.
+    static ref RE_PACKAGE_DATA_CALL: regex::Regex = regex::Regex::new(
+        r#"\b(?:get_data|read_text|read_binary|open_text|open_binary|path)\(\s*(__name__|__package__|'[\w.]+'|"[\w.]+")\s*,\s*(?:'([^'\n]+)'|"([^"\n]+)")"#
+    )
+    .unwrap();
+
+    /// Matches `importlib.resources.files()` traversals like
+    /// `files(__package__) / "data.json"` and `files("foo").joinpath("data.json")`.
+    static ref RE_PACKAGE_DATA_FILES: regex::Regex = regex::Regex::new(
+        r#"\bfiles\(\s*(__name__|__package__|'[\w.]+'|"[\w.]+")\s*\)\s*(?:/|\.joinpath\()\s*(?:'([^'\n]+)'|"([^"\n]+)")"#
+    )
+    .unwrap();
+}
+
+/// A package data file read by Python source code.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct PackageDataReference {
+    /// Name of the package containing the data file.
+    pub package: String,
+
+    /// Path of the data file relative to the package, with `/` separators.
+    pub resource: String,
+}
+
+impl PackageDataReference {
+    /// Obtain the `(leaf package, relative name)` pairs the data file could be indexed as.
+    ///
+    /// Package resources are indexed by their leaf-most package. So a data
+    /// file in a subdirectory is indexed by the subpackage if the directory
+    /// is a package.
+    pub fn resource_locations(&self) -> Vec<(String, String)> {
+        let components = self.resource.split('/').collect::<Vec<_>>();
+
+        (0..components.len())
+            .map(|i| {
+                let mut package = vec![self.package.as_str()];
+                package.extend(&components[0..i]);
+
+                (package.join("."), components[i..].join("/"))
+            })
+            .collect()
+    }
 }
 
 /// Derive the source encoding from Python source code.
@@ -31,11 +75,8 @@ pub fn python_source_encoding(source: &[u8]) -> Vec<u8> {
     b"utf-8".to_vec()
 }
 
-/// Whether __file__ occurs in Python source code.
-pub fn has_dunder_file(source: &[u8]) -> Result<bool> {
-    // We can't just look for b"__file__ because the source file may be in
-    // encodings like UTF-16. So we need to decode to Unicode first then look for
-    // the code points.
+/// Decode Python source code to Unicode using its declared encoding.
+fn decode_python_source(source: &[u8]) -> std::borrow::Cow<'_, str> {
     let encoding = python_source_encoding(source);
 
     let encoder = match encoding_rs::Encoding::for_label(&encoding) {
@@ -45,5 +86,75 @@ pub fn has_dunder_file(source: &[u8]) -> Result<bool> {
 
     let (source, ..) = encoder.decode(source);
 
-    Ok(source.contains("__file__"))
+    source
+}
+
+/// Whether __file__ occurs in Python source code.
+pub fn has_dunder_file(source: &[u8]) -> Result<bool> {
+    // We can't just look for b"__file__ because the source file may be in
+    // encodings like UTF-16. So we need to decode to Unicode first then look for
+    // the code points.
+    Ok(decode_python_source(source).contains("__file__"))
+}
+
+/// Find package data files read by Python source code.
+///
+/// Calls to `pkgutil.get_data()` and the `importlib.resources` (or
+/// `importlib_resources`) APIs are detected. Only calls whose package is a
+/// string literal, `__name__` or `__package__` and whose resource is a string
+/// literal can be resolved. Other calls are ignored.
+///
+/// `module_name` and `is_package` describe the module the source belongs to
+/// and are used to resolve `__name__` and `__package__`.
+pub fn find_package_data_references(
+    module_name: &str,
+    is_package: bool,
+    source: &[u8],
+) -> Result<Vec<PackageDataReference>> {
+    let source = decode_python_source(source);
+
+    // `importlib` also matches `importlib.resources`, `from importlib import
+    // resources` and the `importlib_resources` backport.
+    if !source.contains("pkgutil") && !source.contains("importlib") {
+        return Ok(vec![]);
+    }
+
+    // Both APIs resolve a module name to the package containing it.
+    let containing_package = if is_package {
+        module_name
+    } else {
+        match module_name.rfind('.') {
+            Some(idx) => &module_name[0..idx],
+            None => "",
+        }
+    };
+
+    let mut res = vec![];
+
+    for re in [&*RE_PACKAGE_DATA_CALL, &*RE_PACKAGE_DATA_FILES].iter() {
+        for captures in re.captures_iter(&source) {
+            let package = match &captures[1] {
+                "__name__" | "__package__" => containing_package.to_string(),
+                literal => literal[1..literal.len() - 1].to_string(),
+            };
+
+            if package.is_empty() {
+                continue;
+            }
+
+            let resource = captures
+                .get(2)
+                .or_else(|| captures.get(3))
+                .map(|m| m.as_str().trim_start_matches("./").to_string());
+
+            if let Some(resource) = resource {
+                res.push(PackageDataReference { package, resource });
+            }
+        }
+    }
+
+    res.sort();
+    res.dedup();
+
+    Ok(res)
 }
@@ -9,6 +9,7 @@ use {
     crate::module_util::{
         is_package_from_path, packages_from_module_name, resolve_path_for_module,
     },
+    crate::package_metadata::parse_object_reference,
     crate::python_source::has_dunder_file,
     anyhow::{anyhow, Context, Result},
     std::collections::HashMap,
@@ -40,6 +41,51 @@ impl DataLocation {
     pub fn to_memory(&self) -> Result<DataLocation> {
         Ok(DataLocation::Memory(self.resolve()?))
     }
+
+    /// Resolve the size in bytes of the data.
+    ///
+    /// Filesystem backed data is not read.
+    pub fn size(&self) -> Result<u64> {
+        match self {
+            DataLocation::Path(p) => Ok(std::fs::metadata(p)
+                .context(format!("reading metadata of {}", p.display()))?
+                .len()),
+            DataLocation::Memory(data) => Ok(data.len() as u64),
+        }
+    }
+}
+
+/// Describes where a resource came from.
+///
+/// This allows attributing packaged content to whatever provided it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ResourceProvenance {
+    /// A file on the local filesystem.
+    LocalPath(PathBuf),
+    /// A file installed by a Python package distribution.
+    PackageDistribution { name: String, version: String },
+    /// A wheel file, identified by its filename.
+    Wheel(String),
+    /// A package index URL a distribution was obtained from.
+    IndexUrl(String),
+    /// A file installed by a conda package.
+    CondaPackage { name: String, version: String },
+}
+
+impl std::fmt::Display for ResourceProvenance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResourceProvenance::LocalPath(path) => write!(f, "path:{}", path.display()),
+            ResourceProvenance::PackageDistribution { name, version } => {
+                write!(f, "distribution:{}=={}", name, version)
+            }
+            ResourceProvenance::Wheel(filename) => write!(f, "wheel:{}", filename),
+            ResourceProvenance::IndexUrl(url) => write!(f, "index:{}", url),
+            ResourceProvenance::CondaPackage { name, version } => {
+                write!(f, "conda:{}=={}", name, version)
+            }
+        }
+    }
 }
 
 /// An optimization level for Python bytecode.
@@ -106,6 +152,8 @@ pub struct PythonModuleSource {
     /// Test modules are those defining test code and aren't critical to
     /// run-time functionality of a package.
     pub is_test: bool,
+    /// Where this module came from, if known.
+    pub provenance: Option<ResourceProvenance>,
 }
 
 impl PythonModuleSource {
@@ -117,6 +165,7 @@ impl PythonModuleSource {
             cache_tag: self.cache_tag.clone(),
             is_stdlib: self.is_stdlib,
             is_test: self.is_test,
+            provenance: self.provenance.clone(),
         })
     }
 
@@ -146,6 +195,7 @@ impl PythonModuleSource {
             cache_tag: self.cache_tag.clone(),
             is_stdlib: self.is_stdlib,
             is_test: self.is_test,
+            provenance: self.provenance.clone(),
         }
     }
 
@@ -183,6 +233,8 @@ pub struct PythonModuleBytecodeFromSource {
     /// Test modules are those defining test code and aren't critical to
     /// run-time functionality of a package.
     pub is_test: bool,
+    /// Where this module came from, if known.
+    pub provenance: Option<ResourceProvenance>,
 }
 
 impl PythonModuleBytecodeFromSource {
@@ -195,6 +247,7 @@ impl PythonModuleBytecodeFromSource {
             cache_tag: self.cache_tag.clone(),
             is_stdlib: self.is_stdlib,
             is_test: self.is_test,
+            provenance: self.provenance.clone(),
         })
     }
 
@@ -249,6 +302,8 @@ pub struct PythonModuleBytecode {
     /// Test modules are those defining test code and aren't critical to
     /// run-time functionality of a package.
     pub is_test: bool,
+    /// Where this module came from, if known.
+    pub provenance: Option<ResourceProvenance>,
 }
 
 impl PythonModuleBytecode {
@@ -267,6 +322,7 @@ impl PythonModuleBytecode {
             cache_tag: cache_tag.to_string(),
             is_stdlib: false,
             is_test: false,
+            provenance: None,
         }
     }
 
@@ -284,6 +340,7 @@ impl PythonModuleBytecode {
             cache_tag: cache_tag.to_string(),
             is_stdlib: false,
             is_test: false,
+            provenance: None,
         }
     }
 
@@ -296,6 +353,7 @@ impl PythonModuleBytecode {
             cache_tag: self.cache_tag.clone(),
             is_stdlib: self.is_stdlib,
             is_test: self.is_test,
+            provenance: self.provenance.clone(),
         })
     }
 
@@ -347,6 +405,8 @@ pub struct PythonPackageResource {
     pub is_stdlib: bool,
     /// Whether this resource belongs to a package that is a test.
     pub is_test: bool,
+    /// Where this resource came from, if known.
+    pub provenance: Option<ResourceProvenance>,
 }
 
 impl PythonPackageResource {
@@ -357,6 +417,7 @@ impl PythonPackageResource {
             data: self.data.to_memory()?,
             is_stdlib: self.is_stdlib,
             is_test: self.is_test,
+            provenance: self.provenance.clone(),
         })
     }
 
@@ -414,6 +475,8 @@ pub struct PythonPackageDistributionResource {
 
     /// The raw content of the distribution resource.
     pub data: DataLocation,
+    /// Where this resource came from, if known.
+    pub provenance: Option<ResourceProvenance>,
 }
 
 impl PythonPackageDistributionResource {
@@ -424,9 +487,19 @@ impl PythonPackageDistributionResource {
             version: self.version.clone(),
             name: self.name.clone(),
             data: self.data.to_memory()?,
+            provenance: self.provenance.clone(),
         })
     }
 
+    /// Obtain the package name as normalized by `importlib.metadata`.
+    ///
+    /// Distributions are looked up by lowercased name with `-` replaced
+    /// by `_`. e.g. `importlib.metadata.version("Typing-Extensions")` looks
+    /// for `typing_extensions`.
+    pub fn normalized_package(&self) -> String {
+        self.package.to_lowercase().replace('-', "_")
+    }
+
     /// Resolve filesystem path to this resource file.
     pub fn resolve_path(&self, prefix: &str) -> PathBuf {
         let p = match self.location {
@@ -526,6 +599,8 @@ pub struct PythonExtensionModule {
     pub license_texts: Option<Vec<DataLocation>>,
     /// Whether the license for this extension and any library dependencies are in the public domain.
     pub license_public_domain: Option<bool>,
+    /// Where this extension module came from, if known.
+    pub provenance: Option<ResourceProvenance>,
 }
 
 impl PythonExtensionModule {
@@ -562,6 +637,7 @@ impl PythonExtensionModule {
                 None
             },
             license_public_domain: self.license_public_domain,
+            provenance: self.provenance.clone(),
         })
     }
 
@@ -704,6 +780,190 @@ impl PythonPathExtension {
     }
 }
 
+/// Represents an entry point advertised by a Python package distribution.
+///
+/// i.e. an entry in a distribution's `entry_points.txt` file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PythonEntryPoint {
+    /// Name of the distribution advertising the entry point.
+    pub package: String,
+
+    /// Version of the distribution advertising the entry point.
+    pub version: String,
+
+    /// Entry point group. e.g. `console_scripts`.
+    pub group: String,
+
+    /// Name of the entry point. e.g. `mycli`.
+    pub name: String,
+
+    /// Object reference of the entry point. e.g. `mypkg.cli:main`.
+    pub value: String,
+}
+
+impl PythonEntryPoint {
+    /// Whether this entry point defines a console script.
+    pub fn is_console_script(&self) -> bool {
+        self.group == "console_scripts"
+    }
+
+    /// Resolve the module and attribute names of the referenced object.
+    pub fn module_and_attr(&self) -> Result<(String, Option<String>)> {
+        parse_object_reference(&self.value)
+    }
+
+    /// Obtain Python code running this entry point.
+    ///
+    /// See `entry_point_run_code()`.
+    pub fn run_code(&self) -> Result<String> {
+        entry_point_run_code(&self.value)
+    }
+}
+
+/// Represents a package distribution installed in editable mode.
+///
+/// i.e. via `setup.py develop` or a PEP 660 editable install. Modules of the
+/// distribution are imported from its source tree instead of from installed
+/// copies.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PythonEditableInstall {
+    /// Name of the distribution.
+    pub name: String,
+
+    /// Version of the distribution.
+    pub version: String,
+
+    /// Directory containing the distribution's top-level packages.
+    ///
+    /// This is the directory that needs to be on `sys.path`.
+    pub path: PathBuf,
+
+    /// Names of top-level packages and modules provided by the distribution.
+    pub top_level: Vec<String>,
+}
+
+/// Obtain Python code running an entry point object reference.
+///
+/// If the reference names an attribute, it is called and the process exits
+/// with its return value, just like the launcher scripts installed by pip.
+/// Otherwise the module is run as `__main__`.
+pub fn entry_point_run_code(value: &str) -> Result<String> {
+    let (module, attr) = parse_object_reference(value)?;
+
+    Ok(if let Some(attr) = attr {
+        format!(
+            "import sys; import {}; sys.exit({}.{}())",
+            module, module, attr
+        )
+    } else {
+        format!(
+            "import runpy; runpy.run_module(\"{}\", run_name=\"__main__\", alter_sys=True)",
+            module
+        )
+    })
+}
+
+/// Where a file installed outside of Python package directories belongs.
+///
+/// These correspond to the `data`, `scripts` and `headers` install schemes
+/// of distutils and the matching directories in a wheel's `.data` directory.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DataFileInstallLocation {
+    /// Arbitrary files relative to the installation prefix.
+    ///
+    /// e.g. `data_files` in `setup.py`.
+    Data,
+    /// Executable scripts.
+    Scripts,
+    /// C header files.
+    Headers,
+}
+
+impl DataFileInstallLocation {
+    /// Directory files are installed to, relative to the installation prefix.
+    ///
+    /// This follows the layout of a POSIX installation.
+    pub fn directory(&self) -> &'static str {
+        match self {
+            DataFileInstallLocation::Data => "",
+            DataFileInstallLocation::Scripts => "bin",
+            DataFileInstallLocation::Headers => "include",
+        }
+    }
+}
+
+impl std::fmt::Display for DataFileInstallLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            DataFileInstallLocation::Data => "data",
+            DataFileInstallLocation::Scripts => "scripts",
+            DataFileInstallLocation::Headers => "headers",
+        })
+    }
+}
+
+impl TryFrom<&str> for DataFileInstallLocation {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "data" => Ok(DataFileInstallLocation::Data),
+            "scripts" => Ok(DataFileInstallLocation::Scripts),
+            "headers" => Ok(DataFileInstallLocation::Headers),
+            _ => Err(format!(
+                "{} is not a valid data file install location",
+                value
+            )),
+        }
+    }
+}
+
+/// A file a package distribution installs outside of Python package directories.
+///
+/// e.g. files from `data_files`, `scripts` or `headers` in `setup.py`.
+/// These aren't importable. But applications may expect them next to the
+/// Python installation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PythonDataFile {
+    /// Name of the distribution installing the file.
+    pub package: String,
+
+    /// Where the file is installed.
+    pub location: DataFileInstallLocation,
+
+    /// Path of the file relative to the directory of `location`.
+    pub relative_path: PathBuf,
+
+    /// Content of the file.
+    pub data: DataLocation,
+
+    /// Whether the file is executable.
+    pub executable: bool,
+
+    /// Where this file came from, if known.
+    pub provenance: Option<ResourceProvenance>,
+}
+
+impl PythonDataFile {
+    pub fn to_memory(&self) -> Result<Self> {
+        Ok(Self {
+            package: self.package.clone(),
+            location: self.location,
+            relative_path: self.relative_path.clone(),
+            data: self.data.to_memory()?,
+            executable: self.executable,
+            provenance: self.provenance.clone(),
+        })
+    }
+
+    /// Resolve the filesystem path of this file relative to an installation prefix.
+    pub fn resolve_path(&self, prefix: &str) -> PathBuf {
+        PathBuf::from(prefix)
+            .join(self.location.directory())
+            .join(&self.relative_path)
+    }
+}
+
 /// Represents a resource that can be read by Python somehow.
 #[derive(Clone, Debug, PartialEq)]
 pub enum PythonResource {
@@ -725,6 +985,12 @@ pub enum PythonResource {
     EggFile(PythonEggFile),
     /// A path extension.
     PathExtension(PythonPathExtension),
+    /// An entry point advertised by a package distribution.
+    EntryPoint(PythonEntryPoint),
+    /// A file installed outside of Python package directories.
+    DataFile(PythonDataFile),
+    /// A distribution installed in editable mode.
+    EditableInstall(PythonEditableInstall),
 }
 
 impl PythonResource {
@@ -744,6 +1010,103 @@ impl PythonResource {
             PythonResource::ExtensionModuleStaticallyLinked(em) => em.name.clone(),
             PythonResource::EggFile(_) => "".to_string(),
             PythonResource::PathExtension(_) => "".to_string(),
+            PythonResource::EntryPoint(ep) => format!("{}:{}:{}", ep.package, ep.group, ep.name),
+            PythonResource::DataFile(f) => {
+                format!("{}:{}:{}", f.package, f.location, f.relative_path.display())
+            }
+            PythonResource::EditableInstall(e) => e.name.clone(),
+        }
+    }
+
+    /// Resolve the size in bytes of the data backing this resource.
+    ///
+    /// For bytecode requests, this is the size of the source code. For extension
+    /// modules, this is the size of the shared library, if present, otherwise the
+    /// combined size of object files.
+    pub fn data_size(&self) -> Result<u64> {
+        match self {
+            PythonResource::ModuleSource(m) => m.source.size(),
+            PythonResource::ModuleBytecode(m) => m.bytecode.size(),
+            PythonResource::ModuleBytecodeRequest(m) => m.source.size(),
+            PythonResource::Resource(resource) => resource.data.size(),
+            PythonResource::DistributionResource(resource) => resource.data.size(),
+            PythonResource::ExtensionModuleDynamicLibrary(em)
+            | PythonResource::ExtensionModuleStaticallyLinked(em) => {
+                if let Some(location) = &em.shared_library {
+                    location.size()
+                } else {
+                    let mut size = 0;
+                    for location in &em.object_file_data {
+                        size += location.size()?;
+                    }
+
+                    Ok(size)
+                }
+            }
+            PythonResource::EggFile(egg) => egg.data.size(),
+            PythonResource::PathExtension(pth) => pth.data.size(),
+            PythonResource::EntryPoint(_) => Ok(0),
+            PythonResource::DataFile(f) => f.data.size(),
+            PythonResource::EditableInstall(_) => Ok(0),
+        }
+    }
+
+    /// Obtain the provenance of this resource, if known.
+    ///
+    /// Egg files, path extensions, entry points and editable installs don't
+    /// track provenance.
+    pub fn provenance(&self) -> Option<&ResourceProvenance> {
+        match self {
+            PythonResource::ModuleSource(m) => m.provenance.as_ref(),
+            PythonResource::ModuleBytecode(m) => m.provenance.as_ref(),
+            PythonResource::ModuleBytecodeRequest(m) => m.provenance.as_ref(),
+            PythonResource::Resource(resource) => resource.provenance.as_ref(),
+            PythonResource::DistributionResource(resource) => resource.provenance.as_ref(),
+            PythonResource::ExtensionModuleDynamicLibrary(em)
+            | PythonResource::ExtensionModuleStaticallyLinked(em) => em.provenance.as_ref(),
+            PythonResource::DataFile(f) => f.provenance.as_ref(),
+            PythonResource::EggFile(_)
+            | PythonResource::PathExtension(_)
+            | PythonResource::EntryPoint(_)
+            | PythonResource::EditableInstall(_) => None,
+        }
+    }
+
+    /// Set the provenance of this resource.
+    ///
+    /// Has no effect on resource types not tracking provenance.
+    pub fn set_provenance(&mut self, provenance: Option<ResourceProvenance>) {
+        match self {
+            PythonResource::ModuleSource(m) => m.provenance = provenance,
+            PythonResource::ModuleBytecode(m) => m.provenance = provenance,
+            PythonResource::ModuleBytecodeRequest(m) => m.provenance = provenance,
+            PythonResource::Resource(resource) => resource.provenance = provenance,
+            PythonResource::DistributionResource(resource) => resource.provenance = provenance,
+            PythonResource::ExtensionModuleDynamicLibrary(em)
+            | PythonResource::ExtensionModuleStaticallyLinked(em) => em.provenance = provenance,
+            PythonResource::DataFile(f) => f.provenance = provenance,
+            PythonResource::EggFile(_)
+            | PythonResource::PathExtension(_)
+            | PythonResource::EntryPoint(_)
+            | PythonResource::EditableInstall(_) => {}
+        }
+    }
+
+    /// Obtain the location of the primary data backing this resource.
+    pub fn data_location(&self) -> Option<&DataLocation> {
+        match self {
+            PythonResource::ModuleSource(m) => Some(&m.source),
+            PythonResource::ModuleBytecode(m) => Some(&m.bytecode),
+            PythonResource::ModuleBytecodeRequest(m) => Some(&m.source),
+            PythonResource::Resource(resource) => Some(&resource.data),
+            PythonResource::DistributionResource(resource) => Some(&resource.data),
+            PythonResource::ExtensionModuleDynamicLibrary(em)
+            | PythonResource::ExtensionModuleStaticallyLinked(em) => em.shared_library.as_ref(),
+            PythonResource::EggFile(egg) => Some(&egg.data),
+            PythonResource::PathExtension(pth) => Some(&pth.data),
+            PythonResource::EntryPoint(_) => None,
+            PythonResource::DataFile(f) => Some(&f.data),
+            PythonResource::EditableInstall(_) => None,
         }
     }
 
@@ -758,6 +1121,11 @@ impl PythonResource {
             PythonResource::ExtensionModuleStaticallyLinked(em) => &em.name,
             PythonResource::EggFile(_) => return false,
             PythonResource::PathExtension(_) => return false,
+            PythonResource::EntryPoint(ep) => &ep.package,
+            PythonResource::DataFile(f) => &f.package,
+            PythonResource::EditableInstall(e) => {
+                return e.top_level.iter().any(|name| packages.contains(name))
+            }
         };
 
         for package in packages {
@@ -793,6 +1161,9 @@ impl PythonResource {
             }
             PythonResource::EggFile(e) => PythonResource::EggFile(e.to_memory()?),
             PythonResource::PathExtension(e) => PythonResource::PathExtension(e.to_memory()?),
+            PythonResource::EntryPoint(ep) => PythonResource::EntryPoint(ep.clone()),
+            PythonResource::DataFile(f) => PythonResource::DataFile(f.to_memory()?),
+            PythonResource::EditableInstall(e) => PythonResource::EditableInstall(e.clone()),
         })
     }
 }
@@ -839,6 +1210,24 @@ impl From<PythonPathExtension> for PythonResource {
     }
 }
 
+impl From<PythonEntryPoint> for PythonResource {
+    fn from(ep: PythonEntryPoint) -> Self {
+        PythonResource::EntryPoint(ep)
+    }
+}
+
+impl From<PythonEditableInstall> for PythonResource {
+    fn from(e: PythonEditableInstall) -> Self {
+        PythonResource::EditableInstall(e)
+    }
+}
+
+impl From<PythonDataFile> for PythonResource {
+    fn from(f: PythonDataFile) -> Self {
+        PythonResource::DataFile(f)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -854,6 +1243,7 @@ mod tests {
             cache_tag: DEFAULT_CACHE_TAG.to_string(),
             is_stdlib: false,
             is_test: false,
+            provenance: None,
         });
         assert!(source.is_in_packages(&["foo".to_string()]));
         assert!(!source.is_in_packages(&[]));
@@ -867,9 +1257,61 @@ mod tests {
             cache_tag: DEFAULT_CACHE_TAG.to_string(),
             is_stdlib: false,
             is_test: false,
+            provenance: None,
         });
         assert!(bytecode.is_in_packages(&["foo".to_string()]));
         assert!(!bytecode.is_in_packages(&[]));
         assert!(!bytecode.is_in_packages(&["bar".to_string()]));
     }
+
+    #[test]
+    fn test_entry_point_run_code() -> Result<()> {
+        let ep = PythonEntryPoint {
+            package: "mypkg".to_string(),
+            version: "1.0".to_string(),
+            group: "console_scripts".to_string(),
+            name: "mycli".to_string(),
+            value: "mypkg.cli:main".to_string(),
+        };
+
+        assert!(ep.is_console_script());
+        assert_eq!(
+            ep.run_code()?,
+            "import sys; import mypkg.cli; sys.exit(mypkg.cli.main())"
+        );
+        assert_eq!(
+            entry_point_run_code("mypkg")?,
+            "import runpy; runpy.run_module(\"mypkg\", run_name=\"__main__\", alter_sys=True)"
+        );
+        assert!(entry_point_run_code("mypkg:").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_data_file_resolve_path() -> Result<()> {
+        let mut f = PythonDataFile {
+            package: "foo".to_string(),
+            location: DataFileInstallLocation::Data,
+            relative_path: PathBuf::from("share/foo/data.json"),
+            data: DataLocation::Memory(vec![]),
+            executable: false,
+            provenance: None,
+        };
+
+        assert_eq!(f.resolve_path("."), PathBuf::from("./share/foo/data.json"));
+
+        f.location = DataFileInstallLocation::Scripts;
+        f.relative_path = PathBuf::from("foo-cli");
+        assert_eq!(f.resolve_path("."), PathBuf::from("./bin/foo-cli"));
+        assert_eq!(PythonResource::from(f).full_name(), "foo:scripts:foo-cli");
+
+        assert_eq!(
+            DataFileInstallLocation::try_from("scripts"),
+            Ok(DataFileInstallLocation::Scripts)
+        );
+        assert!(DataFileInstallLocation::try_from("purelib").is_err());
+
+        Ok(())
+    }
 }
@@ -8,16 +8,19 @@ use {
     crate::bytecode::{
         compute_bytecode_header, BytecodeHeaderMode, CompileMode, PythonBytecodeCompiler,
     },
-    crate::module_util::{packages_from_module_name, resolve_path_for_module},
+    crate::module_util::{find_package_entry, packages_from_module_name, resolve_path_for_module},
     crate::policy::PythonResourcesPolicy,
-    crate::python_source::has_dunder_file,
+    crate::python_source::{find_package_data_references, has_dunder_file, PackageDataReference},
     crate::resource::{
         BytecodeOptimizationLevel, DataLocation, PythonExtensionModule, PythonModuleBytecode,
         PythonModuleBytecodeFromSource, PythonModuleSource, PythonPackageDistributionResource,
-        PythonPackageResource,
+        PythonPackageResource, ResourceProvenance,
     },
     anyhow::{anyhow, Result},
-    python_packed_resources::data::{Resource, ResourceFlavor},
+    python_packed_resources::data::{Resource, ResourceField, ResourceFlavor},
+    python_packed_resources::writer::{
+        BlobCompressionReport, BlobDeduplicationReport, ResourceSource, WriteOptions,
+    },
     std::borrow::Cow,
     std::collections::{BTreeMap, BTreeSet, HashMap},
     std::iter::FromIterator,
@@ -71,9 +74,26 @@ pub struct PrePackagedResource {
     pub relative_path_package_resources: Option<BTreeMap<String, (PathBuf, DataLocation)>>,
     pub relative_path_distribution_resources: Option<BTreeMap<String, (PathBuf, DataLocation)>>,
     pub relative_path_shared_library: Option<(String, DataLocation)>,
+    pub provenance: Option<String>,
 }
 
 impl PrePackagedResource {
+    /// Whether this resource holds code for a Python module.
+    ///
+    /// Resources only holding package or distribution resources don't.
+    pub fn has_module_code(&self) -> bool {
+        self.in_memory_source.is_some()
+            || self.in_memory_bytecode.is_some()
+            || self.in_memory_bytecode_opt1.is_some()
+            || self.in_memory_bytecode_opt2.is_some()
+            || self.in_memory_extension_module_shared_library.is_some()
+            || self.relative_path_module_source.is_some()
+            || self.relative_path_bytecode.is_some()
+            || self.relative_path_bytecode_opt1.is_some()
+            || self.relative_path_bytecode_opt2.is_some()
+            || self.relative_path_extension_module_shared_library.is_some()
+    }
+
     /// Convert the instance to a `Resource`.
     ///
     /// This will compile bytecode from source code using the specified compiler.
@@ -83,9 +103,84 @@ impl PrePackagedResource {
         &self,
         compiler: &mut dyn PythonBytecodeCompiler,
     ) -> Result<(Resource<'a, u8>, Vec<FileInstall>)> {
-        let mut installs = Vec::new();
+        let resource = self.compile_bytecode(compiler)?.to_packed_resource()?;
+        let installs = self.file_installs(compiler)?;
+
+        Ok((resource, installs))
+    }
+
+    /// Obtain a copy of this instance with in-memory bytecode compiled.
+    ///
+    /// Bytecode loaded from memory that is derived from source is replaced by
+    /// the bytecode it compiles to, so the copy can be converted by
+    /// `to_packed_resource()`. Other data is left at its location.
+    pub fn compile_bytecode(&self, compiler: &mut dyn PythonBytecodeCompiler) -> Result<Self> {
+        let mut res = self.clone();
+
+        for (provider, level) in &mut [
+            (&mut res.in_memory_bytecode, BytecodeOptimizationLevel::Zero),
+            (
+                &mut res.in_memory_bytecode_opt1,
+                BytecodeOptimizationLevel::One,
+            ),
+            (
+                &mut res.in_memory_bytecode_opt2,
+                BytecodeOptimizationLevel::Two,
+            ),
+        ] {
+            if let Some(PythonModuleBytecodeProvider::FromSource(location)) = provider {
+                **provider = Some(PythonModuleBytecodeProvider::Provided(
+                    DataLocation::Memory(compiler.compile(
+                        &location.resolve()?,
+                        &self.name,
+                        *level,
+                        CompileMode::Bytecode,
+                    )?),
+                ));
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Resolve in-memory bytecode that was compiled by `compile_bytecode()`.
+    fn provided_bytecode<'a>(
+        &self,
+        provider: &Option<PythonModuleBytecodeProvider>,
+    ) -> Result<Option<Cow<'a, [u8]>>> {
+        match provider {
+            Some(PythonModuleBytecodeProvider::Provided(location)) => {
+                Ok(Some(Cow::Owned(location.resolve()?)))
+            }
+            Some(PythonModuleBytecodeProvider::FromSource(_)) => {
+                Err(anyhow!("bytecode for {} has not been compiled", self.name))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Resolve the path bytecode at `level` is installed to.
+    fn relative_bytecode_path(
+        &self,
+        prefix: &str,
+        cache_tag: &str,
+        level: BytecodeOptimizationLevel,
+    ) -> PathBuf {
+        resolve_path_for_module(
+            prefix,
+            &self.name,
+            self.is_package,
+            Some(&format!("{}{}", cache_tag, level.to_extra_tag())),
+        )
+    }
 
-        let resource = Resource {
+    /// Convert the instance to a `Resource` without compiling bytecode.
+    ///
+    /// Data is read from the locations backing it. In-memory bytecode must
+    /// have been compiled by `compile_bytecode()`. Files referenced by
+    /// relative path are not resolved; see `file_installs()`.
+    pub fn to_packed_resource<'a>(&self) -> Result<Resource<'a, u8>> {
+        Ok(Resource {
             flavor: self.flavor,
             name: Cow::Owned(self.name.clone()),
             is_package: self.is_package,
@@ -95,48 +190,9 @@ impl PrePackagedResource {
             } else {
                 None
             },
-            in_memory_bytecode: match &self.in_memory_bytecode {
-                Some(PythonModuleBytecodeProvider::Provided(location)) => {
-                    Some(Cow::Owned(location.resolve()?))
-                }
-                Some(PythonModuleBytecodeProvider::FromSource(location)) => {
-                    Some(Cow::Owned(compiler.compile(
-                        &location.resolve()?,
-                        &self.name,
-                        BytecodeOptimizationLevel::Zero,
-                        CompileMode::Bytecode,
-                    )?))
-                }
-                None => None,
-            },
-            in_memory_bytecode_opt1: match &self.in_memory_bytecode_opt1 {
-                Some(PythonModuleBytecodeProvider::Provided(location)) => {
-                    Some(Cow::Owned(location.resolve()?))
-                }
-                Some(PythonModuleBytecodeProvider::FromSource(location)) => {
-                    Some(Cow::Owned(compiler.compile(
-                        &location.resolve()?,
-                        &self.name,
-                        BytecodeOptimizationLevel::One,
-                        CompileMode::Bytecode,
-                    )?))
-                }
-                None => None,
-            },
-            in_memory_bytecode_opt2: match &self.in_memory_bytecode_opt2 {
-                Some(PythonModuleBytecodeProvider::Provided(location)) => {
-                    Some(Cow::Owned(location.resolve()?))
-                }
-                Some(PythonModuleBytecodeProvider::FromSource(location)) => {
-                    Some(Cow::Owned(compiler.compile(
-                        &location.resolve()?,
-                        &self.name,
-                        BytecodeOptimizationLevel::Two,
-                        CompileMode::Bytecode,
-                    )?))
-                }
-                None => None,
-            },
+            in_memory_bytecode: self.provided_bytecode(&self.in_memory_bytecode)?,
+            in_memory_bytecode_opt1: self.provided_bytecode(&self.in_memory_bytecode_opt1)?,
+            in_memory_bytecode_opt2: self.provided_bytecode(&self.in_memory_bytecode_opt2)?,
             in_memory_extension_module_shared_library: if let Some(location) =
                 &self.in_memory_extension_module_shared_library
             {
@@ -176,173 +232,141 @@ impl PrePackagedResource {
             } else {
                 None
             },
-            relative_path_module_source: if let Some((prefix, location)) =
-                &self.relative_path_module_source
-            {
-                let path = resolve_path_for_module(prefix, &self.name, self.is_package, None);
-
-                installs.push((path.clone(), location.clone(), false));
-
-                Some(Cow::Owned(path))
-            } else {
-                None
-            },
-            relative_path_module_bytecode: if let Some((prefix, cache_tag, provider)) =
-                &self.relative_path_bytecode
-            {
-                let path = resolve_path_for_module(
-                    prefix,
-                    &self.name,
-                    self.is_package,
-                    Some(&format!(
-                        "{}{}",
+            relative_path_module_source: self.relative_path_module_source.as_ref().map(
+                |(prefix, _)| {
+                    Cow::Owned(resolve_path_for_module(
+                        prefix,
+                        &self.name,
+                        self.is_package,
+                        None,
+                    ))
+                },
+            ),
+            relative_path_module_bytecode: self.relative_path_bytecode.as_ref().map(
+                |(prefix, cache_tag, _)| {
+                    Cow::Owned(self.relative_bytecode_path(
+                        prefix,
                         cache_tag,
-                        BytecodeOptimizationLevel::Zero.to_extra_tag()
-                    )),
-                );
-
-                installs.push((
-                    path.clone(),
-                    DataLocation::Memory(match provider {
-                        PythonModuleBytecodeProvider::FromSource(location) => compiler.compile(
-                            &location.resolve()?,
-                            &self.name,
-                            BytecodeOptimizationLevel::Zero,
-                            CompileMode::PycUncheckedHash,
-                        )?,
-                        PythonModuleBytecodeProvider::Provided(location) => {
-                            let mut data = compute_bytecode_header(
-                                compiler.get_magic_number(),
-                                BytecodeHeaderMode::UncheckedHash(0),
-                            )?;
-                            data.extend(location.resolve()?);
-
-                            data
-                        }
-                    }),
-                    false,
-                ));
-
-                Some(Cow::Owned(path))
-            } else {
-                None
-            },
-            relative_path_module_bytecode_opt1: if let Some((prefix, cache_tag, provider)) =
-                &self.relative_path_bytecode_opt1
-            {
-                let path = resolve_path_for_module(
-                    prefix,
-                    &self.name,
-                    self.is_package,
-                    Some(&format!(
-                        "{}{}",
+                        BytecodeOptimizationLevel::Zero,
+                    ))
+                },
+            ),
+            relative_path_module_bytecode_opt1: self.relative_path_bytecode_opt1.as_ref().map(
+                |(prefix, cache_tag, _)| {
+                    Cow::Owned(self.relative_bytecode_path(
+                        prefix,
                         cache_tag,
-                        BytecodeOptimizationLevel::One.to_extra_tag()
-                    )),
-                );
+                        BytecodeOptimizationLevel::One,
+                    ))
+                },
+            ),
+            relative_path_module_bytecode_opt2: self.relative_path_bytecode_opt2.as_ref().map(
+                |(prefix, cache_tag, _)| {
+                    Cow::Owned(self.relative_bytecode_path(
+                        prefix,
+                        cache_tag,
+                        BytecodeOptimizationLevel::Two,
+                    ))
+                },
+            ),
+            relative_path_extension_module_shared_library: self
+                .relative_path_extension_module_shared_library
+                .as_ref()
+                .map(|(path, _)| Cow::Owned(path.clone())),
+            relative_path_package_resources: self.relative_path_package_resources.as_ref().map(
+                |resources| {
+                    resources
+                        .iter()
+                        .map(|(key, (path, _))| (Cow::Owned(key.clone()), Cow::Owned(path.clone())))
+                        .collect()
+                },
+            ),
+            relative_path_distribution_resources: self
+                .relative_path_distribution_resources
+                .as_ref()
+                .map(|resources| {
+                    resources
+                        .iter()
+                        .map(|(key, (path, _))| (Cow::Owned(key.clone()), Cow::Owned(path.clone())))
+                        .collect()
+                }),
+            provenance: self.provenance.as_ref().map(|p| Cow::Owned(p.clone())),
+            encoded_blobs: None,
+        })
+    }
 
-                installs.push((
-                    path.clone(),
-                    DataLocation::Memory(match provider {
-                        PythonModuleBytecodeProvider::FromSource(location) => compiler.compile(
-                            &location.resolve()?,
-                            &self.name,
-                            BytecodeOptimizationLevel::One,
-                            CompileMode::PycUncheckedHash,
-                        )?,
-                        PythonModuleBytecodeProvider::Provided(location) => {
-                            let mut data = compute_bytecode_header(
-                                compiler.get_magic_number(),
-                                BytecodeHeaderMode::UncheckedHash(0),
-                            )?;
-                            data.extend(location.resolve()?);
-
-                            data
-                        }
-                    }),
-                    false,
-                ));
+    /// Resolve the files that must be installed for the resource to function as intended.
+    ///
+    /// Bytecode installed next to module source is compiled using the
+    /// specified compiler.
+    pub fn file_installs(
+        &self,
+        compiler: &mut dyn PythonBytecodeCompiler,
+    ) -> Result<Vec<FileInstall>> {
+        let mut installs = Vec::new();
 
-                Some(Cow::Owned(path))
-            } else {
-                None
-            },
-            relative_path_module_bytecode_opt2: if let Some((prefix, cache_tag, provider)) =
-                &self.relative_path_bytecode_opt2
-            {
-                let path = resolve_path_for_module(
-                    prefix,
-                    &self.name,
-                    self.is_package,
-                    Some(&format!(
-                        "{}{}",
-                        cache_tag,
-                        BytecodeOptimizationLevel::Two.to_extra_tag()
-                    )),
-                );
+        if let Some((prefix, location)) = &self.relative_path_module_source {
+            installs.push((
+                resolve_path_for_module(prefix, &self.name, self.is_package, None),
+                location.clone(),
+                false,
+            ));
+        }
+
+        for (entry, level) in &[
+            (
+                &self.relative_path_bytecode,
+                BytecodeOptimizationLevel::Zero,
+            ),
+            (
+                &self.relative_path_bytecode_opt1,
+                BytecodeOptimizationLevel::One,
+            ),
+            (
+                &self.relative_path_bytecode_opt2,
+                BytecodeOptimizationLevel::Two,
+            ),
+        ] {
+            if let Some((prefix, cache_tag, provider)) = entry {
+                let data = match provider {
+                    PythonModuleBytecodeProvider::FromSource(location) => compiler.compile(
+                        &location.resolve()?,
+                        &self.name,
+                        *level,
+                        CompileMode::PycUncheckedHash,
+                    )?,
+                    PythonModuleBytecodeProvider::Provided(location) => {
+                        let mut data = compute_bytecode_header(
+                            compiler.get_magic_number(),
+                            BytecodeHeaderMode::UncheckedHash(0),
+                        )?;
+                        data.extend(location.resolve()?);
+
+                        data
+                    }
+                };
 
                 installs.push((
-                    path.clone(),
-                    DataLocation::Memory(match provider {
-                        PythonModuleBytecodeProvider::FromSource(location) => compiler.compile(
-                            &location.resolve()?,
-                            &self.name,
-                            BytecodeOptimizationLevel::Two,
-                            CompileMode::PycUncheckedHash,
-                        )?,
-                        PythonModuleBytecodeProvider::Provided(location) => {
-                            let mut data = compute_bytecode_header(
-                                compiler.get_magic_number(),
-                                BytecodeHeaderMode::UncheckedHash(0),
-                            )?;
-                            data.extend(location.resolve()?);
-
-                            data
-                        }
-                    }),
+                    self.relative_bytecode_path(prefix, cache_tag, *level),
+                    DataLocation::Memory(data),
                     false,
                 ));
+            }
+        }
 
-                Some(Cow::Owned(path))
-            } else {
-                None
-            },
-            relative_path_extension_module_shared_library: if let Some((path, location)) =
-                &self.relative_path_extension_module_shared_library
-            {
-                installs.push((path.clone(), location.clone(), true));
-
-                Some(Cow::Owned(path.clone()))
-            } else {
-                None
-            },
-            relative_path_package_resources: if let Some(resources) =
-                &self.relative_path_package_resources
-            {
-                let mut res = HashMap::new();
-                for (key, (path, location)) in resources {
-                    installs.push((path.clone(), location.clone(), false));
-
-                    res.insert(Cow::Owned(key.clone()), Cow::Owned(path.clone()));
-                }
-                Some(res)
-            } else {
-                None
-            },
-            relative_path_distribution_resources: if let Some(resources) =
-                &self.relative_path_distribution_resources
-            {
-                let mut res = HashMap::new();
-                for (key, (path, location)) in resources {
-                    installs.push((path.clone(), location.clone(), false));
+        if let Some((path, location)) = &self.relative_path_extension_module_shared_library {
+            installs.push((path.clone(), location.clone(), true));
+        }
 
-                    res.insert(Cow::Owned(key.clone()), Cow::Owned(path.clone()));
-                }
-                Some(res)
-            } else {
-                None
-            },
-        };
+        for resources in self
+            .relative_path_package_resources
+            .iter()
+            .chain(self.relative_path_distribution_resources.iter())
+        {
+            for (path, location) in resources.values() {
+                installs.push((path.clone(), location.clone(), false));
+            }
+        }
 
         if let Some((prefix, location)) = &self.relative_path_shared_library {
             installs.push((
@@ -352,7 +376,87 @@ impl PrePackagedResource {
             ));
         }
 
-        Ok((resource, installs))
+        Ok(installs)
+    }
+
+    /// Obtain a copy of this instance with docstrings and comments stripped.
+    ///
+    /// Module source is replaced by stripped source. Bytecode derived from
+    /// source is compiled from the stripped source at optimization level 2,
+    /// which also removes `assert` statements. Bytecode that was provided
+    /// verbatim is retained.
+    pub fn strip_docstrings(&self, compiler: &mut dyn PythonBytecodeCompiler) -> Result<Self> {
+        let name = self.name.clone();
+
+        let mut strip_source = |location: &DataLocation| -> Result<DataLocation> {
+            Ok(DataLocation::Memory(
+                compiler.strip_docstrings(&location.resolve()?, &name)?,
+            ))
+        };
+
+        let mut res = self.clone();
+
+        if let Some(location) = &self.in_memory_source {
+            res.in_memory_source = Some(strip_source(location)?);
+        }
+        if let Some((prefix, location)) = &self.relative_path_module_source {
+            res.relative_path_module_source = Some((prefix.clone(), strip_source(location)?));
+        }
+
+        let mut strip_bytecode =
+            |provider: &PythonModuleBytecodeProvider| -> Result<PythonModuleBytecodeProvider> {
+                match provider {
+                    PythonModuleBytecodeProvider::FromSource(location) => {
+                        let source = compiler.strip_docstrings(&location.resolve()?, &name)?;
+
+                        Ok(PythonModuleBytecodeProvider::Provided(
+                            DataLocation::Memory(compiler.compile(
+                                &source,
+                                &name,
+                                BytecodeOptimizationLevel::Two,
+                                CompileMode::Bytecode,
+                            )?),
+                        ))
+                    }
+                    PythonModuleBytecodeProvider::Provided(_) => Ok(provider.clone()),
+                }
+            };
+
+        for provider in [
+            &mut res.in_memory_bytecode,
+            &mut res.in_memory_bytecode_opt1,
+            &mut res.in_memory_bytecode_opt2,
+        ]
+        .iter_mut()
+        {
+            if let Some(p) = provider {
+                *p = strip_bytecode(p)?;
+            }
+        }
+
+        for entry in [
+            &mut res.relative_path_bytecode,
+            &mut res.relative_path_bytecode_opt1,
+            &mut res.relative_path_bytecode_opt2,
+        ]
+        .iter_mut()
+        {
+            if let Some((_, _, p)) = entry {
+                *p = strip_bytecode(p)?;
+            }
+        }
+
+        Ok(res)
+    }
+}
+
+/// Record the provenance of content added to an entry.
+///
+/// The first known provenance is retained, as an entry's content usually
+/// originates from a single place.
+fn record_provenance(entry: &mut PrePackagedResource, provenance: &Option<ResourceProvenance>) {
+    if entry.provenance.is_none() {
+        entry.provenance = provenance.as_ref().map(|p| p.to_string());
     }
 }
 
@@ -369,6 +473,9 @@ impl PrePackagedResource {
 /// a particular field, we populate that field in all its parent
 /// packages. If a corresponding fields is already populated, we
 /// copy its data as well.
+///
+/// Parent packages not having a module of their own are registered as
+/// PEP 420 namespace packages and don't receive any data.
 pub fn populate_parent_packages(
     resources: &mut BTreeMap<String, PrePackagedResource>,
 ) -> Result<()> {
@@ -399,12 +506,28 @@ pub fn populate_parent_packages(
                 .or_insert_with(|| PrePackagedResource {
                     flavor: ResourceFlavor::Module,
                     name: package,
+                    is_namespace_package: true,
                     ..PrePackagedResource::default()
                 });
 
             // Parents must be packages by definition.
             entry.is_package = true;
 
+            // Parents without a module of their own (no `__init__.py`) are
+            // PEP 420 namespace packages. There is no code to materialize for
+            // these: the importer synthesizes the module at import time.
+            let is_code_less = match entry.flavor {
+                ResourceFlavor::None | ResourceFlavor::Module => !entry.has_module_code(),
+                _ => false,
+            };
+            if is_code_less {
+                entry.flavor = ResourceFlavor::Module;
+                entry.is_namespace_package = true;
+            }
+            if entry.is_namespace_package {
+                continue;
+            }
+
             // We want to materialize bytecode on parent packages no matter
             // what. If the original resource has a variant of bytecode in a
             // location, we materialize that variant on parents. We take
@@ -519,11 +642,14 @@ pub enum AbstractResourceLocation {
 }
 
 /// Describes the concrete location of a Python resource.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum ConcreteResourceLocation {
     /// Resource is loaded from memory.
     InMemory,
     /// Reosurce is loaded from a relative filesystem path.
+    ///
+    /// The path is relative to the binary. If the path is absolute, it is
+    /// used as-is.
     RelativePath(String),
 }
 
@@ -540,27 +666,155 @@ impl From<&ConcreteResourceLocation> for AbstractResourceLocation {
 ///
 /// Instances are produced from a `PythonResourceCollector` and a
 /// `PythonBytecodeCompiler` to produce bytecode.
+///
+/// Bytecode loaded from memory is compiled when the collection is produced.
+/// Other resource data stays at its `DataLocation` and is only read when
+/// resources are resolved, which packed resources writers do one resource
+/// at a time.
 #[derive(Clone, Debug, Default)]
-pub struct CompiledResourcesCollection<'a> {
-    pub resources: BTreeMap<String, Resource<'a, u8>>,
+pub struct CompiledResourcesCollection {
+    /// Resources sorted by name.
+    resources: Vec<PrePackagedResource>,
     pub extra_files: Vec<FileInstall>,
 }
 
-impl<'a> CompiledResourcesCollection<'a> {
+/// Resource fields whose data is stored in the blob sections of packed resources data.
+const BLOB_RESOURCE_FIELDS: &[ResourceField] = &[
+    ResourceField::ModuleName,
+    ResourceField::InMemorySource,
+    ResourceField::InMemoryBytecode,
+    ResourceField::InMemoryBytecodeOpt1,
+    ResourceField::InMemoryBytecodeOpt2,
+    ResourceField::InMemoryExtensionModuleSharedLibrary,
+    ResourceField::InMemoryResourcesData,
+    ResourceField::InMemoryDistributionResource,
+    ResourceField::InMemorySharedLibrary,
+    ResourceField::SharedLibraryDependencyNames,
+    ResourceField::RelativeFilesystemModuleSource,
+    ResourceField::RelativeFilesystemModuleBytecode,
+    ResourceField::RelativeFilesystemModuleBytecodeOpt1,
+    ResourceField::RelativeFilesystemModuleBytecodeOpt2,
+    ResourceField::RelativeFilesystemExtensionModuleSharedLibrary,
+    ResourceField::RelativeFilesystemPackageResources,
+    ResourceField::RelativeFilesystemDistributionResource,
+];
+
+impl ResourceSource for CompiledResourcesCollection {
+    fn resources_count(&self) -> usize {
+        self.resources.len()
+    }
+
+    fn resource(&self, index: usize) -> Result<Cow<'_, Resource<'_, u8>>> {
+        let resource = self
+            .resources
+            .get(index)
+            .ok_or_else(|| anyhow!("resource index out of range"))?;
+
+        Ok(Cow::Owned(resource.to_packed_resource()?))
+    }
+}
+
+impl CompiledResourcesCollection {
+    /// Obtain the names of resources in this collection, sorted.
+    pub fn resource_names(&self) -> impl Iterator<Item = &str> {
+        self.resources.iter().map(|resource| resource.name.as_str())
+    }
+
+    /// Resolve every resource in this collection, keyed by name.
+    ///
+    /// This holds the data of all resources in memory. Writing packed
+    /// resources data doesn't need to.
+    pub fn resolve_resources<'a>(&self) -> Result<BTreeMap<String, Resource<'a, u8>>> {
+        self.resources
+            .iter()
+            .map(|resource| Ok((resource.name.clone(), resource.to_packed_resource()?)))
+            .collect()
+    }
+
+    /// Compute the number of bytes each resource contributes to packed resources data.
+    ///
+    /// Only blob data is counted. Index entries are excluded.
+    pub fn packed_resource_sizes(&self) -> Result<BTreeMap<String, u64>> {
+        self.resources
+            .iter()
+            .map(|resource| {
+                let resolved = resource.to_packed_resource()?;
+
+                let size: usize = BLOB_RESOURCE_FIELDS
+                    .iter()
+                    .map(|field| resolved.field_blob_length(*field))
+                    .sum();
+
+                Ok((resource.name.clone(), size as u64))
+            })
+            .collect()
+    }
+
+    /// Compute the number of bytes contributed to packed resources data by provenance.
+    ///
+    /// This attributes packed data to whatever provided it, e.g. a package
+    /// distribution. Resources without a known provenance are grouped under
+    /// `unknown`. Only blob data is counted.
+    pub fn packed_resource_sizes_by_provenance(&self) -> Result<BTreeMap<String, u64>> {
+        let sizes = self.packed_resource_sizes()?;
+
+        let mut res = BTreeMap::new();
+        for resource in &self.resources {
+            let provenance = match &resource.provenance {
+                Some(provenance) => provenance.to_string(),
+                None => "unknown".to_string(),
+            };
+
+            *res.entry(provenance).or_insert(0) += sizes.get(&resource.name).cloned().unwrap_or(0);
+        }
+
+        Ok(res)
+    }
+
     /// Write resources to packed resources data, version 1.
-    pub fn write_packed_resources_v1<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
-        python_packed_resources::writer::write_packed_resources_v1(
-            &self
-                .resources
-                .values()
-                .cloned()
-                .collect::<Vec<Resource<'a, u8>>>(),
-            writer,
-            None,
+    ///
+    /// Resources are resolved one at a time as they are written.
+    ///
+    /// Returns reports describing how much data was deduplicated and compressed.
+    pub fn write_packed_resources_v1<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+        options: &WriteOptions,
+    ) -> Result<(BlobDeduplicationReport, BlobCompressionReport)> {
+        python_packed_resources::writer::write_packed_resources_v1_with_options(
+            self, writer, options,
         )
     }
 }
 
+/// Validate that a resource in the specified location is allowed by a policy.
+fn check_policy_location(
+    policy: &PythonResourcesPolicy,
+    location: AbstractResourceLocation,
+) -> Result<()> {
+    match policy {
+        PythonResourcesPolicy::InMemoryOnly => match location {
+            AbstractResourceLocation::InMemory => Ok(()),
+            AbstractResourceLocation::RelativePath => Err(anyhow!(
+                "in-memory-only policy does not allow relative path resources"
+            )),
+        },
+        PythonResourcesPolicy::FilesystemRelativeOnly(_) => match location {
+            AbstractResourceLocation::InMemory => Err(anyhow!(
+                "filesystem-relative-only policy does not allow in-memory resources"
+            )),
+            AbstractResourceLocation::RelativePath => Ok(()),
+        },
+        PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative(_) => Ok(()),
+        PythonResourcesPolicy::FilesystemAbsolute(_) => match location {
+            AbstractResourceLocation::InMemory => Err(anyhow!(
+                "filesystem-absolute policy does not allow in-memory resources"
+            )),
+            AbstractResourceLocation::RelativePath => Ok(()),
+        },
+    }
+}
+
 /// Type used to collect Python resources so they can be serialized.
 ///
 /// We often want to turn Python resource primitives (module source,
@@ -570,8 +824,11 @@ impl<'a> CompiledResourcesCollection<'a> {
 #[derive(Debug, Clone)]
 pub struct PythonResourceCollector {
     policy: PythonResourcesPolicy,
+    package_policies: HashMap<String, PythonResourcesPolicy>,
+    extension_module_policy: Option<PythonResourcesPolicy>,
     resources: BTreeMap<String, PrePackagedResource>,
     cache_tag: String,
+    strip_docstrings_packages: BTreeSet<String>,
 }
 
 impl PythonResourceCollector {
@@ -585,8 +842,11 @@ impl PythonResourceCollector {
     pub fn new(policy: &PythonResourcesPolicy, cache_tag: &str) -> Self {
         Self {
             policy: policy.clone(),
+            package_policies: HashMap::new(),
+            extension_module_policy: None,
             resources: BTreeMap::new(),
             cache_tag: cache_tag.to_string(),
+            strip_docstrings_packages: BTreeSet::new(),
         }
     }
 
@@ -595,23 +855,63 @@ impl PythonResourceCollector {
         &self.policy
     }
 
+    /// Override the policy for resources in a specific package.
+    ///
+    /// The policy applies to the named package and all its sub-packages.
+    pub fn set_package_policy(&mut self, package: &str, policy: &PythonResourcesPolicy) {
+        self.package_policies
+            .insert(package.to_string(), policy.clone());
+    }
+
+    /// Obtain the policy applying to a named resource.
+    pub fn get_policy_for_name(&self, name: &str) -> &PythonResourcesPolicy {
+        find_package_entry(&self.package_policies, name).unwrap_or(&self.policy)
+    }
+
+    /// Override the policy for extension modules.
+    ///
+    /// Per-package policies take precedence over this policy.
+    pub fn set_extension_module_policy(&mut self, policy: &PythonResourcesPolicy) {
+        self.extension_module_policy = Some(policy.clone());
+    }
+
+    /// Obtain the policy applying to a named extension module.
+    pub fn get_extension_module_policy_for_name(&self, name: &str) -> &PythonResourcesPolicy {
+        find_package_entry(&self.package_policies, name)
+            .or_else(|| self.extension_module_policy.as_ref())
+            .unwrap_or(&self.policy)
+    }
+
+    /// Strip docstrings and comments from a package and its sub-packages.
+    ///
+    /// Stripping happens when resources are compiled.
+    pub fn add_strip_docstrings_package(&mut self, package: &str) {
+        self.strip_docstrings_packages.insert(package.to_string());
+    }
+
+    /// Whether docstrings and comments are stripped from the named module.
+    fn is_docstrings_stripped(&self, name: &str) -> bool {
+        self.strip_docstrings_packages.contains(name)
+            || packages_from_module_name(name)
+                .iter()
+                .any(|package| self.strip_docstrings_packages.contains(package))
+    }
+
     /// Validate that a resource add in the specified location is allowed.
-    pub fn check_policy(&self, location: AbstractResourceLocation) -> Result<()> {
-        match self.policy {
-            PythonResourcesPolicy::InMemoryOnly => match location {
-                AbstractResourceLocation::InMemory => Ok(()),
-                AbstractResourceLocation::RelativePath => Err(anyhow!(
-                    "in-memory-only policy does not allow relative path resources"
-                )),
-            },
-            PythonResourcesPolicy::FilesystemRelativeOnly(_) => match location {
-                AbstractResourceLocation::InMemory => Err(anyhow!(
-                    "filesystem-relative-only policy does not allow in-memory resources"
-                )),
-                AbstractResourceLocation::RelativePath => Ok(()),
-            },
-            PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative(_) => Ok(()),
-        }
+    ///
+    /// `name` is the name of the module or package the resource belongs to
+    /// and is used to resolve per-package policy overrides.
+    pub fn check_policy(&self, name: &str, location: AbstractResourceLocation) -> Result<()> {
+        check_policy_location(self.get_policy_for_name(name), location)
+    }
+
+    /// Validate that an extension module add in the specified location is allowed.
+    pub fn check_extension_module_policy(
+        &self,
+        name: &str,
+        location: AbstractResourceLocation,
+    ) -> Result<()> {
+        check_policy_location(self.get_extension_module_policy_for_name(name), location)
     }
 
     /// Apply a filter function on resources in this collection and mutate in place.
@@ -643,7 +943,7 @@ impl PythonResourceCollector {
         module: &PythonModuleSource,
         location: &ConcreteResourceLocation,
     ) -> Result<()> {
-        self.check_policy(location.into())?;
+        self.check_policy(&module.name, location.into())?;
 
         let entry = self
             .resources
@@ -654,6 +954,7 @@ impl PythonResourceCollector {
                 ..PrePackagedResource::default()
             });
         entry.is_package = module.is_package;
+        record_provenance(entry, &module.provenance);
 
         match location {
             ConcreteResourceLocation::InMemory => {
@@ -674,7 +975,7 @@ impl PythonResourceCollector {
         module: &PythonModuleBytecode,
         location: &ConcreteResourceLocation,
     ) -> Result<()> {
-        self.check_policy(location.into())?;
+        self.check_policy(&module.name, location.into())?;
 
         let entry = self
             .resources
@@ -686,6 +987,7 @@ impl PythonResourceCollector {
             });
 
         entry.is_package = module.is_package;
+        record_provenance(entry, &module.provenance);
 
         // TODO having to resolve the DataLocation here is a bit unfortunate.
         // We could invent a better type to allow the I/O to remain lazy.
@@ -730,7 +1032,7 @@ impl PythonResourceCollector {
         module: &PythonModuleBytecodeFromSource,
         location: &ConcreteResourceLocation,
     ) -> Result<()> {
-        self.check_policy(location.into())?;
+        self.check_policy(&module.name, location.into())?;
 
         let entry = self
             .resources
@@ -742,6 +1044,7 @@ impl PythonResourceCollector {
             });
 
         entry.is_package = module.is_package;
+        record_provenance(entry, &module.provenance);
 
         let bytecode = PythonModuleBytecodeProvider::FromSource(module.source.clone());
 
@@ -784,7 +1087,7 @@ impl PythonResourceCollector {
         resource: &PythonPackageResource,
         location: &ConcreteResourceLocation,
     ) -> Result<()> {
-        self.check_policy(location.into())?;
+        self.check_policy(&resource.leaf_package, location.into())?;
 
         let entry = self
             .resources
@@ -797,6 +1100,7 @@ impl PythonResourceCollector {
 
         // Adding a resource automatically makes the module a package.
         entry.is_package = true;
+        record_provenance(entry, &resource.provenance);
 
         match location {
             ConcreteResourceLocation::InMemory => {
@@ -834,19 +1138,24 @@ impl PythonResourceCollector {
         resource: &PythonPackageDistributionResource,
         location: &ConcreteResourceLocation,
     ) -> Result<()> {
-        self.check_policy(location.into())?;
+        self.check_policy(&resource.package, location.into())?;
+
+        // Distributions are indexed by the name `importlib.metadata` looks
+        // them up by.
+        let package = resource.normalized_package();
 
         let entry = self
             .resources
-            .entry(resource.package.clone())
+            .entry(package.clone())
             .or_insert_with(|| PrePackagedResource {
                 flavor: ResourceFlavor::Module,
-                name: resource.package.clone(),
+                name: package,
                 ..PrePackagedResource::default()
             });
 
         // A distribution resource makes the entity a package.
         entry.is_package = true;
+        record_provenance(entry, &resource.provenance);
 
         match location {
             ConcreteResourceLocation::InMemory => {
@@ -897,6 +1206,7 @@ impl PythonResourceCollector {
             });
 
         entry.is_package = module.is_package;
+        record_provenance(entry, &module.provenance);
 
         Ok(())
     }
@@ -907,7 +1217,7 @@ impl PythonResourceCollector {
         module: &PythonExtensionModule,
         location: &ConcreteResourceLocation,
     ) -> Result<()> {
-        self.check_policy(location.into())?;
+        self.check_extension_module_policy(&module.name, location.into())?;
 
         let data = match &module.shared_library {
             Some(location) => location.resolve()?,
@@ -932,7 +1242,8 @@ impl PythonResourceCollector {
                     }
                 };
 
-                self.add_shared_library(&link.name, shared_library, &library_location)?;
+                // The policy was validated against the extension module above.
+                self.insert_shared_library(&link.name, shared_library, &library_location);
                 depends.push(link.name.to_string());
             }
         }
@@ -949,6 +1260,7 @@ impl PythonResourceCollector {
         if module.is_package {
             entry.is_package = true;
         }
+        record_provenance(entry, &module.provenance);
 
         match location {
             ConcreteResourceLocation::InMemory => {
@@ -972,8 +1284,18 @@ impl PythonResourceCollector {
         data: &DataLocation,
         location: &ConcreteResourceLocation,
     ) -> Result<()> {
-        self.check_policy(location.into())?;
+        self.check_policy(name, location.into())?;
+        self.insert_shared_library(name, data, location);
 
+        Ok(())
+    }
+
+    fn insert_shared_library(
+        &mut self,
+        name: &str,
+        data: &DataLocation,
+        location: &ConcreteResourceLocation,
+    ) {
         let entry = self
             .resources
             .entry(name.to_string())
@@ -991,8 +1313,6 @@ impl PythonResourceCollector {
                 entry.relative_path_shared_library = Some((prefix.to_string(), data.clone()));
             }
         }
-
-        Ok(())
     }
 
     /// Searches for Python sources for references to __file__.
@@ -1037,10 +1357,95 @@ impl PythonResourceCollector {
         Ok(res)
     }
 
+    /// Searches Python sources for package data files they read.
+    ///
+    /// Reads via `pkgutil.get_data()` and `importlib.resources` are detected
+    /// with `find_package_data_references()`. Returns a mapping of module
+    /// name to the package data files it references.
+    pub fn find_package_data_references(
+        &self,
+    ) -> Result<BTreeMap<String, Vec<PackageDataReference>>> {
+        let mut res = BTreeMap::new();
+
+        for (name, module) in &self.resources {
+            fn bytecode_source(
+                provider: Option<&PythonModuleBytecodeProvider>,
+            ) -> Option<&DataLocation> {
+                match provider {
+                    Some(PythonModuleBytecodeProvider::FromSource(location)) => Some(location),
+                    _ => None,
+                }
+            }
+
+            let source = module
+                .in_memory_source
+                .as_ref()
+                .or_else(|| {
+                    module
+                        .relative_path_module_source
+                        .as_ref()
+                        .map(|(_, location)| location)
+                })
+                .or_else(|| bytecode_source(module.in_memory_bytecode.as_ref()))
+                .or_else(|| bytecode_source(module.in_memory_bytecode_opt1.as_ref()))
+                .or_else(|| bytecode_source(module.in_memory_bytecode_opt2.as_ref()))
+                .or_else(|| {
+                    bytecode_source(module.relative_path_bytecode.as_ref().map(|(_, _, p)| p))
+                })
+                .or_else(|| {
+                    bytecode_source(
+                        module
+                            .relative_path_bytecode_opt1
+                            .as_ref()
+                            .map(|(_, _, p)| p),
+                    )
+                })
+                .or_else(|| {
+                    bytecode_source(
+                        module
+                            .relative_path_bytecode_opt2
+                            .as_ref()
+                            .map(|(_, _, p)| p),
+                    )
+                });
+
+            if let Some(location) = source {
+                let references =
+                    find_package_data_references(name, module.is_package, &location.resolve()?)?;
+
+                if !references.is_empty() {
+                    res.insert(name.clone(), references);
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Whether the package data file a source reference reads is present in this collection.
+    pub fn has_package_data(&self, reference: &PackageDataReference) -> bool {
+        reference
+            .resource_locations()
+            .iter()
+            .any(|(package, name)| match self.resources.get(package) {
+                Some(entry) => {
+                    entry
+                        .in_memory_resources
+                        .as_ref()
+                        .map_or(false, |resources| resources.contains_key(name))
+                        || entry
+                            .relative_path_package_resources
+                            .as_ref()
+                            .map_or(false, |resources| resources.contains_key(name))
+                }
+                None => false,
+            })
+    }
+
     /// Compiles resources into a finalized collection.
     ///
-    /// This will take all resources collected so far and convert them into
-    /// a collection of `Resource` plus extra file install rules.
+    /// This will take all resources collected so far, compile their bytecode
+    /// and derive the extra file install rules they need.
     ///
     /// Missing parent packages will be added automatically.
     pub fn compile_resources(
@@ -1050,17 +1455,18 @@ impl PythonResourceCollector {
         let mut input_resources = self.resources.clone();
         populate_parent_packages(&mut input_resources)?;
 
-        let mut resources = BTreeMap::new();
+        let mut resources = Vec::new();
         let mut extra_files = Vec::new();
 
         for (name, resource) in &input_resources {
-            let (entry, installs) = resource.to_resource(compiler)?;
-
-            for install in installs {
-                extra_files.push(install);
-            }
+            let resource = if self.is_docstrings_stripped(name) {
+                resource.strip_docstrings(compiler)?
+            } else {
+                resource.clone()
+            };
 
-            resources.insert(name.clone(), entry);
+            extra_files.extend(resource.file_installs(compiler)?);
+            resources.push(resource.compile_bytecode(compiler)?);
         }
 
         Ok(CompiledResourcesCollection {
@@ -1108,6 +1514,13 @@ mod tests {
 
             Ok(res)
         }
+
+        fn strip_docstrings(&mut self, source: &[u8], _filename: &str) -> Result<Vec<u8>> {
+            let mut res = b"stripped:".to_vec();
+            res.extend(source);
+
+            Ok(res)
+        }
     }
 
     #[test]
@@ -1124,6 +1537,11 @@ mod tests {
             PythonResourcesPolicy::try_from("prefer-in-memory-fallback-filesystem-relative:lib")?,
             PythonResourcesPolicy::PreferInMemoryFallbackFilesystemRelative("lib".to_string())
         );
+        assert_eq!(
+            PythonResourcesPolicy::try_from("filesystem-absolute:/usr/lib/myapp")?,
+            PythonResourcesPolicy::FilesystemAbsolute(PathBuf::from("/usr/lib/myapp"))
+        );
+        assert!(PythonResourcesPolicy::try_from("filesystem-absolute:lib").is_err());
         assert_eq!(
             PythonResourcesPolicy::try_from("foo")
                 .unwrap_err()
@@ -2010,7 +2428,7 @@ mod tests {
                 flavor: ResourceFlavor::Module,
                 name: "root.parent".to_string(),
                 is_package: true,
-                in_memory_source: Some(DataLocation::Memory(vec![])),
+                is_namespace_package: true,
                 ..PrePackagedResource::default()
             })
         );
@@ -2020,7 +2438,7 @@ mod tests {
                 flavor: ResourceFlavor::Module,
                 name: "root".to_string(),
                 is_package: true,
-                in_memory_source: Some(DataLocation::Memory(vec![])),
+                is_namespace_package: true,
                 ..PrePackagedResource::default()
             })
         );
@@ -2054,10 +2472,7 @@ mod tests {
                 flavor: ResourceFlavor::Module,
                 name: "root.parent".to_string(),
                 is_package: true,
-                relative_path_module_source: Some((
-                    "prefix".to_string(),
-                    DataLocation::Memory(vec![])
-                )),
+                is_namespace_package: true,
                 ..PrePackagedResource::default()
             })
         );
@@ -2067,10 +2482,7 @@ mod tests {
                 flavor: ResourceFlavor::Module,
                 name: "root".to_string(),
                 is_package: true,
-                relative_path_module_source: Some((
-                    "prefix".to_string(),
-                    DataLocation::Memory(vec![])
-                )),
+                is_namespace_package: true,
                 ..PrePackagedResource::default()
             })
         );
@@ -2103,9 +2515,7 @@ mod tests {
                 flavor: ResourceFlavor::Module,
                 name: "root.parent".to_string(),
                 is_package: true,
-                in_memory_bytecode: Some(PythonModuleBytecodeProvider::FromSource(
-                    DataLocation::Memory(vec![])
-                )),
+                is_namespace_package: true,
                 ..PrePackagedResource::default()
             })
         );
@@ -2115,9 +2525,7 @@ mod tests {
                 flavor: ResourceFlavor::Module,
                 name: "root".to_string(),
                 is_package: true,
-                in_memory_bytecode: Some(PythonModuleBytecodeProvider::FromSource(
-                    DataLocation::Memory(vec![])
-                )),
+                is_namespace_package: true,
                 ..PrePackagedResource::default()
             })
         );
@@ -2149,6 +2557,7 @@ mod tests {
                 flavor: ResourceFlavor::Module,
                 name: "foo".to_string(),
                 is_package: true,
+                is_namespace_package: true,
                 ..PrePackagedResource::default()
             })
         );
@@ -2182,6 +2591,54 @@ mod tests {
                 flavor: ResourceFlavor::Module,
                 name: "foo".to_string(),
                 is_package: true,
+                is_namespace_package: true,
+                ..PrePackagedResource::default()
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_populate_parent_packages_namespace_package_resources() -> Result<()> {
+        let resources = Some(BTreeMap::from_iter(
+            [("resource.txt".to_string(), DataLocation::Memory(vec![42]))]
+                .iter()
+                .cloned(),
+        ));
+
+        let mut h = BTreeMap::new();
+        h.insert(
+            "foo".to_string(),
+            PrePackagedResource {
+                flavor: ResourceFlavor::Module,
+                name: "foo".to_string(),
+                is_package: true,
+                in_memory_resources: resources.clone(),
+                ..PrePackagedResource::default()
+            },
+        );
+        h.insert(
+            "foo.bar".to_string(),
+            PrePackagedResource {
+                flavor: ResourceFlavor::Module,
+                name: "foo.bar".to_string(),
+                in_memory_source: Some(DataLocation::Memory(vec![42])),
+                ..PrePackagedResource::default()
+            },
+        );
+
+        populate_parent_packages(&mut h)?;
+
+        assert_eq!(h.len(), 2);
+        assert_eq!(
+            h.get("foo"),
+            Some(&PrePackagedResource {
+                flavor: ResourceFlavor::Module,
+                name: "foo".to_string(),
+                is_package: true,
+                is_namespace_package: true,
+                in_memory_resources: resources,
                 ..PrePackagedResource::default()
             })
         );
@@ -2201,6 +2658,7 @@ mod tests {
                 cache_tag: DEFAULT_CACHE_TAG.to_string(),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             },
             &ConcreteResourceLocation::InMemory,
         )?;
@@ -2221,9 +2679,9 @@ mod tests {
 
         let resources = r.compile_resources(&mut compiler)?;
 
-        assert_eq!(resources.resources.len(), 1);
+        assert_eq!(resources.resolve_resources()?.len(), 1);
         assert_eq!(
-            resources.resources.get("foo"),
+            resources.resolve_resources()?.get("foo"),
             Some(&Resource {
                 flavor: ResourceFlavor::Module,
                 name: Cow::Owned("foo".to_string()),
@@ -2236,6 +2694,54 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_strip_docstrings() -> Result<()> {
+        let mut r =
+            PythonResourceCollector::new(&PythonResourcesPolicy::InMemoryOnly, DEFAULT_CACHE_TAG);
+        r.add_strip_docstrings_package("foo");
+
+        for name in &["foo.bar", "other"] {
+            let module = PythonModuleSource {
+                name: name.to_string(),
+                source: DataLocation::Memory(b"src".to_vec()),
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+                provenance: None,
+            };
+
+            r.add_python_module_source(&module, &ConcreteResourceLocation::InMemory)?;
+            r.add_python_module_bytecode_from_source(
+                &module.as_bytecode_module(BytecodeOptimizationLevel::Zero),
+                &ConcreteResourceLocation::InMemory,
+            )?;
+        }
+
+        let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
+
+        let resources = r.compile_resources(&mut compiler)?.resolve_resources()?;
+
+        let stripped = resources.get("foo.bar").unwrap();
+        assert_eq!(
+            stripped.in_memory_source,
+            Some(Cow::Owned(b"stripped:src".to_vec()))
+        );
+        assert_eq!(
+            stripped.in_memory_bytecode,
+            Some(Cow::Owned(b"bc2stripped:src".to_vec()))
+        );
+
+        let other = resources.get("other").unwrap();
+        assert_eq!(other.in_memory_source, Some(Cow::Owned(b"src".to_vec())));
+        assert_eq!(
+            other.in_memory_bytecode,
+            Some(Cow::Owned(b"bc0src".to_vec()))
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_add_in_memory_source_module_parents() -> Result<()> {
         let mut r =
@@ -2248,6 +2754,7 @@ mod tests {
                 cache_tag: DEFAULT_CACHE_TAG.to_string(),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             },
             &ConcreteResourceLocation::InMemory,
         )?;
@@ -2268,29 +2775,29 @@ mod tests {
 
         let resources = r.compile_resources(&mut compiler)?;
 
-        assert_eq!(resources.resources.len(), 3);
+        assert_eq!(resources.resolve_resources()?.len(), 3);
         assert_eq!(
-            resources.resources.get("root"),
+            resources.resolve_resources()?.get("root"),
             Some(&Resource {
                 flavor: ResourceFlavor::Module,
                 name: Cow::Owned("root".to_string()),
                 is_package: true,
-                in_memory_source: Some(Cow::Owned(vec![])),
+                is_namespace_package: true,
                 ..Resource::default()
             })
         );
         assert_eq!(
-            resources.resources.get("root.parent"),
+            resources.resolve_resources()?.get("root.parent"),
             Some(&Resource {
                 flavor: ResourceFlavor::Module,
                 name: Cow::Owned("root.parent".to_string()),
                 is_package: true,
-                in_memory_source: Some(Cow::Owned(vec![])),
+                is_namespace_package: true,
                 ..Resource::default()
             })
         );
         assert_eq!(
-            resources.resources.get("root.parent.child"),
+            resources.resolve_resources()?.get("root.parent.child"),
             Some(&Resource {
                 flavor: ResourceFlavor::Module,
                 name: Cow::Owned("root.parent.child".to_string()),
@@ -2319,6 +2826,7 @@ mod tests {
                 cache_tag: DEFAULT_CACHE_TAG.to_string(),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             },
             &ConcreteResourceLocation::RelativePath("prefix".to_string()),
         )?;
@@ -2342,21 +2850,19 @@ mod tests {
 
         let resources = r.compile_resources(&mut compiler)?;
 
-        assert_eq!(resources.resources.len(), 2);
+        assert_eq!(resources.resolve_resources()?.len(), 2);
         assert_eq!(
-            resources.resources.get("foo"),
+            resources.resolve_resources()?.get("foo"),
             Some(&Resource {
                 flavor: ResourceFlavor::Module,
                 name: Cow::Owned("foo".to_string()),
                 is_package: true,
-                relative_path_module_source: Some(Cow::Owned(PathBuf::from(
-                    "prefix/foo/__init__.py"
-                ))),
+                is_namespace_package: true,
                 ..Resource::default()
             })
         );
         assert_eq!(
-            resources.resources.get("foo.bar"),
+            resources.resolve_resources()?.get("foo.bar"),
             Some(&Resource {
                 flavor: ResourceFlavor::Module,
                 name: Cow::Owned("foo.bar".to_string()),
@@ -2366,18 +2872,11 @@ mod tests {
         );
         assert_eq!(
             resources.extra_files,
-            vec![
-                (
-                    PathBuf::from("prefix/foo/__init__.py"),
-                    DataLocation::Memory(vec![]),
-                    false
-                ),
-                (
-                    PathBuf::from("prefix/foo/bar.py"),
-                    DataLocation::Memory(vec![42]),
-                    false
-                )
-            ]
+            vec![(
+                PathBuf::from("prefix/foo/bar.py"),
+                DataLocation::Memory(vec![42]),
+                false
+            )]
         );
 
         Ok(())
@@ -2416,9 +2915,9 @@ mod tests {
 
         let resources = r.compile_resources(&mut compiler)?;
 
-        assert_eq!(resources.resources.len(), 1);
+        assert_eq!(resources.resolve_resources()?.len(), 1);
         assert_eq!(
-            resources.resources.get("foo"),
+            resources.resolve_resources()?.get("foo"),
             Some(&Resource {
                 flavor: ResourceFlavor::Module,
                 name: Cow::Owned("foo".to_string()),
@@ -2444,6 +2943,7 @@ mod tests {
                 cache_tag: DEFAULT_CACHE_TAG.to_string(),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             },
             &ConcreteResourceLocation::InMemory,
         )?;
@@ -2466,9 +2966,9 @@ mod tests {
 
         let resources = r.compile_resources(&mut compiler)?;
 
-        assert_eq!(resources.resources.len(), 1);
+        assert_eq!(resources.resolve_resources()?.len(), 1);
         assert_eq!(
-            resources.resources.get("foo"),
+            resources.resolve_resources()?.get("foo"),
             Some(&Resource {
                 flavor: ResourceFlavor::Module,
                 name: Cow::Owned("foo".to_string()),
@@ -2494,6 +2994,7 @@ mod tests {
                 cache_tag: DEFAULT_CACHE_TAG.to_string(),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             },
             &ConcreteResourceLocation::InMemory,
         )?;
@@ -2516,29 +3017,29 @@ mod tests {
 
         let resources = r.compile_resources(&mut compiler)?;
 
-        assert_eq!(resources.resources.len(), 3);
+        assert_eq!(resources.resolve_resources()?.len(), 3);
         assert_eq!(
-            resources.resources.get("root"),
+            resources.resolve_resources()?.get("root"),
             Some(&Resource {
                 flavor: ResourceFlavor::Module,
                 name: Cow::Owned("root".to_string()),
                 is_package: true,
-                in_memory_bytecode_opt1: Some(Cow::Owned(b"bc1".to_vec())),
+                is_namespace_package: true,
                 ..Resource::default()
             })
         );
         assert_eq!(
-            resources.resources.get("root.parent"),
+            resources.resolve_resources()?.get("root.parent"),
             Some(&Resource {
                 flavor: ResourceFlavor::Module,
                 name: Cow::Owned("root.parent".to_string()),
                 is_package: true,
-                in_memory_bytecode_opt1: Some(Cow::Owned(b"bc1".to_vec())),
+                is_namespace_package: true,
                 ..Resource::default()
             })
         );
         assert_eq!(
-            resources.resources.get("root.parent.child"),
+            resources.resolve_resources()?.get("root.parent.child"),
             Some(&Resource {
                 flavor: ResourceFlavor::Module,
                 name: Cow::Owned("root.parent.child".to_string()),
@@ -2563,6 +3064,7 @@ mod tests {
                 data: DataLocation::Memory(vec![42]),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             },
             &ConcreteResourceLocation::InMemory,
         )?;
@@ -2587,9 +3089,9 @@ mod tests {
 
         let resources = r.compile_resources(&mut compiler)?;
 
-        assert_eq!(resources.resources.len(), 1);
+        assert_eq!(resources.resolve_resources()?.len(), 1);
         assert_eq!(
-            resources.resources.get("foo"),
+            resources.resolve_resources()?.get("foo"),
             Some(&Resource {
                 flavor: ResourceFlavor::Module,
                 name: Cow::Owned("foo".to_string()),
@@ -2620,6 +3122,7 @@ mod tests {
                 data: DataLocation::Memory(vec![42]),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             },
             &ConcreteResourceLocation::RelativePath("prefix".to_string()),
         )?;
@@ -2650,9 +3153,9 @@ mod tests {
 
         let resources = r.compile_resources(&mut compiler)?;
 
-        assert_eq!(resources.resources.len(), 1);
+        assert_eq!(resources.resolve_resources()?.len(), 1);
         assert_eq!(
-            resources.resources.get("foo"),
+            resources.resolve_resources()?.get("foo"),
             Some(&Resource {
                 flavor: ResourceFlavor::Module,
                 name: Cow::Owned("foo".to_string()),
@@ -2691,6 +3194,7 @@ mod tests {
                 version: "1.0".to_string(),
                 name: "resource.txt".to_string(),
                 data: DataLocation::Memory(vec![42]),
+                provenance: None,
             },
             &ConcreteResourceLocation::InMemory,
         )?;
@@ -2715,9 +3219,9 @@ mod tests {
 
         let resources = r.compile_resources(&mut compiler)?;
 
-        assert_eq!(resources.resources.len(), 1);
+        assert_eq!(resources.resolve_resources()?.len(), 1);
         assert_eq!(
-            resources.resources.get("mypackage"),
+            resources.resolve_resources()?.get("mypackage"),
             Some(&Resource {
                 flavor: ResourceFlavor::Module,
                 name: Cow::Owned("mypackage".to_string()),
@@ -2735,6 +3239,41 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_add_package_distribution_resource_normalized_name() -> Result<()> {
+        let mut r =
+            PythonResourceCollector::new(&PythonResourcesPolicy::InMemoryOnly, DEFAULT_CACHE_TAG);
+        r.add_package_distribution_resource(
+            &PythonPackageDistributionResource {
+                location: PythonPackageDistributionResourceFlavor::DistInfo,
+                package: "My-Package".to_string(),
+                version: "1.0".to_string(),
+                name: "METADATA".to_string(),
+                data: DataLocation::Memory(vec![42]),
+                provenance: None,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+
+        assert_eq!(r.resources.len(), 1);
+        assert_eq!(
+            r.resources.get("my_package"),
+            Some(&PrePackagedResource {
+                flavor: ResourceFlavor::Module,
+                name: "my_package".to_string(),
+                is_package: true,
+                in_memory_distribution_resources: Some(BTreeMap::from_iter(
+                    [("METADATA".to_string(), DataLocation::Memory(vec![42]))]
+                        .iter()
+                        .cloned()
+                )),
+                ..PrePackagedResource::default()
+            })
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_add_relative_path_package_distribution_resource() -> Result<()> {
         let mut r = PythonResourceCollector::new(
@@ -2748,6 +3287,7 @@ mod tests {
                 version: "1.0".to_string(),
                 name: "resource.txt".to_string(),
                 data: DataLocation::Memory(vec![42]),
+                provenance: None,
             },
             &ConcreteResourceLocation::RelativePath("prefix".to_string()),
         )?;
@@ -2778,9 +3318,9 @@ mod tests {
 
         let resources = r.compile_resources(&mut compiler)?;
 
-        assert_eq!(resources.resources.len(), 1);
+        assert_eq!(resources.resolve_resources()?.len(), 1);
         assert_eq!(
-            resources.resources.get("mypackage"),
+            resources.resolve_resources()?.get("mypackage"),
             Some(&Resource {
                 flavor: ResourceFlavor::Module,
                 name: Cow::Owned("mypackage".to_string()),
@@ -2828,6 +3368,7 @@ mod tests {
             licenses: None,
             license_texts: None,
             license_public_domain: None,
+            provenance: None,
         };
 
         c.add_builtin_python_extension_module(&em)?;
@@ -2845,9 +3386,9 @@ mod tests {
 
         let resources = c.compile_resources(&mut compiler)?;
 
-        assert_eq!(resources.resources.len(), 1);
+        assert_eq!(resources.resolve_resources()?.len(), 1);
         assert_eq!(
-            resources.resources.get("_io"),
+            resources.resolve_resources()?.get("_io"),
             Some(&Resource {
                 flavor: ResourceFlavor::BuiltinExtensionModule,
                 name: Cow::Owned("_io".to_string()),
@@ -2885,6 +3426,7 @@ mod tests {
             licenses: None,
             license_texts: None,
             license_public_domain: None,
+            provenance: None,
         };
 
         c.add_python_extension_module(&em, &ConcreteResourceLocation::InMemory)?;
@@ -2913,9 +3455,9 @@ mod tests {
 
         let resources = c.compile_resources(&mut compiler)?;
 
-        assert_eq!(resources.resources.len(), 2);
+        assert_eq!(resources.resolve_resources()?.len(), 2);
         assert_eq!(
-            resources.resources.get("myext"),
+            resources.resolve_resources()?.get("myext"),
             Some(&Resource {
                 flavor: ResourceFlavor::Extension,
                 name: Cow::Owned("myext".to_string()),
@@ -2925,7 +3467,7 @@ mod tests {
             })
         );
         assert_eq!(
-            resources.resources.get("foo"),
+            resources.resolve_resources()?.get("foo"),
             Some(&Resource {
                 flavor: ResourceFlavor::SharedLibrary,
                 name: Cow::Owned("foo".to_string()),
@@ -2967,6 +3509,7 @@ mod tests {
             licenses: None,
             license_texts: None,
             license_public_domain: None,
+            provenance: None,
         };
 
         c.add_python_extension_module(
@@ -3005,18 +3548,19 @@ mod tests {
 
         let resources = c.compile_resources(&mut compiler)?;
 
-        assert_eq!(resources.resources.len(), 3);
+        assert_eq!(resources.resolve_resources()?.len(), 3);
         assert_eq!(
-            resources.resources.get("foo"),
+            resources.resolve_resources()?.get("foo"),
             Some(&Resource {
                 flavor: ResourceFlavor::Module,
                 name: Cow::Owned("foo".to_string()),
                 is_package: true,
+                is_namespace_package: true,
                 ..Resource::default()
             })
         );
         assert_eq!(
-            resources.resources.get("foo.bar"),
+            resources.resolve_resources()?.get("foo.bar"),
             Some(&Resource {
                 flavor: ResourceFlavor::Extension,
                 name: Cow::Owned("foo.bar".to_string()),
@@ -3029,7 +3573,7 @@ mod tests {
             })
         );
         assert_eq!(
-            resources.resources.get("mylib"),
+            resources.resolve_resources()?.get("mylib"),
             Some(&Resource {
                 flavor: ResourceFlavor::SharedLibrary,
                 name: Cow::Owned("mylib".to_string()),
@@ -3056,6 +3600,115 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_package_policy_override() -> Result<()> {
+        let mut r =
+            PythonResourceCollector::new(&PythonResourcesPolicy::InMemoryOnly, DEFAULT_CACHE_TAG);
+        r.set_package_policy(
+            "foo",
+            &PythonResourcesPolicy::FilesystemRelativeOnly("prefix".to_string()),
+        );
+
+        let module = PythonModuleSource {
+            name: "foo.bar".to_string(),
+            source: DataLocation::Memory(vec![42]),
+            is_package: false,
+            cache_tag: DEFAULT_CACHE_TAG.to_string(),
+            is_stdlib: false,
+            is_test: false,
+            provenance: None,
+        };
+
+        r.add_python_module_source(
+            &module,
+            &ConcreteResourceLocation::RelativePath("prefix".to_string()),
+        )?;
+        assert!(r
+            .add_python_module_source(&module, &ConcreteResourceLocation::InMemory)
+            .is_err());
+
+        let module = PythonModuleSource {
+            name: "bar".to_string(),
+            ..module
+        };
+
+        r.add_python_module_source(&module, &ConcreteResourceLocation::InMemory)?;
+        assert!(r
+            .add_python_module_source(
+                &module,
+                &ConcreteResourceLocation::RelativePath("prefix".to_string())
+            )
+            .is_err());
+
+        assert_eq!(r.resources.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extension_module_policy_override() -> Result<()> {
+        let mut r =
+            PythonResourceCollector::new(&PythonResourcesPolicy::InMemoryOnly, DEFAULT_CACHE_TAG);
+        r.set_extension_module_policy(&PythonResourcesPolicy::FilesystemRelativeOnly(
+            "lib".to_string(),
+        ));
+        r.set_package_policy("foo", &PythonResourcesPolicy::InMemoryOnly);
+
+        let em = PythonExtensionModule {
+            name: "myext".to_string(),
+            init_fn: Some("PyInit_myext".to_string()),
+            extension_file_suffix: ".so".to_string(),
+            shared_library: Some(DataLocation::Memory(vec![42])),
+            object_file_data: vec![],
+            is_package: false,
+            link_libraries: vec![],
+            is_stdlib: false,
+            builtin_default: false,
+            required: false,
+            variant: None,
+            licenses: None,
+            license_texts: None,
+            license_public_domain: None,
+            provenance: None,
+        };
+
+        assert!(r
+            .add_python_extension_module(&em, &ConcreteResourceLocation::InMemory)
+            .is_err());
+        r.add_python_extension_module(
+            &em,
+            &ConcreteResourceLocation::RelativePath("lib".to_string()),
+        )?;
+
+        // Per-package policies take precedence.
+        let em = PythonExtensionModule {
+            name: "foo.myext".to_string(),
+            ..em
+        };
+        r.add_python_extension_module(&em, &ConcreteResourceLocation::InMemory)?;
+
+        // Other resources follow the default policy.
+        let module = PythonModuleSource {
+            name: "bar".to_string(),
+            source: DataLocation::Memory(vec![42]),
+            is_package: false,
+            cache_tag: DEFAULT_CACHE_TAG.to_string(),
+            is_stdlib: false,
+            is_test: false,
+            provenance: None,
+        };
+        assert!(r
+            .add_python_module_source(
+                &module,
+                &ConcreteResourceLocation::RelativePath("lib".to_string())
+            )
+            .is_err());
+
+        assert_eq!(r.resources.len(), 2);
+
+        Ok(())
+    }
+
     #[test]
     fn test_find_dunder_file() -> Result<()> {
         let mut r =
@@ -3070,6 +3723,7 @@ mod tests {
                 cache_tag: DEFAULT_CACHE_TAG.to_string(),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             },
             &ConcreteResourceLocation::InMemory,
         )?;
@@ -3083,6 +3737,7 @@ mod tests {
                 cache_tag: DEFAULT_CACHE_TAG.to_string(),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             },
             &ConcreteResourceLocation::InMemory,
         )?;
@@ -3098,6 +3753,7 @@ mod tests {
                 cache_tag: DEFAULT_CACHE_TAG.to_string(),
                 is_stdlib: false,
                 is_test: false,
+                provenance: None,
             },
             &ConcreteResourceLocation::InMemory,
         )?;
@@ -3106,4 +3762,82 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_find_package_data_references() -> Result<()> {
+        let mut r =
+            PythonResourceCollector::new(&PythonResourcesPolicy::InMemoryOnly, DEFAULT_CACHE_TAG);
+
+        let module = |name: &str, is_package: bool, source: &str| PythonModuleSource {
+            name: name.to_string(),
+            source: DataLocation::Memory(Vec::from(source)),
+            is_package,
+            cache_tag: DEFAULT_CACHE_TAG.to_string(),
+            is_stdlib: false,
+            is_test: false,
+            provenance: None,
+        };
+
+        r.add_python_module_source(
+            &module(
+                "foo",
+                true,
+                "import pkgutil\ndata = pkgutil.get_data(__name__, 'data/a.json')",
+            ),
+            &ConcreteResourceLocation::InMemory,
+        )?;
+        r.add_python_module_source(
+            &module(
+                "foo.bar",
+                false,
+                "from importlib import resources\nresources.read_text(__package__, \"b.txt\")\nresources.files('other') / 'c.bin'\nresources.read_text(name, 'dynamic.txt')",
+            ),
+            &ConcreteResourceLocation::InMemory,
+        )?;
+        r.add_python_module_source(
+            &module("baz", false, "get_data(__name__, 'unrelated.txt')"),
+            &ConcreteResourceLocation::InMemory,
+        )?;
+
+        let references = r.find_package_data_references()?;
+        assert_eq!(references.len(), 2);
+        assert_eq!(
+            references.get("foo"),
+            Some(&vec![PackageDataReference {
+                package: "foo".to_string(),
+                resource: "data/a.json".to_string(),
+            }])
+        );
+        assert_eq!(
+            references.get("foo.bar"),
+            Some(&vec![
+                PackageDataReference {
+                    package: "foo".to_string(),
+                    resource: "b.txt".to_string(),
+                },
+                PackageDataReference {
+                    package: "other".to_string(),
+                    resource: "c.bin".to_string(),
+                },
+            ])
+        );
+
+        let reference = &references.get("foo").unwrap()[0];
+        assert!(!r.has_package_data(reference));
+
+        r.add_python_package_resource(
+            &PythonPackageResource {
+                leaf_package: "foo.data".to_string(),
+                relative_name: "a.json".to_string(),
+                data: DataLocation::Memory(vec![]),
+                is_stdlib: false,
+                is_test: false,
+                provenance: None,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+        assert!(r.has_package_data(reference));
+
+        Ok(())
+    }
 }
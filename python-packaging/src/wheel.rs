@@ -0,0 +1,935 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Reading Python wheel files.
+
+This module implements support for obtaining Python resources directly
+from `.whl` archives, without invoking `pip` or any other Python tooling.
+*/
+
+use {
+    crate::filesystem_scanning::{find_python_resources, matches_normal_component},
+    crate::module_util::PythonModuleSuffixes,
+    crate::resource::{
+        DataFileInstallLocation, DataLocation, PythonDataFile, PythonResource, ResourceProvenance,
+    },
+    anyhow::{anyhow, Context, Result},
+    std::convert::TryFrom,
+    std::io::{Cursor, Read},
+    std::path::{Path, PathBuf},
+};
+
+/// Represents the parsed components of a wheel filename.
+///
+/// Wheel filenames have the form
+/// `{distribution}-{version}(-{build})?-{python}-{abi}-{platform}.whl`.
+/// Each tag component can be a `.` delimited set of compressed tags.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WheelFilename {
+    pub distribution: String,
+    pub version: String,
+    pub build: Option<String>,
+    pub python_tags: Vec<String>,
+    pub abi_tags: Vec<String>,
+    pub platform_tags: Vec<String>,
+}
+
+impl TryFrom<&str> for WheelFilename {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        if !value.ends_with(".whl") {
+            return Err(anyhow!("wheel filename does not end in .whl: {}", value));
+        }
+
+        let stem = &value[0..value.len() - ".whl".len()];
+        let parts = stem.split('-').collect::<Vec<_>>();
+
+        let (build, tags) = match parts.len() {
+            5 => (None, &parts[2..]),
+            6 => (Some(parts[2].to_string()), &parts[3..]),
+            _ => return Err(anyhow!("malformed wheel filename: {}", value)),
+        };
+
+        if parts.iter().any(|p| p.is_empty()) {
+            return Err(anyhow!("malformed wheel filename: {}", value));
+        }
+
+        let split_tags = |s: &str| s.split('.').map(|t| t.to_string()).collect::<Vec<_>>();
+
+        Ok(Self {
+            distribution: parts[0].to_string(),
+            version: parts[1].to_string(),
+            build,
+            python_tags: split_tags(tags[0]),
+            abi_tags: split_tags(tags[1]),
+            platform_tags: split_tags(tags[2]),
+        })
+    }
+}
+
+impl std::fmt::Display for WheelFilename {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}-{}-", self.distribution, self.version)?;
+        if let Some(build) = &self.build {
+            write!(f, "{}-", build)?;
+        }
+        write!(
+            f,
+            "{}-{}-{}.whl",
+            self.python_tags.join("."),
+            self.abi_tags.join("."),
+            self.platform_tags.join(".")
+        )
+    }
+}
+
+impl WheelFilename {
+    /// Whether a wheel with these tags can be used with a given target.
+    ///
+    /// `python_version` is the `X.Y` version of the target Python interpreter.
+    /// Free-threaded interpreters have a `t` suffix. e.g. `3.13t`.
+    pub fn is_compatible(&self, target_triple: &str, python_version: &str) -> bool {
+        // Free-threaded interpreters can't load extensions targeting the
+        // stable ABI.
+        let stable_abi =
+            !is_free_threaded_version(python_version) && self.abi_tags.iter().any(|t| t == "abi3");
+
+        self.python_tags.iter().any(|t| {
+            is_python_tag_compatible(t, python_version)
+                || (stable_abi && is_stable_abi_python_tag_compatible(t, python_version))
+        }) && self
+            .abi_tags
+            .iter()
+            .any(|t| is_abi_tag_compatible(t, python_version))
+            && self
+                .platform_tags
+                .iter()
+                .any(|t| is_platform_tag_compatible(t, target_triple))
+    }
+
+    /// Name of the `.dist-info` directory in the wheel.
+    pub fn dist_info_dir(&self) -> String {
+        format!("{}-{}.dist-info", self.distribution, self.version)
+    }
+
+    /// Name of the `.data` directory in the wheel.
+    pub fn data_dir(&self) -> String {
+        format!("{}-{}.data", self.distribution, self.version)
+    }
+
+    /// Obtain the individual tags this filename's compressed tag sets expand to.
+    pub fn tags(&self) -> Vec<WheelTag> {
+        let mut tags = vec![];
+
+        for python in &self.python_tags {
+            for abi in &self.abi_tags {
+                for platform in &self.platform_tags {
+                    tags.push(WheelTag {
+                        python: python.clone(),
+                        abi: abi.clone(),
+                        platform: platform.clone(),
+                    });
+                }
+            }
+        }
+
+        tags
+    }
+}
+
+/// Whether a `X.Y` Python version string denotes a free-threaded interpreter.
+///
+/// Free-threaded interpreters have a `t` suffix. e.g. `3.13t`.
+pub fn is_free_threaded_version(python_version: &str) -> bool {
+    python_version.ends_with('t')
+}
+
+/// Obtain the `XY` form of a `X.Y` Python version string.
+fn python_version_nodot(python_version: &str) -> Option<(String, u32)> {
+    let python_version = python_version.trim_end_matches('t');
+    let mut parts = python_version.split('.');
+    let major = parts.next()?;
+    let minor = parts.next()?;
+
+    Some((major.to_string(), minor.parse::<u32>().ok()?))
+}
+
+/// Whether a wheel Python tag is compatible with a Python version.
+pub fn is_python_tag_compatible(tag: &str, python_version: &str) -> bool {
+    let (major, minor) = match python_version_nodot(python_version) {
+        Some(v) => v,
+        None => return false,
+    };
+
+    let version = if tag.starts_with("py") || tag.starts_with("cp") {
+        &tag[2..]
+    } else {
+        return false;
+    };
+
+    if version == major {
+        // A tag of just the major version is only meaningful for generic
+        // Python tags.
+        tag.starts_with("py")
+    } else {
+        version == format!("{}{}", major, minor)
+    }
+}
+
+/// Whether a CPython tag of a wheel targeting the stable ABI is compatible.
+///
+/// Extensions built against the stable ABI of a CPython version can be
+/// loaded by all subsequent versions.
+fn is_stable_abi_python_tag_compatible(tag: &str, python_version: &str) -> bool {
+    let (major, minor) = match python_version_nodot(python_version) {
+        Some(v) => v,
+        None => return false,
+    };
+
+    if !tag.starts_with("cp") || !tag[2..].starts_with(&major) {
+        return false;
+    }
+
+    match tag[2 + major.len()..].parse::<u32>() {
+        Ok(tag_minor) => tag_minor <= minor,
+        Err(_) => false,
+    }
+}
+
+/// Whether a wheel ABI tag is compatible with a Python version.
+pub fn is_abi_tag_compatible(tag: &str, python_version: &str) -> bool {
+    let (major, minor) = match python_version_nodot(python_version) {
+        Some(v) => v,
+        None => return false,
+    };
+
+    let cpython = format!("cp{}{}", major, minor);
+
+    if tag == "none" {
+        return true;
+    } else if is_free_threaded_version(python_version) {
+        return tag == format!("{}t", cpython);
+    } else if tag == format!("abi{}", major) {
+        return true;
+    }
+
+    // Python 3.7 and older have an `m` suffix for pymalloc builds.
+    tag == cpython || tag == format!("{}m", cpython)
+}
+
+/// Whether a wheel platform tag is compatible with a Rust target triple.
+pub fn is_platform_tag_compatible(tag: &str, target_triple: &str) -> bool {
+    if tag == "any" {
+        return true;
+    }
+
+    match target_triple {
+        "x86_64-unknown-linux-gnu" => {
+            tag == "linux_x86_64" || (tag.starts_with("manylinux") && tag.ends_with("_x86_64"))
+        }
+        "i686-unknown-linux-gnu" => {
+            tag == "linux_i686" || (tag.starts_with("manylinux") && tag.ends_with("_i686"))
+        }
+        "x86_64-unknown-linux-musl" => {
+            tag == "linux_x86_64" || (tag.starts_with("musllinux") && tag.ends_with("_x86_64"))
+        }
+        "x86_64-apple-darwin" => {
+            tag.starts_with("macosx_")
+                && (tag.ends_with("_x86_64")
+                    || tag.ends_with("_intel")
+                    || tag.ends_with("_universal")
+                    || tag.ends_with("_universal2"))
+        }
+        "aarch64-apple-darwin" => {
+            tag.starts_with("macosx_") && (tag.ends_with("_arm64") || tag.ends_with("_universal2"))
+        }
+        "x86_64-pc-windows-msvc" => tag == "win_amd64",
+        "i686-pc-windows-msvc" => tag == "win32",
+        _ => false,
+    }
+}
+
+/// Obtain the glibc version required by a `manylinux` platform tag.
+///
+/// Returns `None` if the tag isn't a `manylinux` tag.
+pub fn manylinux_glibc_version(tag: &str) -> Option<(u32, u32)> {
+    if !tag.starts_with("manylinux") {
+        return None;
+    }
+
+    let version = &tag["manylinux".len()..];
+
+    if version.starts_with("1_") {
+        Some((2, 5))
+    } else if version.starts_with("2010_") {
+        Some((2, 12))
+    } else if version.starts_with("2014_") {
+        Some((2, 17))
+    } else if version.starts_with('_') {
+        // PEP 600 tags have the form `manylinux_{glibc major}_{glibc minor}_{arch}`.
+        let mut parts = version[1..].splitn(3, '_');
+        let major = parts.next()?.parse::<u32>().ok()?;
+        let minor = parts.next()?.parse::<u32>().ok()?;
+
+        Some((major, minor))
+    } else {
+        None
+    }
+}
+
+/// Obtain the minimum macOS version required by a `macosx` platform tag.
+///
+/// Returns `None` if the tag isn't a `macosx` tag.
+pub fn macos_platform_version(tag: &str) -> Option<(u32, u32)> {
+    if !tag.starts_with("macosx_") {
+        return None;
+    }
+
+    let mut parts = tag["macosx_".len()..].splitn(3, '_');
+    let major = parts.next()?.parse::<u32>().ok()?;
+    let minor = parts.next()?.parse::<u32>().ok()?;
+
+    Some((major, minor))
+}
+
+/// A single `{python}-{abi}-{platform}` wheel tag.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WheelTag {
+    pub python: String,
+    pub abi: String,
+    pub platform: String,
+}
+
+impl TryFrom<&str> for WheelTag {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        let parts = value.split('-').collect::<Vec<_>>();
+
+        if parts.len() != 3 || parts.iter().any(|p| p.is_empty()) {
+            return Err(anyhow!("malformed wheel tag: {}", value));
+        }
+
+        Ok(Self {
+            python: parts[0].to_string(),
+            abi: parts[1].to_string(),
+            platform: parts[2].to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for WheelTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}-{}-{}", self.python, self.abi, self.platform)
+    }
+}
+
+/// Obtain the tags declared by the content of a wheel's `WHEEL` metadata file.
+pub fn wheel_metadata_tags(data: &str) -> Result<Vec<WheelTag>> {
+    data.lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, ':');
+            let key = parts.next()?.trim();
+            let value = parts.next()?.trim();
+
+            if key == "Tag" {
+                Some(WheelTag::try_from(value))
+            } else {
+                None
+            }
+        })
+        .collect::<Result<Vec<_>>>()
+}
+
+/// Describes the machines a binary embedding Python needs to run on.
+///
+/// Used to validate that extension modules from binary wheels can be loaded
+/// by the binary, not just by the machine installing them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WheelTarget {
+    /// Rust target triple of the binary.
+    pub target_triple: String,
+
+    /// `X.Y` version of the embedded Python interpreter.
+    ///
+    /// Free-threaded interpreters have a `t` suffix. e.g. `3.13t`.
+    pub python_version: String,
+
+    /// Oldest glibc version the binary supports.
+    ///
+    /// Only defined for Linux glibc targets.
+    pub glibc_version: Option<(u32, u32)>,
+
+    /// Oldest macOS version the binary supports.
+    ///
+    /// Only defined for macOS targets.
+    pub macos_version: Option<(u32, u32)>,
+}
+
+impl WheelTarget {
+    /// Describe why a wheel tag can't be used by this target.
+    ///
+    /// Returns `None` if the tag is compatible.
+    pub fn tag_incompatibility(&self, tag: &WheelTag) -> Option<String> {
+        let free_threaded = is_free_threaded_version(&self.python_version);
+
+        // Free-threaded interpreters can't load extensions targeting the
+        // stable ABI.
+        let python_compatible = is_python_tag_compatible(&tag.python, &self.python_version)
+            || (!free_threaded
+                && tag.abi == "abi3"
+                && is_stable_abi_python_tag_compatible(&tag.python, &self.python_version));
+
+        if !python_compatible {
+            return Some(format!(
+                "{} is for another Python version than {}",
+                tag, self.python_version
+            ));
+        }
+
+        if !is_abi_tag_compatible(&tag.abi, &self.python_version) {
+            return Some(format!(
+                "{} uses ABI {}, which Python {} doesn't provide",
+                tag, tag.abi, self.python_version
+            ));
+        }
+
+        if !is_platform_tag_compatible(&tag.platform, &self.target_triple) {
+            return Some(format!(
+                "{} is for platform {}, not {}",
+                tag, tag.platform, self.target_triple
+            ));
+        }
+
+        if let (Some(required), Some(supported)) =
+            (manylinux_glibc_version(&tag.platform), self.glibc_version)
+        {
+            if required > supported {
+                return Some(format!(
+                    "{} requires glibc {}.{} but binaries for {} support glibc {}.{}",
+                    tag, required.0, required.1, self.target_triple, supported.0, supported.1
+                ));
+            }
+        }
+
+        if let (Some(required), Some(supported)) =
+            (macos_platform_version(&tag.platform), self.macos_version)
+        {
+            if required > supported {
+                return Some(format!(
+                    "{} requires macOS {}.{} but binaries for {} support macOS {}.{}",
+                    tag, required.0, required.1, self.target_triple, supported.0, supported.1
+                ));
+            }
+        }
+
+        None
+    }
+
+    /// Ensure a wheel having the given tags can be used by this target.
+    ///
+    /// A wheel can be used if any of its tags is compatible. Errors describe
+    /// why each tag is incompatible.
+    pub fn check_tags(&self, tags: &[WheelTag]) -> Result<()> {
+        let mut reasons = vec![];
+
+        for tag in tags {
+            match self.tag_incompatibility(tag) {
+                Some(reason) => reasons.push(reason),
+                None => return Ok(()),
+            }
+        }
+
+        if reasons.is_empty() {
+            Err(anyhow!("wheel does not declare any tags"))
+        } else {
+            Err(anyhow!("{}", reasons.join("; ")))
+        }
+    }
+}
+
+/// Represents a Python wheel file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PythonWheel {
+    /// Parsed filename of the wheel.
+    pub filename: WheelFilename,
+    /// Content of the .whl file.
+    pub data: DataLocation,
+}
+
+impl PythonWheel {
+    /// Construct an instance from a filesystem path.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let filename = path
+            .file_name()
+            .ok_or_else(|| anyhow!("unable to resolve filename of {}", path.display()))?
+            .to_string_lossy();
+
+        Ok(Self {
+            filename: WheelFilename::try_from(filename.as_ref())?,
+            data: DataLocation::Path(path.to_path_buf()),
+        })
+    }
+
+    /// Whether this wheel can be used with a given target.
+    pub fn is_compatible(&self, target_triple: &str, python_version: &str) -> bool {
+        self.filename.is_compatible(target_triple, python_version)
+    }
+
+    /// Resolve Python resources within this wheel.
+    ///
+    /// Files in the `purelib` and `platlib` directories of the `.data`
+    /// directory are installed alongside regular package content. Files in
+    /// the `data`, `scripts` and `headers` directories are emitted as
+    /// `PythonResource::DataFile`, after all other resources.
+    ///
+    /// All returned resources are backed by memory.
+    pub fn python_resources(
+        &self,
+        cache_tag: &str,
+        suffixes: &PythonModuleSuffixes,
+    ) -> Result<Vec<PythonResource>> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(self.data.resolve()?))
+            .context("reading .whl file as a zip archive")?;
+
+        let dist_info_dir = PathBuf::from(self.filename.dist_info_dir());
+        let data_dir = PathBuf::from(self.filename.data_dir());
+
+        let temp_dir = tempdir::TempDir::new("python-packaging-wheel")?;
+        let mut seen_wheel = false;
+
+        let provenance = ResourceProvenance::Wheel(self.filename.to_string());
+        let mut data_files = vec![];
+
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i)?;
+
+            if file.is_dir() {
+                continue;
+            }
+
+            let rel_path = PathBuf::from(file.name());
+            if !rel_path.components().all(|c| matches_normal_component(&c)) {
+                return Err(anyhow!(
+                    ".whl file contains entry with unsupported path: {}",
+                    file.name()
+                ));
+            }
+
+            if rel_path == dist_info_dir.join("WHEEL") {
+                let mut data = String::new();
+                file.read_to_string(&mut data)?;
+                verify_wheel_metadata(&data)?;
+                seen_wheel = true;
+
+                let dest_path = temp_dir.path().join(&rel_path);
+                std::fs::create_dir_all(&temp_dir.path().join(&dist_info_dir))?;
+                std::fs::write(&dest_path, data.as_bytes())?;
+                continue;
+            }
+
+            let rel_path = if let Ok(p) = rel_path.strip_prefix(&data_dir) {
+                if let Ok(p) = p.strip_prefix("purelib") {
+                    p.to_path_buf()
+                } else if let Ok(p) = p.strip_prefix("platlib") {
+                    p.to_path_buf()
+                } else {
+                    let mut components = p.components();
+
+                    let location = components
+                        .next()
+                        .and_then(|c| c.as_os_str().to_str())
+                        .and_then(|c| DataFileInstallLocation::try_from(c).ok());
+
+                    // Unknown install locations are ignored.
+                    if let Some(location) = location {
+                        let mut data = vec![];
+                        file.read_to_end(&mut data)?;
+
+                        data_files.push(PythonResource::DataFile(PythonDataFile {
+                            package: self.filename.distribution.clone(),
+                            location,
+                            relative_path: components.as_path().to_path_buf(),
+                            data: DataLocation::Memory(data),
+                            executable: location == DataFileInstallLocation::Scripts,
+                            provenance: Some(provenance.clone()),
+                        }));
+                    }
+
+                    continue;
+                }
+            } else {
+                rel_path
+            };
+
+            let dest_path = temp_dir.path().join(&rel_path);
+            if let Some(parent) = dest_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            let mut fh = std::fs::File::create(&dest_path)?;
+            std::io::copy(&mut file, &mut fh)
+                .with_context(|| format!("extracting {} from .whl file", file.name()))?;
+        }
+
+        if !seen_wheel {
+            return Err(anyhow!(
+                ".whl file does not contain {}/WHEEL",
+                dist_info_dir.display()
+            ));
+        }
+
+        let mut res = find_python_resources(temp_dir.path(), cache_tag, suffixes)
+            .map(|r| {
+                let mut r = r?;
+                r.set_provenance(Some(provenance.clone()));
+                r.to_memory()
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        res.extend(data_files);
+
+        Ok(res)
+    }
+}
+
+/// Verify the content of a wheel's `WHEEL` metadata file is supported.
+fn verify_wheel_metadata(data: &str) -> Result<()> {
+    let version = data
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, ':');
+            let key = parts.next()?.trim();
+            let value = parts.next()?.trim();
+
+            if key == "Wheel-Version" {
+                Some(value)
+            } else {
+                None
+            }
+        })
+        .next()
+        .ok_or_else(|| anyhow!("WHEEL file does not define Wheel-Version"))?;
+
+    if version.split('.').next() != Some("1") {
+        return Err(anyhow!("unsupported wheel version: {}", version));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::module_util::PythonModuleSuffixes,
+        crate::resource::{PythonModuleSource, PythonPackageDistributionResourceFlavor},
+        lazy_static::lazy_static,
+        std::io::Write,
+    };
+
+    const DEFAULT_CACHE_TAG: &str = "cpython-37";
+
+    lazy_static! {
+        static ref DEFAULT_SUFFIXES: PythonModuleSuffixes = PythonModuleSuffixes {
+            source: vec![".py".to_string()],
+            bytecode: vec![".pyc".to_string()],
+            debug_bytecode: vec![],
+            optimized_bytecode: vec![],
+            extension: vec![],
+        };
+    }
+
+    #[test]
+    fn test_parse_filename() -> Result<()> {
+        let f = WheelFilename::try_from("foo-1.0-py2.py3-none-any.whl")?;
+        assert_eq!(f.distribution, "foo");
+        assert_eq!(f.version, "1.0");
+        assert_eq!(f.build, None);
+        assert_eq!(f.python_tags, vec!["py2", "py3"]);
+        assert_eq!(f.abi_tags, vec!["none"]);
+        assert_eq!(f.platform_tags, vec!["any"]);
+
+        let f = WheelFilename::try_from(
+            "numpy-1.18.1-1-cp37-cp37m-manylinux1_x86_64.manylinux2010_x86_64.whl",
+        )?;
+        assert_eq!(f.build, Some("1".to_string()));
+        assert_eq!(f.python_tags, vec!["cp37"]);
+        assert_eq!(f.abi_tags, vec!["cp37m"]);
+        assert_eq!(
+            f.platform_tags,
+            vec!["manylinux1_x86_64", "manylinux2010_x86_64"]
+        );
+
+        assert!(WheelFilename::try_from("foo-1.0.tar.gz").is_err());
+        assert!(WheelFilename::try_from("foo-1.0-any.whl").is_err());
+
+        for name in &["foo-1.0-py3-none-any.whl", "foo-1.0-1-py2.py3-none-any.whl"] {
+            assert_eq!(WheelFilename::try_from(*name)?.to_string(), *name);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compatibility() -> Result<()> {
+        let f = WheelFilename::try_from("foo-1.0-py3-none-any.whl")?;
+        assert!(f.is_compatible("x86_64-unknown-linux-gnu", "3.7"));
+        assert!(f.is_compatible("x86_64-pc-windows-msvc", "3.8"));
+
+        let f = WheelFilename::try_from("foo-1.0-cp37-cp37m-manylinux2010_x86_64.whl")?;
+        assert!(f.is_compatible("x86_64-unknown-linux-gnu", "3.7"));
+        assert!(!f.is_compatible("x86_64-unknown-linux-gnu", "3.8"));
+        assert!(!f.is_compatible("x86_64-unknown-linux-musl", "3.7"));
+        assert!(!f.is_compatible("x86_64-apple-darwin", "3.7"));
+
+        let f = WheelFilename::try_from("foo-1.0-cp36-abi3-win_amd64.whl")?;
+        assert!(f.is_compatible("x86_64-pc-windows-msvc", "3.6"));
+        assert!(f.is_compatible("x86_64-pc-windows-msvc", "3.7"));
+        assert!(!f.is_compatible("x86_64-pc-windows-msvc", "3.5"));
+        assert!(!f.is_compatible("i686-pc-windows-msvc", "3.6"));
+
+        let f = WheelFilename::try_from("foo-1.0-cp38-cp38-macosx_10_9_x86_64.whl")?;
+        assert!(f.is_compatible("x86_64-apple-darwin", "3.8"));
+        assert!(!f.is_compatible("x86_64-pc-windows-msvc", "3.8"));
+        assert!(!f.is_compatible("aarch64-apple-darwin", "3.8"));
+
+        let f = WheelFilename::try_from("foo-1.0-cp38-cp38-macosx_11_0_arm64.whl")?;
+        assert!(f.is_compatible("aarch64-apple-darwin", "3.8"));
+        assert!(!f.is_compatible("x86_64-apple-darwin", "3.8"));
+
+        let f = WheelFilename::try_from("foo-1.0-cp38-cp38-macosx_10_9_universal2.whl")?;
+        assert!(f.is_compatible("aarch64-apple-darwin", "3.8"));
+        assert!(f.is_compatible("x86_64-apple-darwin", "3.8"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_platform_versions() {
+        assert_eq!(manylinux_glibc_version("manylinux1_x86_64"), Some((2, 5)));
+        assert_eq!(
+            manylinux_glibc_version("manylinux2010_x86_64"),
+            Some((2, 12))
+        );
+        assert_eq!(
+            manylinux_glibc_version("manylinux2014_x86_64"),
+            Some((2, 17))
+        );
+        assert_eq!(
+            manylinux_glibc_version("manylinux_2_28_x86_64"),
+            Some((2, 28))
+        );
+        assert_eq!(manylinux_glibc_version("linux_x86_64"), None);
+
+        assert_eq!(macos_platform_version("macosx_10_9_x86_64"), Some((10, 9)));
+        assert_eq!(macos_platform_version("macosx_11_0_arm64"), Some((11, 0)));
+        assert_eq!(macos_platform_version("win_amd64"), None);
+    }
+
+    #[test]
+    fn test_wheel_target() -> Result<()> {
+        let linux = WheelTarget {
+            target_triple: "x86_64-unknown-linux-gnu".to_string(),
+            python_version: "3.8".to_string(),
+            glibc_version: Some((2, 19)),
+            macos_version: None,
+        };
+
+        let f = WheelFilename::try_from(
+            "foo-1.0-cp38-cp38-manylinux1_x86_64.manylinux_2_24_x86_64.whl",
+        )?;
+        assert_eq!(f.tags().len(), 2);
+        linux.check_tags(&f.tags())?;
+
+        let f = WheelFilename::try_from("foo-1.0-cp38-cp38-manylinux_2_24_x86_64.whl")?;
+        assert_eq!(
+            linux.check_tags(&f.tags()).unwrap_err().to_string(),
+            "cp38-cp38-manylinux_2_24_x86_64 requires glibc 2.24 but binaries for x86_64-unknown-linux-gnu support glibc 2.19"
+        );
+
+        let f = WheelFilename::try_from("foo-1.0-cp37-cp37m-manylinux1_x86_64.whl")?;
+        assert_eq!(
+            linux.check_tags(&f.tags()).unwrap_err().to_string(),
+            "cp37-cp37m-manylinux1_x86_64 is for another Python version than 3.8"
+        );
+
+        let macos = WheelTarget {
+            target_triple: "x86_64-apple-darwin".to_string(),
+            python_version: "3.8".to_string(),
+            glibc_version: None,
+            macos_version: Some((10, 9)),
+        };
+
+        let f = WheelFilename::try_from("foo-1.0-cp38-cp38-macosx_10_9_x86_64.whl")?;
+        macos.check_tags(&f.tags())?;
+
+        let f = WheelFilename::try_from("foo-1.0-cp38-cp38-macosx_10_15_x86_64.whl")?;
+        assert_eq!(
+            macos.check_tags(&f.tags()).unwrap_err().to_string(),
+            "cp38-cp38-macosx_10_15_x86_64 requires macOS 10.15 but binaries for x86_64-apple-darwin support macOS 10.9"
+        );
+
+        let windows = WheelTarget {
+            target_triple: "x86_64-pc-windows-msvc".to_string(),
+            python_version: "3.8".to_string(),
+            glibc_version: None,
+            macos_version: None,
+        };
+
+        let tags = wheel_metadata_tags(
+            "Wheel-Version: 1.0\nRoot-Is-Purelib: false\nTag: cp38-cp38-win_arm64\n",
+        )?;
+        assert_eq!(
+            windows.check_tags(&tags).unwrap_err().to_string(),
+            "cp38-cp38-win_arm64 is for platform win_arm64, not x86_64-pc-windows-msvc"
+        );
+
+        let tags = wheel_metadata_tags("Wheel-Version: 1.0\nTag: py3-none-any\n")?;
+        windows.check_tags(&tags)?;
+        linux.check_tags(&tags)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_free_threaded_compatibility() -> Result<()> {
+        let f = WheelFilename::try_from("foo-1.0-cp313-cp313t-manylinux2014_x86_64.whl")?;
+        assert!(f.is_compatible("x86_64-unknown-linux-gnu", "3.13t"));
+        assert!(!f.is_compatible("x86_64-unknown-linux-gnu", "3.13"));
+
+        let f = WheelFilename::try_from("foo-1.0-cp313-cp313-manylinux2014_x86_64.whl")?;
+        assert!(f.is_compatible("x86_64-unknown-linux-gnu", "3.13"));
+        assert!(!f.is_compatible("x86_64-unknown-linux-gnu", "3.13t"));
+
+        let f = WheelFilename::try_from("foo-1.0-cp38-abi3-manylinux2014_x86_64.whl")?;
+        assert!(f.is_compatible("x86_64-unknown-linux-gnu", "3.13"));
+        assert!(!f.is_compatible("x86_64-unknown-linux-gnu", "3.13t"));
+
+        let f = WheelFilename::try_from("foo-1.0-py3-none-any.whl")?;
+        assert!(f.is_compatible("x86_64-unknown-linux-gnu", "3.13t"));
+
+        Ok(())
+    }
+
+    fn wheel_data(files: &[(&str, &[u8])]) -> Result<Vec<u8>> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default();
+        for (name, data) in files {
+            writer.start_file(*name, options)?;
+            writer.write_all(data)?;
+        }
+
+        Ok(writer.finish()?.into_inner())
+    }
+
+    #[test]
+    fn test_python_resources() -> Result<()> {
+        let data = wheel_data(&[
+            ("foo/__init__.py", b""),
+            ("foo-1.0.data/purelib/bar.py", b"# bar"),
+            ("foo-1.0.data/scripts/foo-script", b"#!python"),
+            (
+                "foo-1.0.dist-info/METADATA",
+                b"Metadata-Version: 2.1\nName: foo\nVersion: 1.0\n",
+            ),
+            ("foo-1.0.dist-info/WHEEL", b"Wheel-Version: 1.0\n"),
+        ])?;
+
+        let wheel = PythonWheel {
+            filename: WheelFilename::try_from("foo-1.0-py3-none-any.whl")?,
+            data: DataLocation::Memory(data),
+        };
+
+        let resources = wheel.python_resources(DEFAULT_CACHE_TAG, &DEFAULT_SUFFIXES)?;
+        assert_eq!(resources.len(), 5);
+
+        assert_eq!(
+            resources[0],
+            PythonResource::ModuleSource(PythonModuleSource {
+                name: "bar".to_string(),
+                source: DataLocation::Memory(b"# bar".to_vec()),
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+                provenance: Some(ResourceProvenance::Wheel(
+                    "foo-1.0-py3-none-any.whl".to_string()
+                )),
+            })
+        );
+
+        let dist_names = resources
+            .iter()
+            .filter_map(|r| match r {
+                PythonResource::DistributionResource(d) => {
+                    assert_eq!(
+                        d.location,
+                        PythonPackageDistributionResourceFlavor::DistInfo
+                    );
+                    Some(d.name.clone())
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(dist_names, vec!["METADATA", "WHEEL"]);
+
+        assert_eq!(
+            resources[1],
+            PythonResource::ModuleSource(PythonModuleSource {
+                name: "foo".to_string(),
+                source: DataLocation::Memory(vec![]),
+                is_package: true,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+                provenance: Some(ResourceProvenance::Wheel(
+                    "foo-1.0-py3-none-any.whl".to_string()
+                )),
+            })
+        );
+
+        assert_eq!(
+            resources[4],
+            PythonResource::DataFile(PythonDataFile {
+                package: "foo".to_string(),
+                location: DataFileInstallLocation::Scripts,
+                relative_path: PathBuf::from("foo-script"),
+                data: DataLocation::Memory(b"#!python".to_vec()),
+                executable: true,
+                provenance: Some(ResourceProvenance::Wheel(
+                    "foo-1.0-py3-none-any.whl".to_string()
+                )),
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported_wheel_version() -> Result<()> {
+        let data = wheel_data(&[("foo-1.0.dist-info/WHEEL", b"Wheel-Version: 2.0\n")])?;
+
+        let wheel = PythonWheel {
+            filename: WheelFilename::try_from("foo-1.0-py3-none-any.whl")?,
+            data: DataLocation::Memory(data),
+        };
+        assert!(wheel
+            .python_resources(DEFAULT_CACHE_TAG, &DEFAULT_SUFFIXES)
+            .is_err());
+
+        let data = wheel_data(&[("foo/__init__.py", b"")])?;
+        let wheel = PythonWheel {
+            filename: WheelFilename::try_from("foo-1.0-py3-none-any.whl")?,
+            data: DataLocation::Memory(data),
+        };
+        assert!(wheel
+            .python_resources(DEFAULT_CACHE_TAG, &DEFAULT_SUFFIXES)
+            .is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,514 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Python extension module reading and writing Python packed resources data.
+
+Resources are represented as `dict` whose keys are the names of the fields
+of `python_packed_resources::data::Resource`, the same names used by the
+attributes of `oxidized_importer.OxidizedResource`.
+*/
+
+use {
+    cpython::exc::ValueError,
+    cpython::{
+        py_fn, py_module_initializer, ObjectProtocol, PyBytes, PyDict, PyErr, PyList, PyObject,
+        PyResult, Python, PythonObject, ToPyObject,
+    },
+    python_packed_resources::{
+        data::{Resource, ResourceFlavor},
+        parser, writer,
+    },
+    std::borrow::Cow,
+    std::collections::HashMap,
+    std::path::{Path, PathBuf},
+};
+
+/// Names of the keys of `dict` representing resources.
+const RESOURCE_FIELDS: &[&str] = &[
+    "flavor",
+    "name",
+    "is_package",
+    "is_namespace_package",
+    "in_memory_source",
+    "in_memory_bytecode",
+    "in_memory_bytecode_opt1",
+    "in_memory_bytecode_opt2",
+    "in_memory_extension_module_shared_library",
+    "in_memory_package_resources",
+    "in_memory_distribution_resources",
+    "in_memory_shared_library",
+    "shared_library_dependency_names",
+    "relative_path_module_source",
+    "relative_path_module_bytecode",
+    "relative_path_module_bytecode_opt1",
+    "relative_path_module_bytecode_opt2",
+    "relative_path_extension_module_shared_library",
+    "relative_path_package_resources",
+    "relative_path_distribution_resources",
+    "provenance",
+];
+
+fn value_error(py: Python, message: impl AsRef<str>) -> PyErr {
+    PyErr::new::<ValueError, _>(py, message.as_ref())
+}
+
+fn flavor_to_str(flavor: ResourceFlavor) -> &'static str {
+    match flavor {
+        ResourceFlavor::None => "none",
+        ResourceFlavor::Module => "module",
+        ResourceFlavor::BuiltinExtensionModule => "builtin",
+        ResourceFlavor::FrozenModule => "frozen",
+        ResourceFlavor::Extension => "extension",
+        ResourceFlavor::SharedLibrary => "shared_library",
+    }
+}
+
+fn str_to_flavor(py: Python, value: &str) -> PyResult<ResourceFlavor> {
+    match value {
+        "none" => Ok(ResourceFlavor::None),
+        "module" => Ok(ResourceFlavor::Module),
+        "builtin" => Ok(ResourceFlavor::BuiltinExtensionModule),
+        "frozen" => Ok(ResourceFlavor::FrozenModule),
+        "extension" => Ok(ResourceFlavor::Extension),
+        "shared_library" => Ok(ResourceFlavor::SharedLibrary),
+        _ => Err(value_error(
+            py,
+            format!("unknown resource flavor: {}", value),
+        )),
+    }
+}
+
+fn bytes_to_pyobject(py: Python, value: &Option<Cow<[u8]>>) -> PyObject {
+    match value {
+        Some(data) => PyBytes::new(py, data).into_object(),
+        None => py.None(),
+    }
+}
+
+fn bytes_map_to_pyobject(
+    py: Python,
+    value: &Option<HashMap<Cow<str>, Cow<[u8]>>>,
+) -> PyResult<PyObject> {
+    match value {
+        Some(map) => {
+            let res = PyDict::new(py);
+
+            for (name, data) in map {
+                res.set_item(py, name.as_ref(), PyBytes::new(py, data))?;
+            }
+
+            Ok(res.into_object())
+        }
+        None => Ok(py.None()),
+    }
+}
+
+fn path_to_pyobject(py: Python, value: &Option<Cow<Path>>) -> PyObject {
+    match value {
+        Some(path) => path
+            .to_string_lossy()
+            .as_ref()
+            .to_py_object(py)
+            .into_object(),
+        None => py.None(),
+    }
+}
+
+fn path_map_to_pyobject(
+    py: Python,
+    value: &Option<HashMap<Cow<str>, Cow<Path>>>,
+) -> PyResult<PyObject> {
+    match value {
+        Some(map) => {
+            let res = PyDict::new(py);
+
+            for (name, path) in map {
+                res.set_item(py, name.as_ref(), path.to_string_lossy().as_ref())?;
+            }
+
+            Ok(res.into_object())
+        }
+        None => Ok(py.None()),
+    }
+}
+
+/// Convert a resource to a `dict`.
+fn resource_to_pyobject(py: Python, resource: &Resource<u8>) -> PyResult<PyObject> {
+    let res = PyDict::new(py);
+
+    res.set_item(py, "flavor", flavor_to_str(resource.flavor))?;
+    res.set_item(py, "name", resource.name.as_ref())?;
+    res.set_item(py, "is_package", resource.is_package)?;
+    res.set_item(py, "is_namespace_package", resource.is_namespace_package)?;
+    res.set_item(
+        py,
+        "in_memory_source",
+        bytes_to_pyobject(py, &resource.in_memory_source),
+    )?;
+    res.set_item(
+        py,
+        "in_memory_bytecode",
+        bytes_to_pyobject(py, &resource.in_memory_bytecode),
+    )?;
+    res.set_item(
+        py,
+        "in_memory_bytecode_opt1",
+        bytes_to_pyobject(py, &resource.in_memory_bytecode_opt1),
+    )?;
+    res.set_item(
+        py,
+        "in_memory_bytecode_opt2",
+        bytes_to_pyobject(py, &resource.in_memory_bytecode_opt2),
+    )?;
+    res.set_item(
+        py,
+        "in_memory_extension_module_shared_library",
+        bytes_to_pyobject(py, &resource.in_memory_extension_module_shared_library),
+    )?;
+    res.set_item(
+        py,
+        "in_memory_package_resources",
+        bytes_map_to_pyobject(py, &resource.in_memory_package_resources)?,
+    )?;
+    res.set_item(
+        py,
+        "in_memory_distribution_resources",
+        bytes_map_to_pyobject(py, &resource.in_memory_distribution_resources)?,
+    )?;
+    res.set_item(
+        py,
+        "in_memory_shared_library",
+        bytes_to_pyobject(py, &resource.in_memory_shared_library),
+    )?;
+    res.set_item(
+        py,
+        "shared_library_dependency_names",
+        match &resource.shared_library_dependency_names {
+            Some(names) => names
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>()
+                .to_py_object(py)
+                .into_object(),
+            None => py.None(),
+        },
+    )?;
+    res.set_item(
+        py,
+        "relative_path_module_source",
+        path_to_pyobject(py, &resource.relative_path_module_source),
+    )?;
+    res.set_item(
+        py,
+        "relative_path_module_bytecode",
+        path_to_pyobject(py, &resource.relative_path_module_bytecode),
+    )?;
+    res.set_item(
+        py,
+        "relative_path_module_bytecode_opt1",
+        path_to_pyobject(py, &resource.relative_path_module_bytecode_opt1),
+    )?;
+    res.set_item(
+        py,
+        "relative_path_module_bytecode_opt2",
+        path_to_pyobject(py, &resource.relative_path_module_bytecode_opt2),
+    )?;
+    res.set_item(
+        py,
+        "relative_path_extension_module_shared_library",
+        path_to_pyobject(py, &resource.relative_path_extension_module_shared_library),
+    )?;
+    res.set_item(
+        py,
+        "relative_path_package_resources",
+        path_map_to_pyobject(py, &resource.relative_path_package_resources)?,
+    )?;
+    res.set_item(
+        py,
+        "relative_path_distribution_resources",
+        path_map_to_pyobject(py, &resource.relative_path_distribution_resources)?,
+    )?;
+    res.set_item(
+        py,
+        "provenance",
+        match &resource.provenance {
+            Some(provenance) => provenance.as_ref().to_py_object(py).into_object(),
+            None => py.None(),
+        },
+    )?;
+
+    Ok(res.into_object())
+}
+
+fn pyobject_to_bytes(py: Python, value: &PyObject) -> PyResult<Cow<'static, [u8]>> {
+    Ok(Cow::Owned(value.cast_as::<PyBytes>(py)?.data(py).to_vec()))
+}
+
+fn pyobject_to_string(py: Python, value: &PyObject) -> PyResult<Cow<'static, str>> {
+    Ok(Cow::Owned(value.extract::<String>(py)?))
+}
+
+fn pyobject_to_path(py: Python, value: &PyObject) -> PyResult<Cow<'static, Path>> {
+    Ok(Cow::Owned(PathBuf::from(value.extract::<String>(py)?)))
+}
+
+fn pyobject_to_bytes_map(
+    py: Python,
+    value: &PyObject,
+) -> PyResult<HashMap<Cow<'static, str>, Cow<'static, [u8]>>> {
+    let mut res = HashMap::new();
+
+    for (name, data) in value.cast_as::<PyDict>(py)?.items(py) {
+        res.insert(
+            pyobject_to_string(py, &name)?,
+            pyobject_to_bytes(py, &data)?,
+        );
+    }
+
+    Ok(res)
+}
+
+fn pyobject_to_path_map(
+    py: Python,
+    value: &PyObject,
+) -> PyResult<HashMap<Cow<'static, str>, Cow<'static, Path>>> {
+    let mut res = HashMap::new();
+
+    for (name, path) in value.cast_as::<PyDict>(py)?.items(py) {
+        res.insert(pyobject_to_string(py, &name)?, pyobject_to_path(py, &path)?);
+    }
+
+    Ok(res)
+}
+
+/// Convert a `dict` to a resource.
+///
+/// Missing keys and keys whose value is `None` leave the field at its
+/// default value. Unknown keys are an error, so misspelled fields aren't
+/// silently dropped.
+fn pyobject_to_resource(py: Python, value: &PyObject) -> PyResult<Resource<'static, u8>> {
+    let mut resource = Resource::default();
+
+    for (key, value) in value.cast_as::<PyDict>(py)?.items(py) {
+        let key = key.extract::<String>(py)?;
+
+        if !RESOURCE_FIELDS.contains(&key.as_str()) {
+            return Err(value_error(py, format!("unknown resource field: {}", key)));
+        }
+
+        if value == py.None() {
+            continue;
+        }
+
+        match key.as_str() {
+            "flavor" => resource.flavor = str_to_flavor(py, &value.extract::<String>(py)?)?,
+            "name" => resource.name = pyobject_to_string(py, &value)?,
+            "is_package" => resource.is_package = value.extract(py)?,
+            "is_namespace_package" => resource.is_namespace_package = value.extract(py)?,
+            "in_memory_source" => resource.in_memory_source = Some(pyobject_to_bytes(py, &value)?),
+            "in_memory_bytecode" => {
+                resource.in_memory_bytecode = Some(pyobject_to_bytes(py, &value)?)
+            }
+            "in_memory_bytecode_opt1" => {
+                resource.in_memory_bytecode_opt1 = Some(pyobject_to_bytes(py, &value)?)
+            }
+            "in_memory_bytecode_opt2" => {
+                resource.in_memory_bytecode_opt2 = Some(pyobject_to_bytes(py, &value)?)
+            }
+            "in_memory_extension_module_shared_library" => {
+                resource.in_memory_extension_module_shared_library =
+                    Some(pyobject_to_bytes(py, &value)?)
+            }
+            "in_memory_package_resources" => {
+                resource.in_memory_package_resources = Some(pyobject_to_bytes_map(py, &value)?)
+            }
+            "in_memory_distribution_resources" => {
+                resource.in_memory_distribution_resources = Some(pyobject_to_bytes_map(py, &value)?)
+            }
+            "in_memory_shared_library" => {
+                resource.in_memory_shared_library = Some(pyobject_to_bytes(py, &value)?)
+            }
+            "shared_library_dependency_names" => {
+                let mut names = Vec::new();
+
+                for name in value.iter(py)? {
+                    names.push(pyobject_to_string(py, &name?)?);
+                }
+
+                resource.shared_library_dependency_names = Some(names);
+            }
+            "relative_path_module_source" => {
+                resource.relative_path_module_source = Some(pyobject_to_path(py, &value)?)
+            }
+            "relative_path_module_bytecode" => {
+                resource.relative_path_module_bytecode = Some(pyobject_to_path(py, &value)?)
+            }
+            "relative_path_module_bytecode_opt1" => {
+                resource.relative_path_module_bytecode_opt1 = Some(pyobject_to_path(py, &value)?)
+            }
+            "relative_path_module_bytecode_opt2" => {
+                resource.relative_path_module_bytecode_opt2 = Some(pyobject_to_path(py, &value)?)
+            }
+            "relative_path_extension_module_shared_library" => {
+                resource.relative_path_extension_module_shared_library =
+                    Some(pyobject_to_path(py, &value)?)
+            }
+            "relative_path_package_resources" => {
+                resource.relative_path_package_resources = Some(pyobject_to_path_map(py, &value)?)
+            }
+            "relative_path_distribution_resources" => {
+                resource.relative_path_distribution_resources =
+                    Some(pyobject_to_path_map(py, &value)?)
+            }
+            "provenance" => resource.provenance = Some(pyobject_to_string(py, &value)?),
+            _ => unreachable!("fields should have been validated above"),
+        }
+    }
+
+    Ok(resource)
+}
+
+/// load_resources(data, key=None)
+///
+/// Parse packed resources data into a list of `dict`.
+fn load_resources(py: Python, data: PyBytes, key: Option<PyBytes>) -> PyResult<PyList> {
+    let resources = match &key {
+        Some(key) => parser::load_resources_encrypted(data.data(py), key.data(py)),
+        None => parser::load_resources(data.data(py)),
+    }
+    .map_err(|e| value_error(py, e))?;
+
+    let res = PyList::new(py, &[]);
+
+    for resource in resources {
+        let resource = resource.map_err(|e| value_error(py, e))?;
+        res.append(py, resource_to_pyobject(py, &resource)?);
+    }
+
+    Ok(res)
+}
+
+/// write_resources(resources, deduplicate=False, compression_level=None, key=None)
+///
+/// Serialize an iterable of `dict` to packed resources data.
+fn write_resources(
+    py: Python,
+    resources: PyObject,
+    deduplicate: bool,
+    compression_level: Option<i32>,
+    key: Option<PyBytes>,
+) -> PyResult<PyBytes> {
+    let mut parsed = Vec::new();
+
+    for resource in resources.iter(py)? {
+        parsed.push(pyobject_to_resource(py, &resource?)?);
+    }
+
+    let compression = compression_level.map(|level| writer::BlobCompression {
+        level,
+        ..writer::BlobCompression::default()
+    });
+
+    let mut data = Vec::new();
+    writer::write_packed_resources_v1_with_options(
+        parsed.as_slice(),
+        &mut data,
+        &writer::WriteOptions {
+            deduplicate,
+            compression,
+            encryption_key: key.as_ref().map(|key| key.data(py).to_vec()),
+            ..writer::WriteOptions::default()
+        },
+    )
+    .map_err(|e| value_error(py, e.to_string()))?;
+
+    Ok(PyBytes::new(py, &data))
+}
+
+/// find_packed_resources(data, key=None)
+///
+/// Find the offset of packed resources data in e.g. an executable.
+fn find_packed_resources(py: Python, data: PyBytes, key: Option<PyBytes>) -> PyResult<PyObject> {
+    Ok(
+        match parser::find_packed_resources(data.data(py), key.as_ref().map(|key| key.data(py))) {
+            Some(offset) => offset.to_py_object(py).into_object(),
+            None => py.None(),
+        },
+    )
+}
+
+/// sign_packed_resources(data, secret_key)
+///
+/// Sign packed resources data with a 32 byte ed25519 secret key.
+fn sign_packed_resources(py: Python, data: PyBytes, secret_key: PyBytes) -> PyResult<PyBytes> {
+    let signature = writer::sign_packed_resources(data.data(py), secret_key.data(py))
+        .map_err(|e| value_error(py, e.to_string()))?;
+
+    Ok(PyBytes::new(py, &signature))
+}
+
+/// verify_packed_resources(data, signature, public_key)
+///
+/// Verify the ed25519 signature of packed resources data, raising on failure.
+fn verify_packed_resources(
+    py: Python,
+    data: PyBytes,
+    signature: PyBytes,
+    public_key: PyBytes,
+) -> PyResult<PyObject> {
+    parser::verify_packed_resources(data.data(py), signature.data(py), public_key.data(py))
+        .map_err(|e| value_error(py, e))?;
+
+    Ok(py.None())
+}
+
+py_module_initializer!(python_packed_resources, |py, m| {
+    m.add(
+        py,
+        "__doc__",
+        "Read and write Python packed resources data.",
+    )?;
+    m.add(
+        py,
+        "load_resources",
+        py_fn!(py, load_resources(data: PyBytes, key: Option<PyBytes> = None)),
+    )?;
+    m.add(
+        py,
+        "write_resources",
+        py_fn!(
+            py,
+            write_resources(
+                resources: PyObject,
+                deduplicate: bool = false,
+                compression_level: Option<i32> = None,
+                key: Option<PyBytes> = None
+            )
+        ),
+    )?;
+    m.add(
+        py,
+        "find_packed_resources",
+        py_fn!(
+            py,
+            find_packed_resources(data: PyBytes, key: Option<PyBytes> = None)
+        ),
+    )?;
+    m.add(
+        py,
+        "sign_packed_resources",
+        py_fn!(py, sign_packed_resources(data: PyBytes, secret_key: PyBytes)),
+    )?;
+    m.add(
+        py,
+        "verify_packed_resources",
+        py_fn!(
+            py,
+            verify_packed_resources(data: PyBytes, signature: PyBytes, public_key: PyBytes)
+        ),
+    )?;
+
+    Ok(())
+});
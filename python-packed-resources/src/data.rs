@@ -12,6 +12,12 @@ use {
 /// Header value for version 1 of resources payload.
 pub const HEADER_V1: &[u8] = b"pyembed\x01";
 
+/// Header value for version 3 of resources payload.
+///
+/// Version 3 adds blob index fields for compressed blobs, encrypted blobs
+/// and prefetch lengths. Data not using them is written as version 1.
+pub const HEADER_V3: &[u8] = b"pyembed\x03";
+
 /// Defines the type of a resource.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ResourceFlavor {
@@ -90,6 +96,10 @@ pub enum BlobSectionField {
     ResourceFieldType = 0x03,
     RawPayloadLength = 0x04,
     InteriorPadding = 0x05,
+    BlobReferences = 0x06,
+    CompressedBlobs = 0x07,
+    EncryptedBlobs = 0x08,
+    PrefetchLength = 0x09,
 }
 
 impl Into<u8> for BlobSectionField {
@@ -100,6 +110,10 @@ impl Into<u8> for BlobSectionField {
             BlobSectionField::ResourceFieldType => 0x02,
             BlobSectionField::RawPayloadLength => 0x03,
             BlobSectionField::InteriorPadding => 0x04,
+            BlobSectionField::BlobReferences => 0x05,
+            BlobSectionField::CompressedBlobs => 0x06,
+            BlobSectionField::EncryptedBlobs => 0x07,
+            BlobSectionField::PrefetchLength => 0x08,
             BlobSectionField::EndOfEntry => 0xff,
         }
     }
@@ -115,12 +129,83 @@ impl TryFrom<u8> for BlobSectionField {
             0x02 => Ok(BlobSectionField::ResourceFieldType),
             0x03 => Ok(BlobSectionField::RawPayloadLength),
             0x04 => Ok(BlobSectionField::InteriorPadding),
+            0x05 => Ok(BlobSectionField::BlobReferences),
+            0x06 => Ok(BlobSectionField::CompressedBlobs),
+            0x07 => Ok(BlobSectionField::EncryptedBlobs),
+            0x08 => Ok(BlobSectionField::PrefetchLength),
             0xff => Ok(BlobSectionField::EndOfEntry),
             _ => Err("invalid blob index field type"),
         }
     }
 }
 
+/// Defines how compressed blobs in a blob section are compressed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlobCompressionFormat {
+    /// Each blob is a zstandard frame.
+    Zstd = 0x01,
+}
+
+impl Into<u8> for BlobCompressionFormat {
+    fn into(self) -> u8 {
+        match self {
+            BlobCompressionFormat::Zstd => 0x01,
+        }
+    }
+}
+
+impl TryFrom<u8> for BlobCompressionFormat {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x01 => Ok(BlobCompressionFormat::Zstd),
+            _ => Err("invalid blob compression format"),
+        }
+    }
+}
+
+/// Defines how encrypted blobs in a blob section are encrypted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlobEncryptionFormat {
+    /// Each blob is a 12 byte nonce followed by ChaCha20-Poly1305 ciphertext.
+    ///
+    /// The resource field and the index of the blob within its blob section
+    /// are authenticated as associated data. See
+    /// `encrypted_blob_associated_data()`.
+    ChaCha20Poly1305 = 0x01,
+}
+
+impl Into<u8> for BlobEncryptionFormat {
+    fn into(self) -> u8 {
+        match self {
+            BlobEncryptionFormat::ChaCha20Poly1305 => 0x01,
+        }
+    }
+}
+
+impl TryFrom<u8> for BlobEncryptionFormat {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x01 => Ok(BlobEncryptionFormat::ChaCha20Poly1305),
+            _ => Err("invalid blob encryption format"),
+        }
+    }
+}
+
+/// Obtain the associated data authenticated along with an encrypted blob.
+///
+/// This is the resource field followed by the index of the blob within its
+/// blob section as a little endian u32. It binds ciphertext to the place it
+/// is stored at, so encrypted blobs can't be swapped for one another.
+pub fn encrypted_blob_associated_data(field: ResourceField, index: usize) -> [u8; 5] {
+    let index = (index as u32).to_le_bytes();
+
+    [field.into(), index[0], index[1], index[2], index[3]]
+}
+
 /// Describes a resource field type in the resource index.
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Eq, Ord)]
 pub enum ResourceField {
@@ -147,6 +232,7 @@ pub enum ResourceField {
     RelativeFilesystemExtensionModuleSharedLibrary = 0x13,
     RelativeFilesystemPackageResources = 0x14,
     RelativeFilesystemDistributionResource = 0x15,
+    Provenance = 0x16,
 }
 
 impl Into<u8> for ResourceField {
@@ -174,11 +260,45 @@ impl Into<u8> for ResourceField {
             ResourceField::RelativeFilesystemExtensionModuleSharedLibrary => 0x13,
             ResourceField::RelativeFilesystemPackageResources => 0x14,
             ResourceField::RelativeFilesystemDistributionResource => 0x15,
+            ResourceField::Provenance => 0x16,
             ResourceField::EndOfEntry => 0xff,
         }
     }
 }
 
+impl ResourceField {
+    /// Whether blobs holding data for this field may be stored compressed.
+    ///
+    /// Only fields whose data is copied when it is accessed qualify, so
+    /// readers can hand out bytecode without copying it.
+    pub fn allows_compressed_blobs(self) -> bool {
+        match self {
+            ResourceField::InMemorySource
+            | ResourceField::InMemoryResourcesData
+            | ResourceField::InMemoryDistributionResource => true,
+            _ => false,
+        }
+    }
+
+    /// Whether blobs holding data for this field may be stored encrypted.
+    ///
+    /// Only fields holding resource data qualify. Names and paths are
+    /// needed to index resources and are never encrypted.
+    pub fn allows_encrypted_blobs(self) -> bool {
+        match self {
+            ResourceField::InMemorySource
+            | ResourceField::InMemoryBytecode
+            | ResourceField::InMemoryBytecodeOpt1
+            | ResourceField::InMemoryBytecodeOpt2
+            | ResourceField::InMemoryExtensionModuleSharedLibrary
+            | ResourceField::InMemoryResourcesData
+            | ResourceField::InMemoryDistributionResource
+            | ResourceField::InMemorySharedLibrary => true,
+            _ => false,
+        }
+    }
+}
+
 impl TryFrom<u8> for ResourceField {
     type Error = &'static str;
 
@@ -206,12 +326,41 @@ impl TryFrom<u8> for ResourceField {
             0x13 => Ok(ResourceField::RelativeFilesystemExtensionModuleSharedLibrary),
             0x14 => Ok(ResourceField::RelativeFilesystemPackageResources),
             0x15 => Ok(ResourceField::RelativeFilesystemDistributionResource),
+            0x16 => Ok(ResourceField::Provenance),
             0xff => Ok(ResourceField::EndOfEntry),
             _ => Err("invalid field type"),
         }
     }
 }
 
+/// Describes a blob held by a resource in the form it is stored in.
+///
+/// Resources can be loaded without decoding their blobs, so data is only
+/// decompressed or decrypted when it is accessed. Data of such blobs must be passed to
+/// `parser::decode_blob()` before it is used.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EncodedBlob<'a> {
+    /// The resource field holding the blob.
+    pub field: ResourceField,
+
+    /// The name of the blob, for fields holding a mapping of names to blobs.
+    pub name: Option<Cow<'a, str>>,
+
+    /// The index of the blob within its blob section.
+    pub index: usize,
+
+    /// The length of the blob's data once decoded.
+    pub length: usize,
+
+    /// Whether the blob is compressed.
+    pub compressed: bool,
+
+    /// Whether the blob is encrypted.
+    ///
+    /// Encrypted blobs are compressed before they are encrypted.
+    pub encrypted: bool,
+}
+
 /// Represents an embedded resource and all its metadata.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Resource<'a, X: 'a>
@@ -282,6 +431,16 @@ where
 
     /// Mapping of Python package distribution files to relative filesystem paths for those resources.
     pub relative_path_distribution_resources: Option<HashMap<Cow<'a, str>, Cow<'a, Path>>>,
+
+    /// Describes where this resource came from.
+    ///
+    /// This is informational and has no effect on how the resource is loaded.
+    pub provenance: Option<Cow<'a, str>>,
+
+    /// Blobs held by this resource in the form they are stored in.
+    ///
+    /// Blobs not described here hold their data as is.
+    pub encoded_blobs: Option<Vec<EncodedBlob<'a>>>,
 }
 
 impl<'a, X> Default for Resource<'a, X>
@@ -310,6 +469,8 @@ where
             relative_path_extension_module_shared_library: None,
             relative_path_package_resources: None,
             relative_path_distribution_resources: None,
+            provenance: None,
+            encoded_blobs: None,
         }
     }
 }
@@ -419,6 +580,41 @@ where
                         )
                     }))
                 }),
+            provenance: self
+                .provenance
+                .as_ref()
+                .map(|value| Cow::Owned(value.clone().into_owned())),
+            encoded_blobs: self.encoded_blobs.as_ref().map(|blobs| {
+                Vec::from_iter(blobs.iter().map(|blob| {
+                    EncodedBlob {
+                        field: blob.field,
+                        name: blob
+                            .name
+                            .as_ref()
+                            .map(|name| Cow::Owned(name.clone().into_owned())),
+                        index: blob.index,
+                        length: blob.length,
+                        compressed: blob.compressed,
+                        encrypted: blob.encrypted,
+                    }
+                }))
+            }),
         }
     }
+
+    /// Obtain the description of a blob held in the form it is stored in.
+    ///
+    /// `name` is the name of the blob for fields holding a mapping of names
+    /// to blobs. Returns `None` if the blob holds its data as is.
+    pub fn encoded_blob(
+        &self,
+        field: ResourceField,
+        name: Option<&str>,
+    ) -> Option<&EncodedBlob<'a>> {
+        self.encoded_blobs.as_ref().and_then(|blobs| {
+            blobs
+                .iter()
+                .find(|blob| blob.field == field && blob.name.as_deref() == name)
+        })
+    }
 }
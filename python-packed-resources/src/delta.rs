@@ -0,0 +1,490 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Binary deltas between packed resources data.
+
+A delta describes how to reconstruct a *target* packed resources blob from a
+*base* blob as a sequence of instructions that either copy a range of the
+base or insert literal bytes. Packed resources data is mostly made up of
+blobs that are written verbatim, so when only a few resources change
+between two builds, nearly all of the new data can be copied from the old
+data and the delta is a small fraction of the size of the new data.
+
+This allows updating externally stored packed resources files (see
+`OxidizedPythonInterpreterConfig.packed_resources_files` in the `pyembed`
+crate) without distributing the entire new file.
+
+The delta format is:
+
+* An 8 byte header, `pydelta\x01`.
+* A `u64` holding the length of the base data followed by the 32 byte
+  SHA-256 digest of the base data.
+* A `u64` holding the length of the target data followed by the 32 byte
+  SHA-256 digest of the target data.
+* A sequence of instructions. Each instruction starts with a `u8` denoting
+  its type:
+  * `0x00` - End of instructions.
+  * `0x01` - Copy. A `u64` offset into the base data followed by a `u64`
+    length of data to copy.
+  * `0x02` - Insert. A `u64` length followed by that many bytes of data.
+
+All integers are little endian. Applying a delta verifies the digests of
+the base and the reconstructed data, so a delta is never applied to the
+wrong base and never yields corrupted data.
+*/
+
+use {
+    anyhow::Result,
+    byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt},
+    sha2::{Digest, Sha256},
+    std::collections::HashMap,
+    std::io::{Cursor, Read, Write},
+};
+
+/// Header identifying delta data.
+pub const DELTA_HEADER_V1: &[u8] = b"pydelta\x01";
+
+/// Size of blocks of the base data matched against the target data.
+///
+/// Smaller blocks find more matches at the cost of a larger index.
+const BLOCK_SIZE: usize = 32;
+
+/// Maximum number of base offsets sharing a hash that are considered for a match.
+const MAX_CANDIDATES: usize = 8;
+
+const INSTRUCTION_END: u8 = 0x00;
+const INSTRUCTION_COPY: u8 = 0x01;
+const INSTRUCTION_INSERT: u8 = 0x02;
+
+/// Describes the composition of a delta.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DeltaReport {
+    /// Number of bytes of the target data copied from the base data.
+    pub copied_bytes: usize,
+
+    /// Number of bytes of the target data stored literally in the delta.
+    pub inserted_bytes: usize,
+
+    /// Total length of the delta.
+    pub delta_length: usize,
+}
+
+#[derive(Debug, PartialEq)]
+enum Instruction {
+    Copy(usize, usize),
+    Insert(usize, usize),
+}
+
+/// Adler-32 style checksum that can be rolled over a fixed size window.
+struct RollingHash {
+    a: u32,
+    b: u32,
+}
+
+impl RollingHash {
+    fn new(data: &[u8]) -> Self {
+        let mut a = 0u32;
+        let mut b = 0u32;
+
+        for byte in data {
+            a = a.wrapping_add(*byte as u32);
+            b = b.wrapping_add(a);
+        }
+
+        Self { a, b }
+    }
+
+    fn roll(&mut self, removed: u8, added: u8) {
+        self.a = self
+            .a
+            .wrapping_sub(removed as u32)
+            .wrapping_add(added as u32);
+        self.b = self
+            .b
+            .wrapping_sub((BLOCK_SIZE as u32).wrapping_mul(removed as u32))
+            .wrapping_add(self.a);
+    }
+
+    fn value(&self) -> u32 {
+        (self.b << 16) | (self.a & 0xffff)
+    }
+}
+
+fn push_instruction(instructions: &mut Vec<Instruction>, instruction: Instruction) {
+    // Coalesce adjacent instructions to keep the delta compact.
+    if let Some(last) = instructions.last_mut() {
+        match (last, &instruction) {
+            (Instruction::Copy(offset, length), Instruction::Copy(new_offset, new_length))
+                if *offset + *length == *new_offset =>
+            {
+                *length += new_length;
+                return;
+            }
+            (Instruction::Insert(start, length), Instruction::Insert(new_start, new_length))
+                if *start + *length == *new_start =>
+            {
+                *length += new_length;
+                return;
+            }
+            _ => {}
+        }
+    }
+
+    instructions.push(instruction);
+}
+
+/// Resolve the instructions to produce `target` from `base`.
+fn compute_instructions(base: &[u8], target: &[u8]) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+
+    let mut index: HashMap<u32, Vec<usize>> = HashMap::new();
+    if base.len() >= BLOCK_SIZE {
+        for offset in (0..=base.len() - BLOCK_SIZE).step_by(BLOCK_SIZE) {
+            let offsets = index
+                .entry(RollingHash::new(&base[offset..offset + BLOCK_SIZE]).value())
+                .or_insert_with(Vec::new);
+
+            if offsets.len() < MAX_CANDIDATES {
+                offsets.push(offset);
+            }
+        }
+    }
+
+    let mut literal_start = 0;
+    let mut position = 0;
+    let mut hash = if target.len() >= BLOCK_SIZE {
+        Some(RollingHash::new(&target[0..BLOCK_SIZE]))
+    } else {
+        None
+    };
+
+    while position + BLOCK_SIZE <= target.len() {
+        let rolling = hash.as_mut().unwrap();
+
+        let mut best: Option<(usize, usize)> = None;
+
+        if let Some(offsets) = index.get(&rolling.value()) {
+            for offset in offsets {
+                let length = base[*offset..]
+                    .iter()
+                    .zip(target[position..].iter())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+
+                if length >= BLOCK_SIZE && best.map_or(true, |(_, l)| length > l) {
+                    best = Some((*offset, length));
+                }
+            }
+        }
+
+        if let Some((mut offset, mut length)) = best {
+            // Extend the match backwards into data we would otherwise insert.
+            let mut start = position;
+            while start > literal_start && offset > 0 && base[offset - 1] == target[start - 1] {
+                start -= 1;
+                offset -= 1;
+                length += 1;
+            }
+
+            if start > literal_start {
+                push_instruction(
+                    &mut instructions,
+                    Instruction::Insert(literal_start, start - literal_start),
+                );
+            }
+            push_instruction(&mut instructions, Instruction::Copy(offset, length));
+
+            position = start + length;
+            literal_start = position;
+
+            if position + BLOCK_SIZE <= target.len() {
+                *rolling = RollingHash::new(&target[position..position + BLOCK_SIZE]);
+            }
+        } else {
+            if position + BLOCK_SIZE < target.len() {
+                rolling.roll(target[position], target[position + BLOCK_SIZE]);
+            }
+            position += 1;
+        }
+    }
+
+    if literal_start < target.len() {
+        push_instruction(
+            &mut instructions,
+            Instruction::Insert(literal_start, target.len() - literal_start),
+        );
+    }
+
+    instructions
+}
+
+/// Write a delta that reconstructs `target` from `base`.
+///
+/// Both `base` and `target` are typically packed resources data. But any
+/// data can be used.
+pub fn write_delta<W: Write>(base: &[u8], target: &[u8], dest: &mut W) -> Result<DeltaReport> {
+    let mut report = DeltaReport::default();
+
+    let mut data = Vec::new();
+    data.write_all(DELTA_HEADER_V1)?;
+    data.write_u64::<LittleEndian>(base.len() as u64)?;
+    data.write_all(&Sha256::digest(base))?;
+    data.write_u64::<LittleEndian>(target.len() as u64)?;
+    data.write_all(&Sha256::digest(target))?;
+
+    for instruction in compute_instructions(base, target) {
+        match instruction {
+            Instruction::Copy(offset, length) => {
+                data.write_u8(INSTRUCTION_COPY)?;
+                data.write_u64::<LittleEndian>(offset as u64)?;
+                data.write_u64::<LittleEndian>(length as u64)?;
+                report.copied_bytes += length;
+            }
+            Instruction::Insert(start, length) => {
+                data.write_u8(INSTRUCTION_INSERT)?;
+                data.write_u64::<LittleEndian>(length as u64)?;
+                data.write_all(&target[start..start + length])?;
+                report.inserted_bytes += length;
+            }
+        }
+    }
+
+    data.write_u8(INSTRUCTION_END)?;
+
+    report.delta_length = data.len();
+    dest.write_all(&data)?;
+
+    Ok(report)
+}
+
+/// Ensure appending `length` bytes to `current` bytes of target data doesn't
+/// exceed the advertised target length.
+fn check_target_length(
+    current: usize,
+    length: usize,
+    target_length: usize,
+) -> Result<(), &'static str> {
+    match current.checked_add(length) {
+        Some(length) if length <= target_length => Ok(()),
+        _ => Err("delta produces too much data"),
+    }
+}
+
+/// Apply a delta produced by `write_delta()` to `base`.
+///
+/// Returns the reconstructed target data. Errors if `base` isn't the data
+/// the delta was produced from or if the delta is malformed.
+pub fn apply_delta(base: &[u8], delta: &[u8]) -> Result<Vec<u8>, &'static str> {
+    if delta.len() < DELTA_HEADER_V1.len() {
+        return Err("error reading 8 byte header");
+    }
+
+    if &delta[0..8] != DELTA_HEADER_V1 {
+        return Err("unrecognized delta format");
+    }
+
+    let mut reader = Cursor::new(&delta[8..]);
+
+    let base_length = reader
+        .read_u64::<LittleEndian>()
+        .map_err(|_| "failed reading base length")? as usize;
+    let mut base_digest = [0u8; 32];
+    reader
+        .read_exact(&mut base_digest)
+        .map_err(|_| "failed reading base digest")?;
+    let target_length = reader
+        .read_u64::<LittleEndian>()
+        .map_err(|_| "failed reading target length")? as usize;
+    let mut target_digest = [0u8; 32];
+    reader
+        .read_exact(&mut target_digest)
+        .map_err(|_| "failed reading target digest")?;
+
+    if base.len() != base_length || Sha256::digest(base).as_slice() != &base_digest[..] {
+        return Err("delta was not produced from this base data");
+    }
+
+    // The target length comes from untrusted input. Each copy instruction
+    // takes 17 bytes of the delta and produces at most the entire base, and
+    // inserts produce no more than the delta holds. Longer targets can't be
+    // produced by this delta.
+    let max_target_length = (delta.len() / 17)
+        .saturating_mul(base.len())
+        .saturating_add(delta.len());
+    if target_length > max_target_length {
+        return Err("delta target length exceeds what the delta can produce");
+    }
+
+    // Only reserve as much as is plausible and let the vector grow if copies
+    // of large base ranges produce more.
+    let mut target = Vec::with_capacity(std::cmp::min(
+        target_length,
+        base.len().saturating_add(delta.len()),
+    ));
+
+    loop {
+        let instruction = reader
+            .read_u8()
+            .map_err(|_| "failed reading delta instruction")?;
+
+        match instruction {
+            INSTRUCTION_END => break,
+            INSTRUCTION_COPY => {
+                let offset = reader
+                    .read_u64::<LittleEndian>()
+                    .map_err(|_| "failed reading copy offset")?
+                    as usize;
+                let length = reader
+                    .read_u64::<LittleEndian>()
+                    .map_err(|_| "failed reading copy length")?
+                    as usize;
+
+                let end = offset
+                    .checked_add(length)
+                    .ok_or("copy instruction out of bounds")?;
+                if end > base.len() {
+                    return Err("copy instruction out of bounds");
+                }
+                check_target_length(target.len(), length, target_length)?;
+
+                target.extend_from_slice(&base[offset..end]);
+            }
+            INSTRUCTION_INSERT => {
+                let length = reader
+                    .read_u64::<LittleEndian>()
+                    .map_err(|_| "failed reading insert length")?
+                    as usize;
+
+                let start = reader.position() as usize;
+                let data = reader.get_ref();
+                if length > data.len() - start {
+                    return Err("insert instruction out of bounds");
+                }
+                check_target_length(target.len(), length, target_length)?;
+
+                target.extend_from_slice(&data[start..start + length]);
+                reader.set_position((start + length) as u64);
+            }
+            _ => return Err("invalid delta instruction"),
+        }
+    }
+
+    if target.len() != target_length || Sha256::digest(&target).as_slice() != &target_digest[..] {
+        return Err("delta produced data not matching target digest");
+    }
+
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_data(seed: u32, length: usize) -> Vec<u8> {
+        let mut state = seed;
+        (0..length)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_identical() -> Result<()> {
+        let base = sample_data(1, 4096);
+
+        let mut delta = Vec::new();
+        let report = write_delta(&base, &base, &mut delta)?;
+        assert_eq!(report.copied_bytes, 4096);
+        assert_eq!(report.inserted_bytes, 0);
+        assert_eq!(report.delta_length, delta.len());
+
+        assert_eq!(apply_delta(&base, &delta), Ok(base));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty() -> Result<()> {
+        let mut delta = Vec::new();
+        write_delta(b"", b"", &mut delta)?;
+        assert_eq!(apply_delta(b"", &delta), Ok(vec![]));
+
+        let target = sample_data(1, 10);
+        let mut delta = Vec::new();
+        let report = write_delta(b"", &target, &mut delta)?;
+        assert_eq!(report.inserted_bytes, 10);
+        assert_eq!(apply_delta(b"", &delta), Ok(target));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_modified() -> Result<()> {
+        let base = sample_data(1, 65536);
+
+        // Replace a range, insert new data and remove a range, shifting data around.
+        let mut target = base[0..10000].to_vec();
+        target.extend(sample_data(2, 500));
+        target.extend(&base[10500..30000]);
+        target.extend(sample_data(3, 1000));
+        target.extend(&base[30000..50000]);
+        target.extend(&base[60000..]);
+
+        let mut delta = Vec::new();
+        let report = write_delta(&base, &target, &mut delta)?;
+        assert_eq!(report.copied_bytes + report.inserted_bytes, target.len());
+        assert!(report.inserted_bytes < 1600);
+        assert!(delta.len() < 2000);
+
+        assert_eq!(apply_delta(&base, &delta), Ok(target));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrong_base() -> Result<()> {
+        let base = sample_data(1, 4096);
+        let target = sample_data(2, 4096);
+
+        let mut delta = Vec::new();
+        write_delta(&base, &target, &mut delta)?;
+
+        assert_eq!(
+            apply_delta(&target, &delta),
+            Err("delta was not produced from this base data")
+        );
+        assert_eq!(
+            apply_delta(&base, b"pyembed\x01"),
+            Err("unrecognized delta format")
+        );
+
+        // A huge advertised target length doesn't cause a huge allocation.
+        let mut bogus = delta.clone();
+        bogus[48..56].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(
+            apply_delta(&base, &bogus),
+            Err("delta target length exceeds what the delta can produce")
+        );
+
+        // Copies can't produce more than the advertised target length.
+        let mut bogus = delta.clone();
+        bogus[48..56].copy_from_slice(&1u64.to_le_bytes());
+        assert_eq!(
+            apply_delta(&base, &bogus),
+            Err("delta produces too much data")
+        );
+
+        // Corrupt an inserted byte.
+        let last = delta.len() - 2;
+        delta[last] ^= 0xff;
+        assert_eq!(
+            apply_delta(&base, &delta),
+            Err("delta produced data not matching target digest")
+        );
+
+        Ok(())
+    }
+}
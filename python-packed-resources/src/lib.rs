@@ -22,9 +22,26 @@ the module `import` mechanism.
 This crate is developed primarily for
 [PyOxidizer](https://pyoxidizer.readthedocs.io/en/stable/). But it can
 be used outside the PyOxidizer project.
+
+# Features
+
+Reading and writing plain packed resources data only depends on `anyhow`
+and `byteorder`. Other functionality is provided by optional features,
+which are all disabled by default:
+
+* `compression` - Reading and writing zstandard compressed blobs.
+* `encryption` - Reading and writing ChaCha20-Poly1305 encrypted blobs.
+* `signing` - Signing packed resources data and verifying signatures.
+* `deduplication` - Storing identical blobs once when writing.
+* `delta` - The `delta` module.
+
+Without these features, reading data using them fails with an error, as
+does requesting them when writing.
 */
 
 pub mod data;
+#[cfg(feature = "delta")]
+pub mod delta;
 #[allow(unused)]
 pub mod parser;
 pub mod specifications;
@@ -6,7 +6,8 @@
 
 use {
     super::data::{
-        BlobInteriorPadding, BlobSectionField, Resource, ResourceField, ResourceFlavor, HEADER_V1,
+        BlobCompressionFormat, BlobEncryptionFormat, BlobInteriorPadding, BlobSectionField,
+        EncodedBlob, Resource, ResourceField, ResourceFlavor, HEADER_V1, HEADER_V3,
     },
     byteorder::{LittleEndian, ReadBytesExt},
     std::borrow::Cow,
@@ -17,8 +18,18 @@ use {
     std::path::Path,
 };
 
+#[cfg(feature = "signing")]
+use ed25519_dalek::{PublicKey, Signature};
 #[cfg(unix)]
 use std::os::unix::ffi::OsStrExt;
+#[cfg(feature = "encryption")]
+use {
+    super::data::encrypted_blob_associated_data,
+    chacha20poly1305::{
+        aead::{Aead, NewAead, Payload},
+        ChaCha20Poly1305, Key, Nonce,
+    },
+};
 #[cfg(windows)]
 use {std::ffi::OsString, std::os::windows::ffi::OsStringExt, std::path::PathBuf};
 
@@ -28,6 +39,13 @@ struct BlobSection {
     resource_field: u8,
     raw_payload_length: usize,
     interior_padding: Option<BlobInteriorPadding>,
+    references: Vec<(usize, usize)>,
+    /// Index and stored length of compressed blobs.
+    compressed: Vec<(usize, usize)>,
+    /// Index and stored length of encrypted blobs.
+    encrypted: Vec<(usize, usize)>,
+    /// Number of bytes at the start of the section worth prefetching.
+    prefetch_length: Option<usize>,
 }
 
 /// Holds state used to read an individual blob section.
@@ -35,17 +53,90 @@ struct BlobSection {
 struct BlobSectionReadState {
     offset: usize,
     interior_padding: BlobInteriorPadding,
+    /// Index of the next blob to be read from this section.
+    index: usize,
+    /// Whether some blobs in this section are stored elsewhere.
+    has_references: bool,
+    /// Whether some blobs in this section are compressed.
+    has_compressed: bool,
+    /// Whether some blobs in this section are encrypted.
+    has_encrypted: bool,
 }
 
+/// Length of the nonce preceding the ciphertext of encrypted blobs.
+#[cfg(feature = "encryption")]
+const ENCRYPTED_BLOB_NONCE_LENGTH: usize = 12;
+
+/// Cipher decrypting encrypted blobs.
+#[cfg(feature = "encryption")]
+type Cipher = ChaCha20Poly1305;
+
+/// Without the `encryption` feature, no cipher can be constructed.
+#[cfg(not(feature = "encryption"))]
+enum Cipher {}
+
 pub type PythonPackageResources<'a> = HashMap<&'a str, &'a [u8]>;
 
 pub struct ResourceParserIterator<'a> {
     done: bool,
+    /// Version of the packed resources format.
+    version: u8,
     data: &'a [u8],
     reader: Cursor<&'a [u8]>,
     blob_sections: [Option<BlobSectionReadState>; 256],
+    /// Maps (resource field, blob index) to global offset of deduplicated blobs.
+    blob_references: HashMap<(u8, usize), usize>,
+    /// Maps (resource field, blob index) to stored length of compressed blobs.
+    compressed_blobs: HashMap<(u8, usize), usize>,
+    /// Maps (resource field, blob index) to stored length of encrypted blobs.
+    encrypted_blobs: HashMap<(u8, usize), usize>,
+    /// Cipher decrypting encrypted blobs.
+    cipher: Option<Cipher>,
+    /// Whether compressed and encrypted blobs are decoded as resources are parsed.
+    decode_blobs: bool,
+    /// Whether an encrypted blob was decrypted to check the key.
+    key_checked: bool,
     claimed_resources_count: usize,
     read_resources_count: usize,
+    /// Length of the packed resources data, including the header.
+    data_length: usize,
+    /// Offset and length of data worth prefetching.
+    prefetch_ranges: Vec<(usize, usize)>,
+}
+
+impl<'a> ResourceParserIterator<'a> {
+    /// The length of the packed resources data, including the header.
+    ///
+    /// This is derived from the index. Data following it is not part of the
+    /// packed resources data.
+    pub fn data_length(&self) -> usize {
+        self.data_length
+    }
+
+    /// The number of blobs stored as a reference to an identical blob.
+    pub fn referenced_blobs_count(&self) -> usize {
+        self.blob_references.len()
+    }
+
+    /// The number of blobs stored compressed.
+    pub fn compressed_blobs_count(&self) -> usize {
+        self.compressed_blobs.len()
+    }
+
+    /// The number of blobs stored encrypted.
+    pub fn encrypted_blobs_count(&self) -> usize {
+        self.encrypted_blobs.len()
+    }
+
+    /// Ranges of data likely to be read soon after the data is loaded.
+    ///
+    /// Tuples are the offset from the start of the packed resources data,
+    /// including the header, and the length of each range. Readers holding
+    /// memory mapped data may ask the operating system to read these ranges
+    /// ahead of time.
+    pub fn prefetch_ranges(&self) -> &[(usize, usize)] {
+        &self.prefetch_ranges
+    }
 }
 
 impl<'a> ResourceParserIterator<'a> {
@@ -59,12 +150,36 @@ impl<'a> ResourceParserIterator<'a> {
     /// This accepts a reference to the original blobs payload, an array of
     /// current blob section offsets, the resource field being accessed, and the
     /// length of the blob and returns a slice to that blob.
-    fn resolve_blob_data(&mut self, resource_field: ResourceField, length: usize) -> &'a [u8] {
+    ///
+    /// Errors if there is no blob section for the field or the blob extends
+    /// past the end of the data.
+    fn resolve_blob_data(
+        &mut self,
+        resource_field: ResourceField,
+        length: usize,
+    ) -> Result<&'a [u8], &'static str> {
+        let data = self.data;
         let mut state = self.blob_sections[resource_field as usize]
             .as_mut()
-            .expect("blob state not found");
+            .ok_or("no blob section for resource field")?;
+
+        let index = state.index;
+        state.index += 1;
+
+        if state.has_references {
+            if let Some(offset) = self.blob_references.get(&(resource_field as u8, index)) {
+                return offset
+                    .checked_add(length)
+                    .and_then(|end| data.get(*offset..end))
+                    .ok_or("blob reference out of bounds");
+            }
+        }
 
-        let blob = &self.data[state.offset..state.offset + length];
+        let blob = state
+            .offset
+            .checked_add(length)
+            .and_then(|end| data.get(state.offset..end))
+            .ok_or("blob data out of bounds")?;
 
         let increment = match &state.interior_padding {
             BlobInteriorPadding::None => length,
@@ -73,25 +188,104 @@ impl<'a> ResourceParserIterator<'a> {
 
         state.offset += increment;
 
-        blob
+        Ok(blob)
+    }
+
+    /// Resolve a blob holding a UTF-8 string.
+    fn resolve_str(
+        &mut self,
+        resource_field: ResourceField,
+        length: usize,
+    ) -> Result<&'a str, &'static str> {
+        std::str::from_utf8(self.resolve_blob_data(resource_field, length)?)
+            .map_err(|_| "string is not valid UTF-8")
+    }
+
+    /// Resolve a blob that may be stored compressed or encrypted.
+    ///
+    /// `length` is the length of the blob's data once decrypted and
+    /// decompressed. Compressed and encrypted blobs are resolved as stored
+    /// and described in the `encoded_blobs` of `resource`. `name` is the
+    /// name of the blob for fields holding a mapping of names to blobs.
+    fn resolve_blob(
+        &mut self,
+        resource: &mut Resource<'a, u8>,
+        resource_field: ResourceField,
+        name: Option<&'a str>,
+        length: usize,
+    ) -> Result<Cow<'a, [u8]>, &'static str> {
+        let state = self.blob_sections[resource_field as usize]
+            .as_ref()
+            .ok_or("no blob section for resource field")?;
+        let index = state.index;
+        let key = (resource_field as u8, index);
+
+        let compressed_length = if state.has_compressed {
+            self.compressed_blobs.get(&key).cloned()
+        } else {
+            None
+        };
+        let encrypted_length = if state.has_encrypted {
+            self.encrypted_blobs.get(&key).cloned()
+        } else {
+            None
+        };
+
+        let stored_length = encrypted_length.or(compressed_length).unwrap_or(length);
+        let data = self.resolve_blob_data(resource_field, stored_length)?;
+
+        if compressed_length.is_none() && encrypted_length.is_none() {
+            return Ok(Cow::Borrowed(data));
+        }
+
+        let blob = EncodedBlob {
+            field: resource_field,
+            name: name.map(Cow::Borrowed),
+            index,
+            length,
+            compressed: compressed_length.is_some(),
+            encrypted: encrypted_length.is_some(),
+        };
+
+        // Blobs decoded when accessed are decrypted once here, so a wrong key
+        // is an error when loading rather than when data is accessed.
+        if blob.encrypted && !self.decode_blobs && !self.key_checked {
+            decode_blob_data(&blob, data, self.cipher.as_ref())?;
+            self.key_checked = true;
+        }
+
+        resource
+            .encoded_blobs
+            .get_or_insert_with(Vec::new)
+            .push(blob);
+
+        Ok(Cow::Borrowed(data))
     }
 
     #[cfg(unix)]
-    fn resolve_path(&mut self, resource_field: ResourceField, length: usize) -> Cow<'a, Path> {
-        let path_str = OsStr::from_bytes(self.resolve_blob_data(resource_field, length));
-        Cow::Borrowed(Path::new(path_str))
+    fn resolve_path(
+        &mut self,
+        resource_field: ResourceField,
+        length: usize,
+    ) -> Result<Cow<'a, Path>, &'static str> {
+        let path_str = OsStr::from_bytes(self.resolve_blob_data(resource_field, length)?);
+        Ok(Cow::Borrowed(Path::new(path_str)))
     }
 
     #[cfg(windows)]
-    fn resolve_path(&mut self, resource_field: ResourceField, length: usize) -> Cow<'a, Path> {
-        let raw = self.resolve_blob_data(resource_field, length);
+    fn resolve_path(
+        &mut self,
+        resource_field: ResourceField,
+        length: usize,
+    ) -> Result<Cow<'a, Path>, &'static str> {
+        let raw = self.resolve_blob_data(resource_field, length)?;
         let raw = unsafe { std::slice::from_raw_parts(raw.as_ptr() as *const u16, raw.len() / 2) };
 
         // There isn't an API that lets us get a OsStr from &[u16]. So we need to use
         // owned types.
         let path_string = OsString::from_wide(raw);
 
-        Cow::Owned(PathBuf::from(path_string))
+        Ok(Cow::Owned(PathBuf::from(path_string)))
     }
 
     fn parse_next(&mut self) -> Result<Option<Resource<'a, u8>>, &'static str> {
@@ -150,9 +344,7 @@ impl<'a> ResourceParserIterator<'a> {
                         .map_err(|_| "failed reading resource name length")?
                         as usize;
 
-                    let name = unsafe {
-                        std::str::from_utf8_unchecked(self.resolve_blob_data(field_type, l))
-                    };
+                    let name = self.resolve_str(field_type, l)?;
 
                     current_resource_name = Some(name);
                     current_resource.name = Cow::Borrowed(name);
@@ -171,7 +363,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_source =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(self.resolve_blob(&mut current_resource, field_type, None, l)?);
                 }
                 ResourceField::InMemoryBytecode => {
                     let l = self
@@ -181,7 +373,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_bytecode =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(self.resolve_blob(&mut current_resource, field_type, None, l)?);
                 }
                 ResourceField::InMemoryBytecodeOpt1 => {
                     let l = self
@@ -191,7 +383,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_bytecode_opt1 =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(self.resolve_blob(&mut current_resource, field_type, None, l)?);
                 }
                 ResourceField::InMemoryBytecodeOpt2 => {
                     let l = self
@@ -201,7 +393,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_bytecode_opt2 =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(self.resolve_blob(&mut current_resource, field_type, None, l)?);
                 }
                 ResourceField::InMemoryExtensionModuleSharedLibrary => {
                     let l = self
@@ -211,7 +403,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_extension_module_shared_library =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(self.resolve_blob(&mut current_resource, field_type, None, l)?);
                 }
 
                 ResourceField::InMemoryResourcesData => {
@@ -230,11 +422,7 @@ impl<'a> ResourceParserIterator<'a> {
                             .map_err(|_| "failed reading resource name")?
                             as usize;
 
-                        let resource_name = unsafe {
-                            std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, resource_name_length),
-                            )
-                        };
+                        let resource_name = self.resolve_str(field_type, resource_name_length)?;
 
                         let resource_length = self
                             .reader
@@ -242,10 +430,14 @@ impl<'a> ResourceParserIterator<'a> {
                             .map_err(|_| "failed reading resource length")?
                             as usize;
 
-                        let resource_data = self.resolve_blob_data(field_type, resource_length);
+                        let resource_data = self.resolve_blob(
+                            &mut current_resource,
+                            field_type,
+                            Some(resource_name),
+                            resource_length,
+                        )?;
 
-                        resources
-                            .insert(Cow::Borrowed(resource_name), Cow::Borrowed(resource_data));
+                        resources.insert(Cow::Borrowed(resource_name), resource_data);
                     }
 
                     current_resource.in_memory_package_resources = Some(resources);
@@ -267,20 +459,21 @@ impl<'a> ResourceParserIterator<'a> {
                             .map_err(|_| "failed reading distribution metadata name")?
                             as usize;
 
-                        let name = unsafe {
-                            std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, name_length),
-                            )
-                        };
+                        let name = self.resolve_str(field_type, name_length)?;
 
                         let resource_length =
                             self.reader.read_u64::<LittleEndian>().map_err(|_| {
                                 "failed reading package distribution resource length"
                             })? as usize;
 
-                        let resource_data = self.resolve_blob_data(field_type, resource_length);
+                        let resource_data = self.resolve_blob(
+                            &mut current_resource,
+                            field_type,
+                            Some(name),
+                            resource_length,
+                        )?;
 
-                        resources.insert(Cow::Borrowed(name), Cow::Borrowed(resource_data));
+                        resources.insert(Cow::Borrowed(name), resource_data);
                     }
 
                     current_resource.in_memory_distribution_resources = Some(resources);
@@ -294,7 +487,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_shared_library =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(self.resolve_blob(&mut current_resource, field_type, None, l)?);
                 }
 
                 ResourceField::SharedLibraryDependencyNames => {
@@ -312,11 +505,7 @@ impl<'a> ResourceParserIterator<'a> {
                                 "failed reading shared library dependency name length"
                             })? as usize;
 
-                        let name = unsafe {
-                            std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, name_length),
-                            )
-                        };
+                        let name = self.resolve_str(field_type, name_length)?;
 
                         names.push(Cow::Borrowed(name));
                     }
@@ -331,7 +520,7 @@ impl<'a> ResourceParserIterator<'a> {
                         .map_err(|_| "failed reading Python module relative path length")?
                         as usize;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(field_type, path_length)?;
 
                     current_resource.relative_path_module_source = Some(path);
                 }
@@ -342,7 +531,7 @@ impl<'a> ResourceParserIterator<'a> {
                             "failed reading Python module bytecode relative path length"
                         })? as usize;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(field_type, path_length)?;
 
                     current_resource.relative_path_module_bytecode = Some(path);
                 }
@@ -352,7 +541,7 @@ impl<'a> ResourceParserIterator<'a> {
                         "failed reading Python module bytecode opt 1 relative path length"
                     })? as usize;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(field_type, path_length)?;
 
                     current_resource.relative_path_module_bytecode_opt1 = Some(path);
                 }
@@ -362,7 +551,7 @@ impl<'a> ResourceParserIterator<'a> {
                         "failed reading Python module bytecode opt 2 relative path length"
                     })? as usize;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(field_type, path_length)?;
 
                     current_resource.relative_path_module_bytecode_opt2 = Some(path);
                 }
@@ -372,7 +561,7 @@ impl<'a> ResourceParserIterator<'a> {
                         "failed reading Python extension module shared library relative path length"
                     })? as usize;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(field_type, path_length)?;
 
                     current_resource.relative_path_extension_module_shared_library = Some(path);
                 }
@@ -392,11 +581,7 @@ impl<'a> ResourceParserIterator<'a> {
                             .map_err(|_| "failed reading resource name")?
                             as usize;
 
-                        let resource_name = unsafe {
-                            std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, resource_name_length),
-                            )
-                        };
+                        let resource_name = self.resolve_str(field_type, resource_name_length)?;
 
                         let path_length = self
                             .reader
@@ -404,7 +589,7 @@ impl<'a> ResourceParserIterator<'a> {
                             .map_err(|_| "failed reading resource path length")?
                             as usize;
 
-                        let path = self.resolve_path(field_type, path_length);
+                        let path = self.resolve_path(field_type, path_length)?;
 
                         resources.insert(Cow::Borrowed(resource_name), path);
                     }
@@ -426,11 +611,7 @@ impl<'a> ResourceParserIterator<'a> {
                             .map_err(|_| "failed reading package distribution metadata name")?
                             as usize;
 
-                        let name = unsafe {
-                            std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, name_length),
-                            )
-                        };
+                        let name = self.resolve_str(field_type, name_length)?;
 
                         let path_length = self
                             .reader
@@ -438,13 +619,29 @@ impl<'a> ResourceParserIterator<'a> {
                             .map_err(|_| "failed reading package distribution path length")?
                             as usize;
 
-                        let path = self.resolve_path(field_type, path_length);
+                        let path = self.resolve_path(field_type, path_length)?;
 
                         resources.insert(Cow::Borrowed(name), path);
                     }
 
                     current_resource.relative_path_distribution_resources = Some(resources);
                 }
+
+                ResourceField::Provenance => {
+                    if self.version < 3 {
+                        return Err("resource field requires format version 3");
+                    }
+
+                    let l = self
+                        .reader
+                        .read_u16::<LittleEndian>()
+                        .map_err(|_| "failed reading provenance length")?
+                        as usize;
+
+                    let provenance = self.resolve_str(field_type, l)?;
+
+                    current_resource.provenance = Some(Cow::Borrowed(provenance));
+                }
             }
         }
     }
@@ -460,6 +657,9 @@ impl<'a> Iterator for ResourceParserIterator<'a> {
 
         match self.parse_next() {
             Ok(res) => match res {
+                Some(entry) if self.decode_blobs => {
+                    Some(decode_resource_with(entry, self.cipher.as_ref()))
+                }
                 Some(entry) => Some(Ok(entry)),
                 None => None,
             },
@@ -469,20 +669,358 @@ impl<'a> Iterator for ResourceParserIterator<'a> {
 }
 
 pub fn load_resources<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &'static str> {
+    load_resources_internal(data, None, true)
+}
+
+/// Load packed resources data that may contain encrypted blobs.
+///
+/// `key` is the 32 byte key the data was encrypted with. Encrypted blobs are
+/// decrypted as the resources referring to them are parsed, so no more than
+/// one blob is decrypted at a time and decrypted data is owned by the
+/// resource it belongs to.
+pub fn load_resources_encrypted<'a>(
+    data: &'a [u8],
+    key: &[u8],
+) -> Result<ResourceParserIterator<'a>, &'static str> {
+    load_resources_internal(data, new_cipher(Some(key))?, true)
+}
+
+/// Load packed resources data without decoding compressed or encrypted blobs.
+///
+/// Compressed and encrypted blobs are held by resources as stored and
+/// described by their `encoded_blobs`. They are decompressed and decrypted
+/// by `decode_blob()` when accessed, so loading doesn't allocate memory for
+/// their data and decrypted data isn't kept around. `key` is the 32 byte
+/// key the data was encrypted with. It is checked by decrypting the first
+/// encrypted blob.
+pub fn load_resources_encoded<'a>(
+    data: &'a [u8],
+    key: Option<&[u8]>,
+) -> Result<ResourceParserIterator<'a>, &'static str> {
+    load_resources_internal(data, new_cipher(key)?, false)
+}
+
+/// Construct the cipher decrypting blobs encrypted with `key`.
+#[cfg(feature = "encryption")]
+fn new_cipher(key: Option<&[u8]>) -> Result<Option<Cipher>, &'static str> {
+    match key {
+        Some(key) if key.len() != 32 => Err("encryption key must be 32 bytes"),
+        Some(key) => Ok(Some(ChaCha20Poly1305::new(Key::from_slice(key)))),
+        None => Ok(None),
+    }
+}
+
+#[cfg(not(feature = "encryption"))]
+fn new_cipher(key: Option<&[u8]>) -> Result<Option<Cipher>, &'static str> {
+    match key {
+        Some(_) => Err("encryption support is not enabled"),
+        None => Ok(None),
+    }
+}
+
+fn load_resources_internal<'a>(
+    data: &'a [u8],
+    cipher: Option<Cipher>,
+    decode_blobs: bool,
+) -> Result<ResourceParserIterator<'a>, &'static str> {
     if data.len() < HEADER_V1.len() {
         return Err("error reading 8 byte header");
     }
 
     let header = &data[0..8];
 
+    // Version 3 only adds blob index and resource fields. So both versions are
+    // parsed the same way.
     if header == HEADER_V1 {
-        load_resources_v1(&data[8..])
+        load_resources_v1(&data[8..], 1, cipher, decode_blobs)
+    } else if header == HEADER_V3 {
+        load_resources_v1(&data[8..], 3, cipher, decode_blobs)
     } else {
         Err("unrecognized file format")
     }
 }
 
-fn load_resources_v1<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &'static str> {
+/// Decompress a zstd compressed blob.
+///
+/// No more than `length` bytes are decompressed, so blobs claiming a
+/// different length can't exhaust memory.
+#[cfg(feature = "compression")]
+fn decompress_blob(data: &[u8], length: usize) -> Result<Vec<u8>, &'static str> {
+    let decoder =
+        zstd::stream::read::Decoder::new(data).map_err(|_| "failed decompressing blob")?;
+
+    let mut decompressed = Vec::new();
+    decoder
+        .take((length as u64).saturating_add(1))
+        .read_to_end(&mut decompressed)
+        .map_err(|_| "failed decompressing blob")?;
+
+    if decompressed.len() != length {
+        return Err("decompressed blob length mismatch");
+    }
+
+    Ok(decompressed)
+}
+
+#[cfg(not(feature = "compression"))]
+fn decompress_blob(_data: &[u8], _length: usize) -> Result<Vec<u8>, &'static str> {
+    Err("compression support is not enabled")
+}
+
+/// Decrypt the data of an encrypted blob described by an `EncodedBlob`.
+#[cfg(feature = "encryption")]
+fn decrypt_blob(cipher: &Cipher, blob: &EncodedBlob, data: &[u8]) -> Result<Vec<u8>, &'static str> {
+    if data.len() < ENCRYPTED_BLOB_NONCE_LENGTH {
+        return Err("encrypted blob too short");
+    }
+    let (nonce, ciphertext) = data.split_at(ENCRYPTED_BLOB_NONCE_LENGTH);
+
+    cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: &encrypted_blob_associated_data(blob.field, blob.index),
+            },
+        )
+        .map_err(|_| "failed decrypting blob")
+}
+
+#[cfg(not(feature = "encryption"))]
+fn decrypt_blob(
+    cipher: &Cipher,
+    _blob: &EncodedBlob,
+    _data: &[u8],
+) -> Result<Vec<u8>, &'static str> {
+    match *cipher {}
+}
+
+/// Decrypt and decompress the data of a blob described by an `EncodedBlob`.
+fn decode_blob_data(
+    blob: &EncodedBlob,
+    data: &[u8],
+    cipher: Option<&Cipher>,
+) -> Result<Vec<u8>, &'static str> {
+    let data = if blob.encrypted {
+        let cipher = cipher.ok_or("packed resources data is encrypted; a key is required")?;
+
+        Cow::Owned(decrypt_blob(cipher, blob, data)?)
+    } else {
+        Cow::Borrowed(data)
+    };
+
+    if blob.compressed {
+        decompress_blob(&data, blob.length)
+    } else if data.len() != blob.length {
+        Err("decrypted blob length mismatch")
+    } else {
+        Ok(data.into_owned())
+    }
+}
+
+/// Decode the data of a blob held by a resource.
+///
+/// `data` is the data `resource` holds for `field`, or for the blob named
+/// `name` for fields holding a mapping of names to blobs. Blobs described
+/// by the resource's `encoded_blobs` are decrypted with `key` and
+/// decompressed into owned memory. Other blobs are borrowed.
+pub fn decode_blob<'b>(
+    resource: &Resource<u8>,
+    field: ResourceField,
+    name: Option<&str>,
+    data: &'b [u8],
+    key: Option<&[u8]>,
+) -> Result<Cow<'b, [u8]>, &'static str> {
+    match resource.encoded_blob(field, name) {
+        Some(blob) => Ok(Cow::Owned(decode_blob_data(
+            blob,
+            data,
+            new_cipher(key)?.as_ref(),
+        )?)),
+        None => Ok(Cow::Borrowed(data)),
+    }
+}
+
+/// Obtain the data a resource holds for a blob described by an `EncodedBlob`.
+fn encoded_blob_data<'r, 'a>(
+    resource: &'r mut Resource<'a, u8>,
+    blob: &EncodedBlob,
+) -> Option<&'r mut Cow<'a, [u8]>> {
+    let resources = match blob.field {
+        ResourceField::InMemorySource => return resource.in_memory_source.as_mut(),
+        ResourceField::InMemoryBytecode => return resource.in_memory_bytecode.as_mut(),
+        ResourceField::InMemoryBytecodeOpt1 => return resource.in_memory_bytecode_opt1.as_mut(),
+        ResourceField::InMemoryBytecodeOpt2 => return resource.in_memory_bytecode_opt2.as_mut(),
+        ResourceField::InMemoryExtensionModuleSharedLibrary => {
+            return resource.in_memory_extension_module_shared_library.as_mut()
+        }
+        ResourceField::InMemorySharedLibrary => return resource.in_memory_shared_library.as_mut(),
+        ResourceField::InMemoryResourcesData => resource.in_memory_package_resources.as_mut(),
+        ResourceField::InMemoryDistributionResource => {
+            resource.in_memory_distribution_resources.as_mut()
+        }
+        _ => None,
+    };
+
+    match (resources, &blob.name) {
+        (Some(resources), Some(name)) => resources.get_mut(name.as_ref()),
+        _ => None,
+    }
+}
+
+/// Decode all blobs a resource holds in the form they are stored in.
+///
+/// Returns the resource with decoded data in place of its `encoded_blobs`.
+/// `key` decrypts encrypted blobs.
+pub fn decode_resource<'a>(
+    resource: Resource<'a, u8>,
+    key: Option<&[u8]>,
+) -> Result<Resource<'a, u8>, &'static str> {
+    decode_resource_with(resource, new_cipher(key)?.as_ref())
+}
+
+fn decode_resource_with<'a>(
+    mut resource: Resource<'a, u8>,
+    cipher: Option<&Cipher>,
+) -> Result<Resource<'a, u8>, &'static str> {
+    let blobs = match resource.encoded_blobs.take() {
+        Some(blobs) => blobs,
+        None => return Ok(resource),
+    };
+
+    for blob in &blobs {
+        let data = encoded_blob_data(&mut resource, blob).ok_or("encoded blob has no data")?;
+
+        *data = Cow::Owned(decode_blob_data(blob, data, cipher)?);
+    }
+
+    Ok(resource)
+}
+
+/// Maximum number of candidates `find_packed_resources()` parses entirely.
+const MAX_PACKED_RESOURCES_CANDIDATES: usize = 64;
+
+/// Whether the lengths in the header of packed resources data fit in `data`.
+///
+/// `data` begins with the header magic. This is a cheap check rejecting most
+/// occurrences of the magic that don't begin packed resources data.
+fn packed_resources_header_fits(data: &[u8]) -> bool {
+    let mut reader = Cursor::new(&data[HEADER_V1.len()..]);
+
+    let (blob_index_length, resources_count, resources_index_length) = match (
+        reader.read_u8(),
+        reader.read_u32::<LittleEndian>(),
+        reader.read_u32::<LittleEndian>(),
+        reader.read_u32::<LittleEndian>(),
+    ) {
+        (Ok(_), Ok(blob_index_length), Ok(resources_count), Ok(resources_index_length)) => (
+            blob_index_length as u64,
+            resources_count as u64,
+            resources_index_length as u64,
+        ),
+        _ => return false,
+    };
+
+    // Every resource occupies at least a byte of the resources index.
+    resources_count <= resources_index_length
+        && reader.position() + blob_index_length + resources_index_length
+            <= reader.get_ref().len() as u64
+}
+
+/// Find packed resources data within other data, such as an executable.
+///
+/// Returns the offset of packed resources data that parses entirely and
+/// fits within `data`. Data holding resources is preferred over empty data,
+/// as the header may appear elsewhere, e.g. in the code of a parser. `key`
+/// is the key to parse encrypted data with.
+///
+/// Occurrences of the header whose lengths don't fit in `data` are skipped
+/// and at most `MAX_PACKED_RESOURCES_CANDIDATES` others are parsed, so
+/// searching large data with many occurrences of the header remains fast.
+pub fn find_packed_resources(data: &[u8], key: Option<&[u8]>) -> Option<usize> {
+    let mut empty_offset = None;
+    let mut candidates = 0;
+
+    for (offset, window) in data.windows(HEADER_V1.len()).enumerate() {
+        if window != HEADER_V1 && window != HEADER_V3 {
+            continue;
+        }
+
+        let candidate = &data[offset..];
+
+        if !packed_resources_header_fits(candidate) {
+            continue;
+        }
+
+        if candidates == MAX_PACKED_RESOURCES_CANDIDATES {
+            break;
+        }
+        candidates += 1;
+
+        let resources = match key {
+            Some(key) => load_resources_encrypted(candidate, key),
+            None => load_resources(candidate),
+        };
+
+        let resources = match resources {
+            Ok(resources) => resources,
+            Err(_) => continue,
+        };
+
+        if resources.data_length() > candidate.len() {
+            continue;
+        }
+
+        let empty = resources.expected_resources_count() == 0;
+
+        if resources.collect::<Result<Vec<_>, &'static str>>().is_err() {
+            continue;
+        }
+
+        if !empty {
+            return Some(offset);
+        } else if empty_offset.is_none() {
+            empty_offset = Some(offset);
+        }
+    }
+
+    empty_offset
+}
+
+/// Verify the ed25519 signature of packed resources data.
+///
+/// `signature` is the signature produced by `writer::sign_packed_resources()`
+/// and `public_key` the 32 byte public key of the signing key. Without the
+/// `signing` feature, verification always fails.
+#[cfg(feature = "signing")]
+pub fn verify_packed_resources(
+    data: &[u8],
+    signature: &[u8],
+    public_key: &[u8],
+) -> Result<(), &'static str> {
+    let public_key = PublicKey::from_bytes(public_key).map_err(|_| "invalid ed25519 public key")?;
+    let signature = Signature::try_from(signature).map_err(|_| "invalid ed25519 signature")?;
+
+    public_key
+        .verify_strict(data, &signature)
+        .map_err(|_| "signature verification failed")
+}
+
+#[cfg(not(feature = "signing"))]
+pub fn verify_packed_resources(
+    _data: &[u8],
+    _signature: &[u8],
+    _public_key: &[u8],
+) -> Result<(), &'static str> {
+    Err("signature verification support is not enabled")
+}
+
+fn load_resources_v1<'a>(
+    data: &'a [u8],
+    version: u8,
+    cipher: Option<Cipher>,
+    decode_blobs: bool,
+) -> Result<ResourceParserIterator<'a>, &'static str> {
     let mut reader = Cursor::new(data);
 
     let blob_section_count = reader
@@ -502,6 +1040,10 @@ fn load_resources_v1<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &
     let mut current_blob_field = None;
     let mut current_blob_raw_payload_length = None;
     let mut current_blob_interior_padding = None;
+    let mut current_blob_references = vec![];
+    let mut current_blob_compressed = vec![];
+    let mut current_blob_encrypted = vec![];
+    let mut current_blob_prefetch_length = None;
     let mut blob_entry_count = 0;
     let mut blob_sections = Vec::with_capacity(blob_section_count as usize);
 
@@ -513,6 +1055,18 @@ fn load_resources_v1<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &
 
             let field_type = BlobSectionField::try_from(field_type)?;
 
+            if version < 3 {
+                match field_type {
+                    BlobSectionField::BlobReferences
+                    | BlobSectionField::CompressedBlobs
+                    | BlobSectionField::EncryptedBlobs
+                    | BlobSectionField::PrefetchLength => {
+                        return Err("blob index field requires format version 3");
+                    }
+                    _ => {}
+                }
+            }
+
             match field_type {
                 BlobSectionField::EndOfIndex => break,
                 BlobSectionField::StartOfEntry => {
@@ -520,6 +1074,10 @@ fn load_resources_v1<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &
                     current_blob_field = None;
                     current_blob_raw_payload_length = None;
                     current_blob_interior_padding = None;
+                    current_blob_references = vec![];
+                    current_blob_compressed = vec![];
+                    current_blob_encrypted = vec![];
+                    current_blob_prefetch_length = None;
                 }
                 BlobSectionField::EndOfEntry => {
                     if current_blob_field.is_none() {
@@ -529,15 +1087,54 @@ fn load_resources_v1<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &
                         return Err("blob raw payload length is required");
                     }
 
+                    if !current_blob_compressed.is_empty() {
+                        let field = ResourceField::try_from(current_blob_field.unwrap())?;
+
+                        if !field.allows_compressed_blobs() {
+                            return Err("blob section does not allow compressed blobs");
+                        }
+
+                        if !cfg!(feature = "compression") {
+                            return Err("compression support is not enabled");
+                        }
+                    }
+
+                    if !current_blob_encrypted.is_empty() {
+                        let field = ResourceField::try_from(current_blob_field.unwrap())?;
+
+                        if !field.allows_encrypted_blobs() {
+                            return Err("blob section does not allow encrypted blobs");
+                        }
+
+                        if cipher.is_none() {
+                            return Err(if cfg!(feature = "encryption") {
+                                "packed resources data is encrypted; a key is required"
+                            } else {
+                                "encryption support is not enabled"
+                            });
+                        }
+                    }
+
+                    if let Some(length) = current_blob_prefetch_length {
+                        if length > current_blob_raw_payload_length.unwrap() {
+                            return Err("prefetch length exceeds raw payload length");
+                        }
+                    }
+
                     blob_sections.push(BlobSection {
                         resource_field: current_blob_field.unwrap(),
                         raw_payload_length: current_blob_raw_payload_length.unwrap(),
                         interior_padding: current_blob_interior_padding,
+                        references: std::mem::take(&mut current_blob_references),
+                        compressed: std::mem::take(&mut current_blob_compressed),
+                        encrypted: std::mem::take(&mut current_blob_encrypted),
+                        prefetch_length: current_blob_prefetch_length,
                     });
 
                     current_blob_field = None;
                     current_blob_raw_payload_length = None;
                     current_blob_interior_padding = None;
+                    current_blob_prefetch_length = None;
                 }
                 BlobSectionField::ResourceFieldType => {
                     let field = reader
@@ -562,6 +1159,76 @@ fn load_resources_v1<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &
                         _ => return Err("invalid value for interior padding field"),
                     });
                 }
+                BlobSectionField::BlobReferences => {
+                    let count = reader
+                        .read_u32::<LittleEndian>()
+                        .map_err(|_| "failed reading blob references count")?;
+
+                    for _ in 0..count {
+                        let index = reader
+                            .read_u32::<LittleEndian>()
+                            .map_err(|_| "failed reading blob reference index")?;
+                        let offset = reader
+                            .read_u64::<LittleEndian>()
+                            .map_err(|_| "failed reading blob reference offset")?;
+
+                        current_blob_references.push((index as usize, offset as usize));
+                    }
+                }
+                BlobSectionField::CompressedBlobs => {
+                    let format = reader
+                        .read_u8()
+                        .map_err(|_| "failed reading blob compression format")?;
+
+                    match BlobCompressionFormat::try_from(format)? {
+                        BlobCompressionFormat::Zstd => {}
+                    }
+
+                    let count = reader
+                        .read_u32::<LittleEndian>()
+                        .map_err(|_| "failed reading compressed blobs count")?;
+
+                    for _ in 0..count {
+                        let index = reader
+                            .read_u32::<LittleEndian>()
+                            .map_err(|_| "failed reading compressed blob index")?;
+                        let length = reader
+                            .read_u64::<LittleEndian>()
+                            .map_err(|_| "failed reading compressed blob length")?;
+
+                        current_blob_compressed.push((index as usize, length as usize));
+                    }
+                }
+                BlobSectionField::EncryptedBlobs => {
+                    let format = reader
+                        .read_u8()
+                        .map_err(|_| "failed reading blob encryption format")?;
+
+                    match BlobEncryptionFormat::try_from(format)? {
+                        BlobEncryptionFormat::ChaCha20Poly1305 => {}
+                    }
+
+                    let count = reader
+                        .read_u32::<LittleEndian>()
+                        .map_err(|_| "failed reading encrypted blobs count")?;
+
+                    for _ in 0..count {
+                        let index = reader
+                            .read_u32::<LittleEndian>()
+                            .map_err(|_| "failed reading encrypted blob index")?;
+                        let length = reader
+                            .read_u64::<LittleEndian>()
+                            .map_err(|_| "failed reading encrypted blob length")?;
+
+                        current_blob_encrypted.push((index as usize, length as usize));
+                    }
+                }
+                BlobSectionField::PrefetchLength => {
+                    let l = reader
+                        .read_u64::<LittleEndian>()
+                        .map_err(|_| "failed reading prefetch length")?;
+                    current_blob_prefetch_length = Some(l as usize);
+                }
             }
         }
     }
@@ -573,35 +1240,93 @@ fn load_resources_v1<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &
     // Array indexing resource field to current payload offset within that section.
     let mut blob_offsets: [Option<BlobSectionReadState>; 256] = [None; 256];
 
-    // Global payload offset where blobs data starts.
+    // Global payload offset where blobs data starts. Lengths and offsets
+    // come from the data, which may be arbitrary bytes. So arithmetic on
+    // them is checked.
     let blob_start_offset: usize =
             // Global header.
-            1 + 4 + 4 + 4
-            + blob_index_length
-            + resources_index_length
-        ;
+            (1 + 4 + 4 + 4usize)
+            .checked_add(blob_index_length)
+            .and_then(|offset| offset.checked_add(resources_index_length))
+            .ok_or("index length overflows")?;
+
+    if blob_start_offset > data.len() {
+        return Err("index extends past end of data");
+    }
+
     // Current offset from start of blobs data.
     let mut current_blob_offset = 0;
+    let mut blob_references = HashMap::new();
+    let mut compressed_blobs = HashMap::new();
+    let mut encrypted_blobs = HashMap::new();
+    let mut prefetch_ranges = Vec::new();
 
     for section in &blob_sections {
-        let section_start_offset = blob_start_offset + current_blob_offset;
+        let section_start_offset = blob_start_offset
+            .checked_add(current_blob_offset)
+            .ok_or("blob section offset overflows")?;
+        let section_end_offset = section_start_offset
+            .checked_add(section.raw_payload_length)
+            .ok_or("blob section length overflows")?;
+
+        if section_end_offset > data.len() {
+            return Err("blob section extends past end of data");
+        }
+
+        // Offsets within data can't overflow once the header is added, as
+        // data follows the header in memory.
+        if let Some(length) = section.prefetch_length {
+            prefetch_ranges.push((HEADER_V1.len() + section_start_offset, length));
+        }
+
         blob_offsets[section.resource_field as usize] = Some(BlobSectionReadState {
             offset: section_start_offset,
             interior_padding: match section.interior_padding {
                 Some(padding) => padding,
                 None => BlobInteriorPadding::None,
             },
+            index: 0,
+            has_references: !section.references.is_empty(),
+            has_compressed: !section.compressed.is_empty(),
+            has_encrypted: !section.encrypted.is_empty(),
         });
-        current_blob_offset += section.raw_payload_length;
+
+        for (index, offset) in &section.references {
+            blob_references.insert(
+                (section.resource_field, *index),
+                blob_start_offset
+                    .checked_add(*offset)
+                    .ok_or("blob reference offset overflows")?,
+            );
+        }
+
+        for (index, length) in &section.compressed {
+            compressed_blobs.insert((section.resource_field, *index), *length);
+        }
+
+        for (index, length) in &section.encrypted {
+            encrypted_blobs.insert((section.resource_field, *index), *length);
+        }
+
+        current_blob_offset = section_end_offset - blob_start_offset;
     }
 
     Ok(ResourceParserIterator {
         done: resources_index_length == 0 || resources_count == 0,
+        version,
         data,
         reader,
         blob_sections: blob_offsets,
+        blob_references,
+        compressed_blobs,
+        encrypted_blobs,
+        cipher,
+        decode_blobs,
+        key_checked: false,
         claimed_resources_count: resources_count,
         read_resources_count: 0,
+        data_length: HEADER_V1.len() + blob_start_offset + current_blob_offset,
+        prefetch_ranges,
     })
 }
 
@@ -610,10 +1335,16 @@ mod tests {
     use {
         super::*,
         crate::data::{BlobInteriorPadding, Resource},
-        crate::writer::write_packed_resources_v1,
+        crate::writer::{
+            write_packed_resources_v1, write_packed_resources_v1_with_options, BlobCompression,
+            WriteOptions,
+        },
         std::collections::BTreeMap,
     };
 
+    #[cfg(feature = "signing")]
+    use crate::writer::{packed_resources_public_key, sign_packed_resources};
+
     #[test]
     fn test_too_short_header() {
         let data = b"foo";
@@ -1351,6 +2082,8 @@ mod tests {
             relative_path_extension_module_shared_library: Some(Cow::from(Path::new("em_path"))),
             relative_path_package_resources: Some(relative_path_resources),
             relative_path_distribution_resources: Some(relative_path_distribution),
+            provenance: Some(Cow::from("distribution:foo==1.0")),
+            encoded_blobs: None,
         };
 
         let mut data = Vec::new();
@@ -1451,6 +2184,39 @@ mod tests {
             distribution.get("resource.txt"),
             Some(&Cow::Borrowed(Path::new("package/resource.txt")))
         );
+
+        assert_eq!(
+            entry.provenance,
+            Some(Cow::Borrowed("distribution:foo==1.0"))
+        );
+    }
+
+    #[test]
+    fn test_provenance() {
+        let resource = Resource {
+            flavor: ResourceFlavor::Module,
+            name: Cow::from("foo"),
+            provenance: Some(Cow::from("distribution:foo==1.0")),
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v1(&[resource.clone()], &mut data, None).unwrap();
+        assert!(data.starts_with(HEADER_V3));
+
+        let loaded = load_resources(&data)
+            .unwrap()
+            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .unwrap();
+        assert_eq!(loaded, vec![resource]);
+
+        // Version 1 doesn't know about provenance.
+        data[7] = 0x01;
+        let mut resources = load_resources(&data).unwrap();
+        assert_eq!(
+            resources.next(),
+            Some(Err("resource field requires format version 3"))
+        );
     }
 
     #[test]
@@ -1479,4 +2245,644 @@ mod tests {
 
         assert_eq!(resources, loaded);
     }
+
+    #[test]
+    #[cfg(feature = "deduplication")]
+    fn test_deduplicated() {
+        let license = b"Permission is hereby granted, free of charge".to_vec();
+
+        let mut foo_resources = HashMap::new();
+        foo_resources.insert(Cow::from("LICENSE"), Cow::from(license.clone()));
+        let mut bar_resources = HashMap::new();
+        bar_resources.insert(Cow::from("LICENSE.txt"), Cow::from(license.clone()));
+
+        let resources: Vec<Resource<u8>> = vec![
+            Resource {
+                flavor: ResourceFlavor::Module,
+                name: Cow::from("foo"),
+                in_memory_source: Some(Cow::from(license.clone())),
+                in_memory_package_resources: Some(foo_resources),
+                ..Resource::default()
+            },
+            Resource {
+                flavor: ResourceFlavor::Module,
+                name: Cow::from("bar"),
+                in_memory_bytecode: Some(Cow::from(b"fake bytecode".to_vec())),
+                in_memory_package_resources: Some(bar_resources),
+                ..Resource::default()
+            },
+        ];
+
+        for padding in &[None, Some(BlobInteriorPadding::Null)] {
+            let mut plain = Vec::new();
+            write_packed_resources_v1(&resources, &mut plain, *padding).unwrap();
+
+            let mut data = Vec::new();
+            let (report, _) = write_packed_resources_v1_with_options(
+                &resources[..],
+                &mut data,
+                &WriteOptions {
+                    interior_padding: *padding,
+                    deduplicate: true,
+                    ..WriteOptions::default()
+                },
+            )
+            .unwrap();
+
+            assert_eq!(report.duplicate_blobs, 2);
+            assert_eq!(plain.len() - data.len(), report.saved_bytes);
+            assert!(plain.starts_with(HEADER_V1));
+            assert!(data.starts_with(HEADER_V3));
+
+            let loaded = load_resources(&data)
+                .unwrap()
+                .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+                .unwrap();
+
+            assert_eq!(resources, loaded);
+
+            // Version 1 doesn't know about blob references.
+            data[7] = 0x01;
+            assert_eq!(
+                load_resources(&data).err(),
+                Some("blob index field requires format version 3")
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "compression", feature = "deduplication"))]
+    fn test_compressed() {
+        let source = b"import os\n".repeat(200);
+        let data = b"some package data\n".repeat(100);
+        let bytecode = b"fake bytecode".repeat(100);
+
+        let mut foo_resources = HashMap::new();
+        foo_resources.insert(Cow::from("data.txt"), Cow::from(data.clone()));
+        let mut bar_resources = HashMap::new();
+        bar_resources.insert(Cow::from("data.txt"), Cow::from(data.clone()));
+
+        let resources: Vec<Resource<u8>> = vec![
+            Resource {
+                flavor: ResourceFlavor::Module,
+                name: Cow::from("foo"),
+                in_memory_source: Some(Cow::from(source.clone())),
+                in_memory_package_resources: Some(foo_resources),
+                ..Resource::default()
+            },
+            Resource {
+                flavor: ResourceFlavor::Module,
+                name: Cow::from("bar"),
+                in_memory_source: Some(Cow::from(b"x = 1\n".to_vec())),
+                in_memory_bytecode: Some(Cow::from(bytecode.clone())),
+                in_memory_package_resources: Some(bar_resources),
+                ..Resource::default()
+            },
+        ];
+
+        for padding in &[None, Some(BlobInteriorPadding::Null)] {
+            let mut plain = Vec::new();
+            write_packed_resources_v1(&resources, &mut plain, *padding).unwrap();
+
+            let mut compressed = Vec::new();
+            let (_, report) = write_packed_resources_v1_with_options(
+                &resources[..],
+                &mut compressed,
+                &WriteOptions {
+                    interior_padding: *padding,
+                    compression: Some(BlobCompression::default()),
+                    ..WriteOptions::default()
+                },
+            )
+            .unwrap();
+
+            // Both sources of foo and bar data, but not the short source.
+            assert_eq!(report.compressed_blobs, 3);
+            assert_eq!(plain.len() - compressed.len(), report.saved_bytes);
+            assert!(plain.starts_with(HEADER_V1));
+            assert!(compressed.starts_with(HEADER_V3));
+
+            let mut deduplicated = Vec::new();
+            let (deduplication_report, report) = write_packed_resources_v1_with_options(
+                &resources[..],
+                &mut deduplicated,
+                &WriteOptions {
+                    interior_padding: *padding,
+                    deduplicate: true,
+                    compression: Some(BlobCompression::default()),
+                    ..WriteOptions::default()
+                },
+            )
+            .unwrap();
+
+            assert_eq!(report.compressed_blobs, 2);
+            assert_eq!(deduplication_report.duplicate_blobs, 1);
+            assert!(deduplicated.len() < compressed.len());
+
+            for data in &[compressed, deduplicated] {
+                let loaded = load_resources(data)
+                    .unwrap()
+                    .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+                    .unwrap();
+
+                assert_eq!(resources, loaded);
+
+                // Bytecode is never compressed, so it is still read without copying.
+                match &loaded[1].in_memory_bytecode {
+                    Some(Cow::Borrowed(_)) => {}
+                    _ => panic!("bytecode should be borrowed"),
+                }
+                match &loaded[0].in_memory_source {
+                    Some(Cow::Owned(_)) => {}
+                    _ => panic!("source should be decompressed"),
+                }
+
+                // Compressed blobs are borrowed as stored until decoded.
+                let encoded = load_resources_encoded(data, None)
+                    .unwrap()
+                    .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+                    .unwrap();
+
+                let foo = &encoded[0];
+                let stored_source = match &foo.in_memory_source {
+                    Some(Cow::Borrowed(data)) => *data,
+                    _ => panic!("source should be borrowed"),
+                };
+                assert!(stored_source.len() < source.len());
+                assert_eq!(
+                    decode_blob(
+                        foo,
+                        ResourceField::InMemorySource,
+                        None,
+                        stored_source,
+                        None
+                    )
+                    .unwrap(),
+                    Cow::Borrowed(&source[..])
+                );
+
+                let stored_data = &foo.in_memory_package_resources.as_ref().unwrap()["data.txt"];
+                assert_eq!(
+                    decode_blob(
+                        foo,
+                        ResourceField::InMemoryResourcesData,
+                        Some("data.txt"),
+                        stored_data,
+                        None
+                    )
+                    .unwrap(),
+                    Cow::Borrowed(&b"some package data\n".repeat(100)[..])
+                );
+
+                // The short source of bar isn't compressed.
+                let bar = &encoded[1];
+                let bar_source = bar.in_memory_source.as_ref().unwrap();
+                assert!(bar
+                    .encoded_blob(ResourceField::InMemorySource, None)
+                    .is_none());
+                assert_eq!(
+                    decode_blob(bar, ResourceField::InMemorySource, None, bar_source, None)
+                        .unwrap(),
+                    Cow::Borrowed(&b"x = 1\n"[..])
+                );
+
+                // Blobs not decompressing to their recorded length are rejected.
+                let mut truncated = foo.clone();
+                truncated.encoded_blobs.as_mut().unwrap()[0].length -= 1;
+                assert_eq!(
+                    decode_resource(truncated, None).err(),
+                    Some("decompressed blob length mismatch")
+                );
+
+                let decoded = encoded
+                    .into_iter()
+                    .map(|resource| decode_resource(resource, None))
+                    .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+                    .unwrap();
+                assert_eq!(resources, decoded);
+            }
+        }
+    }
+
+    #[test]
+    fn test_compressed_blob_invalid_field() {
+        let mut data = b"pyembed\x03".to_vec();
+        // Number of blob sections.
+        data.push(1);
+        // Length of blob index.
+        data.extend_from_slice(&(1u32 + 2 + 9 + 6 + 12 + 1 + 1).to_le_bytes());
+        // Number of resources and length of the resources index.
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.push(BlobSectionField::StartOfEntry.into());
+        data.push(BlobSectionField::ResourceFieldType.into());
+        data.push(ResourceField::InMemoryBytecode.into());
+        data.push(BlobSectionField::RawPayloadLength.into());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.push(BlobSectionField::CompressedBlobs.into());
+        data.push(BlobCompressionFormat::Zstd.into());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.push(BlobSectionField::EndOfEntry.into());
+        data.push(BlobSectionField::EndOfIndex.into());
+        data.push(ResourceField::EndOfIndex.into());
+
+        let res = load_resources(&data);
+        assert_eq!(
+            res.err(),
+            Some("blob section does not allow compressed blobs")
+        );
+
+        // Version 1 doesn't know about compressed blobs.
+        data[7] = 0x01;
+        let res = load_resources(&data);
+        assert_eq!(
+            res.err(),
+            Some("blob index field requires format version 3")
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "signing"))]
+    fn test_verify_signature_not_enabled() {
+        assert_eq!(
+            verify_packed_resources(b"pyembed\x01", &[0u8; 64], &[0u8; 32]),
+            Err("signature verification support is not enabled")
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "signing")]
+    fn test_verify_signature() {
+        let resources = vec![Resource {
+            flavor: ResourceFlavor::Module,
+            name: Cow::from("foo"),
+            in_memory_source: Some(Cow::from(b"import io".to_vec())),
+            ..Resource::default()
+        }];
+
+        let mut data = Vec::new();
+        write_packed_resources_v1(&resources, &mut data, None).unwrap();
+
+        let secret_key = [42u8; 32];
+        let public_key = packed_resources_public_key(&secret_key).unwrap();
+        let signature = sign_packed_resources(&data, &secret_key).unwrap();
+
+        verify_packed_resources(&data, &signature, &public_key).unwrap();
+
+        let mut tampered = data.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 0xff;
+        assert_eq!(
+            verify_packed_resources(&tampered, &signature, &public_key),
+            Err("signature verification failed")
+        );
+
+        let other_key = packed_resources_public_key(&[7u8; 32]).unwrap();
+        assert_eq!(
+            verify_packed_resources(&data, &signature, &other_key),
+            Err("signature verification failed")
+        );
+
+        assert_eq!(
+            verify_packed_resources(&data, &signature[0..32], &public_key),
+            Err("invalid ed25519 signature")
+        );
+        assert_eq!(
+            verify_packed_resources(&data, &signature, &public_key[0..16]),
+            Err("invalid ed25519 public key")
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "encryption"))]
+    fn test_encrypted_not_enabled() {
+        assert_eq!(
+            load_resources_encrypted(b"pyembed\x01", &[42u8; 32]).err(),
+            Some("encryption support is not enabled")
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "compression", feature = "encryption"))]
+    fn test_encrypted() {
+        let key = [42u8; 32];
+        let source = b"import os\n".repeat(200);
+        let data = b"some package data\n".repeat(100);
+        let bytecode = b"fake bytecode".repeat(100);
+
+        let mut foo_resources = HashMap::new();
+        foo_resources.insert(Cow::from("data.txt"), Cow::from(data.clone()));
+        let mut bar_resources = HashMap::new();
+        bar_resources.insert(Cow::from("data.txt"), Cow::from(data.clone()));
+
+        let resources: Vec<Resource<u8>> = vec![
+            Resource {
+                flavor: ResourceFlavor::Module,
+                name: Cow::from("foo"),
+                in_memory_source: Some(Cow::from(source.clone())),
+                in_memory_package_resources: Some(foo_resources),
+                ..Resource::default()
+            },
+            Resource {
+                flavor: ResourceFlavor::Module,
+                name: Cow::from("bar"),
+                in_memory_source: Some(Cow::from(b"x = 1\n".to_vec())),
+                in_memory_bytecode: Some(Cow::from(bytecode.clone())),
+                in_memory_package_resources: Some(bar_resources),
+                ..Resource::default()
+            },
+        ];
+
+        let compression = BlobCompression::default();
+
+        for padding in &[None, Some(BlobInteriorPadding::Null)] {
+            for (deduplicate, compression) in &[
+                (false, None),
+                (true, None),
+                (false, Some(compression)),
+                (true, Some(compression)),
+            ] {
+                let options = WriteOptions {
+                    interior_padding: *padding,
+                    deduplicate: *deduplicate,
+                    compression: *compression,
+                    encryption_key: Some(key.to_vec()),
+                    ..WriteOptions::default()
+                };
+
+                let mut encrypted = Vec::new();
+                write_packed_resources_v1_with_options(&resources[..], &mut encrypted, &options)
+                    .unwrap();
+
+                // Resource data is not stored in the clear.
+                for needle in &[&b"import os"[..], b"some package data", b"fake bytecode"] {
+                    assert!(!encrypted.windows(needle.len()).any(|w| w == *needle));
+                }
+
+                // Output is reproducible.
+                let mut again = Vec::new();
+                write_packed_resources_v1_with_options(&resources[..], &mut again, &options)
+                    .unwrap();
+                assert_eq!(encrypted, again);
+
+                assert_eq!(
+                    load_resources(&encrypted).err(),
+                    Some("packed resources data is encrypted; a key is required")
+                );
+
+                let res = load_resources_encrypted(&encrypted, &[7u8; 32])
+                    .unwrap()
+                    .collect::<Result<Vec<Resource<u8>>, &'static str>>();
+                assert_eq!(res.err(), Some("failed decrypting blob"));
+
+                let loaded = load_resources_encrypted(&encrypted, &key)
+                    .unwrap()
+                    .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+                    .unwrap();
+
+                assert_eq!(resources, loaded);
+
+                // Blobs decoded when accessed hold ciphertext. The key is
+                // still checked when loading.
+                let res = load_resources_encoded(&encrypted, Some(&[7u8; 32]))
+                    .unwrap()
+                    .collect::<Result<Vec<Resource<u8>>, &'static str>>();
+                assert_eq!(res.err(), Some("failed decrypting blob"));
+
+                let encoded = load_resources_encoded(&encrypted, Some(&key))
+                    .unwrap()
+                    .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+                    .unwrap();
+
+                let foo_data =
+                    &encoded[0].in_memory_package_resources.as_ref().unwrap()["data.txt"];
+                let bar_data =
+                    &encoded[1].in_memory_package_resources.as_ref().unwrap()["data.txt"];
+                match foo_data {
+                    Cow::Borrowed(_) => {}
+                    _ => panic!("encrypted data should be borrowed"),
+                }
+                assert_eq!(
+                    decode_blob(
+                        &encoded[1],
+                        ResourceField::InMemoryResourcesData,
+                        Some("data.txt"),
+                        bar_data,
+                        Some(&key)
+                    )
+                    .unwrap(),
+                    Cow::Borrowed(&b"some package data\n".repeat(100)[..])
+                );
+
+                // Identical data is encrypted differently at each place and
+                // can't be moved to another.
+                assert_ne!(foo_data, bar_data);
+                assert_eq!(
+                    decode_blob(
+                        &encoded[1],
+                        ResourceField::InMemoryResourcesData,
+                        Some("data.txt"),
+                        foo_data,
+                        Some(&key)
+                    )
+                    .err(),
+                    Some("failed decrypting blob")
+                );
+
+                let decoded = encoded
+                    .into_iter()
+                    .map(|resource| decode_resource(resource, Some(&key)))
+                    .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+                    .unwrap();
+                assert_eq!(resources, decoded);
+            }
+        }
+
+        // Unencrypted data can be loaded with a key.
+        let mut plain = Vec::new();
+        write_packed_resources_v1(&resources, &mut plain, None).unwrap();
+        let loaded = load_resources_encrypted(&plain, &key)
+            .unwrap()
+            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .unwrap();
+        assert_eq!(resources, loaded);
+
+        assert_eq!(
+            load_resources_encrypted(&plain, &[42u8; 16]).err(),
+            Some("encryption key must be 32 bytes")
+        );
+    }
+
+    #[test]
+    fn test_encrypted_blob_invalid_field() {
+        let mut data = b"pyembed\x03".to_vec();
+        // Number of blob sections.
+        data.push(1);
+        // Length of blob index.
+        data.extend_from_slice(&(1u32 + 2 + 9 + 6 + 12 + 1 + 1).to_le_bytes());
+        // Number of resources and length of the resources index.
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.push(BlobSectionField::StartOfEntry.into());
+        data.push(BlobSectionField::ResourceFieldType.into());
+        data.push(ResourceField::ModuleName.into());
+        data.push(BlobSectionField::RawPayloadLength.into());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.push(BlobSectionField::EncryptedBlobs.into());
+        data.push(BlobEncryptionFormat::ChaCha20Poly1305.into());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.push(BlobSectionField::EndOfEntry.into());
+        data.push(BlobSectionField::EndOfIndex.into());
+        data.push(ResourceField::EndOfIndex.into());
+
+        let res = load_resources(&data);
+        assert_eq!(
+            res.err(),
+            Some("blob section does not allow encrypted blobs")
+        );
+    }
+    #[test]
+    fn test_truncated_blob_data() {
+        let resource = Resource {
+            flavor: ResourceFlavor::Module,
+            name: Cow::from("foo"),
+            in_memory_source: Some(Cow::from(b"source".to_vec())),
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v1(&[resource], &mut data, None).unwrap();
+
+        let resources = load_resources(&data).unwrap();
+        assert_eq!(resources.data_length(), data.len());
+        assert_eq!(resources.expected_resources_count(), 1);
+
+        assert_eq!(
+            load_resources(&data[0..data.len() - 1]).err(),
+            Some("blob section extends past end of data")
+        );
+    }
+
+    #[test]
+    fn test_overflowing_lengths() {
+        // An index length pointing past the end of the data.
+        let mut data = HEADER_V1.to_vec();
+        data.push(0);
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            load_resources(&data).err(),
+            Some("index extends past end of data")
+        );
+
+        // A blob section whose length overflows the offset of the next one.
+        let mut index = vec![
+            BlobSectionField::StartOfEntry as u8,
+            BlobSectionField::ResourceFieldType as u8,
+            ResourceField::ModuleName as u8,
+            BlobSectionField::RawPayloadLength as u8,
+        ];
+        index.extend_from_slice(&u64::MAX.to_le_bytes());
+        index.push(BlobSectionField::EndOfEntry as u8);
+        index.push(BlobSectionField::EndOfIndex as u8);
+
+        let mut data = HEADER_V1.to_vec();
+        data.push(1);
+        data.extend_from_slice(&(index.len() as u32).to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&index);
+        assert!(load_resources(&data).is_err());
+        assert_eq!(find_packed_resources(&data, None), None);
+    }
+
+    #[test]
+    fn test_find_packed_resources_candidates() {
+        let resource = Resource {
+            flavor: ResourceFlavor::Module,
+            name: Cow::from("foo"),
+            in_memory_source: Some(Cow::from(b"source".to_vec())),
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v1(&[resource], &mut data, None).unwrap();
+
+        // Headers whose lengths exceed the data aren't candidates.
+        let mut haystack = Vec::new();
+        for _ in 0..10000 {
+            haystack.extend_from_slice(HEADER_V1);
+            haystack.extend_from_slice(&[0xff; 13]);
+        }
+        let offset = haystack.len();
+        haystack.extend_from_slice(&data);
+
+        assert_eq!(find_packed_resources(&haystack, None), Some(offset));
+
+        // Only a limited number of candidates is parsed.
+        let mut empty = Vec::new();
+        write_packed_resources_v1::<Resource<u8>, _>(&[], &mut empty, None).unwrap();
+
+        let mut haystack = Vec::new();
+        for _ in 0..MAX_PACKED_RESOURCES_CANDIDATES {
+            haystack.extend_from_slice(&empty);
+        }
+        let offset = haystack.len();
+        haystack.extend_from_slice(&data);
+
+        assert_eq!(find_packed_resources(&haystack, None), Some(0));
+        assert_eq!(
+            find_packed_resources(&haystack[8..], None),
+            Some(offset - 8)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "encryption")]
+    fn test_find_packed_resources() {
+        let resource = Resource {
+            flavor: ResourceFlavor::Module,
+            name: Cow::from("foo"),
+            in_memory_source: Some(Cow::from(b"source".to_vec())),
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v1(&[resource.clone()], &mut data, None).unwrap();
+
+        assert_eq!(find_packed_resources(&data, None), Some(0));
+        assert_eq!(find_packed_resources(b"no resources here", None), None);
+
+        // Empty packed resources data and a bare header precede the data.
+        let mut haystack = b"junk".to_vec();
+        write_packed_resources_v1::<Resource<u8>, _>(&[], &mut haystack, None).unwrap();
+        haystack.extend_from_slice(b"pyembed\x01\xff more junk");
+        let offset = haystack.len();
+        haystack.extend_from_slice(&data);
+        haystack.extend_from_slice(b"trailing junk");
+
+        assert_eq!(find_packed_resources(&haystack, None), Some(offset));
+        assert_eq!(find_packed_resources(&haystack[0..offset], None), Some(4));
+
+        // Encrypted data is only found with its key.
+        let key = [42u8; 32];
+        let mut encrypted = b"junk".to_vec();
+        write_packed_resources_v1_with_options(
+            &[resource][..],
+            &mut encrypted,
+            &WriteOptions {
+                encryption_key: Some(key.to_vec()),
+                ..WriteOptions::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(find_packed_resources(&encrypted, Some(&key)), Some(4));
+        assert_eq!(find_packed_resources(&encrypted, None), None);
+    }
 }
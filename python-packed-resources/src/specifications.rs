@@ -87,6 +87,29 @@ of different sections holding data for different fields for different resources.
 But there is no internal structure or separators: all the individual
 blobs are just laid out next to each other.
 
+Blob data starts `resources_index_length` bytes after the start of the
+*resources index*. Bytes between the *end of index* marker of the
+*resources index* and the start of blob data are padding and must be
+ignored. Writers use this to align blob data.
+
+## `pyembed\x03`
+
+Version 3 of the embedded resources data.
+
+The layout is identical to version 1. Version 3 only adds fields that
+readers of version 1 don't know about. The following fields require the
+`pyembed\x03` header and must be rejected in data with the `pyembed\x01`
+header:
+
+* The *blob references* (`0x05`) blob index field.
+* The *compressed blobs* (`0x06`) blob index field.
+* The *encrypted blobs* (`0x07`) blob index field.
+* The *prefetch length* (`0x08`) blob index field.
+* The *provenance* (`0x16`) resource field.
+
+Writers emit the `pyembed\x01` header when none of these fields are
+present, so data not using them remains readable by readers of version 1.
+
 ## Blob Field Types
 
 The Blob Index allows attributing a sparse set of metadata with every blob
@@ -113,7 +136,10 @@ the resource field type value (see section below).
 
 `0x03` - Raw payload length. This field defines the raw length in bytes of
 the blob section in the payload. The `u64` containing that length will
-immediately follow this `u8`.
+immediately follow this `u8`. The raw payload length may exceed the length
+of the blobs stored in the section. Bytes following the last blob of a
+section are padding and must be ignored. Writers use this to align the
+start of the next section.
 
 `0x04` - Interior padding mechanism. This field defines interior padding
 between elements in the blob section. Following this `u8` is another `u8`
@@ -123,6 +149,56 @@ is assumed. If the payload data logically consists of discrete resources
 (e.g. Python package resource files), then padding applies to these
 sub-elements as well.
 
+`0x05` - Blob references. This field declares blobs in this section whose
+data is not stored in the section itself but is identical to data stored
+elsewhere in the blob data. Following this `u8` is a `u32` holding the number
+of references. Each reference consists of a `u32` holding the index of the blob
+within this section (counting every blob read for this section's resource
+field, in order) followed by a `u64` holding the offset of the blob's data
+from the start of blob data. Referenced blobs do not occupy space in the
+section and do not advance the section's read offset. Writers use this
+to store identical content (e.g. license files shipped by multiple packages)
+only once.
+
+`0x06` - Compressed blobs. This field declares blobs in this section whose
+data is stored compressed. Following this `u8` is a `u8` denoting the
+compression format. `0x01` indicates each blob is a zstandard frame.
+Following is a `u32` holding the number of compressed blobs. Each entry
+consists of a `u32` holding the index of the blob within this section
+(counted like for *blob references*) followed by a `u64` holding the
+length of the compressed data. Compressed blobs occupy that many bytes in
+the section, while the length recorded in the resources index is the length
+of the decompressed data. A blob can be both compressed and referenced, in
+which case the referenced data is compressed. Only sections holding
+in-memory module source (`0x06`), in-memory resources data (`0x0b`) and
+in-memory distribution resources (`0x0c`) may contain compressed blobs, and
+within the latter two only file data and not file names may be compressed.
+
+`0x07` - Encrypted blobs. This field declares blobs in this section whose
+data is stored encrypted. Following this `u8` is a `u8` denoting the
+encryption format. `0x01` indicates ChaCha20-Poly1305 with a 32 byte key.
+Following is a `u32` holding the number of encrypted blobs. Each entry
+consists of a `u32` holding the index of the blob within this section
+(counted like for *blob references*) followed by a `u64` holding the
+length of the encrypted data. Encrypted data is a 12 byte nonce followed by
+the ciphertext and its 16 byte authentication tag. The associated data
+authenticated along with the ciphertext is 5 bytes: the `u8` resource field
+type of the section followed by the blob's index within the section as a
+`u32`. Decryption fails for a blob moved to another section or index.
+Encrypted blobs occupy that many bytes in the section. If a blob is also compressed, the compressed
+data is encrypted and the length declared by the *compressed blobs* field is
+the length of the decrypted data. Only sections holding in-memory data may
+contain encrypted blobs. Within in-memory resources data (`0x0b`) and
+in-memory distribution resources (`0x0c`), only file data and not file names
+may be encrypted. Resource names are never encrypted.
+
+`0x08` - Prefetch length. Following this `u8` is a `u64` holding the number
+of bytes at the start of the section that are likely to be read soon after
+the data is loaded, e.g. the data of modules imported when an application
+starts. It must not exceed the raw payload length. Readers may use this to
+ask the operating system to read these bytes ahead of time. It has no
+effect on the meaning of the data.
+
 ## Resource Field Types
 
 The Resources Index allows attributing a sparse set of metadata
@@ -226,6 +302,11 @@ The number of files being described is contained in a `u32` that immediately
 follows this byte. Following this `u32` is an array of `(u16, u32)` denoting
 the distribution file name and filesystem path to that distribution file.
 
+`0x16` - Provenance. A `u16` holding the length of a UTF-8 string describing
+where the resource came from (e.g. `distribution:foo==1.0`) immediately
+follows. This field is informational and doesn't influence loading. It
+requires the `pyembed\x03` header.
+
 ## Resource Flavors
 
 The data format allows defining different types/flavors of resources.
@@ -289,13 +370,28 @@ to copy memory in order to reference entries. In Rust speak, we should
 be able to hold `&[u8]` references everywhere.
 
 There is no checksumming of the data because we don't want to incur
-I/O overhead to read the entire blob. It could be added as an optional
-feature.
-
-A potential area for optimization is use of general compression. Various
-fields should compress well - either in streaming mode or by utilizing
-compression dictionaries. Compression would undermine 0-copy, of course.
-But in environments where we want to optimize for size, it could be
-desirable.
+I/O overhead to read the entire blob. Data can instead be signed with an
+ed25519 key. The 64 byte signature covers the entire data structure and
+is stored separately from it, e.g. in a `.sig` file next to a packed
+resources file, so the format is unchanged and readers that don't verify
+signatures don't pay for them.
+
+Individual blobs can be compressed to optimize for size. Compression
+undermines 0-copy, so it is limited to fields whose data is copied when
+it is accessed anyway and never applies to bytecode, which is read on
+every import. Compressing blobs individually rather than entire sections
+keeps reading any resource independent of the others.
+
+Blobs can also be encrypted, so resources aren't readable from the file
+at rest. This also undermines 0-copy: encrypted blobs are decrypted into
+owned memory when they are accessed, not when the data is loaded. The key is not part of the data
+and must be supplied by the reader. Names remain in cleartext so resources
+can still be indexed without a key.
+
+Resources can be ordered so the ones read when an application starts come
+first, grouping their data at the start of each section. Together with
+sections aligned to page boundaries and the *prefetch length*, this keeps
+the pages touched during startup few and contiguous, which matters most
+when the data is read from a cold cache.
 
 */
@@ -5,19 +5,35 @@
 /*! Serializing of structures into packed resources blobs. */
 
 use {
-    super::data::{BlobInteriorPadding, BlobSectionField, Resource, ResourceField, HEADER_V1},
+    super::data::{
+        BlobCompressionFormat, BlobEncryptionFormat, BlobInteriorPadding, BlobSectionField,
+        Resource, ResourceField, HEADER_V1, HEADER_V3,
+    },
     anyhow::{anyhow, Context, Result},
     byteorder::{LittleEndian, WriteBytesExt},
-    std::collections::BTreeMap,
+    std::borrow::Cow,
+    std::collections::{BTreeMap, HashMap},
     std::convert::TryFrom,
     std::io::Write,
     std::path::Path,
 };
 
+#[cfg(feature = "signing")]
+use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signer};
+#[cfg(any(feature = "encryption", feature = "deduplication"))]
+use sha2::{Digest, Sha256};
 #[cfg(unix)]
 use std::os::unix::ffi::OsStrExt;
 #[cfg(windows)]
 use std::os::windows::ffi::OsStrExt;
+#[cfg(feature = "encryption")]
+use {
+    super::data::encrypted_blob_associated_data,
+    chacha20poly1305::{
+        aead::{Aead, NewAead, Payload},
+        ChaCha20Poly1305, Key, Nonce,
+    },
+};
 
 #[cfg(unix)]
 fn path_bytes_length(p: &Path) -> usize {
@@ -47,14 +63,400 @@ fn path_to_bytes(p: &Path) -> Vec<u8> {
     }
 }
 
+/// Length of the blob references field header in the blob index.
+const BLOB_REFERENCES_HEADER_LENGTH: usize = 1 + 4;
+
+/// Length of a single blob reference in the blob index.
+const BLOB_REFERENCE_LENGTH: usize = 4 + 8;
+
+/// Blobs not larger than this are never deduplicated.
+///
+/// Referencing them could take more space than the data they hold.
+const MINIMUM_DEDUPLICATED_BLOB_LENGTH: usize =
+    BLOB_REFERENCES_HEADER_LENGTH + BLOB_REFERENCE_LENGTH + 1;
+
+/// Length of the compressed blobs field header in the blob index.
+const COMPRESSED_BLOBS_HEADER_LENGTH: usize = 1 + 1 + 4;
+
+/// Length of a single compressed blob entry in the blob index.
+const COMPRESSED_BLOB_LENGTH: usize = 4 + 8;
+
+/// Length of the encrypted blobs field header in the blob index.
+const ENCRYPTED_BLOBS_HEADER_LENGTH: usize = 1 + 1 + 4;
+
+/// Length of a single encrypted blob entry in the blob index.
+const ENCRYPTED_BLOB_LENGTH: usize = 4 + 8;
+
+/// Blob fields, in the order their sections are written.
+const BLOB_FIELDS: &[ResourceField] = &[
+    ResourceField::ModuleName,
+    ResourceField::InMemorySource,
+    ResourceField::InMemoryBytecode,
+    ResourceField::InMemoryBytecodeOpt1,
+    ResourceField::InMemoryBytecodeOpt2,
+    ResourceField::InMemoryExtensionModuleSharedLibrary,
+    ResourceField::InMemoryResourcesData,
+    ResourceField::InMemoryDistributionResource,
+    ResourceField::InMemorySharedLibrary,
+    ResourceField::SharedLibraryDependencyNames,
+    ResourceField::RelativeFilesystemModuleSource,
+    ResourceField::RelativeFilesystemModuleBytecode,
+    ResourceField::RelativeFilesystemModuleBytecodeOpt1,
+    ResourceField::RelativeFilesystemModuleBytecodeOpt2,
+    ResourceField::RelativeFilesystemExtensionModuleSharedLibrary,
+    ResourceField::RelativeFilesystemPackageResources,
+    ResourceField::RelativeFilesystemDistributionResource,
+    ResourceField::Provenance,
+];
+
+/// Describes the savings from deduplicating blob data.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BlobDeduplicationReport {
+    /// Number of blobs that were replaced by a reference to an identical blob.
+    pub duplicate_blobs: usize,
+
+    /// Number of bytes the packed resources data shrunk by.
+    ///
+    /// This accounts for the size of the references in the blob index.
+    pub saved_bytes: usize,
+}
+
+/// Describes how blobs are compressed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlobCompression {
+    /// The zstandard compression level.
+    pub level: i32,
+
+    /// Blobs shorter than this many bytes are stored uncompressed.
+    pub minimum_length: usize,
+}
+
+impl Default for BlobCompression {
+    fn default() -> Self {
+        Self {
+            level: 3,
+            minimum_length: 256,
+        }
+    }
+}
+
+/// Describes the savings from compressing blob data.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BlobCompressionReport {
+    /// Number of blobs that were stored compressed.
+    pub compressed_blobs: usize,
+
+    /// Number of bytes the packed resources data shrunk by.
+    ///
+    /// This accounts for the size of the compressed blob entries in the
+    /// blob index.
+    pub saved_bytes: usize,
+}
+
+/// Describes how resources are laid out in packed resources data.
+///
+/// Resources read when an application starts can be grouped at the start of
+/// each blob section and sections aligned to page boundaries, so starting
+/// the application touches fewer pages.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResourcesLayout {
+    /// Names of resources to write first, in this order.
+    ///
+    /// Typically the modules an application imports when it starts, in the
+    /// order they are imported. Blob sections record how many bytes at their
+    /// start hold data of these resources so readers can prefetch them.
+    pub hot_resources: Vec<String>,
+
+    /// Align blob data and every blob section to a multiple of this many bytes.
+    ///
+    /// Typically the page size. Alignment is relative to the start of the
+    /// data, so it only carries over to memory if the data is loaded at an
+    /// aligned address, as memory mapped packed resources files are.
+    pub alignment: Option<usize>,
+}
+
+/// Options controlling how packed resources data is written.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WriteOptions {
+    /// Padding to write after every blob.
+    pub interior_padding: Option<BlobInteriorPadding>,
+
+    /// Store blobs identical to a blob written before them once.
+    ///
+    /// Duplicates are replaced by a reference to the first blob in the blob
+    /// index. This is common when multiple packages ship identical data
+    /// files, such as license texts, or bytecode is identical across
+    /// optimization levels. Blobs are identified by their SHA-256 digest,
+    /// so only digests of written blobs are held in memory. Requires the
+    /// `deduplication` feature.
+    pub deduplicate: bool,
+
+    /// Compress module source and package and distribution resource data.
+    ///
+    /// Blobs are stored zstandard compressed if they are at least
+    /// `minimum_length` bytes long and compressing them saves space.
+    /// Readers decompress these blobs, so loading them is slower and
+    /// requires copying. Bytecode is never compressed. Requires the
+    /// `compression` feature.
+    pub compression: Option<BlobCompression>,
+
+    /// 32 byte key to encrypt resource data with.
+    ///
+    /// Module source and bytecode, extension modules, shared libraries and
+    /// the data of package and distribution resources are encrypted with
+    /// ChaCha20-Poly1305, after being compressed. Resource names and other
+    /// metadata are not encrypted. See `parser::load_resources_encrypted()`.
+    /// Requires the `encryption` feature.
+    pub encryption_key: Option<Vec<u8>>,
+
+    /// How resources are ordered and aligned.
+    pub layout: ResourcesLayout,
+}
+
+/// Resolve the position of each hot resource of `layout`.
+///
+/// The first mention of a resource determines its position.
+fn hot_resource_positions(layout: &ResourcesLayout) -> HashMap<&str, usize> {
+    layout
+        .hot_resources
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(position, name)| (name.as_str(), position))
+        .collect()
+}
+
+/// Number of bytes needed to advance `offset` to a multiple of `alignment`.
+fn alignment_padding(offset: usize, alignment: Option<usize>) -> usize {
+    match alignment {
+        Some(alignment) => (alignment - offset % alignment) % alignment,
+        None => 0,
+    }
+}
+
+/// Whether the blob at `index` among the blobs of `field` may be compressed.
+///
+/// Package and distribution resources alternate between the name and the
+/// data of each file. Names are never compressed.
+fn is_compressible_blob(field: ResourceField, index: usize) -> bool {
+    match field {
+        ResourceField::InMemorySource => true,
+        ResourceField::InMemoryResourcesData | ResourceField::InMemoryDistributionResource => {
+            index % 2 == 1
+        }
+        _ => false,
+    }
+}
+
+/// Whether the blob at `index` among the blobs of `field` may be encrypted.
+///
+/// Like compression, names of package and distribution resources are never
+/// encrypted.
+fn is_encryptable_blob(field: ResourceField, index: usize) -> bool {
+    match field {
+        ResourceField::InMemoryResourcesData | ResourceField::InMemoryDistributionResource => {
+            index % 2 == 1
+        }
+        _ => field.allows_encrypted_blobs(),
+    }
+}
+
+/// Cipher encrypting blobs.
+#[cfg(feature = "encryption")]
+type Cipher = ChaCha20Poly1305;
+
+/// Without the `encryption` feature, no cipher can be constructed.
+#[cfg(not(feature = "encryption"))]
+enum Cipher {}
+
+/// Construct the cipher encrypting blobs with a 32 byte `key`.
+#[cfg(feature = "encryption")]
+fn new_cipher(key: &[u8]) -> Result<Cipher> {
+    Ok(ChaCha20Poly1305::new(Key::from_slice(key)))
+}
+
+#[cfg(not(feature = "encryption"))]
+fn new_cipher(_key: &[u8]) -> Result<Cipher> {
+    Err(anyhow!("encryption support is not enabled"))
+}
+
+/// Derive the nonce to encrypt a blob with.
+///
+/// The nonce is derived from the key, the associated data and the data so
+/// output is reproducible while distinct data or identical data stored at
+/// distinct places is never encrypted with the same nonce.
+#[cfg(feature = "encryption")]
+fn encryption_nonce(key: &[u8], associated_data: &[u8], data: &[u8]) -> [u8; 12] {
+    let mut hasher = Sha256::new();
+    hasher.update(key);
+    hasher.update(associated_data);
+    hasher.update(data);
+
+    let mut nonce = [0u8; 12];
+    nonce.copy_from_slice(&hasher.finalize()[0..12]);
+
+    nonce
+}
+
+/// Encrypt the data of the blob at `index` among the blobs of `field`.
+///
+/// Returns the nonce followed by the ciphertext.
+#[cfg(feature = "encryption")]
+fn encrypt_blob(
+    cipher: &Cipher,
+    key: &[u8],
+    field: ResourceField,
+    index: usize,
+    data: &[u8],
+) -> Result<Vec<u8>> {
+    let associated_data = encrypted_blob_associated_data(field, index);
+    let nonce = encryption_nonce(key, &associated_data, data);
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: data,
+                aad: &associated_data,
+            },
+        )
+        .map_err(|_| anyhow!("error encrypting blob"))?;
+
+    let mut encrypted = nonce.to_vec();
+    encrypted.extend_from_slice(&ciphertext);
+
+    Ok(encrypted)
+}
+
+#[cfg(not(feature = "encryption"))]
+fn encrypt_blob(
+    cipher: &Cipher,
+    _key: &[u8],
+    _field: ResourceField,
+    _index: usize,
+    _data: &[u8],
+) -> Result<Vec<u8>> {
+    match *cipher {}
+}
+
+/// Compress a blob into a zstandard frame.
+#[cfg(feature = "compression")]
+fn compress_blob(blob: &[u8], level: i32) -> Result<Vec<u8>> {
+    zstd::stream::encode_all(blob, level).context("compressing blob")
+}
+
+#[cfg(not(feature = "compression"))]
+fn compress_blob(_blob: &[u8], _level: i32) -> Result<Vec<u8>> {
+    Err(anyhow!("compression support is not enabled"))
+}
+
+/// Digest identifying the content of a blob when deduplicating blobs.
+#[cfg(feature = "deduplication")]
+fn blob_digest(blob: &[u8]) -> Result<[u8; 32]> {
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(&Sha256::digest(blob));
+
+    Ok(digest)
+}
+
+#[cfg(not(feature = "deduplication"))]
+fn blob_digest(_blob: &[u8]) -> Result<[u8; 32]> {
+    Err(anyhow!("deduplication support is not enabled"))
+}
+
+/// A blob as it is stored in packed resources data.
+struct StoredBlob<'b> {
+    data: Cow<'b, [u8]>,
+    /// Length of the compressed data, if the blob is compressed.
+    compressed_length: Option<usize>,
+    /// Length of the encrypted data, if the blob is encrypted.
+    encrypted_length: Option<usize>,
+}
+
+/// Compress and encrypt the blob at `index` among the blobs of `field`.
+///
+/// Blobs are compressed if `compression` is set and it saves space and
+/// then encrypted if `encryption` is set.
+fn store_blob<'b>(
+    field: ResourceField,
+    index: usize,
+    blob: &'b [u8],
+    compression: Option<&BlobCompression>,
+    encryption: Option<(&Cipher, &[u8])>,
+) -> Result<StoredBlob<'b>> {
+    let mut stored = StoredBlob {
+        data: Cow::Borrowed(blob),
+        compressed_length: None,
+        encrypted_length: None,
+    };
+
+    if let Some(compression) = compression {
+        if is_compressible_blob(field, index) && blob.len() >= compression.minimum_length {
+            let compressed = compress_blob(blob, compression.level)?;
+
+            if compressed.len() + COMPRESSED_BLOB_LENGTH < blob.len() {
+                stored.compressed_length = Some(compressed.len());
+                stored.data = Cow::Owned(compressed);
+            }
+        }
+    }
+
+    if let Some((cipher, key)) = encryption {
+        if is_encryptable_blob(field, index) {
+            let encrypted = encrypt_blob(cipher, key, field, index, &stored.data)?;
+
+            stored.encrypted_length = Some(encrypted.len());
+            stored.data = Cow::Owned(encrypted);
+        }
+    }
+
+    Ok(stored)
+}
+
 #[derive(Debug)]
 struct BlobSection {
     resource_field: ResourceField,
     raw_payload_length: usize,
     interior_padding: Option<BlobInteriorPadding>,
+    /// Blobs in this section stored elsewhere in the blobs data.
+    ///
+    /// Tuple is the index of the blob within the section and its offset from
+    /// the start of blobs data.
+    references: Vec<(usize, usize)>,
+    /// Index and stored length of compressed blobs in this section.
+    compressed: Vec<(usize, usize)>,
+    /// Index and stored length of encrypted blobs in this section.
+    encrypted: Vec<(usize, usize)>,
+    /// Number of bytes at the start of this section holding hot resources.
+    prefetch_length: Option<usize>,
+    /// Number of bytes following the blobs of this section to align the next section.
+    alignment_padding: usize,
 }
 
 impl BlobSection {
+    /// Record the compression and encryption of a blob physically written to this section.
+    ///
+    /// Returns the compressed length of the blob.
+    fn add_stored_blob(
+        &mut self,
+        index: usize,
+        blob: &[u8],
+        stored: &StoredBlob,
+        compression_report: &mut BlobCompressionReport,
+    ) -> Option<usize> {
+        if let Some(compressed_length) = stored.compressed_length {
+            self.compressed.push((index, compressed_length));
+            compression_report.compressed_blobs += 1;
+            compression_report.saved_bytes +=
+                blob.len() - compressed_length - COMPRESSED_BLOB_LENGTH;
+        }
+
+        if let Some(encrypted_length) = stored.encrypted_length {
+            self.encrypted.push((index, encrypted_length));
+        }
+
+        stored.compressed_length
+    }
+
     /// Compute length of index entry for version 1 payload format.
     pub fn index_v1_length(&self) -> usize {
         // Start of index entry.
@@ -71,6 +473,27 @@ impl BlobSection {
             index += 2;
         }
 
+        if !self.references.is_empty() {
+            // Field + count + references.
+            index += BLOB_REFERENCES_HEADER_LENGTH + BLOB_REFERENCE_LENGTH * self.references.len();
+        }
+
+        if !self.compressed.is_empty() {
+            // Field + format + count + entries.
+            index +=
+                COMPRESSED_BLOBS_HEADER_LENGTH + COMPRESSED_BLOB_LENGTH * self.compressed.len();
+        }
+
+        if !self.encrypted.is_empty() {
+            // Field + format + count + entries.
+            index += ENCRYPTED_BLOBS_HEADER_LENGTH + ENCRYPTED_BLOB_LENGTH * self.encrypted.len();
+        }
+
+        if self.prefetch_length.is_some() {
+            // Field + value.
+            index += 9;
+        }
+
         // End of index entry.
         index += 1;
 
@@ -98,6 +521,59 @@ impl BlobSection {
                 .context("writing interior padding value")?;
         }
 
+        if !self.references.is_empty() {
+            dest.write_u8(BlobSectionField::BlobReferences.into())
+                .context("writing blob references field")?;
+            dest.write_u32::<LittleEndian>(self.references.len() as u32)
+                .context("writing blob references count")?;
+
+            for (index, offset) in &self.references {
+                dest.write_u32::<LittleEndian>(*index as u32)
+                    .context("writing blob reference index")?;
+                dest.write_u64::<LittleEndian>(*offset as u64)
+                    .context("writing blob reference offset")?;
+            }
+        }
+
+        if !self.compressed.is_empty() {
+            dest.write_u8(BlobSectionField::CompressedBlobs.into())
+                .context("writing compressed blobs field")?;
+            dest.write_u8(BlobCompressionFormat::Zstd.into())
+                .context("writing blob compression format")?;
+            dest.write_u32::<LittleEndian>(self.compressed.len() as u32)
+                .context("writing compressed blobs count")?;
+
+            for (index, length) in &self.compressed {
+                dest.write_u32::<LittleEndian>(*index as u32)
+                    .context("writing compressed blob index")?;
+                dest.write_u64::<LittleEndian>(*length as u64)
+                    .context("writing compressed blob length")?;
+            }
+        }
+
+        if !self.encrypted.is_empty() {
+            dest.write_u8(BlobSectionField::EncryptedBlobs.into())
+                .context("writing encrypted blobs field")?;
+            dest.write_u8(BlobEncryptionFormat::ChaCha20Poly1305.into())
+                .context("writing blob encryption format")?;
+            dest.write_u32::<LittleEndian>(self.encrypted.len() as u32)
+                .context("writing encrypted blobs count")?;
+
+            for (index, length) in &self.encrypted {
+                dest.write_u32::<LittleEndian>(*index as u32)
+                    .context("writing encrypted blob index")?;
+                dest.write_u64::<LittleEndian>(*length as u64)
+                    .context("writing encrypted blob length")?;
+            }
+        }
+
+        if let Some(length) = self.prefetch_length {
+            dest.write_u8(BlobSectionField::PrefetchLength.into())
+                .context("writing prefetch length field")?;
+            dest.write_u64::<LittleEndian>(length as u64)
+                .context("writing prefetch length")?;
+        }
+
         dest.write_u8(BlobSectionField::EndOfEntry.into())
             .context("writing end of index entry")?;
 
@@ -225,6 +701,10 @@ where
             index += 6 * metadata.len();
         }
 
+        if self.provenance.is_some() {
+            index += 3;
+        }
+
         // End of index entry.
         index += 1;
 
@@ -367,6 +847,13 @@ where
                     0
                 }
             }
+            ResourceField::Provenance => {
+                if let Some(provenance) = &self.provenance {
+                    provenance.as_bytes().len()
+                } else {
+                    0
+                }
+            }
         }
     }
 
@@ -496,6 +983,13 @@ where
                     0
                 }
             }
+            ResourceField::Provenance => {
+                if self.provenance.is_some() {
+                    1
+                } else {
+                    0
+                }
+            }
         };
 
         let overhead = match padding {
@@ -588,7 +1082,7 @@ where
             dest.write_u32::<LittleEndian>(l)
                 .context("writing in-memory resources data length")?;
 
-            for (name, value) in resources.iter() {
+            for (name, value) in sorted_entries(resources) {
                 let name_length = u16::try_from(name.as_bytes().len())
                     .context("converting resource name length to u16")?;
                 dest.write_u16::<LittleEndian>(name_length)
@@ -606,7 +1100,7 @@ where
             dest.write_u32::<LittleEndian>(l)
                 .context("writing in-memory package distribution length")?;
 
-            for (name, value) in metadata {
+            for (name, value) in sorted_entries(metadata) {
                 let name_length = u16::try_from(name.as_bytes().len())
                     .context("converting distribution name length to u16")?;
                 dest.write_u16::<LittleEndian>(name_length)
@@ -694,7 +1188,7 @@ where
             dest.write_u32::<LittleEndian>(l)
                 .context("writing relative path resources resources data length")?;
 
-            for (name, path) in resources.iter() {
+            for (name, path) in sorted_entries(resources) {
                 let name_length = u16::try_from(name.as_bytes().len())
                     .context("converting resource name length to u16")?;
                 let path_length = u32::try_from(path_bytes_length(path))
@@ -714,7 +1208,7 @@ where
             dest.write_u32::<LittleEndian>(l)
                 .context("writing relative path distribution data length")?;
 
-            for (name, path) in metadata.iter() {
+            for (name, path) in sorted_entries(metadata) {
                 let name_length = u16::try_from(name.as_bytes().len())
                     .context("converting resource name length to u16")?;
                 let path_length = u32::try_from(path_bytes_length(path))
@@ -726,6 +1220,15 @@ where
             }
         }
 
+        if let Some(provenance) = &self.provenance {
+            let l = u16::try_from(provenance.as_bytes().len())
+                .context("converting provenance length to u16")?;
+            dest.write_u8(ResourceField::Provenance.into())
+                .context("writing provenance field")?;
+            dest.write_u16::<LittleEndian>(l)
+                .context("writing provenance length")?;
+        }
+
         dest.write_u8(ResourceField::EndOfEntry.into())
             .map_err(|_| anyhow!("error writing end of index entry"))?;
 
@@ -733,297 +1236,543 @@ where
     }
 }
 
+/// Obtain the entries of a map of files sorted by name.
+///
+/// The index entries and blobs of files are matched up by their order, so
+/// both are written in this order. Sorting makes the order independent of
+/// the map instance, which keeps output reproducible.
+fn sorted_entries<K: Ord, V>(map: &HashMap<K, V>) -> Vec<(&K, &V)> {
+    let mut entries = map.iter().collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+
+    entries
+}
+
+fn field_data<'b>(value: &'b Option<Cow<'_, [u8]>>) -> Vec<Cow<'b, [u8]>> {
+    value
+        .iter()
+        .map(|data| Cow::Borrowed(data.as_ref()))
+        .collect()
+}
+
+fn field_path<'b>(value: &Option<Cow<'_, Path>>) -> Vec<Cow<'b, [u8]>> {
+    value
+        .iter()
+        .map(|path| Cow::Owned(path_to_bytes(path)))
+        .collect()
+}
+
+impl<'a> Resource<'a, u8> {
+    /// Obtain the blobs holding data for a field, in the order they are written.
+    pub fn field_blobs(&self, field: ResourceField) -> Vec<Cow<'_, [u8]>> {
+        match field {
+            ResourceField::ModuleName => vec![Cow::Borrowed(self.name.as_bytes())],
+            ResourceField::InMemorySource => field_data(&self.in_memory_source),
+            ResourceField::InMemoryBytecode => field_data(&self.in_memory_bytecode),
+            ResourceField::InMemoryBytecodeOpt1 => field_data(&self.in_memory_bytecode_opt1),
+            ResourceField::InMemoryBytecodeOpt2 => field_data(&self.in_memory_bytecode_opt2),
+            ResourceField::InMemoryExtensionModuleSharedLibrary => {
+                field_data(&self.in_memory_extension_module_shared_library)
+            }
+            ResourceField::InMemoryResourcesData => self
+                .in_memory_package_resources
+                .iter()
+                .flat_map(sorted_entries)
+                .flat_map(|(key, value)| {
+                    vec![Cow::Borrowed(key.as_bytes()), Cow::Borrowed(value.as_ref())]
+                })
+                .collect(),
+            ResourceField::InMemoryDistributionResource => self
+                .in_memory_distribution_resources
+                .iter()
+                .flat_map(sorted_entries)
+                .flat_map(|(key, value)| {
+                    vec![Cow::Borrowed(key.as_bytes()), Cow::Borrowed(value.as_ref())]
+                })
+                .collect(),
+            ResourceField::InMemorySharedLibrary => field_data(&self.in_memory_shared_library),
+            ResourceField::SharedLibraryDependencyNames => self
+                .shared_library_dependency_names
+                .iter()
+                .flat_map(|names| names.iter())
+                .map(|name| Cow::Borrowed(name.as_bytes()))
+                .collect(),
+            ResourceField::RelativeFilesystemModuleSource => {
+                field_path(&self.relative_path_module_source)
+            }
+            ResourceField::RelativeFilesystemModuleBytecode => {
+                field_path(&self.relative_path_module_bytecode)
+            }
+            ResourceField::RelativeFilesystemModuleBytecodeOpt1 => {
+                field_path(&self.relative_path_module_bytecode_opt1)
+            }
+            ResourceField::RelativeFilesystemModuleBytecodeOpt2 => {
+                field_path(&self.relative_path_module_bytecode_opt2)
+            }
+            ResourceField::RelativeFilesystemExtensionModuleSharedLibrary => {
+                field_path(&self.relative_path_extension_module_shared_library)
+            }
+            ResourceField::RelativeFilesystemPackageResources => self
+                .relative_path_package_resources
+                .iter()
+                .flat_map(sorted_entries)
+                .flat_map(|(key, path)| {
+                    vec![
+                        Cow::Borrowed(key.as_bytes()),
+                        Cow::Owned(path_to_bytes(path)),
+                    ]
+                })
+                .collect(),
+            ResourceField::RelativeFilesystemDistributionResource => self
+                .relative_path_distribution_resources
+                .iter()
+                .flat_map(sorted_entries)
+                .flat_map(|(key, path)| {
+                    vec![
+                        Cow::Borrowed(key.as_bytes()),
+                        Cow::Owned(path_to_bytes(path)),
+                    ]
+                })
+                .collect(),
+            ResourceField::Provenance => self
+                .provenance
+                .iter()
+                .map(|provenance| Cow::Borrowed(provenance.as_bytes()))
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
 /// Write packed resources data, version 1.
 ///
-/// See the `specifications` module for the format.
-#[allow(clippy::cognitive_complexity)]
+/// See the `specifications` module for the format. See
+/// `write_packed_resources_v1_with_options()` to deduplicate, compress,
+/// encrypt or lay out the data.
 pub fn write_packed_resources_v1<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
     modules: &[T],
     dest: &mut W,
     interior_padding: Option<BlobInteriorPadding>,
 ) -> Result<()> {
-    let mut blob_sections = BTreeMap::new();
-
-    let mut blob_section_count = 0;
-    // 1 for end of index field.
-    let mut blob_index_length = 1;
+    let modules = modules.iter().map(|m| m.as_ref()).collect::<Vec<_>>();
 
-    // 1 for end of index field.
-    let mut module_index_length = 1;
+    write_packed_resources_v1_with_options(
+        modules.as_slice(),
+        dest,
+        &WriteOptions {
+            interior_padding,
+            ..WriteOptions::default()
+        },
+    )?;
 
-    let process_field = |blob_sections: &mut BTreeMap<ResourceField, BlobSection>,
-                         resource: &Resource<u8>,
-                         field: ResourceField| {
-        let padding = match &interior_padding {
-            Some(padding) => *padding,
-            None => BlobInteriorPadding::None,
-        };
+    Ok(())
+}
 
-        let l = resource.field_blob_length(field)
-            + resource.field_blob_interior_padding_length(field, padding);
-        if l > 0 {
-            blob_sections
-                .entry(field)
-                .or_insert_with(|| BlobSection {
-                    resource_field: field,
-                    raw_payload_length: 0,
-                    interior_padding,
-                })
-                .raw_payload_length += l;
-        }
-    };
+/// Provides resources to `write_packed_resources_v1_with_options()`.
+///
+/// Resources are obtained on demand and dropped once written, so sources
+/// can e.g. read resource data from files when asked for a resource instead
+/// of holding all data in memory. Each resource is obtained multiple times
+/// and must have the same content every time.
+pub trait ResourceSource {
+    /// The number of resources this source provides.
+    fn resources_count(&self) -> usize;
+
+    /// Obtain the resource at `index`.
+    fn resource(&self, index: usize) -> Result<Cow<'_, Resource<'_, u8>>>;
+}
 
-    let add_interior_padding = |dest: &mut W| -> Result<()> {
-        if interior_padding == Some(BlobInteriorPadding::Null) {
-            dest.write_all(b"\0")?;
-        }
+impl<'a> ResourceSource for [Resource<'a, u8>] {
+    fn resources_count(&self) -> usize {
+        self.len()
+    }
 
-        Ok(())
-    };
+    fn resource(&self, index: usize) -> Result<Cow<'_, Resource<'_, u8>>> {
+        let resource = self
+            .get(index)
+            .ok_or_else(|| anyhow!("resource index out of range"))?;
 
-    for module in modules {
-        let module = module.as_ref();
-        module_index_length += module.index_v1_length();
-
-        process_field(&mut blob_sections, module, ResourceField::ModuleName);
-        process_field(&mut blob_sections, module, ResourceField::InMemorySource);
-        process_field(&mut blob_sections, module, ResourceField::InMemoryBytecode);
-        process_field(
-            &mut blob_sections,
-            module,
-            ResourceField::InMemoryBytecodeOpt1,
-        );
-        process_field(
-            &mut blob_sections,
-            module,
-            ResourceField::InMemoryBytecodeOpt2,
-        );
-        process_field(
-            &mut blob_sections,
-            module,
-            ResourceField::InMemoryExtensionModuleSharedLibrary,
-        );
-        process_field(
-            &mut blob_sections,
-            module,
-            ResourceField::InMemoryResourcesData,
-        );
-        process_field(
-            &mut blob_sections,
-            module,
-            ResourceField::InMemoryDistributionResource,
-        );
-        process_field(
-            &mut blob_sections,
-            module,
-            ResourceField::InMemorySharedLibrary,
-        );
-        process_field(
-            &mut blob_sections,
-            module,
-            ResourceField::SharedLibraryDependencyNames,
-        );
-        process_field(
-            &mut blob_sections,
-            module,
-            ResourceField::RelativeFilesystemModuleSource,
-        );
-        process_field(
-            &mut blob_sections,
-            module,
-            ResourceField::RelativeFilesystemModuleBytecode,
-        );
-        process_field(
-            &mut blob_sections,
-            module,
-            ResourceField::RelativeFilesystemModuleBytecodeOpt1,
-        );
-        process_field(
-            &mut blob_sections,
-            module,
-            ResourceField::RelativeFilesystemModuleBytecodeOpt2,
-        );
-        process_field(
-            &mut blob_sections,
-            module,
-            ResourceField::RelativeFilesystemExtensionModuleSharedLibrary,
-        );
-        process_field(
-            &mut blob_sections,
-            module,
-            ResourceField::RelativeFilesystemPackageResources,
-        );
-        process_field(
-            &mut blob_sections,
-            module,
-            ResourceField::RelativeFilesystemDistributionResource,
-        );
+        Ok(Cow::Borrowed(resource))
     }
+}
 
-    for section in blob_sections.values() {
-        blob_section_count += 1;
-        blob_index_length += section.index_v1_length();
+impl<'a, 'b> ResourceSource for [&'b Resource<'a, u8>] {
+    fn resources_count(&self) -> usize {
+        self.len()
     }
 
-    dest.write_all(HEADER_V1)?;
+    fn resource(&self, index: usize) -> Result<Cow<'_, Resource<'_, u8>>> {
+        let resource = self
+            .get(index)
+            .ok_or_else(|| anyhow!("resource index out of range"))?;
 
-    dest.write_u8(blob_section_count)?;
-    dest.write_u32::<LittleEndian>(blob_index_length as u32)?;
-    dest.write_u32::<LittleEndian>(modules.len() as u32)?;
-    dest.write_u32::<LittleEndian>(module_index_length as u32)?;
-
-    // Write the blob index.
-    for section in blob_sections.values() {
-        section.write_index_v1(dest)?;
+        Ok(Cow::Borrowed(*resource))
     }
-    dest.write_u8(ResourceField::EndOfIndex.into())?;
+}
 
-    // Write the resources index.
-    for module in modules {
-        module.as_ref().write_index_v1(dest)?;
+/// Write packed resources data, version 1, as described by `options`.
+///
+/// Resources are obtained from `source` as they are needed and their data
+/// is written to `dest` as it goes, so memory usage is bounded by the size
+/// of the largest resource. In exchange, resources are obtained several
+/// times and compression and encryption are performed twice, once to
+/// compute the index and once to write the data.
+///
+/// Returns reports describing how much data was deduplicated and compressed.
+pub fn write_packed_resources_v1_with_options<S: ResourceSource + ?Sized, W: Write>(
+    source: &S,
+    dest: &mut W,
+    options: &WriteOptions,
+) -> Result<(BlobDeduplicationReport, BlobCompressionReport)> {
+    let interior_padding = options.interior_padding;
+    let deduplicate = options.deduplicate;
+    let compression = options.compression.as_ref();
+    let encryption_key = options.encryption_key.as_deref();
+    let layout = &options.layout;
+
+    if let Some(key) = encryption_key {
+        if key.len() != 32 {
+            return Err(anyhow!("encryption key must be 32 bytes"));
+        }
     }
-    dest.write_u8(ResourceField::EndOfIndex.into())?;
 
-    // Write blob data, one field at a time.
-    for module in modules {
-        dest.write_all(module.as_ref().name.as_bytes())?;
-        add_interior_padding(dest)?;
+    if layout.alignment == Some(0) {
+        return Err(anyhow!("alignment must be greater than 0"));
     }
 
-    for module in modules {
-        if let Some(data) = &module.as_ref().in_memory_source {
-            dest.write_all(data)?;
-            add_interior_padding(dest)?;
-        }
+    if deduplicate && !cfg!(feature = "deduplication") {
+        return Err(anyhow!("deduplication support is not enabled"));
     }
 
-    for module in modules {
-        if let Some(data) = &module.as_ref().in_memory_bytecode {
-            dest.write_all(data)?;
-            add_interior_padding(dest)?;
-        }
+    if compression.is_some() && !cfg!(feature = "compression") {
+        return Err(anyhow!("compression support is not enabled"));
     }
 
-    for module in modules {
-        if let Some(data) = &module.as_ref().in_memory_bytecode_opt1 {
-            dest.write_all(data)?;
-            add_interior_padding(dest)?;
+    let cipher = encryption_key.map(new_cipher).transpose()?;
+    let encryption = match (&cipher, encryption_key) {
+        (Some(cipher), Some(key)) => Some((cipher, key)),
+        _ => None,
+    };
+
+    let padding_length = match interior_padding {
+        Some(BlobInteriorPadding::Null) => 1,
+        _ => 0,
+    };
+
+    let resources_count = source.resources_count();
+    let hot_positions = hot_resource_positions(layout);
+
+    // Blob fields in the order their sections are written, with the bit
+    // representing them in `resource_fields`.
+    let field_bits = BLOB_FIELDS
+        .iter()
+        .enumerate()
+        .map(|(i, field)| (*field, 1u32 << i))
+        .collect::<BTreeMap<_, _>>();
+
+    // 1 for end of index field.
+    let mut module_index_length = 1;
+
+    // The fields each resource has blobs for, by index in `source`.
+    let mut resource_fields = Vec::with_capacity(resources_count);
+
+    // Whether any resource has a provenance field.
+    let mut has_provenance = false;
+
+    let mut hot = Vec::new();
+    let mut cold = Vec::new();
+
+    for resource_index in 0..resources_count {
+        let resource = source.resource(resource_index)?;
+
+        if resource.encoded_blobs.is_some() {
+            return Err(anyhow!(
+                "resource {} holds encoded blobs; decode it before writing",
+                resource.name
+            ));
         }
-    }
 
-    for module in modules {
-        if let Some(data) = &module.as_ref().in_memory_bytecode_opt2 {
-            dest.write_all(data)?;
-            add_interior_padding(dest)?;
+        module_index_length += resource.index_v1_length();
+        has_provenance |= resource.provenance.is_some();
+
+        let mut fields = 0;
+        for (field, bit) in &field_bits {
+            if !resource.field_blobs(*field).is_empty() {
+                fields |= bit;
+            }
         }
-    }
 
-    for module in modules {
-        if let Some(data) = &module.as_ref().in_memory_extension_module_shared_library {
-            dest.write_all(data)?;
-            add_interior_padding(dest)?;
+        resource_fields.push(fields);
+
+        match hot_positions.get(resource.name.as_ref()) {
+            Some(position) => hot.push((*position, resource_index)),
+            None => cold.push(resource_index),
         }
     }
 
-    for module in modules {
-        if let Some(resources) = &module.as_ref().in_memory_package_resources {
-            for (key, value) in resources.iter() {
-                dest.write_all(key.as_bytes())?;
-                add_interior_padding(dest)?;
-                dest.write_all(value)?;
-                add_interior_padding(dest)?;
+    // Indices of resources in the order they are written, hot resources first.
+    hot.sort_by_key(|(position, _)| *position);
+    let hot_count = hot.len();
+    let order = hot
+        .into_iter()
+        .map(|(_, resource_index)| resource_index)
+        .chain(cold)
+        .collect::<Vec<_>>();
+
+    let mut report = BlobDeduplicationReport::default();
+    let mut compression_report = BlobCompressionReport::default();
+
+    // Offset from start of blobs data and compressed length of previously
+    // seen blobs, by SHA-256 digest of their content.
+    let mut seen_blobs: HashMap<_, (usize, Option<usize>)> = HashMap::new();
+    // Current offset from start of blobs data.
+    let mut blobs_offset = 0;
+
+    let mut blob_sections = Vec::new();
+
+    // Resolve the blob index one field at a time, in the same order blobs are
+    // written, so deduplication refers to the same blobs as when writing.
+    for (field, bit) in &field_bits {
+        let mut section = BlobSection {
+            resource_field: *field,
+            raw_payload_length: 0,
+            interior_padding,
+            references: vec![],
+            compressed: vec![],
+            encrypted: vec![],
+            prefetch_length: None,
+            alignment_padding: 0,
+        };
+        let mut index = 0;
+        let mut prefetch_length = 0;
+
+        for (position, resource_index) in order.iter().enumerate() {
+            if position == hot_count {
+                prefetch_length = section.raw_payload_length;
             }
-        }
-    }
 
-    for module in modules {
-        if let Some(resources) = &module.as_ref().in_memory_distribution_resources {
-            for (key, value) in resources {
-                dest.write_all(key.as_bytes())?;
-                add_interior_padding(dest)?;
-                dest.write_all(value)?;
-                add_interior_padding(dest)?;
+            if resource_fields[*resource_index] & bit == 0 {
+                continue;
+            }
+
+            let resource = source.resource(*resource_index)?;
+
+            for blob in resource.field_blobs(*field) {
+                let blob_index = index;
+                index += 1;
+
+                // Encrypted blobs are authenticated along with their place, so
+                // they can't be referenced from elsewhere.
+                let encrypted = encryption.is_some() && is_encryptable_blob(*field, blob_index);
+
+                let digest = if deduplicate
+                    && !encrypted
+                    && blob.len() >= MINIMUM_DEDUPLICATED_BLOB_LENGTH
+                {
+                    Some(blob_digest(&blob)?)
+                } else {
+                    None
+                };
+
+                if let Some(digest) = &digest {
+                    if let Some((offset, compressed_length)) = seen_blobs.get(digest) {
+                        // A reference to a compressed blob is decompressed as
+                        // well. If this blob can't be stored the same way, it
+                        // is stored again.
+                        if compressed_length.is_none() || is_compressible_blob(*field, blob_index) {
+                            section.references.push((blob_index, *offset));
+                            report.duplicate_blobs += 1;
+
+                            let mut entries_length = 0;
+                            if let Some(compressed_length) = compressed_length {
+                                section.compressed.push((blob_index, *compressed_length));
+                                entries_length += COMPRESSED_BLOB_LENGTH;
+                            }
+
+                            let stored_length = compressed_length.unwrap_or_else(|| blob.len());
+                            report.saved_bytes +=
+                                (stored_length + padding_length).saturating_sub(entries_length);
+
+                            continue;
+                        }
+                    }
+                }
+
+                let stored = store_blob(*field, blob_index, &blob, compression, encryption)?;
+                let compressed_length =
+                    section.add_stored_blob(blob_index, &blob, &stored, &mut compression_report);
+
+                if let Some(digest) = digest {
+                    seen_blobs
+                        .entry(digest)
+                        .or_insert((blobs_offset + section.raw_payload_length, compressed_length));
+                }
+
+                section.raw_payload_length += stored.data.len() + padding_length;
             }
         }
-    }
 
-    for module in modules {
-        if let Some(data) = &module.as_ref().in_memory_shared_library {
-            dest.write_all(data)?;
-            add_interior_padding(dest)?;
+        if !section.references.is_empty() {
+            report.saved_bytes = report.saved_bytes.saturating_sub(
+                BLOB_REFERENCES_HEADER_LENGTH + BLOB_REFERENCE_LENGTH * section.references.len(),
+            );
         }
-    }
 
-    for module in modules {
-        if let Some(names) = &module.as_ref().shared_library_dependency_names {
-            for name in names {
-                dest.write_all(name.as_bytes())?;
-                add_interior_padding(dest)?;
-            }
+        if !section.compressed.is_empty() {
+            compression_report.saved_bytes = compression_report
+                .saved_bytes
+                .saturating_sub(COMPRESSED_BLOBS_HEADER_LENGTH);
         }
-    }
 
-    for module in modules {
-        if let Some(path) = &module.as_ref().relative_path_module_source {
-            dest.write_all(&path_to_bytes(path))?;
-            add_interior_padding(dest)?;
+        if hot_count == order.len() {
+            prefetch_length = section.raw_payload_length;
         }
-    }
+        if prefetch_length > 0 {
+            section.prefetch_length = Some(prefetch_length);
+        }
+
+        if section.raw_payload_length > 0 || !section.references.is_empty() {
+            section.alignment_padding =
+                alignment_padding(blobs_offset + section.raw_payload_length, layout.alignment);
+            section.raw_payload_length += section.alignment_padding;
 
-    for module in modules {
-        if let Some(path) = &module.as_ref().relative_path_module_bytecode {
-            dest.write_all(&path_to_bytes(path))?;
-            add_interior_padding(dest)?;
+            blobs_offset += section.raw_payload_length;
+            blob_sections.push((section, *bit));
         }
     }
 
-    for module in modules {
-        if let Some(path) = &module.as_ref().relative_path_module_bytecode_opt1 {
-            dest.write_all(&path_to_bytes(path))?;
-            add_interior_padding(dest)?;
-        }
+    // The digests are no longer needed.
+    drop(seen_blobs);
+
+    // Nothing follows the last section, so it doesn't need padding.
+    if let Some((section, _)) = blob_sections.last_mut() {
+        section.raw_payload_length -= section.alignment_padding;
+        section.alignment_padding = 0;
     }
 
-    for module in modules {
-        if let Some(path) = &module.as_ref().relative_path_module_bytecode_opt2 {
-            dest.write_all(&path_to_bytes(path))?;
-            add_interior_padding(dest)?;
-        }
+    // 1 for end of index field.
+    let blob_index_length = 1 + blob_sections
+        .iter()
+        .map(|(section, _)| section.index_v1_length())
+        .sum::<usize>();
+
+    // Blob data starts after the resources index, which is padded so blob
+    // data is aligned.
+    let index_padding = alignment_padding(
+        HEADER_V1.len() + 1 + 4 + 4 + 4 + blob_index_length + module_index_length,
+        layout.alignment,
+    );
+
+    // Blob references, compressed blobs, encrypted blobs, prefetch lengths and
+    // provenance are unknown to readers of version 1 and require the version 3
+    // header.
+    let version_3 = has_provenance
+        || blob_sections.iter().any(|(section, _)| {
+            !section.references.is_empty()
+                || !section.compressed.is_empty()
+                || !section.encrypted.is_empty()
+                || section.prefetch_length.is_some()
+        });
+
+    dest.write_all(if version_3 { HEADER_V3 } else { HEADER_V1 })?;
+
+    dest.write_u8(blob_sections.len() as u8)?;
+    dest.write_u32::<LittleEndian>(blob_index_length as u32)?;
+    dest.write_u32::<LittleEndian>(resources_count as u32)?;
+    dest.write_u32::<LittleEndian>((module_index_length + index_padding) as u32)?;
+
+    // Write the blob index.
+    for (section, _) in &blob_sections {
+        section.write_index_v1(dest)?;
     }
+    dest.write_u8(ResourceField::EndOfIndex.into())?;
 
-    for module in modules {
-        if let Some(path) = &module
-            .as_ref()
-            .relative_path_extension_module_shared_library
-        {
-            dest.write_all(&path_to_bytes(path))?;
-            add_interior_padding(dest)?;
-        }
+    // Write the resources index.
+    for resource_index in &order {
+        source.resource(*resource_index)?.write_index_v1(dest)?;
     }
+    dest.write_u8(ResourceField::EndOfIndex.into())?;
+    dest.write_all(&vec![0; index_padding])?;
+
+    // Write blob data, one field at a time, obtaining only the resources
+    // having data for that field.
+    for (section, bit) in &blob_sections {
+        let field = section.resource_field;
+        // References are recorded in blob order.
+        let mut references = section
+            .references
+            .iter()
+            .map(|(index, _)| *index)
+            .peekable();
+
+        let mut index = 0;
+        let mut written_length = 0;
+
+        for resource_index in &order {
+            if resource_fields[*resource_index] & bit == 0 {
+                continue;
+            }
+
+            let resource = source.resource(*resource_index)?;
+
+            for blob in resource.field_blobs(field) {
+                let blob_index = index;
+                index += 1;
+
+                if references.peek() == Some(&blob_index) {
+                    references.next();
+                    continue;
+                }
+
+                let stored = store_blob(field, blob_index, &blob, compression, encryption)?;
+                dest.write_all(&stored.data)?;
 
-    for module in modules {
-        if let Some(resources) = &module.as_ref().relative_path_package_resources {
-            for (key, path) in resources.iter() {
-                dest.write_all(key.as_bytes())?;
-                add_interior_padding(dest)?;
-                dest.write_all(&path_to_bytes(path))?;
-                add_interior_padding(dest)?;
+                if interior_padding == Some(BlobInteriorPadding::Null) {
+                    dest.write_all(b"\0")?;
+                }
+
+                written_length += stored.data.len() + padding_length;
             }
         }
-    }
 
-    for module in modules {
-        if let Some(resources) = &module.as_ref().relative_path_distribution_resources {
-            for (key, path) in resources {
-                dest.write_all(key.as_bytes())?;
-                add_interior_padding(dest)?;
-                dest.write_all(&path_to_bytes(path))?;
-                add_interior_padding(dest)?;
-            }
+        if written_length + section.alignment_padding != section.raw_payload_length {
+            return Err(anyhow!(
+                "resource data changed while writing packed resources data"
+            ));
         }
+
+        dest.write_all(&vec![0; section.alignment_padding])?;
     }
 
-    Ok(())
+    Ok((report, compression_report))
+}
+
+/// Resolve an ed25519 key pair from the bytes of a secret key.
+#[cfg(feature = "signing")]
+fn signing_keypair(secret_key: &[u8]) -> Result<Keypair> {
+    let secret = SecretKey::from_bytes(secret_key)
+        .map_err(|e| anyhow!("invalid ed25519 secret key: {}", e))?;
+    let public = PublicKey::from(&secret);
+
+    Ok(Keypair { secret, public })
+}
+
+/// Obtain the ed25519 public key verifying signatures made with a secret key.
+///
+/// `secret_key` must be 32 bytes long.
+#[cfg(feature = "signing")]
+pub fn packed_resources_public_key(secret_key: &[u8]) -> Result<Vec<u8>> {
+    Ok(signing_keypair(secret_key)?.public.to_bytes().to_vec())
+}
+
+/// Sign packed resources data with an ed25519 secret key.
+///
+/// `secret_key` must be 32 bytes long. Returns the 64 byte signature of
+/// `data`, which can be checked by
+/// `parser::verify_packed_resources()` using the corresponding public key.
+#[cfg(feature = "signing")]
+pub fn sign_packed_resources(data: &[u8], secret_key: &[u8]) -> Result<Vec<u8>> {
+    Ok(signing_keypair(secret_key)?.sign(data).to_bytes().to_vec())
 }
 
 #[cfg(test)]
@@ -1097,4 +1846,281 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    #[cfg(feature = "signing")]
+    fn test_sign() -> Result<()> {
+        let secret_key = [42u8; 32];
+
+        let public_key = packed_resources_public_key(&secret_key)?;
+        assert_eq!(public_key.len(), 32);
+
+        let signature = sign_packed_resources(b"pyembed\x01", &secret_key)?;
+        assert_eq!(signature.len(), 64);
+        // ed25519 signatures are deterministic.
+        assert_eq!(
+            sign_packed_resources(b"pyembed\x01", &secret_key)?,
+            signature
+        );
+
+        assert!(sign_packed_resources(b"pyembed\x01", &[42u8; 16]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_features_not_enabled() {
+        let resources: Vec<Resource<u8>> = Vec::new();
+
+        for (options, feature) in &[
+            (
+                WriteOptions {
+                    deduplicate: true,
+                    ..WriteOptions::default()
+                },
+                cfg!(feature = "deduplication"),
+            ),
+            (
+                WriteOptions {
+                    compression: Some(BlobCompression::default()),
+                    ..WriteOptions::default()
+                },
+                cfg!(feature = "compression"),
+            ),
+            (
+                WriteOptions {
+                    encryption_key: Some(vec![42u8; 32]),
+                    ..WriteOptions::default()
+                },
+                cfg!(feature = "encryption"),
+            ),
+        ] {
+            let mut data = Vec::new();
+            let res = write_packed_resources_v1_with_options(&resources[..], &mut data, options);
+            assert_eq!(res.is_ok(), *feature);
+        }
+    }
+
+    /// Builds resources anew every time they are obtained.
+    #[cfg(any(
+        feature = "deduplication",
+        all(feature = "compression", feature = "encryption")
+    ))]
+    struct GeneratedResources {
+        loads: std::cell::Cell<usize>,
+    }
+
+    #[cfg(any(
+        feature = "deduplication",
+        all(feature = "compression", feature = "encryption")
+    ))]
+    impl ResourceSource for GeneratedResources {
+        fn resources_count(&self) -> usize {
+            10
+        }
+
+        fn resource(&self, index: usize) -> Result<Cow<'_, Resource<'_, u8>>> {
+            self.loads.set(self.loads.get() + 1);
+
+            let mut package_resources = HashMap::new();
+            for i in 0..index {
+                package_resources.insert(
+                    Cow::Owned(format!("data{}.txt", i)),
+                    Cow::Owned(
+                        format!("data {} of {}\n", i, index)
+                            .repeat(100)
+                            .into_bytes(),
+                    ),
+                );
+            }
+            // Identical in every resource.
+            package_resources.insert(
+                Cow::Borrowed("LICENSE"),
+                Cow::Owned(b"license text\n".repeat(100)),
+            );
+
+            let bytecode = if index % 2 == 0 {
+                Some(Cow::Owned(vec![index as u8; 64]))
+            } else {
+                None
+            };
+
+            Ok(Cow::Owned(Resource {
+                flavor: ResourceFlavor::Module,
+                name: Cow::Owned(format!("module{}", index)),
+                is_package: true,
+                in_memory_source: Some(Cow::Owned(
+                    format!("value = {}\n", index).repeat(100).into_bytes(),
+                )),
+                // Bytecode is often identical across optimization levels.
+                in_memory_bytecode: bytecode.clone(),
+                in_memory_bytecode_opt1: bytecode,
+                in_memory_package_resources: Some(package_resources),
+                ..Resource::default()
+            }))
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "compression", feature = "encryption"))]
+    fn test_write_streaming() -> Result<()> {
+        let source = GeneratedResources {
+            loads: std::cell::Cell::new(0),
+        };
+        let resources = (0..source.resources_count())
+            .map(|i| Ok(source.resource(i)?.into_owned()))
+            .collect::<Result<Vec<_>>>()?;
+
+        for padding in &[None, Some(BlobInteriorPadding::Null)] {
+            source.loads.set(0);
+
+            let mut expected = Vec::new();
+            write_packed_resources_v1(&resources, &mut expected, *padding)?;
+
+            let options = WriteOptions {
+                interior_padding: *padding,
+                ..WriteOptions::default()
+            };
+            let mut data = Vec::new();
+            let reports = write_packed_resources_v1_with_options(&source, &mut data, &options)?;
+            assert_eq!(data, expected);
+            assert_eq!(reports, Default::default());
+
+            // Every resource is obtained twice plus twice per field with data.
+            assert_eq!(source.loads.get(), 10 + 10 + 2 * (10 + 10 + 5 + 5 + 10));
+
+            for deduplicate in &[false, true] {
+                for compression in &[None, Some(BlobCompression::default())] {
+                    for encryption_key in &[None, Some(vec![42u8; 32])] {
+                        let options = WriteOptions {
+                            interior_padding: *padding,
+                            deduplicate: *deduplicate,
+                            compression: *compression,
+                            encryption_key: encryption_key.clone(),
+                            ..WriteOptions::default()
+                        };
+
+                        let mut expected = Vec::new();
+                        let expected_reports = write_packed_resources_v1_with_options(
+                            &resources[..],
+                            &mut expected,
+                            &options,
+                        )?;
+                        let mut data = Vec::new();
+                        let reports =
+                            write_packed_resources_v1_with_options(&source, &mut data, &options)?;
+                        assert_eq!(data, expected);
+                        assert_eq!(reports, expected_reports);
+
+                        // The license of all but one resource and opt1 bytecode.
+                        // Encrypted blobs are never referenced.
+                        if *deduplicate && encryption_key.is_none() {
+                            assert_eq!(reports.0.duplicate_blobs, 9 + 5);
+                        } else {
+                            assert_eq!(reports.0, BlobDeduplicationReport::default());
+                        }
+                        assert_eq!(reports.1.compressed_blobs > 0, compression.is_some());
+                    }
+                }
+            }
+        }
+
+        let mut data = Vec::new();
+        assert!(write_packed_resources_v1_with_options(
+            &source,
+            &mut data,
+            &WriteOptions {
+                encryption_key: Some(vec![42u8; 16]),
+                ..WriteOptions::default()
+            },
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "deduplication")]
+    fn test_write_layout() -> Result<()> {
+        let source = GeneratedResources {
+            loads: std::cell::Cell::new(0),
+        };
+        let resources = (0..source.resources_count())
+            .map(|i| Ok(source.resource(i)?.into_owned()))
+            .collect::<Result<Vec<_>>>()?;
+
+        let layout = ResourcesLayout {
+            hot_resources: vec![
+                "module7".to_string(),
+                "module4".to_string(),
+                "missing".to_string(),
+                "module7".to_string(),
+            ],
+            alignment: Some(4096),
+        };
+
+        for padding in &[None, Some(BlobInteriorPadding::Null)] {
+            let options = WriteOptions {
+                interior_padding: *padding,
+                deduplicate: true,
+                layout: layout.clone(),
+                ..WriteOptions::default()
+            };
+
+            let mut data = Vec::new();
+            let (report, _) =
+                write_packed_resources_v1_with_options(&resources[..], &mut data, &options)?;
+            assert_eq!(report.duplicate_blobs, 9 + 5);
+
+            // Streaming the resources yields the same data.
+            let mut streamed = Vec::new();
+            write_packed_resources_v1_with_options(&source, &mut streamed, &options)?;
+            assert_eq!(streamed, data);
+
+            let parsed = crate::parser::load_resources(&data).map_err(|e| anyhow!(e))?;
+
+            // Name, source, bytecode and package resources of hot resources.
+            // Opt1 bytecode is only stored as references.
+            assert_eq!(parsed.prefetch_ranges().len(), 4);
+            for (offset, length) in parsed.prefetch_ranges() {
+                assert_eq!(offset % 4096, 0);
+                assert!(*length > 0);
+            }
+            assert_eq!(parsed.data_length(), data.len());
+
+            let parsed = parsed
+                .collect::<Result<Vec<_>, &'static str>>()
+                .map_err(|e| anyhow!(e))?;
+            assert_eq!(
+                parsed
+                    .iter()
+                    .map(|resource| resource.name.as_ref())
+                    .collect::<Vec<_>>(),
+                vec![
+                    "module7", "module4", "module0", "module1", "module2", "module3", "module5",
+                    "module6", "module8", "module9"
+                ]
+            );
+
+            for resource in &parsed {
+                assert!(resources.contains(resource));
+            }
+        }
+
+        let mut data = Vec::new();
+        assert!(write_packed_resources_v1_with_options(
+            &resources[..],
+            &mut data,
+            &WriteOptions {
+                layout: ResourcesLayout {
+                    alignment: Some(0),
+                    ..ResourcesLayout::default()
+                },
+                ..WriteOptions::default()
+            },
+        )
+        .is_err());
+
+        Ok(())
+    }
 }
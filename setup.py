@@ -2,9 +2,13 @@
 # License, v. 2.0. If a copy of the MPL was not distributed with this
 # file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-"""This setup.py is for the oxidized_importer Python extension module.
+"""This setup.py is for the Python extension modules built from this repository.
 
-It should exist in the `oxidzed-importer` directory. But since it needs
+By default it builds the oxidized_importer extension module. Set the
+`PYOXIDIZER_WHEEL` environment variable to `python_packed_resources` to
+build the python_packed_resources extension module instead.
+
+It should exist in the crate directory of each extension. But since it needs
 to pull in sources from outside that directory and `pip` can be opinionated
 about not allowing that, the file exists in the root of the repository to
 work around this limitation.
@@ -20,16 +24,39 @@ import subprocess
 import sys
 
 HERE = pathlib.Path(os.path.dirname(os.path.abspath(__file__)))
-OXIDIZED_IMPORTER = HERE / "oxidized-importer"
+
+PACKAGES = {
+    "oxidized_importer": {
+        "crate_dir": HERE / "oxidized-importer",
+        "lib_name": "oxidized_importer",
+        "description": "Python importer implemented in Rust",
+    },
+    "python_packed_resources": {
+        "crate_dir": HERE / "python-packed-resources-py",
+        "lib_name": "python_packed_resources_py",
+        "description": "Read and write Python packed resources data",
+    },
+}
+
+PACKAGE_NAME = os.environ.get("PYOXIDIZER_WHEEL", "oxidized_importer")
+
+if PACKAGE_NAME not in PACKAGES:
+    raise Exception(
+        "PYOXIDIZER_WHEEL must be one of %s; got %s"
+        % (", ".join(sorted(PACKAGES)), PACKAGE_NAME)
+    )
+
+PACKAGE = PACKAGES[PACKAGE_NAME]
 
 
 class RustExtension(distutils.extension.Extension):
-    def __init__(self, name):
+    def __init__(self, name, crate_dir: pathlib.Path, lib_name):
         super().__init__(name, [])
 
-        self.depends.extend(
-            [OXIDIZED_IMPORTER / "Cargo.toml", OXIDIZED_IMPORTER / "src/lib.rs"]
-        )
+        self.crate_dir = crate_dir
+        self.lib_name = lib_name
+
+        self.depends.extend([crate_dir / "Cargo.toml", crate_dir / "src/lib.rs"])
 
     def build(self, build_dir: pathlib.Path, get_ext_path_fn):
         env = os.environ.copy()
@@ -43,16 +70,16 @@ class RustExtension(distutils.extension.Extension):
             str(build_dir),
         ]
 
-        subprocess.run(args, env=env, cwd=OXIDIZED_IMPORTER, check=True)
+        subprocess.run(args, env=env, cwd=self.crate_dir, check=True)
 
         dest_path = pathlib.Path(get_ext_path_fn(self.name))
 
         if os.name == "nt":
-            rust_lib_filename = "%s.dll" % self.name
+            rust_lib_filename = "%s.dll" % self.lib_name
         elif sys.platform == "darwin":
-            rust_lib_filename = "lib%s.dylib" % self.name
+            rust_lib_filename = "lib%s.dylib" % self.lib_name
         else:
-            rust_lib_filename = "lib%s.so" % self.name
+            rust_lib_filename = "lib%s.so" % self.lib_name
 
         rust_lib = build_dir / "release" / rust_lib_filename
 
@@ -70,21 +97,23 @@ class RustBuildExt(distutils.command.build_ext.build_ext):
         )
 
 
-with open("oxidized-importer/README.md", "r", encoding="utf-8") as fh:
+with open(PACKAGE["crate_dir"] / "README.md", "r", encoding="utf-8") as fh:
     long_description = fh.read()
 
 
 setuptools.setup(
-    name="oxidized_importer",
+    name=PACKAGE_NAME,
     version="0.2dev0",
     author="Gregory Szorc",
     author_email="gregory.szorc@gmail.com",
     url="https://github.com/indygreg/PyOxidizer",
-    description="Python importer implemented in Rust",
+    description=PACKAGE["description"],
     long_description=long_description,
     license="MPL 2.0",
     python_requires=">=3.8",
     classifiers=["Intended Audience :: Developers", "Programming Language :: Rust",],
-    ext_modules=[RustExtension("oxidized_importer")],
+    ext_modules=[
+        RustExtension(PACKAGE_NAME, PACKAGE["crate_dir"], PACKAGE["lib_name"])
+    ],
     cmdclass={"build_ext": RustBuildExt},
 )